@@ -0,0 +1,249 @@
+use crate::canvas::{CanvasClient, FileObj};
+use crate::config::Config;
+use crate::fsutil::{apply_remote_mtime, ensure_dir, hash_file, sanitize_filename_preserve_ext};
+use crate::progress::{format_bytes, progress_bar, spinner};
+use crate::state::{is_state_file, ItemState, State};
+use crate::syncer::{attachment_path, claim_dest, module_dir_for, resolve_course_dir};
+use crate::tr;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Local files indexed by sanitized, lowercased name and size.
+type LocalIndex = HashMap<(String, u64), PathBuf>;
+
+/// Outcome counters for `adopt`.
+#[derive(Debug, Default)]
+pub struct AdoptReport {
+    pub adopted: usize,
+    pub bytes: u64,
+    pub already_tracked: usize,
+    pub unmatched: usize,
+}
+
+/// Seed a course's sync state from a manually downloaded folder, placing matched
+/// files where `sync` expects them so the first sync does not download them again.
+pub async fn run_adopt(
+    course_id: u64,
+    source: &Path,
+    move_files: bool,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    if !source.is_dir() {
+        return Err(format!("{} is not a directory", source.display()).into());
+    }
+    let canvas = CanvasClient::from_config().await?;
+    let course = canvas
+        .list_courses()
+        .await?
+        .into_iter()
+        .find(|c| c.id == course_id)
+        .ok_or_else(|| tr!("course {} not found among active courses", course_id))?;
+
+    let course_dir = resolve_course_dir(&cfg, &course, dry_run).await?;
+    let state_path = course_dir.join("state.json");
+    let mut state = State::load(&state_path).await;
+    state.course_id = Some(course_id);
+
+    let index_spinner = spinner(&tr!("Indexing {}", source.display()));
+    let local = index_local_files(source)?;
+    index_spinner.finish_and_clear();
+    info!(course_id, path = %source.display(), files = local.len(), "indexed local files for adoption");
+
+    let modules = canvas.list_modules_with_items(course_id).await?;
+    let module_progress = progress_bar(modules.len() as u64, &tr!("Adopting {}", course.name));
+    let mut report = AdoptReport::default();
+    // Files already moved into the archive, so a file attached to several modules is copied from its new home
+    let mut moved: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for m in &modules {
+        module_progress.inc(1);
+        let module_dir = module_dir_for(&course_dir, m, &cfg.week_pattern);
+        for item in &m.items {
+            let (Some("File"), Some(fid)) = (item.kind.as_deref(), item.content_id) else {
+                continue;
+            };
+            let key = format!("file:{}", fid);
+            let f = match canvas.get_file(fid).await {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!(course_id, file_id = fid, error = %e, "unable to fetch file metadata");
+                    report.unmatched += 1;
+                    continue;
+                }
+            };
+            let dest = claim_dest(
+                &mut state,
+                m.id,
+                &module_dir,
+                &key,
+                attachment_path(&module_dir, &f),
+                &fid.to_string(),
+            );
+            if state.get(&key).is_some() && dest.exists() {
+                report.already_tracked += 1;
+                continue;
+            }
+            let Some(src) = find_match(&local, &f) else {
+                report.unmatched += 1;
+                continue;
+            };
+            let from = moved.get(src).unwrap_or(src).clone();
+            let (size, sha1) = hash_file(&from)?;
+            if dry_run {
+                module_progress.println(tr!(
+                    "DRY-RUN adopt {} -> {}",
+                    from.display(),
+                    dest.display()
+                ));
+            } else {
+                let move_this = move_files && !moved.contains_key(src);
+                place_file(&from, &dest, move_this).await?;
+                if move_this {
+                    moved.insert(src.clone(), dest.clone());
+                }
+                apply_remote_mtime(&dest, f.updated_at.as_deref());
+                state.set(
+                    key,
+                    ItemState {
+                        etag: None,
+                        updated_at: f.updated_at.clone(),
+                        size: Some(size),
+                        content_hash: Some(sha1),
+                        last_error: None,
+                        error_count: None,
+                        last_modified: None,
+                        uuid: None,
+                        locked: None,
+                    },
+                );
+                info!(course_id, file_id = fid, src = %from.display(), dest = %dest.display(), "adopted file");
+            }
+            report.adopted += 1;
+            report.bytes += size;
+        }
+    }
+    module_progress.finish_and_clear();
+
+    if !dry_run {
+        ensure_dir(&course_dir).await?;
+        state.save(&state_path).await?;
+    }
+    println!(
+        "{}",
+        tr!(
+            "{}Adopted {} file(s) ({}) into {}; {} already tracked, {} without a local match",
+            if dry_run { "DRY-RUN: " } else { "" },
+            report.adopted,
+            format_bytes(report.bytes),
+            course_dir.display(),
+            report.already_tracked,
+            report.unmatched
+        )
+    );
+    if report.unmatched > 0 {
+        println!(
+            "{}",
+            tr!(
+                "Run 'u_crawler sync --course-id {}' to fetch the remaining files.",
+                course_id
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Walk `root` and index every regular file by sanitized name and size.
+fn index_local_files(root: &Path) -> std::io::Result<LocalIndex> {
+    let mut index = LocalIndex::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let meta = entry.metadata()?;
+            if meta.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if is_state_file(name) || name.ends_with(".part") {
+                continue;
+            }
+            index.entry((match_key(name), meta.len())).or_insert(path);
+        }
+    }
+    Ok(index)
+}
+
+fn match_key(name: &str) -> String {
+    sanitize_filename_preserve_ext(name).to_ascii_lowercase()
+}
+
+/// Local file with the same name (display name or original filename) and size.
+fn find_match<'a>(index: &'a LocalIndex, f: &FileObj) -> Option<&'a PathBuf> {
+    let size = f.size?;
+    [f.display_name.as_deref(), f.filename.as_deref()]
+        .into_iter()
+        .flatten()
+        .find_map(|name| index.get(&(match_key(name), size)))
+}
+
+/// Copy (or move) `src` into place; a no-op when it already lives at `dest`.
+async fn place_file(src: &Path, dest: &Path, move_files: bool) -> std::io::Result<()> {
+    if src == dest {
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        ensure_dir(parent).await?;
+    }
+    if move_files && tokio::fs::rename(src, dest).await.is_ok() {
+        return Ok(());
+    }
+    // Copy through a .part file so an interrupted adopt never leaves a truncated file
+    let part = dest.with_extension("part");
+    tokio::fs::copy(src, &part).await?;
+    tokio::fs::rename(&part, dest).await?;
+    if move_files {
+        tokio::fs::remove_file(src).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::FileLock;
+
+    fn file(display_name: &str, filename: &str, size: u64) -> FileObj {
+        FileObj {
+            id: 1,
+            display_name: Some(display_name.into()),
+            filename: Some(filename.into()),
+            size: Some(size),
+            updated_at: None,
+            url: None,
+            download_url: None,
+            uuid: None,
+            lock: FileLock::default(),
+        }
+    }
+
+    #[test]
+    fn matches_by_sanitized_name_and_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("Semana 1");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("Guía de estudio.PDF"), b"12345").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"abc").unwrap();
+
+        let index = index_local_files(dir.path()).unwrap();
+        assert_eq!(index.len(), 2);
+        let hit = find_match(&index, &file("Guía de estudio.pdf", "guia.pdf", 5));
+        assert_eq!(hit, Some(&nested.join("Guía de estudio.PDF")));
+        assert!(find_match(&index, &file("Guía de estudio.pdf", "guia.pdf", 6)).is_none());
+        assert!(find_match(&index, &file("Readme", "notes.txt", 3)).is_some());
+    }
+}
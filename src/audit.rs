@@ -0,0 +1,259 @@
+use crate::config::Config;
+use crate::fsutil::course_dirs;
+use crate::state::State;
+use crate::tr;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Top-level directory under `download_root` that holds Zoom recordings.
+const ZOOM_DIR: &str = "Zoom";
+
+/// A relative link in a generated document whose target is missing locally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// Document containing the link, relative to the course folder.
+    pub source: String,
+    /// Link target as written in the document.
+    pub target: String,
+}
+
+/// Broken links and failed downloads for one course folder.
+#[derive(Debug, Default)]
+pub struct CourseAudit {
+    pub name: String,
+    pub documents: usize,
+    pub links_checked: usize,
+    pub broken: Vec<BrokenLink>,
+    /// `(state key, last error)` for items whose last download attempt failed.
+    pub failed_items: Vec<(String, String)>,
+}
+
+/// Check relative links in every Markdown/HTML file of the archive and report
+/// targets that are missing locally, together with items that failed to download.
+pub async fn run_audit_links(course_id: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let root = cfg.canvas_root();
+    if !root.exists() {
+        println!("{}", tr!("No backup directory found at {}", root.display()));
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+    info!(path = %root.display(), "auditing archive links");
+
+    let mut total_broken = 0usize;
+    let mut total_failed = 0usize;
+    let mut audited = 0usize;
+    for dir in course_dirs(&root, &[ZOOM_DIR])? {
+        let state = State::load(&dir.join("state.json")).await;
+        if course_id.is_some() && state.course_id != course_id {
+            continue;
+        }
+        let mut reports = vec![audit_course(&dir, &state)?];
+        // Transcript notes kept with the recordings under zoom.download_root
+        if let Some(recordings) = cfg.zoom_course_dir(&dir).filter(|d| d.is_dir()) {
+            let mut report = audit_course(&recordings, &State::default())?;
+            report.name = recordings.display().to_string();
+            reports.push(report);
+        }
+        for report in reports {
+            audited += 1;
+            total_broken += report.broken.len();
+            total_failed += report.failed_items.len();
+
+            println!(
+                "{}",
+                tr!(
+                    "{} - {} documents, {} links checked",
+                    report.name,
+                    report.documents,
+                    report.links_checked
+                )
+            );
+            if report.broken.is_empty() && report.failed_items.is_empty() {
+                println!("  OK");
+                continue;
+            }
+            for link in &report.broken {
+                println!("{}", tr!("  broken: {} -> {}", link.source, link.target));
+            }
+            for (key, error) in &report.failed_items {
+                println!("{}", tr!("  failed: {} ({})", key, error));
+            }
+        }
+    }
+
+    println!("─────────────────────────────");
+    println!(
+        "{}",
+        tr!(
+            "Audited {} course folder(s): {} broken link(s), {} failed item(s)",
+            audited,
+            total_broken,
+            total_failed
+        )
+    );
+    if total_broken + total_failed > 0 {
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to re-fetch missing content while Canvas access lasts.")
+        );
+    }
+    Ok(())
+}
+
+fn audit_course(dir: &Path, state: &State) -> std::io::Result<CourseAudit> {
+    let mut report = CourseAudit {
+        name: dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        ..Default::default()
+    };
+    for doc in document_files(dir)? {
+        let text = match std::fs::read_to_string(&doc) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        report.documents += 1;
+        let base = doc.parent().unwrap_or(dir);
+        for target in extract_links(&text) {
+            let Some(rel) = local_target(&target) else {
+                continue;
+            };
+            report.links_checked += 1;
+            if !base.join(&rel).exists() {
+                report.broken.push(BrokenLink {
+                    source: doc
+                        .strip_prefix(dir)
+                        .unwrap_or(&doc)
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                    target,
+                });
+            }
+        }
+    }
+    report.broken.sort_by(|a, b| a.source.cmp(&b.source));
+
+    for (key, item) in &state.items {
+        if let Some(err) = &item.last_error {
+            report.failed_items.push((key.clone(), err.clone()));
+        }
+    }
+    report.failed_items.sort();
+    Ok(report)
+}
+
+/// Recursively list Markdown and HTML files under `dir`.
+fn document_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if matches!(
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_ascii_lowercase())
+                    .as_deref(),
+                Some("md" | "html" | "htm")
+            ) {
+                out.push(path);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Extract link targets from Markdown (`[t](x)`, `![t](<x>)`) and HTML (`href`/`src`).
+pub fn extract_links(text: &str) -> Vec<String> {
+    let md = Regex::new(r"!?\[[^\]]*\]\(\s*(?:<([^>]*)>|([^)\s]+))").unwrap();
+    let html = Regex::new(r#"(?i)\b(?:href|src)\s*=\s*["']([^"']+)["']"#).unwrap();
+
+    let mut out = Vec::new();
+    for cap in md.captures_iter(text) {
+        if let Some(m) = cap.get(1).or_else(|| cap.get(2)) {
+            out.push(m.as_str().trim().to_string());
+        }
+    }
+    for cap in html.captures_iter(text) {
+        out.push(cap[1].trim().to_string());
+    }
+    out
+}
+
+/// Local relative path for a link target, or `None` for remote, anchor-only,
+/// or site-absolute links which cannot be checked against the archive.
+pub fn local_target(target: &str) -> Option<PathBuf> {
+    let path = target.split(['#', '?']).next().unwrap_or("");
+    if path.is_empty() || path.starts_with('/') || path.contains(':') {
+        return None;
+    }
+    let decoded = urlencoding::decode(path)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    Some(PathBuf::from(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_markdown_and_html_links() {
+        let text = "- [Page] [Intro](<01-Intro.md>)\n![img](Attachments/a%20b.png \"t\")\n\
+                    <a href=\"https://canvas.example/x\">x</a> <img src='pic.jpg'>";
+        assert_eq!(
+            extract_links(text),
+            vec![
+                "01-Intro.md",
+                "Attachments/a%20b.png",
+                "https://canvas.example/x",
+                "pic.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn local_target_skips_remote_and_decodes() {
+        assert_eq!(local_target("https://x/y"), None);
+        assert_eq!(local_target("mailto:a@b.c"), None);
+        assert_eq!(local_target("#top"), None);
+        assert_eq!(local_target("/courses/1/files/2"), None);
+        assert_eq!(
+            local_target("Attachments/a%20b.pdf#page=2"),
+            Some(PathBuf::from("Attachments/a b.pdf"))
+        );
+    }
+
+    #[test]
+    fn audit_reports_missing_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("Modules").join("01_Intro");
+        std::fs::create_dir_all(module.join("Attachments")).unwrap();
+        std::fs::write(module.join("Attachments").join("ok.pdf"), b"x").unwrap();
+        std::fs::write(
+            module.join("00-INDEX.md"),
+            "- [File] [Ok](<Attachments/ok.pdf>)\n- [File] [Gone](<Attachments/gone.pdf>)\n",
+        )
+        .unwrap();
+
+        let report = audit_course(dir.path(), &State::default()).unwrap();
+        assert_eq!(report.documents, 1);
+        assert_eq!(report.links_checked, 2);
+        assert_eq!(
+            report.broken,
+            vec![BrokenLink {
+                source: "Modules/01_Intro/00-INDEX.md".into(),
+                target: "Attachments/gone.pdf".into(),
+            }]
+        );
+    }
+}
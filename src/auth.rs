@@ -0,0 +1,88 @@
+use crate::config::{load_config_from_path, save_config_to_path, Config, ConfigError, ConfigPaths};
+use crate::http::{build_http_client, HttpCtx};
+use crate::moodle::MoodleClient;
+use crate::tr;
+use tracing::info;
+
+/// Load the config for editing, creating the default one first when missing.
+///
+/// Reads the file as written, without `--download-root` or other runtime overrides,
+/// so saving it back never persists them.
+async fn load_for_update(paths: &ConfigPaths) -> Result<Config, ConfigError> {
+    if !paths.config_file.exists() {
+        match Config::load_or_init() {
+            Ok(_) | Err(ConfigError::MissingConfigFile(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    load_config_from_path(&paths.config_file).await
+}
+
+/// Store the Canvas base URL and token (or the command printing it) in the config.
+pub async fn run_auth_canvas(
+    base_url: Option<String>,
+    token: Option<String>,
+    token_cmd: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = ConfigPaths::new()?;
+    let mut cfg = load_for_update(&paths).await?;
+
+    if let Some(base) = base_url {
+        cfg.canvas.base_url = base;
+    }
+    if let Some(token) = token {
+        cfg.canvas.token = Some(token);
+        cfg.canvas.token_cmd = None;
+    }
+    if let Some(cmd) = token_cmd {
+        cfg.canvas.token_cmd = Some(cmd);
+        cfg.canvas.token = None;
+    }
+
+    cfg.expand_paths();
+    save_config_to_path(&cfg, &paths.config_file).await?;
+    info!(path = %paths.config_file.display(), "saved canvas auth");
+    println!(
+        "{}",
+        tr!("saved canvas auth in {}", paths.config_file.display())
+    );
+    Ok(())
+}
+
+/// Store a Moodle site and web service token in the config once the token is
+/// accepted by the site.
+pub async fn run_auth_moodle(
+    base_url: String,
+    token: Option<String>,
+    token_cmd: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = ConfigPaths::new()?;
+    let mut cfg = load_for_update(&paths).await?;
+
+    let mut moodle = cfg.moodle.take().unwrap_or_default();
+    moodle.base_url = base_url;
+    moodle.token = token;
+    moodle.token_cmd = token_cmd;
+    cfg.moodle = Some(moodle);
+
+    // Check the token before saving it
+    let http = HttpCtx::new(&cfg, build_http_client(&cfg));
+    let site = MoodleClient::with_http(&cfg, http)
+        .await?
+        .site_info()
+        .await?;
+
+    cfg.expand_paths();
+    save_config_to_path(&cfg, &paths.config_file).await?;
+    info!(path = %paths.config_file.display(), site = %site.sitename, "saved moodle auth");
+    println!(
+        "{}",
+        tr!(
+            "signed in to {} as {}; saved moodle auth in {}",
+            site.sitename,
+            site.fullname,
+            paths.config_file.display()
+        )
+    );
+    Ok(())
+}
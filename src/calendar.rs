@@ -0,0 +1,320 @@
+use crate::canvas::{Assignment, CalendarEvent, Course};
+use chrono::{DateTime, NaiveDate, Utc};
+use html2md::parse_html;
+use std::path::Path;
+
+/// Calendar file written to each course folder and, merging them, to the download root.
+pub const CALENDAR_FILE: &str = "calendar.ics";
+
+const PRODID: &str = "-//u_crawler//Course calendar//EN";
+
+/// When an entry happens: a whole day, or an instant in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum When {
+    Day(NaiveDate),
+    At(DateTime<Utc>),
+}
+
+impl When {
+    fn parse(value: &str) -> Option<Self> {
+        match DateTime::parse_from_rfc3339(value) {
+            Ok(t) => Some(When::At(t.with_timezone(&Utc))),
+            Err(_) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(When::Day),
+        }
+    }
+
+    fn property(&self, name: &str) -> String {
+        match self {
+            When::Day(d) => format!("{};VALUE=DATE:{}", name, d.format("%Y%m%d")),
+            When::At(t) => format!("{}:{}", name, t.format("%Y%m%dT%H%M%SZ")),
+        }
+    }
+}
+
+/// One `VEVENT` of a course calendar.
+#[derive(Debug)]
+struct Entry {
+    uid: String,
+    summary: String,
+    start: When,
+    end: Option<When>,
+    description: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    /// Last change, used as `DTSTAMP` so unchanged events render identically.
+    stamp: Option<DateTime<Utc>>,
+}
+
+/// The `.ics` calendar of one course: its calendar events plus a zero-length event
+/// at each assignment's due date. Entries without a date are left out.
+pub fn course_calendar<'a>(
+    course: &Course,
+    events: &[CalendarEvent],
+    assignments: impl IntoIterator<Item = &'a Assignment>,
+) -> String {
+    let prefix = course
+        .course_code
+        .as_deref()
+        .filter(|code| !code.trim().is_empty())
+        .map(|code| format!("[{}] ", code.trim()))
+        .unwrap_or_default();
+    let mut entries: Vec<Entry> = Vec::new();
+    for e in events {
+        let start = if e.all_day {
+            e.all_day_date.as_deref().and_then(When::parse)
+        } else {
+            None
+        }
+        .or_else(|| e.start_at.as_deref().and_then(When::parse));
+        let Some(start) = start else { continue };
+        let end = match start {
+            When::Day(d) => d.succ_opt().map(When::Day),
+            When::At(_) => e.end_at.as_deref().and_then(When::parse),
+        };
+        entries.push(Entry {
+            uid: format!("canvas-event-{}@u_crawler", e.id),
+            summary: format!("{}{}", prefix, e.title.as_deref().unwrap_or("Event")),
+            start,
+            end,
+            description: e.description.as_deref().map(plain_text),
+            location: e.location_name.clone(),
+            url: e.html_url.clone(),
+            stamp: e.updated_at.as_deref().and_then(utc),
+        });
+    }
+    for a in assignments {
+        let Some(due) = a.due_at.as_deref().and_then(When::parse) else {
+            continue;
+        };
+        entries.push(Entry {
+            uid: format!("canvas-assignment-{}@u_crawler", a.id),
+            summary: format!(
+                "{}Due: {}",
+                prefix,
+                a.name.as_deref().unwrap_or("Assignment")
+            ),
+            start: due,
+            end: Some(due),
+            description: None,
+            location: None,
+            url: a.html_url.clone(),
+            stamp: a.updated_at.as_deref().and_then(utc),
+        });
+    }
+    entries.sort_by(|a, b| (a.start, &a.uid).cmp(&(b.start, &b.uid)));
+
+    let mut lines = Vec::new();
+    for e in &entries {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", e.uid));
+        let stamp = e.stamp.map(When::At).unwrap_or(match e.start {
+            When::Day(d) => When::At(d.and_time(Default::default()).and_utc()),
+            at => at,
+        });
+        lines.push(stamp.property("DTSTAMP"));
+        lines.push(e.start.property("DTSTART"));
+        if let Some(end) = e.end {
+            lines.push(end.property("DTEND"));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&e.summary)));
+        if let Some(d) = e.description.as_deref().filter(|d| !d.is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape(d)));
+        }
+        if let Some(l) = e.location.as_deref().filter(|l| !l.trim().is_empty()) {
+            lines.push(format!("LOCATION:{}", escape(l.trim())));
+        }
+        if let Some(u) = e.url.as_deref() {
+            lines.push(format!("URL:{}", u));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    wrap(&course.name, &lines)
+}
+
+/// One calendar with the events of every course calendar in `calendars`.
+pub fn merge_calendars(name: &str, calendars: &[String]) -> String {
+    let mut lines = Vec::new();
+    for cal in calendars {
+        let mut in_event = false;
+        for line in cal.split("\r\n") {
+            if line == "BEGIN:VEVENT" {
+                in_event = true;
+            }
+            if in_event {
+                // Already folded and escaped; copy as is
+                lines.push(line.to_string());
+            }
+            if line == "END:VEVENT" {
+                in_event = false;
+            }
+        }
+    }
+    let mut out = String::new();
+    for line in wrap(name, &[]).split_inclusive("\r\n") {
+        if line == "END:VCALENDAR\r\n" {
+            for l in &lines {
+                out.push_str(l);
+                out.push_str("\r\n");
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// The course calendars found under `root`, for [`merge_calendars`].
+pub fn course_calendars(root: &Path) -> std::io::Result<Vec<String>> {
+    let mut out = Vec::new();
+    for dir in crate::fsutil::course_dirs(root, &[])? {
+        if let Ok(text) = std::fs::read_to_string(dir.join(CALENDAR_FILE)) {
+            out.push(text);
+        }
+    }
+    Ok(out)
+}
+
+fn wrap(name: &str, lines: &[String]) -> String {
+    let mut out = String::new();
+    let header = [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for line in header.iter().chain(lines) {
+        fold(&mut out, line);
+    }
+    out.push_str("END:VCALENDAR\r\n");
+    out
+}
+
+/// Append `line` folded at 75 octets, as RFC 5545 requires.
+fn fold(out: &mut String, line: &str) {
+    let mut width = 0;
+    for ch in line.chars() {
+        let len = ch.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += len;
+    }
+    out.push_str("\r\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn plain_text(html: &str) -> String {
+    parse_html(html).trim().to_string()
+}
+
+fn utc(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course() -> Course {
+        Course {
+            id: 7,
+            name: "Cálculo, sección 2".into(),
+            course_code: Some("MAT101".into()),
+            ..Default::default()
+        }
+    }
+
+    fn event(id: u64, start_at: &str) -> CalendarEvent {
+        CalendarEvent {
+            id,
+            title: Some("Clase; repaso".into()),
+            description: Some("<p>Traer calculadora</p>".into()),
+            start_at: Some(start_at.into()),
+            end_at: Some("2024-03-05T16:00:00-03:00".into()),
+            all_day: false,
+            all_day_date: None,
+            location_name: Some("Sala 204".into()),
+            html_url: None,
+            updated_at: Some("2024-02-01T12:00:00Z".into()),
+        }
+    }
+
+    #[test]
+    fn course_calendar_lists_events_and_due_dates() {
+        let assignment = Assignment {
+            id: 3,
+            name: Some("Tarea 1".into()),
+            description: None,
+            updated_at: None,
+            due_at: Some("2024-03-04T23:59:00Z".into()),
+            html_url: Some("https://canvas.example/courses/7/assignments/3".into()),
+            rubric: None,
+        };
+        let ics = course_calendar(
+            &course(),
+            &[event(11, "2024-03-05T14:00:00-03:00")],
+            &[assignment],
+        );
+        assert_eq!(
+            ics,
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//u_crawler//Course calendar//EN\r\n\
+             CALSCALE:GREGORIAN\r\n\
+             X-WR-CALNAME:Cálculo\\, sección 2\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:canvas-assignment-3@u_crawler\r\n\
+             DTSTAMP:20240304T235900Z\r\n\
+             DTSTART:20240304T235900Z\r\n\
+             DTEND:20240304T235900Z\r\n\
+             SUMMARY:[MAT101] Due: Tarea 1\r\n\
+             URL:https://canvas.example/courses/7/assignments/3\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:canvas-event-11@u_crawler\r\n\
+             DTSTAMP:20240201T120000Z\r\n\
+             DTSTART:20240305T170000Z\r\n\
+             DTEND:20240305T190000Z\r\n\
+             SUMMARY:[MAT101] Clase\\; repaso\r\n\
+             DESCRIPTION:Traer calculadora\r\n\
+             LOCATION:Sala 204\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn merged_calendar_keeps_every_course_event() {
+        let a = course_calendar(&course(), &[event(1, "2024-03-05T14:00:00-03:00")], &[]);
+        let b = course_calendar(&course(), &[event(2, "2024-03-06T14:00:00-03:00")], &[]);
+        let merged = merge_calendars("All courses", &[a, b]);
+        assert!(merged.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(merged.contains("X-WR-CALNAME:All courses\r\n"));
+        assert_eq!(merged.matches("BEGIN:VEVENT").count(), 2);
+        assert!(merged.find("canvas-event-1@").unwrap() < merged.find("canvas-event-2@").unwrap());
+        assert!(merged.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let mut out = String::new();
+        fold(&mut out, &"x".repeat(160));
+        let lines: Vec<&str> = out.trim_end().split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert!(lines[1].starts_with(' ') && lines[1].len() == 75);
+    }
+}
@@ -1,10 +1,16 @@
-use crate::config::Config;
-use crate::http::{build_http_client, parse_next_link};
-use reqwest::{header, Client, Url};
+use crate::config::{Config, Courses};
+use crate::fixtures::{self, FixtureMode};
+use crate::graphql::CourseMetadata;
+use crate::http::{build_http_client, parse_next_link, HttpCtx};
+use crate::{report_warning, tr};
+use reqwest::{header, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::io;
+use std::sync::{Mutex, Once};
 use thiserror::Error;
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Error)]
 pub enum CanvasError {
@@ -20,22 +26,125 @@ pub enum CanvasError {
     Decode(String),
     #[error("missing canvas token; run `auth canvas` first")]
     MissingToken,
+    /// The token was refused by `GET /users/self`, usually because it expired or
+    /// was revoked.
+    #[error(
+        "Canvas rejected the access token ({0}); it has expired or been revoked. \
+         Create a new one under Account > Settings in Canvas and run \
+         `u_crawler auth canvas --token NEW_TOKEN` (or fix canvas.token_cmd)"
+    )]
+    TokenRejected(String),
+    /// Canvas refused `canvas.as_user_id`.
+    #[error(
+        "Canvas refused to act as user {0} ({1}); masquerading needs an account with \
+         the \"Become other users\" permission, or remove canvas.as_user_id"
+    )]
+    MasqueradeRejected(String, String),
 }
 
+/// Reports masquerading on the terminal once per run, however many clients are built.
+static MASQUERADE_NOTICE: Once = Once::new();
+
 pub struct CanvasClient {
     pub base: Url,
-    pub http: Client,
+    pub http: HttpCtx,
     pub token: String,
+    /// Record or replay API responses, from `--record-fixtures`/`--replay-fixtures`.
+    pub fixtures: Option<FixtureMode>,
+    /// Enrollment states and terms [`list_courses`](Self::list_courses) keeps.
+    pub courses: Courses,
+    /// Fetch modules and assignments in bulk through GraphQL (`canvas.api = "graphql"`).
+    pub graphql: bool,
+    /// Course metadata fetched in bulk by [`list_courses`](Self::list_courses).
+    pub(crate) prefetched: Mutex<HashMap<u64, CourseMetadata>>,
+    /// User every request acts as (`canvas.as_user_id`), for admins and support staff.
+    pub as_user_id: Option<String>,
 }
 
 impl CanvasClient {
     pub async fn from_config() -> Result<Self, CanvasError> {
         let cfg = Config::load_or_init()?;
-        let http = build_http_client(&cfg);
+        let http = HttpCtx::new(&cfg, build_http_client(&cfg));
+        Self::with_http(&cfg, http).await
+    }
+
+    /// Build a client that shares `http` (and its concurrency, pacing and
+    /// retry limits) with other callers such as file downloads.
+    pub async fn with_http(cfg: &Config, http: HttpCtx) -> Result<Self, CanvasError> {
         let base = Url::parse(&cfg.canvas.base_url)
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid base_url"))?;
-        let token = resolve_token(&cfg).await.ok_or(CanvasError::MissingToken)?;
-        Ok(CanvasClient { base, http, token })
+        let fixtures = fixtures::mode().cloned();
+        let token =
+            match resolve_token(cfg.canvas.token.as_deref(), cfg.canvas.token_cmd.as_deref()).await
+            {
+                Some(token) => token,
+                // Replayed responses need no credentials
+                None if matches!(fixtures, Some(FixtureMode::Replay(_))) => String::new(),
+                None => return Err(CanvasError::MissingToken),
+            };
+        let client = CanvasClient {
+            base,
+            http,
+            token,
+            fixtures,
+            courses: cfg.courses.clone(),
+            graphql: cfg.canvas.api == "graphql",
+            prefetched: Mutex::default(),
+            as_user_id: cfg
+                .canvas
+                .as_user_id
+                .clone()
+                .filter(|id| !id.trim().is_empty()),
+        };
+        if let Some(id) = &client.as_user_id {
+            warn!(as_user_id = %id, "masquerading: canvas requests act on behalf of another user");
+            MASQUERADE_NOTICE.call_once(|| {
+                report_warning!(
+                    "{}",
+                    tr!(
+                        "Masquerading as Canvas user {} (canvas.as_user_id): every request acts on their behalf",
+                        id
+                    )
+                );
+            });
+        }
+        client.check_token().await?;
+        Ok(client)
+    }
+
+    /// Fail early with [`CanvasError::TokenRejected`] when Canvas refuses the
+    /// token, instead of with a raw 401 halfway through a sync. Other failures
+    /// are left to the requests that follow.
+    async fn check_token(&self) -> Result<(), CanvasError> {
+        if self.fixtures.is_some() {
+            return Ok(());
+        }
+        match self.get_self().await {
+            Ok(user) => {
+                debug!(user_id = user.id, "canvas token accepted");
+                if self.as_user_id.is_some() {
+                    info!(user_id = user.id, name = ?user.name, "masquerading as canvas user");
+                }
+                Ok(())
+            }
+            Err(CanvasError::Status(401, body))
+                if self.as_user_id.is_some() && body.contains("as_user_id") =>
+            {
+                error!(body = %body, "canvas refused to masquerade");
+                Err(CanvasError::MasqueradeRejected(
+                    self.as_user_id.clone().unwrap_or_default(),
+                    rejection_reason(&body),
+                ))
+            }
+            Err(CanvasError::Status(401, body)) => {
+                error!(body = %body, "canvas rejected the token");
+                Err(CanvasError::TokenRejected(rejection_reason(&body)))
+            }
+            Err(e) => {
+                debug!(error = %e, "unable to check the canvas token");
+                Ok(())
+            }
+        }
     }
 
     fn auth_header_val(&self) -> header::HeaderValue {
@@ -43,48 +152,79 @@ impl CanvasClient {
         header::HeaderValue::from_str(&v).expect("valid header")
     }
 
+    /// Send an authenticated request through the shared `HttpCtx`, as
+    /// `as_user_id` when masquerading.
+    async fn send(&self, rb: RequestBuilder) -> reqwest::Result<Response> {
+        let mut rb = rb.header(header::AUTHORIZATION, self.auth_header_val());
+        if let Some(id) = &self.as_user_id {
+            let mut req = rb.build()?;
+            // `Link` URLs for the next page already carry it
+            if !req.url().query_pairs().any(|(k, _)| k == "as_user_id") {
+                req.url_mut()
+                    .query_pairs_mut()
+                    .append_pair("as_user_id", id);
+            }
+            rb = RequestBuilder::from_parts(self.http.client.clone(), req);
+        }
+        let Some(mode) = &self.fixtures else {
+            return self.http.send(rb).await;
+        };
+        let req = rb.try_clone().expect("clone request").build()?;
+        let method = req.method().as_str();
+        match mode {
+            FixtureMode::Replay(dir) => {
+                Ok(fixtures::replay(dir, &self.base, method, req.url()).await)
+            }
+            FixtureMode::Record(dir) => {
+                let resp = self.http.send(rb).await?;
+                fixtures::record(dir, &self.base, method, req.url(), resp).await
+            }
+        }
+    }
+
+    /// Courses in the configured enrollment states (`courses.state`) and terms
+    /// (`courses.terms`). Concluded courses whose access has ended are left out
+    /// with a warning, since Canvas no longer returns their name or content.
+    /// With the GraphQL API, their modules and assignments are fetched here too.
     pub async fn list_courses(&self) -> Result<Vec<Course>, CanvasError> {
-        let mut out = Vec::new();
-        let mut next = Some(
-            self.base
-                .join("/api/v1/courses?enrollment_state=active&per_page=100")
-                .unwrap(),
-        );
-        while let Some(url) = next.take() {
-            debug!(method = "GET", url = %url, "canvas request");
-            let resp = self
-                .http
-                .get(url.clone())
-                .header(header::AUTHORIZATION, self.auth_header_val())
-                .send()
+        let mut out: Vec<Course> = Vec::new();
+        for state in &self.courses.state {
+            let courses: Vec<Course> = self
+                .get_all(
+                    &format!(
+                        "/api/v1/courses?enrollment_state={}&include[]=term&per_page=100",
+                        state
+                    ),
+                    "courses",
+                )
                 .await?;
-            let status = resp.status();
-            // Capture Link header before consuming body
-            let link = resp
-                .headers()
-                .get(header::LINK)
-                .and_then(|h| h.to_str().ok())
-                .map(|s| s.to_string());
-
-            // Body text for diagnostics, then decode
-            let text = resp.text().await?;
-            if !status.is_success() {
-                let snippet = text.chars().take(500).collect::<String>();
-                error!(status = %status.as_u16(), body = %snippet, "canvas non-success response");
-                return Err(CanvasError::Status(status.as_u16(), snippet));
-            }
-            let mut page: Vec<Course> = match serde_json::from_str(&text) {
-                Ok(v) => v,
-                Err(e) => {
-                    let snippet = text.chars().take(500).collect::<String>();
-                    error!(error = %e, body = %snippet, "canvas decode failure (courses)");
-                    return Err(CanvasError::Decode(e.to_string()));
+            for c in courses {
+                if !out.iter().any(|o| o.id == c.id) {
+                    out.push(c);
                 }
-            };
-            out.append(&mut page);
-
-            // Parse Link header for next
-            next = link.as_deref().and_then(parse_next_link);
+            }
+        }
+        out.retain(|c| {
+            if c.access_restricted_by_date {
+                warn!(course_id = c.id, "course access has ended; skipping");
+                crate::report_warning!(
+                    "{}",
+                    tr!(
+                        "Course {} has concluded and its content is no longer available; skipping",
+                        c.id
+                    )
+                );
+                return false;
+            }
+            let term = c
+                .term
+                .as_ref()
+                .map(|t| (t.id, t.name.as_deref().unwrap_or_default()));
+            self.courses.term_matches(term)
+        });
+        if self.graphql && !out.is_empty() {
+            let ids: Vec<u64> = out.iter().map(|c| c.id).collect();
+            self.prefetch_course_metadata(&ids).await;
         }
         Ok(out)
     }
@@ -93,6 +233,9 @@ impl CanvasClient {
         &self,
         course_id: u64,
     ) -> Result<Vec<Module>, CanvasError> {
+        if let Some(modules) = self.prefetched_modules(course_id) {
+            return Ok(modules);
+        }
         let mut out = Vec::new();
         let mut next = Some(
             self.base
@@ -104,12 +247,7 @@ impl CanvasClient {
         );
         while let Some(url) = next.take() {
             debug!(method = "GET", course_id = course_id, url = %url, "canvas request");
-            let resp = self
-                .http
-                .get(url.clone())
-                .header(header::AUTHORIZATION, self.auth_header_val())
-                .send()
-                .await?;
+            let resp = self.send(self.http.client.get(url.clone())).await?;
             let status = resp.status();
             let link = resp
                 .headers()
@@ -137,20 +275,15 @@ impl CanvasClient {
     }
 }
 
-async fn resolve_token(cfg: &Config) -> Option<String> {
-    if let Some(t) = cfg.canvas.token.as_ref() {
+/// A configured token, or the output of the command that prints it.
+pub(crate) async fn resolve_token(token: Option<&str>, token_cmd: Option<&str>) -> Option<String> {
+    if let Some(t) = token {
         if !t.trim().is_empty() {
-            return Some(t.clone());
+            return Some(t.to_string());
         }
     }
-    if let Some(cmd) = cfg.canvas.token_cmd.as_ref() {
-        // Execute via sh -lc to support pipelines; trim output
-        let output = tokio::process::Command::new("sh")
-            .arg("-lc")
-            .arg(cmd)
-            .output()
-            .await
-            .ok()?;
+    if let Some(cmd) = token_cmd {
+        let output = crate::fsutil::shell_command(cmd).output().await.ok()?;
         if !output.status.success() {
             return None;
         }
@@ -165,11 +298,122 @@ async fn resolve_token(cfg: &Config) -> Option<String> {
     }
 }
 
+/// The user a token belongs to, from `GET /users/self`.
 #[derive(Debug, Deserialize)]
+pub struct UserProfile {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Course {
     pub id: u64,
+    /// Missing for courses whose access ended, see `access_restricted_by_date`.
+    #[serde(default)]
     pub name: String,
     pub course_code: Option<String>,
+    /// Enrollment term, with `include[]=term`.
+    #[serde(default)]
+    pub term: Option<Term>,
+    /// Set on concluded courses whose content can no longer be read.
+    #[serde(default)]
+    pub access_restricted_by_date: bool,
+    /// Course start date, when set on the course rather than its term.
+    #[serde(default)]
+    pub start_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Term {
+    pub id: u64,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub start_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExternalTool {
+    pub id: u64,
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub domain: Option<String>,
+}
+
+/// An announcement or discussion topic; `message` is its HTML body.
+#[derive(Debug, Deserialize)]
+pub struct DiscussionTopic {
+    pub id: u64,
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub user_name: Option<String>,
+    pub posted_at: Option<String>,
+}
+
+/// A top-level reply in a discussion.
+#[derive(Debug, Deserialize)]
+pub struct DiscussionEntry {
+    pub id: u64,
+    pub message: Option<String>,
+    pub user_name: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// A Kaltura-backed Canvas media object (media comments and recordings made in the editor).
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaObject {
+    pub media_id: String,
+    pub title: Option<String>,
+    pub user_entered_title: Option<String>,
+    #[serde(default)]
+    pub media_sources: Vec<MediaSource>,
+}
+
+/// One encoding of a media object. Canvas reports the numbers as strings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaSource {
+    pub url: String,
+    pub content_type: Option<String>,
+    #[serde(rename = "fileExt")]
+    pub file_ext: Option<String>,
+    pub height: Option<String>,
+    pub bitrate: Option<String>,
+    /// Size in KiB.
+    pub size: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarEvent {
+    pub id: u64,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub start_at: Option<String>,
+    pub end_at: Option<String>,
+    #[serde(default)]
+    pub all_day: bool,
+    /// `YYYY-MM-DD`, set for all-day events.
+    pub all_day_date: Option<String>,
+    pub location_name: Option<String>,
+    pub html_url: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CourseSyllabus {
+    syllabus_body: Option<String>,
+}
+
+/// One-time URL that launches an LTI tool without a Canvas browser session.
+#[derive(Debug, Deserialize)]
+pub struct SessionlessLaunch {
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Tab {
+    pub id: String,
+    pub label: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -190,6 +434,8 @@ pub struct ModuleItem {
     pub page_url: Option<String>,
     pub external_url: Option<String>,
     pub content_id: Option<u64>,
+    #[serde(default)]
+    pub indent: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -201,6 +447,60 @@ pub struct FileObj {
     pub updated_at: Option<String>,
     pub url: Option<String>,
     pub download_url: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(flatten)]
+    pub lock: FileLock,
+}
+
+/// Availability of a file as Canvas reports it to the current user.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct FileLock {
+    /// Unpublished, or outside its availability dates.
+    #[serde(default)]
+    pub locked: bool,
+    /// Left out of the Files list; still downloadable through its link.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Whether the current user is kept from the file; teachers see `false`
+    /// for files that are locked to students.
+    #[serde(default)]
+    pub locked_for_user: Option<bool>,
+    pub lock_at: Option<String>,
+    pub unlock_at: Option<String>,
+}
+
+impl FileObj {
+    /// Why the file cannot be downloaded at `now`, or `None` when it can.
+    pub fn lock_reason(&self, now: chrono::DateTime<chrono::Utc>) -> Option<String> {
+        let lock = &self.lock;
+        if lock.locked_for_user == Some(false) {
+            return None;
+        }
+        let time = |s: &Option<String>| {
+            s.as_deref()
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        };
+        if time(&lock.unlock_at).is_some_and(|t| t > now) {
+            return lock
+                .unlock_at
+                .as_ref()
+                .map(|at| format!("locked until {}", at));
+        }
+        if time(&lock.lock_at).is_some_and(|t| t <= now) {
+            return lock
+                .lock_at
+                .as_ref()
+                .map(|at| format!("locked since {}", at));
+        }
+        if lock.locked || lock.locked_for_user == Some(true) {
+            return Some("locked".to_string());
+        }
+        if lock.hidden && self.url.is_none() && self.download_url.is_none() {
+            return Some("hidden".to_string());
+        }
+        None
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -210,12 +510,193 @@ pub struct PageObj {
     pub updated_at: Option<String>,
 }
 
+/// A user enrolled in a course, or a member of a group.
+#[derive(Debug, Deserialize)]
+pub struct CourseUser {
+    pub id: u64,
+    pub name: Option<String>,
+    pub sortable_name: Option<String>,
+    pub login_id: Option<String>,
+    pub sis_user_id: Option<String>,
+    /// Only sent when the account may see email addresses.
+    pub email: Option<String>,
+    /// Course enrollments, with `include[]=enrollments`.
+    #[serde(default)]
+    pub enrollments: Vec<Enrollment>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Enrollment {
+    /// `StudentEnrollment`, `TeacherEnrollment`, `TaEnrollment`, ...
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Custom role name, or the base role's.
+    pub role: Option<String>,
+    pub enrollment_state: Option<String>,
+    pub course_section_id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Group {
+    pub id: u64,
+    pub name: Option<String>,
+    pub group_category_id: Option<u64>,
+    /// Course the group belongs to, for course groups.
+    pub course_id: Option<u64>,
+}
+
+/// A group set, e.g. "Lab groups".
+#[derive(Debug, Deserialize)]
+pub struct GroupCategory {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+/// A course export job; `attachment` is the package once it has been exported.
+#[derive(Debug, Deserialize)]
+pub struct ContentExport {
+    pub id: u64,
+    /// `created`, `exporting`, `exported` or `failed`.
+    pub workflow_state: String,
+    pub progress_url: Option<String>,
+    pub attachment: Option<FileObj>,
+}
+
+/// Progress of an asynchronous Canvas job.
+#[derive(Debug, Deserialize)]
+pub struct Progress {
+    /// `queued`, `running`, `completed` or `failed`.
+    pub workflow_state: String,
+    /// Percent done.
+    pub completion: Option<f64>,
+    pub message: Option<String>,
+}
+
+/// An Inbox thread; `messages` is only filled when fetched by id.
+#[derive(Debug, Deserialize)]
+pub struct Conversation {
+    pub id: u64,
+    pub subject: Option<String>,
+    pub last_message_at: Option<String>,
+    pub message_count: Option<u32>,
+    /// Name of the course or group the thread was sent in.
+    pub context_name: Option<String>,
+    #[serde(default)]
+    pub participants: Vec<ConversationParticipant>,
+    /// Newest first.
+    #[serde(default)]
+    pub messages: Vec<ConversationMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationParticipant {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationMessage {
+    pub id: u64,
+    pub created_at: Option<String>,
+    /// Plain text.
+    pub body: Option<String>,
+    pub author_id: Option<u64>,
+    #[serde(default)]
+    pub attachments: Vec<FileObj>,
+}
+
+/// A classic quiz; `description` is its HTML instructions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quiz {
+    pub id: u64,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub html_url: Option<String>,
+    pub description: Option<String>,
+    /// `assignment`, `practice_quiz`, `graded_survey` or `survey`.
+    pub quiz_type: Option<String>,
+    pub points_possible: Option<f64>,
+    pub question_count: Option<u32>,
+    /// Minutes, if the quiz is timed.
+    pub time_limit: Option<u32>,
+    /// `-1` for unlimited attempts.
+    pub allowed_attempts: Option<i32>,
+    pub due_at: Option<String>,
+    pub unlock_at: Option<String>,
+    pub lock_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// A question of a quiz, visible to accounts allowed to see the questions.
+#[derive(Debug, Deserialize)]
+pub struct QuizQuestion {
+    pub id: u64,
+    pub position: Option<u32>,
+    pub question_name: Option<String>,
+    pub question_type: Option<String>,
+    /// HTML body of the question.
+    pub question_text: Option<String>,
+    pub points_possible: Option<f64>,
+    #[serde(default)]
+    pub answers: Vec<QuizAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizAnswer {
+    pub text: Option<String>,
+    /// HTML version of `text`, when the answer is formatted.
+    pub html: Option<String>,
+    /// 100 for a correct answer; only sent to accounts that may see it.
+    pub weight: Option<f64>,
+}
+
+/// A saved version of a page, from its edit history.
+#[derive(Debug, Deserialize)]
+pub struct PageRevision {
+    pub revision_id: u64,
+    pub updated_at: Option<String>,
+    /// Title and body are only sent when a single revision is fetched.
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// A page as listed for a course, without its body.
+#[derive(Debug, Deserialize)]
+pub struct PageSummary {
+    /// URL slug, as in module items' `page_url`.
+    pub url: String,
+    pub title: Option<String>,
+    pub updated_at: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Assignment {
     pub id: u64,
     pub name: Option<String>,
     pub description: Option<String>,
     pub updated_at: Option<String>,
+    pub due_at: Option<String>,
+    pub html_url: Option<String>,
+    /// Grading criteria, present when a rubric is attached.
+    #[serde(default)]
+    pub rubric: Option<Vec<RubricCriterion>>,
+}
+
+/// A rubric row: what is graded and the ratings it can get.
+#[derive(Debug, Deserialize)]
+pub struct RubricCriterion {
+    pub description: Option<String>,
+    pub long_description: Option<String>,
+    pub points: Option<f64>,
+    #[serde(default)]
+    pub ratings: Vec<RubricRating>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RubricRating {
+    pub description: Option<String>,
+    pub long_description: Option<String>,
+    pub points: Option<f64>,
 }
 
 impl CanvasClient {
@@ -229,12 +710,7 @@ impl CanvasClient {
             ))
             .unwrap();
         tracing::debug!(method = "GET", url = %url, "canvas request");
-        let resp = self
-            .http
-            .get(url)
-            .header(header::AUTHORIZATION, self.auth_header_val())
-            .send()
-            .await?;
+        let resp = self.send(self.http.client.get(url)).await?;
         let status = resp.status();
         let text = resp.text().await?;
         if !status.is_success() {
@@ -257,12 +733,7 @@ impl CanvasClient {
             .join(&format!("/api/v1/files/{}", file_id))
             .unwrap();
         debug!(method = "GET", file_id, url = %url, "canvas request (get_file)");
-        let resp = self
-            .http
-            .get(url)
-            .header(header::AUTHORIZATION, self.auth_header_val())
-            .send()
-            .await?;
+        let resp = self.send(self.http.client.get(url)).await?;
         let status = resp.status();
         let text = resp.text().await?;
         if !status.is_success() {
@@ -281,6 +752,9 @@ impl CanvasClient {
     }
 
     pub async fn list_assignments(&self, course_id: u64) -> Result<Vec<Assignment>, CanvasError> {
+        if let Some(assignments) = self.prefetched_assignments(course_id) {
+            return Ok(assignments);
+        }
         let mut out = Vec::new();
         let mut next = Some(
             self.base
@@ -292,12 +766,7 @@ impl CanvasClient {
         );
         while let Some(url) = next.take() {
             debug!(method = "GET", course_id = course_id, url = %url, "canvas request (assignments)");
-            let resp = self
-                .http
-                .get(url.clone())
-                .header(header::AUTHORIZATION, self.auth_header_val())
-                .send()
-                .await?;
+            let resp = self.send(self.http.client.get(url.clone())).await?;
             let status = resp.status();
             let link = resp
                 .headers()
@@ -323,6 +792,492 @@ impl CanvasClient {
         }
         Ok(out)
     }
+
+    /// LTI tools installed in a course or its account (needs tool-management permission).
+    pub async fn list_external_tools(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<ExternalTool>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/external_tools?include_parents=true&per_page=100",
+                course_id
+            ),
+            "external tools",
+        )
+        .await
+    }
+
+    /// Navigation tabs visible to the user, including LTI tools (`context_external_tool_<id>`).
+    pub async fn list_tabs(&self, course_id: u64) -> Result<Vec<Tab>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/courses/{}/tabs?per_page=100", course_id),
+            "tabs",
+        )
+        .await
+    }
+
+    /// Launch URL for a course's LTI tool authenticated by the API token, so a
+    /// fresh browser reaches the tool without signing in to Canvas.
+    pub async fn sessionless_launch(
+        &self,
+        course_id: u64,
+        tool_id: u64,
+    ) -> Result<SessionlessLaunch, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/courses/{}/external_tools/sessionless_launch?id={}",
+                course_id, tool_id
+            ))
+            .unwrap();
+        self.get_one(url, "sessionless launch").await
+    }
+
+    /// Announcements posted in a course.
+    pub async fn list_announcements(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<DiscussionTopic>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/discussion_topics?only_announcements=true&per_page=100",
+                course_id
+            ),
+            "announcements",
+        )
+        .await
+    }
+
+    /// Discussion topics of a course (announcements excluded).
+    pub async fn list_discussion_topics(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<DiscussionTopic>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/discussion_topics?per_page=100",
+                course_id
+            ),
+            "discussion topics",
+        )
+        .await
+    }
+
+    /// Top-level replies to a discussion topic.
+    pub async fn list_discussion_entries(
+        &self,
+        course_id: u64,
+        topic_id: u64,
+    ) -> Result<Vec<DiscussionEntry>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/discussion_topics/{}/entries?per_page=100",
+                course_id, topic_id
+            ),
+            "discussion entries",
+        )
+        .await
+    }
+
+    /// Every calendar event of a course, past and future.
+    pub async fn list_calendar_events(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<CalendarEvent>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/calendar_events?context_codes[]=course_{}&all_events=true&per_page=100",
+                course_id
+            ),
+            "calendar events",
+        )
+        .await
+    }
+
+    /// Inbox threads in `scope` (`inbox`, `sent` or `archived`), without messages.
+    pub async fn list_conversations(&self, scope: &str) -> Result<Vec<Conversation>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/conversations?scope={}&per_page=100", scope),
+            "conversations",
+        )
+        .await
+    }
+
+    /// An Inbox thread with its messages, leaving its read state as it is.
+    pub async fn get_conversation(&self, id: u64) -> Result<Conversation, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/conversations/{}?auto_mark_as_read=false",
+                id
+            ))
+            .unwrap();
+        self.get_one(url, "conversation").await
+    }
+
+    /// Users enrolled in a course, with their enrollments and (if visible) emails.
+    pub async fn list_course_users(&self, course_id: u64) -> Result<Vec<CourseUser>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/users?enrollment_type[]=student&enrollment_type[]=teacher&enrollment_type[]=ta&enrollment_type[]=designer&enrollment_type[]=observer&include[]=enrollments&include[]=email&per_page=100",
+                course_id
+            ),
+            "course users",
+        )
+        .await
+    }
+
+    pub async fn list_groups(&self, course_id: u64) -> Result<Vec<Group>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/courses/{}/groups?per_page=100", course_id),
+            "groups",
+        )
+        .await
+    }
+
+    pub async fn list_group_categories(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<GroupCategory>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/group_categories?per_page=100",
+                course_id
+            ),
+            "group categories",
+        )
+        .await
+    }
+
+    pub async fn list_group_users(&self, group_id: u64) -> Result<Vec<CourseUser>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/groups/{}/users?per_page=100", group_id),
+            "group users",
+        )
+        .await
+    }
+
+    /// Groups the signed-in user belongs to, across courses.
+    pub async fn list_my_groups(&self) -> Result<Vec<Group>, CanvasError> {
+        self.get_all("/api/v1/users/self/groups?per_page=100", "my groups")
+            .await
+    }
+
+    /// Wiki pages of a group workspace, without bodies.
+    pub async fn list_group_pages(&self, group_id: u64) -> Result<Vec<PageSummary>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/groups/{}/pages?per_page=100", group_id),
+            "group pages",
+        )
+        .await
+    }
+
+    pub async fn get_group_page(
+        &self,
+        group_id: u64,
+        page_url: &str,
+    ) -> Result<PageObj, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/groups/{}/pages/{}",
+                group_id,
+                urlencoding::encode(page_url)
+            ))
+            .unwrap();
+        self.get_one(url, "group page").await
+    }
+
+    /// Files uploaded to a group workspace.
+    pub async fn list_group_files(&self, group_id: u64) -> Result<Vec<FileObj>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/groups/{}/files?per_page=100", group_id),
+            "group files",
+        )
+        .await
+    }
+
+    /// Discussion topics of a group, or its announcements with `announcements`.
+    pub async fn list_group_discussion_topics(
+        &self,
+        group_id: u64,
+        announcements: bool,
+    ) -> Result<Vec<DiscussionTopic>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/groups/{}/discussion_topics?only_announcements={}&per_page=100",
+                group_id, announcements
+            ),
+            "group discussion topics",
+        )
+        .await
+    }
+
+    pub async fn get_group_discussion_topic(
+        &self,
+        group_id: u64,
+        topic_id: u64,
+    ) -> Result<DiscussionTopic, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/groups/{}/discussion_topics/{}",
+                group_id, topic_id
+            ))
+            .unwrap();
+        self.get_one(url, "group discussion topic").await
+    }
+
+    /// Top-level replies to a group discussion topic.
+    pub async fn list_group_discussion_entries(
+        &self,
+        group_id: u64,
+        topic_id: u64,
+    ) -> Result<Vec<DiscussionEntry>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/groups/{}/discussion_topics/{}/entries?per_page=100",
+                group_id, topic_id
+            ),
+            "group discussion entries",
+        )
+        .await
+    }
+
+    /// Start a Common Cartridge (`.imscc`) export of a course. Canvas only lets
+    /// teachers and admins export; students get 401/403.
+    pub async fn start_course_export(&self, course_id: u64) -> Result<ContentExport, CanvasError> {
+        let url = self
+            .base
+            .join(&format!("/api/v1/courses/{}/content_exports", course_id))
+            .unwrap();
+        debug!(method = "POST", url = %url, "canvas request (content export)");
+        let rb = self
+            .http
+            .client
+            .post(url)
+            .form(&[("export_type", "common_cartridge")]);
+        self.decode_one(rb, "content export").await
+    }
+
+    pub async fn get_course_export(
+        &self,
+        course_id: u64,
+        export_id: u64,
+    ) -> Result<ContentExport, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/courses/{}/content_exports/{}",
+                course_id, export_id
+            ))
+            .unwrap();
+        self.get_one(url, "content export").await
+    }
+
+    /// An asynchronous job's progress, from the `progress_url` Canvas returned.
+    pub async fn get_progress(&self, progress_url: &str) -> Result<Progress, CanvasError> {
+        let url = self
+            .base
+            .join(progress_url)
+            .map_err(|e| CanvasError::Decode(tr!("invalid progress url: {}", e)))?;
+        self.get_one(url, "progress").await
+    }
+
+    /// Classic quizzes of a course.
+    pub async fn list_quizzes(&self, course_id: u64) -> Result<Vec<Quiz>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/courses/{}/quizzes?per_page=100", course_id),
+            "quizzes",
+        )
+        .await
+    }
+
+    pub async fn get_quiz(&self, course_id: u64, quiz_id: u64) -> Result<Quiz, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/courses/{}/quizzes/{}",
+                course_id, quiz_id
+            ))
+            .unwrap();
+        self.get_one(url, "quiz").await
+    }
+
+    /// Questions of a quiz. Canvas only lists them for accounts that may edit the
+    /// quiz (or when the quiz shows them after submission); others get 401/403.
+    pub async fn list_quiz_questions(
+        &self,
+        course_id: u64,
+        quiz_id: u64,
+    ) -> Result<Vec<QuizQuestion>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/quizzes/{}/questions?per_page=100",
+                course_id, quiz_id
+            ),
+            "quiz questions",
+        )
+        .await
+    }
+
+    /// Every page of a course with its last update time, without bodies.
+    pub async fn list_pages(&self, course_id: u64) -> Result<Vec<PageSummary>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/courses/{}/pages?per_page=100", course_id),
+            "pages",
+        )
+        .await
+    }
+
+    /// Saved versions of a page, newest first, without their bodies. Canvas only
+    /// shows the history to accounts that may edit the page; others get 401/403.
+    pub async fn list_page_revisions(
+        &self,
+        course_id: u64,
+        page_url: &str,
+    ) -> Result<Vec<PageRevision>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/pages/{}/revisions?per_page=100",
+                course_id,
+                urlencoding::encode(page_url)
+            ),
+            "page revisions",
+        )
+        .await
+    }
+
+    pub async fn get_page_revision(
+        &self,
+        course_id: u64,
+        page_url: &str,
+        revision_id: u64,
+    ) -> Result<PageRevision, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/courses/{}/pages/{}/revisions/{}",
+                course_id,
+                urlencoding::encode(page_url),
+                revision_id
+            ))
+            .unwrap();
+        self.get_one(url, "page revision").await
+    }
+
+    /// Every file of a course. Students often may not list files (Canvas answers
+    /// 401 or 403) even though they can open the ones linked from modules.
+    pub async fn list_files(&self, course_id: u64) -> Result<Vec<FileObj>, CanvasError> {
+        self.get_all(
+            &format!("/api/v1/courses/{}/files?per_page=100", course_id),
+            "files",
+        )
+        .await
+    }
+
+    pub async fn list_media_objects(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<MediaObject>, CanvasError> {
+        self.get_all(
+            &format!(
+                "/api/v1/courses/{}/media_objects?exclude[]=tracks&per_page=100",
+                course_id
+            ),
+            "media objects",
+        )
+        .await
+    }
+
+    /// The user the token belongs to; a cheap way to check that the token works.
+    pub async fn get_self(&self) -> Result<UserProfile, CanvasError> {
+        let url = self.base.join("/api/v1/users/self").unwrap();
+        self.get_one(url, "self").await
+    }
+
+    /// HTML body of the course syllabus, if one is published.
+    pub async fn get_syllabus(&self, course_id: u64) -> Result<Option<String>, CanvasError> {
+        let url = self
+            .base
+            .join(&format!(
+                "/api/v1/courses/{}?include[]=syllabus_body",
+                course_id
+            ))
+            .unwrap();
+        let course: CourseSyllabus = self.get_one(url, "syllabus").await?;
+        Ok(course.syllabus_body)
+    }
+
+    /// GET a single JSON object.
+    async fn get_one<T: DeserializeOwned>(&self, url: Url, what: &str) -> Result<T, CanvasError> {
+        debug!(method = "GET", url = %url, "canvas request ({what})");
+        self.decode_one(self.http.client.get(url), what).await
+    }
+
+    /// Send `rb` and decode its JSON body, mapping non-success statuses to errors.
+    pub(crate) async fn decode_one<T: DeserializeOwned>(
+        &self,
+        rb: RequestBuilder,
+        what: &str,
+    ) -> Result<T, CanvasError> {
+        let resp = self.send(rb).await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let snippet = text.chars().take(500).collect::<String>();
+            debug!(status = %status.as_u16(), body = %snippet, "canvas non-success response ({what})");
+            return Err(CanvasError::Status(status.as_u16(), snippet));
+        }
+        serde_json::from_str(&text).map_err(|e| {
+            error!(error = %e, "canvas decode failure ({what})");
+            CanvasError::Decode(e.to_string())
+        })
+    }
+
+    /// GET every page of a paginated list endpoint.
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        what: &str,
+    ) -> Result<Vec<T>, CanvasError> {
+        let mut out = Vec::new();
+        let mut next = Some(self.base.join(path).unwrap());
+        while let Some(url) = next.take() {
+            debug!(method = "GET", url = %url, "canvas request ({what})");
+            let resp = self.send(self.http.client.get(url.clone())).await?;
+            let status = resp.status();
+            let link = resp
+                .headers()
+                .get(header::LINK)
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.to_string());
+            let text = resp.text().await?;
+            if !status.is_success() {
+                let snippet = text.chars().take(500).collect::<String>();
+                debug!(status = %status.as_u16(), body = %snippet, "canvas non-success response ({what})");
+                return Err(CanvasError::Status(status.as_u16(), snippet));
+            }
+            let mut page: Vec<T> = serde_json::from_str(&text).map_err(|e| {
+                error!(error = %e, "canvas decode failure ({what})");
+                CanvasError::Decode(e.to_string())
+            })?;
+            out.append(&mut page);
+            next = link.as_deref().and_then(parse_next_link);
+        }
+        Ok(out)
+    }
+}
+
+/// First message of a Canvas error body such as
+/// `{"errors":[{"message":"Invalid access token."}]}`, else the status.
+fn rejection_reason(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v["errors"][0]["message"].as_str().map(str::to_string))
+        .map(|m| format!("HTTP 401: {}", m.trim_end_matches('.')))
+        .unwrap_or_else(|| "HTTP 401".to_string())
 }
 
 #[cfg(test)]
@@ -350,4 +1305,32 @@ mod tests {
         }
         assert_eq!(count, 3);
     }
+
+    #[test]
+    fn file_lock_reasons() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let file = |json: &str| -> FileObj { serde_json::from_str(json).unwrap() };
+        let open = file(r#"{"id": 1, "url": "https://x/files/1/download"}"#);
+        assert_eq!(open.lock_reason(now), None);
+        let scheduled = file(r#"{"id": 1, "locked": true, "unlock_at": "2024-03-04T08:00:00Z"}"#);
+        assert_eq!(
+            scheduled.lock_reason(now).as_deref(),
+            Some("locked until 2024-03-04T08:00:00Z")
+        );
+        let closed = file(r#"{"id": 1, "lock_at": "2024-02-28T23:59:00Z"}"#);
+        assert_eq!(
+            closed.lock_reason(now).as_deref(),
+            Some("locked since 2024-02-28T23:59:00Z")
+        );
+        // Teachers can still download files locked to students
+        let teacher = file(r#"{"id": 1, "locked": true, "locked_for_user": false}"#);
+        assert_eq!(teacher.lock_reason(now), None);
+        // Hidden files are reachable through their link
+        let hidden = file(r#"{"id": 1, "hidden": true, "url": "https://x/files/1/download"}"#);
+        assert_eq!(hidden.lock_reason(now), None);
+        let withheld = file(r#"{"id": 1, "hidden": true}"#);
+        assert_eq!(withheld.lock_reason(now).as_deref(), Some("hidden"));
+    }
 }
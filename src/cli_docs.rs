@@ -0,0 +1,84 @@
+use clap::Command;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Writes the man page for `cmd` plus one per visible subcommand into `dir`,
+/// named after the command path, e.g. `u_crawler.1` and `u_crawler-zoom-flow.1`.
+/// `cmd` must be the fully configured CLI, e.g. `Cli::command()`.
+pub fn write_man_pages(mut cmd: Command, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    // Propagate global flags and set bin names before rendering subcommands
+    cmd.build();
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    let name = cmd.get_name().to_string();
+    render_tree(&cmd, name, dir, &mut written)?;
+    Ok(written)
+}
+
+fn render_tree(
+    cmd: &Command,
+    name: String,
+    dir: &Path,
+    written: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(cmd.clone().display_name(name.clone())).render(&mut page)?;
+    let path = dir.join(format!("{}.1", name));
+    std::fs::write(&path, page)?;
+    written.push(path);
+    for sub in cmd
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set() && s.get_name() != "help")
+    {
+        render_tree(sub, format!("{}-{}", name, sub.get_name()), dir, written)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn cli() -> Command {
+        Command::new("u_crawler")
+            .version("1.2.3")
+            .about("Canvas/Zoom course backup CLI")
+            .arg(
+                Arg::new("record_fixtures")
+                    .long("record-fixtures")
+                    .global(true)
+                    .value_name("DIR")
+                    .help("Save Canvas responses"),
+            )
+            .subcommand(
+                Command::new("zoom").about("Zoom operations").subcommand(
+                    Command::new("flow").about("Run the Zoom flow").arg(
+                        Arg::new("course_id")
+                            .long("course-id")
+                            .help("Canvas course id"),
+                    ),
+                ),
+            )
+    }
+
+    #[test]
+    fn one_man_page_per_subcommand() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_man_pages(cli(), dir.path()).unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            ["u_crawler.1", "u_crawler-zoom.1", "u_crawler-zoom-flow.1"]
+        );
+
+        let flow = std::fs::read_to_string(dir.path().join("u_crawler-zoom-flow.1")).unwrap();
+        assert!(flow.contains("u_crawler\\-zoom\\-flow"));
+        assert!(flow.contains("\\-\\-course\\-id"));
+        // Global flags are propagated to every page
+        assert!(flow.contains("\\-\\-record\\-fixtures"));
+    }
+}
@@ -1,6 +1,8 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -25,21 +27,319 @@ pub struct Config {
     pub concurrency: u32,
     pub max_rps: u32,
     pub user_agent: String,
+    /// Cap on total download bandwidth, e.g. `"5MiB/s"` or `"800KB/s"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_download_rate: Option<String>,
+    /// Free space to keep on the `download_root` filesystem, e.g. `"1GiB"`.
+    #[serde(default = "default_min_free_space")]
+    pub min_free_space: String,
     pub course_include: Vec<String>,
     pub course_exclude: Vec<String>,
     pub week_pattern: String,
     #[serde(default)]
     pub naming: Naming,
     #[serde(default)]
+    pub courses: Courses,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub storage: Storage,
+    #[serde(default)]
     pub logging: Logging,
+    #[serde(default)]
+    pub ui: Ui,
+    /// Free-form labels per course id, e.g. `"12345" = ["math", "elective"]`.
+    #[serde(default)]
+    pub course_tags: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub manifest: Manifest,
+    #[serde(default)]
+    pub http: Http,
     pub canvas: Canvas,
     pub zoom: Zoom,
+    #[serde(default)]
+    pub panopto: Panopto,
+    /// yt-dlp settings; when set, YouTube and Vimeo videos linked in course pages are archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_video_downloader: Option<ExternalVideoDownloader>,
+    /// Save web pages linked as External URL module items as MHTML snapshots during sync.
+    #[serde(default)]
+    pub snapshot_external_urls: bool,
+    /// Moodle site synced with `sync --moodle`; written by `auth moodle`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moodle: Option<Moodle>,
+    /// `--download-root` / `U_CRAWLER_DOWNLOAD_ROOT` for this run; never saved.
+    #[serde(skip)]
+    pub root_override: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Naming {
     #[serde(default = "default_true")]
     pub safe_fs: bool,
+    /// Nest course folders under their first tag (`<download_root>/<tag>/<course>`).
+    #[serde(default)]
+    pub group_by_tag: bool,
+    /// When a course's folder name changes (renamed course, new tag): `move` the
+    /// existing folder, or `keep` syncing into it and link the new name to it.
+    #[serde(default = "default_course_renames")]
+    pub course_renames: String,
+}
+
+fn default_course_renames() -> String {
+    "move".into()
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Self {
+            safe_fs: true,
+            group_by_tag: false,
+            course_renames: default_course_renames(),
+        }
+    }
+}
+
+/// How the CLI talks to the user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ui {
+    /// Language of messages, summaries and prompts: `en` or `es`.
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+fn default_language() -> String {
+    "en".into()
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self {
+            language: default_language(),
+        }
+    }
+}
+
+/// Which Canvas courses are listed. `sync --include-concluded` adds `completed`
+/// to `state` and `sync --term` replaces `terms` for one run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Courses {
+    /// Enrollment states listed: `active`, `completed` (concluded courses) and
+    /// `invited_or_pending`.
+    #[serde(default = "default_course_states")]
+    pub state: Vec<String>,
+    /// Only courses in these enrollment terms, by term name or id; empty for all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<String>,
+}
+
+fn default_course_states() -> Vec<String> {
+    vec!["active".into()]
+}
+
+impl Default for Courses {
+    fn default() -> Self {
+        Self {
+            state: default_course_states(),
+            terms: Vec::new(),
+        }
+    }
+}
+
+impl Courses {
+    /// Whether a course in `term` passes the `terms` filter. Names match
+    /// case-insensitively; courses without a term only pass an empty filter.
+    pub fn term_matches(&self, term: Option<(u64, &str)>) -> bool {
+        if self.terms.is_empty() {
+            return true;
+        }
+        let Some((id, name)) = term else {
+            return false;
+        };
+        self.terms
+            .iter()
+            .any(|t| t.trim().eq_ignore_ascii_case(name.trim()) || t.trim() == id.to_string())
+    }
+}
+
+/// Which files `sync` downloads. The `sync --include-ext`, `--exclude-ext` and
+/// `--max-file-size` flags replace these for one run.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct SyncSettings {
+    /// Only download files with these extensions, e.g. `["pdf", "pptx"]`; empty for all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_ext: Vec<String>,
+    /// Never download files with these extensions, e.g. `["zip"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_ext: Vec<String>,
+    /// Skip files larger than this, e.g. `"500MiB"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<String>,
+    /// Export each course's roster and group memberships to its `People/` folder.
+    #[serde(default)]
+    pub roster: bool,
+    /// Keep every saved version of each page in `Pages/<slug>/rev-<n>.md`.
+    #[serde(default)]
+    pub page_revisions: bool,
+}
+
+impl SyncSettings {
+    /// These settings with each filter set in `other` taking its place.
+    pub fn overridden_by(&self, other: &SyncSettings) -> SyncSettings {
+        let pick = |a: &Vec<String>, b: &Vec<String>| if b.is_empty() { a } else { b }.clone();
+        SyncSettings {
+            include_ext: pick(&self.include_ext, &other.include_ext),
+            exclude_ext: pick(&self.exclude_ext, &other.exclude_ext),
+            max_file_size: other.max_file_size.clone().or(self.max_file_size.clone()),
+            roster: self.roster,
+            page_revisions: self.page_revisions,
+        }
+    }
+
+    /// Why the file at `path` with `size` bytes is not downloaded, or `None` when
+    /// the filters let it through. Files of unknown size pass the size limit.
+    pub fn skip_reason(&self, path: &Path, size: Option<u64>) -> Option<String> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let listed = |exts: &[String]| {
+            exts.iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+        };
+        if !self.include_ext.is_empty() && !listed(&self.include_ext) {
+            return Some(format!("extension .{} not in sync.include_ext", ext));
+        }
+        if listed(&self.exclude_ext) {
+            return Some(format!("extension .{} in sync.exclude_ext", ext));
+        }
+        let max = self.max_file_size.as_deref().and_then(parse_size)?;
+        match size {
+            Some(size) if size > max => Some(format!(
+                "{} is over sync.max_file_size",
+                crate::progress::format_bytes(size)
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// How downloaded files are kept on disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Storage {
+    /// Keep one copy of files that several courses share, in `download_root/.store`,
+    /// and hard link (or symlink) the course copies to it.
+    #[serde(default)]
+    pub dedup: bool,
+    /// Keep `download_root` in a git repository and commit its text content
+    /// (pages, indexes, transcripts, calendars) after each sync.
+    #[serde(default)]
+    pub git: bool,
+    /// Save a course's `state.json` after this many changed items, besides after
+    /// each module, so a crash in a long module keeps what was downloaded; `0`
+    /// saves only after each module.
+    #[serde(default = "default_state_flush_items")]
+    pub state_flush_items: usize,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self {
+            dedup: false,
+            git: false,
+            state_flush_items: default_state_flush_items(),
+        }
+    }
+}
+
+/// Settings for exchanging archive manifests with classmates.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Manifest {
+    /// Name recorded in published manifests (defaults to `$USER`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    /// Shared group secret used to sign and verify manifests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// Network settings shared by all HTTP clients.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Http {
+    /// Proxy for all requests, e.g. `http://proxy.campus.edu:3128` or `socks5h://127.0.0.1:1080`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Concurrent range requests per large download; `1` disables splitting.
+    #[serde(default = "default_parallel_chunks")]
+    pub parallel_chunks: u32,
+    /// Minimum size before a download is split, e.g. `"64MiB"`.
+    #[serde(default = "default_parallel_threshold")]
+    pub parallel_threshold: String,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            parallel_chunks: default_parallel_chunks(),
+            parallel_threshold: default_parallel_threshold(),
+        }
+    }
+}
+
+/// Panopto recordings, for departments using Panopto instead of Zoom.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Panopto {
+    /// Panopto folder GUID per course id, for tools whose launch does not open a folder.
+    #[serde(default)]
+    pub folders: BTreeMap<String, String>,
+}
+
+/// yt-dlp invocation for externally hosted lecture videos.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExternalVideoDownloader {
+    /// yt-dlp executable.
+    #[serde(default = "default_yt_dlp_path")]
+    pub path: String,
+    /// Format selector passed as `-f`, e.g. `"bv*[height<=720]+ba/b[height<=720]"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Extra arguments, e.g. `["--cookies-from-browser", "firefox"]`.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Moodle {
+    /// Site URL, e.g. `https://moodle.example.edu`.
+    pub base_url: String,
+    /// Web service token for the Moodle mobile app service.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_cmd: Option<String>,
+    #[serde(default)]
+    pub ignored_courses: Vec<String>,
+}
+
+fn default_yt_dlp_path() -> String {
+    "yt-dlp".to_string()
+}
+
+fn default_min_free_space() -> String {
+    "1GiB".to_string()
+}
+
+fn default_state_flush_items() -> usize {
+    25
+}
+
+fn default_parallel_chunks() -> u32 {
+    4
+}
+
+fn default_parallel_threshold() -> String {
+    "64MiB".to_string()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -57,6 +357,20 @@ pub struct Canvas {
     pub sso_email: Option<String>,
     #[serde(default)]
     pub sso_password: Option<String>,
+    /// `rest`, or `graphql` to fetch modules and assignments of all courses in bulk.
+    #[serde(default = "default_canvas_api")]
+    pub api: String,
+    /// Folder for course folders instead of the global `download_root`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_root: Option<String>,
+    /// Canvas user to act as (`as_user_id`), e.g. `1234` or `sis_user_id:A001`,
+    /// for admins and support staff archiving a course on someone's behalf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_user_id: Option<String>,
+}
+
+fn default_canvas_api() -> String {
+    "rest".into()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -67,6 +381,164 @@ pub struct Zoom {
     pub user_agent: String,
     #[serde(default = "default_tool_id")]
     pub external_tool_id: u64,
+    /// ffmpeg `-readrate` multiplier of real-time speed (e.g. `2.0`) to throttle recordings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffmpeg_readrate: Option<f32>,
+    /// Proxy passed to Chromium via `--proxy-server` (defaults to `http.proxy`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser_proxy: Option<String>,
+    /// Times a recording is re-captured after its download token is rejected (401/403).
+    #[serde(default = "default_token_retries")]
+    pub token_retries: u32,
+    /// Renew the Zoom session through a sessionless LTI launch when its cookies expire
+    /// within this many hours (0 disables the proactive refresh).
+    #[serde(default = "default_refresh_within_hours")]
+    pub refresh_within_hours: u64,
+    /// Write title, date, course and transcript chapters into downloaded MP4s.
+    #[serde(default = "default_true")]
+    pub embed_metadata: bool,
+    /// Write a readable Markdown copy of each downloaded transcript next to it.
+    #[serde(default)]
+    pub transcript_notes: bool,
+    /// File recordings into `Recordings/Week_NN/` by meeting date, counting weeks
+    /// from the course start.
+    #[serde(default)]
+    pub recordings_by_week: bool,
+    /// Recording layouts to keep when a meeting has several videos (e.g. `speaker_view`,
+    /// `shared_screen_with_speaker_view`); empty keeps every view.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preferred_views: Vec<String>,
+    /// Timing and page selectors for the Canvas → SSO → Zoom browser flow.
+    #[serde(default)]
+    pub sso: Sso,
+    /// Re-encode recordings after download; absent means keep Zoom's original files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcode: Option<Transcode>,
+    /// Server-to-Server OAuth app; when set, recordings come from the Zoom REST API
+    /// instead of the Canvas LTI and headless browser.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<ZoomApi>,
+    /// Folder for recordings instead of the course folders, e.g. on an external
+    /// drive; recordings go to `<zoom root>/<course folder>/Recordings`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_root: Option<String>,
+}
+
+/// Credentials of a Zoom Server-to-Server OAuth app with `recording:read` scope.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ZoomApi {
+    pub account_id: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Zoom user (email or id) whose cloud recordings are listed.
+    pub user_id: String,
+    /// Canvas course id → regex over meeting topics selecting that course's
+    /// recordings; courses without an entry get every recording of `user_id`.
+    #[serde(default)]
+    pub course_topics: BTreeMap<String, String>,
+}
+
+/// Waits, timeouts and selectors used by the headless SSO flow, so other
+/// institutions' Canvas login pages can be handled without code changes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sso {
+    /// Upper bound, in seconds, for Canvas pages to finish loading after a redirect.
+    pub redirect_wait_secs: u64,
+    /// Upper bound, in seconds, for the Zoom player to appear once back from login.
+    pub step_wait_secs: u64,
+    /// Upper bound, in seconds, for the redirect away from Microsoft after the last step.
+    pub post_login_wait_secs: u64,
+    /// Upper bound, in seconds, for a Zoom play page to show the player or a login screen.
+    pub player_settle_secs: u64,
+    /// Seconds to look for a login button or form field (Canvas, Microsoft, Zoom).
+    pub button_timeout_secs: u64,
+    /// Seconds to wait for the redirect to the identity provider and back.
+    pub redirect_timeout_secs: u64,
+    /// Seconds to wait for the Zoom LTI to expose `lti_scid`.
+    pub scid_timeout_secs: u64,
+    /// Selectors for candidate SSO buttons on the Canvas login page.
+    pub login_button_selectors: Vec<String>,
+    /// Text identifying the SSO button among the candidates (case-insensitive);
+    /// when empty the first candidate is clicked.
+    pub login_button_texts: Vec<String>,
+}
+
+impl Default for Sso {
+    fn default() -> Self {
+        Self {
+            redirect_wait_secs: 5,
+            step_wait_secs: 2,
+            post_login_wait_secs: 5,
+            player_settle_secs: 3,
+            button_timeout_secs: 10,
+            redirect_timeout_secs: 30,
+            scid_timeout_secs: 60,
+            login_button_selectors: vec![".ic-Login__body button".to_string()],
+            login_button_texts: vec!["ESTUDIANTES Y DOCENTES".to_string()],
+        }
+    }
+}
+
+/// ffmpeg settings for compressing recordings after download.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transcode {
+    /// Video encoder, e.g. `libx265` or `libx264`.
+    #[serde(default = "default_transcode_codec")]
+    pub codec: String,
+    /// Constant rate factor; higher is smaller and lower quality.
+    #[serde(default = "default_transcode_crf")]
+    pub crf: u32,
+    /// Encoder speed/size trade-off (`veryfast` … `slow`).
+    #[serde(default = "default_transcode_preset")]
+    pub preset: String,
+    /// Scale down to at most this many lines (e.g. `720`); never upscales.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    /// AAC audio bitrate, e.g. `96k`.
+    #[serde(default = "default_transcode_audio_bitrate")]
+    pub audio_bitrate: String,
+}
+
+impl Default for Transcode {
+    fn default() -> Self {
+        Self {
+            codec: default_transcode_codec(),
+            crf: default_transcode_crf(),
+            preset: default_transcode_preset(),
+            max_height: None,
+            audio_bitrate: default_transcode_audio_bitrate(),
+        }
+    }
+}
+
+/// Parse a rate such as `5MiB/s`, `800 KB/s` or `1048576` into bytes per second.
+pub fn parse_rate(input: &str) -> Option<u64> {
+    let s = input.trim();
+    parse_size(s.strip_suffix("/s").unwrap_or(s))
+}
+
+/// Parse a size such as `64MiB`, `1.5 GB` or `4096` into bytes.
+/// Decimal (`KB`, `MB`, `GB`) and binary (`KiB`, `MiB`, `GiB`) units are accepted.
+pub fn parse_size(input: &str) -> Option<u64> {
+    let s = input.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: f64 = num.parse().ok()?;
+    let multiplier: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "ki" | "kib" => 1024.0,
+        "mi" | "mib" => 1024.0 * 1024.0,
+        "gi" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    let bytes = (value * multiplier).round();
+    (bytes >= 1.0).then_some(bytes as u64)
 }
 
 fn default_true() -> bool {
@@ -80,11 +552,25 @@ impl Default for Config {
             concurrency: 4,
             max_rps: 2,
             user_agent: String::new(),
+            max_download_rate: None,
+            min_free_space: default_min_free_space(),
             course_include: vec!["*".to_string()],
             course_exclude: vec![],
             week_pattern: String::new(),
-            naming: Naming { safe_fs: true },
+            naming: Naming::default(),
+            courses: Courses::default(),
+            sync: SyncSettings::default(),
+            storage: Storage::default(),
             logging: Logging::default(),
+            ui: Ui::default(),
+            course_tags: BTreeMap::new(),
+            manifest: Manifest::default(),
+            http: Http::default(),
+            panopto: Panopto::default(),
+            external_video_downloader: None,
+            snapshot_external_urls: false,
+            moodle: None,
+            root_override: None,
             canvas: Canvas {
                 base_url: "https://<tenant>.instructure.com".to_string(),
                 token: None,
@@ -93,6 +579,9 @@ impl Default for Config {
                 cookie_file: Some("~/.config/u_crawler/canvas_cookies.txt".to_string()),
                 sso_email: None,
                 sso_password: None,
+                api: default_canvas_api(),
+                download_root: None,
+                as_user_id: None,
             },
             zoom: Zoom {
                 enabled: true,
@@ -100,6 +589,18 @@ impl Default for Config {
                 cookie_file: "~/.config/u_crawler/zoom_cookies.txt".to_string(),
                 user_agent: "Mozilla/5.0".to_string(),
                 external_tool_id: 187,
+                ffmpeg_readrate: None,
+                browser_proxy: None,
+                token_retries: default_token_retries(),
+                refresh_within_hours: default_refresh_within_hours(),
+                embed_metadata: true,
+                transcript_notes: false,
+                recordings_by_week: false,
+                preferred_views: Vec::new(),
+                sso: Sso::default(),
+                transcode: None,
+                api: None,
+                download_root: None,
             },
         }
     }
@@ -124,6 +625,13 @@ impl Config {
         let content = std::fs::read_to_string(&paths.config_file)?;
         let mut cfg: Config = toml::from_str(&content)?;
         cfg.postprocess_and_validate()?;
+        cfg.root_override = overrides()
+            .and_then(|o| o.download_root.as_deref())
+            .map(|root| {
+                crate::fsutil::long_path(root)
+                    .to_string_lossy()
+                    .into_owned()
+            });
         Ok(cfg)
     }
 
@@ -155,6 +663,127 @@ impl Config {
             missing.push("canvas.token or canvas.token_cmd".to_string());
         }
 
+        if let Some(proxy) = self.http.proxy.as_deref() {
+            if reqwest::Proxy::all(proxy).is_err() {
+                missing.push("http.proxy".to_string());
+            }
+        }
+
+        if let Some(rate) = self.max_download_rate.as_deref() {
+            if parse_rate(rate).is_none() {
+                missing.push("max_download_rate".to_string());
+            }
+        }
+
+        if parse_size(&self.min_free_space).is_none() && self.min_free_space.trim() != "0" {
+            missing.push("min_free_space".to_string());
+        }
+
+        if parse_size(&self.http.parallel_threshold).is_none() {
+            missing.push("http.parallel_threshold".to_string());
+        }
+
+        if let Some(max) = &self.sync.max_file_size {
+            if parse_size(max).is_none() {
+                missing.push("sync.max_file_size".to_string());
+            }
+        }
+
+        if self.courses.state.is_empty()
+            || self
+                .courses
+                .state
+                .iter()
+                .any(|s| !matches!(s.as_str(), "active" | "completed" | "invited_or_pending"))
+        {
+            missing.push("courses.state".to_string());
+        }
+
+        if !self.week_pattern.trim().is_empty() && regex::Regex::new(&self.week_pattern).is_err() {
+            missing.push("week_pattern".to_string());
+        }
+
+        if !matches!(self.canvas.api.as_str(), "rest" | "graphql") {
+            missing.push("canvas.api".to_string());
+        }
+
+        if !matches!(self.naming.course_renames.as_str(), "move" | "keep") {
+            missing.push("naming.course_renames".to_string());
+        }
+
+        if !matches!(self.logging.format.as_str(), "text" | "json") {
+            missing.push("logging.format".to_string());
+        }
+
+        if self.logging.rotation().is_none() {
+            missing.push("logging.rotate".to_string());
+        }
+
+        if self.logging.max_files == 0 {
+            missing.push("logging.max_files".to_string());
+        }
+
+        if crate::i18n::Lang::parse(&self.ui.language).is_none() {
+            missing.push("ui.language".to_string());
+        }
+
+        let otlp = self.logging.otlp_endpoint.trim();
+        if !otlp.is_empty() && !otlp.starts_with("http://") && !otlp.starts_with("https://") {
+            missing.push("logging.otlp_endpoint".to_string());
+        }
+
+        if self.zoom.ffmpeg_readrate.is_some_and(|r| r <= 0.0) {
+            missing.push("zoom.ffmpeg_readrate".to_string());
+        }
+
+        if let Some(t) = &self.zoom.transcode {
+            if t.codec.trim().is_empty() {
+                missing.push("zoom.transcode.codec".to_string());
+            }
+            if t.crf > 51 {
+                missing.push("zoom.transcode.crf".to_string());
+            }
+            if t.max_height == Some(0) {
+                missing.push("zoom.transcode.max_height".to_string());
+            }
+        }
+
+        if let Some(api) = &self.zoom.api {
+            for (field, value) in [
+                ("zoom.api.account_id", &api.account_id),
+                ("zoom.api.client_id", &api.client_id),
+                ("zoom.api.client_secret", &api.client_secret),
+                ("zoom.api.user_id", &api.user_id),
+            ] {
+                if value.trim().is_empty() {
+                    missing.push(field.to_string());
+                }
+            }
+            for (course, pattern) in &api.course_topics {
+                if regex::Regex::new(pattern).is_err() {
+                    missing.push(format!("zoom.api.course_topics.{}", course));
+                }
+            }
+        }
+
+        let sso = &self.zoom.sso;
+        for (field, secs) in [
+            ("zoom.sso.button_timeout_secs", sso.button_timeout_secs),
+            ("zoom.sso.redirect_timeout_secs", sso.redirect_timeout_secs),
+            ("zoom.sso.scid_timeout_secs", sso.scid_timeout_secs),
+        ] {
+            if secs == 0 {
+                missing.push(field.to_string());
+            }
+        }
+        if sso
+            .login_button_selectors
+            .iter()
+            .all(|s| s.trim().is_empty())
+        {
+            missing.push("zoom.sso.login_button_selectors".to_string());
+        }
+
         if self.zoom.enabled && self.zoom.ffmpeg_path.trim().is_empty() {
             missing.push("zoom.ffmpeg_path".to_string());
         }
@@ -166,16 +795,140 @@ impl Config {
         Ok(())
     }
 
-    /// Expand tildes in path-like fields. No-op if expansion fails.
+    /// Download bandwidth cap in bytes per second, if configured.
+    pub fn download_rate_limit(&self) -> Option<u64> {
+        self.max_download_rate.as_deref().and_then(parse_rate)
+    }
+
+    /// Bytes to keep free on the `download_root` filesystem.
+    pub fn free_space_reserve(&self) -> u64 {
+        parse_size(&self.min_free_space).unwrap_or(0)
+    }
+
+    /// `download_root`, or the `--download-root` given for this run.
+    pub fn base_root(&self) -> PathBuf {
+        PathBuf::from(self.root_override.as_deref().unwrap_or(&self.download_root))
+    }
+
+    /// Folder holding the course folders: `canvas.download_root`, else `download_root`.
+    /// A `--download-root` for this run replaces both.
+    pub fn canvas_root(&self) -> PathBuf {
+        if self.root_override.is_some() {
+            return self.base_root();
+        }
+        PathBuf::from(
+            self.canvas
+                .download_root
+                .as_deref()
+                .filter(|root| !root.trim().is_empty())
+                .unwrap_or(&self.download_root),
+        )
+    }
+
+    /// `zoom.download_root`, when recordings are kept apart from the course folders.
+    /// `None` under a `--download-root` for this run, which holds everything.
+    pub fn zoom_root(&self) -> Option<PathBuf> {
+        if self.root_override.is_some() {
+            return None;
+        }
+        self.zoom
+            .download_root
+            .as_deref()
+            .filter(|root| !root.trim().is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// `zoom.download_root` when it differs from the course folders' root. It is
+    /// laid out like that root: `<course folder>/Recordings` and `Zoom/<course id>`.
+    pub fn separate_zoom_root(&self) -> Option<PathBuf> {
+        self.zoom_root().filter(|zoom| *zoom != self.canvas_root())
+    }
+
+    /// Folders the archive is spread over: the course folders' root, then
+    /// `zoom.download_root` when recordings are kept apart from them.
+    pub fn archive_roots(&self) -> Vec<PathBuf> {
+        std::iter::once(self.canvas_root())
+            .chain(self.separate_zoom_root())
+            .collect()
+    }
+
+    /// Folder under `zoom.download_root` mirroring `course_dir`, which holds the
+    /// course's `Recordings` when they are kept apart from the course folders.
+    pub fn zoom_course_dir(&self, course_dir: &Path) -> Option<PathBuf> {
+        let zoom_root = self.separate_zoom_root()?;
+        let rel = course_dir.strip_prefix(self.canvas_root()).ok()?;
+        Some(zoom_root.join(rel))
+    }
+
+    /// Proxy for the headless browser: `zoom.browser_proxy`, else `http.proxy`.
+    pub fn browser_proxy(&self) -> Option<&str> {
+        self.zoom
+            .browser_proxy
+            .as_deref()
+            .or(self.http.proxy.as_deref())
+            .filter(|p| !p.trim().is_empty())
+    }
+
+    /// Tags configured for a course, empty when untagged.
+    pub fn tags_for(&self, course_id: u64) -> &[String] {
+        self.course_tags
+            .get(&course_id.to_string())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Add tags to a course, keeping them unique and sorted.
+    pub fn add_tags(&mut self, course_id: u64, tags: &[String]) {
+        let entry = self.course_tags.entry(course_id.to_string()).or_default();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !entry.iter().any(|t| t == tag) {
+                entry.push(tag.to_string());
+            }
+        }
+        entry.sort();
+    }
+
+    /// Remove tags from a course, dropping the entry once it is empty.
+    pub fn remove_tags(&mut self, course_id: u64, tags: &[String]) {
+        let key = course_id.to_string();
+        if let Some(entry) = self.course_tags.get_mut(&key) {
+            entry.retain(|t| !tags.contains(t));
+            if entry.is_empty() {
+                self.course_tags.remove(&key);
+            }
+        }
+    }
+
+    /// Expand tildes in path-like fields, and give the download roots the long-path
+    /// form on Windows.
     pub fn expand_paths(&mut self) {
+        let long_path = |root: &str| {
+            crate::fsutil::long_path(Path::new(root))
+                .to_string_lossy()
+                .into_owned()
+        };
         if let Some(home) = dirs_next::home_dir() {
             self.download_root = expand_tilde(&self.download_root, &home);
+            for root in [&mut self.canvas.download_root, &mut self.zoom.download_root]
+                .into_iter()
+                .flatten()
+            {
+                *root = expand_tilde(root, &home);
+            }
             self.zoom.cookie_file = expand_tilde(&self.zoom.cookie_file, &home);
             self.logging.file = expand_tilde(&self.logging.file, &home);
             if let Some(cf) = &self.canvas.cookie_file {
                 self.canvas.cookie_file = Some(expand_tilde(cf, &home));
             }
         }
+        self.download_root = long_path(&self.download_root);
+        for root in [&mut self.canvas.download_root, &mut self.zoom.download_root]
+            .into_iter()
+            .flatten()
+        {
+            *root = long_path(root);
+        }
     }
 }
 
@@ -188,20 +941,60 @@ fn expand_tilde(input: &str, home: &Path) -> String {
     input.to_string()
 }
 
+/// Locations given on the command line instead of the defaults.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    /// Config file to use; its directory also holds the history, log and Zoom state.
+    pub config_file: Option<PathBuf>,
+    /// Download root replacing the one in the config file, without saving it there.
+    pub download_root: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Apply `overrides` to every later config lookup; only the first call applies.
+pub fn set_overrides(overrides: Overrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn overrides() -> Option<&'static Overrides> {
+    OVERRIDES.get()
+}
+
 #[derive(Clone, Debug)]
 pub struct ConfigPaths {
     pub config_dir: PathBuf,
     pub config_file: PathBuf,
+    pub history_file: PathBuf,
 }
 
 impl ConfigPaths {
+    /// Default locations, or the ones under `--config` / `U_CRAWLER_CONFIG` when given.
     pub fn new() -> Result<Self, ConfigError> {
-        let proj = ProjectDirs::from("", "", "u_crawler").ok_or(ConfigError::NoConfigDir)?;
-        let dir = proj.config_dir().to_path_buf();
-        let file = dir.join("config.toml");
+        let custom = overrides()
+            .and_then(|o| o.config_file.clone())
+            .or_else(|| std::env::var_os("U_CRAWLER_CONFIG").map(PathBuf::from));
+        let (dir, file) = match custom {
+            Some(file) => {
+                let dir = match file.parent() {
+                    Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                (dir, file)
+            }
+            None => {
+                let proj =
+                    ProjectDirs::from("", "", "u_crawler").ok_or(ConfigError::NoConfigDir)?;
+                let dir = proj.config_dir().to_path_buf();
+                let file = dir.join("config.toml");
+                (dir, file)
+            }
+        };
+        let history_file = dir.join("history.json");
         Ok(ConfigPaths {
             config_dir: dir,
             config_file: file,
+            history_file,
         })
     }
 }
@@ -241,6 +1034,44 @@ pub struct Logging {
     pub level: String,
     #[serde(default = "default_log_file")]
     pub file: String,
+    /// `text`, or `json` for one structured event per line in the log file.
+    #[serde(default = "default_log_format")]
+    pub format: String,
+    /// Level for log lines on stderr, `off` to only write the file.
+    #[serde(default = "default_console_level")]
+    pub console: String,
+    /// `never`, `hourly`, `daily`, or a size such as `10MiB`.
+    #[serde(default = "default_log_rotate")]
+    pub rotate: String,
+    /// Log files kept, including the current one.
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+    /// OTLP/HTTP collector (e.g. `http://localhost:4318`) that receives the sync
+    /// spans; empty to not export them.
+    #[serde(default)]
+    pub otlp_endpoint: String,
+}
+
+/// When the log file is started afresh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+    /// Once the file reaches this many bytes.
+    Size(u64),
+}
+
+impl Logging {
+    /// Parsed `rotate`, or `None` if it is not a valid value.
+    pub fn rotation(&self) -> Option<LogRotation> {
+        match self.rotate.trim().to_ascii_lowercase().as_str() {
+            "never" => Some(LogRotation::Never),
+            "hourly" => Some(LogRotation::Hourly),
+            "daily" => Some(LogRotation::Daily),
+            size => parse_size(size).map(LogRotation::Size),
+        }
+    }
 }
 
 fn default_level() -> String {
@@ -249,12 +1080,29 @@ fn default_level() -> String {
 fn default_log_file() -> String {
     "~/.config/u_crawler/u_crawler.log".into()
 }
+fn default_log_format() -> String {
+    "text".into()
+}
+fn default_console_level() -> String {
+    "off".into()
+}
+fn default_log_rotate() -> String {
+    "10MiB".into()
+}
+fn default_log_max_files() -> usize {
+    5
+}
 
 impl Default for Logging {
     fn default() -> Self {
         Self {
             level: default_level(),
             file: default_log_file(),
+            format: default_log_format(),
+            console: default_console_level(),
+            rotate: default_log_rotate(),
+            max_files: default_log_max_files(),
+            otlp_endpoint: String::new(),
         }
     }
 }
@@ -263,6 +1111,30 @@ fn default_tool_id() -> u64 {
     187
 }
 
+fn default_refresh_within_hours() -> u64 {
+    6
+}
+
+fn default_token_retries() -> u32 {
+    3
+}
+
+fn default_transcode_codec() -> String {
+    "libx265".to_string()
+}
+
+fn default_transcode_crf() -> u32 {
+    28
+}
+
+fn default_transcode_preset() -> String {
+    "medium".to_string()
+}
+
+fn default_transcode_audio_bitrate() -> String {
+    "96k".to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,4 +1155,137 @@ mod tests {
         assert_eq!(loaded.canvas.base_url, cfg.canvas.base_url);
         assert_eq!(loaded.zoom.enabled, cfg.zoom.enabled);
     }
+
+    #[test]
+    fn root_override_applies_to_roots_but_is_never_saved() {
+        let mut cfg = Config::default();
+        cfg.download_root = "/data/archive".into();
+        cfg.canvas.download_root = Some("/data/canvas".into());
+        cfg.zoom.download_root = Some("/data/zoom".into());
+        cfg.root_override = Some("/tmp/override".into());
+
+        assert_eq!(cfg.base_root(), PathBuf::from("/tmp/override"));
+        assert_eq!(cfg.canvas_root(), PathBuf::from("/tmp/override"));
+        assert_eq!(cfg.zoom_root(), None);
+
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&cfg).unwrap()).unwrap();
+        assert_eq!(saved.root_override, None);
+        assert_eq!(saved.download_root, "/data/archive");
+        assert_eq!(saved.canvas.download_root.as_deref(), Some("/data/canvas"));
+        assert_eq!(saved.zoom.download_root.as_deref(), Some("/data/zoom"));
+    }
+
+    #[test]
+    fn zoom_root_mirrors_course_folders() {
+        let mut cfg = Config::default();
+        cfg.download_root = "/data/archive".into();
+        assert_eq!(cfg.archive_roots(), [PathBuf::from("/data/archive")]);
+        assert_eq!(
+            cfg.zoom_course_dir(Path::new("/data/archive/Calculo")),
+            None
+        );
+
+        cfg.zoom.download_root = Some("/media/zoom".into());
+        assert_eq!(
+            cfg.archive_roots(),
+            [PathBuf::from("/data/archive"), PathBuf::from("/media/zoom")]
+        );
+        assert_eq!(
+            cfg.zoom_course_dir(Path::new("/data/archive/math/Calculo")),
+            Some(PathBuf::from("/media/zoom/math/Calculo"))
+        );
+    }
+
+    #[test]
+    fn course_tags_add_and_remove() {
+        let mut cfg = Config::default();
+        cfg.add_tags(12345, &["math".into(), "elective".into(), "math".into()]);
+        assert_eq!(cfg.tags_for(12345), ["elective", "math"]);
+        cfg.remove_tags(12345, &["elective".into(), "math".into()]);
+        assert!(cfg.tags_for(12345).is_empty());
+        assert!(cfg.course_tags.is_empty());
+    }
+
+    #[test]
+    fn parse_rate_accepts_decimal_and_binary_units() {
+        assert_eq!(parse_rate("5MiB/s"), Some(5 * 1024 * 1024));
+        assert_eq!(parse_rate("800 KB/s"), Some(800_000));
+        assert_eq!(parse_rate("1.5M"), Some(1_500_000));
+        assert_eq!(parse_rate("2048"), Some(2048));
+        assert_eq!(parse_rate("0"), None);
+        assert_eq!(parse_rate("fast"), None);
+        assert_eq!(parse_rate("5 parsecs"), None);
+    }
+
+    #[test]
+    fn log_rotation_accepts_periods_and_sizes() {
+        let mut logging = Logging::default();
+        assert_eq!(
+            logging.rotation(),
+            Some(LogRotation::Size(10 * 1024 * 1024))
+        );
+        logging.rotate = "Daily".into();
+        assert_eq!(logging.rotation(), Some(LogRotation::Daily));
+        logging.rotate = "weekly".into();
+        assert_eq!(logging.rotation(), None);
+    }
+
+    #[test]
+    fn browser_proxy_falls_back_to_http_proxy() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.browser_proxy(), None);
+        cfg.http.proxy = Some("http://proxy.campus.edu:3128".into());
+        assert_eq!(cfg.browser_proxy(), Some("http://proxy.campus.edu:3128"));
+        cfg.zoom.browser_proxy = Some("socks5://127.0.0.1:1080".into());
+        assert_eq!(cfg.browser_proxy(), Some("socks5://127.0.0.1:1080"));
+    }
+
+    #[test]
+    fn file_filters_check_extension_and_size() {
+        let configured = SyncSettings {
+            exclude_ext: vec![".ZIP".into()],
+            max_file_size: Some("100MB".into()),
+            ..Default::default()
+        };
+        let slides = Path::new("Attachments/Clase_1.pptx");
+        assert_eq!(configured.skip_reason(slides, Some(5_000_000)), None);
+        assert!(configured
+            .skip_reason(Path::new("dataset.zip"), Some(10))
+            .is_some());
+        assert!(configured.skip_reason(slides, Some(200_000_000)).is_some());
+        assert_eq!(configured.skip_reason(slides, None), None);
+
+        let cli = SyncSettings {
+            include_ext: vec!["pdf".into()],
+            ..Default::default()
+        };
+        let merged = configured.overridden_by(&cli);
+        assert!(merged.skip_reason(slides, Some(10)).is_some());
+        assert_eq!(merged.skip_reason(Path::new("guia.PDF"), Some(10)), None);
+        assert_eq!(merged.max_file_size.as_deref(), Some("100MB"));
+    }
+
+    #[test]
+    fn course_terms_match_by_name_or_id() {
+        let all = Courses::default();
+        assert_eq!(all.state, ["active"]);
+        assert!(all.term_matches(None));
+
+        let past = Courses {
+            terms: vec!["2024-1".into(), "88".into()],
+            ..Default::default()
+        };
+        assert!(past.term_matches(Some((10, "2024-1"))));
+        assert!(past.term_matches(Some((88, "Verano"))));
+        assert!(!past.term_matches(Some((11, "2024-2"))));
+        assert!(!past.term_matches(None));
+
+        let mut cfg = Config::default();
+        cfg.courses.state = vec!["finished".into()];
+        assert!(cfg
+            .postprocess_and_validate()
+            .unwrap_err()
+            .to_string()
+            .contains("courses.state"));
+    }
 }
@@ -0,0 +1,186 @@
+use crate::canvas::{CanvasClient, Course};
+use crate::config::{load_config_from_path, save_config_to_path, ConfigPaths};
+use crate::http::{build_http_client, HttpCtx};
+use crate::moodle::MoodleClient;
+use crate::provider::CourseProvider;
+use crate::tr;
+use console::{Key, Term};
+use std::collections::HashSet;
+use std::io;
+use tracing::info;
+
+/// Checkbox list state: which rows are checked and where the cursor is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub checked: Vec<bool>,
+    pub cursor: usize,
+}
+
+/// What a key press asks the picker to do next.
+#[derive(Debug, PartialEq)]
+enum Step {
+    Continue,
+    Save,
+    Cancel,
+}
+
+impl Selection {
+    pub fn new(checked: Vec<bool>) -> Self {
+        Self { checked, cursor: 0 }
+    }
+
+    fn apply(&mut self, key: &Key) -> Step {
+        let last = self.checked.len().saturating_sub(1);
+        match key {
+            Key::ArrowUp | Key::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('j') => self.cursor = (self.cursor + 1).min(last),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = last,
+            Key::Char(' ') => {
+                if let Some(c) = self.checked.get_mut(self.cursor) {
+                    *c = !*c;
+                }
+            }
+            // Check everything, or clear everything when all are already checked
+            Key::Char('a') => {
+                let all = self.checked.iter().all(|c| *c);
+                self.checked.iter_mut().for_each(|c| *c = !all);
+            }
+            Key::Enter => return Step::Save,
+            Key::Escape | Key::CtrlC | Key::Char('q') => return Step::Cancel,
+            _ => {}
+        }
+        Step::Continue
+    }
+}
+
+/// Let the user toggle `labels` in the terminal. Returns `None` when cancelled.
+pub fn pick(term: &Term, labels: &[String], selection: Selection) -> io::Result<Option<Selection>> {
+    let mut sel = selection;
+    let render = |sel: &Selection| -> io::Result<usize> {
+        term.write_line(&tr!("Space toggles, a toggles all, Enter saves, q cancels"))?;
+        for (i, label) in labels.iter().enumerate() {
+            let cursor = if i == sel.cursor { '>' } else { ' ' };
+            let mark = if sel.checked[i] { 'x' } else { ' ' };
+            term.write_line(&format!("{} [{}] {}", cursor, mark, label))?;
+        }
+        Ok(labels.len() + 1)
+    };
+
+    term.hide_cursor()?;
+    let result = (|| {
+        let mut drawn = render(&sel)?;
+        loop {
+            let step = sel.apply(&term.read_key()?);
+            term.clear_last_lines(drawn)?;
+            match step {
+                Step::Continue => drawn = render(&sel)?,
+                Step::Save => return Ok(Some(sel)),
+                Step::Cancel => return Ok(None),
+            }
+        }
+    })();
+    term.show_cursor()?;
+    result
+}
+
+/// Pick which active courses to sync and store the rest in `ignored_courses`.
+/// Ignored ids of courses that are no longer listed are kept.
+pub async fn run_pick(moodle: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err("courses pick needs an interactive terminal; edit ignored_courses in config.toml instead".into());
+    }
+    let paths = ConfigPaths::new()?;
+    let mut cfg = load_config_from_path(&paths.config_file).await?;
+    let http = HttpCtx::new(&cfg, build_http_client(&cfg));
+    let mut courses = if moodle {
+        MoodleClient::with_http(&cfg, http)
+            .await?
+            .list_courses()
+            .await?
+    } else {
+        CanvasClient::with_http(&cfg, http)
+            .await?
+            .list_courses()
+            .await?
+    };
+    if courses.is_empty() {
+        println!("{}", tr!("No active courses found."));
+        return Ok(());
+    }
+    courses.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let ignored = if moodle {
+        cfg.moodle.as_mut().map(|m| &mut m.ignored_courses)
+    } else {
+        Some(&mut cfg.canvas.ignored_courses)
+    }
+    .ok_or("moodle is not configured; run `auth moodle` first")?;
+    let labels: Vec<String> = courses.iter().map(label).collect();
+    let checked = courses
+        .iter()
+        .map(|c| !ignored.contains(&c.id.to_string()))
+        .collect();
+
+    let picked = tokio::task::spawn_blocking(move || pick(&term, &labels, Selection::new(checked)))
+        .await??;
+    let Some(picked) = picked else {
+        println!("{}", tr!("Selection cancelled; config unchanged."));
+        return Ok(());
+    };
+
+    let listed: HashSet<String> = courses.iter().map(|c| c.id.to_string()).collect();
+    ignored.retain(|id| !listed.contains(id));
+    for (course, checked) in courses.iter().zip(&picked.checked) {
+        if !checked {
+            ignored.push(course.id.to_string());
+        }
+    }
+    let synced = picked.checked.iter().filter(|c| **c).count();
+    let skipped = courses.len() - synced;
+
+    save_config_to_path(&cfg, &paths.config_file).await?;
+    info!(synced, skipped, path = %paths.config_file.display(), "saved course selection");
+    println!(
+        "{}",
+        tr!(
+            "Syncing {} course(s), ignoring {}; saved in {}",
+            synced,
+            skipped,
+            paths.config_file.display()
+        )
+    );
+    Ok(())
+}
+
+fn label(course: &Course) -> String {
+    match &course.course_code {
+        Some(code) => format!("{} ({}) [{}]", course.name, code, course.id),
+        None => format!("{} [{}]", course.name, course.id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_move_toggle_and_finish() {
+        let mut sel = Selection::new(vec![true, true, false]);
+        assert_eq!(sel.apply(&Key::ArrowUp), Step::Continue);
+        assert_eq!(sel.cursor, 0);
+        sel.apply(&Key::ArrowDown);
+        sel.apply(&Key::Char(' '));
+        assert_eq!(sel.checked, [true, false, false]);
+        sel.apply(&Key::End);
+        sel.apply(&Key::ArrowDown);
+        assert_eq!(sel.cursor, 2);
+        sel.apply(&Key::Char('a'));
+        assert_eq!(sel.checked, [true, true, true]);
+        sel.apply(&Key::Char('a'));
+        assert_eq!(sel.checked, [false, false, false]);
+        assert_eq!(sel.apply(&Key::Enter), Step::Save);
+        assert_eq!(sel.apply(&Key::Escape), Step::Cancel);
+    }
+}
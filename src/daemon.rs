@@ -0,0 +1,328 @@
+use crate::config::{Config, ConfigPaths};
+use crate::error::Error;
+use crate::fsutil::course_dirs;
+use crate::history::RunRecord;
+use crate::shutdown;
+use crate::state::State;
+use crate::syncer::{self, summary_line, SyncOptions};
+use crate::zoom::db::ZoomDb;
+use crate::zoom::models::SessionExpiry;
+use crate::{report, report_warning, tr};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+/// Totals of the syncs run since the daemon started.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub runs: u64,
+    pub failed_runs: u64,
+    pub files_downloaded: u64,
+    pub bytes_downloaded: u64,
+    /// Failed courses and items per error category.
+    pub failures: BTreeMap<String, u64>,
+    /// Unix time the last run finished, and the last one without failures.
+    pub last_run: Option<i64>,
+    pub last_success: Option<i64>,
+}
+
+impl Metrics {
+    fn record(&mut self, run: Result<&RunRecord, &Error>, now: i64) {
+        self.runs += 1;
+        self.last_run = Some(now);
+        match run {
+            Ok(run) => {
+                self.files_downloaded += run.files_downloaded as u64;
+                self.bytes_downloaded += run.bytes_downloaded;
+                for (kind, n) in &run.failures {
+                    *self.failures.entry(kind.clone()).or_default() += *n as u64;
+                }
+                if run.failed == 0 {
+                    self.last_success = Some(now);
+                } else {
+                    self.failed_runs += 1;
+                }
+            }
+            Err(e) => {
+                self.failed_runs += 1;
+                *self.failures.entry(e.kind().to_string()).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Sync every `interval` until the process is stopped, serving the outcome in the
+/// OpenMetrics text format at `http://<metrics_addr>/metrics` when an address is
+/// given. A failed run is logged and retried at the next interval. Ctrl-C or
+/// SIGTERM stops the daemon once the running sync has saved its progress.
+pub async fn run_daemon(interval: Duration, metrics_addr: Option<SocketAddr>) -> Result<(), Error> {
+    shutdown::listen();
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    if let Some(addr) = metrics_addr {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Error::Config(format!("unable to listen on {}: {}", addr, e)))?;
+        report!("{}", tr!("Serving metrics at http://{}/metrics", addr));
+        tokio::spawn(serve_metrics(listener, metrics.clone()));
+    }
+
+    loop {
+        report!("{}", tr!("Starting scheduled sync"));
+        let run = syncer::run_sync(SyncOptions::default()).await;
+        let now = chrono::Utc::now().timestamp();
+        match &run {
+            Ok(run) => report!("{}", summary_line(run)),
+            Err(e) => {
+                warn!(error = %e, kind = %e.kind(), "scheduled sync failed");
+                report_warning!("{}", tr!("Scheduled sync failed: {}", e));
+            }
+        }
+        metrics.lock().unwrap().record(run.as_ref(), now);
+        shutdown::check()?;
+        info!(
+            next_in_secs = interval.as_secs(),
+            "waiting for the next sync"
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = shutdown::wait() => {}
+        }
+        shutdown::check()?;
+    }
+}
+
+async fn serve_metrics(listener: TcpListener, metrics: Arc<Mutex<Metrics>>) {
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "metrics connection failed");
+                continue;
+            }
+        };
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let path = request.split_whitespace().nth(1).unwrap_or_default();
+        debug!(%peer, path, "metrics request");
+        let response = if path == "/metrics" {
+            let body = scrape(&metrics).await;
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+}
+
+/// Current metrics, with the per-course and Zoom ones read from disk.
+async fn scrape(metrics: &Mutex<Metrics>) -> String {
+    let courses = match Config::load_or_init() {
+        Ok(cfg) => last_course_syncs(&cfg.canvas_root()).await,
+        Err(_) => BTreeMap::new(),
+    };
+    // Only read the Zoom database once Zoom has been set up
+    let sessions = ConfigPaths::new()
+        .ok()
+        .filter(|paths| paths.config_dir.join("zoom_state.sqlite").exists())
+        .and_then(|paths| ZoomDb::new(&paths.config_dir).ok())
+        .and_then(|db| db.list_session_expiries().ok())
+        .unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    let metrics = metrics.lock().unwrap();
+    render_metrics(&metrics, &courses, &sessions, now)
+}
+
+/// Start of each course's last completed sync (Unix time), from its state file.
+async fn last_course_syncs(root: &Path) -> BTreeMap<u64, i64> {
+    let mut out = BTreeMap::new();
+    for dir in course_dirs(root, &[]).unwrap_or_default() {
+        let state = State::load(&dir.join("state.json")).await;
+        let synced = state
+            .last_synced
+            .as_deref()
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok());
+        if let (Some(course_id), Some(synced)) = (state.course_id, synced) {
+            out.insert(course_id, synced.timestamp());
+        }
+    }
+    out
+}
+
+/// The metrics in the OpenMetrics text format.
+fn render_metrics(
+    m: &Metrics,
+    courses: &BTreeMap<u64, i64>,
+    sessions: &[(u64, SessionExpiry)],
+    now: i64,
+) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, i64)>| {
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        for (labels, value) in samples {
+            let suffix = if kind == "counter" { "_total" } else { "" };
+            let _ = writeln!(out, "{}{}{} {}", name, suffix, labels, value);
+        }
+    };
+    family(
+        "u_crawler_sync_runs",
+        "counter",
+        "Syncs run by the daemon.",
+        vec![(String::new(), m.runs as i64)],
+    );
+    family(
+        "u_crawler_sync_failed_runs",
+        "counter",
+        "Syncs that failed or had failed items.",
+        vec![(String::new(), m.failed_runs as i64)],
+    );
+    family(
+        "u_crawler_files_downloaded",
+        "counter",
+        "Files downloaded.",
+        vec![(String::new(), m.files_downloaded as i64)],
+    );
+    family(
+        "u_crawler_downloaded_bytes",
+        "counter",
+        "Bytes downloaded.",
+        vec![(String::new(), m.bytes_downloaded as i64)],
+    );
+    family(
+        "u_crawler_errors",
+        "counter",
+        "Failed courses and items by error category.",
+        m.failures
+            .iter()
+            .map(|(kind, n)| (format!("{{category=\"{}\"}}", kind), *n as i64))
+            .collect(),
+    );
+    family(
+        "u_crawler_last_run_timestamp_seconds",
+        "gauge",
+        "When the last sync finished.",
+        m.last_run.map(|t| (String::new(), t)).into_iter().collect(),
+    );
+    family(
+        "u_crawler_last_success_timestamp_seconds",
+        "gauge",
+        "When the last sync without failures finished.",
+        m.last_success
+            .map(|t| (String::new(), t))
+            .into_iter()
+            .collect(),
+    );
+    family(
+        "u_crawler_course_last_success_timestamp_seconds",
+        "gauge",
+        "Start of each course's last completed sync.",
+        courses
+            .iter()
+            .map(|(id, t)| (format!("{{course_id=\"{}\"}}", id), *t))
+            .collect(),
+    );
+    family(
+        "u_crawler_zoom_session_valid",
+        "gauge",
+        "Whether the stored Zoom session of a course has not expired.",
+        sessions
+            .iter()
+            .map(|(id, s)| {
+                let valid = s.expires_at.is_none_or(|at| at > now);
+                (format!("{{course_id=\"{}\"}}", id), i64::from(valid))
+            })
+            .collect(),
+    );
+    family(
+        "u_crawler_zoom_session_expiry_timestamp_seconds",
+        "gauge",
+        "When the stored Zoom session cookies of a course expire.",
+        sessions
+            .iter()
+            .filter_map(|(id, s)| Some((format!("{{course_id=\"{}\"}}", id), s.expires_at?)))
+            .collect(),
+    );
+    out.push_str("# EOF\n");
+    out
+}
+
+/// A sync interval such as `90s`, `30m`, `6h` or `1d`.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().map_err(|_| tr!("invalid interval '{}'", s))?;
+    let secs = match unit {
+        "s" => n,
+        "m" | "" => n * 60,
+        "h" => n * 3600,
+        "d" => n * 86400,
+        _ => return Err(tr!("invalid interval '{}'; use e.g. 30m, 6h or 1d", s)),
+    };
+    if secs == 0 {
+        return Err("the interval must be longer than zero".into());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_openmetrics_with_labels() {
+        let mut m = Metrics::default();
+        let mut run = RunRecord::start();
+        run.files_downloaded = 3;
+        run.bytes_downloaded = 2048;
+        run.failed = 1;
+        run.failures.insert("network".into(), 1);
+        m.record(Ok(&run), 1_700_000_000);
+        let courses = BTreeMap::from([(101, 1_699_990_000)]);
+        let sessions = [(
+            101,
+            SessionExpiry {
+                captured_at: 0,
+                expires_at: Some(1_600_000_000),
+            },
+        )];
+        let text = render_metrics(&m, &courses, &sessions, 1_700_000_000);
+        for line in [
+            "# TYPE u_crawler_files_downloaded counter",
+            "u_crawler_files_downloaded_total 3",
+            "u_crawler_downloaded_bytes_total 2048",
+            "u_crawler_sync_failed_runs_total 1",
+            "u_crawler_errors_total{category=\"network\"} 1",
+            "u_crawler_last_run_timestamp_seconds 1700000000",
+            "u_crawler_course_last_success_timestamp_seconds{course_id=\"101\"} 1699990000",
+            "u_crawler_zoom_session_valid{course_id=\"101\"} 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line}");
+        }
+        // No successful run yet, so no sample
+        assert!(!text
+            .lines()
+            .any(|l| l.starts_with("u_crawler_last_success_timestamp_seconds")));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("6h"), Ok(Duration::from_secs(6 * 3600)));
+        assert_eq!(parse_interval("30"), Ok(Duration::from_secs(30 * 60)));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("soon").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use crate::progress::format_bytes;
+use crate::redact::redact_secrets;
+use crate::tr;
+use console::{measure_text_width, truncate_str, Term};
+use indicatif::{ProgressBar, ProgressDrawTarget, WeakProgressBar};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+const REDRAW_EVERY: Duration = Duration::from_millis(250);
+/// Lines kept for the log and error panels.
+const KEEP_LINES: usize = 200;
+const MAX_DOWNLOAD_ROWS: usize = 8;
+const MAX_ERROR_ROWS: usize = 5;
+
+/// Which panel a progress bar is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarKind {
+    /// Course and module counters.
+    Progress,
+    /// One file being downloaded.
+    Download,
+    /// Open-ended work such as loading a module list.
+    Spinner,
+}
+
+#[derive(Default)]
+struct Panels {
+    started: Option<Instant>,
+    bars: Vec<(BarKind, WeakProgressBar)>,
+    zoom_stage: Option<String>,
+    log: VecDeque<String>,
+    errors: VecDeque<String>,
+    error_count: usize,
+}
+
+impl Panels {
+    fn push_log(&mut self, line: String) {
+        self.log.push_back(line);
+        if self.log.len() > KEEP_LINES {
+            self.log.pop_front();
+        }
+    }
+}
+
+/// Full-screen view of a long run: progress counters, active downloads with their
+/// speed, the Zoom capture stage, recent warnings and errors, and the latest output.
+pub struct Dashboard {
+    running: AtomicBool,
+    panels: Mutex<Panels>,
+}
+
+static DASHBOARD: OnceLock<Dashboard> = OnceLock::new();
+
+/// The dashboard, while one is on screen.
+pub fn active() -> Option<&'static Dashboard> {
+    DASHBOARD
+        .get()
+        .filter(|d| d.running.load(Ordering::Relaxed))
+}
+
+/// Take over the terminal until the returned guard is dropped. Progress bars created
+/// meanwhile are drawn by the dashboard instead of indicatif. Without a terminal
+/// (e.g. output redirected to a file) the usual output is kept.
+pub fn start() -> DashboardGuard {
+    if !Term::stdout().is_term() {
+        return DashboardGuard { renderer: None };
+    }
+    let dashboard = DASHBOARD.get_or_init(|| Dashboard {
+        running: AtomicBool::new(false),
+        panels: Mutex::new(Panels::default()),
+    });
+    *dashboard.panels.lock().unwrap() = Panels {
+        started: Some(Instant::now()),
+        ..Panels::default()
+    };
+    dashboard.running.store(true, Ordering::Relaxed);
+    let renderer = thread::spawn(move || {
+        let term = Term::stdout();
+        while dashboard.running.load(Ordering::Relaxed) {
+            let (rows, cols) = term.size();
+            let frame = dashboard.render(rows as usize, cols as usize);
+            // Redraw from the top-left corner without scrolling the terminal
+            let _ = term.write_str(&format!("\x1b[H\x1b[J{}", frame));
+            thread::sleep(REDRAW_EVERY);
+        }
+    });
+    DashboardGuard {
+        renderer: Some(renderer),
+    }
+}
+
+/// Restores the terminal and prints the errors seen during the run when dropped.
+pub struct DashboardGuard {
+    renderer: Option<JoinHandle<()>>,
+}
+
+impl Drop for DashboardGuard {
+    fn drop(&mut self) {
+        let (Some(dashboard), Some(renderer)) = (DASHBOARD.get(), self.renderer.take()) else {
+            return;
+        };
+        dashboard.running.store(false, Ordering::Relaxed);
+        let _ = renderer.join();
+        let _ = Term::stdout().clear_screen();
+        let panels = dashboard.panels.lock().unwrap();
+        for line in &panels.log {
+            println!("{}", line);
+        }
+        if panels.error_count > 0 {
+            eprintln!(
+                "{}",
+                tr!(
+                    "{} warning(s) or error(s) during the run; the latest:",
+                    panels.error_count
+                )
+            );
+            for line in panels.errors.iter().rev().take(MAX_ERROR_ROWS).rev() {
+                eprintln!("  {}", line);
+            }
+        }
+    }
+}
+
+impl Dashboard {
+    /// Show `pb` in the panel for `kind` and stop indicatif from drawing it.
+    pub fn track(&self, kind: BarKind, pb: &ProgressBar) {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+        let mut panels = self.panels.lock().unwrap();
+        panels
+            .bars
+            .retain(|(_, weak)| weak.upgrade().is_some_and(|b| !b.is_finished()));
+        panels.bars.push((kind, pb.downgrade()));
+    }
+
+    /// Add `message` from `module` to the log panel.
+    pub fn log(&self, module: &str, message: &str) {
+        let mut panels = self.panels.lock().unwrap();
+        for line in message.lines().filter(|l| !l.trim().is_empty()) {
+            if module.starts_with("u_crawler::zoom") {
+                panels.zoom_stage = Some(line.trim().to_string());
+            }
+            panels.push_log(line.to_string());
+        }
+    }
+
+    fn error(&self, line: String) {
+        let mut panels = self.panels.lock().unwrap();
+        panels.error_count += 1;
+        panels.errors.push_back(line);
+        if panels.errors.len() > KEEP_LINES {
+            panels.errors.pop_front();
+        }
+    }
+
+    fn render(&self, rows: usize, cols: usize) -> String {
+        let panels = self.panels.lock().unwrap();
+        let bars: Vec<(BarKind, ProgressBar)> = panels
+            .bars
+            .iter()
+            .filter_map(|(kind, weak)| weak.upgrade().map(|b| (*kind, b)))
+            .filter(|(_, b)| !b.is_finished())
+            .collect();
+        let elapsed = panels
+            .started
+            .map(|s| s.elapsed().as_secs())
+            .unwrap_or_default();
+
+        let mut lines = vec![tr!(
+            "u_crawler sync  {}:{}:{} elapsed  {} warning(s)/error(s)",
+            format!("{:02}", elapsed / 3600),
+            format!("{:02}", elapsed / 60 % 60),
+            format!("{:02}", elapsed % 60),
+            panels.error_count
+        )];
+
+        lines.push(section("Progress", cols));
+        for (kind, bar) in &bars {
+            match kind {
+                BarKind::Progress => lines.push(progress_line(bar)),
+                BarKind::Spinner => lines.push(format!("  ... {}", bar.message())),
+                BarKind::Download => {}
+            }
+        }
+
+        lines.push(section("Zoom", cols));
+        lines.push(format!(
+            "  {}",
+            panels.zoom_stage.as_deref().unwrap_or("idle")
+        ));
+
+        let downloads: Vec<_> = bars
+            .iter()
+            .filter(|(kind, _)| *kind == BarKind::Download)
+            .collect();
+        lines.push(section(&tr!("Downloads ({})", downloads.len()), cols));
+        for (_, bar) in downloads.iter().take(MAX_DOWNLOAD_ROWS) {
+            lines.push(download_line(bar));
+        }
+        if downloads.len() > MAX_DOWNLOAD_ROWS {
+            lines.push(format!(
+                "  ... and {} more",
+                downloads.len() - MAX_DOWNLOAD_ROWS
+            ));
+        }
+
+        lines.push(section("Recent warnings and errors", cols));
+        let errors = panels.errors.len().min(MAX_ERROR_ROWS);
+        lines.extend(
+            panels
+                .errors
+                .iter()
+                .skip(panels.errors.len() - errors)
+                .map(|e| format!("  {}", e)),
+        );
+
+        // The log fills whatever height is left
+        lines.push(section("Log", cols));
+        let room = rows.saturating_sub(lines.len() + 1);
+        let skip = panels.log.len().saturating_sub(room);
+        lines.extend(panels.log.iter().skip(skip).map(|l| format!("  {}", l)));
+
+        let mut frame = String::new();
+        for line in lines.iter().take(rows.saturating_sub(1)) {
+            let _ = writeln!(frame, "{}", truncate_str(line, cols, "…"));
+        }
+        frame
+    }
+}
+
+fn section(title: &str, cols: usize) -> String {
+    let head = format!("── {} ", title);
+    let fill = cols.saturating_sub(measure_text_width(&head));
+    format!("{}{}", head, "─".repeat(fill))
+}
+
+fn progress_line(bar: &ProgressBar) -> String {
+    const WIDTH: u64 = 20;
+    let pos = bar.position();
+    match bar.length().filter(|len| *len > 0) {
+        Some(len) => {
+            let done = (pos.min(len) * WIDTH / len) as usize;
+            format!(
+                "  [{}{}] {}/{} {}",
+                "#".repeat(done),
+                "-".repeat(WIDTH as usize - done),
+                pos,
+                len,
+                bar.message()
+            )
+        }
+        None => format!("  {} {}", pos, bar.message()),
+    }
+}
+
+pub(crate) fn download_line(bar: &ProgressBar) -> String {
+    let done = format_bytes(bar.position());
+    let size = match bar.length() {
+        Some(len) => format!(
+            "{} / {} ({:.0}%)",
+            done,
+            format_bytes(len),
+            bar.position() as f64 * 100.0 / len.max(1) as f64
+        ),
+        None => done,
+    };
+    format!(
+        "  {}  {}  {}/s {}",
+        bar.prefix(),
+        size,
+        format_bytes(bar.per_sec() as u64),
+        bar.message()
+    )
+}
+
+/// Tracing layer feeding warnings and errors to the dashboard's error panel.
+pub struct ErrorPanelLayer;
+
+impl<S: Subscriber> Layer<S> for ErrorPanelLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(dashboard) = active() else {
+            return;
+        };
+        if *event.metadata().level() > Level::WARN {
+            return;
+        }
+        let mut fields = FieldText::default();
+        event.record(&mut fields);
+        let line = format!("{}{}", fields.message, fields.rest);
+        dashboard.error(format!(
+            "{} {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            redact_secrets(&line)
+        ));
+    }
+}
+
+/// An event's message followed by its other fields as `key=value`.
+#[derive(Default)]
+struct FieldText {
+    message: String,
+    rest: String,
+}
+
+impl Visit for FieldText {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            let _ = write!(self.rest, " {}={}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_panels_within_the_terminal() {
+        let dashboard = Dashboard {
+            running: AtomicBool::new(true),
+            panels: Mutex::new(Panels::default()),
+        };
+        let courses = ProgressBar::new(4);
+        courses.set_position(1);
+        courses.set_message("Syncing course 101");
+        dashboard.track(BarKind::Progress, &courses);
+        let file = ProgressBar::new(2048);
+        file.set_prefix("guia.pdf");
+        file.set_position(1024);
+        dashboard.track(BarKind::Download, &file);
+        dashboard.log("u_crawler::zoom::headless", "Capturing scid for course 101");
+        for i in 0..50 {
+            dashboard.log("u_crawler::syncer", &format!("line {i}"));
+        }
+        dashboard.error("12:00:00 download failed file_id=7".into());
+
+        let frame = dashboard.render(24, 60);
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(lines.len(), 23);
+        assert!(lines.iter().all(|l| measure_text_width(l) <= 60));
+        assert!(frame.contains("[#####---------------] 1/4 Syncing course 101"));
+        assert!(frame.contains("guia.pdf  1.00 KB / 2.00 KB (50%)"));
+        assert!(frame.contains("  Capturing scid for course 101\n"));
+        assert!(frame.contains("download failed file_id=7"));
+        assert!(frame.ends_with("line 49\n"));
+
+        courses.finish_and_clear();
+        assert!(!dashboard.render(24, 60).contains("Syncing course 101"));
+    }
+}
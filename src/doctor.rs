@@ -0,0 +1,340 @@
+use crate::canvas::{CanvasClient, CanvasError};
+use crate::config::{Config, ConfigPaths};
+use crate::ffmpeg::{ffmpeg_version, FfmpegError};
+use crate::fsutil::available_space;
+use crate::http::{build_http_client, HttpCtx};
+use crate::progress::format_bytes;
+use crate::tr;
+use crate::zoom::db::ZoomDb;
+use chromiumoxide::detection::{default_executable, DetectionOptions};
+use reqwest::header;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Clock skew tolerated before sign-ins and signed URLs start failing.
+const SKEW_WARN_SECS: i64 = 60;
+const SKEW_FAIL_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed or the feature is unused.
+    Skip,
+}
+
+/// Result of one environment check, with a remediation hint when it did not pass.
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Check the config, Canvas token, external tools, download root, Zoom database
+/// and clock, printing each result with a hint for the ones that did not pass.
+pub async fn run_doctor() -> Result<(), Box<dyn std::error::Error>> {
+    let checks = diagnose().await;
+    for check in &checks {
+        let label = match check.status {
+            CheckStatus::Pass => " ok ",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "skip",
+        };
+        println!("[{}] {:<14} {}", label, check.name, check.detail);
+        if let Some(hint) = &check.hint {
+            println!("{}", tr!("       hint: {}", hint));
+        }
+    }
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        // Reported with the network exit code (12), as documented for `doctor`
+        return Err(crate::error::Error::Network(tr!("{} check(s) failed", failed)).into());
+    }
+    Ok(())
+}
+
+/// Run every check without printing.
+pub async fn diagnose() -> Vec<Check> {
+    let mut checks = Vec::new();
+    let paths = ConfigPaths::new().ok();
+
+    let cfg = match Config::load_or_init() {
+        Ok(cfg) => {
+            let file = paths.as_ref().map(|p| p.config_file.display().to_string());
+            checks.push(Check::pass("config", file.unwrap_or_default()));
+            Some(cfg)
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "config",
+                e.to_string(),
+                tr!("fill in the reported fields in config.toml; `u_crawler auth canvas` sets the Canvas ones"),
+            ));
+            None
+        }
+    };
+
+    match &cfg {
+        Some(cfg) => {
+            let http = HttpCtx::new(cfg, build_http_client(cfg));
+            checks.push(check_canvas(cfg, http.clone()).await);
+            checks.push(check_clock(cfg, &http).await);
+            checks.push(check_ffmpeg(cfg).await);
+        }
+        None => {
+            checks.push(Check::skip("canvas token", tr!("config not loaded")));
+            checks.push(Check::skip("clock", tr!("config not loaded")));
+            checks.push(Check::skip("ffmpeg", tr!("config not loaded")));
+        }
+    }
+    checks.push(check_chromium().await);
+    checks.push(match &cfg {
+        Some(cfg) => check_download_root(&cfg.canvas_root()).await,
+        None => Check::skip("download root", tr!("config not loaded")),
+    });
+    if let Some(root) = cfg.as_ref().and_then(Config::zoom_root) {
+        checks.push(check_download_root(&root).await);
+    }
+    checks.push(match &paths {
+        Some(paths) => check_zoom_db(&paths.config_dir),
+        None => Check::skip("zoom db", tr!("no config directory")),
+    });
+    checks
+}
+
+async fn check_canvas(cfg: &Config, http: HttpCtx) -> Check {
+    const NAME: &str = "canvas token";
+    if cfg.canvas.base_url.trim().is_empty() {
+        return Check::fail(
+            NAME,
+            tr!("canvas.base_url is not set"),
+            tr!("run `u_crawler auth canvas --base-url URL --token TOKEN`"),
+        );
+    }
+    let canvas = match CanvasClient::with_http(cfg, http).await {
+        Ok(c) => c,
+        Err(CanvasError::MissingToken) => {
+            return Check::fail(
+                NAME,
+                tr!("no token configured, or token_cmd printed nothing"),
+                tr!("run `u_crawler auth canvas --token TOKEN` or check canvas.token_cmd"),
+            )
+        }
+        Err(CanvasError::TokenRejected(reason)) => {
+            return Check::fail(
+                NAME,
+                tr!("Canvas rejected the token ({})", reason),
+                tr!("create a new access token under Account > Settings in Canvas and run `u_crawler auth canvas --token TOKEN`"),
+            )
+        }
+        Err(e @ CanvasError::MasqueradeRejected(..)) => {
+            return Check::fail(
+                NAME,
+                e.to_string(),
+                tr!("remove canvas.as_user_id or use an account that may act as other users"),
+            )
+        }
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                e.to_string(),
+                tr!("check canvas.base_url in config.toml"),
+            )
+        }
+    };
+    match canvas.get_self().await {
+        Ok(user) => Check::pass(
+            NAME,
+            tr!(
+                "signed in as {} (user {})",
+                user.name.unwrap_or_default(),
+                user.id
+            ),
+        ),
+        Err(CanvasError::Status(401, _)) => Check::fail(
+            NAME,
+            tr!("Canvas rejected the token (401)"),
+            tr!("create a new access token under Account > Settings in Canvas and run `u_crawler auth canvas --token TOKEN`"),
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            e.to_string(),
+            tr!("check canvas.base_url, your network and http.proxy"),
+        ),
+    }
+}
+
+/// Compare the local clock with the `Date` header of the Canvas server.
+async fn check_clock(cfg: &Config, http: &HttpCtx) -> Check {
+    const NAME: &str = "clock";
+    if cfg.canvas.base_url.trim().is_empty() {
+        return Check::skip(NAME, tr!("no Canvas server to compare with"));
+    }
+    let resp = match http.send(http.client.head(&cfg.canvas.base_url)).await {
+        Ok(resp) => resp,
+        Err(e) => return Check::skip(NAME, tr!("could not reach Canvas: {}", e)),
+    };
+    let server = resp
+        .headers()
+        .get(header::DATE)
+        .and_then(|d| d.to_str().ok())
+        .and_then(|d| chrono::DateTime::parse_from_rfc2822(d).ok());
+    match server {
+        Some(server) => {
+            clock_check((chrono::Utc::now() - server.with_timezone(&chrono::Utc)).num_seconds())
+        }
+        None => Check::skip(NAME, tr!("Canvas sent no Date header")),
+    }
+}
+
+fn clock_check(skew_secs: i64) -> Check {
+    const NAME: &str = "clock";
+    let detail = tr!("{}s from the Canvas server", format!("{:+}", skew_secs));
+    let hint = tr!("enable automatic time sync (NTP); SSO sign-ins and signed download links depend on an accurate clock");
+    match skew_secs.abs() {
+        s if s > SKEW_FAIL_SECS => Check::fail(NAME, detail, hint),
+        s if s > SKEW_WARN_SECS => Check::warn(NAME, detail, hint),
+        _ => Check::pass(NAME, detail),
+    }
+}
+
+async fn check_ffmpeg(cfg: &Config) -> Check {
+    const NAME: &str = "ffmpeg";
+    let path = &cfg.zoom.ffmpeg_path;
+    let hint = tr!("install ffmpeg (see Prerequisites in the README) or set zoom.ffmpeg_path");
+    match ffmpeg_version(path).await {
+        Ok(version) => Check::pass(NAME, version),
+        // Only recordings need ffmpeg
+        Err(e) if !cfg.zoom.enabled => Check::warn(NAME, e.to_string(), hint),
+        Err(e @ FfmpegError::NotFound(_)) => Check::fail(NAME, e.to_string(), hint),
+        Err(e) => Check::fail(NAME, tr!("{} failed: {}", path, e), hint),
+    }
+}
+
+/// Find the browser chromiumoxide launches for Zoom, Panopto and SharePoint sign-ins.
+async fn check_chromium() -> Check {
+    const NAME: &str = "chromium";
+    let hint = tr!("install Google Chrome or Chromium, or set CHROME to its executable");
+    let exe = match default_executable(DetectionOptions::default()) {
+        Ok(exe) => exe,
+        Err(e) => return Check::fail(NAME, e, hint),
+    };
+    match Command::new(&exe).arg("--version").output().await {
+        Ok(out) if out.status.success() => Check::pass(
+            NAME,
+            format!(
+                "{} ({})",
+                String::from_utf8_lossy(&out.stdout).trim(),
+                exe.display()
+            ),
+        ),
+        Ok(out) => Check::fail(
+            NAME,
+            tr!(
+                "{} --version exited with {}: {}",
+                exe.display(),
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            ),
+            hint,
+        ),
+        Err(e) => Check::fail(NAME, format!("{}: {}", exe.display(), e), hint),
+    }
+}
+
+/// Create and remove a file in the download root.
+async fn check_download_root(root: &Path) -> Check {
+    const NAME: &str = "download root";
+    let probe = root.join(".u_crawler_doctor");
+    let result = async {
+        tokio::fs::create_dir_all(root).await?;
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await
+    };
+    match result.await {
+        Ok(()) => {
+            let free = available_space(root)
+                .map(|b| tr!(", {} free", format_bytes(b)))
+                .unwrap_or_default();
+            Check::pass(NAME, tr!("{} is writable{}", root.display(), free))
+        }
+        Err(e) => Check::fail(
+            NAME,
+            tr!("cannot write to {}: {}", root.display(), e),
+            tr!("point download_root at a writable folder or fix its permissions"),
+        ),
+    }
+}
+
+fn check_zoom_db(config_dir: &Path) -> Check {
+    const NAME: &str = "zoom db";
+    let hint = tr!("move zoom_state.sqlite aside and run `u_crawler zoom flow` to rebuild it");
+    if !config_dir.join("zoom_state.sqlite").exists() {
+        return Check::skip(NAME, tr!("not created yet"));
+    }
+    match ZoomDb::new(config_dir).and_then(|db| db.integrity_check()) {
+        Ok(problems) if problems.is_empty() => Check::pass(NAME, tr!("integrity check passed")),
+        Ok(problems) => Check::fail(NAME, problems.join("; "), hint),
+        Err(e) => Check::fail(NAME, e.to_string(), hint),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skew_thresholds() {
+        assert_eq!(clock_check(-5).status, CheckStatus::Pass);
+        assert_eq!(clock_check(90).status, CheckStatus::Warn);
+        let late = clock_check(-600);
+        assert_eq!(late.status, CheckStatus::Fail);
+        assert_eq!(late.detail, "-600s from the Canvas server");
+        assert!(late.hint.is_some());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::canvas::CanvasError;
+use crate::config::ConfigError;
+use crate::external_video::ExternalVideoError;
+use crate::ffmpeg::FfmpegError;
+use crate::moodle::MoodleError;
+use crate::zoom::api::ZoomApiError;
+use crate::zoom::rest::ZoomRestError;
+use crate::zoom::session::SessionError;
+use std::fmt;
+use std::io;
+use thiserror::Error;
+
+/// Errors of the sync engine and the Zoom flows, grouped by what the user can do
+/// about them. Each kind maps to a documented exit code.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Config(String),
+    /// Missing, expired or rejected credentials or sessions.
+    #[error("{0}")]
+    Auth(String),
+    /// The account may not access the resource (HTTP 403).
+    #[error("{0}")]
+    Permission(String),
+    /// Connection failures, timeouts and server errors.
+    #[error("{0}")]
+    Network(String),
+    /// Retries ran out while the server kept throttling.
+    #[error("{0}")]
+    RateLimit(String),
+    /// Local files, the download root and the state databases.
+    #[error("{0}")]
+    Filesystem(String),
+    /// Canvas, Moodle or Zoom answered with something unexpected.
+    #[error("{0}")]
+    Provider(String),
+    #[error("{0}")]
+    Ffmpeg(String),
+    /// Stopped by Ctrl-C or SIGTERM after saving progress.
+    #[error("{0}")]
+    Cancelled(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Category of an [`Error`], used for exit codes and the sync summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    Config,
+    Auth,
+    Permission,
+    Network,
+    RateLimit,
+    Filesystem,
+    Provider,
+    Ffmpeg,
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    /// Process exit code; see "Exit Codes" in the README.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Config => 10,
+            ErrorKind::Auth => 11,
+            ErrorKind::Network | ErrorKind::RateLimit => 12,
+            ErrorKind::Ffmpeg => 13,
+            ErrorKind::Permission => 14,
+            ErrorKind::Filesystem => 16,
+            ErrorKind::Provider => 17,
+            ErrorKind::Cancelled => 130,
+            ErrorKind::Other => 1,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Config => "config",
+            ErrorKind::Auth => "auth",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Network => "network",
+            ErrorKind::RateLimit => "rate-limit",
+            ErrorKind::Filesystem => "filesystem",
+            ErrorKind::Provider => "provider",
+            ErrorKind::Ffmpeg => "ffmpeg",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Config(_) => ErrorKind::Config,
+            Error::Auth(_) => ErrorKind::Auth,
+            Error::Permission(_) => ErrorKind::Permission,
+            Error::Network(_) => ErrorKind::Network,
+            Error::RateLimit(_) => ErrorKind::RateLimit,
+            Error::Filesystem(_) => ErrorKind::Filesystem,
+            Error::Provider(_) => ErrorKind::Provider,
+            Error::Ffmpeg(_) => ErrorKind::Ffmpeg,
+            Error::Cancelled(_) => ErrorKind::Cancelled,
+            Error::Other(_) => ErrorKind::Other,
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        self.kind().exit_code()
+    }
+
+    /// Classify an error whose type was erased, by trying the crate's error types.
+    /// The message is kept as is.
+    fn from_dyn(e: &(dyn std::error::Error + 'static)) -> Self {
+        Error::from_kind(kind_of(e), e.to_string())
+    }
+
+    /// An error of `kind` carrying only a message.
+    fn from_kind(kind: ErrorKind, message: String) -> Self {
+        match kind {
+            ErrorKind::Config => Error::Config(message),
+            ErrorKind::Auth => Error::Auth(message),
+            ErrorKind::Permission => Error::Permission(message),
+            ErrorKind::Network => Error::Network(message),
+            ErrorKind::RateLimit => Error::RateLimit(message),
+            ErrorKind::Filesystem => Error::Filesystem(message),
+            ErrorKind::Provider => Error::Provider(message),
+            ErrorKind::Ffmpeg => Error::Ffmpeg(message),
+            ErrorKind::Cancelled => Error::Cancelled(message),
+            ErrorKind::Other => Error::Other(message),
+        }
+    }
+}
+
+/// Category of an HTTP error status.
+pub fn status_kind(status: u16, message: &str) -> ErrorKind {
+    match status {
+        401 => ErrorKind::Auth,
+        // Canvas also reports an exhausted quota as 403
+        403 if message.to_ascii_lowercase().contains("rate limit") => ErrorKind::RateLimit,
+        403 => ErrorKind::Permission,
+        429 => ErrorKind::RateLimit,
+        500..=599 => ErrorKind::Network,
+        _ => ErrorKind::Provider,
+    }
+}
+
+fn reqwest_kind(e: &reqwest::Error) -> ErrorKind {
+    match e.status() {
+        Some(status) => status_kind(status.as_u16(), &e.to_string()),
+        None if e.is_decode() => ErrorKind::Provider,
+        None => ErrorKind::Network,
+    }
+}
+
+fn kind_of(e: &(dyn std::error::Error + 'static)) -> ErrorKind {
+    if let Some(e) = e.downcast_ref::<Error>() {
+        return e.kind();
+    }
+    if let Some(e) = e.downcast_ref::<CanvasError>() {
+        return match e {
+            CanvasError::Status(code, body) => status_kind(*code, body),
+            CanvasError::Http(e) => reqwest_kind(e),
+            CanvasError::Config(_) => ErrorKind::Config,
+            CanvasError::Io(_) => ErrorKind::Filesystem,
+            CanvasError::Decode(_) => ErrorKind::Provider,
+            CanvasError::MissingToken | CanvasError::TokenRejected(_) => ErrorKind::Auth,
+            CanvasError::MasqueradeRejected(..) => ErrorKind::Permission,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<MoodleError>() {
+        return match e {
+            MoodleError::Status(code, body) => status_kind(*code, body),
+            MoodleError::Http(e) => reqwest_kind(e),
+            MoodleError::Config(_) | MoodleError::BaseUrl(_) => ErrorKind::Config,
+            MoodleError::NotConfigured | MoodleError::MissingToken => ErrorKind::Auth,
+            MoodleError::Api { code, .. } if code.contains("token") => ErrorKind::Auth,
+            _ => ErrorKind::Provider,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<ZoomApiError>() {
+        return match e {
+            ZoomApiError::MissingState => ErrorKind::Auth,
+            ZoomApiError::Http(e) => reqwest_kind(e),
+            ZoomApiError::Db(inner) => inner.kind(),
+            _ => ErrorKind::Provider,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<ZoomRestError>() {
+        return match e {
+            ZoomRestError::Http(e) => reqwest_kind(e),
+            ZoomRestError::Status(code, body) => status_kind(*code, body),
+            ZoomRestError::BadSince(_) => ErrorKind::Other,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<ExternalVideoError>() {
+        return match e {
+            ExternalVideoError::Io(_) => ErrorKind::Filesystem,
+            ExternalVideoError::NotFound(_) => ErrorKind::Other,
+            _ => ErrorKind::Provider,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+        return reqwest_kind(e);
+    }
+    if e.is::<SessionError>() {
+        ErrorKind::Auth
+    } else if e.is::<ConfigError>() {
+        ErrorKind::Config
+    } else if e.is::<FfmpegError>() {
+        ErrorKind::Ffmpeg
+    } else if e.is::<io::Error>() || e.is::<rusqlite::Error>() {
+        ErrorKind::Filesystem
+    } else if e.is::<serde_json::Error>() || e.is::<chromiumoxide::error::CdpError>() {
+        ErrorKind::Provider
+    } else {
+        ErrorKind::Other
+    }
+}
+
+macro_rules! classified_from {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl From<$ty> for Error {
+                fn from(e: $ty) -> Self {
+                    Error::from_dyn(&e)
+                }
+            }
+        )*
+    };
+}
+
+classified_from!(
+    ConfigError,
+    FfmpegError,
+    CanvasError,
+    MoodleError,
+    ZoomApiError,
+    ZoomRestError,
+    SessionError,
+    ExternalVideoError,
+    reqwest::Error,
+    io::Error,
+    rusqlite::Error,
+    serde_json::Error,
+    chromiumoxide::error::CdpError,
+    regex::Error,
+    reqwest::header::InvalidHeaderValue,
+    tokio::task::JoinError,
+    tokio::sync::AcquireError,
+);
+
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Error::from_dyn(e.as_ref())
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Error::from_dyn(e.as_ref())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_classified_by_cause() {
+        let expired: Error = CanvasError::Status(401, "Invalid access token".into()).into();
+        assert_eq!(expired.kind(), ErrorKind::Auth);
+        assert_eq!(expired.exit_code(), 11);
+
+        let throttled: Error =
+            CanvasError::Status(403, "403 Forbidden (Rate Limit Exceeded)".into()).into();
+        assert_eq!(throttled.kind(), ErrorKind::RateLimit);
+        let forbidden: Error = CanvasError::Status(403, "unauthorized".into()).into();
+        assert_eq!(forbidden.exit_code(), 14);
+
+        let full: Box<dyn std::error::Error> =
+            Box::new(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+        assert_eq!(Error::from(full).kind(), ErrorKind::Filesystem);
+
+        let nested: Box<dyn std::error::Error> = Box::new(Error::Network("reset".into()));
+        assert_eq!(Error::from(nested).kind(), ErrorKind::Network);
+        assert_eq!(Error::from("no scid").exit_code(), 1);
+    }
+}
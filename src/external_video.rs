@@ -0,0 +1,136 @@
+use crate::config::ExternalVideoDownloader;
+use crate::state::{ItemState, State};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::process::Command;
+
+/// Output template under the target folder: title and video id, so re-uploads with
+/// the same title do not collide.
+const OUTPUT_TEMPLATE: &str = "%(title).150B [%(id)s].%(ext)s";
+
+#[derive(Debug, Error)]
+pub enum ExternalVideoError {
+    #[error("yt-dlp binary not found at {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("yt-dlp exited with status {code:?}: {message}")]
+    Process { code: Option<i32>, message: String },
+    #[error("yt-dlp did not report the downloaded file")]
+    NoOutput,
+}
+
+/// Download `url` into `dir` with yt-dlp, returning the file written.
+pub async fn download(
+    settings: &ExternalVideoDownloader,
+    url: &str,
+    dir: &Path,
+) -> Result<PathBuf, ExternalVideoError> {
+    tokio::fs::create_dir_all(dir).await?;
+    let output = match Command::new(&settings.path)
+        .args(ytdlp_args(settings, url, dir))
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(ExternalVideoError::NotFound(settings.path.clone()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.status.success() {
+        return Err(ExternalVideoError::Process {
+            code: output.status.code(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .rfind(|l| !l.is_empty())
+        .map(PathBuf::from)
+        .ok_or(ExternalVideoError::NoOutput)
+}
+
+/// Archive `url` into `dir` unless `state` already records `key` as done, and record
+/// the download. Returns the file written, or `None` when it was already archived.
+pub async fn archive(
+    settings: &ExternalVideoDownloader,
+    key: String,
+    url: &str,
+    dir: &Path,
+    state: &mut State,
+) -> Result<Option<PathBuf>, ExternalVideoError> {
+    if state.get(&key).is_some_and(|s| s.last_error.is_none()) {
+        return Ok(None);
+    }
+    let path = download(settings, url, dir).await?;
+    let size = tokio::fs::metadata(&path).await.ok().map(|m| m.len());
+    state.set(
+        key,
+        ItemState {
+            etag: None,
+            updated_at: None,
+            size,
+            content_hash: None,
+            last_error: None,
+            error_count: None,
+            last_modified: None,
+            uuid: None,
+            locked: None,
+        },
+    );
+    Ok(Some(path))
+}
+
+fn ytdlp_args(settings: &ExternalVideoDownloader, url: &str, dir: &Path) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "--no-playlist",
+        "--no-progress",
+        "--no-overwrites",
+        "--continue",
+        "--windows-filenames",
+        "--print",
+        "after_move:filepath",
+        "-o",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    args.push(dir.join(OUTPUT_TEMPLATE).to_string_lossy().into_owned());
+    if let Some(format) = &settings.format {
+        args.push("-f".into());
+        args.push(format.clone());
+    }
+    args.extend(settings.extra_args.iter().cloned());
+    args.push(url.to_string());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_a_controlled_output_template() {
+        let settings = ExternalVideoDownloader {
+            path: "yt-dlp".into(),
+            format: Some("bv*[height<=720]+ba/b".into()),
+            extra_args: vec!["--cookies-from-browser".into(), "firefox".into()],
+        };
+        let args = ytdlp_args(
+            &settings,
+            "https://vimeo.com/123/abc",
+            Path::new("/m/Media"),
+        );
+        let template = args.iter().position(|a| a == "-o").unwrap() + 1;
+        assert_eq!(
+            Path::new(&args[template]),
+            Path::new("/m/Media").join(OUTPUT_TEMPLATE)
+        );
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-f" && w[1] == "bv*[height<=720]+ba/b"));
+        assert_eq!(args.last().unwrap(), "https://vimeo.com/123/abc");
+    }
+}
@@ -1,5 +1,10 @@
+use crate::config::Transcode;
+use crate::tr;
+use indicatif::ProgressBar;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command;
 
 #[derive(Debug, Error)]
@@ -12,14 +17,32 @@ pub enum FfmpegError {
     Process { code: Option<i32>, message: String },
 }
 
+impl FfmpegError {
+    /// Whether the server refused the input URL (401/403), e.g. because its token expired.
+    pub fn is_auth_rejected(&self) -> bool {
+        match self {
+            Self::Process { message, .. } => {
+                message.contains("401 Unauthorized") || message.contains("403 Forbidden")
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Run `ffmpeg -version` to ensure the binary is callable.
 pub async fn ensure_ffmpeg_available(path: &str) -> Result<(), FfmpegError> {
+    ffmpeg_version(path).await.map(|_| ())
+}
+
+/// First line of `ffmpeg -version`, e.g. `ffmpeg version 6.1.1 Copyright ...`.
+pub async fn ffmpeg_version(path: &str) -> Result<String, FfmpegError> {
     let mut cmd = Command::new(path);
     cmd.arg("-version");
     match cmd.output().await {
         Ok(output) => {
             if output.status.success() {
-                Ok(())
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
             } else {
                 Err(FfmpegError::Process {
                     code: output.status.code(),
@@ -34,18 +57,129 @@ pub async fn ensure_ffmpeg_available(path: &str) -> Result<(), FfmpegError> {
     }
 }
 
+/// Latest values from an ffmpeg `-progress` report.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FfmpegProgress {
+    /// Bytes written to the output so far.
+    pub total_size: u64,
+    /// Media time written so far, in microseconds.
+    pub out_time_us: u64,
+    /// Processing speed as reported by ffmpeg (e.g. `3.2x`).
+    pub speed: Option<String>,
+}
+
+impl FfmpegProgress {
+    /// Apply one `key=value` line; returns `true` at the end of a report block.
+    pub fn apply(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        match key {
+            "total_size" => self.total_size = value.parse().unwrap_or(self.total_size),
+            "out_time_us" | "out_time_ms" => {
+                // Both keys carry microseconds; `N/A` appears before the first packet
+                self.out_time_us = value.parse().unwrap_or(self.out_time_us)
+            }
+            "speed" if value != "N/A" => self.speed = Some(value.trim().to_string()),
+            "progress" => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Media time as `HH:MM:SS`.
+    pub fn out_time(&self) -> String {
+        let secs = self.out_time_us / 1_000_000;
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+}
+
+/// Protocols an HLS playlist may reference; ffmpeg refuses nested protocols otherwise.
+const HLS_PROTOCOLS: &str = "file,http,https,tcp,tls,crypto";
+
+/// Whether `url` points at an HLS playlist rather than a single media file.
+pub fn is_hls(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.to_ascii_lowercase().ends_with(".m3u8")
+}
+
 /// Download the given media URL using ffmpeg with provided headers, writing to `dest` atomically.
+/// `readrate` maps to ffmpeg's `-readrate` (multiple of real-time input speed). When `progress`
+/// is given it follows ffmpeg's `-progress` reports: bytes written as position, media time and
+/// speed as message.
+///
+/// HLS playlists are remuxed segment by segment with the same headers on every request, and
+/// the result is rejected unless its duration matches the playlist's.
 pub async fn download_via_ffmpeg(
     path: &str,
+    readrate: Option<f32>,
     headers: &[(String, String)],
     input_url: &str,
     dest: &Path,
+    progress: Option<&ProgressBar>,
 ) -> Result<(), FfmpegError> {
     if let Some(parent) = dest.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
     let tmp = temp_path(dest);
+    let hls = is_hls(input_url);
+    let input_args = input_args(headers, hls);
+    let expected = if hls {
+        probe_input_duration(path, &input_args, input_url).await
+    } else {
+        None
+    };
+
+    let mut cmd = Command::new(path);
+    cmd.arg("-y") // overwrite partial outputs
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
+        .args(&input_args);
+    if let Some(rate) = readrate {
+        cmd.arg("-readrate").arg(rate.to_string());
+    }
+    cmd.arg("-i").arg(input_url).arg("-c").arg("copy");
+    if hls {
+        // MPEG-TS segments carry ADTS audio, which MP4 cannot hold as-is
+        cmd.arg("-bsf:a").arg("aac_adtstoasc");
+    }
+    cmd.arg("-map")
+        .arg("0")
+        .arg("-movflags")
+        .arg("+faststart")
+        .arg(tmp.as_os_str());
 
+    run_with_progress(cmd, path, &tmp, progress, "").await?;
+    if hls {
+        let actual = probe_duration(path, &tmp).await;
+        let complete = match (expected, actual) {
+            (Some(a), Some(b)) => durations_match(a, b),
+            (None, Some(_)) => true,
+            _ => false,
+        };
+        if !complete {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(FfmpegError::Process {
+                code: None,
+                message: tr!(
+                    "HLS download incomplete: duration {}s vs playlist {}s",
+                    format!("{:?}", actual),
+                    format!("{:?}", expected)
+                ),
+            });
+        }
+    }
+    tokio::fs::rename(&tmp, dest).await?;
+    Ok(())
+}
+
+/// Input options applying `headers` to the request, and for HLS to every playlist and
+/// segment request ffmpeg makes.
+fn input_args(headers: &[(String, String)], hls: bool) -> Vec<String> {
     let mut header_blob = String::new();
     for (name, value) in headers {
         header_blob.push_str(name);
@@ -53,47 +187,328 @@ pub async fn download_via_ffmpeg(
         header_blob.push_str(value);
         header_blob.push_str("\r\n");
     }
+    let mut args = vec!["-headers".to_string(), header_blob];
+    if hls {
+        args.extend(["-protocol_whitelist".to_string(), HLS_PROTOCOLS.to_string()]);
+    }
+    args
+}
+
+/// Run an ffmpeg command that writes `tmp`, feeding `-progress pipe:1` reports into
+/// `progress` (message prefixed with `label`). `tmp` is removed on failure.
+async fn run_with_progress(
+    mut cmd: Command,
+    path: &str,
+    tmp: &Path,
+    progress: Option<&ProgressBar>,
+    label: &str,
+) -> Result<(), FfmpegError> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(FfmpegError::NotFound(path.to_string()));
+        }
+        Err(e) => return Err(FfmpegError::Io(e)),
+    };
+
+    // Drain stderr concurrently so a chatty ffmpeg never blocks on a full pipe
+    let stderr = child.stderr.take();
+    let stderr_task = tokio::spawn(async move {
+        let mut message = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut message).await;
+        }
+        message
+    });
 
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut report = FfmpegProgress::default();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let (true, Some(pb)) = (report.apply(&line), progress) {
+                pb.set_position(report.total_size);
+                pb.set_message(match &report.speed {
+                    Some(speed) => format!("{}{} at {}", label, report.out_time(), speed),
+                    None => format!("{}{}", label, report.out_time()),
+                });
+            }
+        }
+    }
+
+    let status = match child.wait().await {
+        Ok(status) => status,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(tmp).await;
+            return Err(FfmpegError::Io(e));
+        }
+    };
+    let message = stderr_task.await.unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        let _ = tokio::fs::remove_file(tmp).await;
+        Err(FfmpegError::Process {
+            code: status.code(),
+            message,
+        })
+    }
+}
+
+/// Re-encode `file` in place with the given settings. The original is replaced only
+/// when the encoded copy's duration matches it; otherwise it is kept untouched.
+pub async fn transcode_in_place(
+    path: &str,
+    settings: &Transcode,
+    file: &Path,
+    progress: Option<&ProgressBar>,
+) -> Result<(), FfmpegError> {
+    let tmp = file.with_extension("transcode.part");
     let mut cmd = Command::new(path);
-    cmd.arg("-y") // overwrite partial outputs
+    cmd.arg("-y")
         .arg("-loglevel")
         .arg("error")
         .arg("-hide_banner")
-        .arg("-headers")
-        .arg(header_blob)
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
         .arg("-i")
-        .arg(input_url)
-        .arg("-c")
-        .arg("copy")
+        .arg(file.as_os_str())
+        .args(transcode_args(settings))
+        .arg("-f")
+        .arg("mp4")
+        .arg(tmp.as_os_str());
+    run_with_progress(cmd, path, &tmp, progress, "transcoding ").await?;
+
+    let original = probe_duration(path, file).await;
+    let encoded = probe_duration(path, &tmp).await;
+    match (original, encoded) {
+        (Some(a), Some(b)) if durations_match(a, b) => {
+            tokio::fs::rename(&tmp, file).await?;
+            Ok(())
+        }
+        _ => {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            Err(FfmpegError::Process {
+                code: None,
+                message: tr!(
+                    "transcode verification failed: duration {}s vs original {}s",
+                    format!("{:?}", encoded),
+                    format!("{:?}", original)
+                ),
+            })
+        }
+    }
+}
+
+/// A chapter marker in milliseconds from the start of the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub title: String,
+}
+
+/// Render global tags and chapters in ffmpeg's FFMETADATA1 format.
+pub fn ffmetadata(tags: &[(&str, &str)], chapters: &[Chapter]) -> String {
+    fn escape(value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        for ch in value.chars() {
+            if matches!(ch, '=' | ';' | '#' | '\\' | '\n') {
+                out.push('\\');
+            }
+            out.push(ch);
+        }
+        out
+    }
+
+    let mut out = String::from(";FFMETADATA1\n");
+    for (key, value) in tags {
+        out.push_str(&format!("{}={}\n", key, escape(value)));
+    }
+    for chapter in chapters {
+        out.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_ms,
+            chapter.end_ms,
+            escape(&chapter.title)
+        ));
+    }
+    out
+}
+
+/// Remux `file` in place with the given FFMETADATA1 text (tags and chapters),
+/// copying all streams without re-encoding.
+pub async fn embed_metadata(path: &str, file: &Path, metadata: &str) -> Result<(), FfmpegError> {
+    let meta_path = file.with_extension("ffmeta.part");
+    let tmp = file.with_extension("meta.part");
+    tokio::fs::write(&meta_path, metadata).await?;
+    let mut cmd = Command::new(path);
+    cmd.arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-i")
+        .arg(file.as_os_str())
+        .arg("-i")
+        .arg(meta_path.as_os_str())
         .arg("-map")
         .arg("0")
+        .arg("-map_metadata")
+        .arg("1")
+        .arg("-map_chapters")
+        .arg("1")
+        .arg("-c")
+        .arg("copy")
         .arg("-movflags")
         .arg("+faststart")
+        .arg("-f")
+        .arg("mp4")
         .arg(tmp.as_os_str());
+    let result = run_with_progress(cmd, path, &tmp, None, "").await;
+    let _ = tokio::fs::remove_file(&meta_path).await;
+    result?;
+    tokio::fs::rename(&tmp, file).await?;
+    Ok(())
+}
 
-    match cmd.output().await {
-        Ok(output) => {
-            if output.status.success() {
-                tokio::fs::rename(&tmp, dest).await?;
-                Ok(())
-            } else {
-                let _ = tokio::fs::remove_file(&tmp).await;
-                Err(FfmpegError::Process {
-                    code: output.status.code(),
-                    message: String::from_utf8_lossy(&output.stderr).into_owned(),
-                })
-            }
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            Err(FfmpegError::NotFound(path.to_string()))
-        }
-        Err(e) => {
-            let _ = tokio::fs::remove_file(&tmp).await;
-            Err(FfmpegError::Io(e))
-        }
+/// Encoder arguments for `settings`, between the input and the output file.
+pub fn transcode_args(settings: &Transcode) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "-map".into(),
+        "0:v".into(),
+        "-map".into(),
+        "0:a?".into(),
+        "-c:v".into(),
+        settings.codec.clone(),
+        "-crf".into(),
+        settings.crf.to_string(),
+        "-preset".into(),
+        settings.preset.clone(),
+    ];
+    if settings.codec == "libx265" {
+        // Lets QuickTime and iOS recognise HEVC in MP4
+        args.extend(["-tag:v".into(), "hvc1".into()]);
+    }
+    if let Some(height) = settings.max_height {
+        args.extend(["-vf".into(), format!("scale=-2:'min({},ih)'", height)]);
     }
+    args.extend([
+        "-c:a".into(),
+        "aac".into(),
+        "-b:a".into(),
+        settings.audio_bitrate.clone(),
+        "-movflags".into(),
+        "+faststart".into(),
+    ]);
+    args
+}
+
+/// Container duration in seconds, read from ffmpeg's input banner.
+async fn probe_duration(path: &str, file: &Path) -> Option<f64> {
+    probe_input_duration(path, &[], file.as_os_str()).await
+}
+
+/// Duration of any ffmpeg input (file or URL) opened with `input_args`.
+async fn probe_input_duration(
+    path: &str,
+    input_args: &[String],
+    input: impl AsRef<std::ffi::OsStr>,
+) -> Option<f64> {
+    let output = Command::new(path)
+        .arg("-hide_banner")
+        .args(input_args)
+        .arg("-i")
+        .arg(input)
+        .output()
+        .await
+        .ok()?;
+    parse_duration(&String::from_utf8_lossy(&output.stderr))
+}
+
+/// Durations agree within 1% or two seconds, whichever is larger.
+fn durations_match(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() <= (expected * 0.01).max(2.0)
+}
+
+/// Parse `Duration: HH:MM:SS.ss` from ffmpeg's stderr.
+fn parse_duration(stderr: &str) -> Option<f64> {
+    let rest = stderr.split("Duration: ").nth(1)?;
+    let stamp = rest.split(',').next()?.trim();
+    let mut parts = stamp.split(':');
+    let h: f64 = parts.next()?.parse().ok()?;
+    let m: f64 = parts.next()?.parse().ok()?;
+    let s: f64 = parts.next()?.parse().ok()?;
+    Some(h * 3600.0 + m * 60.0 + s)
 }
 
 fn temp_path(dest: &Path) -> PathBuf {
     dest.with_extension("mp4.part")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_progress_reports() {
+        let mut report = FfmpegProgress::default();
+        let block = "frame=10\nout_time_us=N/A\nspeed=N/A\nprogress=continue\n\
+                     total_size=1048576\nout_time_us=3723000000\nspeed=3.15x\nprogress=end\n";
+        let ends: Vec<bool> = block.lines().map(|l| report.apply(l)).collect();
+        assert_eq!(ends.iter().filter(|e| **e).count(), 2);
+        assert_eq!(report.total_size, 1_048_576);
+        assert_eq!(report.out_time(), "01:02:03");
+        assert_eq!(report.speed.as_deref(), Some("3.15x"));
+    }
+
+    #[test]
+    fn transcode_args_and_duration() {
+        let settings = Transcode {
+            max_height: Some(720),
+            ..Transcode::default()
+        };
+        let args = transcode_args(&settings).join(" ");
+        assert!(args.contains("-c:v libx265 -crf 28 -preset medium -tag:v hvc1"));
+        assert!(args.contains("-vf scale=-2:'min(720,ih)'"));
+        assert!(args.ends_with("-c:a aac -b:a 96k -movflags +faststart"));
+
+        let banner = "Input #0, mov,mp4 from 'a.mp4':\n  Duration: 01:02:03.50, start: 0.000000, bitrate: 812 kb/s";
+        assert_eq!(parse_duration(banner), Some(3723.5));
+        assert_eq!(parse_duration("N/A"), None);
+    }
+
+    #[test]
+    fn renders_ffmetadata_with_escaping() {
+        let text = ffmetadata(
+            &[("title", "Clase 1; intro"), ("comment", "id=abc")],
+            &[Chapter {
+                start_ms: 0,
+                end_ms: 600_000,
+                title: "Bienvenida #1".into(),
+            }],
+        );
+        assert_eq!(
+            text,
+            ";FFMETADATA1\ntitle=Clase 1\\; intro\ncomment=id\\=abc\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=600000\ntitle=Bienvenida \\#1\n"
+        );
+    }
+
+    #[test]
+    fn hls_inputs_whitelist_segment_protocols() {
+        assert!(is_hls(
+            "https://ssrweb.zoom.us/replay/x/playlist.m3u8?token=abc"
+        ));
+        assert!(is_hls("https://cdn.example/VOD.M3U8"));
+        assert!(!is_hls("https://ssrweb.zoom.us/replay/x.mp4?file=a.m3u8"));
+
+        let headers = vec![("Cookie".to_string(), "a=1".to_string())];
+        assert_eq!(input_args(&headers, false), ["-headers", "Cookie: a=1\r\n"]);
+        let args = input_args(&headers, true);
+        assert_eq!(args[2..], ["-protocol_whitelist", HLS_PROTOCOLS]);
+        assert!(durations_match(3600.0, 3570.0));
+        assert!(!durations_match(3600.0, 1800.0));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::tr;
+use regex::Regex;
+use reqwest::{header, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// Origin written in place of the institution's Canvas host.
+pub const PLACEHOLDER_ORIGIN: &str = "https://canvas.example.edu";
+
+/// JSON keys whose values identify a person; their strings are replaced.
+const REDACTED_KEYS: &[&str] = &[
+    "email",
+    "primary_email",
+    "login_id",
+    "sis_user_id",
+    "sis_login_id",
+    "integration_id",
+    "lti_user_id",
+    "avatar_url",
+    "avatar_image_url",
+    "user_name",
+    "sortable_name",
+];
+
+/// Where Canvas API responses are written to, or served from.
+#[derive(Debug, Clone)]
+pub enum FixtureMode {
+    /// Send requests and save each sanitized response under the directory.
+    Record(PathBuf),
+    /// Answer requests from the directory without contacting Canvas.
+    Replay(PathBuf),
+}
+
+static MODE: OnceLock<FixtureMode> = OnceLock::new();
+
+/// Set the mode for every Canvas client created afterwards; only the first call applies.
+pub fn set_mode(mode: FixtureMode) {
+    let _ = MODE.set(mode);
+}
+
+pub fn mode() -> Option<&'static FixtureMode> {
+    MODE.get()
+}
+
+/// One recorded response. The body is kept as text so malformed JSON replays as-is.
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    path: String,
+    status: u16,
+    #[serde(default)]
+    link: Option<String>,
+    body: String,
+}
+
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    }
+}
+
+/// File for a request: a readable slug of the path plus a hash of the full path and query.
+fn fixture_file(dir: &Path, method: &str, url: &Url) -> PathBuf {
+    let path = path_and_query(url);
+    let slug: String = url
+        .path()
+        .trim_start_matches("/api/v1/")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(80)
+        .collect();
+    let hash = hex::encode(Sha1::digest(format!("{method} {path}").as_bytes()));
+    dir.join(format!("{}-{}-{}.json", method, slug, &hash[..8]))
+}
+
+/// Replace the Canvas origin, credentials in URLs and personal fields.
+fn sanitize(text: &str, origin: &str) -> String {
+    let text = match serde_json::from_str::<Value>(text) {
+        Ok(mut v) => {
+            redact(&mut v);
+            serde_json::to_string_pretty(&v).unwrap_or_else(|_| text.to_string())
+        }
+        // Not JSON: likely the very body a decode failure was about, keep it verbatim
+        Err(_) => text.to_string(),
+    };
+    static SECRET_PARAM: OnceLock<Regex> = OnceLock::new();
+    let re = SECRET_PARAM.get_or_init(|| {
+        Regex::new(r"([?&](?:verifier|sf_verifier|access_token|token)=)[^&#\s\\]+").unwrap()
+    });
+    re.replace_all(&text, "${1}redacted")
+        .replace(origin, PLACEHOLDER_ORIGIN)
+}
+
+fn redact(v: &mut Value) {
+    match v {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&k.as_str()) && v.is_string() {
+                    *v = Value::String("redacted".into());
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn origin_of(base: &Url) -> String {
+    base.origin().ascii_serialization()
+}
+
+/// Save a sanitized copy of `resp` and hand back an equivalent response for the caller.
+pub async fn record(
+    dir: &Path,
+    base: &Url,
+    method: &str,
+    url: &Url,
+    resp: Response,
+) -> reqwest::Result<Response> {
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let bytes = resp.bytes().await?;
+    let origin = origin_of(base);
+    let fixture = Fixture {
+        method: method.to_string(),
+        path: path_and_query(url),
+        status: status.as_u16(),
+        link: headers
+            .get(header::LINK)
+            .and_then(|h| h.to_str().ok())
+            .map(|l| sanitize(l, &origin)),
+        body: sanitize(&String::from_utf8_lossy(&bytes), &origin),
+    };
+    let file = fixture_file(dir, method, url);
+    let write = async {
+        tokio::fs::create_dir_all(dir).await?;
+        let json = serde_json::to_vec_pretty(&fixture).map_err(std::io::Error::other)?;
+        tokio::fs::write(&file, json).await
+    };
+    match write.await {
+        Ok(()) => debug!(path = %file.display(), url = %url, "recorded canvas response"),
+        Err(e) => warn!(error = %e, path = %file.display(), "could not record canvas response"),
+    }
+
+    let mut rebuilt = http::Response::new(bytes);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    Ok(Response::from(rebuilt))
+}
+
+/// Answer a request from its recorded fixture, with the Canvas origin pointing at
+/// `base`. Requests that were never recorded get a 404.
+pub async fn replay(dir: &Path, base: &Url, method: &str, url: &Url) -> Response {
+    let file = fixture_file(dir, method, url);
+    let fixture = match tokio::fs::read(&file).await {
+        Ok(bytes) => serde_json::from_slice::<Fixture>(&bytes).ok(),
+        Err(_) => None,
+    };
+    let Some(fixture) = fixture else {
+        warn!(path = %file.display(), url = %url, "no recorded canvas response");
+        let mut resp = http::Response::new(tr!(
+            "no recorded fixture for {} {}",
+            method,
+            path_and_query(url)
+        ));
+        *resp.status_mut() = StatusCode::NOT_FOUND;
+        return Response::from(resp);
+    };
+    debug!(path = %file.display(), url = %url, "replaying canvas response");
+    let origin = origin_of(base);
+    let mut resp = http::Response::new(fixture.body.replace(PLACEHOLDER_ORIGIN, &origin));
+    *resp.status_mut() = StatusCode::from_u16(fixture.status).unwrap_or(StatusCode::OK);
+    if let Some(link) = fixture
+        .link
+        .and_then(|l| header::HeaderValue::from_str(&l.replace(PLACEHOLDER_ORIGIN, &origin)).ok())
+    {
+        resp.headers_mut().insert(header::LINK, link);
+    }
+    Response::from(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_strips_host_credentials_and_people() {
+        let body = r#"[{"id": 1, "display_name": "guia.pdf", "user": {"login_id": "jperez", "email": "j@unab.cl"},
+            "url": "https://unab.instructure.com/files/1/download?download_frd=1&verifier=abc123"}]"#;
+        let out = sanitize(body, "https://unab.instructure.com");
+        assert!(!out.contains("unab.instructure.com"));
+        assert!(!out.contains("jperez") && !out.contains("j@unab.cl") && !out.contains("abc123"));
+        assert!(out.contains("guia.pdf"));
+        assert!(out.contains(
+            "https://canvas.example.edu/files/1/download?download_frd=1&verifier=redacted"
+        ));
+        // Malformed bodies are kept for reproducing decode failures
+        assert_eq!(sanitize("{\"id\": \"x\",", "https://a"), "{\"id\": \"x\",");
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = Url::parse("https://unab.instructure.com").unwrap();
+        let url = base.join("/api/v1/courses?per_page=100").unwrap();
+        let mut original = http::Response::new(r#"[{"id": 7, "name": "Fisica"}]"#);
+        original.headers_mut().insert(
+            header::LINK,
+            header::HeaderValue::from_static(
+                "<https://unab.instructure.com/api/v1/courses?page=2&per_page=100>; rel=\"next\"",
+            ),
+        );
+        let passed = record(dir.path(), &base, "GET", &url, Response::from(original))
+            .await
+            .unwrap();
+        assert_eq!(
+            passed.text().await.unwrap(),
+            r#"[{"id": 7, "name": "Fisica"}]"#
+        );
+
+        let local = Url::parse("http://127.0.0.1:9").unwrap();
+        let replayed = replay(dir.path(), &local, "GET", &url).await;
+        assert_eq!(
+            replayed.headers()[header::LINK],
+            "<http://127.0.0.1:9/api/v1/courses?page=2&per_page=100>; rel=\"next\""
+        );
+        let courses: Value = serde_json::from_str(&replayed.text().await.unwrap()).unwrap();
+        assert_eq!(courses[0]["name"], "Fisica");
+
+        let missing = local.join("/api/v1/courses/8/modules").unwrap();
+        assert_eq!(
+            replay(dir.path(), &local, "GET", &missing).await.status(),
+            404
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write as _;
+
+/// Canvas object a page, assignment or quiz Markdown file was rendered from,
+/// written as YAML front matter so tools can map the file back to Canvas.
+#[derive(Debug, Clone, Default)]
+pub struct FrontMatter<'a> {
+    /// Id of the object; pages linked only by URL have none.
+    pub canvas_id: Option<u64>,
+    /// `page`, `assignment` or `quiz`.
+    pub kind: &'a str,
+    /// Course (or group workspace) the object belongs to.
+    pub course_id: u64,
+    pub course: &'a str,
+    pub module_id: u64,
+    pub module: &'a str,
+    pub updated_at: Option<&'a str>,
+    /// The object's page in Canvas.
+    pub url: Option<&'a str>,
+}
+
+impl FrontMatter<'_> {
+    /// The `---` delimited block, ending with `synced_at` when given.
+    pub fn render(&self, synced_at: Option<&str>) -> String {
+        let mut out = String::from("---\n");
+        if let Some(id) = self.canvas_id {
+            let _ = writeln!(out, "canvas_id: {}", id);
+        }
+        let _ = writeln!(out, "type: {}", self.kind);
+        let _ = writeln!(out, "course_id: {}", self.course_id);
+        let _ = writeln!(out, "course: {}", quote(self.course));
+        let _ = writeln!(out, "module_id: {}", self.module_id);
+        let _ = writeln!(out, "module: {}", quote(self.module));
+        if let Some(updated_at) = self.updated_at {
+            let _ = writeln!(out, "updated_at: {}", quote(updated_at));
+        }
+        if let Some(url) = self.url {
+            let _ = writeln!(out, "url: {}", quote(url));
+        }
+        if let Some(synced_at) = synced_at {
+            let _ = writeln!(out, "synced_at: {}", quote(synced_at));
+        }
+        out.push_str("---\n\n");
+        out
+    }
+}
+
+/// `s` as a double-quoted YAML scalar.
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_quoted_yaml() {
+        let meta = FrontMatter {
+            canvas_id: Some(42),
+            kind: "assignment",
+            course_id: 101,
+            course: "Cálculo \"I\"",
+            module_id: 7,
+            module: "Unidad 1",
+            updated_at: Some("2024-03-01T10:00:00Z"),
+            url: Some("https://canvas.example.edu/courses/101/assignments/42"),
+        };
+        assert_eq!(
+            meta.render(Some("2024-03-02T08:00:00Z")),
+            "---\n\
+             canvas_id: 42\n\
+             type: assignment\n\
+             course_id: 101\n\
+             course: \"Cálculo \\\"I\\\"\"\n\
+             module_id: 7\n\
+             module: \"Unidad 1\"\n\
+             updated_at: \"2024-03-01T10:00:00Z\"\n\
+             url: \"https://canvas.example.edu/courses/101/assignments/42\"\n\
+             synced_at: \"2024-03-02T08:00:00Z\"\n\
+             ---\n\n"
+        );
+        assert!(!meta.render(None).contains("synced_at"));
+    }
+}
@@ -1,6 +1,9 @@
+use crate::tr;
 use sanitize_filename::sanitize;
-use std::io;
-use std::path::Path;
+use sha1::{Digest, Sha1};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 fn ascii_skeleton(input: &str) -> String {
     // Transliterate to ASCII, then replace any non [A-Za-z0-9_] with '_'
@@ -32,6 +35,32 @@ fn ascii_skeleton(input: &str) -> String {
     collapsed.trim_matches('_').to_string()
 }
 
+/// Device names Windows reserves in every folder, with or without an extension.
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Append `_` to names Windows cannot create, so archives copied there keep working.
+fn avoid_reserved(stem: String) -> String {
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(&stem))
+    {
+        stem + "_"
+    } else {
+        stem
+    }
+}
+
+/// Windows drops trailing dots and spaces from names, so never end with one.
+fn truncate_name(mut name: String, max_len: usize) -> String {
+    name.truncate(max_len);
+    let end = name.trim_end_matches(['.', ' ']).len();
+    name.truncate(end);
+    name
+}
+
 pub fn sanitize_component<S: AsRef<str>>(s: S) -> String {
     let name = s.as_ref().trim();
     if name.is_empty() {
@@ -42,13 +71,8 @@ pub fn sanitize_component<S: AsRef<str>>(s: S) -> String {
     // Second pass: strict ASCII and restricted charset
     let s2 = ascii_skeleton(&s1);
     let final_s = if s2.is_empty() { "untitled".into() } else { s2 };
-    // Optional max length
     const MAX_LEN: usize = 120;
-    if final_s.len() > MAX_LEN {
-        final_s[..MAX_LEN].to_string()
-    } else {
-        final_s
-    }
+    avoid_reserved(truncate_name(final_s, MAX_LEN))
 }
 
 fn sanitize_stem(input: &str) -> String {
@@ -79,7 +103,7 @@ pub fn sanitize_filename_preserve_ext<S: AsRef<str>>(s: S) -> String {
         stem = "untitled".into();
     }
 
-    let mut out = stem;
+    let mut out = avoid_reserved(stem);
     if !ext_raw.is_empty() {
         // sanitize extension: transliterate and keep alphanumeric only
         let ext_ascii = deunicode::deunicode(ext_raw).to_lowercase();
@@ -94,14 +118,59 @@ pub fn sanitize_filename_preserve_ext<S: AsRef<str>>(s: S) -> String {
     }
 
     const MAX_LEN: usize = 180;
-    if out.len() > MAX_LEN {
-        out[..MAX_LEN].to_string()
+    truncate_name(out, MAX_LEN)
+}
+
+// Intentionally left out join_sanitized until needed to avoid dead code warnings.
+
+/// `path` in the `\\?\` form on Windows, which lifts the 260-character limit on
+/// full paths; relative paths are made absolute first. Other platforms get `path`
+/// back unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        match std::path::absolute(path) {
+            Ok(abs) => PathBuf::from(verbatim(&abs.to_string_lossy())),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        path.to_path_buf()
+    }
+}
+
+/// The verbatim form of an absolute Windows path, e.g. `\\?\C:\Users` or
+/// `\\?\UNC\server\share` for a network share.
+#[cfg(any(windows, test))]
+fn verbatim(abs: &str) -> String {
+    let abs = abs.replace('/', "\\");
+    if abs.starts_with(r"\\?\") {
+        abs
+    } else if let Some(share) = abs.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", share)
     } else {
-        out
+        format!(r"\\?\{}", abs)
     }
 }
 
-// Intentionally left out join_sanitized until needed to avoid dead code warnings.
+/// A command that runs `script` through the platform shell (`sh -lc` on Unix,
+/// `cmd /C` on Windows), so pipelines such as `pass show canvas | head -1` work.
+pub fn shell_command(script: &str) -> tokio::process::Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = tokio::process::Command::new("cmd");
+        // cmd.exe does its own parsing, so pass the script as written
+        cmd.arg("/C").raw_arg(script);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-lc").arg(script);
+        cmd
+    }
+}
 
 pub async fn ensure_dir(path: &Path) -> io::Result<()> {
     tokio::fs::create_dir_all(path).await
@@ -128,3 +197,190 @@ pub async fn atomic_rename(src: &Path, dest: &Path) -> io::Result<()> {
     }
     tokio::fs::rename(src, dest).await
 }
+
+/// Size and hex SHA-1 of a file, streamed in chunks.
+pub fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Parse a Canvas (RFC 3339) or Zoom (`2024-03-01 10:00:00`, `Mar 1, 2024 10:00 AM`)
+/// timestamp. Zoom times without an offset are taken as local time.
+pub fn parse_timestamp(s: &str) -> Option<SystemTime> {
+    use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+    let s = s.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Some(t.into());
+    }
+    const NAIVE: [&str; 4] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%b %d, %Y %I:%M %p",
+    ];
+    NAIVE.iter().find_map(|fmt| {
+        let naive = NaiveDateTime::parse_from_str(s, fmt).ok()?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(SystemTime::from)
+    })
+}
+
+/// Set the modification time of `path`.
+pub fn set_mtime(path: &Path, time: SystemTime) -> io::Result<()> {
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(time)
+}
+
+/// Set the modification time of `path` from a remote timestamp, if it parses.
+/// Failures are logged only; a wrong mtime never fails a download.
+pub fn apply_remote_mtime(path: &Path, timestamp: Option<&str>) {
+    let Some(time) = timestamp.and_then(parse_timestamp) else {
+        return;
+    };
+    if let Err(e) = set_mtime(path, time) {
+        tracing::warn!(path = %path.display(), error = %e, "unable to set file mtime");
+    }
+}
+
+/// Free bytes on the filesystem holding `path`, or its nearest existing ancestor
+/// when `path` has not been created yet.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let mut probe = path;
+    while !probe.exists() {
+        match probe.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => probe = parent,
+            _ => break,
+        }
+    }
+    fs2::available_space(probe)
+}
+
+/// Fail with `StorageFull` when `needed` bytes would leave less than `reserve`
+/// free under `path`.
+pub fn check_free_space(path: &Path, needed: u64, reserve: u64) -> io::Result<()> {
+    let available = available_space(path)?;
+    if needed.saturating_add(reserve) > available {
+        return Err(io::Error::new(
+            io::ErrorKind::StorageFull,
+            tr!(
+                "not enough disk space on {}: need {} plus {} reserve, {} available",
+                path.display(),
+                crate::progress::format_bytes(needed),
+                crate::progress::format_bytes(reserve),
+                crate::progress::format_bytes(available)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// List course folders under `root`, descending one level into tag group folders
+/// (a folder without `state.json` whose children carry one). Names in `skip` are ignored,
+/// and so are symlinks, which alias a renamed course's folder.
+pub fn course_dirs(root: &Path, skip: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if !path.is_dir() || path.is_symlink() {
+            continue;
+        }
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        // Hidden folders, such as the content store, are never courses
+        if skip.contains(&name) || name.starts_with('.') {
+            continue;
+        }
+        if path.join("state.json").exists() {
+            out.push(path);
+            continue;
+        }
+        let mut nested = Vec::new();
+        for child in std::fs::read_dir(&path)? {
+            let child = child?.path();
+            if child.is_dir() && !child.is_symlink() && child.join("state.json").exists() {
+                nested.push(child);
+            }
+        }
+        if nested.is_empty() {
+            out.push(path);
+        } else {
+            out.extend(nested);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_valid_on_windows() {
+        assert_eq!(sanitize_component("con"), "con_");
+        assert_eq!(sanitize_component("Lpt1"), "Lpt1_");
+        assert_eq!(sanitize_component("Console"), "Console");
+        assert_eq!(sanitize_filename_preserve_ext("AUX.pdf"), "AUX_.pdf");
+        assert_eq!(sanitize_filename_preserve_ext("nul"), "nul_");
+        let long = format!("{}.", "a".repeat(179));
+        assert_eq!(
+            sanitize_filename_preserve_ext(format!("{}x", long)),
+            "a".repeat(179)
+        );
+    }
+
+    #[test]
+    fn long_paths_use_the_verbatim_prefix() {
+        assert_eq!(verbatim(r"C:\Users\ana/Canvas"), r"\\?\C:\Users\ana\Canvas");
+        assert_eq!(verbatim(r"\\nas\share\Canvas"), r"\\?\UNC\nas\share\Canvas");
+        assert_eq!(verbatim(r"\\?\D:\Canvas"), r"\\?\D:\Canvas");
+    }
+
+    #[test]
+    fn free_space_check_uses_nearest_existing_ancestor() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not").join("yet").join("created");
+        assert!(available_space(&missing).unwrap() > 0);
+        assert!(check_free_space(&missing, 1, 0).is_ok());
+        let err = check_free_space(&missing, u64::MAX / 2, u64::MAX / 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+    }
+
+    #[test]
+    fn remote_timestamps_become_mtimes() {
+        let canvas = parse_timestamp("2024-03-01T10:00:00Z").unwrap();
+        assert_eq!(
+            canvas
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            1_709_287_200
+        );
+        assert!(parse_timestamp("2024-03-01 10:00:00").is_some());
+        assert!(parse_timestamp("Mar 1, 2024 10:00 AM").is_some());
+        assert!(parse_timestamp("yesterday").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("slides.pdf");
+        std::fs::write(&file, b"x").unwrap();
+        apply_remote_mtime(&file, Some("2024-03-01T10:00:00Z"));
+        assert_eq!(
+            std::fs::metadata(&file).unwrap().modified().unwrap(),
+            canvas
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::history::RunRecord;
+use std::path::Path;
+use thiserror::Error;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("git not found; install it or set storage.git = false")]
+    NotFound,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("git {args} exited with status {code:?}: {message}")]
+    Process {
+        args: String,
+        code: Option<i32>,
+        message: String,
+    },
+}
+
+/// `.gitignore` written when the repository is created: only text is versioned,
+/// so PDFs, slides and recordings stay out of the history. Edits are kept.
+const GITIGNORE: &str = "\
+# Written by u_crawler (storage.git): only text content is versioned
+*
+!*/
+!.gitignore
+!*.md
+!*.html
+!*.csv
+!*.ics
+!*.vtt
+!*.txt
+!*.json
+state.json
+.*/
+";
+
+/// Commit the changes a sync made to the archive at `root`, creating the
+/// repository first when needed. Returns whether a commit was made.
+pub async fn commit_sync(root: &Path, run: &RunRecord) -> Result<bool, GitError> {
+    init(root).await?;
+    git(root, &["add", "--all"]).await?;
+    if git(root, &["status", "--porcelain"])
+        .await?
+        .trim()
+        .is_empty()
+    {
+        debug!(root = %root.display(), "archive unchanged; nothing to commit");
+        return Ok(false);
+    }
+    git(
+        root,
+        &["commit", "--quiet", "--message", &commit_message(run)],
+    )
+    .await?;
+    info!(root = %root.display(), "committed archive changes");
+    Ok(true)
+}
+
+/// Make `root` a git repository with the text-only `.gitignore`, unless it is one.
+async fn init(root: &Path) -> Result<(), GitError> {
+    if root.join(".git").exists() {
+        return Ok(());
+    }
+    git(root, &["init", "--quiet"]).await?;
+    let ignore = root.join(".gitignore");
+    if !ignore.exists() {
+        tokio::fs::write(&ignore, GITIGNORE).await?;
+    }
+    // Commits must not fail on machines without a git identity
+    if git(root, &["config", "user.email"]).await.is_err() {
+        git(root, &["config", "user.name", "u_crawler"]).await?;
+        git(root, &["config", "user.email", "u_crawler@localhost"]).await?;
+    }
+    info!(root = %root.display(), "initialized archive repository");
+    Ok(())
+}
+
+/// Subject and body of the commit recording `run`.
+fn commit_message(run: &RunRecord) -> String {
+    let when = run.finished_at.as_deref().unwrap_or(&run.started_at);
+    let mut message = format!(
+        "Sync {}\n\n{} course(s) synced, {} file(s) downloaded, {} item(s) failed\n",
+        when, run.courses, run.files_downloaded, run.failed
+    );
+    for item in &run.failed_items {
+        message.push_str(&format!("\n- {}", item));
+    }
+    message
+}
+
+/// Output of `git <args>` run in `root`.
+async fn git(root: &Path, args: &[&str]) -> Result<String, GitError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => GitError::NotFound,
+            _ => GitError::Io(e),
+        })?;
+    if !output.status.success() {
+        return Err(GitError::Process {
+            args: args.join(" "),
+            code: output.status.code(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn commits_text_content_only_when_it_changed() {
+        let root = tempfile::tempdir().unwrap();
+        let module = root
+            .path()
+            .join("Calculo_I")
+            .join("Modules")
+            .join("1_Unidad");
+        std::fs::create_dir_all(&module).unwrap();
+        std::fs::write(module.join("01-Intro.md"), "# Intro\n").unwrap();
+        std::fs::write(module.join("slides.pdf"), b"%PDF-1.7").unwrap();
+        std::fs::write(root.path().join("Calculo_I").join("state.json"), "{}").unwrap();
+        let run = RunRecord::start();
+
+        assert!(commit_sync(root.path(), &run).await.unwrap());
+        let files = git(root.path(), &["ls-files"]).await.unwrap();
+        assert_eq!(
+            files.lines().collect::<Vec<_>>(),
+            [".gitignore", "Calculo_I/Modules/1_Unidad/01-Intro.md"]
+        );
+        assert!(!commit_sync(root.path(), &run).await.unwrap());
+
+        std::fs::write(module.join("01-Intro.md"), "# Intro\n\nUpdated\n").unwrap();
+        assert!(commit_sync(root.path(), &run).await.unwrap());
+        let log = git(root.path(), &["log", "--format=%s"]).await.unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.starts_with("Sync "));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::canvas::{
+    Assignment, CanvasClient, CanvasError, Module, ModuleItem, RubricCriterion, RubricRating,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+/// Courses aliased into one query; keeps each request under Canvas' complexity limit.
+const COURSES_PER_QUERY: usize = 20;
+
+/// Fields read for every course, mirroring what the REST module and assignment
+/// lists return.
+const COURSE_FIELDS: &str = "\
+modulesConnection(first: 100) { pageInfo { hasNextPage } nodes { _id name moduleItems { _id url \
+content { __typename \
+... on Assignment { _id name } \
+... on Discussion { _id title } \
+... on File { _id displayName } \
+... on Page { _id title url } \
+... on Quiz { _id title } \
+... on ExternalUrl { title url } \
+... on ModuleExternalTool { title url } \
+... on SubHeader { title } } } } } \
+assignmentsConnection(first: 100) { pageInfo { hasNextPage } nodes { _id name description \
+updatedAt dueAt htmlUrl rubric { criteria { description longDescription points \
+ratings { description longDescription points } } } } }";
+
+/// Modules and assignments of a course fetched in bulk. Each list is handed out
+/// once; later calls go back to the REST API for fresh data.
+#[derive(Debug, Default)]
+pub struct CourseMetadata {
+    pub modules: Option<Vec<Module>>,
+    pub assignments: Option<Vec<Assignment>>,
+}
+
+impl CanvasClient {
+    /// Modules (with items) and assignments of `course_ids` through `/api/graphql`,
+    /// one request per 20 courses instead of several paginated REST calls each.
+    /// Lists longer than one page are left out so the REST endpoints page them.
+    pub async fn fetch_course_metadata(
+        &self,
+        course_ids: &[u64],
+    ) -> Result<HashMap<u64, CourseMetadata>, CanvasError> {
+        let url = self.base.join("/api/graphql").unwrap();
+        let mut out = HashMap::new();
+        for chunk in course_ids.chunks(COURSES_PER_QUERY) {
+            debug!(method = "POST", url = %url, courses = chunk.len(), "canvas request (graphql)");
+            let rb = self
+                .http
+                .client
+                .post(url.clone())
+                .json(&json!({ "query": course_query(chunk) }));
+            let resp: GqlResponse = self.decode_one(rb, "graphql").await?;
+            if let Some(e) = resp.errors.first() {
+                if resp.data.is_none() {
+                    return Err(CanvasError::Decode(format!("graphql: {}", e.message)));
+                }
+                warn!(error = %e.message, "graphql query returned partial data");
+            }
+            for (alias, course) in resp.data.unwrap_or_default() {
+                let (Some(id), Some(course)) = (alias.strip_prefix('c'), course) else {
+                    continue;
+                };
+                if let Ok(id) = id.parse() {
+                    out.insert(id, course_metadata(course));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Fetch and keep the metadata of `course_ids`; on failure the REST API is used.
+    pub(crate) async fn prefetch_course_metadata(&self, course_ids: &[u64]) {
+        match self.fetch_course_metadata(course_ids).await {
+            Ok(metadata) => self.prefetched.lock().unwrap().extend(metadata),
+            Err(e) => {
+                warn!(error = %e, "graphql metadata fetch failed; using the REST API");
+            }
+        }
+    }
+
+    pub(crate) fn prefetched_modules(&self, course_id: u64) -> Option<Vec<Module>> {
+        let mut prefetched = self.prefetched.lock().unwrap();
+        prefetched.get_mut(&course_id)?.modules.take()
+    }
+
+    pub(crate) fn prefetched_assignments(&self, course_id: u64) -> Option<Vec<Assignment>> {
+        let mut prefetched = self.prefetched.lock().unwrap();
+        prefetched.get_mut(&course_id)?.assignments.take()
+    }
+}
+
+/// One query with each course aliased as `c<id>`.
+fn course_query(course_ids: &[u64]) -> String {
+    let courses: Vec<String> = course_ids
+        .iter()
+        .map(|id| format!("c{id}: course(id: \"{id}\") {{ {COURSE_FIELDS} }}"))
+        .collect();
+    format!("query CourseMetadata {{ {} }}", courses.join(" "))
+}
+
+fn course_metadata(course: GqlCourse) -> CourseMetadata {
+    let modules = course.modules_connection.and_then(|c| {
+        (!c.page_info.has_next_page).then(|| {
+            c.nodes
+                .into_iter()
+                .filter_map(GqlModule::into_module)
+                .collect()
+        })
+    });
+    let assignments = course.assignments_connection.and_then(|c| {
+        (!c.page_info.has_next_page).then(|| {
+            c.nodes
+                .into_iter()
+                .filter_map(GqlAssignment::into_assignment)
+                .collect()
+        })
+    });
+    CourseMetadata {
+        modules,
+        assignments,
+    }
+}
+
+#[derive(Deserialize)]
+struct GqlResponse {
+    data: Option<HashMap<String, Option<GqlCourse>>>,
+    #[serde(default)]
+    errors: Vec<GqlError>,
+}
+
+#[derive(Deserialize)]
+struct GqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlCourse {
+    modules_connection: Option<Connection<GqlModule>>,
+    assignments_connection: Option<Connection<GqlAssignment>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    page_info: PageInfo,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlModule {
+    #[serde(rename = "_id")]
+    id: String,
+    name: Option<String>,
+    #[serde(default)]
+    module_items: Vec<GqlModuleItem>,
+}
+
+impl GqlModule {
+    fn into_module(self) -> Option<Module> {
+        Some(Module {
+            id: self.id.parse().ok()?,
+            name: self.name.unwrap_or_default(),
+            items: self
+                .module_items
+                .into_iter()
+                .filter_map(GqlModuleItem::into_item)
+                .collect(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct GqlModuleItem {
+    #[serde(rename = "_id")]
+    id: String,
+    url: Option<String>,
+    content: Option<GqlContent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlContent {
+    #[serde(rename = "__typename")]
+    typename: String,
+    #[serde(rename = "_id")]
+    id: Option<String>,
+    title: Option<String>,
+    name: Option<String>,
+    display_name: Option<String>,
+    url: Option<String>,
+}
+
+impl GqlModuleItem {
+    /// The item as the REST API lists it (`type`, `page_url`, `content_id`, ...).
+    fn into_item(self) -> Option<ModuleItem> {
+        let content = self.content?;
+        let kind = match content.typename.as_str() {
+            "ModuleExternalTool" => "ExternalTool",
+            other => other,
+        };
+        let page_url = (kind == "Page")
+            .then(|| content.url.as_deref().map(page_slug))
+            .flatten();
+        let external_url = matches!(kind, "ExternalUrl" | "ExternalTool")
+            .then(|| content.url.clone())
+            .flatten();
+        Some(ModuleItem {
+            id: self.id.parse().ok()?,
+            title: content.title.or(content.name).or(content.display_name),
+            kind: Some(kind.to_string()),
+            html_url: self.url,
+            page_url,
+            external_url,
+            content_id: content.id.and_then(|id| id.parse().ok()),
+            indent: None,
+        })
+    }
+}
+
+/// Page slug from its URL (`.../pages/<slug>`), as REST module items give it.
+fn page_slug(url: &str) -> String {
+    match url.rsplit_once("/pages/") {
+        Some((_, slug)) => slug.trim_end_matches('/').to_string(),
+        None => url.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlAssignment {
+    #[serde(rename = "_id")]
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    updated_at: Option<String>,
+    due_at: Option<String>,
+    html_url: Option<String>,
+    rubric: Option<GqlRubric>,
+}
+
+#[derive(Deserialize)]
+struct GqlRubric {
+    #[serde(default)]
+    criteria: Vec<GqlCriterion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlCriterion {
+    description: Option<String>,
+    long_description: Option<String>,
+    points: Option<f64>,
+    #[serde(default)]
+    ratings: Vec<GqlRating>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlRating {
+    description: Option<String>,
+    long_description: Option<String>,
+    points: Option<f64>,
+}
+
+impl GqlAssignment {
+    fn into_assignment(self) -> Option<Assignment> {
+        Some(Assignment {
+            id: self.id.parse().ok()?,
+            name: self.name,
+            description: self.description,
+            updated_at: self.updated_at,
+            due_at: self.due_at,
+            html_url: self.html_url,
+            rubric: self.rubric.map(|r| {
+                r.criteria
+                    .into_iter()
+                    .map(|c| RubricCriterion {
+                        description: c.description,
+                        long_description: c.long_description,
+                        points: c.points,
+                        ratings: c
+                            .ratings
+                            .into_iter()
+                            .map(|r| RubricRating {
+                                description: r.description,
+                                long_description: r.long_description,
+                                points: r.points,
+                            })
+                            .collect(),
+                    })
+                    .collect()
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_graphql_courses_to_rest_shapes() {
+        let query = course_query(&[12, 34]);
+        assert!(query.contains("c12: course(id: \"12\")"));
+        assert!(query.contains("c34: course(id: \"34\")"));
+
+        let resp: GqlResponse = serde_json::from_str(
+            r#"{"data": {
+                "c12": {
+                    "modulesConnection": {"pageInfo": {"hasNextPage": false}, "nodes": [
+                        {"_id": "5", "name": "Unidad 1", "moduleItems": [
+                            {"_id": "50", "url": "https://canvas.example/courses/12/modules/items/50",
+                             "content": {"__typename": "Page", "_id": "7", "title": "Intro",
+                                         "url": "https://canvas.example/courses/12/pages/intro"}},
+                            {"_id": "51", "content": {"__typename": "File", "_id": "8", "displayName": "guia.pdf"}},
+                            {"_id": "52", "content": {"__typename": "ModuleExternalTool", "title": "Zoom",
+                                                      "url": "https://zoom.example/lti"}}
+                        ]}
+                    ]},
+                    "assignmentsConnection": {"pageInfo": {"hasNextPage": true}, "nodes": []}
+                },
+                "c34": null
+            }}"#,
+        )
+        .unwrap();
+        let mut courses = resp.data.unwrap();
+        assert!(courses.remove("c34").unwrap().is_none());
+        let metadata = course_metadata(courses.remove("c12").unwrap().unwrap());
+        // A truncated list is left for the REST API to page
+        assert!(metadata.assignments.is_none());
+
+        let modules = metadata.modules.unwrap();
+        assert_eq!(modules[0].id, 5);
+        let items = &modules[0].items;
+        assert_eq!(items[0].kind.as_deref(), Some("Page"));
+        assert_eq!(items[0].page_url.as_deref(), Some("intro"));
+        assert_eq!(items[0].content_id, Some(7));
+        assert_eq!(items[1].title.as_deref(), Some("guia.pdf"));
+        assert_eq!(items[1].content_id, Some(8));
+        assert_eq!(items[2].kind.as_deref(), Some("ExternalTool"));
+        assert_eq!(
+            items[2].external_url.as_deref(),
+            Some("https://zoom.example/lti")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::canvas::{
+    Assignment, CanvasClient, CanvasError, Course, DiscussionEntry, DiscussionTopic, FileObj,
+    Group, Module, ModuleItem, PageObj,
+};
+use crate::provider::CourseProvider;
+
+/// Folder in each course holding the workspaces of the user's groups.
+pub const GROUPS_DIR: &str = "Groups";
+
+/// Page slug prefix under which discussion topics and announcements are served.
+const TOPIC_PREFIX: &str = "discussion_topics/";
+
+/// A group workspace seen as a course, so the sync engine can run its page and
+/// file pipeline on it: the "course id" is the group id, and its pages, files,
+/// discussions and announcements are listed as one module each. Discussion
+/// topics are served as pages with slug `discussion_topics/<id>`, their replies
+/// appended to the body.
+pub struct GroupProvider<'a> {
+    canvas: &'a CanvasClient,
+}
+
+impl<'a> GroupProvider<'a> {
+    pub fn new(canvas: &'a CanvasClient) -> Self {
+        Self { canvas }
+    }
+
+    /// The signed-in user's groups in `course_id`.
+    pub async fn my_groups(&self, course_id: u64) -> Result<Vec<Group>, CanvasError> {
+        let mut groups = self.canvas.list_my_groups().await?;
+        groups.retain(|g| g.course_id == Some(course_id));
+        Ok(groups)
+    }
+}
+
+impl CourseProvider for GroupProvider<'_> {
+    type Error = CanvasError;
+
+    async fn list_courses(&self) -> Result<Vec<Course>, CanvasError> {
+        Ok(Vec::new())
+    }
+
+    async fn list_modules_with_items(&self, group_id: u64) -> Result<Vec<Module>, CanvasError> {
+        let mut pages = self.canvas.list_group_pages(group_id).await?;
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        let mut files = self.canvas.list_group_files(group_id).await?;
+        files.sort_by_key(|f| f.id);
+        let mut topics = self
+            .canvas
+            .list_group_discussion_topics(group_id, false)
+            .await?;
+        topics.sort_by_key(|t| t.id);
+        let mut announcements = self
+            .canvas
+            .list_group_discussion_topics(group_id, true)
+            .await?;
+        announcements.sort_by_key(|t| t.id);
+
+        let page_items = pages
+            .into_iter()
+            .map(|p| item(p.title, "Page", Some(p.url), None))
+            .collect();
+        let file_items = files
+            .into_iter()
+            .map(|f| item(f.display_name, "File", None, Some(f.id)))
+            .collect();
+        let topic_items = |topics: Vec<DiscussionTopic>| {
+            topics
+                .into_iter()
+                .map(|t| {
+                    let slug = format!("{}{}", TOPIC_PREFIX, t.id);
+                    item(t.title, "Page", Some(slug), None)
+                })
+                .collect()
+        };
+        Ok(vec![
+            module(1, "Pages", page_items),
+            module(2, "Files", file_items),
+            module(3, "Discussions", topic_items(topics)),
+            module(4, "Announcements", topic_items(announcements)),
+        ])
+    }
+
+    async fn get_page(&self, group_id: u64, page_url: &str) -> Result<PageObj, CanvasError> {
+        let Some(topic_id) = page_url
+            .strip_prefix(TOPIC_PREFIX)
+            .and_then(|id| id.parse().ok())
+        else {
+            return self.canvas.get_group_page(group_id, page_url).await;
+        };
+        let topic = self
+            .canvas
+            .get_group_discussion_topic(group_id, topic_id)
+            .await?;
+        let entries = self
+            .canvas
+            .list_group_discussion_entries(group_id, topic_id)
+            .await?;
+        Ok(topic_page(topic, &entries))
+    }
+
+    async fn get_file(&self, file_id: u64) -> Result<FileObj, CanvasError> {
+        self.canvas.get_file(file_id).await
+    }
+
+    async fn list_assignments(&self, _group_id: u64) -> Result<Vec<Assignment>, CanvasError> {
+        Ok(Vec::new())
+    }
+}
+
+fn module(id: u64, name: &str, items: Vec<ModuleItem>) -> Module {
+    Module {
+        id,
+        name: name.to_string(),
+        items,
+    }
+}
+
+fn item(
+    title: Option<String>,
+    kind: &str,
+    page_url: Option<String>,
+    content_id: Option<u64>,
+) -> ModuleItem {
+    ModuleItem {
+        id: content_id.unwrap_or_default(),
+        title,
+        kind: Some(kind.to_string()),
+        html_url: None,
+        page_url,
+        external_url: None,
+        content_id,
+        indent: None,
+    }
+}
+
+/// A discussion topic as a page: its message, then each reply under the author's name.
+fn topic_page(topic: DiscussionTopic, entries: &[DiscussionEntry]) -> PageObj {
+    let mut body = String::new();
+    if let Some(author) = topic.user_name.as_deref() {
+        body.push_str(&format!(
+            "<p><em>{} — {}</em></p>",
+            author,
+            topic.posted_at.as_deref().unwrap_or("")
+        ));
+    }
+    body.push_str(topic.message.as_deref().unwrap_or_default());
+    if !entries.is_empty() {
+        body.push_str("<h2>Replies</h2>");
+        for e in entries {
+            body.push_str(&format!(
+                "<h3>{} — {}</h3>{}",
+                e.user_name.as_deref().unwrap_or("Unknown"),
+                e.created_at.as_deref().unwrap_or(""),
+                e.message.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+    PageObj {
+        title: topic.title,
+        body: Some(body),
+        updated_at: None,
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::ErrorKind;
+use crate::plan::{PlanAction, PlanReason};
+use crate::{report_warning, tr};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Maximum number of runs kept in the history file.
+const MAX_RUNS: usize = 200;
+
+/// Summary of a single `sync` invocation, used by `stats` to report download volume.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub started_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub courses: usize,
+    #[serde(default)]
+    pub files_downloaded: usize,
+    #[serde(default)]
+    pub bytes_downloaded: u64,
+    /// Downloaded bytes linked to an identical file of another course instead of
+    /// stored again (`storage.dedup`).
+    #[serde(default)]
+    pub bytes_deduplicated: u64,
+    #[serde(default)]
+    pub failed: usize,
+    #[serde(default)]
+    pub courses_failed: usize,
+    /// Failures per error category (`network`, `auth`, ...), courses and items alike.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub failures: BTreeMap<String, usize>,
+    /// `course <id> <item key>: <error>` for each failed item, shown at the end of
+    /// the run; not kept in the history.
+    #[serde(skip)]
+    pub failed_items: Vec<String>,
+    /// Stopped by Ctrl-C or SIGTERM; the next sync resumes from the saved state
+    /// and the partial downloads.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// What a dry run would write or download; not kept in the history.
+    #[serde(skip)]
+    pub plan: Vec<PlanAction>,
+}
+
+impl RunRecord {
+    pub fn start() -> Self {
+        Self {
+            started_at: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        }
+    }
+
+    /// Count a failed course or item.
+    pub fn fail(&mut self, kind: ErrorKind) {
+        self.failed += 1;
+        *self.failures.entry(kind.to_string()).or_default() += 1;
+    }
+
+    /// Record an item a dry run would write or download.
+    pub fn plan(&mut self, course_id: u64, key: &str, path: Option<&Path>, reason: PlanReason) {
+        self.plan.push(PlanAction {
+            course_id,
+            key: key.to_string(),
+            path: path.map(|p| p.to_string_lossy().into_owned()),
+            reason,
+        });
+    }
+
+    pub fn finish(&mut self) {
+        self.finished_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunHistory {
+    pub runs: Vec<RunRecord>,
+}
+
+impl RunHistory {
+    /// The history at `path`, empty when missing. An unreadable file is moved
+    /// aside to `history.json.corrupt` so the next save does not overwrite it.
+    pub async fn load(path: &Path) -> RunHistory {
+        let buf = match tokio::fs::read(path).await {
+            Ok(buf) => buf,
+            Err(_) => return RunHistory::default(),
+        };
+        let e = match serde_json::from_slice(&buf) {
+            Ok(history) => return history,
+            Err(e) => e,
+        };
+        let mut aside = path.as_os_str().to_owned();
+        aside.push(".corrupt");
+        let aside = PathBuf::from(aside);
+        if let Err(err) = tokio::fs::rename(path, &aside).await {
+            warn!(path = %path.display(), error = %err, "could not move corrupt run history aside");
+        }
+        report_warning!(
+            "{}",
+            tr!(
+                "Warning: run history {} is unreadable ({}); moved it to {} and started a new one.",
+                path.display(),
+                e,
+                aside.display()
+            )
+        );
+        RunHistory::default()
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("json.part");
+        let data = serde_json::to_vec_pretty(self).unwrap();
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+
+    /// Append a run, dropping the oldest entries beyond `MAX_RUNS`.
+    pub fn push(&mut self, run: RunRecord) {
+        self.runs.push(run);
+        if self.runs.len() > MAX_RUNS {
+            let excess = self.runs.len() - MAX_RUNS;
+            self.runs.drain(..excess);
+        }
+    }
+
+    /// Most recent `n` runs, newest first.
+    pub fn last(&self, n: usize) -> impl Iterator<Item = &RunRecord> {
+        self.runs.iter().rev().take(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_caps_history_and_last_is_newest_first() {
+        let mut h = RunHistory::default();
+        for i in 0..(MAX_RUNS + 5) {
+            h.push(RunRecord {
+                started_at: i.to_string(),
+                ..Default::default()
+            });
+        }
+        assert_eq!(h.runs.len(), MAX_RUNS);
+        let last: Vec<&str> = h.last(2).map(|r| r.started_at.as_str()).collect();
+        assert_eq!(
+            last,
+            vec![
+                (MAX_RUNS + 4).to_string().as_str(),
+                (MAX_RUNS + 3).to_string().as_str()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn corrupt_history_is_moved_aside_before_saving() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        std::fs::write(&path, b"{\"runs\": [{\"started_at\"").unwrap();
+
+        let mut history = RunHistory::load(&path).await;
+        assert!(history.runs.is_empty());
+        let aside = dir.path().join("history.json.corrupt");
+        assert_eq!(
+            std::fs::read(&aside).unwrap(),
+            b"{\"runs\": [{\"started_at\""
+        );
+
+        history.push(RunRecord::start());
+        history.save(&path).await.unwrap();
+        assert_eq!(RunHistory::load(&path).await.runs.len(), 1);
+        assert!(aside.exists());
+    }
+}
@@ -1,10 +1,18 @@
 use crate::config::Config;
-use reqwest::{header, Client, ClientBuilder, RequestBuilder, Response, Url};
+use crate::progress;
+use crate::shutdown;
+use crate::tr;
+use futures_util::StreamExt;
+use indicatif::ProgressBar;
+use reqwest::{header, Client, ClientBuilder, RequestBuilder, Response, StatusCode, Url};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
-use tracing::warn;
+use tracing::{debug, trace, warn};
 
 pub fn build_http_client(cfg: &Config) -> Client {
     let mut headers = header::HeaderMap::new();
@@ -13,7 +21,7 @@ pub fn build_http_client(cfg: &Config) -> Client {
         header::HeaderValue::from_static("application/json"),
     );
 
-    let builder = ClientBuilder::new()
+    let builder = with_proxy(ClientBuilder::new(), cfg)
         .user_agent(if cfg.user_agent.is_empty() {
             format!("u_crawler/{}", env!("CARGO_PKG_VERSION"))
         } else {
@@ -30,6 +38,21 @@ pub fn build_http_client(cfg: &Config) -> Client {
     builder.build().expect("http client build")
 }
 
+/// Route a client through `http.proxy` when configured. The proxy URL is
+/// validated when the config is loaded.
+pub fn with_proxy(builder: ClientBuilder, cfg: &Config) -> ClientBuilder {
+    match cfg.http.proxy.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(proxy) => match reqwest::Proxy::all(proxy) {
+            Ok(p) => builder.proxy(p),
+            Err(e) => {
+                warn!(proxy, error = %e, "ignoring invalid http.proxy");
+                builder
+            }
+        },
+        None => builder,
+    }
+}
+
 /// Extract the rel="next" link from an RFC5988 Link header, if present.
 pub fn parse_next_link(link_header: &str) -> Option<Url> {
     // Simple stateful parser to avoid false positives in quoted params
@@ -64,6 +87,112 @@ pub fn parse_next_link(link_header: &str) -> Option<Url> {
     None
 }
 
+/// Canvas starts throttling once the remaining quota drops below this value.
+const RATE_LIMIT_LOW_WATER: f64 = 200.0;
+/// Longest pause inserted before a request when the quota is nearly exhausted.
+const RATE_LIMIT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Tracks Canvas `X-Rate-Limit-Remaining` / `X-Request-Cost` headers and slows
+/// requests down before the quota runs out (Canvas answers 403 when it does).
+#[derive(Clone, Default)]
+pub struct AdaptiveThrottle {
+    /// Remaining quota and cost of the last response that reported them.
+    quota: Arc<Mutex<Option<(f64, f64)>>>,
+}
+
+impl AdaptiveThrottle {
+    /// Sleep for the delay implied by the last observed quota, if any.
+    pub async fn wait(&self) {
+        let delay = self.delay().await;
+        if !delay.is_zero() {
+            debug!(delay_ms = %delay.as_millis(), "canvas quota low, slowing down");
+            sleep(delay).await;
+        }
+    }
+
+    async fn delay(&self) -> Duration {
+        match *self.quota.lock().await {
+            Some((remaining, cost)) => adaptive_delay(remaining, cost),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Record the quota reported by a response.
+    pub async fn observe(&self, resp: &Response) {
+        if let Some(remaining) = header_f64(resp, "x-rate-limit-remaining") {
+            let cost = header_f64(resp, "x-request-cost").unwrap_or(0.0);
+            trace!(remaining, cost, "canvas rate limit headers");
+            *self.quota.lock().await = Some((remaining, cost));
+        }
+    }
+}
+
+fn header_f64(resp: &Response, name: &str) -> Option<f64> {
+    resp.headers()
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.trim().parse::<f64>().ok())
+}
+
+/// Delay before the next request given the remaining quota and the cost of the
+/// last request: zero above the low-water mark, growing linearly to
+/// `RATE_LIMIT_MAX_DELAY` at zero, and stretched by `cost / remaining` so that
+/// expensive requests back off sooner.
+pub fn adaptive_delay(remaining: f64, cost: f64) -> Duration {
+    if remaining >= RATE_LIMIT_LOW_WATER {
+        return Duration::ZERO;
+    }
+    let remaining = remaining.max(0.0);
+    let deficit = (RATE_LIMIT_LOW_WATER - remaining) / RATE_LIMIT_LOW_WATER;
+    let weight = 1.0 + cost.max(0.0) / remaining.max(1.0);
+    RATE_LIMIT_MAX_DELAY.mul_f64((deficit * weight).min(1.0))
+}
+
+/// Canvas signals an exhausted quota with 403 and a non-positive remaining header.
+fn is_rate_limited(resp: &Response) -> bool {
+    resp.status().as_u16() == 429
+        || (resp.status().as_u16() == 403
+            && header_f64(resp, "x-rate-limit-remaining").is_some_and(|r| r <= 0.0))
+}
+
+/// Shared download bandwidth cap. Each chunk reserves its transmission slot
+/// on a common timeline, so concurrent downloads split the configured rate.
+#[derive(Clone)]
+pub struct BandwidthLimiter {
+    bytes_per_sec: u64,
+    next: Arc<Mutex<Instant>>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Limiter for `max_download_rate`, or `None` when downloads are unthrottled.
+    pub fn from_config(cfg: &Config) -> Option<Self> {
+        cfg.download_rate_limit().map(Self::new)
+    }
+
+    /// Account for `bytes` just received, sleeping until they fit within the rate.
+    pub async fn consume(&self, bytes: usize) {
+        let wait = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            if *next < now {
+                *next = now;
+            }
+            *next += Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+            next.saturating_duration_since(now)
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HttpCtx {
     pub client: Client,
@@ -71,26 +200,35 @@ pub struct HttpCtx {
     last: Arc<Mutex<Instant>>, // crude RPS cap
     min_interval: Duration,
     max_retries: usize,
+    throttle: AdaptiveThrottle,
+    pub bandwidth: Option<BandwidthLimiter>,
+    parallel_chunks: u32,
+    parallel_threshold: u64,
 }
 
 impl HttpCtx {
     pub fn new(cfg: &Config, client: Client) -> Self {
-        let min_interval = if cfg.max_rps == 0 {
-            Duration::from_millis(0)
-        } else {
-            Duration::from_millis((1000 / cfg.max_rps) as u64)
-        };
+        let min_interval = 1000u32
+            .checked_div(cfg.max_rps)
+            .map(|ms| Duration::from_millis(ms as u64))
+            .unwrap_or(Duration::from_millis(0));
         Self {
             client,
             limiter: Arc::new(Semaphore::new(cfg.concurrency as usize)),
             last: Arc::new(Mutex::new(Instant::now() - min_interval)),
             min_interval,
             max_retries: 5,
+            throttle: AdaptiveThrottle::default(),
+            bandwidth: BandwidthLimiter::from_config(cfg),
+            parallel_chunks: cfg.http.parallel_chunks,
+            parallel_threshold: crate::config::parse_size(&cfg.http.parallel_threshold)
+                .unwrap_or(u64::MAX),
         }
     }
 
     pub async fn send(&self, rb: RequestBuilder) -> reqwest::Result<Response> {
         let _permit = self.limiter.acquire().await.expect("semaphore");
+        self.throttle.wait().await;
         // RPS pacing
         {
             let mut last = self.last.lock().await;
@@ -104,7 +242,8 @@ impl HttpCtx {
         let mut attempt = 0;
         loop {
             let resp = rb.try_clone().expect("clone request").send().await?;
-            if resp.status().as_u16() == 429 {
+            self.throttle.observe(&resp).await;
+            if is_rate_limited(&resp) && attempt < self.max_retries {
                 let wait = resp
                     .headers()
                     .get(header::RETRY_AFTER)
@@ -112,7 +251,7 @@ impl HttpCtx {
                     .and_then(|s| s.parse::<u64>().ok())
                     .map(Duration::from_secs)
                     .unwrap_or_else(|| Duration::from_millis(500 * (attempt + 1) as u64));
-                warn!(attempt, wait_ms = %wait.as_millis(), "rate limited (429), backing off");
+                warn!(attempt, status = %resp.status().as_u16(), wait_ms = %wait.as_millis(), "rate limited, backing off");
                 sleep(wait).await;
             } else if resp.status().is_server_error() && attempt < self.max_retries {
                 let back = Duration::from_millis(300 * (1 << attempt));
@@ -124,6 +263,288 @@ impl HttpCtx {
             attempt += 1;
         }
     }
+
+    /// Number of concurrent range requests to use for a full (200) response,
+    /// or `None` when the body is small or the server does not accept ranges.
+    pub fn range_chunks_for(&self, resp: &Response) -> Option<u32> {
+        if self.parallel_chunks < 2 || resp.status() != StatusCode::OK {
+            return None;
+        }
+        let accepts_ranges = resp
+            .headers()
+            .get(header::ACCEPT_RANGES)
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+        let len = resp.content_length()?;
+        (accepts_ranges && len >= self.parallel_threshold).then_some(self.parallel_chunks)
+    }
+
+    /// Download `total` bytes into `dest` using `chunks` concurrent range requests
+    /// cloned from `base`, each conditional on `validator` (`If-Range`). Ranges are
+    /// written into the preallocated `part` file used by the single-stream path,
+    /// which is renamed into place once every range has arrived in full.
+    ///
+    /// A 200 to any range means the remote file changed since the probe: the other
+    /// ranges are dropped and the full response is returned as
+    /// [`RangedDownload::Changed`] for the caller to stream instead. On failure or
+    /// interrupt `part` is cut back to its contiguous prefix and keeps `validator`,
+    /// so the next attempt resumes it like an interrupted single stream.
+    pub async fn download_ranges(
+        &self,
+        base: RequestBuilder,
+        part: &Path,
+        dest: &Path,
+        validator: &str,
+        total: u64,
+        chunks: u32,
+    ) -> Result<RangedDownload, Box<dyn std::error::Error + Send + Sync>> {
+        let file = tokio::fs::File::create(part).await?;
+        file.set_len(total).await?;
+        drop(file);
+        tokio::fs::write(resume_validator_path(part), validator).await?;
+        progress::transfer_expect(total);
+        let bar = progress::file_bars().then(|| {
+            let name = dest.file_name().unwrap_or_default().to_string_lossy();
+            progress::attach(progress::download_bar(Some(total), &name))
+        });
+
+        let ranges = split_ranges(total, chunks);
+        let done: Vec<AtomicU64> = ranges.iter().map(|_| AtomicU64::new(0)).collect();
+        let fetches = ranges.iter().zip(&done).map(|(&(start, end), done)| {
+            let rb = base.try_clone();
+            let bar = bar.as_ref();
+            async move {
+                let Some(rb) = rb else {
+                    return Err(RangeFailure::Failed(
+                        "request cannot be cloned for range download".into(),
+                    ));
+                };
+                let rb = rb.header(header::IF_RANGE, validator);
+                match self.fetch_range(rb, part, start, end, done, bar).await {
+                    Ok(None) => Ok(()),
+                    Ok(Some(full)) => Err(RangeFailure::Changed(full)),
+                    Err(e) => Err(RangeFailure::Failed(e)),
+                }
+            }
+        });
+        let fetched = futures_util::future::try_join_all(fetches).await;
+        if let Some(bar) = bar {
+            bar.finish_and_clear();
+        }
+        match fetched {
+            Ok(_) => {}
+            Err(RangeFailure::Changed(full)) => {
+                debug!(path = %dest.display(), "remote changed during ranged download; streaming it whole");
+                discard_partial(part).await?;
+                return Ok(RangedDownload::Changed(full));
+            }
+            Err(RangeFailure::Failed(e)) => {
+                let done: Vec<u64> = done.iter().map(|d| d.load(Ordering::Relaxed)).collect();
+                let prefix = contiguous_prefix(&ranges, &done);
+                if prefix == 0 {
+                    discard_partial(part).await?;
+                } else {
+                    let file = tokio::fs::OpenOptions::new().write(true).open(part).await?;
+                    file.set_len(prefix).await?;
+                    debug!(path = %part.display(), prefix, "kept ranged download prefix for resume");
+                }
+                return Err(e);
+            }
+        }
+
+        let len = tokio::fs::metadata(part).await?.len();
+        if len != total {
+            discard_partial(part).await?;
+            return Err(tr!("ranged download size mismatch: {} of {} bytes", len, total).into());
+        }
+        tokio::fs::rename(part, dest).await?;
+        clear_resume_validator(part).await?;
+        debug!(path = %dest.display(), total, chunks, "ranged download complete");
+        Ok(RangedDownload::Complete(total))
+    }
+
+    /// Fetch one inclusive byte range into `part`, counting written bytes in `done`.
+    /// Returns the response instead when the server sent the full body (200).
+    async fn fetch_range(
+        &self,
+        rb: RequestBuilder,
+        part: &Path,
+        start: u64,
+        end: u64,
+        done: &AtomicU64,
+        bar: Option<&ProgressBar>,
+    ) -> Result<Option<Response>, Box<dyn std::error::Error + Send + Sync>> {
+        let resp = self
+            .send(rb.header(header::RANGE, format!("bytes={}-{}", start, end)))
+            .await?;
+        if resp.status() == StatusCode::OK {
+            return Ok(Some(resp));
+        }
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(tr!("range {}-{} not honored: {}", start, end, resp.status()).into());
+        }
+        let mut file = tokio::fs::OpenOptions::new().write(true).open(part).await?;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        let expected = end - start + 1;
+        let mut written = 0u64;
+        let mut stream = resp.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            if shutdown::requested() {
+                // Keep what arrived; the contiguous prefix is resumed next time
+                file.flush().await?;
+                shutdown::check()?;
+            }
+            let bytes = chunk?;
+            if written + bytes.len() as u64 > expected {
+                return Err(tr!("range {}-{} returned too many bytes", start, end).into());
+            }
+            if let Some(limiter) = &self.bandwidth {
+                limiter.consume(bytes.len()).await;
+            }
+            file.write_all(&bytes).await?;
+            written += bytes.len() as u64;
+            done.store(written, Ordering::Relaxed);
+            if let Some(bar) = bar {
+                bar.inc(bytes.len() as u64);
+            }
+            progress::transfer_inc(bytes.len() as u64);
+        }
+        file.flush().await?;
+        if written != expected {
+            return Err(format!(
+                "range {}-{} incomplete: {} of {} bytes",
+                start, end, written, expected
+            )
+            .into());
+        }
+        Ok(None)
+    }
+}
+
+/// Outcome of [`HttpCtx::download_ranges`].
+pub enum RangedDownload {
+    /// Every range arrived; holds the number of bytes written.
+    Complete(u64),
+    /// A range request got the full body of a newer version; stream it from zero.
+    Changed(Response),
+}
+
+enum RangeFailure {
+    Changed(Response),
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Bytes from offset zero that arrived without a gap, given the bytes written to
+/// each of `ranges` (as returned by [`split_ranges`]).
+fn contiguous_prefix(ranges: &[(u64, u64)], done: &[u64]) -> u64 {
+    let mut prefix = 0;
+    for (&(start, end), &done) in ranges.iter().zip(done) {
+        prefix = start + done;
+        if done < end - start + 1 {
+            break;
+        }
+    }
+    prefix
+}
+
+/// Sidecar holding the validator of a `.part` file's remote version. It keeps the
+/// `.part` suffix so archive walkers treat it as bookkeeping.
+pub fn resume_validator_path(part: &Path) -> PathBuf {
+    part.with_extension("validator.part")
+}
+
+/// Validator usable in `If-Range`: a strong ETag, else `Last-Modified`.
+/// Weak ETags are not allowed in `If-Range`.
+pub fn resume_validator(resp: &Response) -> Option<String> {
+    let header_str = |name| resp.headers().get(name).and_then(|h| h.to_str().ok());
+    header_str(header::ETAG)
+        .filter(|e| !e.trim_start().starts_with("W/"))
+        .or_else(|| header_str(header::LAST_MODIFIED))
+        .map(|v| v.trim().to_string())
+}
+
+pub async fn load_resume_validator(part: &Path) -> Option<String> {
+    tokio::fs::read_to_string(resume_validator_path(part))
+        .await
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Remember the validator of the version about to be streamed into `part`.
+pub async fn save_resume_validator(part: &Path, resp: &Response) -> std::io::Result<()> {
+    match resume_validator(resp) {
+        Some(v) => tokio::fs::write(resume_validator_path(part), v).await,
+        None => clear_resume_validator(part).await,
+    }
+}
+
+pub async fn clear_resume_validator(part: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(resume_validator_path(part)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Drop a partial download and its validator so the next attempt starts from zero.
+pub async fn discard_partial(part: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(part).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    clear_resume_validator(part).await
+}
+
+/// How to write the body of a response to a request that may carry `Range: bytes=<start>-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumePlan {
+    /// Offset the body starts at: the requested start for 206, zero for a full body.
+    pub offset: u64,
+    /// Expected size of the complete file, when the server reported it.
+    pub total: Option<u64>,
+}
+
+/// A 206 must continue exactly at `start`; a 200 (e.g. `If-Range` mismatch) restarts at zero.
+pub fn resume_plan(resp: &Response, start: u64) -> Result<ResumePlan, String> {
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return Ok(ResumePlan {
+            offset: 0,
+            total: resp.content_length(),
+        });
+    }
+    let range = resp
+        .headers()
+        .get(header::CONTENT_RANGE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(parse_content_range);
+    match range {
+        Some((offset, total)) if offset == start => Ok(ResumePlan { offset, total }),
+        _ => Err(tr!("unexpected Content-Range for resume at byte {}", start)),
+    }
+}
+
+/// Parse `bytes <first>-<last>/<total|*>` into the first byte and the total size.
+pub fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let rest = value.trim().strip_prefix("bytes")?.trim_start();
+    let (range, total) = rest.split_once('/')?;
+    let (first, _last) = range.split_once('-')?;
+    let total = match total.trim() {
+        "*" => None,
+        t => Some(t.parse().ok()?),
+    };
+    Some((first.trim().parse().ok()?, total))
+}
+
+/// Split `0..total` into at most `chunks` inclusive byte ranges of near-equal size.
+pub fn split_ranges(total: u64, chunks: u32) -> Vec<(u64, u64)> {
+    if total == 0 {
+        return Vec::new();
+    }
+    let chunk_len = total.div_ceil(chunks.max(1) as u64);
+    (0..total)
+        .step_by(chunk_len as usize)
+        .map(|start| (start, (start + chunk_len).min(total) - 1))
+        .collect()
 }
 
 #[cfg(test)]
@@ -143,6 +564,72 @@ mod tests {
         assert!(parse_next_link(h).is_none());
     }
 
+    #[test]
+    fn adaptive_delay_scales_below_low_water() {
+        assert_eq!(adaptive_delay(700.0, 0.0), Duration::ZERO);
+        assert_eq!(adaptive_delay(RATE_LIMIT_LOW_WATER, 0.0), Duration::ZERO);
+        assert_eq!(adaptive_delay(0.0, 0.0), RATE_LIMIT_MAX_DELAY);
+        assert_eq!(adaptive_delay(-20.0, 0.0), RATE_LIMIT_MAX_DELAY);
+        assert_eq!(
+            adaptive_delay(RATE_LIMIT_LOW_WATER / 2.0, 0.0),
+            RATE_LIMIT_MAX_DELAY / 2
+        );
+    }
+
+    #[tokio::test]
+    async fn costly_responses_lengthen_the_delay() {
+        let response = |cost: &str| {
+            Response::from(
+                http::Response::builder()
+                    .header("x-rate-limit-remaining", "100")
+                    .header("x-request-cost", cost)
+                    .body("")
+                    .unwrap(),
+            )
+        };
+        let throttle = AdaptiveThrottle::default();
+        throttle.observe(&response("0")).await;
+        let cheap = throttle.delay().await;
+        throttle.observe(&response("50")).await;
+        let costly = throttle.delay().await;
+        assert_eq!(cheap, RATE_LIMIT_MAX_DELAY / 2);
+        assert_eq!(costly, RATE_LIMIT_MAX_DELAY.mul_f64(0.75));
+        // Never beyond the ceiling, however expensive the request
+        assert_eq!(adaptive_delay(100.0, 1_000.0), RATE_LIMIT_MAX_DELAY);
+        assert_eq!(adaptive_delay(700.0, 1_000.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn split_ranges_covers_body_without_gaps() {
+        assert_eq!(split_ranges(10, 3), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(8, 4), vec![(0, 1), (2, 3), (4, 5), (6, 7)]);
+        assert_eq!(split_ranges(2, 4), vec![(0, 0), (1, 1)]);
+        assert!(split_ranges(0, 4).is_empty());
+    }
+
+    #[test]
+    fn contiguous_prefix_stops_at_first_gap() {
+        let ranges = split_ranges(100, 4);
+        assert_eq!(contiguous_prefix(&ranges, &[25, 25, 25, 25]), 100);
+        assert_eq!(contiguous_prefix(&ranges, &[25, 10, 25, 0]), 35);
+        assert_eq!(contiguous_prefix(&ranges, &[0, 25, 25, 25]), 0);
+    }
+
+    #[test]
+    fn content_range_parses_start_and_total() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/1000"),
+            Some((100, Some(1000)))
+        );
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((0, None)));
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(
+            resume_validator_path(Path::new("a/lecture.mp4.part")),
+            PathBuf::from("a/lecture.mp4.validator.part")
+        );
+    }
+
     #[test]
     fn link_header_ignores_other_rels() {
         let h = "<https://api.example.com/courses?page=2>; rel=\"prev\", <https://api.example.com/courses?page=3>; rel=\"first\"";
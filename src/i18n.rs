@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Format a user-facing message in the configured language. The English text is
+/// the key into the catalog; `{}` placeholders are filled with the arguments in
+/// order, and a translation may use `{0}`, `{1}`, ... to reorder them.
+#[macro_export]
+macro_rules! tr {
+    ($msg:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::fill(
+            $crate::i18n::text($msg),
+            &[$(&$arg as &dyn ::std::fmt::Display),*],
+        )
+    };
+}
+
+/// Language of the CLI's messages, from `ui.language`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    Es,
+}
+
+impl Lang {
+    pub fn parse(s: &str) -> Option<Lang> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "en" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Set the language for the rest of the process; only the first call applies.
+pub fn set_language(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn language() -> Lang {
+    LANG.get().copied().unwrap_or_default()
+}
+
+/// `msg` in the current language; untranslated messages stay in English.
+pub fn text(msg: &'static str) -> &'static str {
+    match language() {
+        Lang::En => msg,
+        Lang::Es => {
+            static SPANISH: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+            let spanish = SPANISH.get_or_init(|| CATALOG.iter().copied().collect());
+            spanish.get(msg).copied().unwrap_or(msg)
+        }
+    }
+}
+
+/// Replace the `{}` / `{N}` placeholders of `template` with `args`; `{{` and `}}`
+/// stand for literal braces. A placeholder may pad its argument to a width with
+/// an alignment, as in `format!`: `{:<8}`, `{:>6}`, `{1:^5}`.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) else {
+            out.push_str(&tail[..1]);
+            rest = &tail[1..];
+            continue;
+        };
+        let (index, spec) = match tail[1..end].split_once(':') {
+            Some((index, spec)) => (index, parse_spec(spec)),
+            None => (&tail[1..end], Some(('<', 0))),
+        };
+        let index = match index {
+            "" => {
+                next += 1;
+                Some(next - 1)
+            }
+            n => n.parse().ok(),
+        };
+        match (index.and_then(|n: usize| args.get(n)), spec) {
+            (Some(arg), Some((align, width))) => pad(&mut out, &arg.to_string(), align, width),
+            _ => out.push_str(&tail[..=end]),
+        }
+        rest = &tail[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Alignment and width of a `{:<8}`-style spec; alignment defaults to left.
+fn parse_spec(spec: &str) -> Option<(char, usize)> {
+    let (align, width) = match spec.chars().next()? {
+        c @ ('<' | '>' | '^') => (c, &spec[1..]),
+        _ => ('<', spec),
+    };
+    Some((align, width.parse().ok()?))
+}
+
+fn pad(out: &mut String, text: &str, align: char, width: usize) {
+    let gap = width.saturating_sub(text.chars().count());
+    let before = match align {
+        '>' => gap,
+        '^' => gap / 2,
+        _ => 0,
+    };
+    out.extend(std::iter::repeat_n(' ', before));
+    out.push_str(text);
+    out.extend(std::iter::repeat_n(' ', gap - before));
+}
+
+/// English messages and their Spanish translations.
+const CATALOG: &[(&str, &str)] = &[
+    // adopt
+    ("course {} not found among active courses", "no se encontró el curso {} entre los cursos activos"),
+    ("Indexing {}", "Indexando {}"),
+    ("Adopting {}", "Adoptando {}"),
+    ("DRY-RUN adopt {} -> {}", "DRY-RUN adoptar {} -> {}"),
+    ("{}Adopted {} file(s) ({}) into {}; {} already tracked, {} without a local match", "{}Se adoptaron {} archivo(s) ({}) en {}; {} ya registrados, {} sin coincidencia local"),
+    ("Run 'u_crawler sync --course-id {}' to fetch the remaining files.", "Ejecuta 'u_crawler sync --course-id {}' para descargar los archivos restantes."),
+    // audit
+    ("No backup directory found at {}", "No se encontró un directorio de respaldo en {}"),
+    ("Run 'u_crawler sync' to create your first backup.", "Ejecuta 'u_crawler sync' para crear tu primer respaldo."),
+    ("{} - {} documents, {} links checked", "{} - {} documentos, {} enlaces revisados"),
+    ("  broken: {} -> {}", "  roto: {} -> {}"),
+    ("  failed: {} ({})", "  falló: {} ({})"),
+    ("Audited {} course folder(s): {} broken link(s), {} failed item(s)", "Se auditaron {} carpeta(s) de curso: {} enlace(s) roto(s), {} elemento(s) fallido(s)"),
+    ("Run 'u_crawler sync' to re-fetch missing content while Canvas access lasts.", "Ejecuta 'u_crawler sync' para volver a descargar el contenido faltante mientras dure el acceso a Canvas."),
+    // auth
+    ("saved canvas auth in {}", "credenciales de canvas guardadas en {}"),
+    ("signed in to {} as {}; saved moodle auth in {}", "sesión iniciada en {} como {}; credenciales de moodle guardadas en {}"),
+    // canvas
+    ("Course {} has concluded and its content is no longer available; skipping", "El curso {} finalizó y su contenido ya no está disponible; se omite"),
+    ("invalid progress url: {}", "url de progreso no válida: {}"),
+    ("Masquerading as Canvas user {} (canvas.as_user_id): every request acts on their behalf", "Suplantando al usuario de Canvas {} (canvas.as_user_id): cada solicitud actúa en su nombre"),
+    // courses
+    ("No active courses found.", "No se encontraron cursos activos."),
+    ("Space toggles, a toggles all, Enter saves, q cancels", "Espacio marca, a marca todos, Enter guarda, q cancela"),
+    ("Selection cancelled; config unchanged.", "Selección cancelada; la configuración no cambió."),
+    ("Syncing {} course(s), ignoring {}; saved in {}", "Sincronizando {} curso(s), ignorando {}; guardado en {}"),
+    // daemon
+    ("Serving metrics at http://{}/metrics", "Publicando métricas en http://{}/metrics"),
+    ("Starting scheduled sync", "Iniciando sincronización programada"),
+    ("Scheduled sync failed: {}", "Falló la sincronización programada: {}"),
+    ("invalid interval '{}'", "intervalo no válido '{}'"),
+    ("invalid interval '{}'; use e.g. 30m, 6h or 1d", "intervalo no válido '{}'; usa p. ej. 30m, 6h o 1d"),
+    // dashboard
+    ("{} warning(s) or error(s) during the run; the latest:", "{} advertencia(s) o error(es) durante la ejecución; los más recientes:"),
+    ("u_crawler sync  {}:{}:{} elapsed  {} warning(s)/error(s)", "u_crawler sync  {}:{}:{} transcurrido  {} advertencia(s)/error(es)"),
+    ("Downloads ({})", "Descargas ({})"),
+    // doctor
+    ("       hint: {}", "       sugerencia: {}"),
+    ("could not reach Canvas: {}", "no se pudo contactar a Canvas: {}"),
+    ("{}s from the Canvas server", "{}s respecto del servidor de Canvas"),
+    ("{} --version exited with {}: {}", "{} --version terminó con {}: {}"),
+    ("cannot write to {}: {}", "no se puede escribir en {}: {}"),
+    ("{} check(s) failed", "{} comprobación(es) fallida(s)"),
+    ("{} failed: {}", "{} falló: {}"),
+    ("fill in the reported fields in config.toml; `u_crawler auth canvas` sets the Canvas ones", "completa los campos indicados en config.toml; `u_crawler auth canvas` configura los de Canvas"),
+    ("config not loaded", "configuración no cargada"),
+    ("no config directory", "no hay directorio de configuración"),
+    ("canvas.base_url is not set", "canvas.base_url no está definido"),
+    ("run `u_crawler auth canvas --base-url URL --token TOKEN`", "ejecuta `u_crawler auth canvas --base-url URL --token TOKEN`"),
+    ("no token configured, or token_cmd printed nothing", "no hay token configurado, o token_cmd no imprimió nada"),
+    ("run `u_crawler auth canvas --token TOKEN` or check canvas.token_cmd", "ejecuta `u_crawler auth canvas --token TOKEN` o revisa canvas.token_cmd"),
+    ("check canvas.base_url in config.toml", "revisa canvas.base_url en config.toml"),
+    ("Canvas rejected the token (401)", "Canvas rechazó el token (401)"),
+    ("Canvas rejected the token ({})", "Canvas rechazó el token ({})"),
+    ("remove canvas.as_user_id or use an account that may act as other users", "quita canvas.as_user_id o usa una cuenta que pueda actuar como otros usuarios"),
+    ("create a new access token under Account > Settings in Canvas and run `u_crawler auth canvas --token TOKEN`", "crea un nuevo token de acceso en Cuenta > Configuración en Canvas y ejecuta `u_crawler auth canvas --token TOKEN`"),
+    ("check canvas.base_url, your network and http.proxy", "revisa canvas.base_url, tu red y http.proxy"),
+    ("no Canvas server to compare with", "no hay servidor de Canvas con el que comparar"),
+    ("Canvas sent no Date header", "Canvas no envió la cabecera Date"),
+    ("enable automatic time sync (NTP); SSO sign-ins and signed download links depend on an accurate clock", "activa la sincronización automática de la hora (NTP); los inicios de sesión SSO y los enlaces de descarga firmados dependen de un reloj preciso"),
+    ("install ffmpeg (see Prerequisites in the README) or set zoom.ffmpeg_path", "instala ffmpeg (ver Prerequisites en el README) o define zoom.ffmpeg_path"),
+    ("install Google Chrome or Chromium, or set CHROME to its executable", "instala Google Chrome o Chromium, o apunta CHROME a su ejecutable"),
+    ("point download_root at a writable folder or fix its permissions", "apunta download_root a una carpeta con permiso de escritura o corrige sus permisos"),
+    ("move zoom_state.sqlite aside and run `u_crawler zoom flow` to rebuild it", "aparta zoom_state.sqlite y ejecuta `u_crawler zoom flow` para reconstruirla"),
+    ("not created yet", "aún no creada"),
+    ("integrity check passed", "la comprobación de integridad fue correcta"),
+    ("signed in as {} (user {})", "sesión iniciada como {} (usuario {})"),
+    ("{} is writable{}", "{} admite escritura{}"),
+    (", {} free", ", {} libres"),
+    // ffmpeg
+    ("HLS download incomplete: duration {}s vs playlist {}s", "descarga HLS incompleta: duración {}s frente a {}s de la lista"),
+    ("transcode verification failed: duration {}s vs original {}s", "falló la verificación de la transcodificación: duración {}s frente a {}s del original"),
+    // fixtures
+    ("no recorded fixture for {} {}", "no hay una respuesta grabada para {} {}"),
+    // fsutil
+    ("not enough disk space on {}: need {} plus {} reserve, {} available", "no hay suficiente espacio en disco en {}: se necesitan {} más {} de reserva, hay {} disponibles"),
+    // http
+    ("ranged download size mismatch: {} of {} bytes", "el tamaño de la descarga por rangos no coincide: {} de {} bytes"),
+    ("range {}-{} not honored: {}", "no se respetó el rango {}-{}: {}"),
+    ("range {}-{} returned too many bytes", "el rango {}-{} devolvió demasiados bytes"),
+    ("unexpected Content-Range for resume at byte {}", "Content-Range inesperado al reanudar en el byte {}"),
+    // imscc
+    ("No matching course to export.", "No hay cursos que exportar."),
+    ("DRY-RUN course {} ({}) -> {}", "DRY-RUN curso {} ({}) -> {}"),
+    ("Exporting course {} ({})...", "Exportando el curso {} ({})..."),
+    ("✓ Course {} exported to {}", "✓ Curso {} exportado a {}"),
+    ("✗ Course {} ({}): {}", "✗ Curso {} ({}): {}"),
+    ("IMSCC export: {} course(s) exported, {} failed", "Exportación IMSCC: {} curso(s) exportado(s), {} fallido(s)"),
+    ("Canvas does not allow this account to export course {}; only teachers can", "Canvas no permite que esta cuenta exporte el curso {}; solo los docentes pueden"),
+    ("Packaging {}", "Empaquetando {}"),
+    ("export of course {} did not finish within {} minutes", "la exportación del curso {} no terminó en {} minutos"),
+    ("Canvas could not export course {}: {}", "Canvas no pudo exportar el curso {}: {}"),
+    ("Canvas could not export course {}", "Canvas no pudo exportar el curso {}"),
+    // inbox
+    ("DRY-RUN conversation {} -> {} ({} message(s))", "DRY-RUN conversación {} -> {} ({} mensaje(s))"),
+    ("✗ Conversation {}: {}", "✗ Conversación {}: {}"),
+    ("✗ Conversation {} attachment {}: {}", "✗ Conversación {}, adjunto {}: {}"),
+    ("to write", "por escribir"),
+    ("written", "escrita(s)"),
+    ("Inbox: {} conversation(s) {}, {} unchanged, {} attachment(s) downloaded, {} failed", "Bandeja de entrada: {} conversación(es) {}, {} sin cambios, {} adjunto(s) descargado(s), {} fallida(s)"),
+    // index
+    ("Course files", "Archivos del curso"),
+    ("Week {}", "Semana {}"),
+    ("Zoom recordings", "Grabaciones de Zoom"),
+    // logger
+    ("u_crawler: spans will not be exported: {}", "u_crawler: no se exportarán los spans: {}"),
+    // main
+    ("error: {}", "error: {}"),
+    ("error: {} course(s) and {} item(s) failed; see the summary above", "error: fallaron {} curso(s) y {} elemento(s); revisa el resumen anterior"),
+    ("clean: stub (implement in M5)", "clean: aún no implementado (M5)"),
+    ("Config file already exists and is valid.", "El archivo de configuración ya existe y es válido."),
+    ("Created default config at {}", "Configuración por defecto creada en {}"),
+    ("course {}: {}", "curso {}: {}"),
+    ("course {}: no tags", "curso {}: sin etiquetas"),
+    ("No tagged courses. Use `courses tag <course_id> <tags...>`.", "No hay cursos etiquetados. Usa `courses tag <course_id> <tags...>`."),
+    ("--{} must be a date like 2024-03-01, got '{}'", "--{} debe ser una fecha como 2024-03-01, se recibió '{}'"),
+    ("invalid --topic-regex: {}", "--topic-regex no válido: {}"),
+    // manifest
+    ("Published manifest with {} course(s), {} file(s) to {}", "Manifiesto publicado con {} curso(s) y {} archivo(s) en {}"),
+    ("DRY-RUN import {} -> {}", "DRY-RUN importar {} -> {}"),
+    ("{}Merged manifest from {}: {} imported ({}), {} already present, {} missing in shared folder, {} hash mismatches", "{}Manifiesto de {} combinado: {} importado(s) ({}), {} ya presente(s), {} faltante(s) en la carpeta compartida, {} con hash distinto"),
+    // media
+    ("Canvas Studio returned {} for media {}; it may not be shared by link", "Canvas Studio devolvió {} para el medio {}; puede que no esté compartido por enlace"),
+    ("Canvas Studio media {} has no MP4 source", "el medio {} de Canvas Studio no tiene una fuente MP4"),
+    // panopto
+    ("course {} has no Panopto tool", "el curso {} no tiene la herramienta Panopto"),
+    ("Launching Panopto (tool {})...", "Abriendo Panopto (herramienta {})..."),
+    ("⚠ The Panopto launch did not open a folder; set panopto.folders.\"{}\" to list it.", "⚠ Panopto no abrió ninguna carpeta; configura panopto.folders.\"{}\" para listarla."),
+    ("⚠ Embedded session {}: {}", "⚠ Sesión incrustada {}: {}"),
+    ("⏩ Skipping (already exists): {}", "⏩ Omitido (ya existe): {}"),
+    ("DRY-RUN: {} Panopto session(s) to download.", "DRY-RUN: {} sesión(es) de Panopto por descargar."),
+    ("↪ {}: trying the HLS stream...", "↪ {}: probando con el stream HLS..."),
+    ("Downloaded {} of {} Panopto session(s) to {}", "Se descargaron {} de {} sesión(es) de Panopto en {}"),
+    ("{} Panopto session(s) failed", "fallaron {} sesión(es) de Panopto"),
+    // recordings
+    ("No active course with id {} found.", "No se encontró un curso activo con id {}."),
+    ("Scanning course {}", "Revisando el curso {}"),
+    ("Loading modules for {}", "Cargando módulos de {}"),
+    ("Modules in {}", "Módulos de {}"),
+    ("Module {}", "Módulo {}"),
+    ("✓ Archived {}", "✓ Archivado {}"),
+    ("Loading assignments for {}", "Cargando tareas de {}"),
+    ("Loading announcements, discussions, syllabus and events for {}", "Cargando anuncios, foros, programa y eventos de {}"),
+    ("{}Discovered {} Zoom link(s).", "{}Se encontraron {} enlace(s) de Zoom."),
+    ("Saved {} recording link(s) to the Zoom DB.", "Se guardaron {} enlace(s) de grabaciones en la base de datos de Zoom."),
+    ("Run 'u_crawler zoom from-links --course-id {}' to download them.", "Ejecuta 'u_crawler zoom from-links --course-id {}' para descargarlas."),
+    // scan
+    ("Modules for course {}", "Módulos del curso {}"),
+    ("Modules (course_id={}):", "Módulos (course_id={}):"),
+    ("Files (discovered via modules) count: {}", "Archivos (encontrados en los módulos): {}"),
+    ("Inventory ({} course(s)):", "Inventario ({} curso(s)):"),
+    ("- {} {} item(s) {}", "- {} {} elemento(s) {}"),
+    ("Total: {} item(s), about {}", "Total: {} elemento(s), aprox. {}"),
+    ("Inventory written to {}", "Inventario escrito en {}"),
+    ("Scanning {}", "Revisando {}"),
+    // search
+    ("No matches for '{}'", "No hay resultados para '{}'"),
+    // sharepoint
+    ("No SharePoint or Stream videos linked in course {}.", "El curso {} no enlaza videos de SharePoint ni Stream."),
+    ("DRY-RUN: {} video link(s) found.", "DRY-RUN: se encontraron {} enlace(s) de video."),
+    ("Opening {} video link(s)...", "Abriendo {} enlace(s) de video..."),
+    ("↪ {}: downloading the streaming manifest...", "↪ {}: descargando el manifiesto de streaming..."),
+    ("Downloaded {} SharePoint/Stream video(s) to {}", "Se descargaron {} video(s) de SharePoint/Stream en {}"),
+    ("{} video(s) could not be downloaded", "no se pudieron descargar {} video(s)"),
+    // shutdown
+    ("Interrupted: saving progress after the current download. Press Ctrl-C again to quit now.", "Interrumpido: se guardará el avance tras la descarga en curso. Presiona Ctrl-C otra vez para salir ya."),
+    ("interrupted; run the same command again to resume", "interrumpido; ejecuta el mismo comando otra vez para continuar"),
+    // site
+    ("Site written to {}: {} course(s), {} page(s), {} file(s)", "Sitio escrito en {}: {} curso(s), {} página(s), {} archivo(s)"),
+    ("Courses", "Cursos"),
+    ("Search pages", "Buscar páginas"),
+    // snapshot
+    ("timed out loading {}", "se agotó el tiempo al cargar {}"),
+    // stats
+    ("Archive Stats ({})\n", "Estadísticas del archivo ({})\n"),
+    ("Per course:", "Por curso:"),
+    ("  [{}] {} - {} files, {}", "  [{}] {} - {} archivos, {}"),
+    (" ({} tracked, {} failed)", " ({} registrados, {} fallidos)"),
+    ("\nPer provider:", "\nPor proveedor:"),
+    ("  {}: {} files, {}", "  {}: {} archivos, {}"),
+    ("\nPer tag:", "\nPor etiqueta:"),
+    ("\nBy file type:", "\nPor tipo de archivo:"),
+    ("  {:<8} {:>6} files  {}", "  {:<8} {:>6} archivos  {}"),
+    ("\nLargest files:", "\nArchivos más grandes:"),
+    ("\nRecent runs:", "\nEjecuciones recientes:"),
+    ("  No sync runs recorded yet.", "  Aún no hay sincronizaciones registradas."),
+    ("  {} - {} course(s), {} file(s), {}, {} failed", "  {} - {} curso(s), {} archivo(s), {}, {} fallido(s)"),
+    ("  Downloaded over last {} run(s): {}", "  Descargado en las últimas {} ejecución(es): {}"),
+    ("Total: {} course folders, {} files, {}", "Total: {} carpetas de curso, {} archivos, {}"),
+    ("Warning: run history {} is unreadable ({}); moved it to {} and started a new one.", "Advertencia: no se puede leer el historial de ejecuciones {} ({}); se movió a {} y se comenzó uno nuevo."),
+    // state
+    ("Warning: {} is unreadable ({}); using {}. Run 'u_crawler state repair' to restore it.", "Advertencia: no se puede leer {} ({}); se usa {}. Ejecuta 'u_crawler state repair' para restaurarlo."),
+    ("Warning: {} is unreadable ({}) and has no backup; the course will be synced as new.", "Advertencia: no se puede leer {} ({}) y no tiene copia de respaldo; el curso se sincronizará como nuevo."),
+    ("✓ Restored {} from {}", "✓ Se restauró {} desde {}"),
+    ("✗ {} was corrupt and has no backup; moved it aside. Run 'u_crawler adopt' to rebuild it from the downloaded files.", "✗ {} estaba dañado y no tiene copia de respaldo; se apartó. Ejecuta 'u_crawler adopt' para reconstruirlo a partir de los archivos descargados."),
+    ("All state files are readable.", "Todos los archivos de estado se pueden leer."),
+    // status
+    ("No courses found in {}", "No se encontraron cursos en {}"),
+    ("Backup Status:\n", "Estado del respaldo:\n"),
+    ("Course: {}", "Curso: {}"),
+    ("  Tags: {}", "  Etiquetas: {}"),
+    ("  Files: {}", "  Archivos: {}"),
+    ("  Storage: {}", "  Espacio: {}"),
+    ("  Recordings: {} file(s), {} in {}", "  Grabaciones: {} archivo(s), {} en {}"),
+    ("  Last sync: {}", "  Última sincronización: {}"),
+    ("  Last sync: Never", "  Última sincronización: nunca"),
+    ("  Failed downloads: {} items need retry", "  Descargas fallidas: {} elementos por reintentar"),
+    ("    - {} (failed {} time(s))", "    - {} (falló {} vez/veces)"),
+    ("      Error: {}", "      Error: {}"),
+    ("      Run with --verbose to see details", "      Ejecuta con --verbose para ver los detalles"),
+    ("  Locked files: {} waiting for Canvas to unlock them", "  Archivos bloqueados: {} a la espera de que Canvas los desbloquee"),
+    ("    - {} ({})", "    - {} ({})"),
+    ("Total: {} courses, {} files, {}", "Total: {} cursos, {} archivos, {}"),
+    ("Tip: Run 'u_crawler sync --dry-run' to check for remote changes", "Sugerencia: ejecuta 'u_crawler sync --dry-run' para revisar cambios remotos"),
+    // syncer
+    ("Warning: unable to commit the archive to git: {}", "Advertencia: no se pudo guardar el respaldo en git: {}"),
+    ("🔒 {} is not available yet ({}); will retry on the next sync", "🔒 {} aún no está disponible ({}); se reintentará en la próxima sincronización"),
+    ("Moved module folder {} to {}", "Se movió la carpeta del módulo {} a {}"),
+    ("; {} already stored for another course", "; {} ya guardados para otro curso"),
+    ("expected a date like 2024-03-01 or `last`, got '{}'", "se esperaba una fecha como 2024-03-01 o `last`, se recibió '{}'"),
+    ("Syncing course {}", "Sincronizando el curso {}"),
+    ("Warning: unable to write the combined calendar: {}", "Advertencia: no se pudo escribir el calendario combinado: {}"),
+    ("DRY-RUN summary: pages to write: {}, files to download: {}", "DRY-RUN resumen: páginas por escribir: {}, archivos por descargar: {}"),
+    ("unable to read plan {}: {}", "no se pudo leer el plan {}: {}"),
+    ("Plan compared with {}: {} difference(s)", "Plan comparado con {}: {} diferencia(s)"),
+    ("Plan with {} action(s) ({}) written to {}", "Plan con {} acción(es) ({}) escrito en {}"),
+    ("Failed items:", "Elementos fallidos:"),
+    ("  ... and {} more", "  ... y {} más"),
+    ("Sync summary: {} course(s) synced, {} failed; {} file(s) downloaded ({}), {} item(s) failed", "Resumen: {} curso(s) sincronizado(s), {} fallido(s); {} archivo(s) descargado(s) ({}), {} elemento(s) fallido(s)"),
+    ("DRY-RUN warning: {}", "DRY-RUN advertencia: {}"),
+    ("DRY-RUN course {} -> about {} of new module files", "DRY-RUN curso {} -> aprox. {} en archivos nuevos de módulos"),
+    ("Course {} module {}", "Curso {} módulo {}"),
+    ("DRY-RUN module {} -> pages: {}, files: {}", "DRY-RUN módulo {} -> páginas: {}, archivos: {}"),
+    ("Starting Zoom sync for course {}...", "Iniciando la sincronización de Zoom del curso {}..."),
+    ("✓ Zoom sync completed for course {}", "✓ Sincronización de Zoom completada para el curso {}"),
+    ("Warning: Zoom sync failed for course {}: {}", "Advertencia: falló la sincronización de Zoom del curso {}: {}"),
+    ("DRY-RUN course {} was renamed: {} -> {}", "DRY-RUN el curso {} cambió de nombre: {} -> {}"),
+    ("Course {} was renamed but {} already exists; still syncing into {}", "El curso {} cambió de nombre, pero {} ya existe; se sigue sincronizando en {}"),
+    ("Course {} was renamed: {} now links to {}", "El curso {} cambió de nombre: {} ahora enlaza a {}"),
+    ("Course {} was renamed: moved {} to {}", "El curso {} cambió de nombre: se movió {} a {}"),
+    ("media object {} is not listed for the course", "el objeto multimedia {} no aparece en el curso"),
+    ("attachment {} is a Canvas file", "el adjunto {} es un archivo de Canvas"),
+    ("size mismatch: got {} of {} bytes", "el tamaño no coincide: se recibieron {} de {} bytes"),
+    // zoom
+    ("Transcript", "Transcripción"),
+    ("Moved recordings of course {} from {} to {}", "Se movieron las grabaciones del curso {} de {} a {}"),
+    ("✓ Transcript notes: {}", "✓ Notas de la transcripción: {}"),
+    ("Zoom returned {} (likely cookies/headers invalid): {}", "Zoom devolvió {} (probablemente cookies/cabeceras no válidas): {}"),
+    ("Connecting to Chrome on port {} for course {}...", "Conectando con Chrome en el puerto {} para el curso {}..."),
+    ("Captured Zoom session for course {}. Run 'u_crawler zoom flow --course-id {}' to download recordings.", "Sesión de Zoom capturada para el curso {}. Ejecuta 'u_crawler zoom flow --course-id {}' para descargar las grabaciones."),
+    ("HTTP {} while downloading {}", "HTTP {} al descargar {}"),
+    ("{} returned a web page instead of the file", "{} devolvió una página web en lugar del archivo"),
+    ("Remote recording changed since the partial download; restarting", "La grabación remota cambió desde la descarga parcial; se reinicia"),
+    ("Remote recording changed during the download; restarting", "La grabación remota cambió durante la descarga; se reinicia"),
+    ("size mismatch for {}: got {} of {} bytes", "el tamaño de {} no coincide: se recibieron {} de {} bytes"),
+    ("⚠ Warning: No cookies found for domain {}", "⚠ Advertencia: no hay cookies para el dominio {}"),
+    ("LTI launch never reached {}", "el lanzamiento LTI nunca llegó a {}"),
+    ("⚠ No video player found at {}", "⚠ No se encontró un reproductor de video en {}"),
+    ("Browser handler error: {}", "Error del manejador del navegador: {}"),
+    ("Browser handler loop exited.", "El ciclo del manejador del navegador terminó."),
+    ("could not connect to Chrome at {} ({}); start it with --remote-debugging-port={}", "no se pudo conectar con Chrome en {} ({}); inícialo con --remote-debugging-port={}"),
+    ("Found existing lti_scid in DB: {}", "lti_scid existente en la base de datos: {}"),
+    ("Captured lti_scid from Fetch: {}", "lti_scid capturado desde Fetch: {}"),
+    ("Captured {} ajaxHeaders from Fetch (array format)", "Se capturaron {} ajaxHeaders desde Fetch (formato de arreglo)"),
+    ("Captured x-xsrf-token from the page config", "x-xsrf-token capturado desde la configuración de la página"),
+    ("Failed to get body in Fetch interception: {}", "No se pudo obtener el cuerpo en la intercepción de Fetch: {}"),
+    ("Captured lti_scid from URL: {}", "lti_scid capturado desde la URL: {}"),
+    ("Captured Zoom API headers", "Cabeceras de la API de Zoom capturadas"),
+    ("Navigating to: {}", "Navegando a: {}"),
+    ("Waiting for Zoom LTI to load...", "Esperando que cargue el LTI de Zoom..."),
+    ("Saved lti_scid to DB: {}", "lti_scid guardado en la base de datos: {}"),
+    ("Saving headers: {}", "Guardando cabeceras: {}"),
+    ("Saved {} request headers to DB", "Se guardaron {} cabeceras de solicitud en la base de datos"),
+    ("Warning: No request headers captured", "Advertencia: no se capturaron cabeceras de solicitud"),
+    ("AFTER HEADLESS SAVE -> scid={}, cookies={}, headers={}", "TRAS GUARDAR (HEADLESS) -> scid={}, cookies={}, cabeceras={}"),
+    ("Checking for SSO login...", "Buscando el inicio de sesión SSO..."),
+    ("Detected Canvas login page. Attempting to initiate SSO...", "Se detectó la página de inicio de sesión de Canvas. Intentando iniciar SSO..."),
+    ("Found SSO initiation button. Clicking...", "Se encontró el botón de SSO. Haciendo clic..."),
+    ("Warning: Could not find an SSO button matching {} on the Canvas login page (see zoom.sso.login_button_texts).", "Advertencia: no se encontró un botón de SSO que coincida con {} en la página de inicio de sesión de Canvas (ver zoom.sso.login_button_texts)."),
+    ("Not on Microsoft SSO page (URL: {}), assuming already logged in or not required.", "No se está en la página SSO de Microsoft (URL: {}); se asume que la sesión ya está iniciada o no es necesaria."),
+    ("Handling Microsoft SSO...", "Procesando el SSO de Microsoft..."),
+    ("Found remembered account tile, clicking...", "Se encontró una cuenta recordada, haciendo clic..."),
+    ("Warning: Failed to click account tile: {}", "Advertencia: no se pudo hacer clic en la cuenta: {}"),
+    ("Attempting to enter email...", "Intentando ingresar el correo..."),
+    ("Warning: sso_email not set; skipping email entry.", "Advertencia: sso_email no está configurado; se omite el correo."),
+    ("Attempting to enter password...", "Intentando ingresar la contraseña..."),
+    ("Warning: sso_password not set; skipping password entry.", "Advertencia: sso_password no está configurado; se omite la contraseña."),
+    ("Handling 'Stay signed in' prompt...", "Respondiendo a '¿Mantener la sesión iniciada?'..."),
+    ("Entering recording passcode...", "Ingresando el código de acceso de la grabación..."),
+    ("Zoom player already loaded, no authentication needed", "El reproductor de Zoom ya cargó; no hace falta autenticarse"),
+    ("No Zoom login detected, assuming already authenticated", "No se detectó inicio de sesión de Zoom; se asume que ya está autenticado"),
+    ("Zoom play_url: detected login screen, initiating Microsoft SSO...", "play_url de Zoom: se detectó la pantalla de inicio de sesión, iniciando SSO de Microsoft..."),
+    ("Clicked 'Sign in with Microsoft' button (aria-label match)", "Clic en el botón 'Sign in with Microsoft' (coincidencia de aria-label)"),
+    ("Clicked 'Sign in with Microsoft' button (aria-label partial match)", "Clic en el botón 'Sign in with Microsoft' (coincidencia parcial de aria-label)"),
+    ("Clicked 'Microsoft' login method (text match)", "Clic en el método de inicio de sesión 'Microsoft' (coincidencia de texto)"),
+    ("Clicked Microsoft sign-in button, waiting for redirect...", "Clic en el botón de Microsoft, esperando la redirección..."),
+    ("Redirected to Microsoft login: {}", "Redirigido al inicio de sesión de Microsoft: {}"),
+    ("Microsoft authentication complete, waiting for Zoom player...", "Autenticación de Microsoft completa, esperando el reproductor de Zoom..."),
+    ("Back on Zoom page: {}", "De vuelta en la página de Zoom: {}"),
+    ("Zoom player loaded", "Reproductor de Zoom cargado"),
+    ("Zoom player should now be loaded", "El reproductor de Zoom ya debería estar cargado"),
+    ("All recordings already downloaded!", "¡Todas las grabaciones ya están descargadas!"),
+    ("Found {} recordings, {} new to download", "Se encontraron {} grabaciones, {} nuevas por descargar"),
+    ("Processing {} recordings with {} download worker(s) (capture → queue download → next)...\n", "Procesando {} grabaciones con {} descarga(s) en paralelo (capturar → encolar descarga → siguiente)...\n"),
+    ("\n↻ Re-capturing {} after its token expired (attempt {}, waiting {}s)", "\n↻ Recapturando {} porque su token expiró (intento {}, esperando {}s)"),
+    ("\n[{}/{}] Processing: {}", "\n[{}/{}] Procesando: {}"),
+    ("Skipping this file...", "Omitiendo este archivo..."),
+    ("Warning: SSO failed for {}: {}", "Advertencia: falló el SSO para {}: {}"),
+    ("Capturing fresh cookies after SSO...", "Capturando cookies nuevas tras el SSO..."),
+    ("Saved {} fresh cookies for downloads", "Se guardaron {} cookies nuevas para las descargas"),
+    ("✓ Captured download URL: {}", "✓ URL de descarga capturada: {}"),
+    ("  Captured {} headers from MP4 request", "  Se capturaron {} cabeceras de la solicitud MP4"),
+    ("✗ Could not capture download URL, skipping...", "✗ No se pudo capturar la URL de descarga, se omite..."),
+    ("⬇ Queued download to: {}", "⬇ Descarga encolada en: {}"),
+    ("\nAll files processed! Downloads saved to: {}", "\n¡Todos los archivos procesados! Descargas guardadas en: {}"),
+    ("Saved diagnostics for '{}' (final URL: {}) to {}", "Diagnóstico de '{}' (URL final: {}) guardado en {}"),
+    ("⏩ Skipping (already downloaded): {}", "⏩ Omitido (ya descargado): {}"),
+    ("↪ Moved into meeting folder: {}", "↪ Movido a la carpeta de la reunión: {}"),
+    ("⚠ {}: metadata not embedded: {}", "⚠ {}: no se incrustaron los metadatos: {}"),
+    ("✗ {}: token rejected by Zoom", "✗ {}: Zoom rechazó el token"),
+    ("✗ {}: ffmpeg failed, trying HTTP fallback...", "✗ {}: ffmpeg falló, probando descarga HTTP..."),
+    ("⚠ {}: keeping original recording: {}", "⚠ {}: se conserva la grabación original: {}"),
+    ("✓ Downloaded {}", "✓ Descargado {}"),
+    ("✗ {}: download error: {}", "✗ {}: error de descarga: {}"),
+    ("✗ Giving up on {} after {} re-capture(s)", "✗ Se abandona {} tras {} recaptura(s)"),
+    ("no Zoom recording links saved for course {}; run 'u_crawler recordings --course-id {}' first", "no hay enlaces de grabaciones de Zoom guardados para el curso {}; ejecuta primero 'u_crawler recordings --course-id {}'"),
+    ("- {}: already listed as meeting {}", "- {}: ya aparece como la reunión {}"),
+    ("All {} saved link(s) belong to meetings in the Zoom listing; 'zoom flow' downloads them.", "Los {} enlace(s) guardado(s) pertenecen a reuniones del listado de Zoom; 'zoom flow' los descarga."),
+    ("Downloading {} linked recording(s); {} already covered by the Zoom listing.", "Descargando {} grabación(es) enlazada(s); {} ya cubierta(s) por el listado de Zoom."),
+    ("Linked recordings processed!", "¡Grabaciones enlazadas procesadas!"),
+    ("{} is not a Zoom recording link (/rec/share/ or /rec/play/)", "{} no es un enlace de grabación de Zoom (/rec/share/ o /rec/play/)"),
+    ("Shared recordings processed!", "¡Grabaciones compartidas procesadas!"),
+    ("Starting Zoom flow for course {}", "Iniciando el flujo de Zoom para el curso {}"),
+    ("Starting listing and download for course {}", "Iniciando el listado y la descarga del curso {}"),
+    ("No Zoom meetings were found for course {}.", "No se encontraron reuniones de Zoom para el curso {}."),
+    ("Captured {} Zoom meetings; fetching individual recording files...", "Se capturaron {} reuniones de Zoom; obteniendo los archivos de cada grabación..."),
+    ("Found Meeting: ID={}, Topic='{}', Start={}", "Reunión encontrada: ID={}, Tema='{}', Inicio={}"),
+    ("Gathering recording files for course {}", "Reuniendo los archivos de grabación del curso {}"),
+    ("Meeting {}", "Reunión {}"),
+    ("- {}: Zoom did not report downloadable files", "- {}: Zoom no informó archivos descargables"),
+    ("- {}: captured {} playUrl entries", "- {}: se capturaron {} entradas playUrl"),
+    ("No recordings with playUrl entries were available after the full flow; try again or verify permissions.", "No hubo grabaciones con entradas playUrl tras el flujo completo; inténtalo de nuevo o revisa los permisos."),
+    ("Starting capture and download (tokens expire quickly, processing one by one)...", "Iniciando captura y descarga (los tokens expiran rápido, se procesan de a uno)..."),
+    ("All recordings processed!", "¡Todas las grabaciones procesadas!"),
+    ("no cached Zoom meetings for course {}; run 'u_crawler zoom flow --course-id {}' first", "no hay reuniones de Zoom en caché para el curso {}; ejecuta primero 'u_crawler zoom flow --course-id {}'"),
+    ("- {}: no recording files cached; run 'zoom flow' to refresh the listing", "- {}: no hay archivos de grabación en caché; ejecuta 'zoom flow' para actualizar el listado"),
+    ("No cached recordings match the selection for course {}.", "Ninguna grabación en caché coincide con la selección para el curso {}."),
+    ("Selected {} meeting(s) with {} recording file(s) for course {}", "Se seleccionaron {} reunión(es) con {} archivo(s) de grabación para el curso {}"),
+    ("Selected recordings processed!", "¡Grabaciones seleccionadas procesadas!"),
+    ("Zoom session expires {}; refreshing it ahead of time...", "La sesión de Zoom expira {}; renovándola con anticipación..."),
+    ("Starting headless capture (SSO + LTI scid + cookies)...", "Iniciando captura headless (SSO + scid del LTI + cookies)..."),
+    ("Headless capture finished.", "Captura headless terminada."),
+    ("Found existing credentials in DB. Validating...", "Hay credenciales en la base de datos. Validando..."),
+    ("Cookies are valid. Skipping headless capture.", "Las cookies son válidas. Se omite la captura headless."),
+    ("Cookies are invalid or expired.", "Las cookies no son válidas o expiraron."),
+    ("Failed to initialize Zoom client for validation: {}", "No se pudo iniciar el cliente de Zoom para validar: {}"),
+    ("Missing some credentials in DB.", "Faltan credenciales en la base de datos."),
+    ("Relaunching the Zoom LTI for course {} (no sign-in)...", "Relanzando el LTI de Zoom para el curso {} (sin iniciar sesión)..."),
+    ("zoom.api is configured; the REST backend needs no browser session.", "zoom.api está configurado; el backend REST no necesita sesión de navegador."),
+    ("Zoom session for course {} is valid until {}", "La sesión de Zoom del curso {} es válida hasta {}"),
+    ("Zoom session for course {} is valid (no cookie expiry recorded)", "La sesión de Zoom del curso {} es válida (sin vencimiento de cookies registrado)"),
+    ("No cached Zoom meetings for course {}.", "No hay reuniones de Zoom en caché para el curso {}."),
+    ("Run 'u_crawler zoom flow --course-id {}' to list them from Zoom.", "Ejecuta 'u_crawler zoom flow --course-id {}' para listarlas desde Zoom."),
+    ("unknown date", "fecha desconocida"),
+    ("(no topic)", "(sin tema)"),
+    ("    no recording files cached", "    sin archivos de grabación en caché"),
+    ("{} meeting(s), {} file(s), {} downloaded", "{} reunión(es), {} archivo(s), {} descargado(s)"),
+    ("Listing Zoom cloud recordings for {} via the REST API...", "Listando las grabaciones en la nube de Zoom de {} mediante la API REST..."),
+    ("- {} ({}): {} file(s)", "- {} ({}): {} archivo(s)"),
+    ("No Zoom cloud recordings matched course {}.", "Ninguna grabación en la nube de Zoom coincide con el curso {}."),
+    ("Exported Zoom session for course {} ({} cookies, {} headers) to {}", "Sesión de Zoom del curso {} ({} cookies, {} cabeceras) exportada a {}"),
+    ("Imported Zoom session for course {} exported {}", "Sesión de Zoom importada para el curso {}, exportada {}"),
+    ("at an unknown time", "en una fecha desconocida"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Indices of the arguments a template uses.
+    fn placeholders(template: &str) -> Vec<String> {
+        let args: Vec<String> = (0..10).map(|n| format!("<{n}>")).collect();
+        let args: Vec<&dyn Display> = args.iter().map(|a| a as &dyn Display).collect();
+        let mut used: Vec<String> = fill(template, &args)
+            .split('<')
+            .skip(1)
+            .filter_map(|s| s.split_once('>').map(|(n, _)| n.to_string()))
+            .collect();
+        used.sort();
+        used
+    }
+
+    fn unescape(literal: &str) -> String {
+        literal
+            .replace("\\n", "\n")
+            .replace("\\t", "\t")
+            .replace("\\\"", "\"")
+            .replace("\\'", "'")
+            .replace("\\\\", "\\")
+    }
+
+    fn collect_messages(dir: &Path, pattern: &regex::Regex, out: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_messages(&path, pattern, out);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                out.extend(pattern.captures_iter(&source).map(|c| unescape(&c[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn fills_placeholders_in_order_or_by_index() {
+        assert_eq!(fill("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(fill("{1}: {0} {{x}}", &[&"a", &"b"]), "b: a {x}");
+        assert_eq!(fill("{} {}", &[&1]), "1 {}");
+        assert_eq!(
+            fill("[{:<4}|{:>3}|{1:^5}|{:x}]", &[&"ab", &7, &0]),
+            "[ab  |  7|  7  |{:x}]"
+        );
+    }
+
+    #[test]
+    fn every_message_is_translated_with_the_same_placeholders() {
+        let mut seen = std::collections::HashSet::new();
+        for (en, es) in CATALOG {
+            assert!(seen.insert(en), "duplicate catalog entry {en:?}");
+            assert_eq!(placeholders(en), placeholders(es), "placeholders of {en:?}");
+        }
+        let pattern = regex::Regex::new(r#"tr!\(\s*"((?:[^"\\]|\\.)*)""#).unwrap();
+        let mut messages = Vec::new();
+        collect_messages(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &pattern,
+            &mut messages,
+        );
+        for msg in messages {
+            assert!(
+                seen.contains(&msg.as_str()),
+                "{msg:?} is not in the catalog"
+            );
+        }
+    }
+}
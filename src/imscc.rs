@@ -0,0 +1,168 @@
+use crate::canvas::{CanvasClient, CanvasError, ContentExport, Course};
+use crate::config::{Config, SyncSettings};
+use crate::error::Error;
+use crate::progress::progress_bar;
+use crate::state::State;
+use crate::syncer::{download_if_needed, resolve_course_dir};
+use crate::{report, report_warning, tr};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Package file written to each course folder; each export replaces the previous one.
+pub const EXPORT_FILE: &str = "course_export.imscc";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Large courses can take a while to package, but not this long.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Export each active course (or only `course_id`) as a Common Cartridge package
+/// through the content exports API and download it to `<course dir>/course_export.imscc`.
+/// Courses the account may not export are reported and skipped.
+pub async fn run_export(course_id: Option<u64>, dry_run: bool) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let canvas = CanvasClient::from_config().await?;
+    let mut courses = canvas.list_courses().await?;
+    match course_id {
+        Some(id) => courses.retain(|c| c.id == id),
+        None => courses.retain(|c| !cfg.canvas.ignored_courses.contains(&c.id.to_string())),
+    }
+    if courses.is_empty() {
+        report!("{}", tr!("No matching course to export."));
+        return Ok(());
+    }
+
+    let (mut exported, mut failed) = (0usize, Vec::new());
+    for c in &courses {
+        let course_dir = resolve_course_dir(&cfg, c, dry_run).await?;
+        let dest = course_dir.join(EXPORT_FILE);
+        if dry_run {
+            report!(
+                "{}",
+                tr!("DRY-RUN course {} ({}) -> {}", c.id, c.name, dest.display())
+            );
+            continue;
+        }
+        report!("{}", tr!("Exporting course {} ({})...", c.id, c.name));
+        let result = match export_course(&canvas, c).await {
+            Ok(export) => {
+                let state_path = course_dir.join("state.json");
+                let mut state = State::load(&state_path).await;
+                state.course_id = Some(c.id);
+                let file = export
+                    .attachment
+                    .ok_or_else(|| Error::Provider("export finished without a package".into()));
+                match file {
+                    Ok(file) => {
+                        // The package is the course itself; file filters don't apply
+                        let downloaded = download_if_needed(
+                            &canvas.http,
+                            &SyncSettings::default(),
+                            "imscc".into(),
+                            &file,
+                            &dest,
+                            &mut state,
+                            false,
+                        )
+                        .await;
+                        state.save(&state_path).await?;
+                        downloaded.map(|_| ())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                info!(course_id = c.id, path = %dest.display(), "downloaded course export");
+                report!(
+                    "{}",
+                    tr!("✓ Course {} exported to {}", c.id, dest.display())
+                );
+                exported += 1;
+            }
+            Err(e) => {
+                warn!(course_id = c.id, kind = %e.kind(), error = %e, "course export failed");
+                report_warning!("{}", tr!("✗ Course {} ({}): {}", c.id, c.name, e));
+                failed.push(e);
+            }
+        }
+    }
+
+    if !dry_run {
+        report!(
+            "{}",
+            tr!(
+                "IMSCC export: {} course(s) exported, {} failed",
+                exported,
+                failed.len()
+            )
+        );
+    }
+    match (exported, failed.into_iter().next()) {
+        (0, Some(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Start an export of `c` and wait for Canvas to package it.
+async fn export_course(canvas: &CanvasClient, c: &Course) -> Result<ContentExport, Error> {
+    let export = canvas
+        .start_course_export(c.id)
+        .await
+        .map_err(|e| match e {
+            CanvasError::Status(401 | 403, _) => Error::Permission(tr!(
+                "Canvas does not allow this account to export course {}; only teachers can",
+                c.id
+            )),
+            e => e.into(),
+        })?;
+    info!(
+        course_id = c.id,
+        export_id = export.id,
+        "started course export"
+    );
+
+    let bar = progress_bar(100, &tr!("Packaging {}", c.name));
+    let started = Instant::now();
+    let result = async {
+        loop {
+            if started.elapsed() > EXPORT_TIMEOUT {
+                break Err(Error::Network(tr!(
+                    "export of course {} did not finish within {} minutes",
+                    c.id,
+                    EXPORT_TIMEOUT.as_secs() / 60
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if let Some(url) = export.progress_url.as_deref() {
+                let progress = canvas.get_progress(url).await?;
+                bar.set_position(progress.completion.unwrap_or(0.0) as u64);
+                if progress.workflow_state == "failed" {
+                    break Err(Error::Provider(tr!(
+                        "Canvas could not export course {}: {}",
+                        c.id,
+                        progress.message.as_deref().unwrap_or("export failed")
+                    )));
+                }
+                if progress.workflow_state != "completed" {
+                    continue;
+                }
+            }
+            let current = canvas.get_course_export(c.id, export.id).await?;
+            match current.workflow_state.as_str() {
+                "exported" => break Ok(current),
+                "failed" => {
+                    break Err(Error::Provider(tr!(
+                        "Canvas could not export course {}",
+                        c.id
+                    )))
+                }
+                _ => continue,
+            }
+        }
+    }
+    .await;
+    bar.finish_and_clear();
+    result
+}
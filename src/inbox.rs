@@ -0,0 +1,293 @@
+use crate::canvas::{CanvasClient, Conversation};
+use crate::config::Config;
+use crate::error::Error;
+use crate::fsutil::{
+    apply_remote_mtime, atomic_write, ensure_dir, sanitize_component,
+    sanitize_filename_preserve_ext,
+};
+use crate::progress::{progress_bar, spinner};
+use crate::state::{ItemState, State};
+use crate::syncer::download_if_needed;
+use crate::{report, report_warning, tr};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+
+/// Top-level directory under `download_root` that holds Inbox conversations.
+pub const INBOX_DIR: &str = "Inbox";
+
+/// Conversation scopes listed; a thread can show up in more than one.
+const SCOPES: [&str; 3] = ["inbox", "sent", "archived"];
+
+/// Export every Inbox conversation to `<download_root>/Inbox/<id>-<subject>.md`,
+/// with message attachments under `Inbox/Attachments/<id>/`. Threads whose last
+/// message is unchanged since the previous run are not fetched again.
+pub async fn run_inbox_sync(dry_run: bool) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let canvas = CanvasClient::from_config().await?;
+    let inbox_dir = cfg.base_root().join(INBOX_DIR);
+    let state_path = inbox_dir.join("state.json");
+    let mut state = State::load(&state_path).await;
+
+    let list_spinner = spinner("Listing Inbox conversations");
+    let mut threads: BTreeMap<u64, Conversation> = BTreeMap::new();
+    for scope in SCOPES {
+        for c in canvas.list_conversations(scope).await? {
+            threads.entry(c.id).or_insert(c);
+        }
+    }
+    list_spinner.finish_and_clear();
+    if !dry_run {
+        ensure_dir(&inbox_dir).await?;
+    }
+
+    let (mut written, mut unchanged, mut files, mut failed) = (0usize, 0usize, 0usize, 0usize);
+    let progress = progress_bar(threads.len() as u64, "Inbox conversations");
+    for (id, listed) in threads {
+        progress.inc(1);
+        let key = format!("conversation:{}", id);
+        let dest = inbox_dir.join(conversation_file(&listed));
+        if dest.exists()
+            && listed.last_message_at.is_some()
+            && state.get(&key).and_then(|s| s.updated_at.as_deref())
+                == listed.last_message_at.as_deref()
+        {
+            unchanged += 1;
+            continue;
+        }
+        if dry_run {
+            report!(
+                "{}",
+                tr!(
+                    "DRY-RUN conversation {} -> {} ({} message(s))",
+                    id,
+                    dest.display(),
+                    listed.message_count.unwrap_or(0)
+                )
+            );
+            written += 1;
+            continue;
+        }
+        let thread = match canvas.get_conversation(id).await {
+            Ok(thread) => thread,
+            Err(e) => {
+                warn!(conversation_id = id, error = %e, "unable to fetch conversation");
+                report_warning!("{}", tr!("✗ Conversation {}: {}", id, e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        let attachments_dir = inbox_dir.join("Attachments").join(id.to_string());
+        let mut saved: HashMap<u64, String> = HashMap::new();
+        for f in thread.messages.iter().flat_map(|m| &m.attachments) {
+            let name = f
+                .display_name
+                .as_deref()
+                .or(f.filename.as_deref())
+                .map(sanitize_filename_preserve_ext)
+                .unwrap_or_else(|| format!("file_{}", f.id));
+            let path = attachments_dir.join(&name);
+            let file_key = format!("file:{}", f.id);
+            match download_if_needed(
+                &canvas.http,
+                &cfg.sync,
+                file_key,
+                f,
+                &path,
+                &mut state,
+                false,
+            )
+            .await
+            {
+                Ok(bytes) => {
+                    files += usize::from(bytes.is_some());
+                    if path.exists() {
+                        saved.insert(f.id, format!("Attachments/{}/{}", id, name));
+                    }
+                }
+                Err(e) => {
+                    warn!(conversation_id = id, file_id = f.id, error = %e, "attachment download failed");
+                    report_warning!(
+                        "{}",
+                        tr!("✗ Conversation {} attachment {}: {}", id, name, e)
+                    );
+                    failed += 1;
+                }
+            }
+        }
+
+        let md = render_conversation(&thread, &saved);
+        atomic_write(&dest, md.as_bytes()).await?;
+        apply_remote_mtime(&dest, thread.last_message_at.as_deref());
+        state.set(
+            key,
+            ItemState {
+                etag: None,
+                updated_at: listed.last_message_at.clone(),
+                size: Some(md.len() as u64),
+                content_hash: None,
+                last_error: None,
+                error_count: None,
+                last_modified: None,
+                uuid: None,
+                locked: None,
+            },
+        );
+        info!(conversation_id = id, path = %dest.display(), "wrote conversation");
+        written += 1;
+    }
+    progress.finish_and_clear();
+    if !dry_run {
+        state.save(&state_path).await?;
+    }
+
+    let verb = if dry_run {
+        tr!("to write")
+    } else {
+        tr!("written")
+    };
+    report!(
+        "{}",
+        tr!(
+            "Inbox: {} conversation(s) {}, {} unchanged, {} attachment(s) downloaded, {} failed",
+            written,
+            verb,
+            unchanged,
+            files,
+            failed
+        )
+    );
+    Ok(())
+}
+
+/// `<id>-<subject>.md`; the id keeps threads with the same subject apart.
+fn conversation_file(c: &Conversation) -> String {
+    let subject = c
+        .subject
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or("no subject");
+    format!("{}-{}.md", c.id, sanitize_component(subject))
+}
+
+/// Markdown for a thread, oldest message first. `saved` maps attachment ids to
+/// their path relative to the Inbox folder; others are linked by their Canvas URL.
+fn render_conversation(c: &Conversation, saved: &HashMap<u64, String>) -> String {
+    let names: HashMap<u64, &str> = c
+        .participants
+        .iter()
+        .filter_map(|p| Some((p.id, p.name.as_deref()?)))
+        .collect();
+    let mut out = format!("# {}\n\n", c.subject.as_deref().unwrap_or("(no subject)"));
+    if let Some(context) = c.context_name.as_deref() {
+        out.push_str(&format!("- **Course:** {}\n", context));
+    }
+    let people: Vec<&str> = c
+        .participants
+        .iter()
+        .filter_map(|p| p.name.as_deref())
+        .collect();
+    if !people.is_empty() {
+        out.push_str(&format!("- **Participants:** {}\n", people.join(", ")));
+    }
+    for m in c.messages.iter().rev() {
+        let author = m
+            .author_id
+            .and_then(|id| names.get(&id).copied())
+            .unwrap_or("Unknown");
+        out.push_str(&format!(
+            "\n## {} — {}\n\n",
+            author,
+            m.created_at.as_deref().unwrap_or("")
+        ));
+        let body = m.body.as_deref().unwrap_or("").trim();
+        if !body.is_empty() {
+            out.push_str(body);
+            out.push('\n');
+        }
+        if !m.attachments.is_empty() {
+            out.push_str("\nAttachments:\n");
+            for f in &m.attachments {
+                let name = f
+                    .display_name
+                    .as_deref()
+                    .or(f.filename.as_deref())
+                    .unwrap_or("file");
+                let target = saved
+                    .get(&f.id)
+                    .map(|p| p.replace(' ', "%20"))
+                    .or_else(|| f.url.clone());
+                match target {
+                    Some(target) => out.push_str(&format!("- [{}]({})\n", name, target)),
+                    None => out.push_str(&format!("- {}\n", name)),
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{ConversationMessage, ConversationParticipant, FileLock, FileObj};
+
+    #[test]
+    fn renders_messages_oldest_first_with_attachments() {
+        let attachment = FileObj {
+            id: 40,
+            display_name: Some("pauta.pdf".into()),
+            filename: None,
+            size: Some(10),
+            updated_at: None,
+            url: Some("https://canvas.example/files/40/download".into()),
+            download_url: None,
+            uuid: None,
+            lock: FileLock::default(),
+        };
+        let c = Conversation {
+            id: 7,
+            subject: Some("Consulta certamen".into()),
+            last_message_at: None,
+            message_count: Some(2),
+            context_name: Some("Física".into()),
+            participants: vec![
+                ConversationParticipant {
+                    id: 1,
+                    name: Some("Ana".into()),
+                },
+                ConversationParticipant {
+                    id: 2,
+                    name: Some("Prof. Rojas".into()),
+                },
+            ],
+            messages: vec![
+                ConversationMessage {
+                    id: 12,
+                    created_at: Some("2024-04-02T10:00:00Z".into()),
+                    body: Some("Adjunto la pauta.".into()),
+                    author_id: Some(2),
+                    attachments: vec![attachment],
+                },
+                ConversationMessage {
+                    id: 11,
+                    created_at: Some("2024-04-01T09:00:00Z".into()),
+                    body: Some("¿Entra la unidad 3?".into()),
+                    author_id: Some(1),
+                    attachments: vec![],
+                },
+            ],
+        };
+        let saved = HashMap::from([(40, "Attachments/7/pauta.pdf".to_string())]);
+        assert_eq!(conversation_file(&c), "7-Consulta_certamen.md");
+        assert_eq!(
+            render_conversation(&c, &saved),
+            "# Consulta certamen\n\n\
+             - **Course:** Física\n\
+             - **Participants:** Ana, Prof. Rojas\n\
+             \n## Ana — 2024-04-01T09:00:00Z\n\n¿Entra la unidad 3?\n\
+             \n## Prof. Rojas — 2024-04-02T10:00:00Z\n\nAdjunto la pauta.\n\
+             \nAttachments:\n- [pauta.pdf](Attachments/7/pauta.pdf)\n"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::fsutil::atomic_write;
+use crate::state::is_state_file;
+use crate::tr;
+use crate::weeks::{leading_date, parse_week_dir, week_of_date, WEEKS_DIR};
+use crate::zoom::headless::RECORDINGS_DIR;
+use chrono::{DateTime, Local, NaiveDate};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the course index and of each module's index.
+pub const INDEX_FILE: &str = "00-INDEX.md";
+
+/// Top-level directory under `download_root` that holds Zoom recordings.
+const ZOOM_DIR: &str = "Zoom";
+
+/// A heading of the course index and the files listed under it.
+#[derive(Debug, Default)]
+struct Section {
+    title: String,
+    /// Page the heading links to, e.g. the module's own index.
+    link: Option<String>,
+    entries: Vec<Entry>,
+}
+
+/// A file linked from the course index.
+#[derive(Debug)]
+struct Entry {
+    label: String,
+    link: String,
+    /// Modification date, which sync sets to the remote update time.
+    date: Option<String>,
+}
+
+/// Write the index at the top of `course_dir`, linking every synced file by
+/// module (in `module_dirs` order), the other course folders and the course's
+/// Zoom recordings. Returns whether the file changed.
+pub async fn write_course_index(
+    root: &Path,
+    course_dir: &Path,
+    course_id: u64,
+    title: &str,
+    module_dirs: &[PathBuf],
+) -> io::Result<bool> {
+    let md = course_index(root, course_dir, course_id, title, module_dirs)?;
+    let path = course_dir.join(INDEX_FILE);
+    if tokio::fs::read(&path)
+        .await
+        .is_ok_and(|old| old == md.as_bytes())
+    {
+        return Ok(false);
+    }
+    atomic_write(&path, md.as_bytes()).await?;
+    Ok(true)
+}
+
+/// Write an index into each week folder of the course, linking the week's
+/// modules and, when the course start is known, the Zoom recordings made that
+/// week. Returns how many indexes changed.
+pub async fn write_week_indexes(
+    root: &Path,
+    course_dir: &Path,
+    course_id: u64,
+    start: Option<NaiveDate>,
+) -> io::Result<usize> {
+    let mut changed = 0;
+    for dir in subdirs(&course_dir.join(WEEKS_DIR))? {
+        let Some(week) = parse_week_dir(&file_name(&dir)) else {
+            continue;
+        };
+        let mut sections = Vec::new();
+        for module in subdirs(&dir)? {
+            sections.push(module_section(&dir, &module)?);
+        }
+        if let Some(start) = start {
+            let in_week =
+                |date: Option<NaiveDate>| date.and_then(|d| week_of_date(start, d)) == Some(week);
+            if let Some(zoom) = zoom_section(root, course_dir, &dir, course_id, in_week)? {
+                sections.push(zoom);
+            }
+        }
+        sections.retain(|s| s.link.is_some() || !s.entries.is_empty());
+        let md = render_course_index(&tr!("Week {}", week), &sections);
+        let path = dir.join(INDEX_FILE);
+        if tokio::fs::read(&path)
+            .await
+            .is_ok_and(|old| old == md.as_bytes())
+        {
+            continue;
+        }
+        atomic_write(&path, md.as_bytes()).await?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Markdown of the course index; modules not in `module_dirs` follow in folder order.
+pub fn course_index(
+    root: &Path,
+    course_dir: &Path,
+    course_id: u64,
+    title: &str,
+    module_dirs: &[PathBuf],
+) -> io::Result<String> {
+    let sections = course_sections(root, course_dir, course_id, module_dirs)?;
+    Ok(render_course_index(title, &sections))
+}
+
+fn course_sections(
+    root: &Path,
+    course_dir: &Path,
+    course_id: u64,
+    module_dirs: &[PathBuf],
+) -> io::Result<Vec<Section>> {
+    let mut sections = Vec::new();
+    // Modules in Canvas order, then folders of modules no longer in the course
+    let mut modules: Vec<PathBuf> = module_dirs.iter().filter(|d| d.is_dir()).cloned().collect();
+    let mut removed = subdirs(&course_dir.join("Modules"))?;
+    for week in subdirs(&course_dir.join(WEEKS_DIR))? {
+        removed.extend(subdirs(&week)?);
+    }
+    removed.retain(|d| !modules.contains(d));
+    modules.extend(removed);
+    for dir in &modules {
+        sections.push(module_section(course_dir, dir)?);
+    }
+
+    // Other course folders (People, Groups, Inbox...) and loose files
+    let mut loose = Section {
+        title: tr!("Course files"),
+        ..Section::default()
+    };
+    for path in listing(course_dir)? {
+        let name = file_name(&path);
+        if name == "Modules"
+            || name == WEEKS_DIR
+            || name == RECORDINGS_DIR
+            || name == INDEX_FILE
+            || is_state_file(&name)
+        {
+            continue;
+        }
+        if path.is_dir() {
+            sections.push(Section {
+                title: name,
+                link: None,
+                entries: entries(&path, course_dir, "")?,
+            });
+        } else {
+            loose
+                .entries
+                .push(entry(&path, name, link_to(course_dir, &path)));
+        }
+    }
+    if !loose.entries.is_empty() {
+        sections.push(loose);
+    }
+
+    if let Some(zoom) = zoom_section(root, course_dir, course_dir, course_id, |_| true)? {
+        sections.push(zoom);
+    }
+    sections.retain(|s| s.link.is_some() || !s.entries.is_empty());
+    Ok(sections)
+}
+
+/// A module's files under a heading with its title, linking its own index.
+fn module_section(base: &Path, dir: &Path) -> io::Result<Section> {
+    let index = dir.join(INDEX_FILE);
+    let title = std::fs::read_to_string(&index)
+        .ok()
+        .and_then(|md| md.lines().next()?.strip_prefix("# ").map(str::to_string))
+        .unwrap_or_else(|| file_name(dir));
+    Ok(Section {
+        title,
+        link: index.exists().then(|| link_to(base, &index)),
+        entries: entries(dir, base, "")?,
+    })
+}
+
+/// The course's Zoom recordings whose meeting date `keep` accepts, linked from an
+/// index in `base`. Recordings are in the course's `Recordings` folder, or under
+/// `Zoom/<course id>` when downloaded before the course was synced.
+fn zoom_section(
+    root: &Path,
+    course_dir: &Path,
+    base: &Path,
+    course_id: u64,
+    keep: impl Fn(Option<NaiveDate>) -> bool,
+) -> io::Result<Option<Section>> {
+    let Some(dir) = [
+        course_dir.join(RECORDINGS_DIR),
+        root.join(ZOOM_DIR).join(course_id.to_string()),
+    ]
+    .into_iter()
+    .find(|d| d.is_dir()) else {
+        return Ok(None);
+    };
+    let mut zoom_entries = entries(&dir, base, "")?;
+    // Meeting folders start with the meeting date
+    zoom_entries.retain(|e| keep(e.label.split('/').find_map(leading_date)));
+    Ok(Some(Section {
+        title: tr!("Zoom recordings"),
+        link: None,
+        entries: zoom_entries,
+    }))
+}
+
+/// Files under `dir`, recursively and sorted, labelled relative to `dir`.
+fn entries(dir: &Path, course_dir: &Path, prefix: &str) -> io::Result<Vec<Entry>> {
+    let mut out = Vec::new();
+    for path in listing(dir)? {
+        let label = format!("{}{}", prefix, file_name(&path));
+        if path.is_dir() {
+            out.extend(entries(&path, course_dir, &format!("{}/", label))?);
+        } else if label != INDEX_FILE {
+            out.push(entry(&path, label, link_to(course_dir, &path)));
+        }
+    }
+    Ok(out)
+}
+
+fn entry(path: &Path, label: String, link: String) -> Entry {
+    let date = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(|t| DateTime::<Local>::from(t).format("%Y-%m-%d").to_string());
+    Entry { label, link, date }
+}
+
+/// Entries of `dir` sorted by name, without hidden and partially downloaded files.
+fn listing(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = file_name(&path);
+        if name.starts_with('.') || name.ends_with(".part") {
+            continue;
+        }
+        out.push(path);
+    }
+    out.sort();
+    Ok(out)
+}
+
+fn subdirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    match listing(dir) {
+        Ok(paths) => Ok(paths.into_iter().filter(|p| p.is_dir()).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Relative link from a page in `base` to `path`, leaving `base` when needed.
+fn link_to(base: &Path, path: &Path) -> String {
+    let from: Vec<_> = base.components().collect();
+    let to: Vec<_> = path.components().collect();
+    let shared = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec!["..".to_string(); from.len() - shared];
+    parts.extend(
+        to[shared..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+fn render_course_index(title: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {}\n", title);
+    for s in sections {
+        match &s.link {
+            Some(link) => out.push_str(&format!("\n## [{}](<{}>)\n\n", s.title, link)),
+            None => out.push_str(&format!("\n## {}\n\n", s.title)),
+        }
+        for e in &s.entries {
+            match &e.date {
+                Some(date) => out.push_str(&format!("- [{}](<{}>) · {}\n", e.label, e.link, date)),
+                None => out.push_str(&format!("- [{}](<{}>)\n", e.label, e.link)),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::parse_timestamp;
+
+    #[tokio::test]
+    async fn course_index_links_modules_folders_and_recordings() {
+        let root = tempfile::tempdir().unwrap();
+        let course = root.path().join("Calculo_I");
+        let module = course.join("Modules").join("12_Unidad_1");
+        let zoom = root
+            .path()
+            .join("Zoom")
+            .join("101")
+            .join("2024-03-01 - Clase 3");
+        for dir in [&module.join("Attachments"), &course.join("People"), &zoom] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let noon = parse_timestamp("2024-03-01T12:00:00Z").unwrap();
+        for (path, body) in [
+            (module.join(INDEX_FILE), "# Unidad 1\n"),
+            (module.join("01-Intro.md"), "intro"),
+            (module.join("Attachments").join("guia.pdf"), "pdf"),
+            (course.join("People").join("roster.csv"), "csv"),
+            (course.join("calendar.ics"), "ics"),
+            (course.join("state.json"), "{}"),
+            (zoom.join("clase.mp4"), "mp4"),
+        ] {
+            std::fs::write(&path, body).unwrap();
+            crate::fsutil::set_mtime(&path, noon).unwrap();
+        }
+
+        let modules = [module];
+        assert!(
+            write_course_index(root.path(), &course, 101, "Cálculo I", &modules)
+                .await
+                .unwrap()
+        );
+        let md = std::fs::read_to_string(course.join(INDEX_FILE)).unwrap();
+        assert_eq!(
+            md,
+            "# Cálculo I\n\
+             \n## [Unidad 1](<Modules/12_Unidad_1/00-INDEX.md>)\n\n\
+             - [01-Intro.md](<Modules/12_Unidad_1/01-Intro.md>) · 2024-03-01\n\
+             - [Attachments/guia.pdf](<Modules/12_Unidad_1/Attachments/guia.pdf>) · 2024-03-01\n\
+             \n## People\n\n\
+             - [roster.csv](<People/roster.csv>) · 2024-03-01\n\
+             \n## Course files\n\n\
+             - [calendar.ics](<calendar.ics>) · 2024-03-01\n\
+             \n## Zoom recordings\n\n\
+             - [2024-03-01 - Clase 3/clase.mp4](<../Zoom/101/2024-03-01 - Clase 3/clase.mp4>) · 2024-03-01\n"
+        );
+        // Unchanged archive, unchanged index
+        assert!(
+            !write_course_index(root.path(), &course, 101, "Cálculo I", &modules)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn week_index_links_modules_and_recordings_of_the_week() {
+        let root = tempfile::tempdir().unwrap();
+        let course = root.path().join("Calculo_I");
+        let week = course.join(WEEKS_DIR).join("Week_02");
+        let module = week.join("12_Semana_2");
+        let zoom = course.join(RECORDINGS_DIR);
+        let meetings = [
+            zoom.join("Week_02").join("2024-02-14 - Clase 3"),
+            zoom.join("2024-02-21 - Clase 5"),
+        ];
+        std::fs::create_dir_all(&module).unwrap();
+        std::fs::write(module.join("01-Derivadas.md"), "md").unwrap();
+        for meeting in &meetings {
+            std::fs::create_dir_all(meeting).unwrap();
+            std::fs::write(meeting.join("clase.mp4"), "mp4").unwrap();
+        }
+
+        let start = NaiveDate::from_ymd_opt(2024, 2, 5);
+        assert_eq!(
+            write_week_indexes(root.path(), &course, 101, start)
+                .await
+                .unwrap(),
+            1
+        );
+        let md = std::fs::read_to_string(week.join(INDEX_FILE)).unwrap();
+        assert!(md.starts_with("# Week 2\n\n## 12_Semana_2\n"));
+        assert!(md.contains("- [01-Derivadas.md](<12_Semana_2/01-Derivadas.md>)"));
+        assert!(md.contains("(<../../Recordings/Week_02/2024-02-14 - Clase 3/clase.mp4>)"));
+        assert!(!md.contains("Clase 5"));
+
+        // The course index lists the week's module and the recordings, not their folders
+        let index = course_index(root.path(), &course, 101, "Cálculo I", &[]).unwrap();
+        assert!(index.contains("## 12_Semana_2\n"));
+        assert!(index.contains(
+            "- [2024-02-21 - Clase 5/clase.mp4](<Recordings/2024-02-21 - Clase 5/clase.mp4>)"
+        ));
+        assert!(!index.contains("## Weeks") && !index.contains("## Recordings"));
+    }
+}
@@ -1,11 +1,139 @@
+//! Canvas, Moodle and Zoom course backup library behind the `u_crawler` CLI.
+//!
+//! Each CLI command is a `run_*` function in its module, so GUIs and schedulers can
+//! embed the crawler instead of shelling out. They read the same config file as the
+//! CLI ([`Config::load_or_init`]) and print progress to the terminal; lower-level
+//! pieces such as [`CanvasClient`] and the [`CourseProvider`] trait are public for
+//! callers that need finer control.
+//!
+//! ```no_run
+//! use u_crawler::{run_sync, SyncOptions};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Incremental backup of the courses tagged "math"
+//! run_sync(SyncOptions {
+//!     tags: vec!["math".into()],
+//!     ..Default::default()
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+/// Seed sync state from folders downloaded by hand.
+pub mod adopt;
+/// Check exported Markdown for dead local and remote links.
+pub mod audit;
+/// Store Canvas and Moodle credentials in the config.
+pub mod auth;
+/// iCalendar export of course events and assignment due dates.
+pub mod calendar;
+/// Canvas REST API client and its data model.
 pub mod canvas;
+/// Shell completions and man page generated from the CLI definition.
+pub mod cli_docs;
+/// Config file loading, defaults and paths.
 pub mod config;
+/// Interactive course selection.
+pub mod courses;
+/// Scheduled syncs with an OpenMetrics endpoint.
+pub mod daemon;
+/// Full-screen progress view for `sync --tui`.
+pub mod dashboard;
+/// Environment diagnostics behind `u_crawler doctor`.
+pub mod doctor;
+/// Error categories and exit codes shared by the sync engine and Zoom flows.
+pub mod error;
+/// YouTube/Vimeo archiving through yt-dlp.
+pub mod external_video;
+/// ffmpeg invocation for streams, metadata and transcoding.
 pub mod ffmpeg;
+/// Record and replay of Canvas API responses for debugging.
+pub mod fixtures;
+/// YAML front matter of generated Markdown.
+pub mod front_matter;
+/// Filesystem helpers: safe names, atomic writes, free space.
 pub mod fsutil;
+/// Git history of the archive's text content (`storage.git`).
+pub mod git;
+/// Bulk course metadata through the Canvas GraphQL API.
+pub mod graphql;
+/// Group workspaces synced like courses.
+pub mod groups;
+/// History of sync runs.
+pub mod history;
+/// Shared HTTP client with pacing, retries and resumable downloads.
 pub mod http;
+/// Message catalog for English and Spanish output.
+pub mod i18n;
+/// Common Cartridge course packages from the content exports API.
+pub mod imscc;
+/// Backup of Canvas Inbox conversations.
+pub mod inbox;
+/// Browsable Markdown indexes of course folders.
+pub mod index;
+/// Tracing setup for the console and log file.
 pub mod logger;
+/// Signed archive manifests shared between classmates.
+pub mod manifest;
+/// Videos embedded in course HTML (Canvas media, Kaltura, Studio, YouTube, Vimeo).
+pub mod media;
+/// Moodle web service client implementing [`CourseProvider`].
+pub mod moodle;
+/// Console output levels and the `report!` macros.
+pub mod output;
+/// Panopto recordings reached through the course's LTI.
+pub mod panopto;
+/// Dry-run plans of what a sync would write, and their comparison.
+pub mod plan;
+/// Progress bars and spinners.
 pub mod progress;
+/// The LMS abstraction the sync engine runs on.
+pub mod provider;
+/// Markdown export of Canvas quizzes.
+pub mod quizzes;
+/// Zoom recording discovery across courses.
 pub mod recordings;
+/// Masking of tokens, cookies and passwords in console and log output.
+pub mod redact;
+/// Course roster and group membership export.
+pub mod roster;
+/// Markdown tables for assignment rubrics.
+pub mod rubrics;
+/// Course and module listing.
+pub mod scan;
+/// Full-text search over the synced archive.
+pub mod search;
+/// Google Drive, OneDrive and SharePoint document links.
+pub mod shared_files;
+/// SharePoint and Microsoft Stream videos.
+pub mod sharepoint;
+/// Ctrl-C and SIGTERM handling that lets syncs stop cleanly.
+pub mod shutdown;
+/// Static HTML site export of the archive.
+pub mod site;
+/// MHTML snapshots of linked web pages.
+pub mod snapshot;
+/// Per-course sync state.
 pub mod state;
+/// Disk usage and download volume reports.
+pub mod stats;
+/// Backup status per course.
+pub mod status;
+/// Content-addressed store shared by course folders.
+pub mod store;
+/// The incremental sync engine.
 pub mod syncer;
+/// Week folders derived from module names and recording dates.
+pub mod weeks;
+/// Zoom cloud recordings: session capture, listing and downloads.
 pub mod zoom;
+
+pub use canvas::{CanvasClient, CanvasError};
+pub use config::{Config, ConfigError, ConfigPaths};
+pub use error::{Error, ErrorKind};
+pub use moodle::{MoodleClient, MoodleError};
+pub use provider::CourseProvider;
+pub use state::State;
+pub use syncer::{run_sync, SyncOptions};
+pub use zoom::zoom_flow;
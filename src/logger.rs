@@ -1,43 +1,297 @@
-use crate::config::{Config, ConfigPaths};
-use std::fs::{File, OpenOptions};
-use std::path::PathBuf;
+use crate::config::{ConfigPaths, LogRotation, Logging};
+use crate::dashboard::{self, ErrorPanelLayer};
+use crate::output::{self, Verbosity};
+use crate::redact::RedactingWriter;
+use crate::tr;
+use crate::Config;
+use console::Term;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::{NonBlockingBuilder, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{filter_fn, FilterExt, LevelFilter};
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 
-pub fn init_logging(cfg: Option<&Config>) {
-    let (level, file_path) = if let Some(c) = cfg {
-        (c.logging.level.clone(), PathBuf::from(&c.logging.file))
-    } else {
-        // Fallback to default path inside config dir
-        let paths = ConfigPaths::new().ok();
-        let p = paths
-            .as_ref()
-            .map(|p| p.config_dir.join("u_crawler.log"))
-            .unwrap_or_else(|| PathBuf::from("u_crawler.log"));
-        ("info".to_string(), p)
-    };
+/// Flushes the log file and the exported spans when dropped.
+pub struct LogGuard {
+    _file: WorkerGuard,
+    tracer: Option<SdkTracerProvider>,
+}
 
-    // Ensure parent dir exists
-    if let Some(parent) = file_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer.take() {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "unable to export the remaining spans");
+            }
+        }
     }
+}
 
-    // Open file in append mode
-    let file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&file_path)
-        .unwrap_or_else(|_| File::create(&file_path).expect("create log file"));
+/// Install the file and console loggers, and the span exporter when
+/// `logging.otlp_endpoint` is set. Keep the returned guard alive for the life of
+/// the process; dropping it writes out the log lines and spans still buffered.
+pub fn init_logging(cfg: Option<&Config>) -> LogGuard {
+    let logging = match cfg {
+        Some(c) => c.logging.clone(),
+        None => {
+            // Fallback to default path inside config dir
+            let file = ConfigPaths::new()
+                .map(|p| p.config_dir.join("u_crawler.log"))
+                .unwrap_or_else(|_| PathBuf::from("u_crawler.log"));
+            Logging {
+                file: file.to_string_lossy().into_owned(),
+                ..Logging::default()
+            }
+        }
+    };
+
+    // -q / -v on the command line take precedence over the configured levels
+    let verbosity = output::verbosity();
+    let level = verbosity
+        .log_level()
+        .map(str::to_string)
+        .unwrap_or(logging.level.clone());
+    let console_level = match verbosity {
+        Verbosity::Quiet => "off".to_string(),
+        Verbosity::Normal => logging.console.clone(),
+        _ => level.clone(),
+    };
 
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file);
-    // Leak guard so it lives for process lifetime
-    Box::leak(Box::new(_guard));
+    // Block instead of dropping lines when the writer falls behind
+    let (non_blocking, guard) = NonBlockingBuilder::default()
+        .lossy(false)
+        .finish(RedactingWriter(log_writer(&logging)));
 
-    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
-    fmt()
-        .with_env_filter(filter)
+    let file_filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let file_layer = fmt::layer()
         .with_writer(non_blocking)
         .with_ansi(false)
         .with_target(true)
-        .with_level(true)
-        .init();
+        .with_level(true);
+    let file_layer = if logging.format == "json" {
+        file_layer
+            .json()
+            .flatten_event(true)
+            .with_filter(file_filter)
+            .boxed()
+    } else {
+        file_layer.with_filter(file_filter).boxed()
+    };
+
+    let tty = Term::stderr().is_term();
+    // Keep the console quiet while the dashboard owns the screen; it shows warnings itself
+    let console_filter = EnvFilter::try_new(console_level)
+        .unwrap_or_else(|_| EnvFilter::new("off"))
+        .and(filter_fn(|_| dashboard::active().is_none()));
+    let console_layer = fmt::layer()
+        .compact()
+        .with_writer(|| RedactingWriter(io::stderr()))
+        .with_ansi(tty)
+        .with_target(!tty)
+        .with_filter(console_filter);
+
+    let tracer = otlp_tracer(&logging.otlp_endpoint);
+    // Only our own spans; the exporter's HTTP client would otherwise trace itself
+    let otel_layer = tracer.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("u_crawler"))
+            .with_filter(
+                LevelFilter::INFO.and(filter_fn(|meta| meta.target().starts_with("u_crawler"))),
+            )
+    });
+
+    // A subscriber may already be installed (e.g. when embedded); keep the existing one
+    let _ = tracing_subscriber::registry()
+        .with(file_layer)
+        .with(console_layer)
+        .with(ErrorPanelLayer.with_filter(LevelFilter::WARN))
+        .with(otel_layer)
+        .try_init();
+    LogGuard {
+        _file: guard,
+        tracer,
+    }
+}
+
+/// Span exporter batching to the OTLP/HTTP collector at `endpoint`, if one is set.
+fn otlp_tracer(endpoint: &str) -> Option<SdkTracerProvider> {
+    let endpoint = endpoint.trim();
+    if endpoint.is_empty() {
+        return None;
+    }
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url(endpoint))
+        .build();
+    match exporter {
+        Ok(exporter) => Some(
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(Resource::builder().with_service_name("u_crawler").build())
+                .build(),
+        ),
+        Err(e) => {
+            eprintln!("{}", tr!("u_crawler: spans will not be exported: {}", e));
+            None
+        }
+    }
+}
+
+/// The collector's trace endpoint; a bare `http://host:4318` gets `/v1/traces`.
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+/// Writer for `logging.file`, rotated as configured.
+fn log_writer(logging: &Logging) -> Box<dyn Write + Send> {
+    let path = PathBuf::from(&logging.file);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let keep = logging.max_files.max(1);
+    let period = match logging.rotation().unwrap_or(LogRotation::Never) {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Size(max_bytes) => {
+            if let Ok(file) = SizeRotatingFile::open(&path, max_bytes, keep - 1) {
+                return Box::new(file);
+            }
+            Rotation::NEVER
+        }
+        LogRotation::Never => Rotation::NEVER,
+    };
+    if period != Rotation::NEVER {
+        // Dated files such as u_crawler.2024-03-01.log next to the configured path
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut builder = RollingFileAppender::builder()
+            .rotation(period)
+            .max_log_files(keep);
+        if let Some(stem) = path.file_stem() {
+            builder = builder.filename_prefix(stem.to_string_lossy());
+        }
+        if let Some(ext) = path.extension() {
+            builder = builder.filename_suffix(ext.to_string_lossy());
+        }
+        if let Ok(appender) = builder.build(dir) {
+            return Box::new(appender);
+        }
+    }
+    Box::new(append(&path).unwrap_or_else(|_| File::create(&path).expect("create log file")))
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Log file that moves to `<file>.1` once it reaches `max_bytes`, shifting older
+/// files up to `<file>.<keep>` and deleting the one beyond.
+struct SizeRotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl SizeRotatingFile {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = append(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            written,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn numbered(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep > 0 {
+            let _ = fs::remove_file(self.numbered(self.keep));
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(self.numbered(n), self.numbered(n + 1));
+            }
+            fs::rename(&self.path, self.numbered(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Each call is one formatted event, so lines are never split across files
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_rotation_keeps_the_newest_files() {
+        let dir = std::env::temp_dir().join("u_crawler_log_rotation");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("u_crawler.log");
+
+        let mut log = SizeRotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+        log.flush().unwrap();
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(dir.join("u_crawler.log.1")), "third\n");
+        assert_eq!(read(dir.join("u_crawler.log.2")), "second\n");
+        assert!(!dir.join("u_crawler.log.3").exists());
+    }
+
+    #[test]
+    fn otlp_endpoint_gets_the_traces_path() {
+        assert_eq!(
+            traces_url("http://localhost:4318/"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("https://tempo.example/v1/traces"),
+            "https://tempo.example/v1/traces"
+        );
+    }
 }
@@ -1,16 +1,34 @@
-use u_crawler::canvas;
+use u_crawler::adopt;
+use u_crawler::audit;
+use u_crawler::auth;
+use u_crawler::cli_docs;
 use u_crawler::config;
+use u_crawler::courses;
+use u_crawler::daemon;
+use u_crawler::doctor;
+use u_crawler::fixtures;
+use u_crawler::i18n;
+use u_crawler::imscc;
+use u_crawler::inbox;
 use u_crawler::logger;
-use u_crawler::progress;
+use u_crawler::manifest;
+use u_crawler::output;
+use u_crawler::panopto;
 use u_crawler::recordings;
-use u_crawler::state::State;
+use u_crawler::scan;
+use u_crawler::search;
+use u_crawler::sharepoint;
+use u_crawler::site;
+use u_crawler::state;
+use u_crawler::stats;
+use u_crawler::status;
 use u_crawler::syncer;
 use u_crawler::zoom;
 
-use clap::{ArgGroup, Parser, Subcommand};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
 use config::{load_config_from_path, save_config_to_path, Config, ConfigError, ConfigPaths};
-use progress::progress_bar;
 use std::process::ExitCode;
+use u_crawler::{tr, Error};
 
 /// u_crawler — Canvas/Zoom course backup CLI
 #[derive(Parser, Debug)]
@@ -23,6 +41,30 @@ use std::process::ExitCode;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Save sanitized Canvas API responses to DIR (for bug reports)
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        conflicts_with = "replay_fixtures"
+    )]
+    record_fixtures: Option<std::path::PathBuf>,
+    /// Answer Canvas API requests from responses saved with --record-fixtures
+    #[arg(long, global = true, value_name = "DIR")]
+    replay_fixtures: Option<std::path::PathBuf>,
+    /// Config file to use instead of the default (also read from U_CRAWLER_CONFIG);
+    /// history and Zoom state are kept next to it
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+    /// Download everything into PATH instead of the config's download roots
+    #[arg(long, global = true, value_name = "PATH")]
+    download_root: Option<std::path::PathBuf>,
+    /// Only print warnings, errors and results; no progress bars
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print more details (-v), with debug logging; -vv also enables trace logging
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
 }
 
 #[derive(Subcommand, Debug)]
@@ -37,6 +79,12 @@ enum Commands {
         /// Optional course id to filter
         #[arg(long)]
         course_id: Option<u64>,
+        /// Look up every page, assignment and file (without downloading) for an inventory with sizes
+        #[arg(long)]
+        deep: bool,
+        /// Write the deep scan inventory to this file (CSV if it ends in .csv, JSON otherwise)
+        #[arg(long, requires = "deep")]
+        output: Option<std::path::PathBuf>,
     },
     /// Incremental download of Canvas files and Zoom recordings
     Sync {
@@ -46,9 +94,64 @@ enum Commands {
         /// Do not write files or state; show planned actions
         #[arg(long)]
         dry_run: bool,
-        /// Print extra info (e.g., skipped items)
+        /// Only sync courses with this tag (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Sync the Moodle site configured with `auth moodle` instead of Canvas
+        #[arg(long)]
+        moodle: bool,
+        /// Full-screen dashboard with course progress, downloads and errors
+        #[arg(long)]
+        tui: bool,
+        /// Stop at the first failed item instead of recording it and continuing
+        #[arg(long)]
+        fail_fast: bool,
+        /// Only sync these kinds of content (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        only: Vec<syncer::ContentKind>,
+        /// Leave out these kinds of content (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        skip: Vec<syncer::ContentKind>,
+        /// Only download files with these extensions, e.g. `pdf,pptx` (overrides sync.include_ext)
+        #[arg(long, value_delimiter = ',', value_name = "EXTS")]
+        include_ext: Vec<String>,
+        /// Never download files with these extensions, e.g. `zip` (overrides sync.exclude_ext)
+        #[arg(long, value_delimiter = ',', value_name = "EXTS")]
+        exclude_ext: Vec<String>,
+        /// Skip files larger than SIZE, e.g. `500MiB` (overrides sync.max_file_size)
+        #[arg(long, value_name = "SIZE")]
+        max_file_size: Option<String>,
+        /// Skip pages and files not updated since DATE (YYYY-MM-DD), or since each
+        /// course's last sync with `last`
+        #[arg(long, value_name = "DATE")]
+        since: Option<syncer::Since>,
+        /// Also sync concluded courses (adds `completed` to courses.state)
+        #[arg(long)]
+        include_concluded: bool,
+        /// Only sync courses in this enrollment term, by name or id (repeatable;
+        /// overrides courses.terms)
+        #[arg(long = "term", value_name = "TERM")]
+        terms: Vec<String>,
+        /// Write the dry-run plan here (default: plan.json in the config directory)
+        #[arg(long, value_name = "PATH", requires = "dry_run")]
+        plan_out: Option<std::path::PathBuf>,
+        /// Compare the dry-run plan with an earlier plan file and list the differences
+        #[arg(long, value_name = "PATH", requires = "dry_run")]
+        plan_compare: Option<std::path::PathBuf>,
+    },
+    /// Seed sync state from an existing, manually downloaded folder
+    Adopt {
+        /// Canvas course id the folder belongs to
+        #[arg(long)]
+        course_id: u64,
+        /// Folder with previously downloaded course files
+        path: std::path::PathBuf,
+        /// Move matched files into the archive instead of copying them
+        #[arg(long = "move")]
+        move_files: bool,
+        /// Only list files that would be adopted
         #[arg(long)]
-        verbose: bool,
+        dry_run: bool,
     },
     /// Only process and download Zoom recordings
     Recordings {
@@ -59,25 +162,184 @@ enum Commands {
         #[arg(long)]
         dry_run: bool,
     },
+    /// Download a course's Panopto recordings
+    Panopto {
+        /// Canvas course id
+        #[arg(long)]
+        course_id: u64,
+        /// Do not download; only list the sessions found
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Download SharePoint / Microsoft Stream videos linked in a course
+    Sharepoint {
+        /// Canvas course id
+        #[arg(long)]
+        course_id: u64,
+        /// Do not download; only list the video links found
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Manage course tags used for filtering and grouping
+    #[command(subcommand)]
+    Courses(CoursesCommands),
+    /// Exchange signed archive manifests with classmates
+    #[command(subcommand)]
+    Manifest(ManifestCommands),
+    /// Back up Canvas Inbox conversations
+    #[command(subcommand)]
+    Inbox(InboxCommands),
     /// Advanced Zoom operations (CDP, listing, downloads)
     Zoom {
         #[command(subcommand)]
         command: ZoomCommands,
     },
-    /// Show last run, pending items, failed jobs
-    Status {
-        /// Show detailed information including failed items
+    /// Print a shell completion script, e.g. `u_crawler completions bash > /etc/bash_completion.d/u_crawler`
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page in roff format, e.g. `u_crawler man > u_crawler.1`
+    Man {
+        /// Write u_crawler.1 and one page per subcommand into DIR instead
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+    /// Check config, Canvas token, ffmpeg, Chromium, download root, Zoom DB and clock
+    Doctor,
+    /// Show last run, pending items, failed jobs (failed items with -v)
+    Status,
+    /// Keep running and sync on a schedule, optionally serving Prometheus metrics
+    Daemon {
+        /// Time between syncs, e.g. 30m, 6h or 1d
+        #[arg(long, default_value = "6h", value_parser = daemon::parse_interval)]
+        interval: std::time::Duration,
+        /// Serve OpenMetrics at http://ADDR/metrics, e.g. 127.0.0.1:9464
+        #[arg(long, value_name = "ADDR")]
+        metrics_addr: Option<std::net::SocketAddr>,
+    },
+    /// Summarize disk usage, file types, largest files, and recent download volume
+    Stats {
+        /// Number of recent sync runs to include
+        #[arg(long, default_value = "10")]
+        runs: usize,
+        /// Number of largest files to list
+        #[arg(long, default_value = "10")]
+        top: usize,
+    },
+    /// Download each course as a Common Cartridge (.imscc) package from Canvas
+    ExportImscc {
+        /// Export only this course
         #[arg(long)]
-        verbose: bool,
+        course_id: Option<u64>,
+        /// Only list the courses that would be exported
+        #[arg(long)]
+        dry_run: bool,
     },
+    /// Render the archive as a static HTML site that can be browsed without u_crawler
+    Export {
+        /// Folder to write the site to; open its index.html in a browser
+        #[arg(long, value_name = "DIR")]
+        site: std::path::PathBuf,
+    },
+    /// Search synced pages, assignments and Zoom transcripts
+    Search {
+        /// Words to look for; accents and case are ignored
+        query: String,
+        /// Maximum number of results
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Check the local archive for broken links and missing content
+    #[command(subcommand)]
+    Audit(AuditCommands),
+    /// Inspect and restore the per-course sync state files
+    #[command(subcommand)]
+    State(StateCommands),
     /// Verify checksums, remove .part leftovers
     Clean,
 }
 
+#[derive(Subcommand, Debug)]
+enum StateCommands {
+    /// Restore corrupt state.json files from their backups
+    Repair,
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditCommands {
+    /// Report relative links in generated Markdown/HTML whose targets are missing
+    Links {
+        /// Only audit a specific course by id
+        #[arg(long)]
+        course_id: Option<u64>,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum AuthCommands {
     /// Configure Canvas Personal Access Token
     Canvas(CanvasAuthArgs),
+    /// Configure a Moodle site and web service token
+    Moodle(MoodleAuthArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum CoursesCommands {
+    /// Add tags to a course, e.g. `courses tag 12345 math elective`
+    Tag {
+        course_id: u64,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a course
+    Untag {
+        course_id: u64,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// List tagged courses
+    Tags,
+    /// Choose which active courses to sync from a checklist; the rest are ignored
+    Pick {
+        /// Pick Moodle courses instead of Canvas ones
+        #[arg(long)]
+        moodle: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum InboxCommands {
+    /// Export conversations and their attachments to <download_root>/Inbox
+    Sync {
+        /// Only list the conversations that would be written
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ManifestCommands {
+    /// Hash the local archive and write a signed manifest
+    Publish {
+        /// Output path (default: <download_root>/manifest.json)
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Import files missing locally from a peer's shared folder, verifying hashes
+    Merge {
+        /// Path to the peer's manifest.json
+        manifest: std::path::PathBuf,
+        /// Peer archive folder holding the files (default: the manifest's folder)
+        #[arg(long)]
+        files_root: Option<std::path::PathBuf>,
+        /// Accept manifests without a valid signature
+        #[arg(long)]
+        allow_unsigned: bool,
+        /// Only list files that would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -91,6 +353,97 @@ enum ZoomCommands {
         #[arg(long)]
         since: Option<String>,
     },
+    /// Download selected meetings from the cached listing
+    #[command(name = "download")]
+    Download {
+        #[arg(long)]
+        course_id: u64,
+        /// Meeting to download (repeatable)
+        #[arg(long = "meeting-id")]
+        meeting_ids: Vec<String>,
+        /// Only meetings on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only meetings on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<String>,
+        /// Only meetings whose topic matches this regex
+        #[arg(long)]
+        topic_regex: Option<String>,
+        #[arg(long, default_value = "1")]
+        concurrency: usize,
+    },
+    /// List cached meetings and recording files without contacting Zoom
+    #[command(name = "list")]
+    List {
+        #[arg(long)]
+        course_id: u64,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Move a captured Zoom session between machines as an encrypted file
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+    /// Download recordings behind share links found by `recordings`
+    #[command(name = "from-links")]
+    FromLinks {
+        #[arg(long)]
+        course_id: u64,
+        #[arg(long, default_value = "1")]
+        concurrency: usize,
+    },
+    /// Download recordings from Zoom share links that are not in the course listing
+    #[command(name = "share-link")]
+    ShareLink {
+        /// Zoom recording URLs (https://*.zoom.us/rec/share/... or /rec/play/...)
+        #[arg(required = true)]
+        urls: Vec<String>,
+        /// Course whose recordings folder and Zoom session are used
+        #[arg(long)]
+        course_id: u64,
+        /// Passcode for protected recordings
+        #[arg(long)]
+        passcode: Option<String>,
+        #[arg(long, default_value = "1")]
+        concurrency: usize,
+    },
+    /// Re-validate the stored Zoom session and renew it if invalid or about to expire
+    Refresh {
+        #[arg(long)]
+        course_id: u64,
+        /// Relaunch the LTI even if the session is still valid
+        #[arg(long)]
+        force: bool,
+    },
+    /// Capture Zoom credentials from a running Chrome started with --remote-debugging-port
+    #[command(name = "sniff-cdp")]
+    SniffCdp {
+        #[arg(long)]
+        course_id: u64,
+        /// Chrome remote debugging port
+        #[arg(long, default_value = "9222")]
+        debug_port: u16,
+        /// Leave the capture tab open afterwards
+        #[arg(long)]
+        keep_tab: bool,
+    },
+}
+
+/// Passphrase is read from U_CRAWLER_SESSION_PASSPHRASE
+#[derive(Subcommand, Debug)]
+enum SessionCommands {
+    /// Write the stored session for a course to an encrypted file
+    Export {
+        #[arg(long)]
+        course_id: u64,
+        #[arg(long)]
+        out: std::path::PathBuf,
+    },
+    /// Replace the stored session with one from an encrypted file
+    Import { path: std::path::PathBuf },
 }
 
 #[derive(Parser, Debug)]
@@ -111,17 +464,51 @@ struct CanvasAuthArgs {
     token_cmd: Option<String>,
 }
 
+#[derive(clap::Args, Debug)]
+#[command(group(
+    ArgGroup::new("moodle_token_src")
+        .required(true)
+        .args(["token", "token_cmd"])
+))]
+struct MoodleAuthArgs {
+    /// Moodle site URL, e.g. https://moodle.example.edu
+    #[arg(long)]
+    base_url: String,
+    /// Web service token (Preferences > Security keys, "Moodle mobile web service")
+    #[arg(long)]
+    token: Option<String>,
+    /// Command to retrieve token (e.g., `pass show moodle/token`)
+    #[arg(long)]
+    token_cmd: Option<String>,
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     let cli = Cli::parse();
+    config::set_overrides(config::Overrides {
+        config_file: cli.config.clone(),
+        download_root: cli.download_root.clone(),
+    });
+    let verbosity = output::Verbosity::from_flags(cli.quiet, cli.verbose);
+    output::set_verbosity(verbosity);
+    let verbose = verbosity >= output::Verbosity::Verbose;
 
     // Attempt to init logging from config before executing command.
     // If config missing, fall back to defaults.
     // Attempt to init logging from config before executing command.
     // We use load_or_init but ignore errors (logging fallback)
-    {
+    // Completions and the man page come from the CLI definition alone, so packagers
+    // can generate them without a config file
+    let generated = matches!(
+        cli.command,
+        Commands::Completions { .. } | Commands::Man { .. }
+    );
+    let _log_guard = (!generated).then(|| {
         match config::Config::load_or_init() {
-            Ok(cfg) => logger::init_logging(Some(&cfg)),
+            Ok(cfg) => {
+                i18n::set_language(i18n::Lang::parse(&cfg.ui.language).unwrap_or_default());
+                logger::init_logging(Some(&cfg))
+            }
             Err(config::ConfigError::MissingConfigFile(path)) => {
                 eprintln!(
                     "u_crawler: created example config at {}. Please edit it.",
@@ -134,59 +521,191 @@ async fn main() -> ExitCode {
             Err(_) => {
                 // Other errors (e.g. validation) will be caught later by specific commands
                 // or we just fall back to default logging.
-                logger::init_logging(None);
+                logger::init_logging(None)
             }
         }
+    });
+
+    if let Some(dir) = cli.record_fixtures {
+        tracing::info!(dir = %dir.display(), "recording canvas responses");
+        fixtures::set_mode(fixtures::FixtureMode::Record(dir));
+    } else if let Some(dir) = cli.replay_fixtures {
+        tracing::info!(dir = %dir.display(), "replaying canvas responses");
+        fixtures::set_mode(fixtures::FixtureMode::Replay(dir));
     }
 
     match cli.command {
-        Commands::Init => {
-            match handle_init().await {
+        Commands::Init => match handle_init().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "init failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Auth(AuthCommands::Canvas(args)) => {
+            match auth::run_auth_canvas(args.base_url, args.token, args.token_cmd).await {
                 Ok(()) => ExitCode::SUCCESS,
                 Err(e) => {
-                    tracing::error!(error = %e, "init failed");
-                    eprintln!("error: {e}");
-                    ExitCode::from(10) // config error
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "auth canvas failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
                 }
             }
         }
-        Commands::Auth(AuthCommands::Canvas(args)) => {
-            match handle_auth_canvas(args).await {
+        Commands::Auth(AuthCommands::Moodle(args)) => {
+            match auth::run_auth_moodle(args.base_url, args.token, args.token_cmd).await {
                 Ok(()) => ExitCode::SUCCESS,
                 Err(e) => {
-                    tracing::error!(error = %e, "auth canvas failed");
-                    eprintln!("error: {e}");
-                    ExitCode::from(11) // auth error
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "auth moodle failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
                 }
             }
         }
-        Commands::Scan { course_id } => {
-            if let Err(e) = handle_scan(course_id).await {
-                tracing::error!(error = %e, course_id = ?course_id, "scan failed");
-                eprintln!("error: {e}");
-                return ExitCode::from(12); // network
+        Commands::Scan {
+            course_id,
+            deep,
+            output,
+        } => {
+            if let Err(e) = scan::run_scan(course_id, deep, output).await {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, course_id = ?course_id, "scan failed");
+                eprintln!("{}", tr!("error: {}", e));
+                return ExitCode::from(e.exit_code());
             }
             ExitCode::SUCCESS
         }
         Commands::Sync {
             course_id,
             dry_run,
+            tags,
+            moodle,
+            tui,
+            fail_fast,
+            only,
+            skip,
+            include_ext,
+            exclude_ext,
+            max_file_size,
+            since,
+            include_concluded,
+            terms,
+            plan_out,
+            plan_compare,
+        } => match syncer::run_sync(syncer::SyncOptions {
+            course_id,
+            tags,
+            dry_run,
             verbose,
-        } => match syncer::run_sync(course_id, dry_run, verbose).await {
-            Ok(()) => ExitCode::SUCCESS,
+            moodle,
+            tui,
+            fail_fast,
+            content: syncer::ContentFilter { only, skip },
+            files: config::SyncSettings {
+                include_ext,
+                exclude_ext,
+                max_file_size,
+                ..Default::default()
+            },
+            since: since.unwrap_or_default(),
+            include_concluded,
+            terms,
+            plan_out,
+            plan_compare,
+        })
+        .await
+        {
+            Ok(run) if run.failed == 0 => ExitCode::SUCCESS,
+            Ok(run) => {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "error: {} course(s) and {} item(s) failed; see the summary above",
+                        run.courses_failed,
+                        run.failed.saturating_sub(run.courses_failed)
+                    )
+                );
+                ExitCode::from(15) // partial failure
+            }
             Err(e) => {
-                tracing::error!(error = %e, "sync failed");
-                eprintln!("error: {e}");
-                ExitCode::from(12)
+                tracing::error!(error = %e, kind = %e.kind(), "sync failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
             }
         },
+        Commands::Panopto { course_id, dry_run } => {
+            match panopto::run_panopto(course_id, dry_run).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "panopto download failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::Sharepoint { course_id, dry_run } => {
+            match sharepoint::run_sharepoint(course_id, dry_run).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "sharepoint download failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
         Commands::Recordings { course_id, dry_run } => {
             match recordings::run_discovery(course_id, dry_run).await {
                 Ok(()) => ExitCode::SUCCESS,
                 Err(e) => {
-                    tracing::error!(error = %e, "recordings discovery failed");
-                    eprintln!("error: {e}");
-                    ExitCode::from(12)
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "recordings discovery failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::Courses(command) => match handle_courses(command).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "courses command failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Inbox(InboxCommands::Sync { dry_run }) => {
+            match inbox::run_inbox_sync(dry_run).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    tracing::error!(kind = %e.kind(), error = %e, "inbox sync failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::Manifest(command) => {
+            let res = match command {
+                ManifestCommands::Publish { out } => manifest::run_publish(out).await,
+                ManifestCommands::Merge {
+                    manifest: path,
+                    files_root,
+                    allow_unsigned,
+                    dry_run,
+                } => manifest::run_merge(&path, files_root, allow_unsigned, dry_run).await,
+            };
+            match res {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "manifest command failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
                 }
             }
         }
@@ -199,21 +718,232 @@ async fn main() -> ExitCode {
                 Ok(()) => ExitCode::SUCCESS,
                 Err(e) => {
                     tracing::error!(error = %e, "zoom flow failed");
-                    eprintln!("error: {e}");
-                    ExitCode::from(12)
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            },
+            ZoomCommands::Download {
+                course_id,
+                meeting_ids,
+                since,
+                until,
+                topic_regex,
+                concurrency,
+            } => {
+                let res = match meeting_filter(meeting_ids, since, until, topic_regex) {
+                    Ok(filter) => zoom::zoom_download(course_id, filter, concurrency).await,
+                    Err(e) => Err(e.into()),
+                };
+                match res {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        tracing::error!(error = %e, "zoom download failed");
+                        eprintln!("{}", tr!("error: {}", e));
+                        ExitCode::from(e.exit_code())
+                    }
+                }
+            }
+            ZoomCommands::Session { command } => {
+                let res = match command {
+                    SessionCommands::Export { course_id, out } => {
+                        zoom::session::export_session(course_id, &out).await
+                    }
+                    SessionCommands::Import { path } => zoom::session::import_session(&path).await,
+                };
+                match res {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        tracing::error!(error = %e, "zoom session failed");
+                        eprintln!("{}", tr!("error: {}", e));
+                        ExitCode::from(e.exit_code())
+                    }
+                }
+            }
+            ZoomCommands::List { course_id, json } => match zoom::zoom_list(course_id, json) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    tracing::error!(error = %e, "zoom list failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            },
+            ZoomCommands::FromLinks {
+                course_id,
+                concurrency,
+            } => match zoom::links::zoom_from_links(course_id, concurrency).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    tracing::error!(error = %e, "zoom from-links failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            },
+            ZoomCommands::ShareLink {
+                urls,
+                course_id,
+                passcode,
+                concurrency,
+            } => {
+                match zoom::links::zoom_share(course_id, &urls, passcode.as_deref(), concurrency)
+                    .await
+                {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        tracing::error!(error = %e, "zoom share-link failed");
+                        eprintln!("{}", tr!("error: {}", e));
+                        ExitCode::from(e.exit_code())
+                    }
+                }
+            }
+            ZoomCommands::Refresh { course_id, force } => {
+                match zoom::zoom_refresh(course_id, force).await {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(e) => {
+                        tracing::error!(error = %e, "zoom refresh failed");
+                        eprintln!("{}", tr!("error: {}", e));
+                        ExitCode::from(e.exit_code())
+                    }
+                }
+            }
+            ZoomCommands::SniffCdp {
+                course_id,
+                debug_port,
+                keep_tab,
+            } => match zoom::cdp::sniff_cdp(course_id, debug_port, keep_tab).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    tracing::error!(error = %e, "zoom sniff-cdp failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
                 }
             },
         },
-        Commands::Status { verbose } => match handle_status(verbose).await {
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "u_crawler",
+                &mut std::io::stdout(),
+            );
+            ExitCode::SUCCESS
+        }
+        Commands::Man { out_dir } => {
+            let res = match out_dir {
+                Some(dir) => cli_docs::write_man_pages(Cli::command(), &dir).map(|_| ()),
+                None => clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout()),
+            };
+            match res {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "man failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::Doctor => match doctor::run_doctor().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "doctor found problems");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Status => match status::run_status(verbose).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "status failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Daemon {
+            interval,
+            metrics_addr,
+        } => match daemon::run_daemon(interval, metrics_addr).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                tracing::error!(error = %e, kind = %e.kind(), "daemon failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Stats { runs, top } => match stats::run_stats(runs, top).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "stats failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Adopt {
+            course_id,
+            path,
+            move_files,
+            dry_run,
+        } => match adopt::run_adopt(course_id, &path, move_files, dry_run).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "adopt failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::ExportImscc { course_id, dry_run } => {
+            match imscc::run_export(course_id, dry_run).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    tracing::error!(kind = %e.kind(), error = %e, "course export failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::Export { site } => match site::run_export_site(&site).await {
             Ok(()) => ExitCode::SUCCESS,
             Err(e) => {
-                tracing::error!(error = %e, "status failed");
-                eprintln!("error: {e}");
-                ExitCode::from(12)
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "site export failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Search { query, limit } => match search::run_search(&query, limit).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "search failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
+            }
+        },
+        Commands::Audit(AuditCommands::Links { course_id }) => {
+            match audit::run_audit_links(course_id).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    let e = Error::from(e);
+                    tracing::error!(kind = %e.kind(), error = %e, "audit failed");
+                    eprintln!("{}", tr!("error: {}", e));
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Commands::State(StateCommands::Repair) => match state::run_state_repair().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                let e = Error::from(e);
+                tracing::error!(kind = %e.kind(), error = %e, "state repair failed");
+                eprintln!("{}", tr!("error: {}", e));
+                ExitCode::from(e.exit_code())
             }
         },
         Commands::Clean => {
-            println!("clean: stub (implement in M5)");
+            println!("{}", tr!("clean: stub (implement in M5)"));
             ExitCode::SUCCESS
         }
     }
@@ -222,254 +952,82 @@ async fn main() -> ExitCode {
 async fn handle_init() -> Result<(), Box<dyn std::error::Error>> {
     match Config::load_or_init() {
         Ok(_paths) => {
-            println!("Config file already exists and is valid.");
+            println!("{}", tr!("Config file already exists and is valid."));
         }
         Err(ConfigError::MissingConfigFile(path)) => {
-            println!("Created default config at {}", path);
+            println!("{}", tr!("Created default config at {}", path));
         }
         Err(e) => return Err(e.into()),
     }
     Ok(())
 }
 
-async fn handle_auth_canvas(args: CanvasAuthArgs) -> Result<(), Box<dyn std::error::Error>> {
+async fn handle_courses(command: CoursesCommands) -> Result<(), Box<dyn std::error::Error>> {
     let paths = ConfigPaths::new()?;
 
-    // Load or init, but if it was just created (MissingConfigFile), we proceed with default config
-    let mut cfg = match Config::load_or_init() {
-        Ok(c) => c,
-        Err(ConfigError::MissingConfigFile(_)) => {
-            // It was just created, load it again (it's default)
-            load_config_from_path(&paths.config_file).await?
-        }
-        Err(e) => return Err(e.into()),
-    };
-
-    if let Some(base) = args.base_url {
-        cfg.canvas.base_url = base;
-    }
-    if let Some(token) = args.token {
-        cfg.canvas.token = Some(token);
-        cfg.canvas.token_cmd = None;
-    }
-    if let Some(cmd) = args.token_cmd {
-        cfg.canvas.token_cmd = Some(cmd);
-        cfg.canvas.token = None;
-    }
-
-    cfg.expand_paths();
-    save_config_to_path(&cfg, &paths.config_file).await?;
-    tracing::info!(path = %paths.config_file.display(), "saved canvas auth");
-    println!("saved canvas auth in {}", paths.config_file.display());
-    Ok(())
-}
-
-async fn handle_scan(course_id: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
-    use canvas::CanvasClient;
-    let client = CanvasClient::from_config().await?;
-
-    if let Some(cid) = course_id {
-        let modules = client.list_modules_with_items(cid).await?;
-        let pb = progress_bar(modules.len() as u64, &format!("Modules for course {cid}"));
-        pb.println(format!("Modules (course_id={cid}):"));
-        for m in &modules {
-            pb.inc(1);
-            pb.println(format!(
-                "- [{}] {} (items: {})",
-                m.id,
-                m.name,
-                m.items.len()
-            ));
+    match command {
+        CoursesCommands::Pick { moodle } => courses::run_pick(moodle).await?,
+        CoursesCommands::Tag { course_id, tags } => {
+            let mut cfg = load_config_from_path(&paths.config_file).await?;
+            cfg.add_tags(course_id, &tags);
+            save_config_to_path(&cfg, &paths.config_file).await?;
+            println!(
+                "{}",
+                tr!(
+                    "course {}: {}",
+                    course_id,
+                    cfg.tags_for(course_id).join(", ")
+                )
+            );
         }
-        pb.finish_and_clear();
-        // Derive files via module items to avoid list_files 403
-        let mut file_count = 0usize;
-        for m in &modules {
-            for it in &m.items {
-                if matches!(it.kind.as_deref(), Some("File")) {
-                    file_count += 1;
-                }
+        CoursesCommands::Untag { course_id, tags } => {
+            let mut cfg = load_config_from_path(&paths.config_file).await?;
+            cfg.remove_tags(course_id, &tags);
+            save_config_to_path(&cfg, &paths.config_file).await?;
+            let remaining = cfg.tags_for(course_id);
+            if remaining.is_empty() {
+                println!("{}", tr!("course {}: no tags", course_id));
+            } else {
+                println!("{}", tr!("course {}: {}", course_id, remaining.join(", ")));
             }
         }
-        println!("Files (discovered via modules) count: {}", file_count);
-    } else {
-        let courses = client.list_courses().await?;
-        let pb = progress_bar(courses.len() as u64, "Courses");
-        pb.println("Courses:");
-        for c in courses {
-            let code = c.course_code.unwrap_or_default();
-            pb.inc(1);
-            pb.println(format!(
-                "- [{}] {} {}",
-                c.id,
-                c.name,
-                if code.is_empty() {
-                    "".to_string()
-                } else {
-                    format!("- {}", code)
-                }
-            ));
-        }
-        pb.finish_and_clear();
-    }
-    Ok(())
-}
-
-async fn handle_status(verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
-    use std::path::PathBuf;
-    use tracing::info;
-
-    let cfg = Config::load_or_init()?;
-    let download_root = PathBuf::from(&cfg.download_root);
-
-    info!(path = %download_root.display(), "scanning download root for courses");
-
-    // Check if download_root exists
-    if !download_root.exists() {
-        println!("No backup directory found at {}", download_root.display());
-        println!("Run 'u_crawler sync' to create your first backup.");
-        return Ok(());
-    }
-
-    // Scan for course directories
-    let mut entries = tokio::fs::read_dir(&download_root).await?;
-    let mut course_dirs = Vec::new();
-
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-        if path.is_dir() {
-            course_dirs.push(path);
-        }
-    }
-
-    if course_dirs.is_empty() {
-        println!("No courses found in {}", download_root.display());
-        println!("Run 'u_crawler sync' to create your first backup.");
-        return Ok(());
-    }
-
-    info!(count = course_dirs.len(), "found course directories");
-
-    println!("Backup Status:\n");
-
-    // Track totals across all courses
-    let mut total_files: usize = 0;
-    let mut total_storage: u64 = 0;
-
-    // Load state from each course directory
-    for course_dir in &course_dirs {
-        let state_path = course_dir.join("state.json");
-        let state = State::load(&state_path).await;
-
-        let course_name = course_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-
-        // Calculate statistics
-        let file_count = state.items.len();
-        let mut course_size: u64 = 0;
-        let mut last_updated: Option<String> = None;
-        let failed_items: Vec<_> = state
-            .items
-            .iter()
-            .filter(|(_, item)| item.last_error.is_some())
-            .collect();
-
-        for item in state.items.values() {
-            // Sum up file sizes
-            if let Some(size) = item.size {
-                course_size += size;
+        CoursesCommands::Tags => {
+            let cfg = load_config_from_path(&paths.config_file).await?;
+            if cfg.course_tags.is_empty() {
+                println!(
+                    "{}",
+                    tr!("No tagged courses. Use `courses tag <course_id> <tags...>`.")
+                );
             }
-
-            // Find most recent updated_at
-            if let Some(ref updated) = item.updated_at {
-                match &last_updated {
-                    None => last_updated = Some(updated.clone()),
-                    Some(current) => {
-                        if updated > current {
-                            last_updated = Some(updated.clone());
-                        }
-                    }
-                }
+            for (course_id, tags) in &cfg.course_tags {
+                println!("- [{}] {}", course_id, tags.join(", "));
             }
         }
-
-        // Accumulate totals
-        total_files += file_count;
-        total_storage += course_size;
-
-        info!(
-            course = course_name,
-            items = file_count,
-            size = course_size,
-            "loaded course state"
-        );
-
-        // Display course statistics
-        println!("Course: {}", course_name);
-        println!("  Files: {}", file_count);
-        println!("  Storage: {}", format_bytes(course_size));
-        if let Some(timestamp) = last_updated {
-            println!("  Last sync: {}", timestamp);
-        } else {
-            println!("  Last sync: Never");
-        }
-
-        // Display failed downloads if any
-        if !failed_items.is_empty() {
-            println!(
-                "  Failed downloads: {} items need retry",
-                failed_items.len()
-            );
-            if verbose {
-                for (key, item) in &failed_items {
-                    let attempts = item.error_count.unwrap_or(1);
-                    println!("    - {} (failed {} time(s))", key, attempts);
-                    if let Some(err) = &item.last_error {
-                        // Truncate long errors
-                        let err_short: String = if err.len() > 60 {
-                            format!("{}...", &err[..60])
-                        } else {
-                            err.clone()
-                        };
-                        println!("      Error: {}", err_short);
-                    }
-                }
-            } else {
-                println!("      Run with --verbose to see details");
-            }
-        }
-
-        println!();
     }
-
-    // Display totals summary
-    println!("─────────────────────────────");
-    println!(
-        "Total: {} courses, {} files, {}",
-        course_dirs.len(),
-        total_files,
-        format_bytes(total_storage)
-    );
-    println!();
-    println!("Tip: Run 'u_crawler sync --dry-run' to check for remote changes");
-
     Ok(())
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} bytes", bytes)
-    }
+fn meeting_filter(
+    meeting_ids: Vec<String>,
+    since: Option<String>,
+    until: Option<String>,
+    topic_regex: Option<String>,
+) -> Result<zoom::models::MeetingFilter, Box<dyn std::error::Error>> {
+    let date = |flag: &str, value: Option<String>| {
+        value
+            .map(|v| {
+                chrono::NaiveDate::parse_from_str(&v, "%Y-%m-%d")
+                    .map_err(|_| tr!("--{} must be a date like 2024-03-01, got '{}'", flag, v))
+            })
+            .transpose()
+    };
+    Ok(zoom::models::MeetingFilter {
+        meeting_ids,
+        since: date("since", since)?,
+        until: date("until", until)?,
+        topic: topic_regex
+            .map(|re| regex::Regex::new(&re))
+            .transpose()
+            .map_err(|e| tr!("invalid --topic-regex: {}", e))?,
+    })
 }
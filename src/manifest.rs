@@ -0,0 +1,416 @@
+use crate::config::Config;
+use crate::fsutil::{course_dirs, hash_file};
+use crate::state::{is_state_file, State};
+use crate::tr;
+use crate::zoom::headless::RECORDINGS_DIR;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use tracing::{info, warn};
+
+// 2: signatures switched from HMAC-SHA1 to HMAC-SHA256
+const MANIFEST_VERSION: u32 = 2;
+const ZOOM_DIR: &str = "Zoom";
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("manifest signature does not match; check `manifest.secret`")]
+    BadSignature,
+    #[error("manifest is unsigned; set `manifest.secret` or pass --allow-unsigned")]
+    Unsigned,
+    #[error("`manifest.secret` is not configured; cannot sign or verify manifests")]
+    MissingSecret,
+    #[error("unsupported manifest version {0}")]
+    Version(u32),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Inventory of one user's archive, exchanged between classmates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub publisher: String,
+    pub generated_at: String,
+    pub courses: Vec<ManifestCourse>,
+    /// Hex HMAC-SHA1 over the manifest serialized without this field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestCourse {
+    pub course_id: Option<u64>,
+    /// Folder name relative to the archive root (e.g. `Course_CODE` or `Zoom/123`).
+    pub dir: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestFile {
+    /// Path relative to the course folder, using `/` separators.
+    pub path: String,
+    pub size: u64,
+    pub sha1: String,
+}
+
+/// Outcome counters for `manifest merge`.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub imported: usize,
+    pub bytes: u64,
+    pub present: usize,
+    pub missing_source: usize,
+    pub mismatched: usize,
+}
+
+impl Manifest {
+    fn signing_payload(&self) -> Result<Vec<u8>, serde_json::Error> {
+        let mut unsigned = self.clone();
+        unsigned.signature = None;
+        serde_json::to_vec(&unsigned)
+    }
+
+    /// Signs the manifest with HMAC-SHA256 over its JSON without the signature.
+    pub fn sign(&mut self, secret: &str) -> Result<(), serde_json::Error> {
+        let payload = self.signing_payload()?;
+        let tag = hmac::sign(&signing_key(secret), &payload);
+        self.signature = Some(hex::encode(tag.as_ref()));
+        Ok(())
+    }
+
+    /// Checks the signature in constant time; a missing or non-hex signature fails.
+    pub fn verify(&self, secret: &str) -> Result<bool, serde_json::Error> {
+        let Some(tag) = self.signature.as_deref().and_then(|s| hex::decode(s).ok()) else {
+            return Ok(false);
+        };
+        let payload = self.signing_payload()?;
+        Ok(hmac::verify(&signing_key(secret), &payload, &tag).is_ok())
+    }
+}
+
+/// Build and sign a manifest of the local archive, writing it to `out`.
+pub async fn run_publish(out: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let secret = cfg
+        .manifest
+        .secret
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .ok_or(ManifestError::MissingSecret)?;
+    let root = cfg.canvas_root();
+    let out = out.unwrap_or_else(|| root.join("manifest.json"));
+
+    let zoom_root = cfg.separate_zoom_root();
+    let mut manifest = build_manifest(&root, zoom_root.as_deref(), &publisher_name(&cfg)).await?;
+    manifest.sign(secret)?;
+
+    let files: usize = manifest.courses.iter().map(|c| c.files.len()).sum();
+    crate::fsutil::atomic_write(&out, &serde_json::to_vec_pretty(&manifest)?).await?;
+    info!(path = %out.display(), courses = manifest.courses.len(), files, "published manifest");
+    println!(
+        "{}",
+        tr!(
+            "Published manifest with {} course(s), {} file(s) to {}",
+            manifest.courses.len(),
+            files,
+            out.display()
+        )
+    );
+    Ok(())
+}
+
+/// Import files listed in a peer's manifest that are missing locally.
+/// `files_root` is the peer's shared archive folder (defaults to the manifest's folder).
+pub async fn run_merge(
+    manifest_path: &Path,
+    files_root: Option<PathBuf>,
+    allow_unsigned: bool,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let manifest: Manifest = serde_json::from_slice(&tokio::fs::read(manifest_path).await?)?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(ManifestError::Version(manifest.version).into());
+    }
+    match cfg.manifest.secret.as_deref().filter(|s| !s.is_empty()) {
+        Some(secret) if manifest.signature.is_some() => {
+            if !manifest.verify(secret)? {
+                return Err(ManifestError::BadSignature.into());
+            }
+        }
+        _ if allow_unsigned => {
+            warn!(publisher = %manifest.publisher, "merging unverified manifest");
+        }
+        Some(_) => return Err(ManifestError::Unsigned.into()),
+        None => return Err(ManifestError::MissingSecret.into()),
+    }
+
+    let source_root = files_root.unwrap_or_else(|| {
+        manifest_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    });
+    let local_root = cfg.canvas_root();
+    let local_dirs = local_course_dirs(&local_root).await?;
+
+    let mut report = MergeReport::default();
+    for course in &manifest.courses {
+        let Some(course_rel) = safe_relative(&course.dir) else {
+            warn!(dir = %course.dir, "skipping course with unsafe path");
+            continue;
+        };
+        // Prefer the local folder for the same course id, since names may differ between users
+        let local_course = if course_rel.starts_with(ZOOM_DIR) {
+            cfg.separate_zoom_root()
+                .unwrap_or_else(|| local_root.clone())
+                .join(&course_rel)
+        } else {
+            course
+                .course_id
+                .and_then(|id| {
+                    local_dirs
+                        .iter()
+                        .find(|(cid, _)| *cid == Some(id))
+                        .map(|(_, p)| p.clone())
+                })
+                .unwrap_or_else(|| local_root.join(&course_rel))
+        };
+        // Recordings go under zoom.download_root when it is set
+        let local_recordings = cfg.zoom_course_dir(&local_course);
+        let source_course = source_root.join(&course_rel);
+
+        for file in &course.files {
+            let Some(rel) = safe_relative(&file.path) else {
+                warn!(path = %file.path, "skipping file with unsafe path");
+                continue;
+            };
+            let dest = match &local_recordings {
+                Some(dir) if rel.starts_with(RECORDINGS_DIR) => dir.join(&rel),
+                _ => local_course.join(&rel),
+            };
+            if dest.exists() {
+                report.present += 1;
+                continue;
+            }
+            let src = source_course.join(&rel);
+            if !src.exists() {
+                report.missing_source += 1;
+                continue;
+            }
+            let (size, sha) = hash_file(&src)?;
+            if size != file.size || sha != file.sha1 {
+                warn!(path = %src.display(), "hash mismatch; not importing");
+                report.mismatched += 1;
+                continue;
+            }
+            if dry_run {
+                println!(
+                    "{}",
+                    tr!("DRY-RUN import {} -> {}", src.display(), dest.display())
+                );
+            } else {
+                import_file(&src, &dest).await?;
+                info!(src = %src.display(), dest = %dest.display(), "imported file from manifest");
+            }
+            report.imported += 1;
+            report.bytes += size;
+        }
+    }
+
+    println!(
+        "{}",
+        tr!(
+            "{}Merged manifest from {}: {} imported ({}), {} already present, {} missing in shared folder, {} hash mismatches",
+            if dry_run { "DRY-RUN: " } else { "" },
+            manifest.publisher,
+            report.imported,
+            crate::progress::format_bytes(report.bytes),
+            report.present,
+            report.missing_source,
+            report.mismatched,
+        )
+    );
+    Ok(())
+}
+
+/// Manifest of the archive at `root`. Recordings kept apart under `zoom_root`
+/// are listed with their course, as if they were in the course folder.
+async fn build_manifest(
+    root: &Path,
+    zoom_root: Option<&Path>,
+    publisher: &str,
+) -> Result<Manifest, ManifestError> {
+    let mut courses = Vec::new();
+    if root.is_dir() {
+        for dir in course_dirs(root, &[ZOOM_DIR])? {
+            let state = State::load(&dir.join("state.json")).await;
+            let mut course = manifest_course(root, &dir, state.course_id)?;
+            if let Some(recordings) = zoom_root
+                .map(|zoom| zoom.join(dir.strip_prefix(root).unwrap_or(&dir)))
+                .filter(|d| d.is_dir())
+            {
+                course.files.extend(course_files(&recordings)?);
+                course.files.sort_by(|a, b| a.path.cmp(&b.path));
+            }
+            courses.push(course);
+        }
+    }
+    for base in std::iter::once(root).chain(zoom_root) {
+        let zoom = base.join(ZOOM_DIR);
+        if zoom.is_dir() {
+            for entry in std::fs::read_dir(&zoom)? {
+                let dir = entry?.path();
+                if dir.is_dir() {
+                    let course_id = dir
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .and_then(|n| n.parse().ok());
+                    courses.push(manifest_course(base, &dir, course_id)?);
+                }
+            }
+        }
+    }
+    Ok(Manifest {
+        version: MANIFEST_VERSION,
+        publisher: publisher.to_string(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        courses,
+        signature: None,
+    })
+}
+
+fn manifest_course(
+    root: &Path,
+    dir: &Path,
+    course_id: Option<u64>,
+) -> Result<ManifestCourse, ManifestError> {
+    let mut files = course_files(dir)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(ManifestCourse {
+        course_id,
+        dir: slash_path(dir.strip_prefix(root).unwrap_or(dir)),
+        files,
+    })
+}
+
+/// Files under `dir` with paths relative to it, without state and partial files.
+fn course_files(dir: &Path) -> Result<Vec<ManifestFile>, ManifestError> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if is_state_file(name) || name.ends_with(".part") {
+                continue;
+            }
+            let (size, sha1) = hash_file(&path)?;
+            files.push(ManifestFile {
+                path: slash_path(path.strip_prefix(dir).unwrap_or(&path)),
+                size,
+                sha1,
+            });
+        }
+    }
+    Ok(files)
+}
+
+async fn local_course_dirs(root: &Path) -> std::io::Result<Vec<(Option<u64>, PathBuf)>> {
+    let mut out = Vec::new();
+    if root.is_dir() {
+        for dir in course_dirs(root, &[ZOOM_DIR])? {
+            let state = State::load(&dir.join("state.json")).await;
+            out.push((state.course_id, dir));
+        }
+    }
+    Ok(out)
+}
+
+async fn import_file(src: &Path, dest: &Path) -> std::io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = dest.with_extension("part");
+    tokio::fs::copy(src, &tmp).await?;
+    tokio::fs::rename(&tmp, dest).await
+}
+
+fn publisher_name(cfg: &Config) -> String {
+    cfg.manifest
+        .publisher
+        .clone()
+        .filter(|p| !p.trim().is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+/// Reject absolute paths and `..` so a manifest cannot write outside the archive.
+fn safe_relative(path: &str) -> Option<PathBuf> {
+    let p = Path::new(path);
+    if p.components().all(|c| matches!(c, Component::Normal(_))) && !path.is_empty() {
+        Some(p.to_path_buf())
+    } else {
+        None
+    }
+}
+
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn signing_key(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_manifest_detects_tampering() {
+        let mut m = Manifest {
+            version: MANIFEST_VERSION,
+            publisher: "ana".into(),
+            generated_at: "2025-01-01T00:00:00Z".into(),
+            courses: vec![ManifestCourse {
+                course_id: Some(1),
+                dir: "Course_C1".into(),
+                files: vec![ManifestFile {
+                    path: "Modules/1_intro/01-Intro.md".into(),
+                    size: 5,
+                    sha1: "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d".into(),
+                }],
+            }],
+            signature: None,
+        };
+        m.sign("group-secret").unwrap();
+        assert!(m.verify("group-secret").unwrap());
+        assert!(!m.verify("other").unwrap());
+        m.courses[0].files[0].size = 6;
+        assert!(!m.verify("group-secret").unwrap());
+        m.courses[0].files[0].size = 5;
+        m.signature = Some("not hex".into());
+        assert!(!m.verify("group-secret").unwrap());
+    }
+
+    #[test]
+    fn safe_relative_rejects_escapes() {
+        assert!(safe_relative("Modules/a.pdf").is_some());
+        assert!(safe_relative("../etc/passwd").is_none());
+        assert!(safe_relative("/etc/passwd").is_none());
+        assert!(safe_relative("").is_none());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::canvas::{MediaObject, MediaSource};
+use crate::tr;
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// A video embedded in (or linked from) page HTML through an iframe or media
+/// player rather than a plain `/files/<id>` link.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MediaEmbed {
+    /// Canvas media attachment (`/media_attachments_iframe/<id>`), stored as a Canvas file.
+    Attachment(u64),
+    /// Kaltura-backed Canvas media object, e.g. `m-abc123` or `0_abcd1234`.
+    CanvasMedia(String),
+    /// Kaltura KAF/MediaSpace or player embed.
+    Kaltura { partner_id: u64, entry_id: String },
+    /// Canvas Studio media, through its LTI embed or a share link.
+    Studio { host: String, id: String },
+    /// YouTube video, archived with yt-dlp when configured.
+    YouTube(String),
+    /// Vimeo video; unlisted videos need their privacy hash.
+    Vimeo { id: String, hash: Option<String> },
+}
+
+impl MediaEmbed {
+    /// Sync state key; attachments are tracked as regular `file:<id>` entries.
+    pub fn state_key(&self) -> String {
+        match self {
+            MediaEmbed::Attachment(id) => format!("file:{}", id),
+            MediaEmbed::CanvasMedia(id) => format!("media:canvas:{}", id),
+            MediaEmbed::Kaltura { entry_id, .. } => format!("media:kaltura:{}", entry_id),
+            MediaEmbed::Studio { id, .. } => format!("media:studio:{}", id),
+            MediaEmbed::YouTube(id) => format!("video:youtube:{}", id),
+            MediaEmbed::Vimeo { id, .. } => format!("video:vimeo:{}", id),
+        }
+    }
+
+    /// Page URL handed to yt-dlp for externally hosted videos.
+    pub fn external_url(&self) -> Option<String> {
+        match self {
+            MediaEmbed::YouTube(id) => Some(format!("https://www.youtube.com/watch?v={}", id)),
+            MediaEmbed::Vimeo { id, hash: Some(h) } => {
+                Some(format!("https://vimeo.com/{}/{}", id, h))
+            }
+            MediaEmbed::Vimeo { id, hash: None } => Some(format!("https://vimeo.com/{}", id)),
+            _ => None,
+        }
+    }
+}
+
+/// Where an embedded video can be downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedMedia {
+    pub url: String,
+    /// File name without extension.
+    pub name: String,
+    pub ext: String,
+    pub size: Option<u64>,
+}
+
+/// Every media embed in `html`, in order of appearance. LTI embeds carry the
+/// provider URL percent-encoded in `external_tools/retrieve?url=`, so the HTML
+/// is decoded before matching.
+pub fn discover_media(html: &str) -> Vec<MediaEmbed> {
+    let decoded = urlencoding::decode_binary(html.replace("&amp;", "&").as_bytes()).into_owned();
+    let text = String::from_utf8_lossy(&decoded);
+
+    let attachment = Regex::new(r"/media_attachments_iframe/(\d+)").unwrap();
+    let canvas_media = Regex::new(
+        r#"(?:/media_objects_iframe/|data-media_comment_id=["']?|media_comment_)(m-[A-Za-z0-9]+|\d_[A-Za-z0-9]{8})"#,
+    )
+    .unwrap();
+    let kaltura_url = Regex::new(r#"(?i)https?://[^\s"'<>]*kaltura\.com[^\s"'<>]*"#).unwrap();
+    let partner = Regex::new(
+        r"(?i)(?:/p/|partner_?id[/=])(\d+)|https?://(\d+)\.(?:kaf|mediaspace)\.kaltura\.com",
+    )
+    .unwrap();
+    let entry = Regex::new(r"(?i)entry_?id[/=](\d_[A-Za-z0-9]{8})").unwrap();
+    let studio = Regex::new(
+        r#"(?i)https?://([a-z0-9.-]+\.instructuremedia\.com)/(?:lti/launch\?[^\s"'<>]*?custom_arc_media_id=|embed/)([A-Za-z0-9-]+)"#,
+    )
+    .unwrap();
+    let youtube = Regex::new(
+        r#"(?i)https?://(?:www\.|m\.)?(?:youtube(?:-nocookie)?\.com/(?:watch\?(?:[^\s"'<>]*?&)?v=|embed/|shorts/|live/)|youtu\.be/)([A-Za-z0-9_-]{11})"#,
+    )
+    .unwrap();
+    let vimeo = Regex::new(
+        r#"(?i)https?://(?:www\.|player\.)?vimeo\.com/(?:video/)?(\d+)(?:/([0-9a-f]{6,}))?(?:\?(?:[^\s"'<>]*?&)?h=([0-9a-f]+))?"#,
+    )
+    .unwrap();
+
+    let mut found: Vec<(usize, MediaEmbed)> = Vec::new();
+    for c in attachment.captures_iter(&text) {
+        if let Ok(id) = c[1].parse() {
+            found.push((c.get(0).unwrap().start(), MediaEmbed::Attachment(id)));
+        }
+    }
+    for c in canvas_media.captures_iter(&text) {
+        found.push((
+            c.get(0).unwrap().start(),
+            MediaEmbed::CanvasMedia(c[1].to_string()),
+        ));
+    }
+    for m in kaltura_url.find_iter(&text) {
+        let url = m.as_str();
+        let partner_id = partner
+            .captures(url)
+            .and_then(|c| c.get(1).or(c.get(2)))
+            .and_then(|p| p.as_str().parse().ok());
+        let entry_id = entry.captures(url).map(|c| c[1].to_string());
+        if let (Some(partner_id), Some(entry_id)) = (partner_id, entry_id) {
+            found.push((
+                m.start(),
+                MediaEmbed::Kaltura {
+                    partner_id,
+                    entry_id,
+                },
+            ));
+        }
+    }
+    for c in studio.captures_iter(&text) {
+        found.push((
+            c.get(0).unwrap().start(),
+            MediaEmbed::Studio {
+                host: c[1].to_ascii_lowercase(),
+                id: c[2].to_string(),
+            },
+        ));
+    }
+    for c in youtube.captures_iter(&text) {
+        found.push((
+            c.get(0).unwrap().start(),
+            MediaEmbed::YouTube(c[1].to_string()),
+        ));
+    }
+    for c in vimeo.captures_iter(&text) {
+        found.push((
+            c.get(0).unwrap().start(),
+            MediaEmbed::Vimeo {
+                id: c[1].to_string(),
+                hash: c.get(2).or(c.get(3)).map(|h| h.as_str().to_string()),
+            },
+        ));
+    }
+
+    found.sort_by_key(|(pos, _)| *pos);
+    let mut seen = HashSet::new();
+    found
+        .into_iter()
+        .map(|(_, embed)| embed)
+        .filter(|embed| seen.insert(embed.clone()))
+        .collect()
+}
+
+/// Highest-quality source of a Canvas media object, resolved from the course listing.
+pub fn canvas_media_source(objects: &[MediaObject], media_id: &str) -> Option<ResolvedMedia> {
+    let object = objects.iter().find(|o| o.media_id == media_id)?;
+    let number = |v: &Option<String>| v.as_deref().and_then(|s| s.parse::<u64>().ok());
+    let source: &MediaSource = object
+        .media_sources
+        .iter()
+        .max_by_key(|s| (number(&s.height), number(&s.bitrate)))?;
+    let ext = source
+        .file_ext
+        .clone()
+        .or_else(|| {
+            source
+                .content_type
+                .as_deref()
+                .and_then(|t| t.split('/').nth(1))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "mp4".to_string());
+    Some(ResolvedMedia {
+        url: source.url.clone(),
+        name: object
+            .user_entered_title
+            .clone()
+            .or(object.title.clone())
+            .unwrap_or_else(|| media_id.to_string()),
+        ext,
+        size: number(&source.size).map(|kib| kib * 1024),
+    })
+}
+
+/// Original upload of a Kaltura entry through its public play manifest; entries
+/// behind access control answer 403.
+pub fn kaltura_source(partner_id: u64, entry_id: &str) -> ResolvedMedia {
+    ResolvedMedia {
+        url: format!(
+            "https://cdnapisec.kaltura.com/p/{p}/sp/{p}00/playManifest/entryId/{e}/format/url/protocol/https/flavorParamId/0/video.mp4",
+            p = partner_id,
+            e = entry_id
+        ),
+        name: entry_id.to_string(),
+        ext: "mp4".to_string(),
+        size: None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StudioPerspective {
+    media: StudioMedia,
+}
+
+#[derive(Debug, Deserialize)]
+struct StudioMedia {
+    title: Option<String>,
+    #[serde(default)]
+    sources: Vec<StudioSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StudioSource {
+    url: String,
+    height: Option<u64>,
+    mime_type: Option<String>,
+}
+
+/// Resolve Canvas Studio media through the endpoint its embed player reads. Only
+/// media shared by link can be fetched this way.
+pub async fn studio_source(
+    http: &Client,
+    host: &str,
+    id: &str,
+) -> Result<ResolvedMedia, Box<dyn std::error::Error>> {
+    let url = format!("https://{}/api/media_management/perspectives/{}", host, id);
+    let resp = http.get(&url).send().await?;
+    if !resp.status().is_success() {
+        return Err(tr!(
+            "Canvas Studio returned {} for media {}; it may not be shared by link",
+            resp.status(),
+            id
+        )
+        .into());
+    }
+    let perspective: StudioPerspective = resp.json().await?;
+    let source = perspective
+        .media
+        .sources
+        .iter()
+        .filter(|s| s.mime_type.as_deref().is_none_or(|t| t.contains("mp4")))
+        .max_by_key(|s| s.height)
+        .ok_or_else(|| tr!("Canvas Studio media {} has no MP4 source", id))?;
+    Ok(ResolvedMedia {
+        url: source.url.clone(),
+        name: perspective.media.title.unwrap_or_else(|| id.to_string()),
+        ext: "mp4".to_string(),
+        size: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_iframe_embeds() {
+        let html = r#"
+            <iframe src="/media_attachments_iframe/4321?type=video&amp;embedded=true"></iframe>
+            <a id="media_comment_m-5Fq2xYz" class="instructure_inline_media_comment video_comment"></a>
+            <iframe src="/courses/7/external_tools/retrieve?display=borderless&amp;url=https%3A%2F%2F1234561.kaf.kaltura.com%2Fbrowseandembed%2Findex%2Fmedia%2Fentryid%2F1_abcd1234%2FshowDescription%2Ffalse"></iframe>
+            <iframe src="https://cdnapisec.kaltura.com/p/987/sp/98700/embedIframeJs/uiconf_id/1/partner_id/987?iframeembed=true&entry_id=0_zyxw9876"></iframe>
+            <iframe src="/courses/7/external_tools/retrieve?url=https%3A%2F%2Funab.instructuremedia.com%2Flti%2Flaunch%3Fcustom_arc_launch_type%3Dembed%26custom_arc_media_id%3Dd3adb33f-0000-1111"></iframe>
+            <iframe src="/media_attachments_iframe/4321?type=video"></iframe>
+            <a href="https://youtu.be/dQw4w9WgXcQ">Repaso</a>
+            <iframe src="https://player.vimeo.com/video/76979871?h=8272103f6e&amp;badge=0"></iframe>
+        "#;
+        assert_eq!(
+            discover_media(html),
+            vec![
+                MediaEmbed::Attachment(4321),
+                MediaEmbed::CanvasMedia("m-5Fq2xYz".into()),
+                MediaEmbed::Kaltura {
+                    partner_id: 1234561,
+                    entry_id: "1_abcd1234".into()
+                },
+                MediaEmbed::Kaltura {
+                    partner_id: 987,
+                    entry_id: "0_zyxw9876".into()
+                },
+                MediaEmbed::Studio {
+                    host: "unab.instructuremedia.com".into(),
+                    id: "d3adb33f-0000-1111".into()
+                },
+                MediaEmbed::YouTube("dQw4w9WgXcQ".into()),
+                MediaEmbed::Vimeo {
+                    id: "76979871".into(),
+                    hash: Some("8272103f6e".into())
+                },
+            ]
+        );
+        assert_eq!(
+            MediaEmbed::Vimeo {
+                id: "76979871".into(),
+                hash: Some("8272103f6e".into())
+            }
+            .external_url()
+            .as_deref(),
+            Some("https://vimeo.com/76979871/8272103f6e")
+        );
+    }
+}
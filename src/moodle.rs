@@ -0,0 +1,499 @@
+use crate::canvas::{
+    resolve_token, Assignment, Course, FileLock, FileObj, Module, ModuleItem, PageObj,
+};
+use crate::config::{Config, ConfigError};
+use crate::http::{build_http_client, HttpCtx};
+use crate::provider::CourseProvider;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use thiserror::Error;
+use tracing::{debug, error};
+
+#[derive(Debug, Error)]
+pub enum MoodleError {
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("http status {0}: {1}")]
+    Status(u16, String),
+    #[error("decode error: {0}")]
+    Decode(String),
+    #[error("moodle {function} failed ({code}): {message}")]
+    Api {
+        function: String,
+        code: String,
+        message: String,
+    },
+    #[error("invalid moodle base_url: {0}")]
+    BaseUrl(String),
+    #[error("moodle is not configured; run `auth moodle` first")]
+    NotConfigured,
+    #[error("missing moodle token; run `auth moodle` first")]
+    MissingToken,
+    #[error("file {0} is not part of a listed course")]
+    UnknownFile(u64),
+    #[error("page {1} not found in course {0}")]
+    UnknownPage(u64, String),
+}
+
+/// Moodle web service client for the REST protocol, mapping course sections,
+/// resources, folders, pages and assignments onto the sync engine's course model.
+pub struct MoodleClient {
+    pub base: Url,
+    pub http: HttpCtx,
+    token: String,
+    /// Files seen while listing course contents, by synthetic id.
+    files: Mutex<HashMap<u64, MoodleContent>>,
+    /// Pages of each course, fetched on first use.
+    pages: Mutex<HashMap<u64, Vec<MoodlePage>>>,
+}
+
+/// Signed-in user and site, from `core_webservice_get_site_info`.
+#[derive(Debug, Deserialize)]
+pub struct SiteInfo {
+    pub sitename: String,
+    pub fullname: String,
+    pub userid: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoodleCourse {
+    id: u64,
+    fullname: String,
+    shortname: Option<String>,
+    /// Unix time, `0` when the course has no start date.
+    #[serde(default)]
+    startdate: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoodleSection {
+    id: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    section: u64,
+    #[serde(default)]
+    modules: Vec<MoodleModule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoodleModule {
+    id: u64,
+    name: String,
+    instance: Option<u64>,
+    modname: String,
+    url: Option<String>,
+    #[serde(default)]
+    indent: Option<u32>,
+    #[serde(default)]
+    contents: Vec<MoodleContent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MoodleContent {
+    #[serde(rename = "type")]
+    kind: String,
+    filename: String,
+    #[serde(default)]
+    filepath: Option<String>,
+    filesize: Option<u64>,
+    fileurl: Option<String>,
+    timemodified: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MoodlePage {
+    coursemodule: u64,
+    name: String,
+    content: Option<String>,
+    timemodified: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PagesResponse {
+    #[serde(default)]
+    pages: Vec<MoodlePage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignmentsResponse {
+    #[serde(default)]
+    courses: Vec<AssignmentCourse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignmentCourse {
+    #[serde(default)]
+    assignments: Vec<MoodleAssignment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoodleAssignment {
+    id: u64,
+    name: String,
+    intro: Option<String>,
+    /// Unix time, `0` when the assignment has no due date.
+    #[serde(default)]
+    duedate: i64,
+    timemodified: Option<i64>,
+}
+
+/// Error payload Moodle returns with a 200 status.
+#[derive(Debug, Deserialize)]
+struct MoodleException {
+    exception: String,
+    errorcode: Option<String>,
+    message: Option<String>,
+}
+
+impl MoodleClient {
+    pub async fn from_config() -> Result<Self, MoodleError> {
+        let cfg = Config::load_or_init()?;
+        let http = HttpCtx::new(&cfg, build_http_client(&cfg));
+        Self::with_http(&cfg, http).await
+    }
+
+    /// Build a client that shares `http` with other callers such as file downloads.
+    pub async fn with_http(cfg: &Config, http: HttpCtx) -> Result<Self, MoodleError> {
+        let moodle = cfg.moodle.as_ref().ok_or(MoodleError::NotConfigured)?;
+        // Trailing slash so joins keep sites installed under a path, e.g. `/moodle`
+        let base = Url::parse(&format!("{}/", moodle.base_url.trim_end_matches('/')))
+            .map_err(|_| MoodleError::BaseUrl(moodle.base_url.clone()))?;
+        let token = resolve_token(moodle.token.as_deref(), moodle.token_cmd.as_deref())
+            .await
+            .ok_or(MoodleError::MissingToken)?;
+        Ok(MoodleClient {
+            base,
+            http,
+            token,
+            files: Mutex::new(HashMap::new()),
+            pages: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Call a web service function with form `params`.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        function: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, MoodleError> {
+        let url = self
+            .base
+            .join("webservice/rest/server.php")
+            .map_err(|_| MoodleError::BaseUrl(self.base.to_string()))?;
+        let mut form: Vec<(&str, String)> = vec![
+            ("wstoken", self.token.clone()),
+            ("wsfunction", function.to_string()),
+            ("moodlewsrestformat", "json".to_string()),
+        ];
+        form.extend(params.iter().cloned());
+        debug!(method = "POST", function, "moodle request");
+        let resp = self
+            .http
+            .send(self.http.client.post(url).form(&form))
+            .await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let snippet = text.chars().take(500).collect::<String>();
+            error!(status = %status.as_u16(), body = %snippet, "moodle non-success response");
+            return Err(MoodleError::Status(status.as_u16(), snippet));
+        }
+        if let Ok(e) = serde_json::from_str::<MoodleException>(&text) {
+            return Err(MoodleError::Api {
+                function: function.to_string(),
+                code: e.errorcode.unwrap_or(e.exception),
+                message: e.message.unwrap_or_default(),
+            });
+        }
+        serde_json::from_str(&text).map_err(|e| {
+            let snippet = text.chars().take(500).collect::<String>();
+            error!(error = %e, function, body = %snippet, "moodle decode failure");
+            MoodleError::Decode(e.to_string())
+        })
+    }
+
+    pub async fn site_info(&self) -> Result<SiteInfo, MoodleError> {
+        self.call("core_webservice_get_site_info", &[]).await
+    }
+
+    async fn course_pages(&self, course_id: u64) -> Result<Vec<MoodlePage>, MoodleError> {
+        if let Some(pages) = self.pages.lock().unwrap().get(&course_id) {
+            return Ok(pages.clone());
+        }
+        let resp: PagesResponse = self
+            .call(
+                "mod_page_get_pages_by_courses",
+                &[("courseids[0]", course_id.to_string())],
+            )
+            .await?;
+        self.pages
+            .lock()
+            .unwrap()
+            .insert(course_id, resp.pages.clone());
+        Ok(resp.pages)
+    }
+
+    /// Remember a file listed in course contents and return its synthetic id.
+    fn register_file(&self, content: &MoodleContent) -> Option<u64> {
+        let id = file_id(content.fileurl.as_deref()?);
+        self.files.lock().unwrap().insert(id, content.clone());
+        Some(id)
+    }
+
+    /// Module items of one course section.
+    fn section_items(&self, section: &MoodleSection) -> Vec<ModuleItem> {
+        let mut items = Vec::new();
+        for m in &section.modules {
+            let item = |kind: &str| ModuleItem {
+                id: m.id,
+                title: Some(m.name.clone()),
+                kind: Some(kind.to_string()),
+                html_url: m.url.clone(),
+                page_url: None,
+                external_url: None,
+                content_id: None,
+                indent: m.indent,
+            };
+            match m.modname.as_str() {
+                "resource" => {
+                    let file = m.contents.iter().find(|c| c.kind == "file");
+                    items.push(ModuleItem {
+                        content_id: file.and_then(|f| self.register_file(f)),
+                        ..item("File")
+                    });
+                }
+                "folder" => {
+                    items.push(item("SubHeader"));
+                    for f in m.contents.iter().filter(|c| c.kind == "file") {
+                        let path = f.filepath.as_deref().unwrap_or("/").trim_matches('/');
+                        let title = match path {
+                            "" => f.filename.clone(),
+                            path => format!("{}/{}", path, f.filename),
+                        };
+                        items.push(ModuleItem {
+                            title: Some(title),
+                            content_id: self.register_file(f),
+                            indent: Some(m.indent.unwrap_or(0) + 1),
+                            ..item("File")
+                        });
+                    }
+                }
+                "page" => items.push(ModuleItem {
+                    page_url: Some(m.id.to_string()),
+                    ..item("Page")
+                }),
+                "url" => items.push(ModuleItem {
+                    external_url: m
+                        .contents
+                        .iter()
+                        .find(|c| c.kind == "url")
+                        .and_then(|c| c.fileurl.clone()),
+                    ..item("ExternalUrl")
+                }),
+                "assign" => items.push(ModuleItem {
+                    content_id: m.instance,
+                    ..item("Assignment")
+                }),
+                "label" => items.push(item("SubHeader")),
+                other => items.push(item(other)),
+            }
+        }
+        items
+    }
+
+    /// File URL with the token Moodle requires on `webservice/pluginfile.php`.
+    fn authorized_url(&self, fileurl: &str) -> String {
+        match Url::parse(fileurl) {
+            Ok(mut url) => {
+                url.query_pairs_mut().append_pair("token", &self.token);
+                url.into()
+            }
+            Err(_) => fileurl.to_string(),
+        }
+    }
+}
+
+impl CourseProvider for MoodleClient {
+    type Error = MoodleError;
+
+    async fn list_courses(&self) -> Result<Vec<Course>, MoodleError> {
+        let site = self.site_info().await?;
+        let courses: Vec<MoodleCourse> = self
+            .call(
+                "core_enrol_get_users_courses",
+                &[("userid", site.userid.to_string())],
+            )
+            .await?;
+        Ok(courses
+            .into_iter()
+            .map(|c| Course {
+                id: c.id,
+                name: c.fullname,
+                course_code: c.shortname,
+                start_at: chrono::DateTime::from_timestamp(c.startdate, 0)
+                    .filter(|_| c.startdate > 0)
+                    .map(|t| t.to_rfc3339()),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn list_modules_with_items(&self, course_id: u64) -> Result<Vec<Module>, MoodleError> {
+        let sections: Vec<MoodleSection> = self
+            .call(
+                "core_course_get_contents",
+                &[("courseid", course_id.to_string())],
+            )
+            .await?;
+        Ok(sections
+            .iter()
+            .filter(|s| !s.modules.is_empty())
+            .map(|s| Module {
+                id: s.id,
+                name: if s.name.trim().is_empty() {
+                    format!("Section {}", s.section)
+                } else {
+                    s.name.clone()
+                },
+                items: self.section_items(s),
+            })
+            .collect())
+    }
+
+    async fn get_page(&self, course_id: u64, page_url: &str) -> Result<PageObj, MoodleError> {
+        let page = self
+            .course_pages(course_id)
+            .await?
+            .into_iter()
+            .find(|p| p.coursemodule.to_string() == page_url)
+            .ok_or_else(|| MoodleError::UnknownPage(course_id, page_url.to_string()))?;
+        Ok(PageObj {
+            title: Some(page.name),
+            body: page.content,
+            updated_at: page.timemodified.and_then(rfc3339),
+        })
+    }
+
+    async fn get_file(&self, file_id: u64) -> Result<FileObj, MoodleError> {
+        let content = self
+            .files
+            .lock()
+            .unwrap()
+            .get(&file_id)
+            .cloned()
+            .ok_or(MoodleError::UnknownFile(file_id))?;
+        Ok(FileObj {
+            id: file_id,
+            display_name: Some(content.filename.clone()),
+            filename: Some(content.filename),
+            size: content.filesize,
+            updated_at: content.timemodified.and_then(rfc3339),
+            url: content.fileurl.as_deref().map(|u| self.authorized_url(u)),
+            download_url: None,
+            uuid: None,
+            lock: FileLock::default(),
+        })
+    }
+
+    async fn list_assignments(&self, course_id: u64) -> Result<Vec<Assignment>, MoodleError> {
+        let resp: AssignmentsResponse = self
+            .call(
+                "mod_assign_get_assignments",
+                &[("courseids[0]", course_id.to_string())],
+            )
+            .await?;
+        Ok(resp
+            .courses
+            .into_iter()
+            .flat_map(|c| c.assignments)
+            .map(|a| Assignment {
+                id: a.id,
+                name: Some(a.name),
+                description: a.intro,
+                updated_at: a.timemodified.and_then(rfc3339),
+                due_at: Some(a.duedate).filter(|&t| t > 0).and_then(rfc3339),
+                html_url: None,
+                rubric: None,
+            })
+            .collect())
+    }
+}
+
+/// Stable id for a Moodle file, which the web service only identifies by URL.
+fn file_id(fileurl: &str) -> u64 {
+    let path = fileurl.split('?').next().unwrap_or(fileurl);
+    let digest = Sha1::digest(path.as_bytes());
+    // Keep ids within the range JSON consumers read exactly
+    u64::from_be_bytes(digest[..8].try_into().unwrap()) >> 11
+}
+
+fn rfc3339(timestamp: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(timestamp, 0).map(|t| t.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_course_contents_to_module_items() {
+        let client = MoodleClient {
+            base: Url::parse("https://moodle.example.edu/").unwrap(),
+            http: HttpCtx::new(&Config::default(), build_http_client(&Config::default())),
+            token: "tok".into(),
+            files: Mutex::new(HashMap::new()),
+            pages: Mutex::new(HashMap::new()),
+        };
+        let sections: Vec<MoodleSection> = serde_json::from_str(
+            r#"[{"id": 10, "name": "Unidad 1", "section": 1, "modules": [
+                {"id": 101, "name": "Guía", "instance": 5, "modname": "resource", "contents": [
+                    {"type": "file", "filename": "guia.pdf", "filepath": "/", "filesize": 2048,
+                     "fileurl": "https://moodle.example.edu/webservice/pluginfile.php/9/mod_resource/content/1/guia.pdf?forcedownload=1",
+                     "timemodified": 1700000000}]},
+                {"id": 102, "name": "Lecturas", "instance": 6, "modname": "folder", "contents": [
+                    {"type": "file", "filename": "cap1.pdf", "filepath": "/semana1/", "filesize": 10,
+                     "fileurl": "https://moodle.example.edu/webservice/pluginfile.php/9/mod_folder/content/0/semana1/cap1.pdf"}]},
+                {"id": 103, "name": "Intro", "instance": 7, "modname": "page"},
+                {"id": 104, "name": "Wiki", "instance": 8, "modname": "url", "contents": [
+                    {"type": "url", "filename": "Wiki", "fileurl": "https://es.wikipedia.org/wiki/Calor"}]},
+                {"id": 105, "name": "Tarea 1", "instance": 9, "modname": "assign"}
+            ]}]"#,
+        )
+        .unwrap();
+        let items = client.section_items(&sections[0]);
+        let kinds: Vec<_> = items.iter().map(|i| i.kind.as_deref().unwrap()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "File",
+                "SubHeader",
+                "File",
+                "Page",
+                "ExternalUrl",
+                "Assignment"
+            ]
+        );
+        assert_eq!(items[2].title.as_deref(), Some("semana1/cap1.pdf"));
+        assert_eq!(items[3].page_url.as_deref(), Some("103"));
+        assert_eq!(
+            items[4].external_url.as_deref(),
+            Some("https://es.wikipedia.org/wiki/Calor")
+        );
+        assert_eq!(items[5].content_id, Some(9));
+
+        let file =
+            futures::executor::block_on(client.get_file(items[0].content_id.unwrap())).unwrap();
+        assert_eq!(file.display_name.as_deref(), Some("guia.pdf"));
+        assert_eq!(
+            file.url.as_deref(),
+            Some("https://moodle.example.edu/webservice/pluginfile.php/9/mod_resource/content/1/guia.pdf?forcedownload=1&token=tok")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::dashboard;
+use crate::redact::redact_secrets;
+use console::Term;
+use std::sync::OnceLock;
+
+/// `println!` for progress messages. It is silenced by `--quiet` and shown in the
+/// `--tui` dashboard's log panel while that is running. Messages from the Zoom
+/// modules also update the dashboard's Zoom stage panel.
+#[macro_export]
+macro_rules! report {
+    ($($arg:tt)*) => {
+        $crate::output::report(module_path!(), format!($($arg)*))
+    };
+}
+
+/// [`report!`] for details only shown with `-v`.
+#[macro_export]
+macro_rules! report_verbose {
+    ($($arg:tt)*) => {
+        $crate::output::report_verbose(module_path!(), format!($($arg)*))
+    };
+}
+
+/// `eprintln!` counterpart of [`report!`]; warnings are printed even with `--quiet`.
+#[macro_export]
+macro_rules! report_warning {
+    ($($arg:tt)*) => {
+        $crate::output::report_warning(module_path!(), format!($($arg)*))
+    };
+}
+
+/// How much the CLI prints, from `-q` / `-v` / `-vv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// Warnings, errors and command results only; no progress bars.
+    Quiet,
+    #[default]
+    Normal,
+    /// Extra details such as skipped items; debug logging.
+    Verbose,
+    /// Everything, with trace logging.
+    Trace,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// Log filter replacing `logging.level`, or `None` to keep the configured one.
+    pub fn log_level(self) -> Option<&'static str> {
+        match self {
+            Verbosity::Quiet => Some("warn"),
+            Verbosity::Normal => None,
+            Verbosity::Verbose => Some("debug"),
+            Verbosity::Trace => Some("trace"),
+        }
+    }
+}
+
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+/// Set the verbosity for the rest of the process; only the first call applies.
+pub fn set_verbosity(verbosity: Verbosity) {
+    let _ = VERBOSITY.set(verbosity);
+}
+
+pub fn verbosity() -> Verbosity {
+    VERBOSITY.get().copied().unwrap_or_default()
+}
+
+/// Whether progress bars should be drawn: not with `--quiet` or when stdout or
+/// stderr is redirected (cron, systemd), where their control characters would
+/// only add noise to logs.
+pub fn show_progress() -> bool {
+    verbosity() > Verbosity::Quiet && interactive()
+}
+
+/// Whether bars are reported as periodic plain-text status lines instead, which
+/// happens when the output is not a terminal.
+pub fn plain_status() -> bool {
+    verbosity() > Verbosity::Quiet && !interactive()
+}
+
+fn interactive() -> bool {
+    Term::stdout().is_term() && Term::stderr().is_term()
+}
+
+/// Print a progress message with secrets masked; see [`report!`].
+pub fn report(module: &str, message: String) {
+    let message = redact_secrets(&message);
+    match dashboard::active() {
+        Some(dashboard) => dashboard.log(module, &message),
+        None if verbosity() == Verbosity::Quiet => {}
+        None => println!("{}", message),
+    }
+}
+
+/// Print a detail; see [`report_verbose!`].
+pub fn report_verbose(module: &str, message: String) {
+    if verbosity() >= Verbosity::Verbose {
+        report(module, message);
+    }
+}
+
+/// Print a warning to stderr with secrets masked; see [`report_warning!`].
+pub fn report_warning(module: &str, message: String) {
+    let message = redact_secrets(&message);
+    match dashboard::active() {
+        Some(dashboard) => dashboard.log(module, &message),
+        None => eprintln!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_map_to_verbosity_and_log_level() {
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 1).log_level(), Some("debug"));
+        assert_eq!(Verbosity::from_flags(false, 3).log_level(), Some("trace"));
+        assert_eq!(Verbosity::Normal.log_level(), None);
+    }
+}
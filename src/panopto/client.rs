@@ -0,0 +1,280 @@
+use crate::config::Config;
+use crate::http::build_http_client;
+use crate::zoom::models::ZoomCookie;
+use chrono::DateTime;
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use tracing::debug;
+
+/// Sessions requested per `GetSessions` page.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum PanoptoError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("Panopto returned {0} for {1}; the session may have expired")]
+    Status(u16, String),
+    #[error("Panopto has no session {0}")]
+    UnknownSession(String),
+}
+
+/// A recorded Panopto session.
+#[derive(Debug, Clone)]
+pub struct PanoptoSession {
+    pub delivery_id: String,
+    pub name: String,
+    /// Start time as RFC 3339.
+    pub start_time: Option<String>,
+    /// Length in seconds.
+    pub duration: Option<f64>,
+    /// HLS stream, tried when the podcast download fails.
+    pub stream_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionsEnvelope {
+    d: SessionsPage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SessionsPage {
+    #[serde(default)]
+    results: Vec<ApiSession>,
+    #[serde(default)]
+    total_number: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiSession {
+    #[serde(rename = "DeliveryID")]
+    delivery_id: String,
+    session_name: Option<String>,
+    start_time: Option<String>,
+    duration: Option<f64>,
+    ios_video_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeliveryInfo {
+    delivery: Option<Delivery>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Delivery {
+    session_name: Option<String>,
+    duration: Option<f64>,
+    #[serde(default)]
+    podcast_streams: Vec<Stream>,
+    #[serde(default)]
+    streams: Vec<Stream>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Stream {
+    stream_url: Option<String>,
+}
+
+/// Client for a Panopto server's web endpoints, authenticated with the cookies
+/// of an LTI launch.
+pub struct PanoptoClient {
+    http: Client,
+    base: String,
+    cookie: String,
+}
+
+impl PanoptoClient {
+    pub fn new(cfg: &Config, host: &str, cookies: &[ZoomCookie]) -> Self {
+        let cookie = cookies
+            .iter()
+            .filter(|c| {
+                let domain = c.domain.trim_start_matches('.');
+                host == domain || host.ends_with(&format!(".{domain}"))
+            })
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self {
+            http: build_http_client(cfg),
+            base: format!("https://{host}/Panopto"),
+            cookie,
+        }
+    }
+
+    /// Headers ffmpeg needs to fetch streams and podcasts.
+    pub fn headers(&self) -> Vec<(String, String)> {
+        vec![("Cookie".to_string(), self.cookie.clone())]
+    }
+
+    /// MP4 podcast of a session.
+    pub fn podcast_url(&self, delivery_id: &str) -> String {
+        format!(
+            "{}/Podcast/Download/{}.mp4?mediaTargetType=videoPodcast",
+            self.base, delivery_id
+        )
+    }
+
+    /// Every session in a folder, oldest first.
+    pub async fn list_sessions(
+        &self,
+        folder_id: &str,
+    ) -> Result<Vec<PanoptoSession>, PanoptoError> {
+        let url = format!("{}/Services/Data.svc/GetSessions", self.base);
+        let mut sessions = Vec::new();
+        for page in 0.. {
+            let body = json!({
+                "queryParameters": {
+                    "query": null,
+                    "sortColumn": 1,
+                    "sortAscending": true,
+                    "maxResults": PAGE_SIZE,
+                    "page": page,
+                    "startDate": null,
+                    "endDate": null,
+                    "folderID": folder_id,
+                    "bookmarked": false,
+                    "getFolderData": false,
+                    "isSharedWithMe": false,
+                    "includePlaylists": false,
+                }
+            });
+            let resp = self
+                .http
+                .post(&url)
+                .header("Cookie", &self.cookie)
+                .json(&body)
+                .send()
+                .await?;
+            if !resp.status().is_success() {
+                return Err(PanoptoError::Status(resp.status().as_u16(), url));
+            }
+            let page: SessionsEnvelope = resp.json().await?;
+            let fetched = page.d.results.len();
+            sessions.extend(page.d.results.into_iter().map(PanoptoSession::from));
+            debug!(
+                folder_id,
+                fetched,
+                total = page.d.total_number,
+                "panopto sessions page"
+            );
+            if fetched < PAGE_SIZE || sessions.len() >= page.d.total_number {
+                break;
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Look up a single session, e.g. one only embedded in a Canvas page.
+    pub async fn session(&self, delivery_id: &str) -> Result<PanoptoSession, PanoptoError> {
+        let url = format!("{}/Pages/Viewer/DeliveryInfo.aspx", self.base);
+        let resp = self
+            .http
+            .post(&url)
+            .header("Cookie", &self.cookie)
+            .form(&[
+                ("deliveryId", delivery_id),
+                ("responseType", "json"),
+                ("isEmbed", "true"),
+            ])
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(PanoptoError::Status(resp.status().as_u16(), url));
+        }
+        let info: DeliveryInfo = resp.json().await?;
+        let delivery = info
+            .delivery
+            .ok_or_else(|| PanoptoError::UnknownSession(delivery_id.to_string()))?;
+        let stream_url = delivery
+            .podcast_streams
+            .iter()
+            .chain(&delivery.streams)
+            .find_map(|s| s.stream_url.clone());
+        Ok(PanoptoSession {
+            delivery_id: delivery_id.to_string(),
+            name: delivery
+                .session_name
+                .unwrap_or_else(|| delivery_id.to_string()),
+            start_time: None,
+            duration: delivery.duration,
+            stream_url,
+        })
+    }
+}
+
+impl From<ApiSession> for PanoptoSession {
+    fn from(s: ApiSession) -> Self {
+        Self {
+            name: s.session_name.unwrap_or_else(|| s.delivery_id.clone()),
+            start_time: s.start_time.as_deref().and_then(parse_dotnet_date),
+            duration: s.duration,
+            stream_url: s.ios_video_url.filter(|u| !u.is_empty()),
+            delivery_id: s.delivery_id,
+        }
+    }
+}
+
+/// Convert a WCF date such as `/Date(1700000000000-0500)/` to RFC 3339 UTC.
+fn parse_dotnet_date(s: &str) -> Option<String> {
+    let millis = s
+        .strip_prefix("/Date(")?
+        .strip_suffix(")/")?
+        .split(['+', '-'])
+        .find(|part| !part.is_empty())?;
+    // A leading '-' (before 1970) is not worth supporting
+    let millis: i64 = millis.parse().ok()?;
+    DateTime::from_timestamp_millis(millis).map(|t| t.to_rfc3339())
+}
+
+/// Folder GUID opened by a Panopto URL, e.g. `List.aspx#folderID="<guid>"`.
+pub fn folder_id_from_url(url: &str) -> Option<String> {
+    let re = Regex::new(r#"(?i)folderID=(?:%22|")?([0-9a-f]{8}-(?:[0-9a-f]{4}-){3}[0-9a-f]{12})"#)
+        .expect("valid regex");
+    re.captures(url).map(|c| c[1].to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_session_pages() {
+        let body = r#"{"d":{"__type":"SessionsResult","TotalNumber":1,"Results":[{
+            "DeliveryID":"0f9e8d7c-1111-2222-3333-444455556666",
+            "SessionName":"Clase 3 - Derivadas",
+            "StartTime":"/Date(1709294400000-0300)/",
+            "Duration":3540.5,
+            "IosVideoUrl":""}]}}"#;
+        let page: SessionsEnvelope = serde_json::from_str(body).unwrap();
+        let sessions: Vec<PanoptoSession> = page
+            .d
+            .results
+            .into_iter()
+            .map(PanoptoSession::from)
+            .collect();
+        assert_eq!(sessions[0].name, "Clase 3 - Derivadas");
+        assert_eq!(
+            sessions[0].start_time.as_deref(),
+            Some("2024-03-01T12:00:00+00:00")
+        );
+        assert_eq!(sessions[0].stream_url, None);
+    }
+
+    #[test]
+    fn finds_folder_in_launch_url() {
+        let url = "https://unab.hosted.panopto.com/Panopto/Pages/Sessions/List.aspx?embedded=1#folderID=%22A1B2C3D4-0000-1111-2222-333344445555%22";
+        assert_eq!(
+            folder_id_from_url(url).as_deref(),
+            Some("a1b2c3d4-0000-1111-2222-333344445555")
+        );
+        assert_eq!(folder_id_from_url("https://unab.hosted.panopto.com/"), None);
+    }
+}
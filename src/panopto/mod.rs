@@ -0,0 +1,237 @@
+pub mod client;
+
+use crate::canvas::{CanvasClient, ExternalTool, Tab};
+use crate::config::{Config, ConfigPaths};
+use crate::fsutil::sanitize_filename_preserve_ext;
+use crate::progress::{download_bar, spinner};
+use crate::recordings::course_html;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::headless::{display_name, fetch_recording, FetchOutcome, ZoomHeadless};
+use crate::{report, report_warning, tr};
+use client::{folder_id_from_url, PanoptoClient, PanoptoSession};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use url::Url;
+
+/// Download the Panopto recordings of a course: the sessions of the folder its
+/// Panopto LTI opens, plus sessions embedded in pages, assignments and posts.
+pub async fn run_panopto(course_id: u64, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let canvas = CanvasClient::from_config().await?;
+
+    let tools = canvas.list_external_tools(course_id).await?;
+    let tabs = canvas.list_tabs(course_id).await.unwrap_or_default();
+    let tool_id = panopto_tool_ids(&tools, &tabs)
+        .into_iter()
+        .next()
+        .ok_or_else(|| tr!("course {} has no Panopto tool", course_id))?;
+
+    let scan = spinner("Looking for embedded Panopto sessions");
+    let embeds = course_embeds(&canvas, course_id).await;
+    scan.finish_and_clear();
+
+    report!("{}", tr!("Launching Panopto (tool {})...", tool_id));
+    let launch = canvas.sessionless_launch(course_id, tool_id).await?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+    let (tool_url, cookies) = ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_tool_launch(&launch.url, "panopto")
+        .await?;
+    let host = Url::parse(&tool_url)?
+        .host_str()
+        .ok_or("Panopto launch ended on a URL without a host")?
+        .to_string();
+    let client = PanoptoClient::new(&cfg, &host, &cookies);
+
+    let folder = cfg
+        .panopto
+        .folders
+        .get(&course_id.to_string())
+        .cloned()
+        .or_else(|| folder_id_from_url(&tool_url));
+    let mut sessions = match folder.as_deref() {
+        Some(folder) => client.list_sessions(folder).await?,
+        None => {
+            report_warning!(
+                "{}",
+                tr!(
+                    "⚠ The Panopto launch did not open a folder; set panopto.folders.\"{}\" to list it.",
+                    course_id,
+                )
+            );
+            Vec::new()
+        }
+    };
+    info!(course_id, host = %host, folder = ?folder, sessions = sessions.len(), "panopto folder listed");
+
+    let mut known: HashSet<String> = sessions.iter().map(|s| s.delivery_id.clone()).collect();
+    for (embed_host, id) in embeds {
+        if embed_host != host {
+            warn!(course_id, host = %embed_host, id = %id, "skipping session on another Panopto server");
+            continue;
+        }
+        if !known.insert(id.clone()) {
+            continue;
+        }
+        match client.session(&id).await {
+            Ok(session) => sessions.push(session),
+            Err(e) => report_warning!("{}", tr!("⚠ Embedded session {}: {}", id, e)),
+        }
+    }
+
+    let base = cfg.base_root()
+        .join("Panopto")
+        .join(course_id.to_string());
+    let mut name_counts: HashMap<PathBuf, usize> = HashMap::new();
+    let mut pending = Vec::new();
+    for session in sessions {
+        let dest = session_dest(&base, &session, &mut name_counts);
+        if dest.exists() {
+            report!(
+                "{}",
+                tr!("⏩ Skipping (already exists): {}", dest.display())
+            );
+            continue;
+        }
+        pending.push((session, dest));
+    }
+
+    if dry_run {
+        for (session, dest) in &pending {
+            report!("DRY-RUN {} -> {}", session.name, dest.display());
+        }
+        report!(
+            "{}",
+            tr!("DRY-RUN: {} Panopto session(s) to download.", pending.len())
+        );
+        return Ok(());
+    }
+
+    tokio::fs::create_dir_all(&base).await?;
+    let headers = client.headers();
+    let mut downloaded = 0usize;
+    for (session, dest) in &pending {
+        let bar = download_bar(None, &display_name(dest));
+        let podcast = client.podcast_url(&session.delivery_id);
+        let start = session.start_time.as_deref();
+        let mut outcome = fetch_recording(&cfg, true, &headers, &podcast, dest, start, bar).await;
+        if outcome != FetchOutcome::Complete {
+            if let Some(stream) = session.stream_url.as_deref() {
+                report!("{}", tr!("↪ {}: trying the HLS stream...", session.name));
+                let bar = download_bar(None, &display_name(dest));
+                outcome = fetch_recording(&cfg, true, &headers, stream, dest, start, bar).await;
+            }
+        }
+        if outcome == FetchOutcome::Complete {
+            downloaded += 1;
+        }
+    }
+    report!(
+        "{}",
+        tr!(
+            "Downloaded {} of {} Panopto session(s) to {}",
+            downloaded,
+            pending.len(),
+            base.display()
+        )
+    );
+    if downloaded < pending.len() {
+        return Err(tr!("{} Panopto session(s) failed", pending.len() - downloaded).into());
+    }
+    Ok(())
+}
+
+fn is_panopto(text: Option<&str>) -> bool {
+    text.is_some_and(|t| t.to_ascii_lowercase().contains("panopto"))
+}
+
+/// Ids of installed tools and LTI tabs that look like Panopto.
+fn panopto_tool_ids(tools: &[ExternalTool], tabs: &[Tab]) -> Vec<u64> {
+    let mut ids: Vec<u64> = tools
+        .iter()
+        .filter(|t| {
+            is_panopto(t.name.as_deref())
+                || is_panopto(t.domain.as_deref())
+                || is_panopto(t.url.as_deref())
+        })
+        .map(|t| t.id)
+        .chain(
+            tabs.iter()
+                .filter(|t| is_panopto(t.label.as_deref()))
+                .filter_map(|t| t.id.strip_prefix("context_external_tool_")?.parse().ok()),
+        )
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// `(host, delivery id)` of every Panopto viewer or embed link in `html`.
+fn extract_embeds(html: &str) -> Vec<(String, String)> {
+    let re = Regex::new(
+        r#"(?i)https?://([a-z0-9.-]*panopto\.[a-z.]+)/Panopto/Pages/(?:Embed|Viewer)\.aspx\?[^"'\s<>]*?\bid=([0-9a-f]{8}-(?:[0-9a-f]{4}-){3}[0-9a-f]{12})"#,
+    )
+    .expect("valid regex");
+    re.captures_iter(html)
+        .map(|c| (c[1].to_ascii_lowercase(), c[2].to_ascii_lowercase()))
+        .collect()
+}
+
+/// Panopto sessions linked from module pages and items, assignments and posts.
+async fn course_embeds(canvas: &CanvasClient, course_id: u64) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    course_html(canvas, course_id)
+        .await
+        .iter()
+        .flat_map(|t| extract_embeds(t))
+        .filter(|embed| seen.insert(embed.clone()))
+        .collect()
+}
+
+/// `<base>/<date> <session name>.mp4`, numbered when names repeat.
+fn session_dest(
+    base: &Path,
+    session: &PanoptoSession,
+    name_counts: &mut HashMap<PathBuf, usize>,
+) -> PathBuf {
+    let date = session.start_time.as_deref().and_then(|t| t.get(..10));
+    let stem = match date {
+        Some(date) => format!("{} {}", date, session.name),
+        None => session.name.clone(),
+    };
+    let planned = base.join(sanitize_filename_preserve_ext(format!("{stem}.mp4")));
+    let count = name_counts.entry(planned.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        return planned;
+    }
+    base.join(sanitize_filename_preserve_ext(format!(
+        "{stem} ({count}).mp4"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_embedded_sessions() {
+        let html = r#"<iframe src="https://unab.hosted.panopto.com/Panopto/Pages/Embed.aspx?id=0F9E8D7C-1111-2222-3333-444455556666&autoplay=false"></iframe>
+            <a href="https://unab.hosted.panopto.com/Panopto/Pages/Viewer.aspx?tid=1&id=0f9e8d7c-aaaa-2222-3333-444455556666">Clase</a>"#;
+        assert_eq!(
+            extract_embeds(html),
+            vec![
+                (
+                    "unab.hosted.panopto.com".to_string(),
+                    "0f9e8d7c-1111-2222-3333-444455556666".to_string()
+                ),
+                (
+                    "unab.hosted.panopto.com".to_string(),
+                    "0f9e8d7c-aaaa-2222-3333-444455556666".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Why a dry run would write or download an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlanReason {
+    /// Never synced, or its local copy is gone.
+    New,
+    /// The file's size or update time changed; a real run revalidates its ETag.
+    ChangedEtag,
+    /// The Markdown rendered from it differs from the one last written.
+    ChangedHash,
+    /// A partial download is continued.
+    Resume,
+    /// The previous attempt failed.
+    Retry,
+}
+
+impl fmt::Display for PlanReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlanReason::New => "new",
+            PlanReason::ChangedEtag => "changed-etag",
+            PlanReason::ChangedHash => "changed-hash",
+            PlanReason::Resume => "resume",
+            PlanReason::Retry => "retry",
+        })
+    }
+}
+
+/// One item a real run would write or download.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PlanAction {
+    pub course_id: u64,
+    /// State key of the item, e.g. `page:intro` or `file:42`.
+    pub key: String,
+    /// Destination, relative to `download_root` once the plan is built.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub reason: PlanReason,
+}
+
+/// The actions of a dry run, sorted by course and key so that two plans of the
+/// same archive compare line by line.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub actions: Vec<PlanAction>,
+}
+
+impl Plan {
+    /// Sort `actions`, drop repeats and make their paths relative to `root`.
+    pub fn new(mut actions: Vec<PlanAction>, root: &Path) -> Plan {
+        for a in &mut actions {
+            if let Some(path) = a.path.as_deref() {
+                if let Ok(rel) = Path::new(path).strip_prefix(root) {
+                    a.path = Some(rel.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        actions.sort();
+        actions.dedup_by(|a, b| a.course_id == b.course_id && a.key == b.key);
+        Plan { actions }
+    }
+
+    pub async fn load(path: &Path) -> std::io::Result<Plan> {
+        let buf = tokio::fs::read(path).await?;
+        serde_json::from_slice(&buf)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut json = serde_json::to_vec_pretty(self)?;
+        json.push(b'\n');
+        crate::fsutil::atomic_write(path, &json).await
+    }
+
+    /// Actions per reason, for the dry-run summary.
+    pub fn counts(&self) -> BTreeMap<PlanReason, usize> {
+        let mut counts = BTreeMap::new();
+        for a in &self.actions {
+            *counts.entry(a.reason).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Lines describing how this plan differs from `previous`: `+` for new actions,
+    /// `-` for ones no longer planned and `~` for ones planned for another reason.
+    pub fn diff(&self, previous: &Plan) -> Vec<String> {
+        let key = |a: &PlanAction| (a.course_id, a.key.clone());
+        let before: BTreeMap<_, &PlanAction> =
+            previous.actions.iter().map(|a| (key(a), a)).collect();
+        let after: BTreeMap<_, &PlanAction> = self.actions.iter().map(|a| (key(a), a)).collect();
+        let mut lines = Vec::new();
+        for (k, a) in &after {
+            match before.get(k) {
+                None => lines.push(format!("+ {}", describe(a))),
+                Some(b) if b.reason != a.reason => {
+                    lines.push(format!("~ {} (was {})", describe(a), b.reason))
+                }
+                Some(_) => {}
+            }
+        }
+        for (k, b) in &before {
+            if !after.contains_key(k) {
+                lines.push(format!("- {}", describe(b)));
+            }
+        }
+        lines
+    }
+}
+
+fn describe(a: &PlanAction) -> String {
+    match a.path.as_deref() {
+        Some(path) => format!(
+            "[{}] course {} {} -> {}",
+            a.reason, a.course_id, a.key, path
+        ),
+        None => format!("[{}] course {} {}", a.reason, a.course_id, a.key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(key: &str, path: &str, reason: PlanReason) -> PlanAction {
+        PlanAction {
+            course_id: 1,
+            key: key.into(),
+            path: Some(path.into()),
+            reason,
+        }
+    }
+
+    #[test]
+    fn plans_are_sorted_relative_and_diffable() {
+        let root = Path::new("/archive");
+        let previous = Plan::new(
+            vec![
+                action("page:intro", "/archive/C/01-Intro.md", PlanReason::New),
+                action("file:7", "/archive/C/guia.pdf", PlanReason::New),
+            ],
+            root,
+        );
+        assert_eq!(previous.actions[0].key, "file:7");
+        assert_eq!(previous.actions[1].path.as_deref(), Some("C/01-Intro.md"));
+
+        let current = Plan::new(
+            vec![
+                action("file:7", "/archive/C/guia.pdf", PlanReason::Resume),
+                action("file:8", "/archive/C/pauta.pdf", PlanReason::ChangedEtag),
+            ],
+            root,
+        );
+        assert_eq!(
+            current.diff(&previous),
+            vec![
+                "~ [resume] course 1 file:7 -> C/guia.pdf (was new)",
+                "+ [changed-etag] course 1 file:8 -> C/pauta.pdf",
+                "- [new] course 1 page:intro -> C/01-Intro.md",
+            ]
+        );
+        assert!(current.diff(&current).is_empty());
+    }
+}
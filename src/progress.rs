@@ -1,4 +1,23 @@
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::dashboard::{self, BarKind};
+use crate::output;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle, WeakProgressBar};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+/// How often bars are reported as plain text when the output is not a terminal.
+const STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Bars reported as plain text, see [`watch`].
+static STATUS: Mutex<Vec<(BarKind, WeakProgressBar)>> = Mutex::new(Vec::new());
+static STATUS_THREAD: Once = Once::new();
+
+/// Overall byte bar of the running sync, see [`start_transfer`].
+static TRANSFER: Mutex<Option<Transfer>> = Mutex::new(None);
+
+struct Transfer {
+    bars: MultiProgress,
+    total: ProgressBar,
+}
 
 fn default_style() -> ProgressStyle {
     ProgressStyle::with_template("{spinner:.blue} {msg} [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
@@ -14,6 +33,186 @@ pub fn progress_bar(len: u64, message: &str) -> ProgressBar {
     let pb = ProgressBar::new(len);
     pb.set_style(default_style());
     pb.set_message(message.to_string());
+    track(BarKind::Progress, &pb);
+    pb
+}
+
+/// Hand `pb` to the `--tui` dashboard when it is running, or hide it when
+/// progress bars are off (`--quiet` or no terminal). Without a terminal its
+/// state is printed as plain status lines instead.
+fn track(kind: BarKind, pb: &ProgressBar) {
+    match dashboard::active() {
+        Some(d) => d.track(kind, pb),
+        None if !output::show_progress() => {
+            pb.set_draw_target(ProgressDrawTarget::hidden());
+            if output::plain_status() {
+                watch(kind, pb);
+            }
+        }
+        // Download bars join through `attach` or the caller's own `MultiProgress`
+        None if kind != BarKind::Download => {
+            attach(pb.clone());
+        }
+        None => {}
+    }
+}
+
+/// Show one bar for the bytes of every download until the returned guard is
+/// dropped. Bars created meanwhile are drawn above it through one `MultiProgress`,
+/// so they no longer redraw over each other.
+pub fn start_transfer() -> TransferGuard {
+    let bars = multi_progress();
+    let total = bars.add(ProgressBar::new(0));
+    total.set_style(
+        ProgressStyle::with_template(
+            "{prefix:.bold} [{bar:40.green/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+        )
+        .unwrap()
+        .progress_chars("##-"),
+    );
+    total.set_prefix("Total");
+    total.enable_steady_tick(std::time::Duration::from_millis(250));
+    if let Some(d) = dashboard::active() {
+        d.track(BarKind::Download, &total);
+    } else if output::plain_status() {
+        watch(BarKind::Download, &total);
+    }
+    *TRANSFER.lock().unwrap() = Some(Transfer { bars, total });
+    TransferGuard(())
+}
+
+/// Removes the overall byte bar when dropped.
+pub struct TransferGuard(());
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        if let Some(t) = TRANSFER.lock().unwrap().take() {
+            t.total.finish_and_clear();
+        }
+    }
+}
+
+/// Whether per-file download bars are shown: on the dashboard, or above a drawn
+/// overall byte bar.
+pub fn file_bars() -> bool {
+    dashboard::active().is_some()
+        || TRANSFER
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|t| !t.bars.is_hidden())
+}
+
+/// Report `pb` every [`STATUS_INTERVAL`] until it finishes, from a thread started
+/// with the first watched bar.
+fn watch(kind: BarKind, pb: &ProgressBar) {
+    let mut bars = STATUS.lock().unwrap();
+    bars.retain(|(_, weak)| weak.upgrade().is_some_and(|b| !b.is_finished()));
+    bars.push((kind, pb.downgrade()));
+    STATUS_THREAD.call_once(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(STATUS_INTERVAL);
+            for line in status_lines() {
+                output::report(module_path!(), line);
+            }
+        });
+    });
+}
+
+/// One line per running bar, e.g. `[status] Syncing courses 3/12` or
+/// `[status] Total 1.20 MB / 4.00 MB (30%) 512.00 KB/s`.
+fn status_lines() -> Vec<String> {
+    let bars = STATUS.lock().unwrap();
+    bars.iter()
+        .filter_map(|(kind, weak)| Some((kind, weak.upgrade()?)))
+        .filter(|(_, bar)| !bar.is_finished())
+        .map(|(kind, bar)| {
+            let line = match kind {
+                BarKind::Progress => match bar.length() {
+                    Some(len) => format!("{} {}/{}", bar.message(), bar.position(), len),
+                    None => format!("{} {}", bar.message(), bar.position()),
+                },
+                BarKind::Spinner => format!("{}...", bar.message()),
+                BarKind::Download => dashboard::download_line(&bar).trim().to_string(),
+            };
+            format!("[status] {}", line)
+        })
+        .collect()
+}
+
+/// Draw `pb` above the overall byte bar when one is shown.
+pub fn attach(pb: ProgressBar) -> ProgressBar {
+    match TRANSFER.lock().unwrap().as_ref() {
+        Some(t) if dashboard::active().is_none() => t.bars.insert_before(&t.total, pb),
+        _ => pb,
+    }
+}
+
+/// Add a download's known length to the overall total.
+pub fn transfer_expect(bytes: u64) {
+    if let Some(t) = TRANSFER.lock().unwrap().as_ref() {
+        t.total.inc_length(bytes);
+    }
+}
+
+/// Count bytes received for the overall total.
+pub fn transfer_inc(bytes: u64) {
+    if let Some(t) = TRANSFER.lock().unwrap().as_ref() {
+        t.total.inc(bytes);
+    }
+}
+
+/// Container for concurrent download bars; hidden while the dashboard draws them
+/// or when progress bars are off. During a sync, the one holding the overall byte bar.
+pub fn multi_progress() -> MultiProgress {
+    if let Some(t) = TRANSFER.lock().unwrap().as_ref() {
+        return t.bars.clone();
+    }
+    if dashboard::active().is_some() || !output::show_progress() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+/// Print `line` above `pb`, or through [`output::report`] when the bar is not drawn.
+pub fn bar_println(pb: &ProgressBar, line: String) {
+    if pb.is_hidden() {
+        output::report(module_path!(), line);
+    } else {
+        pb.println(line);
+    }
+}
+
+/// Byte-count bar for a single download labelled `name`, with speed and ETA when
+/// `total` is known. The message slot is left for live status such as media time.
+pub fn download_bar(total: Option<u64>, name: &str) -> ProgressBar {
+    let pb = match total {
+        Some(len) => {
+            let pb = ProgressBar::new(len);
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.blue} {prefix} {msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )
+                .unwrap()
+                .progress_chars("##-"),
+            );
+            pb
+        }
+        None => {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.blue} {prefix} {msg} {bytes} ({bytes_per_sec}, {elapsed})",
+                )
+                .unwrap(),
+            );
+            pb
+        }
+    };
+    pb.set_prefix(name.to_string());
+    pb.enable_steady_tick(std::time::Duration::from_millis(250));
+    track(BarKind::Download, &pb);
     pb
 }
 
@@ -22,5 +221,40 @@ pub fn spinner(message: &str) -> ProgressBar {
     pb.set_style(spinner_style());
     pb.set_message(message.to_string());
     pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    track(BarKind::Spinner, &pb);
     pb
 }
+
+/// Human-readable byte count using binary units.
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watched_bars_are_reported_until_they_finish() {
+        let pb = ProgressBar::hidden();
+        pb.set_length(12);
+        pb.set_position(3);
+        pb.set_message("Watched bar");
+        watch(BarKind::Progress, &pb);
+        assert!(status_lines().contains(&"[status] Watched bar 3/12".to_string()));
+        pb.finish();
+        assert!(!status_lines().iter().any(|l| l.contains("Watched bar")));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::canvas::{
+    Assignment, CalendarEvent, CanvasClient, CanvasError, Course, FileObj, MediaObject, Module,
+    PageObj, PageRevision, Quiz, QuizQuestion,
+};
+use crate::groups::GroupProvider;
+use crate::roster::Roster;
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Last update times of a course's pages (by URL slug) and files (by id), listed
+/// in bulk so a `sync --since` can tell unchanged items apart without fetching each.
+#[derive(Debug, Default)]
+pub struct UpdateTimes {
+    pub pages: HashMap<String, String>,
+    pub files: HashMap<u64, String>,
+}
+
+/// An LMS backend the sync engine can read courses from. Canvas is the only
+/// implementation today; the course, module, page, file and assignment types double
+/// as the provider-neutral model, so other backends map their API onto them.
+pub trait CourseProvider: Sync {
+    type Error: std::error::Error + Send + Sync + 'static + Into<crate::Error>;
+
+    /// Courses the signed-in user is enrolled in.
+    fn list_courses(&self) -> impl Future<Output = Result<Vec<Course>, Self::Error>> + Send;
+
+    /// Modules of a course in display order, with their items.
+    fn list_modules_with_items(
+        &self,
+        course_id: u64,
+    ) -> impl Future<Output = Result<Vec<Module>, Self::Error>> + Send;
+
+    /// A content page by its URL slug.
+    fn get_page(
+        &self,
+        course_id: u64,
+        page_url: &str,
+    ) -> impl Future<Output = Result<PageObj, Self::Error>> + Send;
+
+    /// File metadata, including the URL it downloads from.
+    fn get_file(&self, file_id: u64) -> impl Future<Output = Result<FileObj, Self::Error>> + Send;
+
+    /// Assignments of a course, with their HTML descriptions.
+    fn list_assignments(
+        &self,
+        course_id: u64,
+    ) -> impl Future<Output = Result<Vec<Assignment>, Self::Error>> + Send;
+
+    /// Calendar events of a course; backends without a course calendar return none.
+    fn list_calendar_events(
+        &self,
+        _course_id: u64,
+    ) -> impl Future<Output = Result<Vec<CalendarEvent>, Self::Error>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// People enrolled in a course and its groups, or `None` when the backend
+    /// cannot list them.
+    fn course_roster(
+        &self,
+        _course_id: u64,
+    ) -> impl Future<Output = Result<Option<Roster>, Self::Error>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Quizzes of a course; backends without quizzes return none.
+    fn list_quizzes(
+        &self,
+        _course_id: u64,
+    ) -> impl Future<Output = Result<Vec<Quiz>, Self::Error>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// A quiz missing from [`list_quizzes`](Self::list_quizzes), e.g. when listing
+    /// is not allowed; `None` if the backend has no quizzes.
+    fn get_quiz(
+        &self,
+        _course_id: u64,
+        _quiz_id: u64,
+    ) -> impl Future<Output = Result<Option<Quiz>, Self::Error>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Questions of a quiz, where the account may see them.
+    fn list_quiz_questions(
+        &self,
+        _course_id: u64,
+        _quiz_id: u64,
+    ) -> impl Future<Output = Result<Vec<QuizQuestion>, Self::Error>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// Saved versions of a page, newest first; backends without page history
+    /// return none.
+    fn list_page_revisions(
+        &self,
+        _course_id: u64,
+        _page_url: &str,
+    ) -> impl Future<Output = Result<Vec<PageRevision>, Self::Error>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// A page as saved in revision `revision_id`, with its body; `None` if the
+    /// backend has no page history.
+    fn get_page_revision(
+        &self,
+        _course_id: u64,
+        _page_url: &str,
+        _revision_id: u64,
+    ) -> impl Future<Output = Result<Option<PageRevision>, Self::Error>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Update times of a course's pages and files, or `None` when the backend cannot
+    /// list them; every item is then fetched.
+    fn update_times(
+        &self,
+        _course_id: u64,
+    ) -> impl Future<Output = Result<Option<UpdateTimes>, Self::Error>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The user's group workspaces, read through the same pipeline as courses;
+    /// `None` when the backend has no groups.
+    fn group_provider(&self) -> Option<GroupProvider<'_>> {
+        None
+    }
+
+    /// Hosted media of a course; backends without a media library return none.
+    fn list_media_objects(
+        &self,
+        _course_id: u64,
+    ) -> impl Future<Output = Result<Vec<MediaObject>, Self::Error>> + Send {
+        async { Ok(Vec::new()) }
+    }
+}
+
+impl CourseProvider for CanvasClient {
+    type Error = CanvasError;
+
+    async fn list_courses(&self) -> Result<Vec<Course>, CanvasError> {
+        CanvasClient::list_courses(self).await
+    }
+
+    async fn list_modules_with_items(&self, course_id: u64) -> Result<Vec<Module>, CanvasError> {
+        CanvasClient::list_modules_with_items(self, course_id).await
+    }
+
+    async fn get_page(&self, course_id: u64, page_url: &str) -> Result<PageObj, CanvasError> {
+        CanvasClient::get_page(self, course_id, page_url).await
+    }
+
+    async fn get_file(&self, file_id: u64) -> Result<FileObj, CanvasError> {
+        CanvasClient::get_file(self, file_id).await
+    }
+
+    async fn list_assignments(&self, course_id: u64) -> Result<Vec<Assignment>, CanvasError> {
+        CanvasClient::list_assignments(self, course_id).await
+    }
+
+    async fn list_calendar_events(
+        &self,
+        course_id: u64,
+    ) -> Result<Vec<CalendarEvent>, CanvasError> {
+        CanvasClient::list_calendar_events(self, course_id).await
+    }
+
+    fn group_provider(&self) -> Option<GroupProvider<'_>> {
+        Some(GroupProvider::new(self))
+    }
+
+    async fn course_roster(&self, course_id: u64) -> Result<Option<Roster>, CanvasError> {
+        crate::roster::fetch_roster(self, course_id).await.map(Some)
+    }
+
+    async fn list_quizzes(&self, course_id: u64) -> Result<Vec<Quiz>, CanvasError> {
+        CanvasClient::list_quizzes(self, course_id).await
+    }
+
+    async fn get_quiz(&self, course_id: u64, quiz_id: u64) -> Result<Option<Quiz>, CanvasError> {
+        CanvasClient::get_quiz(self, course_id, quiz_id)
+            .await
+            .map(Some)
+    }
+
+    async fn list_quiz_questions(
+        &self,
+        course_id: u64,
+        quiz_id: u64,
+    ) -> Result<Vec<QuizQuestion>, CanvasError> {
+        CanvasClient::list_quiz_questions(self, course_id, quiz_id).await
+    }
+
+    async fn list_page_revisions(
+        &self,
+        course_id: u64,
+        page_url: &str,
+    ) -> Result<Vec<PageRevision>, CanvasError> {
+        CanvasClient::list_page_revisions(self, course_id, page_url).await
+    }
+
+    async fn get_page_revision(
+        &self,
+        course_id: u64,
+        page_url: &str,
+        revision_id: u64,
+    ) -> Result<Option<PageRevision>, CanvasError> {
+        CanvasClient::get_page_revision(self, course_id, page_url, revision_id)
+            .await
+            .map(Some)
+    }
+
+    async fn update_times(&self, course_id: u64) -> Result<Option<UpdateTimes>, CanvasError> {
+        let mut times = UpdateTimes::default();
+        for page in self.list_pages(course_id).await? {
+            if let Some(updated_at) = page.updated_at {
+                times.pages.insert(page.url, updated_at);
+            }
+        }
+        // Without permission to list files, each file is still fetched on its own
+        if let Ok(files) = self.list_files(course_id).await {
+            for file in files {
+                if let Some(updated_at) = file.updated_at {
+                    times.files.insert(file.id, updated_at);
+                }
+            }
+        }
+        Ok(Some(times))
+    }
+
+    async fn list_media_objects(&self, course_id: u64) -> Result<Vec<MediaObject>, CanvasError> {
+        CanvasClient::list_media_objects(self, course_id).await
+    }
+}
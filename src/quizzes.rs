@@ -0,0 +1,160 @@
+use crate::canvas::{Quiz, QuizQuestion};
+use html2md::parse_html;
+use std::fmt::Write as _;
+
+/// Markdown for a quiz: its settings, instructions and, when the account may see
+/// them, its questions with their answer options.
+pub fn render_quiz(quiz: &Quiz, questions: &[QuizQuestion]) -> String {
+    let title = quiz.title.as_deref().unwrap_or("Quiz");
+    let mut out = format!("# {}\n\n", title);
+    let mut fact = |label: &str, value: Option<String>| {
+        if let Some(value) = value {
+            let _ = writeln!(out, "- **{}:** {}", label, value);
+        }
+    };
+    fact("Type", quiz.quiz_type.as_deref().map(quiz_type_label));
+    fact("Points", quiz.points_possible.map(format_points));
+    fact("Questions", quiz.question_count.map(|n| n.to_string()));
+    fact("Time limit", quiz.time_limit.map(|m| format!("{} min", m)));
+    fact(
+        "Allowed attempts",
+        quiz.allowed_attempts.map(|n| match n {
+            n if n < 0 => "unlimited".to_string(),
+            n => n.to_string(),
+        }),
+    );
+    fact("Available from", quiz.unlock_at.clone());
+    fact("Available until", quiz.lock_at.clone());
+    fact("Due", quiz.due_at.clone());
+
+    let description = quiz.description.as_deref().unwrap_or_default();
+    if !description.trim().is_empty() {
+        let _ = write!(out, "\n{}\n", parse_html(description).trim_end());
+    }
+
+    if !questions.is_empty() {
+        out.push_str("\n## Questions\n");
+        let mut ordered: Vec<&QuizQuestion> = questions.iter().collect();
+        ordered.sort_by_key(|q| (q.position.unwrap_or(u32::MAX), q.id));
+        for (n, q) in ordered.into_iter().enumerate() {
+            let _ = write!(
+                out,
+                "\n### {}. {}",
+                n + 1,
+                q.question_name.as_deref().unwrap_or("Question")
+            );
+            if let Some(points) = q.points_possible {
+                let _ = write!(out, " ({} pts)", format_points(points));
+            }
+            out.push('\n');
+            let text = parse_html(q.question_text.as_deref().unwrap_or_default());
+            if !text.trim().is_empty() {
+                let _ = write!(out, "\n{}\n", text.trim_end());
+            }
+            let answers: Vec<String> = q
+                .answers
+                .iter()
+                .filter_map(|a| {
+                    let text = match a.html.as_deref().filter(|h| !h.trim().is_empty()) {
+                        Some(html) => parse_html(html).trim().to_string(),
+                        None => a.text.clone()?.trim().to_string(),
+                    };
+                    let correct = a.weight.is_some_and(|w| w >= 100.0);
+                    (!text.is_empty())
+                        .then(|| format!("- {}{}", text, if correct { " ✓" } else { "" }))
+                })
+                .collect();
+            if !answers.is_empty() {
+                let _ = write!(out, "\n{}\n", answers.join("\n"));
+            }
+        }
+    }
+    out
+}
+
+/// HTML of the quiz description and question texts, for the files and videos they link to.
+pub fn linked_html(quiz: &Quiz, questions: &[QuizQuestion]) -> String {
+    let mut html = quiz.description.clone().unwrap_or_default();
+    for q in questions {
+        html.push('\n');
+        html.push_str(q.question_text.as_deref().unwrap_or_default());
+    }
+    html
+}
+
+fn quiz_type_label(kind: &str) -> String {
+    match kind {
+        "assignment" => "Graded quiz".to_string(),
+        "practice_quiz" => "Practice quiz".to_string(),
+        "graded_survey" => "Graded survey".to_string(),
+        "survey" => "Survey".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Points without a trailing `.0` for whole numbers.
+pub(crate) fn format_points(points: f64) -> String {
+    if points.fract() == 0.0 {
+        format!("{}", points as i64)
+    } else {
+        format!("{}", points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::QuizAnswer;
+
+    #[test]
+    fn renders_settings_description_and_questions() {
+        let quiz = Quiz {
+            id: 9,
+            title: Some("Quiz 1".into()),
+            html_url: None,
+            description: Some("<p>Read <strong>chapter 2</strong> first.</p>".into()),
+            quiz_type: Some("assignment".into()),
+            points_possible: Some(10.0),
+            question_count: Some(2),
+            time_limit: Some(30),
+            allowed_attempts: Some(-1),
+            due_at: Some("2024-03-08T23:59:00Z".into()),
+            unlock_at: None,
+            lock_at: None,
+            updated_at: None,
+        };
+        let question = |id, position, text: &str, answers| QuizQuestion {
+            id,
+            position: Some(position),
+            question_name: Some(format!("Pregunta {}", position)),
+            question_type: Some("multiple_choice_question".into()),
+            question_text: Some(format!("<p>{}</p>", text)),
+            points_possible: Some(5.0),
+            answers,
+        };
+        let answer = |text: &str, weight| QuizAnswer {
+            text: Some(text.into()),
+            html: None,
+            weight: Some(weight),
+        };
+        let questions = [
+            question(2, 2, "Unidad de fuerza", vec![]),
+            question(1, 1, "2 + 2", vec![answer("4", 100.0), answer("5", 0.0)]),
+        ];
+        let md = render_quiz(&quiz, &questions);
+        assert_eq!(
+            md,
+            "# Quiz 1\n\n\
+             - **Type:** Graded quiz\n\
+             - **Points:** 10\n\
+             - **Questions:** 2\n\
+             - **Time limit:** 30 min\n\
+             - **Allowed attempts:** unlimited\n\
+             - **Due:** 2024-03-08T23:59:00Z\n\
+             \nRead **chapter 2** first.\n\
+             \n## Questions\n\
+             \n### 1. Pregunta 1 (5 pts)\n\n2 + 2\n\n- 4 ✓\n- 5\n\
+             \n### 2. Pregunta 2 (5 pts)\n\nUnidad de fuerza\n"
+        );
+    }
+}
@@ -1,9 +1,21 @@
 use crate::canvas::{CanvasClient, Module};
+use crate::config::ConfigPaths;
+use crate::external_video;
 use crate::http::build_http_client;
+use crate::media::discover_media;
 use crate::progress::{progress_bar, spinner};
+use crate::state::State;
+use crate::syncer::{module_dir_for, resolve_course_dir};
+use crate::tr;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::models::{is_recording_link, DiscoveredLink};
 use regex::Regex;
 use tracing::info;
 
+/// Scan course pages, module items and assignments for Zoom links. Recording share
+/// links are saved to the Zoom DB for `zoom from-links` unless `dry_run` is set.
+/// YouTube and Vimeo videos in module pages are archived into the module's `Media`
+/// folder when an external video downloader is configured.
 pub async fn run_discovery(
     filter_course_id: Option<u64>,
     dry_run: bool,
@@ -17,34 +29,85 @@ pub async fn run_discovery(
     if let Some(cid) = filter_course_id {
         courses.retain(|c| c.id == cid);
         if courses.is_empty() {
-            println!("No active course with id {} found.", cid);
+            println!("{}", tr!("No active course with id {} found.", cid));
             return Ok(());
         }
     }
 
+    let db = if dry_run {
+        None
+    } else {
+        Some(ZoomDb::new(&ConfigPaths::new()?.config_dir)?)
+    };
     let mut total = 0usize;
+    let mut saved = 0usize;
+    let mut saved_courses = Vec::new();
     let course_progress = progress_bar(courses.len() as u64, "Scanning courses for Zoom links");
     for course in courses {
         course_progress.inc(1);
-        course_progress.set_message(format!("Scanning course {}", course.id));
+        course_progress.set_message(tr!("Scanning course {}", course.id));
         info!(course_id = course.id, name = %course.name, "scan recordings");
-        let modules_spinner = spinner(&format!("Loading modules for {}", course.name));
+        // Recording links with the title of the page, item or assignment posting them
+        let mut found: Vec<(String, Option<String>)> = Vec::new();
+        let course_dir = resolve_course_dir(&cfg, &course, dry_run).await?;
+        let state_path = course_dir.join("state.json");
+        let mut state = State::load(&state_path).await;
+        let mut archived = 0usize;
+        let modules_spinner = spinner(&tr!("Loading modules for {}", course.name));
         let modules: Vec<Module> = canvas
             .list_modules_with_items(course.id)
             .await
             .unwrap_or_default();
         modules_spinner.finish_and_clear();
         let module_progress =
-            progress_bar(modules.len() as u64, &format!("Modules in {}", course.name));
+            progress_bar(modules.len() as u64, &tr!("Modules in {}", course.name));
         for module in modules {
             module_progress.inc(1);
-            module_progress.set_message(format!("Module {}", module.id));
+            module_progress.set_message(tr!("Module {}", module.id));
+            let media_dir = module_dir_for(&course_dir, &module, &cfg.week_pattern).join("Media");
             for item in module.items {
                 if let Some(page_url) = item.page_url.as_deref() {
                     if let Ok(page) = canvas.get_page(course.id, page_url).await {
                         let html = page.body.unwrap_or_default();
+                        for embed in discover_media(&html) {
+                            let Some(url) = embed.external_url() else {
+                                continue;
+                            };
+                            println!(
+                                "{}[course:{}] {:<40} | module:{} | page:{} | video {}",
+                                if dry_run { "DRY-RUN " } else { "" },
+                                course.id,
+                                course.name,
+                                module.id,
+                                page_url,
+                                url
+                            );
+                            let Some(settings) = cfg.external_video_downloader.as_ref() else {
+                                continue;
+                            };
+                            if dry_run {
+                                continue;
+                            }
+                            match external_video::archive(
+                                settings,
+                                embed.state_key(),
+                                &url,
+                                &media_dir,
+                                &mut state,
+                            )
+                            .await
+                            {
+                                Ok(Some(path)) => {
+                                    archived += 1;
+                                    println!("{}", tr!("✓ Archived {}", path.display()));
+                                }
+                                Ok(None) => {}
+                                Err(e) => println!("✗ {}: {}", url, e),
+                            }
+                        }
                         for url in extract_zoom_links(&html) {
                             total += 1;
+                            found.push((url.clone(), page.title.clone()));
                             println!(
                                 "{}[course:{}] {:<40} | module:{} | page:{} | {}",
                                 if dry_run { "DRY-RUN " } else { "" },
@@ -61,6 +124,7 @@ pub async fn run_discovery(
                 if let Some(u) = item.external_url.as_deref().or(item.html_url.as_deref()) {
                     for url in extract_zoom_links(u) {
                         total += 1;
+                        found.push((url.clone(), item.title.clone()));
                         println!(
                             "{}[course:{}] {:<40} | module:{} | item:{} | {}",
                             if dry_run { "DRY-RUN " } else { "" },
@@ -76,13 +140,14 @@ pub async fn run_discovery(
         }
         module_progress.finish_and_clear();
 
-        let assignments_spinner = spinner(&format!("Loading assignments for {}", course.name));
+        let assignments_spinner = spinner(&tr!("Loading assignments for {}", course.name));
         let assignments = canvas.list_assignments(course.id).await.unwrap_or_default();
         assignments_spinner.finish_and_clear();
         for assignment in assignments {
             if let Some(desc) = assignment.description.as_deref() {
                 for url in extract_zoom_links(desc) {
                     total += 1;
+                    found.push((url.clone(), assignment.name.clone()));
                     println!(
                         "{}[course:{}] {:<40} | assignment:{} | {}",
                         if dry_run { "DRY-RUN " } else { "" },
@@ -94,17 +159,179 @@ pub async fn run_discovery(
                 }
             }
         }
+
+        let posts_spinner = spinner(&tr!(
+            "Loading announcements, discussions, syllabus and events for {}",
+            course.name
+        ));
+        let posts = course_posts(&canvas, course.id).await;
+        posts_spinner.finish_and_clear();
+        for post in posts {
+            for url in extract_zoom_links(&post.body) {
+                total += 1;
+                found.push((url.clone(), post.title.clone()));
+                println!(
+                    "{}[course:{}] {:<40} | {} | {}",
+                    if dry_run { "DRY-RUN " } else { "" },
+                    course.id,
+                    course.name,
+                    post.source,
+                    url
+                );
+            }
+        }
+
+        if archived > 0 {
+            state.save(&state_path).await?;
+        }
+
+        if let Some(db) = &db {
+            let mut course_saved = 0usize;
+            for (url, label) in found {
+                if !is_recording_link(&url) {
+                    continue;
+                }
+                db.save_link(
+                    course.id,
+                    &DiscoveredLink {
+                        url,
+                        label,
+                        play_url: None,
+                        meeting_id: None,
+                    },
+                )?;
+                course_saved += 1;
+            }
+            if course_saved > 0 {
+                saved += course_saved;
+                saved_courses.push(course.id);
+            }
+        }
     }
     course_progress.finish_and_clear();
 
     println!(
-        "{}Discovered {} Zoom link(s).",
-        if dry_run { "DRY-RUN: " } else { "" },
-        total
+        "{}",
+        tr!(
+            "{}Discovered {} Zoom link(s).",
+            if dry_run { "DRY-RUN: " } else { "" },
+            total
+        )
     );
+    if saved > 0 {
+        println!(
+            "{}",
+            tr!("Saved {} recording link(s) to the Zoom DB.", saved)
+        );
+        for course_id in saved_courses {
+            println!(
+                "{}",
+                tr!(
+                    "Run 'u_crawler zoom from-links --course-id {}' to download them.",
+                    course_id
+                )
+            );
+        }
+    }
     Ok(())
 }
 
+/// HTML written by the teacher outside modules and assignments.
+struct Post {
+    /// Where the text lives, e.g. `announcement:12` or `syllabus`.
+    source: String,
+    title: Option<String>,
+    body: String,
+}
+
+/// Announcements, discussion topics and their replies, the syllabus, and calendar
+/// event descriptions of a course. Sources the user cannot read are skipped.
+async fn course_posts(canvas: &CanvasClient, course_id: u64) -> Vec<Post> {
+    let mut posts = Vec::new();
+    for topic in canvas
+        .list_announcements(course_id)
+        .await
+        .unwrap_or_default()
+    {
+        posts.push(Post {
+            source: format!("announcement:{}", topic.id),
+            title: topic.title,
+            body: topic.message.unwrap_or_default(),
+        });
+    }
+    for topic in canvas
+        .list_discussion_topics(course_id)
+        .await
+        .unwrap_or_default()
+    {
+        for entry in canvas
+            .list_discussion_entries(course_id, topic.id)
+            .await
+            .unwrap_or_default()
+        {
+            posts.push(Post {
+                source: format!("discussion:{} entry:{}", topic.id, entry.id),
+                title: topic.title.clone(),
+                body: entry.message.unwrap_or_default(),
+            });
+        }
+        posts.push(Post {
+            source: format!("discussion:{}", topic.id),
+            title: topic.title,
+            body: topic.message.unwrap_or_default(),
+        });
+    }
+    if let Ok(Some(body)) = canvas.get_syllabus(course_id).await {
+        posts.push(Post {
+            source: "syllabus".into(),
+            title: Some("Syllabus".into()),
+            body,
+        });
+    }
+    for event in canvas
+        .list_calendar_events(course_id)
+        .await
+        .unwrap_or_default()
+    {
+        posts.push(Post {
+            source: format!("event:{}", event.id),
+            title: event.title,
+            body: event.description.unwrap_or_default(),
+        });
+    }
+    posts
+}
+
+/// HTML and URLs of module pages and items, assignments and posts, for scanning
+/// a course for links to other video providers.
+pub(crate) async fn course_html(canvas: &CanvasClient, course_id: u64) -> Vec<String> {
+    let mut texts = Vec::new();
+    for module in canvas
+        .list_modules_with_items(course_id)
+        .await
+        .unwrap_or_default()
+    {
+        for item in module.items {
+            if let Some(page_url) = item.page_url.as_deref() {
+                if let Ok(page) = canvas.get_page(course_id, page_url).await {
+                    texts.push(page.body.unwrap_or_default());
+                }
+            }
+            texts.extend(item.external_url);
+        }
+    }
+    for assignment in canvas.list_assignments(course_id).await.unwrap_or_default() {
+        texts.extend(assignment.description);
+    }
+    texts.extend(
+        course_posts(canvas, course_id)
+            .await
+            .into_iter()
+            .map(|p| p.body),
+    );
+    texts
+}
+
 fn extract_zoom_links(input: &str) -> Vec<String> {
     static PATTERN: &str = r#"https?://[A-Za-z0-9-]+\.zoom\.(us|com\.cn)/[A-Za-z0-9_/\-?&=%#\.]+"#;
     let regex = Regex::new(PATTERN).expect("valid regex");
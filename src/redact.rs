@@ -0,0 +1,112 @@
+use regex::{Captures, Regex, Replacer};
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+const MASK: &str = "[redacted]";
+
+/// Cookie and authorization headers, masked up to the end of the line or quote.
+fn header_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?i)(\b(?:set-)?cookie"?\s*:\s*"?|\b(?:proxy-)?authorization"?\s*:\s*"?)[^"\r\n]+"#,
+        )
+        .unwrap()
+    })
+}
+
+/// `key=value`, `key: value` and `"key":"value"` pairs whose key names a secret.
+fn pair_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?i)(\b[\w-]*(?:token|xsrf|csrf|secret|password|passwd|passphrase|verifier|signature|api[_-]?key)[\w-]*"?\s*[:=]\s*"?|\bzak=)([^"'\s,;&})\[\]]+)"#,
+        )
+        .unwrap()
+    })
+}
+
+fn bearer_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)\b(bearer|basic)\s+[\w.~+/=-]{8,}").unwrap())
+}
+
+/// Mask tokens, cookies, xsrf values and passwords in `text`. Values made only of
+/// digits are kept, so counts such as `tokens=3` stay readable.
+pub fn redact_secrets(text: &str) -> Cow<'_, str> {
+    let text = replace(
+        Cow::Borrowed(text),
+        header_pattern(),
+        format!("${{1}}{}", MASK),
+    );
+    let text = replace(text, bearer_pattern(), format!("$1 {}", MASK));
+    replace(text, pair_pattern(), |caps: &Captures| {
+        let value = &caps[2];
+        if value.chars().all(|c| c.is_ascii_digit()) {
+            caps[0].to_string()
+        } else {
+            format!("{}{}", &caps[1], MASK)
+        }
+    })
+}
+
+fn replace<'a>(text: Cow<'a, str>, re: &Regex, rep: impl Replacer) -> Cow<'a, str> {
+    let replaced = match re.replace_all(&text, rep) {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(s) => Some(s),
+    };
+    replaced.map(Cow::Owned).unwrap_or(text)
+}
+
+/// Writer that redacts each write before passing it on. The tracing formatters
+/// write one whole event per call, so secrets are never split across writes.
+pub struct RedactingWriter<W>(pub W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.0.write_all(redact_secrets(text).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_secrets_and_keeps_the_rest() {
+        assert_eq!(
+            redact_secrets("    Cookie: _zm_ssid=abc; cred=xyz"),
+            "    Cookie: [redacted]"
+        );
+        assert_eq!(
+            redact_secrets(r#"{"x-xsrf-token":"q1w2e3","page":2}"#),
+            r#"{"x-xsrf-token":"[redacted]","page":2}"#
+        );
+        assert_eq!(
+            redact_secrets("GET /rec?zak=eyJ0&access_token=s3cr3t&id=7"),
+            "GET /rec?zak=[redacted]&access_token=[redacted]&id=7"
+        );
+        assert_eq!(
+            redact_secrets("authorization: Bearer 7~abcdefghijk"),
+            "authorization: [redacted]"
+        );
+        assert_eq!(
+            redact_secrets("password = hunter2, tokens=3"),
+            "password = [redacted], tokens=3"
+        );
+        assert_eq!(
+            redact_secrets("Saved 4 fresh cookies for downloads"),
+            "Saved 4 fresh cookies for downloads"
+        );
+        assert!(matches!(redact_secrets("nothing here"), Cow::Borrowed(_)));
+    }
+}
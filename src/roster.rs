@@ -0,0 +1,246 @@
+use crate::canvas::{CanvasClient, CanvasError, CourseUser, Group};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Folder in each course holding the roster and group exports.
+pub const PEOPLE_DIR: &str = "People";
+
+/// People enrolled in a course and its groups with their members.
+#[derive(Debug, Default)]
+pub struct Roster {
+    pub users: Vec<CourseUser>,
+    pub groups: Vec<GroupRoster>,
+}
+
+#[derive(Debug)]
+pub struct GroupRoster {
+    pub group: Group,
+    /// Name of the group set, e.g. "Lab groups".
+    pub category: Option<String>,
+    pub members: Vec<CourseUser>,
+}
+
+/// List a Canvas course's enrolled users, groups and group members.
+pub async fn fetch_roster(canvas: &CanvasClient, course_id: u64) -> Result<Roster, CanvasError> {
+    let users = canvas.list_course_users(course_id).await?;
+    let categories: HashMap<u64, Option<String>> = canvas
+        .list_group_categories(course_id)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, c.name))
+        .collect();
+    let mut groups = Vec::new();
+    for group in canvas.list_groups(course_id).await? {
+        let members = canvas.list_group_users(group.id).await?;
+        let category = group
+            .group_category_id
+            .and_then(|id| categories.get(&id).cloned().flatten());
+        groups.push(GroupRoster {
+            group,
+            category,
+            members,
+        });
+    }
+    Ok(Roster { users, groups })
+}
+
+#[derive(Serialize)]
+struct Person<'a> {
+    id: u64,
+    name: Option<&'a str>,
+    sortable_name: Option<&'a str>,
+    login_id: Option<&'a str>,
+    sis_user_id: Option<&'a str>,
+    email: Option<&'a str>,
+    roles: Vec<&'a str>,
+    sections: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct GroupRecord<'a> {
+    id: u64,
+    name: Option<&'a str>,
+    category: Option<&'a str>,
+    members: Vec<Member<'a>>,
+}
+
+#[derive(Serialize)]
+struct Member<'a> {
+    id: u64,
+    name: Option<&'a str>,
+}
+
+/// The files written to `People/`: `(file name, contents)` for `roster.csv`,
+/// `roster.json`, and, when the course has groups, `groups.csv` and `groups.json`.
+pub fn render_roster(roster: &Roster) -> Vec<(&'static str, String)> {
+    let people: Vec<Person> = roster.users.iter().map(person).collect();
+    let mut csv = String::from("id,name,sortable_name,login_id,sis_user_id,email,roles,sections\n");
+    for p in &people {
+        let sections: Vec<String> = p.sections.iter().map(u64::to_string).collect();
+        csv_row(
+            &mut csv,
+            &[
+                &p.id.to_string(),
+                p.name.unwrap_or_default(),
+                p.sortable_name.unwrap_or_default(),
+                p.login_id.unwrap_or_default(),
+                p.sis_user_id.unwrap_or_default(),
+                p.email.unwrap_or_default(),
+                &p.roles.join(";"),
+                &sections.join(";"),
+            ],
+        );
+    }
+    let mut files = vec![("roster.csv", csv), ("roster.json", to_json(&people))];
+    if roster.groups.is_empty() {
+        return files;
+    }
+
+    let groups: Vec<GroupRecord> = roster
+        .groups
+        .iter()
+        .map(|g| GroupRecord {
+            id: g.group.id,
+            name: g.group.name.as_deref(),
+            category: g.category.as_deref(),
+            members: g
+                .members
+                .iter()
+                .map(|u| Member {
+                    id: u.id,
+                    name: u.name.as_deref(),
+                })
+                .collect(),
+        })
+        .collect();
+    let mut csv = String::from("group_id,group,category,user_id,name\n");
+    for g in &groups {
+        for m in &g.members {
+            csv_row(
+                &mut csv,
+                &[
+                    &g.id.to_string(),
+                    g.name.unwrap_or_default(),
+                    g.category.unwrap_or_default(),
+                    &m.id.to_string(),
+                    m.name.unwrap_or_default(),
+                ],
+            );
+        }
+    }
+    files.push(("groups.csv", csv));
+    files.push(("groups.json", to_json(&groups)));
+    files
+}
+
+fn person(u: &CourseUser) -> Person<'_> {
+    let mut roles: Vec<&str> = u
+        .enrollments
+        .iter()
+        .filter_map(|e| e.role.as_deref().or(e.kind.as_deref()))
+        .collect();
+    roles.sort_unstable();
+    roles.dedup();
+    let mut sections: Vec<u64> = u
+        .enrollments
+        .iter()
+        .filter_map(|e| e.course_section_id)
+        .collect();
+    sections.sort_unstable();
+    sections.dedup();
+    Person {
+        id: u.id,
+        name: u.name.as_deref(),
+        sortable_name: u.sortable_name.as_deref(),
+        login_id: u.login_id.as_deref(),
+        sis_user_id: u.sis_user_id.as_deref(),
+        email: u.email.as_deref(),
+        roles,
+        sections,
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    let mut json = serde_json::to_string_pretty(value).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Append one CSV record, quoting fields with commas, quotes or line breaks.
+pub(crate) fn csv_row(out: &mut String, fields: &[&str]) {
+    let quoted: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect();
+    out.push_str(&quoted.join(","));
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Enrollment;
+
+    fn user(id: u64, name: &str, role: &str) -> CourseUser {
+        CourseUser {
+            id,
+            name: Some(name.into()),
+            sortable_name: None,
+            login_id: Some(format!("u{}", id)),
+            sis_user_id: None,
+            email: None,
+            enrollments: vec![Enrollment {
+                kind: None,
+                role: Some(role.into()),
+                enrollment_state: Some("active".into()),
+                course_section_id: Some(30),
+            }],
+        }
+    }
+
+    #[test]
+    fn roster_and_groups_render_as_csv_and_json() {
+        let roster = Roster {
+            users: vec![
+                user(1, "Rojas, Ana", "StudentEnrollment"),
+                user(2, "Pérez", "TaEnrollment"),
+            ],
+            groups: vec![GroupRoster {
+                group: Group {
+                    id: 5,
+                    name: Some("Lab \"A\"".into()),
+                    group_category_id: Some(9),
+                    course_id: Some(3),
+                },
+                category: Some("Laboratorio".into()),
+                members: vec![user(1, "Rojas, Ana", "StudentEnrollment")],
+            }],
+        };
+        let files = render_roster(&roster);
+        let names: Vec<&str> = files.iter().map(|(n, _)| *n).collect();
+        assert_eq!(
+            names,
+            ["roster.csv", "roster.json", "groups.csv", "groups.json"]
+        );
+        assert_eq!(
+            files[0].1,
+            "id,name,sortable_name,login_id,sis_user_id,email,roles,sections\n\
+             1,\"Rojas, Ana\",,u1,,,StudentEnrollment,30\n\
+             2,Pérez,,u2,,,TaEnrollment,30\n"
+        );
+        assert_eq!(
+            files[2].1,
+            "group_id,group,category,user_id,name\n\
+             5,\"Lab \"\"A\"\"\",Laboratorio,1,\"Rojas, Ana\"\n"
+        );
+        let groups: serde_json::Value = serde_json::from_str(&files[3].1).unwrap();
+        assert_eq!(groups[0]["members"][0]["name"], "Rojas, Ana");
+        assert_eq!(render_roster(&Roster::default()).len(), 2);
+    }
+}
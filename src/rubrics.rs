@@ -0,0 +1,104 @@
+use crate::canvas::RubricCriterion;
+use crate::quizzes::format_points;
+use std::fmt::Write as _;
+
+/// A `## Rubric` section with one table row per criterion, appended to the
+/// assignment Markdown. Empty when the rubric has no criteria.
+pub fn render_rubric(criteria: &[RubricCriterion]) -> String {
+    if criteria.is_empty() {
+        return String::new();
+    }
+    let mut out =
+        String::from("\n\n## Rubric\n\n| Criterion | Ratings | Points |\n| --- | --- | --- |\n");
+    let mut total = 0.0;
+    for c in criteria {
+        let mut criterion = cell(c.description.as_deref().unwrap_or("Criterion"));
+        if let Some(long) = c
+            .long_description
+            .as_deref()
+            .filter(|d| !d.trim().is_empty())
+        {
+            let _ = write!(criterion, "<br>{}", cell(long));
+        }
+        let ratings: Vec<String> = c
+            .ratings
+            .iter()
+            .map(|r| {
+                let mut rating =
+                    format!("**{}**", cell(r.description.as_deref().unwrap_or("Rating")));
+                if let Some(points) = r.points {
+                    let _ = write!(rating, " ({})", format_points(points));
+                }
+                if let Some(long) = r
+                    .long_description
+                    .as_deref()
+                    .filter(|d| !d.trim().is_empty())
+                {
+                    let _ = write!(rating, ": {}", cell(long));
+                }
+                rating
+            })
+            .collect();
+        let points = c.points.map(format_points).unwrap_or_default();
+        total += c.points.unwrap_or(0.0);
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} |",
+            criterion,
+            ratings.join("<br>"),
+            points
+        );
+    }
+    let _ = writeln!(out, "\nTotal points: {}", format_points(total));
+    out
+}
+
+/// Text made safe for a single Markdown table cell.
+fn cell(text: &str) -> String {
+    text.trim()
+        .replace('|', "\\|")
+        .split('\n')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::RubricRating;
+
+    #[test]
+    fn renders_criteria_as_a_table() {
+        let rating = |description: &str, points| RubricRating {
+            description: Some(description.into()),
+            long_description: None,
+            points: Some(points),
+        };
+        let criteria = [
+            RubricCriterion {
+                description: Some("Claridad".into()),
+                long_description: Some("Ideas | argumentos\nbien ordenados".into()),
+                points: Some(6.0),
+                ratings: vec![rating("Excelente", 6.0), rating("Insuficiente", 0.0)],
+            },
+            RubricCriterion {
+                description: Some("Formato".into()),
+                long_description: None,
+                points: Some(1.5),
+                ratings: vec![],
+            },
+        ];
+        assert_eq!(
+            render_rubric(&criteria),
+            "\n\n## Rubric\n\n\
+             | Criterion | Ratings | Points |\n\
+             | --- | --- | --- |\n\
+             | Claridad<br>Ideas \\| argumentos<br>bien ordenados | **Excelente** (6)<br>**Insuficiente** (0) | 6 |\n\
+             | Formato |  | 1.5 |\n\
+             \nTotal points: 7.5\n"
+        );
+        assert_eq!(render_rubric(&[]), "");
+    }
+}
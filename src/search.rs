@@ -0,0 +1,346 @@
+use crate::config::Config;
+use crate::index::INDEX_FILE;
+use crate::site::files_under;
+use crate::tr;
+use crate::zoom::transcript::parse_vtt;
+use console::style;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info};
+
+/// Search index kept at the top of `download_root`; hidden, so sync, stats and
+/// the site export leave it alone.
+pub const SEARCH_DB: &str = ".search.sqlite";
+
+/// Words of context shown around the matches of each result.
+const SNIPPET_WORDS: usize = 16;
+
+/// Markers SQLite puts around matched words in snippets, replaced when printing.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// A file matching a search, best matches first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    /// Path relative to `download_root`.
+    pub path: String,
+    pub title: String,
+    /// Text around the matches, with matched words between [`MATCH_START`] and [`MATCH_END`].
+    pub snippet: String,
+}
+
+/// Files added to or updated in the index, and files dropped from it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Refresh {
+    pub indexed: usize,
+    pub removed: usize,
+}
+
+/// Full-text index over the synced pages, assignments, quizzes and Zoom
+/// transcripts, in an SQLite FTS5 table.
+pub struct SearchIndex {
+    conn: Connection,
+}
+
+/// Search the archive for `query`, refreshing the index first, and print the
+/// matching files with a snippet each.
+pub async fn run_search(query: &str, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let root = cfg.canvas_root();
+    if !root.exists() {
+        println!("{}", tr!("No backup directory found at {}", root.display()));
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+    let roots = cfg.archive_roots();
+    let roots: Vec<&Path> = roots.iter().map(PathBuf::as_path).collect();
+    let mut index = SearchIndex::open(&root)?;
+    let refresh = index.refresh(&roots)?;
+    info!(
+        indexed = refresh.indexed,
+        removed = refresh.removed,
+        "search index refreshed"
+    );
+    let hits = index.search(query, limit)?;
+    if hits.is_empty() {
+        println!("{}", tr!("No matches for '{}'", query));
+        return Ok(());
+    }
+    for hit in &hits {
+        println!("{}", style(&hit.title).bold());
+        println!("  {}", root.join(&hit.path).display());
+        println!("  {}", highlight(&hit.snippet));
+    }
+    Ok(())
+}
+
+impl SearchIndex {
+    /// Open the index of the archive at `root`, creating it when missing.
+    pub fn open(root: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(root.join(SEARCH_DB))?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_file (
+                path TEXT PRIMARY KEY,
+                mtime INTEGER NOT NULL,
+                size INTEGER NOT NULL
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS search_text USING fts5(
+                path UNINDEXED,
+                title,
+                body,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+            "#,
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Bring the index in line with the files under `roots`: Markdown other than
+    /// the generated indexes, and WebVTT transcripts. Paths are kept relative to
+    /// the first root, which holds the index, and in full for the others (such as
+    /// `zoom.download_root`). Only files whose size or modification time changed
+    /// are read again.
+    pub fn refresh(&mut self, roots: &[&Path]) -> Result<Refresh, crate::error::Error> {
+        let tx = self.conn.transaction()?;
+        let mut known: HashMap<String, (i64, i64)> = HashMap::new();
+        {
+            let mut stmt = tx.prepare("SELECT path, mtime, size FROM search_file")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?)))
+            })?;
+            for row in rows {
+                let (path, stamp) = row?;
+                known.insert(path, stamp);
+            }
+        }
+
+        let mut refresh = Refresh::default();
+        for (i, root) in roots.iter().enumerate() {
+            if !root.is_dir() {
+                continue;
+            }
+            for path in files_under(root)? {
+                let Some(kind) = Kind::of(&path) else {
+                    continue;
+                };
+                let rel = if i == 0 {
+                    path.strip_prefix(root).unwrap_or(&path)
+                } else {
+                    path.as_path()
+                }
+                .to_string_lossy()
+                .replace('\\', "/");
+                let meta = std::fs::metadata(&path)?;
+                let mtime = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64);
+                let stamp = (mtime, meta.len() as i64);
+                if known.remove(&rel) == Some(stamp) {
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&std::fs::read(&path)?).into_owned();
+                let (title, body) = kind.document(&path, &text);
+                tx.execute("DELETE FROM search_text WHERE path = ?1", params![rel])?;
+                tx.execute(
+                    "INSERT INTO search_text (path, title, body) VALUES (?1, ?2, ?3)",
+                    params![rel, title, body],
+                )?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO search_file (path, mtime, size) VALUES (?1, ?2, ?3)",
+                    params![rel, stamp.0, stamp.1],
+                )?;
+                debug!(path = %rel, "indexed for search");
+                refresh.indexed += 1;
+            }
+        }
+        // Whatever was not seen on disk has been deleted or moved
+        for rel in known.keys() {
+            tx.execute("DELETE FROM search_text WHERE path = ?1", params![rel])?;
+            tx.execute("DELETE FROM search_file WHERE path = ?1", params![rel])?;
+            refresh.removed += 1;
+        }
+        tx.commit()?;
+        Ok(refresh)
+    }
+
+    /// Files containing every word of `query` (or words starting with them),
+    /// ignoring case and accents, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> rusqlite::Result<Vec<Hit>> {
+        let Some(query) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+        let sql = format!(
+            "SELECT path, title, snippet(search_text, 2, '{}', '{}', '…', {})
+             FROM search_text WHERE search_text MATCH ?1
+             ORDER BY bm25(search_text, 0.0, 5.0, 1.0) LIMIT ?2",
+            MATCH_START, MATCH_END, SNIPPET_WORDS
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![query, limit as i64], |row| {
+            Ok(Hit {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                snippet: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Kinds of files the index covers.
+enum Kind {
+    Markdown,
+    Transcript,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match ext.as_str() {
+            "md" if path.file_name().is_some_and(|n| n != INDEX_FILE) => Some(Kind::Markdown),
+            // Transcripts turned into notes are found through the notes
+            "vtt" if !path.with_extension("md").exists() => Some(Kind::Transcript),
+            _ => None,
+        }
+    }
+
+    /// Title and searchable text of a file with contents `text`.
+    fn document(&self, path: &Path, text: &str) -> (String, String) {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match self {
+            Kind::Markdown => {
+                let body = strip_front_matter(text);
+                let title = body
+                    .lines()
+                    .find_map(|l| l.strip_prefix("# "))
+                    .map_or(stem, |t| t.trim().to_string());
+                (title, body.to_string())
+            }
+            Kind::Transcript => {
+                // Transcripts are named after the recording; its folder names the meeting
+                let title = path
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .map_or(stem, |n| n.to_string_lossy().into_owned());
+                let cues: Vec<String> = parse_vtt(text).into_iter().map(|c| c.text).collect();
+                (title, cues.join("\n"))
+            }
+        }
+    }
+}
+
+/// Markdown without the YAML front matter sync puts at the top.
+fn strip_front_matter(md: &str) -> &str {
+    md.strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n"))
+        .map_or(md, |(_, body)| body)
+}
+
+/// FTS5 query matching every word of `query` as a prefix. Each word is quoted so
+/// punctuation such as `-` or `:` is not read as query syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// `snippet` with matched words in bold on terminals.
+fn highlight(snippet: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in snippet.split(MATCH_START).enumerate() {
+        match part.split_once(MATCH_END) {
+            Some((word, rest)) if i > 0 => {
+                out.push_str(&style(word).bold().to_string());
+                out.push_str(rest);
+            }
+            _ => out.push_str(part),
+        }
+    }
+    out.replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexes_markdown_and_transcripts_incrementally() {
+        let root = tempfile::tempdir().unwrap();
+        let module = root
+            .path()
+            .join("Calculo_I")
+            .join("Modules")
+            .join("12_Unidad_1");
+        let meeting = root
+            .path()
+            .join("Zoom")
+            .join("101")
+            .join("2024-03-01 - Clase 3");
+        std::fs::create_dir_all(&module).unwrap();
+        std::fs::create_dir_all(&meeting).unwrap();
+        std::fs::write(
+            module.join("01-Derivadas.md"),
+            "---\ncanvas_id: 7\ncourse: \"Cálculo I\"\n---\n\n# Derivadas\n\nLa regla de la cadena permite derivar funciones compuestas.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            module.join(INDEX_FILE),
+            "# Unidad 1\n\n- [Derivadas](01-Derivadas.md)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            meeting.join("clase.vtt"),
+            "WEBVTT\n\n1\n00:00:01.000 --> 00:00:04.000\nHoy vemos límites laterales\n",
+        )
+        .unwrap();
+
+        let mut index = SearchIndex::open(root.path()).unwrap();
+        assert_eq!(
+            index.refresh(&[root.path()]).unwrap(),
+            Refresh {
+                indexed: 2,
+                removed: 0
+            }
+        );
+
+        let hits = index.search("cadena derivar", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].path,
+            "Calculo_I/Modules/12_Unidad_1/01-Derivadas.md"
+        );
+        assert_eq!(hits[0].title, "Derivadas");
+        assert!(hits[0].snippet.contains("\u{2}cadena\u{3}"));
+
+        // Accents and word endings don't have to match; front matter is not indexed
+        let hits = index.search("limite", 10).unwrap();
+        assert_eq!(hits[0].title, "2024-03-01 - Clase 3");
+        assert!(index.search("canvas_id", 10).unwrap().is_empty());
+        assert!(index.search("Unidad", 10).unwrap().is_empty());
+        assert!(index.search("\"-:", 10).unwrap().is_empty());
+
+        // Unchanged files are not read again; deleted ones leave the index
+        std::fs::remove_file(meeting.join("clase.vtt")).unwrap();
+        assert_eq!(
+            index.refresh(&[root.path()]).unwrap(),
+            Refresh {
+                indexed: 0,
+                removed: 1
+            }
+        );
+        assert!(index.search("limite", 10).unwrap().is_empty());
+    }
+}
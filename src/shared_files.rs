@@ -0,0 +1,217 @@
+use base64::prelude::*;
+use regex::Regex;
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use reqwest::Client;
+use std::collections::HashSet;
+use url::Url;
+
+/// A file shared through Google Drive, OneDrive or SharePoint and linked from page HTML.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SharedFile {
+    /// Google Drive file id.
+    GoogleDrive(String),
+    /// Google Docs editor file, exported as `format` (`docx`, `xlsx` or `pptx`).
+    GoogleDoc { kind: String, id: String },
+    /// `1drv.ms` or `onedrive.live.com` share link.
+    OneDrive(String),
+    /// SharePoint sharing link to a document (`/:b:/`, `/:w:/`, ...).
+    SharePoint(String),
+}
+
+impl SharedFile {
+    /// Sync state key.
+    pub fn state_key(&self) -> String {
+        match self {
+            SharedFile::GoogleDrive(id) => format!("external:gdrive:{}", id),
+            SharedFile::GoogleDoc { id, .. } => format!("external:gdoc:{}", id),
+            SharedFile::OneDrive(url) => format!("external:onedrive:{}", url),
+            SharedFile::SharePoint(url) => format!("external:sharepoint:{}", url),
+        }
+    }
+
+    /// URL answering with the file itself when the link is shared publicly.
+    pub fn download_url(&self) -> String {
+        match self {
+            // confirm=t skips the virus-scan page Drive shows for large files
+            SharedFile::GoogleDrive(id) => format!(
+                "https://drive.usercontent.google.com/download?id={}&export=download&confirm=t",
+                id
+            ),
+            SharedFile::GoogleDoc { kind, id } => format!(
+                "https://docs.google.com/{}/d/{}/export?format={}",
+                kind,
+                id,
+                export_format(kind)
+            ),
+            SharedFile::OneDrive(url) => format!(
+                "https://api.onedrive.com/v1.0/shares/u!{}/root/content",
+                BASE64_URL_SAFE_NO_PAD.encode(url)
+            ),
+            SharedFile::SharePoint(url) => match Url::parse(url) {
+                Ok(mut u) => {
+                    u.query_pairs_mut().append_pair("download", "1");
+                    u.into()
+                }
+                Err(_) => url.clone(),
+            },
+        }
+    }
+
+    /// Name used when the server does not send one.
+    pub fn fallback_name(&self) -> String {
+        match self {
+            SharedFile::GoogleDrive(id) => id.clone(),
+            SharedFile::GoogleDoc { kind, id } => format!("{}.{}", id, export_format(kind)),
+            SharedFile::OneDrive(url) | SharedFile::SharePoint(url) => url
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .and_then(|s| s.split('?').next())
+                .unwrap_or("shared_file")
+                .to_string(),
+        }
+    }
+}
+
+fn export_format(kind: &str) -> &'static str {
+    match kind {
+        "spreadsheets" => "xlsx",
+        "presentation" => "pptx",
+        _ => "docx",
+    }
+}
+
+/// Every shared file link in `html`, in order of appearance. Folders and videos
+/// (handled by `sharepoint`) are left out.
+pub fn discover_shared_files(html: &str) -> Vec<SharedFile> {
+    let html = html.replace("&amp;", "&");
+    let re = Regex::new(
+        r#"(?i)https://(?:drive\.google\.com/(?:file/d/([\w-]{20,})|(?:open|uc)\?(?:[^\s"'<>]*?&)?id=([\w-]{20,}))|docs\.google\.com/(document|spreadsheets|presentation)/d/([\w-]{20,})|(1drv\.ms/[a-eg-z]/[^\s"'<>]+|onedrive\.live\.com/[^\s"'<>]+)|([a-z0-9-]+\.sharepoint\.com/:[bwxpt]:/[^\s"'<>]+))"#,
+    )
+    .expect("valid regex");
+    let mut seen = HashSet::new();
+    re.captures_iter(&html)
+        .filter_map(|c| {
+            let trim = |s: &str| {
+                s.trim_end_matches(&[',', ';', ')', ']', '.'][..])
+                    .to_string()
+            };
+            if let Some(id) = c.get(1).or(c.get(2)) {
+                Some(SharedFile::GoogleDrive(id.as_str().to_string()))
+            } else if let (Some(kind), Some(id)) = (c.get(3), c.get(4)) {
+                Some(SharedFile::GoogleDoc {
+                    kind: kind.as_str().to_ascii_lowercase(),
+                    id: id.as_str().to_string(),
+                })
+            } else if let Some(m) = c.get(5) {
+                Some(SharedFile::OneDrive(format!(
+                    "https://{}",
+                    trim(m.as_str())
+                )))
+            } else {
+                c.get(6)
+                    .map(|m| SharedFile::SharePoint(format!("https://{}", trim(m.as_str()))))
+            }
+        })
+        .filter(|f| seen.insert(f.clone()))
+        .collect()
+}
+
+/// Check with a `HEAD` request that `url` serves a file rather than a sign-in page,
+/// returning the file name the server suggests.
+pub async fn probe(client: &Client, url: &str) -> Result<Option<String>, String> {
+    let resp = client.head(url).send().await.map_err(|e| e.to_string())?;
+    if resp.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {} for {}", resp.status(), url));
+    }
+    let is_html = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/html"));
+    if is_html {
+        return Err("the link is not shared publicly (got a web page)".to_string());
+    }
+    Ok(resp
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(disposition_filename))
+}
+
+/// File name from a `Content-Disposition` header, preferring the RFC 5987 `filename*`.
+fn disposition_filename(header: &str) -> Option<String> {
+    let params = header.split(';').map(str::trim);
+    let mut plain = None;
+    for param in params {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                let encoded = value.trim().split("''").last()?;
+                if let Ok(name) = urlencoding::decode(encoded) {
+                    return Some(name.into_owned());
+                }
+            }
+            "filename" => plain = Some(value.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    plain.filter(|n| !n.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_shared_files_and_their_downloads() {
+        let html = r#"<a href="https://drive.google.com/file/d/1AbCdEfGhIjKlMnOpQrStUvWx/view?usp=sharing">Guía</a>
+            <a href="https://docs.google.com/presentation/d/1ZyXwVuTsRqPoNmLkJiHgFeDc/edit">Slides</a>
+            <a href="https://1drv.ms/b/s!AkP9xYz">Apuntes</a>
+            <a href="https://1drv.ms/f/s!Folder">Carpeta</a>
+            <a href="https://unab.sharepoint.com/:w:/s/Fisica/EbQ12?e=abc">Pauta</a>
+            <a href="https://unab.sharepoint.com/:v:/s/Fisica/Video1">Video</a>"#;
+        let files = discover_shared_files(html);
+        assert_eq!(files.len(), 4);
+        assert_eq!(
+            files[0].download_url(),
+            "https://drive.usercontent.google.com/download?id=1AbCdEfGhIjKlMnOpQrStUvWx&export=download&confirm=t"
+        );
+        assert_eq!(
+            files[1].download_url(),
+            "https://docs.google.com/presentation/d/1ZyXwVuTsRqPoNmLkJiHgFeDc/export?format=pptx"
+        );
+        assert_eq!(
+            files[2].download_url(),
+            format!(
+                "https://api.onedrive.com/v1.0/shares/u!{}/root/content",
+                BASE64_URL_SAFE_NO_PAD.encode("https://1drv.ms/b/s!AkP9xYz")
+            )
+        );
+        assert_eq!(
+            files[3].download_url(),
+            "https://unab.sharepoint.com/:w:/s/Fisica/EbQ12?e=abc&download=1"
+        );
+    }
+
+    #[test]
+    fn reads_content_disposition_names() {
+        assert_eq!(
+            disposition_filename(
+                "attachment; filename=\"Gu_a.pdf\"; filename*=UTF-8''Gu%C3%ADa%201.pdf"
+            )
+            .as_deref(),
+            Some("Guía 1.pdf")
+        );
+        assert_eq!(
+            disposition_filename("attachment; filename=notas.xlsx").as_deref(),
+            Some("notas.xlsx")
+        );
+        assert_eq!(disposition_filename("inline"), None);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::canvas::CanvasClient;
+use crate::config::{Config, ConfigPaths};
+use crate::fsutil::sanitize_filename_preserve_ext;
+use crate::progress::{download_bar, spinner};
+use crate::recordings::course_html;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::headless::{display_name, fetch_recording, FetchOutcome, ZoomHeadless};
+use crate::zoom::models::ZoomCookie;
+use crate::{report, tr};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mov", "m4v", "webm"];
+
+/// Download the SharePoint and Microsoft Stream videos linked from a course's
+/// pages, assignments and posts, signing in with the headless Microsoft SSO.
+pub async fn run_sharepoint(
+    course_id: u64,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let canvas = CanvasClient::from_config().await?;
+
+    let scan = spinner("Looking for SharePoint and Stream videos");
+    let mut seen = HashSet::new();
+    let links: Vec<String> = course_html(&canvas, course_id)
+        .await
+        .iter()
+        .flat_map(|t| extract_video_links(t))
+        .filter(|l| seen.insert(l.clone()))
+        .collect();
+    scan.finish_and_clear();
+    if links.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "No SharePoint or Stream videos linked in course {}.",
+                course_id
+            )
+        );
+        return Ok(());
+    }
+    if dry_run {
+        for link in &links {
+            report!("DRY-RUN {}", link);
+        }
+        report!("{}", tr!("DRY-RUN: {} video link(s) found.", links.len()));
+        return Ok(());
+    }
+
+    let db = ZoomDb::new(&ConfigPaths::new()?.config_dir)?;
+    report!("{}", tr!("Opening {} video link(s)...", links.len()));
+    let (cookies, captures) = ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_sharepoint_videos(&links)
+        .await?;
+
+    let base = cfg.base_root()
+        .join("SharePoint")
+        .join(course_id.to_string());
+    tokio::fs::create_dir_all(&base).await?;
+    let mut name_counts: HashMap<PathBuf, usize> = HashMap::new();
+    let (mut downloaded, mut failed) = (0usize, 0usize);
+    for (link, capture) in links.iter().zip(&captures) {
+        let dest = video_dest(&base, capture.title.as_deref(), link, &mut name_counts);
+        if dest.exists() {
+            report!(
+                "{}",
+                tr!("⏩ Skipping (already exists): {}", dest.display())
+            );
+            continue;
+        }
+        let mut outcome = FetchOutcome::Failed;
+        // The original upload is preferred; view-only videos only expose the manifest
+        if let Some(url) = download_url(link) {
+            let headers: Vec<(String, String)> = cookie_header(&cookies, &url)
+                .map(|c| vec![("Cookie".to_string(), c)])
+                .unwrap_or_default();
+            let bar = download_bar(None, &display_name(&dest));
+            outcome = fetch_recording(&cfg, false, &headers, &url, &dest, None, bar).await;
+        }
+        if outcome != FetchOutcome::Complete {
+            if let Some(manifest) = capture.manifest.as_deref() {
+                report!(
+                    "{}",
+                    tr!(
+                        "↪ {}: downloading the streaming manifest...",
+                        display_name(&dest)
+                    )
+                );
+                let bar = download_bar(None, &display_name(&dest));
+                outcome = fetch_recording(&cfg, true, &[], manifest, &dest, None, bar).await;
+            }
+        }
+        if outcome == FetchOutcome::Complete {
+            downloaded += 1;
+        } else {
+            failed += 1;
+        }
+    }
+    report!(
+        "{}",
+        tr!(
+            "Downloaded {} SharePoint/Stream video(s) to {}",
+            downloaded,
+            base.display()
+        )
+    );
+    if failed > 0 {
+        return Err(tr!("{} video(s) could not be downloaded", failed).into());
+    }
+    Ok(())
+}
+
+/// SharePoint sharing links (`/:v:/`), Stream player pages, direct video files on
+/// SharePoint and classic Stream links in `html`.
+pub fn extract_video_links(html: &str) -> Vec<String> {
+    let re = Regex::new(
+        r#"(?i)https://(?:[a-z0-9-]+\.sharepoint\.com/(?::v:/|[^\s"'<>]*?/_layouts/15/stream\.aspx\?|[^\s"'<>?]+\.(?:mp4|mov|m4v|webm)\b)|web\.microsoftstream\.com/video/)[^\s"'<>]*"#,
+    )
+    .expect("valid regex");
+    let html = html.replace("&amp;", "&");
+    let mut seen = HashSet::new();
+    re.find_iter(&html)
+        .map(|m| {
+            m.as_str()
+                .trim_end_matches(&[',', ';', ')', ']', '.'][..])
+                .to_string()
+        })
+        .filter(|l| seen.insert(l.clone()))
+        .collect()
+}
+
+/// URL that returns the original file: `download.aspx` for Stream player pages,
+/// `download=1` for sharing links. Classic Stream has no download URL.
+pub fn download_url(link: &str) -> Option<String> {
+    let mut url = Url::parse(link).ok()?;
+    if url.host_str()?.ends_with("microsoftstream.com") {
+        return None;
+    }
+    if let Some((site, _)) = url.path().split_once("/_layouts/15/stream.aspx") {
+        let id = url.query_pairs().find(|(k, _)| k == "id")?.1.into_owned();
+        let mut download = url.clone();
+        download.set_path(&format!("{}/_layouts/15/download.aspx", site));
+        download.set_query(None);
+        download.query_pairs_mut().append_pair("SourceUrl", &id);
+        return Some(download.into());
+    }
+    if url.path().starts_with("/:v:/") {
+        url.query_pairs_mut().append_pair("download", "1");
+    }
+    Some(url.into())
+}
+
+/// `Cookie` header value for the host of `url`.
+fn cookie_header(cookies: &[ZoomCookie], url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_string();
+    let values: Vec<String> = cookies
+        .iter()
+        .filter(|c| {
+            let domain = c.domain.trim_start_matches('.');
+            host == domain || host.ends_with(&format!(".{domain}"))
+        })
+        .map(|c| format!("{}={}", c.name, c.value))
+        .collect();
+    (!values.is_empty()).then(|| values.join("; "))
+}
+
+/// `<base>/<video name>`, named after the player title or the link's file name,
+/// numbered when names repeat.
+fn video_dest(
+    base: &Path,
+    title: Option<&str>,
+    link: &str,
+    name_counts: &mut HashMap<PathBuf, usize>,
+) -> PathBuf {
+    let from_link = || {
+        let url = Url::parse(link).ok()?;
+        let path = url
+            .query_pairs()
+            .find(|(k, _)| k == "id")
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_else(|| url.path().to_string());
+        path.rsplit('/')
+            .next()
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+    };
+    let name = title
+        .map(str::to_string)
+        .or_else(from_link)
+        .unwrap_or_else(|| "video".to_string());
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) => {
+            (stem.to_string(), ext.to_string())
+        }
+        _ => (name.clone(), "mp4".to_string()),
+    };
+    let planned = base.join(sanitize_filename_preserve_ext(format!("{stem}.{ext}")));
+    let count = name_counts.entry(planned.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        return planned;
+    }
+    base.join(sanitize_filename_preserve_ext(format!(
+        "{stem} ({count}).{ext}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_video_links_and_their_downloads() {
+        let html = r#"<p><a href="https://unab.sharepoint.com/:v:/s/Fisica/EaBcD123?e=xYz">Clase 1</a>
+            <iframe src="https://unab-my.sharepoint.com/personal/jperez_unab_cl/_layouts/15/stream.aspx?id=%2Fpersonal%2Fjperez_unab_cl%2FDocuments%2FGrabaciones%2Fclase%202.mp4&amp;embed=1"></iframe>
+            <a href="https://unab.sharepoint.com/sites/Fisica/Shared%20Documents/lab.pdf">Lab</a>
+            (https://web.microsoftstream.com/video/0f9e8d7c-1111-2222-3333-444455556666).</p>"#;
+        let links = extract_video_links(html);
+        assert_eq!(links.len(), 3);
+        assert_eq!(
+            download_url(&links[0]).as_deref(),
+            Some("https://unab.sharepoint.com/:v:/s/Fisica/EaBcD123?e=xYz&download=1")
+        );
+        assert_eq!(
+            download_url(&links[1]).as_deref(),
+            Some("https://unab-my.sharepoint.com/personal/jperez_unab_cl/_layouts/15/download.aspx?SourceUrl=%2Fpersonal%2Fjperez_unab_cl%2FDocuments%2FGrabaciones%2Fclase+2.mp4")
+        );
+        assert_eq!(download_url(&links[2]), None);
+
+        let mut counts = HashMap::new();
+        let dest = video_dest(Path::new("/b"), None, &links[1], &mut counts);
+        assert_eq!(dest, Path::new("/b/clase_2.mp4"));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::error::{Error, ErrorKind};
+use crate::{report_warning, tr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use tracing::warn;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static LISTEN: Once = Once::new();
+
+/// Start watching for Ctrl-C and SIGTERM. The first signal asks running work to
+/// stop at the next safe point: no new items or recordings are started, partial
+/// downloads stay as `.part` files and state is saved. A second signal exits at
+/// once. Later calls do nothing; must run inside the Tokio runtime.
+pub fn listen() {
+    LISTEN.call_once(|| {
+        tokio::spawn(async {
+            signal().await;
+            REQUESTED.store(true, Ordering::SeqCst);
+            warn!("interrupt received; stopping at the next safe point");
+            report_warning!(
+                "{}",
+                tr!("Interrupted: saving progress after the current download. Press Ctrl-C again to quit now.")
+            );
+            signal().await;
+            std::process::exit(ErrorKind::Cancelled.exit_code() as i32);
+        });
+    });
+}
+
+/// Whether an interrupt asked running work to stop.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Wait until an interrupt asks running work to stop.
+pub async fn wait() {
+    while !requested() {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// [`Error::Cancelled`] once an interrupt asked running work to stop.
+pub fn check() -> Result<(), Error> {
+    if requested() {
+        return Err(Error::Cancelled(tr!(
+            "interrupted; run the same command again to resume"
+        )));
+    }
+    Ok(())
+}
+
+/// Wait for the next Ctrl-C or, on Unix, SIGTERM.
+async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    Ok(()) = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!(error = %e, "unable to watch for SIGTERM"),
+        }
+    }
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
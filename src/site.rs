@@ -0,0 +1,512 @@
+use crate::config::Config;
+use crate::fsutil::course_dirs;
+use crate::i18n::{language, Lang};
+use crate::index::{course_index, INDEX_FILE};
+use crate::state::{is_state_file, State};
+use crate::tr;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Top-level directory under `download_root` that holds Zoom recordings.
+const ZOOM_DIR: &str = "Zoom";
+
+/// Characters of each page's text kept in the search index.
+const SEARCH_TEXT_LEN: usize = 2000;
+
+const STYLE_CSS: &str = "\
+body { font-family: system-ui, sans-serif; line-height: 1.5; margin: 0; color: #222; }
+nav { background: #f3f3f3; border-bottom: 1px solid #ddd; padding: 0.6em 1.5em; }
+main { max-width: 52em; margin: 0 auto; padding: 1em 1.5em 3em; }
+a { color: #0b5cad; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+pre { background: #f6f6f6; padding: 0.8em; overflow-x: auto; }
+img { max-width: 100%; }
+#q { width: 100%; font-size: 1.1em; padding: 0.4em; box-sizing: border-box; }
+";
+
+const SEARCH_JS: &str = "\
+const input = document.getElementById('q');
+const results = document.getElementById('results');
+input.addEventListener('input', () => {
+  const q = input.value.trim().toLowerCase();
+  results.replaceChildren();
+  if (q.length < 2) return;
+  for (const doc of SEARCH_INDEX) {
+    if (!doc.title.toLowerCase().includes(q) && !doc.text.toLowerCase().includes(q)) continue;
+    const li = document.createElement('li');
+    const a = document.createElement('a');
+    a.href = doc.url;
+    a.textContent = doc.title;
+    li.append(a, ' \u{2014} ' + doc.course);
+    results.append(li);
+    if (results.children.length >= 50) break;
+  }
+});
+";
+
+/// A page in the site's search index.
+#[derive(Debug, Serialize)]
+struct SearchDoc {
+    title: String,
+    course: String,
+    /// Page URL relative to the site root.
+    url: String,
+    text: String,
+}
+
+/// Pages rendered and files copied into the site.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SiteReport {
+    pub courses: usize,
+    pub pages: usize,
+    pub files: usize,
+}
+
+/// Render the archive as a static HTML site in `out`, for classmates without
+/// u_crawler: a course list with search, each course's index, and every synced
+/// page with links between them kept working.
+pub async fn run_export_site(out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let root = cfg.canvas_root();
+    if !root.exists() {
+        println!("{}", tr!("No backup directory found at {}", root.display()));
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+    info!(path = %root.display(), out = %out.display(), "exporting static site");
+    let zoom_root = cfg.separate_zoom_root();
+    let report = export_site(&root, zoom_root.as_deref(), out).await?;
+    println!(
+        "{}",
+        tr!(
+            "Site written to {}: {} course(s), {} page(s), {} file(s)",
+            out.join("index.html").display(),
+            report.courses,
+            report.pages,
+            report.files
+        )
+    );
+    Ok(())
+}
+
+/// Write the site for the archive at `root` into `out`, with the recordings kept
+/// apart under `zoom_root` merged into their courses. Files other than Markdown
+/// are hard linked into the site when possible and copied otherwise.
+pub async fn export_site(
+    root: &Path,
+    zoom_root: Option<&Path>,
+    out: &Path,
+) -> io::Result<SiteReport> {
+    let mut site = Site {
+        root,
+        zoom_root,
+        out,
+        search: Vec::new(),
+    };
+    let mut report = SiteReport::default();
+    let mut courses = Vec::new();
+    // Position in `courses` of each course id, for the pages of Zoom recordings
+    let mut course_ids = HashMap::new();
+    for dir in course_dirs(root, &[ZOOM_DIR])? {
+        let state = State::load(&dir.join("state.json")).await;
+        let index_md = dir.join(INDEX_FILE);
+        let title = std::fs::read_to_string(&index_md)
+            .ok()
+            .and_then(|md| heading(&md))
+            .unwrap_or_else(|| rel_url(root, &dir));
+        let course = Course {
+            title: &title,
+            url: format!("{}/{}", rel_url(root, &dir), html_name(INDEX_FILE)),
+        };
+        // Archives synced before course indexes existed get one for the site only
+        if !index_md.exists() {
+            if let Some(id) = state.course_id {
+                let md = course_index(root, &dir, id, &title, &[])?;
+                site.write_page(&index_md, &md, &title, &course)?;
+                report.pages += 1;
+            }
+        }
+        let mut files = files_under(&dir)?;
+        if let Some(recordings) = zoom_root
+            .map(|zoom| zoom.join(rel_path(root, &dir)))
+            .filter(|d| d.is_dir())
+        {
+            files.extend(files_under(&recordings)?);
+        }
+        for path in files {
+            if path
+                .file_name()
+                .is_some_and(|n| is_state_file(&n.to_string_lossy()))
+            {
+                continue;
+            }
+            if is_markdown(&path) {
+                let md = std::fs::read_to_string(&path)?;
+                let page_title = heading(&md).unwrap_or_else(|| file_stem(&path));
+                site.write_page(&path, &md, &page_title, &course)?;
+                report.pages += 1;
+            } else {
+                copy_file(&path, &out.join(site.rel_path(&path)))?;
+                report.files += 1;
+            }
+        }
+        if let Some(id) = state.course_id {
+            course_ids.insert(id.to_string(), courses.len());
+        }
+        courses.push((title.clone(), course.url));
+        report.courses += 1;
+    }
+    for base in std::iter::once(root).chain(zoom_root) {
+        let zoom = base.join(ZOOM_DIR);
+        if !zoom.is_dir() {
+            continue;
+        }
+        for path in files_under(&zoom)? {
+            if !is_markdown(&path) {
+                copy_file(&path, &out.join(site.rel_path(&path)))?;
+                report.files += 1;
+                continue;
+            }
+            // Transcript notes, under Zoom/<course id>/
+            let id = rel_path(&zoom, &path)
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (title, url) = match course_ids.get(&id) {
+                Some(&i) => (courses[i].0.as_str(), courses[i].1.clone()),
+                None => (ZOOM_DIR, "index.html".to_string()),
+            };
+            let course = Course { title, url };
+            let md = std::fs::read_to_string(&path)?;
+            let page_title = heading(&md).unwrap_or_else(|| file_stem(&path));
+            site.write_page(&path, &md, &page_title, &course)?;
+            report.pages += 1;
+        }
+    }
+
+    std::fs::create_dir_all(out)?;
+    std::fs::write(out.join("style.css"), STYLE_CSS)?;
+    std::fs::write(out.join("search.js"), SEARCH_JS)?;
+    let json = serde_json::to_string(&site.search)?.replace("</", "<\\/");
+    std::fs::write(
+        out.join("search-index.js"),
+        format!("const SEARCH_INDEX = {};\n", json),
+    )?;
+    std::fs::write(out.join("index.html"), home_page(&courses))?;
+    Ok(report)
+}
+
+/// Archive being exported and the search index built so far.
+struct Site<'a> {
+    root: &'a Path,
+    /// `zoom.download_root`, laid out like `root`.
+    zoom_root: Option<&'a Path>,
+    out: &'a Path,
+    search: Vec<SearchDoc>,
+}
+
+/// A course's title and the URL of its index page, for the navigation bar.
+struct Course<'a> {
+    title: &'a str,
+    url: String,
+}
+
+impl Site<'_> {
+    /// Path of `path` in the site: relative to `zoom_root` for the recordings kept
+    /// there, else to `root`.
+    fn rel_path(&self, path: &Path) -> PathBuf {
+        match self.zoom_root.filter(|zoom| path.starts_with(zoom)) {
+            Some(zoom) => rel_path(zoom, path),
+            None => rel_path(self.root, path),
+        }
+    }
+
+    /// Render the Markdown file `src` of the archive to its place in the site.
+    fn write_page(&mut self, src: &Path, md: &str, title: &str, course: &Course) -> io::Result<()> {
+        let url = html_name(&self.rel_path(src).to_string_lossy().replace('\\', "/"));
+        let (body, text) = render_markdown(md);
+        let up = "../".repeat(url.matches('/').count());
+        let nav = format!(
+            "<a href=\"{up}index.html\">{}</a> › <a href=\"{up}{}\">{}</a>",
+            escape(&tr!("Courses")),
+            escape(&course.url),
+            escape(course.title)
+        );
+        let dest = self.out.join(&url);
+        std::fs::create_dir_all(dest.parent().unwrap())?;
+        std::fs::write(&dest, page(title, &up, &nav, &body))?;
+        debug!(page = %dest.display(), "wrote site page");
+        self.search.push(SearchDoc {
+            title: title.to_string(),
+            course: course.title.to_string(),
+            url,
+            text,
+        });
+        Ok(())
+    }
+}
+
+/// HTML of `md` with links to other Markdown files pointing at their pages, and
+/// its plain text for the search index. Front matter is left out of both.
+fn render_markdown(md: &str) -> (String, String) {
+    let mut text = String::new();
+    let mut in_front_matter = false;
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let events = Parser::new_ext(md, options).map(|event| {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => {
+                in_front_matter = true;
+                event
+            }
+            Event::End(TagEnd::MetadataBlock(_)) => {
+                in_front_matter = false;
+                event
+            }
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: page_link(&dest_url).map_or(dest_url, CowStr::from),
+                title,
+                id,
+            }),
+            Event::Text(ref t) | Event::Code(ref t) => {
+                if !in_front_matter && text.len() < SEARCH_TEXT_LEN {
+                    text.push_str(t);
+                }
+                event
+            }
+            // Keep words of separate blocks and lines apart
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::TableCell
+                | TagEnd::CodeBlock,
+            )
+            | Event::SoftBreak
+            | Event::HardBreak => {
+                if !text.ends_with(' ') {
+                    text.push(' ');
+                }
+                event
+            }
+            other => other,
+        }
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+    let mut end = text.len().min(SEARCH_TEXT_LEN);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (body, text.trim_end().to_string())
+}
+
+/// `dest` pointing at the rendered page when it is a relative link to a Markdown file.
+fn page_link(dest: &str) -> Option<String> {
+    if dest.contains("://") || dest.starts_with("mailto:") || dest.starts_with('#') {
+        return None;
+    }
+    let (path, fragment) = match dest.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (dest, None),
+    };
+    if !path.to_ascii_lowercase().ends_with(".md") {
+        return None;
+    }
+    let page = html_name(path);
+    Some(match fragment {
+        Some(fragment) => format!("{}#{}", page, fragment),
+        None => page,
+    })
+}
+
+fn home_page(courses: &[(String, String)]) -> String {
+    let mut body = format!(
+        "<h1>{}</h1>\n<input id=\"q\" type=\"search\" placeholder=\"{}\" autofocus>\n<ul id=\"results\"></ul>\n<ul>\n",
+        escape(&tr!("Courses")),
+        escape(&tr!("Search pages"))
+    );
+    for (title, url) in courses {
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape(url),
+            escape(title)
+        ));
+    }
+    body.push_str(
+        "</ul>\n<script src=\"search-index.js\"></script>\n<script src=\"search.js\"></script>\n",
+    );
+    page(&tr!("Courses"), "", "", &body)
+}
+
+fn page(title: &str, up: &str, nav: &str, body: &str) -> String {
+    let lang = match language() {
+        Lang::En => "en",
+        Lang::Es => "es",
+    };
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"{up}style.css\">\n</head>\n<body>\n\
+         <nav>{nav}</nav>\n<main>\n{body}</main>\n</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// Text of the first `# ` heading.
+fn heading(md: &str) -> Option<String> {
+    md.lines()
+        .find_map(|l| l.strip_prefix("# "))
+        .map(|t| t.trim().to_string())
+}
+
+/// Files under `dir`, recursively and sorted, without hidden and partial files.
+pub(crate) fn files_under(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.starts_with('.') || name.ends_with(".part") {
+                continue;
+            }
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                out.push(path);
+            }
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Hard link `src` to `dest`, or copy it where links are not possible.
+fn copy_file(src: &Path, dest: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dest.parent().unwrap())?;
+    let _ = std::fs::remove_file(dest);
+    if std::fs::hard_link(src, dest).is_err() {
+        std::fs::copy(src, dest)?;
+    }
+    Ok(())
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("md"))
+}
+
+fn html_name(md_path: &str) -> String {
+    match md_path.len().checked_sub(3) {
+        Some(stem) if md_path[stem..].eq_ignore_ascii_case(".md") => {
+            format!("{}.html", &md_path[..stem])
+        }
+        _ => md_path.to_string(),
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn rel_path(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+fn rel_url(root: &Path, path: &Path) -> String {
+    rel_path(root, path).to_string_lossy().replace('\\', "/")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn archive_becomes_linked_pages_with_a_search_index() {
+        let root = tempfile::tempdir().unwrap();
+        let course = root.path().join("Calculo_I");
+        let module = course.join("Modules").join("12_Unidad_1");
+        std::fs::create_dir_all(module.join("Attachments")).unwrap();
+        std::fs::write(course.join("state.json"), r#"{"course_id":101,"items":{}}"#).unwrap();
+        std::fs::write(
+            course.join(INDEX_FILE),
+            "# Cálculo I\n\n- [01-Intro.md](<Modules/12_Unidad_1/01-Intro.md>)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            module.join("01-Intro.md"),
+            "---\ntype: page\ncourse_id: 101\n---\n\n# Introducción\n\nVer la [guía](<Attachments/guia.pdf>) y [Canvas](https://canvas.example.edu/x.md).\n",
+        )
+        .unwrap();
+        std::fs::write(module.join("Attachments").join("guia.pdf"), "pdf").unwrap();
+        let meeting = root.path().join("Zoom").join("101").join("Clase 3");
+        std::fs::create_dir_all(&meeting).unwrap();
+        std::fs::write(meeting.join("clase.vtt"), "WEBVTT\n").unwrap();
+        std::fs::write(meeting.join("clase.md"), "# Clase 3 — Transcript\n").unwrap();
+
+        let site = tempfile::tempdir().unwrap();
+        let report = export_site(root.path(), None, site.path()).await.unwrap();
+        assert_eq!(
+            report,
+            SiteReport {
+                courses: 1,
+                pages: 3,
+                files: 2
+            }
+        );
+
+        let home = std::fs::read_to_string(site.path().join("index.html")).unwrap();
+        assert!(home.contains("<a href=\"Calculo_I/00-INDEX.html\">Cálculo I</a>"));
+        let index = std::fs::read_to_string(site.path().join("Calculo_I/00-INDEX.html")).unwrap();
+        assert!(index.contains("href=\"Modules/12_Unidad_1/01-Intro.html\""));
+        let intro = std::fs::read_to_string(
+            site.path()
+                .join("Calculo_I/Modules/12_Unidad_1/01-Intro.html"),
+        )
+        .unwrap();
+        assert!(intro.contains("href=\"Attachments/guia.pdf\""));
+        assert!(intro.contains("href=\"https://canvas.example.edu/x.md\""));
+        assert!(intro.contains("<a href=\"../../../index.html\">"));
+        assert!(intro.contains("<a href=\"../../../Calculo_I/00-INDEX.html\">Cálculo I</a>"));
+        assert!(site
+            .path()
+            .join("Calculo_I/Modules/12_Unidad_1/Attachments/guia.pdf")
+            .exists());
+
+        let notes =
+            std::fs::read_to_string(site.path().join("Zoom/101/Clase 3/clase.html")).unwrap();
+        assert!(notes.contains("<a href=\"../../../Calculo_I/00-INDEX.html\">Cálculo I</a>"));
+
+        let search = std::fs::read_to_string(site.path().join("search-index.js")).unwrap();
+        assert!(search.contains("\"url\":\"Calculo_I/Modules/12_Unidad_1/01-Intro.html\""));
+        assert!(search.contains("\"text\":\"Introducción Ver la guía"));
+        assert!(!intro.contains("course_id"));
+    }
+}
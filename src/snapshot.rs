@@ -0,0 +1,117 @@
+use crate::config::Config;
+use crate::media::discover_media;
+use crate::shared_files::discover_shared_files;
+use crate::sharepoint::extract_video_links;
+use crate::tr;
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::page::{CaptureSnapshotFormat, CaptureSnapshotParams};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use url::Url;
+
+/// Time a page gets to load before its snapshot is abandoned.
+const PAGE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Pause after the load event so scripts can render late content.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// Extensions of links that point at a document rather than a web page.
+const FILE_EXTENSIONS: [&str; 14] = [
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "zip", "rar", "mp3", "mp4", "mov", "png",
+    "jpg",
+];
+
+/// Headless Chromium that saves web pages as single-file MHTML archives.
+pub struct PageSnapshotter {
+    browser: Browser,
+    handler: JoinHandle<()>,
+    user_agent: String,
+}
+
+impl PageSnapshotter {
+    /// Start a headless Chromium with the configured proxy and user agent.
+    pub async fn launch(cfg: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut browser_config = BrowserConfig::builder()
+            .arg("--no-sandbox")
+            .arg("--disable-gpu")
+            .arg("--disable-dev-shm-usage");
+        if let Some(proxy) = cfg.browser_proxy() {
+            browser_config = browser_config.arg(format!("--proxy-server={}", proxy));
+        }
+        let (browser, mut handler) = Browser::launch(browser_config.build()?).await?;
+        let handler = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        Ok(Self {
+            browser,
+            handler,
+            user_agent: cfg.zoom.user_agent.clone(),
+        })
+    }
+
+    /// Load `url` in a new tab and return its MHTML snapshot, with stylesheets,
+    /// images and frames inlined.
+    pub async fn snapshot(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let page = self.browser.new_page("about:blank").await?;
+        let result = async {
+            page.set_user_agent(&self.user_agent).await?;
+            timeout(PAGE_TIMEOUT, page.goto(url))
+                .await
+                .map_err(|_| tr!("timed out loading {}", url))??;
+            sleep(SETTLE).await;
+            let snapshot = page
+                .execute(
+                    CaptureSnapshotParams::builder()
+                        .format(CaptureSnapshotFormat::Mhtml)
+                        .build(),
+                )
+                .await?;
+            Ok::<_, Box<dyn std::error::Error>>(snapshot.result.data)
+        }
+        .await;
+        let _ = page.close().await;
+        result
+    }
+
+    /// Close the browser and wait for its CDP handler to exit.
+    pub async fn close(mut self) {
+        let _ = self.browser.close().await;
+        let _ = self.handler.await;
+    }
+}
+
+/// Whether an external link is a web page worth snapshotting rather than a video,
+/// a shared file or a direct download, which are fetched as files instead.
+pub fn is_snapshot_target(url: &str) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return false;
+    }
+    let is_file = parsed
+        .path()
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| FILE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    !is_file
+        && discover_media(url).is_empty()
+        && discover_shared_files(url).is_empty()
+        && extract_video_links(url).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_only_web_pages() {
+        assert!(is_snapshot_target(
+            "https://es.wikipedia.org/wiki/Termodin%C3%A1mica"
+        ));
+        assert!(!is_snapshot_target("https://example.org/lecturas/cap1.PDF"));
+        assert!(!is_snapshot_target("https://youtu.be/dQw4w9WgXcQ"));
+        assert!(!is_snapshot_target(
+            "https://drive.google.com/file/d/1AbCdEfGhIjKlMnOpQrStUvWx/view"
+        ));
+        assert!(!is_snapshot_target("mailto:profesor@unab.cl"));
+    }
+}
@@ -1,11 +1,57 @@
+use crate::config::Config;
+use crate::fsutil::course_dirs;
+use crate::{report_warning, tr};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::path::Path;
-use tokio::io::AsyncReadExt;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+/// Copies of a course's `state.json` kept from before its last syncs, as
+/// `state.json.1` (newest) to `state.json.3`.
+pub const STATE_BACKUPS: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("corrupt state file: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+/// What `state repair` did with a course's state file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Repair {
+    /// Missing or readable; left alone.
+    Healthy,
+    /// Corrupt, and replaced with this backup.
+    Restored(PathBuf),
+    /// Corrupt, with no readable backup; moved aside so the next sync starts over.
+    Reset,
+}
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct State {
+    /// Canvas course id this state belongs to (absent in older state files).
+    #[serde(default)]
+    pub course_id: Option<u64>,
+    /// Start of the last sync of the course that completed (RFC 3339), the window
+    /// start for `sync --since last`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_synced: Option<String>,
+    /// Course (or term) start date from Canvas (RFC 3339), for filing
+    /// recordings by week.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<String>,
     pub items: BTreeMap<String, ItemState>,
+    /// Item key writing each file, by `<module id>/<path in the module folder>`, so
+    /// two names that sanitize alike do not overwrite each other.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub paths: BTreeMap<String, String>,
+    /// Items set since the state was loaded or last flushed.
+    #[serde(skip)]
+    pub(crate) unsaved: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,19 +64,103 @@ pub struct ItemState {
     pub last_error: Option<String>,
     #[serde(default)]
     pub error_count: Option<u32>,
+    /// Raw `Last-Modified` header, used for conditional GETs when no ETag is available.
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// Canvas file uuid, which changes when the file is replaced; tells changes
+    /// apart when the server sends no ETag or `Last-Modified`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// Why Canvas withheld the file on the last sync, e.g. `locked until
+    /// 2024-03-01T10:00:00Z`; it is looked up again on every sync until unlocked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<String>,
 }
 
 impl State {
+    /// The state at `path`, empty when there is none. A corrupt file is reported
+    /// and its newest readable backup used instead, so the course is not
+    /// downloaded again from scratch.
     pub async fn load(path: &Path) -> State {
-        if let Ok(mut f) = tokio::fs::File::open(path).await {
-            let mut buf = Vec::new();
-            if f.read_to_end(&mut buf).await.is_ok() {
-                if let Ok(s) = serde_json::from_slice(&buf) {
-                    return s;
-                }
+        let e = match State::read(path).await {
+            Ok(state) => return state.unwrap_or_default(),
+            Err(e) => e,
+        };
+        error!(path = %path.display(), error = %e, "unable to read state file");
+        match newest_backup(path).await {
+            Some((backup, state)) => {
+                report_warning!(
+                    "{}",
+                    tr!(
+                        "Warning: {} is unreadable ({}); using {}. Run 'u_crawler state repair' to restore it.",
+                        path.display(),
+                        e,
+                        backup.display()
+                    )
+                );
+                state
+            }
+            None => {
+                report_warning!(
+                    "{}",
+                    tr!(
+                        "Warning: {} is unreadable ({}) and has no backup; the course will be synced as new.",
+                        path.display(),
+                        e
+                    )
+                );
+                State::default()
+            }
+        }
+    }
+
+    /// The state at `path`, or `None` when the file does not exist.
+    pub async fn read(path: &Path) -> Result<Option<State>, StateError> {
+        match tokio::fs::read(path).await {
+            Ok(buf) => Ok(Some(serde_json::from_slice(&buf)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Keep a copy of the readable state file at `path` as `state.json.1`,
+    /// shifting older copies up to [`STATE_BACKUPS`]. Nothing is rotated when the
+    /// newest copy is already the same, or when the file is missing or corrupt.
+    pub async fn backup(path: &Path) -> Result<(), StateError> {
+        let Ok(buf) = tokio::fs::read(path).await else {
+            return Ok(());
+        };
+        if serde_json::from_slice::<State>(&buf).is_err()
+            || tokio::fs::read(backup_path(path, 1)).await.ok().as_deref() == Some(&buf[..])
+        {
+            return Ok(());
+        }
+        for n in (1..STATE_BACKUPS).rev() {
+            let from = backup_path(path, n);
+            if from.exists() {
+                tokio::fs::rename(&from, backup_path(path, n + 1)).await?;
+            }
+        }
+        crate::fsutil::atomic_write(&backup_path(path, 1), &buf).await?;
+        Ok(())
+    }
+
+    /// Replace a corrupt state file at `path` with its newest readable backup, or
+    /// move it aside when there is none. The corrupt file is kept as `state.json.corrupt`.
+    pub async fn repair(path: &Path) -> Result<Repair, StateError> {
+        if !matches!(State::read(path).await, Err(StateError::Corrupt(_))) {
+            return Ok(Repair::Healthy);
+        }
+        let mut aside = path.as_os_str().to_owned();
+        aside.push(".corrupt");
+        tokio::fs::rename(path, PathBuf::from(aside)).await?;
+        match newest_backup(path).await {
+            Some((backup, _)) => {
+                tokio::fs::copy(&backup, path).await?;
+                Ok(Repair::Restored(backup))
             }
+            None => Ok(Repair::Reset),
         }
-        State::default()
     }
 
     pub async fn save(&self, path: &Path) -> std::io::Result<()> {
@@ -43,10 +173,238 @@ impl State {
         tokio::fs::rename(&tmp, path).await
     }
 
+    /// Save to `path` and start counting unsaved items again.
+    pub async fn flush(&mut self, path: &Path) -> std::io::Result<()> {
+        self.save(path).await?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    /// Flush to `path` once `every` items were set since the last flush (never
+    /// when `every` is 0). Returns whether the state was saved.
+    pub async fn flush_every(&mut self, path: &Path, every: usize) -> std::io::Result<bool> {
+        if every == 0 || self.unsaved < every {
+            return Ok(false);
+        }
+        self.flush(path).await?;
+        Ok(true)
+    }
+
     pub fn get(&self, key: &str) -> Option<&ItemState> {
         self.items.get(key)
     }
     pub fn set(&mut self, key: String, st: ItemState) {
         self.items.insert(key, st);
+        self.unsaved += 1;
+    }
+
+    /// Claim `path` for `key`; false when another item already writes to it.
+    pub fn claim_path(&mut self, path: String, key: &str) -> bool {
+        self.paths.entry(path).or_insert_with(|| key.to_string()) == key
+    }
+
+    /// Drop the claims of module `module_id` that `keep` rejects, given the path
+    /// in the module folder and the claiming key. Returns how many were dropped.
+    pub fn release_paths(
+        &mut self,
+        module_id: u64,
+        mut keep: impl FnMut(&str, &str) -> bool,
+    ) -> usize {
+        let prefix = format!("{}/", module_id);
+        let before = self.paths.len();
+        self.paths
+            .retain(|path, key| match path.strip_prefix(&prefix) {
+                Some(rel) => keep(rel, key),
+                None => true,
+            });
+        before - self.paths.len()
+    }
+}
+
+/// Whether `name` is a course state file or one of its backups.
+pub fn is_state_file(name: &str) -> bool {
+    name.starts_with("state.json")
+}
+
+/// `state.json.<n>`, the `n`th newest backup of the state file at `path`.
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Newest backup of the state file at `path` that can be read, with its contents.
+async fn newest_backup(path: &Path) -> Option<(PathBuf, State)> {
+    for n in 1..=STATE_BACKUPS {
+        let backup = backup_path(path, n);
+        match State::read(&backup).await {
+            Ok(Some(state)) => return Some((backup, state)),
+            Ok(None) => {}
+            Err(e) => warn!(path = %backup.display(), error = %e, "unreadable state backup"),
+        }
+    }
+    None
+}
+
+/// Check the state file of every course under the download roots and restore the
+/// corrupt ones from their backups.
+pub async fn run_state_repair() -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    // A missing root is skipped; the Zoom root may live on a disk that is not mounted
+    let (roots, missing): (Vec<_>, Vec<_>) =
+        cfg.archive_roots().into_iter().partition(|r| r.is_dir());
+    if roots.is_empty() {
+        let missing: Vec<_> = missing.iter().map(|r| r.display().to_string()).collect();
+        println!(
+            "{}",
+            tr!("No backup directory found at {}", missing.join(", "))
+        );
+        return Ok(());
+    }
+    let mut dirs = Vec::new();
+    for root in &roots {
+        dirs.extend(course_dirs(root, &[])?);
+    }
+    let mut repaired = 0usize;
+    for dir in dirs {
+        let path = dir.join("state.json");
+        match State::repair(&path).await? {
+            Repair::Healthy => continue,
+            Repair::Restored(backup) => {
+                info!(path = %path.display(), backup = %backup.display(), "restored state file");
+                println!(
+                    "{}",
+                    tr!("✓ Restored {} from {}", path.display(), backup.display())
+                );
+            }
+            Repair::Reset => {
+                warn!(path = %path.display(), "corrupt state file without backup");
+                println!(
+                    "{}",
+                    tr!(
+                        "✗ {} was corrupt and has no backup; moved it aside. Run 'u_crawler adopt' to rebuild it from the downloaded files.",
+                        path.display()
+                    )
+                );
+            }
+        }
+        repaired += 1;
+    }
+    if repaired == 0 {
+        println!("{}", tr!("All state files are readable."));
+    }
+    Ok(())
+}
+
+/// File under `download_root` recording which folder each course syncs into.
+pub const COURSE_DIRS_FILE: &str = "courses.json";
+
+/// Course id to folder (relative to `download_root`) of the last sync, so a course
+/// renamed in Canvas is recognized instead of starting a new tree.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CourseDirs {
+    pub courses: BTreeMap<u64, String>,
+}
+
+impl CourseDirs {
+    pub async fn load(root: &Path) -> CourseDirs {
+        match tokio::fs::read(root.join(COURSE_DIRS_FILE)).await {
+            Ok(buf) => serde_json::from_slice(&buf).unwrap_or_default(),
+            Err(_) => CourseDirs::default(),
+        }
+    }
+
+    pub async fn save(&self, root: &Path) -> std::io::Result<()> {
+        let data = serde_json::to_vec_pretty(self).unwrap();
+        crate::fsutil::atomic_write(&root.join(COURSE_DIRS_FILE), &data).await
+    }
+
+    /// Recorded folder of `course_id`, as an absolute path under `root`.
+    pub fn get(&self, root: &Path, course_id: u64) -> Option<PathBuf> {
+        self.courses.get(&course_id).map(|dir| root.join(dir))
+    }
+
+    /// Record `dir` for `course_id`; returns whether the mapping changed.
+    pub fn set(&mut self, root: &Path, course_id: u64, dir: &Path) -> bool {
+        let rel = dir
+            .strip_prefix(root)
+            .unwrap_or(dir)
+            .to_string_lossy()
+            .replace('\\', "/");
+        self.courses.insert(course_id, rel.clone()).as_deref() != Some(rel.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn corrupt_state_is_restored_from_its_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut state = State {
+            course_id: Some(101),
+            ..State::default()
+        };
+        state.save(&path).await.unwrap();
+        State::backup(&path).await.unwrap();
+        state.course_id = Some(102);
+        state.save(&path).await.unwrap();
+        State::backup(&path).await.unwrap();
+        // Unchanged files are not copied again
+        State::backup(&path).await.unwrap();
+        assert!(backup_path(&path, 2).exists());
+        assert!(!backup_path(&path, 3).exists());
+
+        tokio::fs::write(&path, b"{\"course_id\": 10")
+            .await
+            .unwrap();
+        assert!(matches!(
+            State::read(&path).await,
+            Err(StateError::Corrupt(_))
+        ));
+        // A corrupt file is never rotated into the backups
+        State::backup(&path).await.unwrap();
+        assert_eq!(State::load(&path).await.course_id, Some(102));
+
+        assert_eq!(
+            State::repair(&path).await.unwrap(),
+            Repair::Restored(backup_path(&path, 1))
+        );
+        assert_eq!(
+            State::read(&path).await.unwrap().unwrap().course_id,
+            Some(102)
+        );
+        assert!(dir.path().join("state.json.corrupt").exists());
+        assert_eq!(State::repair(&path).await.unwrap(), Repair::Healthy);
+    }
+
+    #[tokio::test]
+    async fn state_is_flushed_every_few_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let item = ItemState {
+            etag: None,
+            updated_at: None,
+            size: Some(1),
+            content_hash: None,
+            last_error: None,
+            error_count: None,
+            last_modified: None,
+            uuid: None,
+            locked: None,
+        };
+        let mut state = State::default();
+        for key in ["file:1", "file:2"] {
+            state.set(key.into(), item.clone());
+            assert!(!state.flush_every(&path, 3).await.unwrap());
+        }
+        assert!(!path.exists());
+        state.set("file:3".into(), item.clone());
+        assert!(!state.flush_every(&path, 0).await.unwrap());
+        assert!(state.flush_every(&path, 3).await.unwrap());
+        assert_eq!(State::load(&path).await.items.len(), 3);
+        assert!(!state.flush_every(&path, 3).await.unwrap());
     }
 }
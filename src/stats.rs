@@ -0,0 +1,391 @@
+use crate::config::{Config, ConfigPaths};
+use crate::fsutil::course_dirs;
+use crate::history::RunHistory;
+use crate::progress::format_bytes;
+use crate::state::{is_state_file, State};
+use crate::tr;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Top-level directory under `download_root` that holds Zoom recordings.
+const ZOOM_DIR: &str = "Zoom";
+
+/// Aggregated disk usage for one course folder.
+#[derive(Debug, Default)]
+pub struct CourseUsage {
+    pub provider: &'static str,
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub tracked_items: usize,
+    pub failed_items: usize,
+    pub tags: Vec<String>,
+}
+
+/// Totals for files sharing an extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TypeUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+pub async fn run_stats(last_runs: usize, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let roots = cfg.archive_roots();
+    let listed = roots
+        .iter()
+        .map(|r| r.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if !roots.iter().any(|r| r.exists()) {
+        println!("{}", tr!("No backup directory found at {}", listed));
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+    info!(roots = %listed, "collecting archive stats");
+
+    let (courses, mut all_files) = collect_usage(&cfg).await?;
+    let mut by_type: BTreeMap<String, TypeUsage> = BTreeMap::new();
+
+    for (path, size) in &all_files {
+        let entry = by_type.entry(file_type(path)).or_default();
+        entry.files += 1;
+        entry.bytes += size;
+    }
+
+    println!("{}", tr!("Archive Stats ({})\n", listed));
+
+    println!("{}", tr!("Per course:"));
+    for c in &courses {
+        let mut line = tr!(
+            "  [{}] {} - {} files, {}",
+            c.provider,
+            c.name,
+            c.files,
+            format_bytes(c.bytes)
+        );
+        if c.provider == "canvas" {
+            line += &tr!(" ({} tracked, {} failed)", c.tracked_items, c.failed_items);
+        }
+        println!("{}", line);
+    }
+
+    println!("{}", tr!("\nPer provider:"));
+    let mut providers: BTreeMap<&str, TypeUsage> = BTreeMap::new();
+    for c in &courses {
+        let entry = providers.entry(c.provider).or_default();
+        entry.files += c.files;
+        entry.bytes += c.bytes;
+    }
+    for (provider, usage) in &providers {
+        println!(
+            "{}",
+            tr!(
+                "  {}: {} files, {}",
+                provider,
+                usage.files,
+                format_bytes(usage.bytes)
+            )
+        );
+    }
+
+    let mut by_tag: BTreeMap<&str, TypeUsage> = BTreeMap::new();
+    for c in &courses {
+        for tag in &c.tags {
+            let entry = by_tag.entry(tag.as_str()).or_default();
+            entry.files += c.files;
+            entry.bytes += c.bytes;
+        }
+    }
+    if !by_tag.is_empty() {
+        println!("{}", tr!("\nPer tag:"));
+        for (tag, usage) in &by_tag {
+            println!(
+                "{}",
+                tr!(
+                    "  {}: {} files, {}",
+                    tag,
+                    usage.files,
+                    format_bytes(usage.bytes)
+                )
+            );
+        }
+    }
+
+    println!("{}", tr!("\nBy file type:"));
+    let mut types: Vec<_> = by_type.into_iter().collect();
+    types.sort_by_key(|(_, u)| std::cmp::Reverse(u.bytes));
+    for (ext, usage) in &types {
+        println!(
+            "{}",
+            tr!(
+                "  {:<8} {:>6} files  {}",
+                ext,
+                usage.files,
+                format_bytes(usage.bytes)
+            )
+        );
+    }
+
+    println!("{}", tr!("\nLargest files:"));
+    all_files.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    for (path, size) in all_files.iter().take(top) {
+        let rel = roots
+            .iter()
+            .find_map(|r| path.strip_prefix(r).ok())
+            .unwrap_or(path);
+        println!("  {:>10}  {}", format_bytes(*size), rel.display());
+    }
+
+    println!("{}", tr!("\nRecent runs:"));
+    let history = RunHistory::load(&paths.history_file).await;
+    if history.runs.is_empty() {
+        println!("{}", tr!("  No sync runs recorded yet."));
+    } else {
+        let mut volume = 0u64;
+        for run in history.last(last_runs) {
+            volume += run.bytes_downloaded;
+            println!(
+                "{}",
+                tr!(
+                    "  {} - {} course(s), {} file(s), {}, {} failed",
+                    run.started_at,
+                    run.courses,
+                    run.files_downloaded,
+                    format_bytes(run.bytes_downloaded),
+                    run.failed
+                )
+            );
+        }
+        println!(
+            "{}",
+            tr!(
+                "  Downloaded over last {} run(s): {}",
+                last_runs.min(history.runs.len()),
+                format_bytes(volume)
+            )
+        );
+    }
+
+    let total: u64 = courses.iter().map(|c| c.bytes).sum();
+    let total_files: usize = courses.iter().map(|c| c.files).sum();
+    println!("─────────────────────────────");
+    println!(
+        "{}",
+        tr!(
+            "Total: {} course folders, {} files, {}",
+            courses.len(),
+            total_files,
+            format_bytes(total)
+        )
+    );
+    Ok(())
+}
+
+/// Usage of every course folder and Zoom recordings folder, with the files found
+/// in them. Recordings under `zoom.download_root` count as the course's Zoom usage.
+async fn collect_usage(cfg: &Config) -> std::io::Result<(Vec<CourseUsage>, Vec<(PathBuf, u64)>)> {
+    let mut courses = Vec::new();
+    let mut all_files = Vec::new();
+
+    for base in cfg.archive_roots() {
+        let zoom_dir = base.join(ZOOM_DIR);
+        if !zoom_dir.is_dir() {
+            continue;
+        }
+        for course_dir in list_dirs(&zoom_dir)? {
+            let files = walk_files(&course_dir)?;
+            let mut usage = usage_for("zoom", dir_name(&course_dir), &files, None);
+            usage.tags = dir_name(&course_dir)
+                .parse::<u64>()
+                .map(|id| cfg.tags_for(id).to_vec())
+                .unwrap_or_default();
+            courses.push(usage);
+            all_files.extend(files);
+        }
+    }
+    let canvas_root = cfg.canvas_root();
+    let canvas_dirs = if canvas_root.is_dir() {
+        course_dirs(&canvas_root, &[ZOOM_DIR])?
+    } else {
+        Vec::new()
+    };
+    let mut mirrored = Vec::new();
+    for dir in canvas_dirs {
+        let state = State::load(&dir.join("state.json")).await;
+        let tags = state
+            .course_id
+            .map(|id| cfg.tags_for(id).to_vec())
+            .unwrap_or_default();
+        let files = walk_files(&dir)?;
+        let mut usage = usage_for("canvas", dir_name(&dir), &files, Some(&state));
+        usage.tags = tags.clone();
+        courses.push(usage);
+        all_files.extend(files);
+
+        if let Some(recordings) = cfg.zoom_course_dir(&dir).filter(|d| d.is_dir()) {
+            let files = walk_files(&recordings)?;
+            let mut usage = usage_for("zoom", dir_name(&recordings), &files, None);
+            usage.tags = tags;
+            courses.push(usage);
+            all_files.extend(files);
+            mirrored.push(recordings);
+        }
+    }
+    // Recordings of courses whose Canvas folder is missing, e.g. on another disk
+    if let Some(zoom_root) = cfg.separate_zoom_root().filter(|r| r.is_dir()) {
+        for dir in list_dirs(&zoom_root)? {
+            let name = dir_name(&dir);
+            if name == ZOOM_DIR || name.starts_with('.') || mirrored.contains(&dir) {
+                continue;
+            }
+            let files = walk_files(&dir)?;
+            courses.push(usage_for("zoom", name, &files, None));
+            all_files.extend(files);
+        }
+    }
+    Ok((courses, all_files))
+}
+
+fn usage_for(
+    provider: &'static str,
+    name: String,
+    files: &[(PathBuf, u64)],
+    state: Option<&State>,
+) -> CourseUsage {
+    CourseUsage {
+        provider,
+        name,
+        files: files.len(),
+        bytes: files.iter().map(|(_, s)| s).sum(),
+        tracked_items: state.map(|s| s.items.len()).unwrap_or(0),
+        failed_items: state
+            .map(|s| s.items.values().filter(|i| i.last_error.is_some()).count())
+            .unwrap_or(0),
+        tags: Vec::new(),
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+fn list_dirs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Recursively list archived files with their sizes, skipping state and partial files.
+pub(crate) fn walk_files(dir: &Path) -> std::io::Result<Vec<(PathBuf, u64)>> {
+    let mut out = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let meta = entry.metadata()?;
+            if meta.is_dir() {
+                stack.push(path);
+            } else if !is_bookkeeping(&path) {
+                out.push((path, meta.len()));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn is_bookkeeping(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    is_state_file(name) || name.ends_with(".part")
+}
+
+/// Lowercased extension used to bucket files, or `(none)` when missing.
+pub fn file_type(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_else(|| "(none)".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_files_skips_state_and_part_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("Modules").join("1_intro");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join("state.json"), b"{}").unwrap();
+        std::fs::write(nested.join("01-intro.md"), b"hello").unwrap();
+        std::fs::write(nested.join("slides.part"), b"partial").unwrap();
+
+        let files = walk_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1, 5);
+        assert_eq!(file_type(&files[0].0), "md");
+    }
+
+    #[tokio::test]
+    async fn recordings_under_zoom_root_are_counted() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("archive");
+        let videos = dir.path().join("videos");
+        let course = root.join("Calculo_I");
+        std::fs::create_dir_all(&course).unwrap();
+        std::fs::write(course.join("state.json"), r#"{"course_id":101,"items":{}}"#).unwrap();
+        std::fs::write(course.join("01-intro.md"), b"hello").unwrap();
+        let meeting = videos.join("Calculo_I").join("Recordings").join("Clase 1");
+        std::fs::create_dir_all(&meeting).unwrap();
+        std::fs::write(meeting.join("clase.mp4"), b"video").unwrap();
+        let legacy = videos.join(ZOOM_DIR).join("202");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join("clase.m4a"), b"au").unwrap();
+
+        let mut cfg = Config {
+            download_root: root.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        cfg.zoom.download_root = Some(videos.to_string_lossy().into_owned());
+        let (courses, files) = collect_usage(&cfg).await.unwrap();
+
+        let usage: Vec<_> = courses
+            .iter()
+            .map(|c| (c.provider, c.name.as_str(), c.files, c.bytes))
+            .collect();
+        assert_eq!(
+            usage,
+            [
+                ("zoom", "202", 1, 2),
+                ("canvas", "Calculo_I", 1, 5),
+                ("zoom", "Calculo_I", 1, 5),
+            ]
+        );
+        assert!(files.iter().any(|(p, _)| p.ends_with("clase.mp4")));
+
+        // Recordings are still counted once the Canvas folder is gone
+        std::fs::remove_dir_all(&root).unwrap();
+        let (courses, _) = collect_usage(&cfg).await.unwrap();
+        let usage: Vec<_> = courses
+            .iter()
+            .map(|c| (c.provider, c.name.as_str()))
+            .collect();
+        assert_eq!(usage, [("zoom", "202"), ("zoom", "Calculo_I")]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::config::Config;
+use crate::fsutil::course_dirs;
+use crate::progress::format_bytes;
+use crate::state::State;
+use crate::stats::walk_files;
+use crate::tr;
+use crate::zoom::headless::RECORDINGS_DIR;
+use tracing::info;
+
+/// Summarize each synced course from its state file: files, storage, last sync
+/// and failed downloads.
+pub async fn run_status(verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = Config::load_or_init()?;
+    let download_root = cfg.canvas_root();
+
+    info!(path = %download_root.display(), "scanning download root for courses");
+
+    // Check if download_root exists
+    if !download_root.exists() {
+        println!(
+            "{}",
+            tr!("No backup directory found at {}", download_root.display())
+        );
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+
+    // Scan for course directories (including those nested under tag folders)
+    let course_dirs = course_dirs(&download_root, &[])?;
+
+    if course_dirs.is_empty() {
+        println!("{}", tr!("No courses found in {}", download_root.display()));
+        println!(
+            "{}",
+            tr!("Run 'u_crawler sync' to create your first backup.")
+        );
+        return Ok(());
+    }
+
+    info!(count = course_dirs.len(), "found course directories");
+
+    println!("{}", tr!("Backup Status:\n"));
+
+    // Track totals across all courses
+    let mut total_files: usize = 0;
+    let mut total_storage: u64 = 0;
+
+    // Load state from each course directory
+    for course_dir in &course_dirs {
+        let state_path = course_dir.join("state.json");
+        let state = State::load(&state_path).await;
+
+        let course_name = course_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        // Calculate statistics
+        let file_count = state.items.len();
+        let mut course_size: u64 = 0;
+        let mut last_updated: Option<String> = None;
+        let failed_items: Vec<_> = state
+            .items
+            .iter()
+            .filter(|(_, item)| item.last_error.is_some())
+            .collect();
+        let locked_items: Vec<_> = state
+            .items
+            .iter()
+            .filter_map(|(key, item)| item.locked.as_ref().map(|reason| (key, reason)))
+            .collect();
+
+        for item in state.items.values() {
+            // Sum up file sizes
+            if let Some(size) = item.size {
+                course_size += size;
+            }
+
+            // Find most recent updated_at
+            if let Some(ref updated) = item.updated_at {
+                match &last_updated {
+                    None => last_updated = Some(updated.clone()),
+                    Some(current) => {
+                        if updated > current {
+                            last_updated = Some(updated.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Accumulate totals
+        total_files += file_count;
+        total_storage += course_size;
+
+        info!(
+            course = course_name,
+            items = file_count,
+            size = course_size,
+            "loaded course state"
+        );
+
+        // Display course statistics
+        println!("{}", tr!("Course: {}", course_name));
+        if let Some(tags) = state.course_id.map(|id| cfg.tags_for(id)) {
+            if !tags.is_empty() {
+                println!("{}", tr!("  Tags: {}", tags.join(", ")));
+            }
+        }
+        println!("{}", tr!("  Files: {}", file_count));
+        println!("{}", tr!("  Storage: {}", format_bytes(course_size)));
+        // Recordings kept under zoom.download_root are not in the state file
+        if let Some(recordings) = cfg
+            .zoom_course_dir(course_dir)
+            .map(|dir| dir.join(RECORDINGS_DIR))
+            .filter(|dir| dir.is_dir())
+        {
+            let files = walk_files(&recordings)?;
+            println!(
+                "{}",
+                tr!(
+                    "  Recordings: {} file(s), {} in {}",
+                    files.len(),
+                    format_bytes(files.iter().map(|(_, size)| size).sum()),
+                    recordings.display()
+                )
+            );
+        }
+        if let Some(timestamp) = last_updated {
+            println!("{}", tr!("  Last sync: {}", timestamp));
+        } else {
+            println!("{}", tr!("  Last sync: Never"));
+        }
+
+        // Display failed downloads if any
+        if !failed_items.is_empty() {
+            println!(
+                "{}",
+                tr!(
+                    "  Failed downloads: {} items need retry",
+                    failed_items.len()
+                )
+            );
+            if verbose {
+                for (key, item) in &failed_items {
+                    let attempts = item.error_count.unwrap_or(1);
+                    println!("{}", tr!("    - {} (failed {} time(s))", key, attempts));
+                    if let Some(err) = &item.last_error {
+                        // Truncate long errors
+                        let err_short: String = if err.len() > 60 {
+                            format!("{}...", &err[..60])
+                        } else {
+                            err.clone()
+                        };
+                        println!("{}", tr!("      Error: {}", err_short));
+                    }
+                }
+            } else {
+                println!("{}", tr!("      Run with --verbose to see details"));
+            }
+        }
+
+        // Locked files are not failures: they are fetched once Canvas unlocks them
+        if !locked_items.is_empty() {
+            println!(
+                "{}",
+                tr!(
+                    "  Locked files: {} waiting for Canvas to unlock them",
+                    locked_items.len()
+                )
+            );
+            if verbose {
+                for (key, reason) in &locked_items {
+                    println!("{}", tr!("    - {} ({})", key, reason));
+                }
+            }
+        }
+
+        println!();
+    }
+
+    // Display totals summary
+    println!("─────────────────────────────");
+    println!(
+        "{}",
+        tr!(
+            "Total: {} courses, {} files, {}",
+            course_dirs.len(),
+            total_files,
+            format_bytes(total_storage)
+        )
+    );
+    println!();
+    println!(
+        "{}",
+        tr!("Tip: Run 'u_crawler sync --dry-run' to check for remote changes")
+    );
+
+    Ok(())
+}
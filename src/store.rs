@@ -0,0 +1,89 @@
+use crate::fsutil::hash_file;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Folder under `download_root` holding one copy of each file when
+/// `storage.dedup` is on; course folders link to it.
+pub const STORE_DIR: &str = ".store";
+
+/// Location of the blob with SHA-1 `hash`, fanned out by its first two digits.
+pub fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(STORE_DIR).join(&hash[..2]).join(hash)
+}
+
+/// Share the content of the file at `path` with identical files downloaded for
+/// other courses: the first copy becomes the blob, later ones are replaced by a
+/// hard link to it (or a symlink where hard links fail, e.g. across drives).
+/// Returns the bytes saved, which is the file size when the blob already existed.
+pub async fn dedup(root: &Path, path: &Path) -> io::Result<u64> {
+    let file = path.to_path_buf();
+    let (size, hash) = tokio::task::spawn_blocking(move || hash_file(&file))
+        .await
+        .map_err(io::Error::other)??;
+    let blob = blob_path(root, &hash);
+    if tokio::fs::try_exists(&blob).await? {
+        let tmp = path.with_extension("link.part");
+        let _ = tokio::fs::remove_file(&tmp).await;
+        link(&blob, &tmp).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        debug!(path = %path.display(), blob = %blob.display(), "linked to stored copy");
+        return Ok(size);
+    }
+    tokio::fs::create_dir_all(blob.parent().unwrap()).await?;
+    if tokio::fs::hard_link(path, &blob).await.is_err() {
+        // Move the file into the store and point the course copy at it
+        tokio::fs::rename(path, &blob).await?;
+        if let Err(e) = symlink(&blob, path).await {
+            tokio::fs::rename(&blob, path).await?;
+            return Err(e);
+        }
+    }
+    debug!(path = %path.display(), blob = %blob.display(), "stored new content");
+    Ok(0)
+}
+
+async fn link(blob: &Path, path: &Path) -> io::Result<()> {
+    match tokio::fs::hard_link(blob, path).await {
+        Ok(()) => Ok(()),
+        Err(_) => symlink(blob, path).await,
+    }
+}
+
+#[cfg(unix)]
+async fn symlink(blob: &Path, path: &Path) -> io::Result<()> {
+    tokio::fs::symlink(blob, path).await
+}
+
+#[cfg(windows)]
+async fn symlink(blob: &Path, path: &Path) -> io::Result<()> {
+    tokio::fs::symlink_file(blob, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn identical_files_share_one_blob() {
+        let root = tempfile::tempdir().unwrap();
+        let a = root.path().join("A").join("guia.pdf");
+        let b = root.path().join("B").join("guia_2024.pdf");
+        for path in [&a, &b] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"same slides").unwrap();
+        }
+        assert_eq!(dedup(root.path(), &a).await.unwrap(), 0);
+        assert_eq!(dedup(root.path(), &b).await.unwrap(), 11);
+
+        let (_, hash) = hash_file(&a).unwrap();
+        let blob = blob_path(root.path(), &hash);
+        assert_eq!(std::fs::read(&blob).unwrap(), b"same slides");
+        assert_eq!(std::fs::read(&b).unwrap(), b"same slides");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(std::fs::metadata(&blob).unwrap().nlink(), 3);
+        }
+    }
+}
@@ -1,39 +1,287 @@
-use crate::canvas::{Assignment, CanvasClient, FileObj, Module};
-use crate::config::Config;
+use crate::calendar::{self, CALENDAR_FILE};
+use crate::canvas::{
+    Assignment, CanvasClient, Course, FileLock, FileObj, MediaObject, Module, Quiz,
+};
+use crate::config::{parse_size, Config, ConfigPaths, SyncSettings};
+use crate::dashboard;
+use crate::error::{Error, ErrorKind};
+use crate::external_video;
+use crate::front_matter::FrontMatter;
 use crate::fsutil::{
-    atomic_rename, atomic_write, ensure_dir, sanitize_component, sanitize_filename_preserve_ext,
+    apply_remote_mtime, atomic_rename, atomic_write, check_free_space, ensure_dir, parse_timestamp,
+    sanitize_component, sanitize_filename_preserve_ext,
+};
+use crate::git;
+use crate::groups::{GroupProvider, GROUPS_DIR};
+use crate::history::{RunHistory, RunRecord};
+use crate::http::{
+    build_http_client, clear_resume_validator, discard_partial, load_resume_validator, resume_plan,
+    resume_validator, save_resume_validator, HttpCtx, RangedDownload,
+};
+use crate::index::{write_course_index, write_week_indexes, INDEX_FILE};
+use crate::media::{
+    canvas_media_source, discover_media, kaltura_source, studio_source, MediaEmbed, ResolvedMedia,
+};
+use crate::moodle::MoodleClient;
+use crate::plan::{Plan, PlanReason};
+use crate::progress::{
+    attach, bar_println, download_bar, file_bars, progress_bar, spinner, start_transfer,
+    transfer_expect, transfer_inc,
 };
-use crate::http::{build_http_client, HttpCtx};
-use crate::progress::{progress_bar, spinner};
-use crate::state::{ItemState, State};
+use crate::provider::{CourseProvider, UpdateTimes};
+use crate::quizzes::{self, render_quiz};
+use crate::roster::{self, PEOPLE_DIR};
+use crate::rubrics::render_rubric;
+use crate::shared_files::{self, discover_shared_files, SharedFile};
+use crate::shutdown;
+use crate::snapshot::{is_snapshot_target, PageSnapshotter};
+use crate::state::{CourseDirs, ItemState, State};
+use crate::store;
+use crate::weeks::{leading_date, week_dir, week_number, WEEKS_DIR};
+use crate::zoom::headless::display_name;
+use crate::{report, report_verbose, report_warning, tr};
+use clap::ValueEnum;
 use html2md::parse_html;
 use regex::Regex;
 use reqwest::header;
 use sha1::{Digest, Sha1};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 use tokio::io::AsyncWriteExt;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, instrument, warn, Instrument};
 
-pub async fn run_sync(
-    filter_course_id: Option<u64>,
-    dry_run: bool,
-    verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+/// Kinds of module content that `sync --only` / `--skip` select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ContentKind {
+    /// Canvas pages, written as Markdown
+    Pages,
+    /// Course files: module file items and files linked from pages and assignments
+    Files,
+    /// Assignment descriptions, written as Markdown
+    Assignments,
+    /// Quiz settings, instructions and (where visible) questions, written as Markdown
+    Quizzes,
+    /// Embedded videos (Canvas media, Kaltura, Canvas Studio, YouTube/Vimeo)
+    Media,
+    /// Google Drive / OneDrive / SharePoint documents and external URL snapshots
+    External,
+    /// The course's Zoom recordings
+    Zoom,
+    /// Calendar events and assignment due dates, written as `calendar.ics`
+    Calendar,
+    /// Pages, files, discussions and announcements of your groups in the course,
+    /// under `Groups/<name>/`
+    Groups,
+    /// The course roster and groups, written to `People/` (with `sync.roster`, or
+    /// when named in `--only`)
+    People,
+}
+
+/// Content kinds a sync covers: everything in `only` (all kinds when empty) minus `skip`.
+#[derive(Debug, Clone, Default)]
+pub struct ContentFilter {
+    pub only: Vec<ContentKind>,
+    pub skip: Vec<ContentKind>,
+}
+
+impl ContentFilter {
+    pub fn includes(&self, kind: ContentKind) -> bool {
+        (self.only.is_empty() || self.only.contains(&kind)) && !self.skip.contains(&kind)
+    }
+
+    /// Whether every kind is synced, i.e. neither `--only` nor `--skip` was given.
+    pub fn is_all(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty()
+    }
+
+    /// Whether `kind` was named in `--only`, for kinds that are off by default.
+    fn requests(&self, kind: ContentKind) -> bool {
+        self.only.contains(&kind) && !self.skip.contains(&kind)
+    }
+
+    /// Whether pages, assignments and quizzes must be read for the links in them.
+    fn follows_links(&self) -> bool {
+        self.includes(ContentKind::Files)
+            || self.includes(ContentKind::Media)
+            || self.includes(ContentKind::External)
+    }
+}
+
+/// Modified-since window of `sync --since`: items already synced and not updated
+/// since its start are not fetched again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Since {
+    /// No window; every item is fetched.
+    #[default]
+    All,
+    /// Each course's last completed sync.
+    LastRun,
+    /// Local midnight at the start of this day.
+    Date(chrono::NaiveDate),
+}
+
+impl FromStr for Since {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("last") {
+            return Ok(Since::LastRun);
+        }
+        chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(Since::Date)
+            .map_err(|_| tr!("expected a date like 2024-03-01 or `last`, got '{}'", s))
+    }
+}
+
+impl Since {
+    /// Window start for a course whose state is `state`, if there is one.
+    fn start(self, state: &State) -> Option<SystemTime> {
+        use chrono::{Local, TimeZone};
+        match self {
+            Since::All => None,
+            Since::LastRun => state.last_synced.as_deref().and_then(parse_timestamp),
+            Since::Date(day) => Local
+                .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+                .earliest()
+                .map(SystemTime::from),
+        }
+    }
+}
+
+/// Pages and files of a course updated before `start`, per the provider's bulk listing.
+struct Window {
+    start: SystemTime,
+    times: UpdateTimes,
+}
+
+impl Window {
+    fn before_start(&self, updated_at: Option<&String>) -> bool {
+        updated_at
+            .and_then(|t| parse_timestamp(t))
+            .is_some_and(|t| t < self.start)
+    }
+
+    fn page_unchanged(&self, slug: &str) -> bool {
+        self.before_start(self.times.pages.get(slug))
+    }
+
+    fn file_unchanged(&self, file_id: u64) -> bool {
+        self.before_start(self.times.files.get(&file_id))
+    }
+}
+
+/// Command-line options for a sync run.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Sync a single course by id
+    pub course_id: Option<u64>,
+    /// Only sync courses carrying at least one of these tags
+    pub tags: Vec<String>,
+    pub dry_run: bool,
+    pub verbose: bool,
+    /// Sync the configured Moodle site instead of Canvas
+    pub moodle: bool,
+    /// Show the full-screen dashboard instead of progress bars and log lines
+    pub tui: bool,
+    /// Stop at the first failed item or course instead of recording it and moving on
+    pub fail_fast: bool,
+    /// Content kinds to sync
+    pub content: ContentFilter,
+    /// File filters replacing the config's `[sync]` ones where set
+    pub files: SyncSettings,
+    /// Skip items not updated since this point
+    pub since: Since,
+    /// Also list concluded courses, adding `completed` to `courses.state`
+    pub include_concluded: bool,
+    /// Enrollment terms replacing `courses.terms` where set
+    pub terms: Vec<String>,
+    /// Where a dry run writes its plan (default: `plan.json` next to the run history)
+    pub plan_out: Option<PathBuf>,
+    /// Earlier plan to compare a dry run's plan with
+    pub plan_compare: Option<PathBuf>,
+}
+
+/// Plan file a dry run writes when no `plan_out` is given.
+pub const PLAN_FILE: &str = "plan.json";
+
+/// Sync the configured courses. Failed courses and items are counted in the
+/// returned run record instead of stopping the run; only errors that would fail
+/// every course (credentials, config) end it early.
+pub async fn run_sync(opts: SyncOptions) -> Result<RunRecord, Error> {
     let cfg = Config::load_or_init()?;
+    let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+    sync_with(&cfg, httpctx, &ConfigPaths::new()?.history_file, opts).await
+}
 
-    let http = build_http_client(&cfg);
-    let httpctx = HttpCtx::new(&cfg, http);
-    let canvas = CanvasClient::from_config().await?;
+/// Run a sync with an explicit config, HTTP client and run history file instead of
+/// the user's config directory, e.g. against a test server.
+pub async fn sync_with(
+    cfg: &Config,
+    httpctx: HttpCtx,
+    history_file: &Path,
+    opts: SyncOptions,
+) -> Result<RunRecord, Error> {
+    if let Some(max) = opts.files.max_file_size.as_deref() {
+        if parse_size(max).is_none() {
+            return Err(Error::Config(format!("invalid --max-file-size: {}", max)));
+        }
+    }
+    let mut cfg = cfg.clone();
+    cfg.sync = cfg.sync.overridden_by(&opts.files);
+    if opts.include_concluded && !cfg.courses.state.iter().any(|s| s == "completed") {
+        cfg.courses.state.push("completed".into());
+    }
+    if !opts.terms.is_empty() {
+        cfg.courses.terms = opts.terms.clone();
+    }
+    let cfg = &cfg;
+    shutdown::listen();
+    let _dashboard = opts.tui.then(dashboard::start);
+    if opts.moodle {
+        let moodle = MoodleClient::with_http(cfg, httpctx.clone()).await?;
+        let ignored = cfg
+            .moodle
+            .as_ref()
+            .map(|m| m.ignored_courses.clone())
+            .unwrap_or_default();
+        return sync_courses(cfg, &moodle, &httpctx, history_file, &opts, &ignored, false).await;
+    }
+    let canvas = CanvasClient::with_http(cfg, httpctx.clone()).await?;
+    let ignored = cfg.canvas.ignored_courses.clone();
+    let zoom = cfg.zoom.enabled;
+    sync_courses(cfg, &canvas, &httpctx, history_file, &opts, &ignored, zoom).await
+}
 
-    let courses = canvas.list_courses().await?;
-    let ignored: std::collections::HashSet<String> =
-        cfg.canvas.ignored_courses.iter().cloned().collect();
+/// Sync the selected courses of `provider` into the download root, followed by
+/// each course's Zoom recordings when `zoom` is set.
+async fn sync_courses<P: CourseProvider>(
+    cfg: &Config,
+    provider: &P,
+    httpctx: &HttpCtx,
+    history_file: &Path,
+    opts: &SyncOptions,
+    ignored: &[String],
+    zoom: bool,
+) -> Result<RunRecord, Error> {
+    let SyncOptions {
+        course_id: filter_course_id,
+        ref tags,
+        dry_run,
+        verbose,
+        fail_fast,
+        ref content,
+        since,
+        ..
+    } = *opts;
+    let courses = provider.list_courses().await.map_err(Into::into)?;
+    let ignored: std::collections::HashSet<String> = ignored.iter().cloned().collect();
 
     let selected_courses: Vec<crate::canvas::Course> = if let Some(cid) = filter_course_id {
         if ignored.contains(&cid.to_string()) {
             tracing::info!(course_id = cid, "skipping ignored course");
-            return Ok(());
+            return Ok(RunRecord::start());
         }
         let sel = courses
             .into_iter()
@@ -44,7 +292,7 @@ pub async fn run_sync(
                 course_id = cid,
                 "course not found in active list; nothing to sync"
             );
-            return Ok(());
+            return Ok(RunRecord::start());
         }
         sel
     } else {
@@ -53,541 +301,892 @@ pub async fn run_sync(
             .filter(move |c| !ignored.contains(&c.id.to_string()))
             .collect()
     };
+    let selected_courses: Vec<crate::canvas::Course> = if tags.is_empty() {
+        selected_courses
+    } else {
+        selected_courses
+            .into_iter()
+            .filter(|c| {
+                let course_tags = cfg.tags_for(c.id);
+                tags.iter().any(|t| course_tags.contains(t))
+            })
+            .collect()
+    };
 
-    let course_progress = progress_bar(selected_courses.len() as u64, "Syncing courses");
+    let snapshots = match (cfg.snapshot_external_urls, dry_run) {
+        (true, false) => match PageSnapshotter::launch(cfg).await {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!(error = %e, "unable to start browser; external URLs will not be snapshotted");
+                None
+            }
+        },
+        _ => None,
+    };
 
-    let mut total_pages = 0usize;
-    let mut total_files = 0usize;
+    let _transfer = (!dry_run).then(start_transfer);
+    let course_progress = progress_bar(selected_courses.len() as u64, "Syncing courses");
+    let mut run = RunRecord::start();
+    let mut totals = (0usize, 0usize);
+    let mut fatal = None;
     for c in selected_courses {
+        if let Err(e) = shutdown::check() {
+            fatal = Some(e);
+            break;
+        }
         course_progress.inc(1);
-        course_progress.set_message(format!("Syncing course {}", c.id));
-        let code = c.course_code.clone().unwrap_or_default();
-        let course_dir = PathBuf::from(&cfg.download_root).join(if code.is_empty() {
-            sanitize_component(&c.name)
-        } else {
-            format!(
-                "{}_{}",
-                sanitize_component(&c.name),
-                sanitize_component(code)
+        course_progress.set_message(tr!("Syncing course {}", c.id));
+        match sync_course(
+            cfg,
+            provider,
+            httpctx,
+            &c,
+            snapshots.as_ref(),
+            dry_run,
+            verbose,
+            fail_fast,
+            content,
+            since,
+            zoom,
+            &mut run,
+        )
+        .instrument(info_span!("course", course_id = c.id, name = %c.name))
+        .await
+        {
+            Ok((pages, files)) => {
+                totals.0 += pages;
+                totals.1 += files;
+                run.courses += 1;
+            }
+            Err(e) if e.kind() == ErrorKind::Cancelled => {
+                info!(course_id = c.id, "sync interrupted; progress saved");
+                fatal = Some(e);
+                break;
+            }
+            Err(e) => {
+                warn!(course_id = c.id, kind = %e.kind(), error = %e, "course sync failed");
+                report_warning!("{}", tr!("✗ Course {} ({}): {}", c.id, c.name, e));
+                run.fail(e.kind());
+                run.courses_failed += 1;
+                // Bad credentials or config fail every remaining course the same way
+                if fail_fast || matches!(e.kind(), ErrorKind::Auth | ErrorKind::Config) {
+                    fatal = Some(e);
+                    break;
+                }
+            }
+        }
+    }
+    course_progress.finish_and_clear();
+    if content.includes(ContentKind::Calendar) && !dry_run {
+        if let Err(e) = write_combined_calendar(&cfg.canvas_root()).await {
+            warn!(error = %e, "unable to write the combined calendar");
+            report_warning!(
+                "{}",
+                tr!("Warning: unable to write the combined calendar: {}", e)
+            );
+        }
+    }
+    if let Some(snapshots) = snapshots {
+        snapshots.close().await;
+    }
+    if !dry_run {
+        run.finish();
+        run.interrupted = fatal
+            .as_ref()
+            .is_some_and(|e| e.kind() == ErrorKind::Cancelled);
+        let mut history = RunHistory::load(history_file).await;
+        history.push(run.clone());
+        history.save(history_file).await?;
+        if cfg.storage.git {
+            if let Err(e) = git::commit_sync(&cfg.canvas_root(), &run).await {
+                warn!(error = %e, "unable to commit the archive");
+                report_warning!(
+                    "{}",
+                    tr!("Warning: unable to commit the archive to git: {}", e)
+                );
+            }
+        }
+    }
+    if dry_run {
+        report!(
+            "{}",
+            tr!(
+                "DRY-RUN summary: pages to write: {}, files to download: {}",
+                totals.0,
+                totals.1
             )
-        });
+        );
+        let plan = Plan::new(std::mem::take(&mut run.plan), &cfg.canvas_root());
+        let plan_file = opts
+            .plan_out
+            .clone()
+            .unwrap_or_else(|| history_file.with_file_name(PLAN_FILE));
+        write_plan(&plan, &plan_file, opts.plan_compare.as_deref()).await?;
+    } else {
+        report_failed_items(&run);
+        report!("{}", summary_line(&run));
+    }
+    match fatal {
+        Some(e) => Err(e),
+        None => Ok(run),
+    }
+}
+
+/// Save a dry run's plan to `path`, after comparing it with the plan at `compare`.
+async fn write_plan(plan: &Plan, path: &Path, compare: Option<&Path>) -> Result<(), Error> {
+    if let Some(compare) = compare {
+        let previous = Plan::load(compare)
+            .await
+            .map_err(|e| Error::Config(tr!("unable to read plan {}: {}", compare.display(), e)))?;
+        let diff = plan.diff(&previous);
+        report!(
+            "{}",
+            tr!(
+                "Plan compared with {}: {} difference(s)",
+                compare.display(),
+                diff.len()
+            )
+        );
+        for line in &diff {
+            report!("  {}", line);
+        }
+    }
+    let counts: Vec<String> = plan
+        .counts()
+        .iter()
+        .map(|(reason, n)| format!("{}: {}", reason, n))
+        .collect();
+    plan.save(path).await?;
+    report!(
+        "{}",
+        tr!(
+            "Plan with {} action(s) ({}) written to {}",
+            plan.actions.len(),
+            counts.join(", "),
+            path.display()
+        )
+    );
+    Ok(())
+}
+
+/// List the items that failed in this run; the full list is also in the log.
+fn report_failed_items(run: &RunRecord) {
+    const SHOWN: usize = 20;
+    if run.failed_items.is_empty() {
+        return;
+    }
+    report_warning!("{}", tr!("Failed items:"));
+    for item in run.failed_items.iter().take(SHOWN) {
+        report_warning!("  ✗ {}", item);
+    }
+    if run.failed_items.len() > SHOWN {
+        report_warning!(
+            "{}",
+            tr!("  ... and {} more", run.failed_items.len() - SHOWN)
+        );
+    }
+}
+
+/// One-line outcome of a run, e.g. `Sync summary: 3 course(s) synced, 1 failed;
+/// 12 file(s) downloaded (40.00 MB), 2 item(s) failed (network: 3)`.
+pub fn summary_line(run: &RunRecord) -> String {
+    let mut line = tr!("Sync summary: {} course(s) synced, {} failed; {} file(s) downloaded ({}), {} item(s) failed", run.courses, run.courses_failed, run.files_downloaded, crate::progress::format_bytes(run.bytes_downloaded), run.failed.saturating_sub(run.courses_failed));
+    if run.bytes_deduplicated > 0 {
+        line.push_str(&tr!(
+            "; {} already stored for another course",
+            crate::progress::format_bytes(run.bytes_deduplicated)
+        ));
+    }
+    if !run.failures.is_empty() {
+        let by_kind: Vec<String> = run
+            .failures
+            .iter()
+            .map(|(kind, n)| format!("{}: {}", kind, n))
+            .collect();
+        line.push_str(&format!(" ({})", by_kind.join(", ")));
+    }
+    line
+}
+
+/// Sync one course's modules, then its Zoom recordings when `zoom` is set.
+/// Returns the pages and files written (or planned, in a dry run).
+#[allow(clippy::too_many_arguments)]
+async fn sync_course<P: CourseProvider>(
+    cfg: &Config,
+    provider: &P,
+    httpctx: &HttpCtx,
+    c: &Course,
+    snapshots: Option<&PageSnapshotter>,
+    dry_run: bool,
+    verbose: bool,
+    fail_fast: bool,
+    content: &ContentFilter,
+    since: Since,
+    zoom: bool,
+    run: &mut RunRecord,
+) -> Result<(usize, usize), Error> {
+    let course_dir = resolve_course_dir(cfg, c, dry_run).await?;
+    if !dry_run {
+        ensure_dir(&course_dir).await?;
+    }
+    info!(course_id = c.id, path = %course_dir.display(), "sync course");
+
+    // Load course state, keeping a copy of it from before this sync
+    let state_path = course_dir.join("state.json");
+    if !dry_run {
+        if let Err(e) = State::backup(&state_path).await {
+            warn!(course_id = c.id, error = %e, "unable to back up the state file");
+        }
+    }
+    let mut state = State::load(&state_path).await;
+    state.course_id = Some(c.id);
+    state.start_at = c
+        .start_at
+        .clone()
+        .or_else(|| c.term.as_ref().and_then(|t| t.start_at.clone()));
+    let started = chrono::Utc::now().to_rfc3339();
+    let window = match since.start(&state) {
+        Some(start) => match provider.update_times(c.id).await {
+            Ok(times) => times.map(|times| Window { start, times }),
+            Err(e) => {
+                let e: Error = e.into();
+                warn!(course_id = c.id, error = %e, "unable to list update times; fetching every item");
+                None
+            }
+        },
+        None => None,
+    };
+
+    let modules_spinner = spinner(&tr!("Loading modules for {}", c.name));
+    let modules = provider
+        .list_modules_with_items(c.id)
+        .await
+        .map_err(Into::into);
+    modules_spinner.finish_and_clear();
+    let modules = modules?;
+
+    let needed = estimate_new_bytes(provider, &modules, &state, &cfg.sync).await;
+    if let Err(e) = check_free_space(&course_dir, needed, cfg.free_space_reserve()) {
         if !dry_run {
-            ensure_dir(&course_dir).await?;
-        }
-        info!(course_id = c.id, path = %course_dir.display(), "sync course");
-
-        // Load course state
-        let state_path = course_dir.join("state.json");
-        let mut state = State::load(&state_path).await;
-
-        let modules_spinner = spinner(&format!("Loading modules for {}", c.name));
-        let modules = canvas.list_modules_with_items(c.id).await?;
-        modules_spinner.finish_and_clear();
-        // Preload assignments to avoid per-item fetch; map by id
-        let assignments_spinner = spinner(&format!("Loading assignments for {}", c.name));
-        let assignments_list = canvas.list_assignments(c.id).await.unwrap_or_default();
-        assignments_spinner.finish_and_clear();
-        let assignments: std::collections::HashMap<u64, Assignment> =
-            assignments_list.into_iter().map(|a| (a.id, a)).collect();
-        let module_progress = progress_bar(modules.len() as u64, &format!("Modules in {}", c.name));
-        for m in modules {
-            module_progress.inc(1);
-            module_progress.set_message(format!("Course {} module {}", c.id, m.id));
-            let (p, f) = sync_module(
-                &cfg,
-                &canvas,
-                &httpctx,
-                &course_dir,
+            return Err(e.into());
+        }
+        warn!(course_id = c.id, error = %e, "dry-run: insufficient disk space");
+        report!("{}", tr!("DRY-RUN warning: {}", e));
+    }
+    if dry_run && needed > 0 {
+        report!(
+            "{}",
+            tr!(
+                "DRY-RUN course {} -> about {} of new module files",
                 c.id,
-                &assignments,
-                &mut state,
-                &m,
+                crate::progress::format_bytes(needed)
+            )
+        );
+    }
+    // Preload assignments and quizzes to avoid per-item fetch; map by id
+    let listings_spinner = spinner(&tr!("Loading assignments for {}", c.name));
+    let mut listings = CourseListings::default();
+    if content.includes(ContentKind::Assignments)
+        || content.includes(ContentKind::Calendar)
+        || content.follows_links()
+    {
+        let assignments = provider.list_assignments(c.id).await.unwrap_or_default();
+        listings.assignments = assignments.into_iter().map(|a| (a.id, a)).collect();
+    }
+    if content.includes(ContentKind::Quizzes) || content.follows_links() {
+        let quizzes = provider.list_quizzes(c.id).await.unwrap_or_default();
+        listings.quizzes = quizzes.into_iter().map(|q| (q.id, q)).collect();
+    }
+    listings_spinner.finish_and_clear();
+    let module_progress = progress_bar(modules.len() as u64, &tr!("Modules in {}", c.name));
+    let (mut total_pages, mut total_files) = (0usize, 0usize);
+    let module_dirs: Vec<PathBuf> = modules
+        .iter()
+        .map(|m| module_dir_for(&course_dir, m, &cfg.week_pattern))
+        .collect();
+    for m in modules {
+        if let Err(e) = shutdown::check() {
+            module_progress.finish_and_clear();
+            return Err(e);
+        }
+        module_progress.inc(1);
+        module_progress.set_message(tr!("Course {} module {}", c.id, m.id));
+        let result = sync_module(
+            cfg,
+            provider,
+            httpctx,
+            &course_dir,
+            c.id,
+            &c.name,
+            &listings,
+            &mut state,
+            run,
+            &m,
+            snapshots,
+            dry_run,
+            verbose,
+            fail_fast,
+            content,
+            window.as_ref(),
+        )
+        .instrument(info_span!("module", module_id = m.id, name = %m.name))
+        .await;
+        let (p, f) = match result {
+            Ok(counts) => counts,
+            Err(e) => {
+                // Keep what the earlier modules recorded
+                module_progress.finish_and_clear();
+                if !dry_run {
+                    state.save(&state_path).await?;
+                }
+                return Err(e);
+            }
+        };
+        total_pages += p;
+        total_files += f;
+        // Checkpoint, so an interrupted run keeps every finished module
+        if !dry_run {
+            state.flush(&state_path).await?;
+        }
+        if dry_run && (p > 0 || f > 0) {
+            bar_println(
+                &module_progress,
+                tr!("DRY-RUN module {} -> pages: {}, files: {}", m.id, p, f),
+            );
+        }
+    }
+    module_progress.finish_and_clear();
+
+    if content.includes(ContentKind::Calendar)
+        && sync_calendar(
+            provider,
+            c,
+            &course_dir,
+            &listings,
+            dry_run,
+            fail_fast,
+            &mut state,
+            run,
+        )
+        .await?
+        && dry_run
+    {
+        total_pages += 1;
+    }
+
+    if (cfg.sync.roster && content.includes(ContentKind::People))
+        || content.requests(ContentKind::People)
+    {
+        let written = sync_roster(
+            provider,
+            c,
+            &course_dir,
+            dry_run,
+            fail_fast,
+            &mut state,
+            run,
+        )
+        .await?;
+        if written && dry_run {
+            total_pages += 1;
+        }
+    }
+
+    if content.includes(ContentKind::Groups) {
+        if let Some(groups) = provider.group_provider() {
+            let (p, f) = sync_groups(
+                cfg,
+                &groups,
+                httpctx,
+                c,
+                &course_dir,
+                snapshots,
                 dry_run,
                 verbose,
+                fail_fast,
+                content,
+                &mut state,
+                run,
             )
             .await?;
             total_pages += p;
             total_files += f;
-            if dry_run && (p > 0 || f > 0) {
-                module_progress.println(format!(
-                    "DRY-RUN module {} -> pages: {}, files: {}",
-                    m.id, p, f
-                ));
-            }
         }
-        module_progress.finish_and_clear();
+    }
 
-        // Sync Zoom recordings for this course
-        println!("Starting Zoom sync for course {}...", c.id);
+    // Sync Zoom recordings for this course
+    if zoom && content.includes(ContentKind::Zoom) {
+        report!("{}", tr!("Starting Zoom sync for course {}...", c.id));
+        // Recordings are filed by week from the course start saved in the state
+        if !dry_run {
+            state.save(&state_path).await?;
+        }
         match crate::zoom::zoom_flow(c.id, 1, None).await {
             Ok(()) => {
-                println!("✓ Zoom sync completed for course {}", c.id);
+                report!("{}", tr!("✓ Zoom sync completed for course {}", c.id));
             }
+            Err(e) if e.kind() == ErrorKind::Cancelled => return Err(e),
             Err(e) => {
-                warn!(course_id = c.id, error = %e, "zoom flow failed for course");
-                eprintln!("Warning: Zoom sync failed for course {}: {}", c.id, e);
+                warn!(course_id = c.id, kind = %e.kind(), error = %e, "zoom flow failed for course");
+                report_warning!(
+                    "{}",
+                    tr!("Warning: Zoom sync failed for course {}: {}", c.id, e)
+                );
+                run.fail(e.kind());
                 // Continue with other courses even if Zoom fails
             }
         }
+    }
+
+    if !dry_run {
+        let root = &cfg.canvas_root();
+        if write_course_index(root, &course_dir, c.id, &c.name, &module_dirs).await? {
+            debug!(course_id = c.id, "wrote course index");
+        }
+        let start = state.start_at.as_deref().and_then(leading_date);
+        let weeks = write_week_indexes(root, &course_dir, c.id, start).await?;
+        if weeks > 0 {
+            debug!(course_id = c.id, weeks, "wrote week indexes");
+        }
+        state.last_synced = Some(started);
+        state.save(&state_path).await?;
+    }
+    Ok((total_pages, total_files))
+}
+
+/// Write the course's calendar events and assignment due dates to `calendar.ics`,
+/// unless they are unchanged. Returns whether the file was written (or planned).
+#[allow(clippy::too_many_arguments)]
+async fn sync_calendar<P: CourseProvider>(
+    provider: &P,
+    c: &Course,
+    course_dir: &Path,
+    listings: &CourseListings,
+    dry_run: bool,
+    fail_fast: bool,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<bool, Error> {
+    let key = "calendar".to_string();
+    let events = match provider.list_calendar_events(c.id).await {
+        Ok(events) => events,
+        Err(e) => {
+            let e: Error = e.into();
+            warn!(course_id = c.id, error = %e, "unable to list calendar events");
+            record_failure(state, run, key, e, fail_fast)?;
+            return Ok(false);
+        }
+    };
+    let ics = calendar::course_calendar(c, &events, listings.assignments.values());
+    let hash = sha1_hex(ics.as_bytes());
+    let dest = course_dir.join(CALENDAR_FILE);
+    if state.get(&key).and_then(|s| s.content_hash.as_deref()) == Some(hash.as_str())
+        && dest.exists()
+    {
+        debug!(course_id = c.id, "calendar unchanged");
+        return Ok(false);
+    }
+    if dry_run {
+        info!(course_id = c.id, path = %dest.display(), events = events.len(), "dry-run calendar planned");
+        return Ok(true);
+    }
+    atomic_write(&dest, ics.as_bytes()).await?;
+    state.set(
+        key,
+        ItemState {
+            etag: None,
+            updated_at: None,
+            size: Some(ics.len() as u64),
+            content_hash: Some(hash),
+            last_error: None,
+            error_count: None,
+            last_modified: None,
+            uuid: None,
+            locked: None,
+        },
+    );
+    info!(course_id = c.id, path = %dest.display(), "wrote course calendar");
+    Ok(true)
+}
 
+/// Sync the workspaces of the user's groups in course `c` into `Groups/<name>/`,
+/// each with its own state file. Returns the pages and files written (or planned).
+#[allow(clippy::too_many_arguments)]
+async fn sync_groups(
+    cfg: &Config,
+    groups: &GroupProvider<'_>,
+    httpctx: &HttpCtx,
+    c: &Course,
+    course_dir: &Path,
+    snapshots: Option<&PageSnapshotter>,
+    dry_run: bool,
+    verbose: bool,
+    fail_fast: bool,
+    content: &ContentFilter,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<(usize, usize), Error> {
+    let mine = match groups.my_groups(c.id).await {
+        Ok(mine) => mine,
+        Err(e) => {
+            let e: Error = e.into();
+            warn!(course_id = c.id, error = %e, "unable to list groups");
+            record_failure(state, run, "groups".to_string(), e, fail_fast)?;
+            return Ok((0, 0));
+        }
+    };
+    // Everything in a workspace follows --skip; `--only groups` syncs all of it
+    let inner = ContentFilter {
+        only: Vec::new(),
+        skip: content.skip.clone(),
+    };
+    let mut totals = (0usize, 0usize);
+    for g in mine {
+        let name = g.name.clone().unwrap_or_else(|| format!("group_{}", g.id));
+        let group_dir = course_dir.join(GROUPS_DIR).join(sanitize_component(&name));
+        let modules = match groups.list_modules_with_items(g.id).await {
+            Ok(modules) => modules,
+            Err(e) => {
+                let e: Error = e.into();
+                warn!(course_id = c.id, group_id = g.id, error = %e, "unable to list group content");
+                record_failure(state, run, format!("group:{}", g.id), e, fail_fast)?;
+                continue;
+            }
+        };
+        let state_path = group_dir.join("state.json");
+        let mut group_state = State::load(&state_path).await;
+        for m in &modules {
+            let result = sync_module(
+                cfg,
+                groups,
+                httpctx,
+                &group_dir,
+                g.id,
+                &name,
+                &CourseListings::default(),
+                &mut group_state,
+                run,
+                m,
+                snapshots,
+                dry_run,
+                verbose,
+                fail_fast,
+                &inner,
+                None,
+            )
+            .await;
+            match result {
+                Ok((p, f)) => {
+                    totals.0 += p;
+                    totals.1 += f;
+                    if !dry_run {
+                        group_state.flush(&state_path).await?;
+                    }
+                }
+                Err(e) => {
+                    if !dry_run {
+                        group_state.save(&state_path).await?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
         if !dry_run {
-            state.save(&state_path).await?;
+            group_state.save(&state_path).await?;
         }
+        info!(course_id = c.id, group_id = g.id, path = %group_dir.display(), "synced group workspace");
+    }
+    Ok(totals)
+}
+
+/// Write the course roster and group memberships to `People/`, unless they are
+/// unchanged. Returns whether the files were written (or planned).
+async fn sync_roster<P: CourseProvider>(
+    provider: &P,
+    c: &Course,
+    course_dir: &Path,
+    dry_run: bool,
+    fail_fast: bool,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<bool, Error> {
+    let key = "roster".to_string();
+    let roster = match provider.course_roster(c.id).await {
+        Ok(Some(roster)) => roster,
+        Ok(None) => return Ok(false),
+        Err(e) => {
+            let e: Error = e.into();
+            warn!(course_id = c.id, error = %e, "unable to list the course roster");
+            record_failure(state, run, key, e, fail_fast)?;
+            return Ok(false);
+        }
+    };
+    let files = roster::render_roster(&roster);
+    let mut hasher = Sha1::new();
+    for (name, contents) in &files {
+        hasher.update(name.as_bytes());
+        hasher.update(contents.as_bytes());
+    }
+    let hash = hex::encode(hasher.finalize());
+    let dir = course_dir.join(PEOPLE_DIR);
+    if state.get(&key).and_then(|s| s.content_hash.as_deref()) == Some(hash.as_str())
+        && files.iter().all(|(name, _)| dir.join(name).exists())
+    {
+        debug!(course_id = c.id, "roster unchanged");
+        return Ok(false);
     }
-    course_progress.finish_and_clear();
     if dry_run {
-        println!(
-            "DRY-RUN summary: pages to write: {}, files to download: {}",
-            total_pages, total_files
-        );
+        info!(course_id = c.id, path = %dir.display(), people = roster.users.len(), groups = roster.groups.len(), "dry-run roster planned");
+        return Ok(true);
+    }
+    for (name, contents) in &files {
+        atomic_write(&dir.join(name), contents.as_bytes()).await?;
+    }
+    state.set(
+        key,
+        ItemState {
+            etag: None,
+            updated_at: None,
+            size: Some(files.iter().map(|(_, c)| c.len() as u64).sum()),
+            content_hash: Some(hash),
+            last_error: None,
+            error_count: None,
+            last_modified: None,
+            uuid: None,
+            locked: None,
+        },
+    );
+    info!(course_id = c.id, path = %dir.display(), people = roster.users.len(), groups = roster.groups.len(), "wrote course roster");
+    Ok(true)
+}
+
+/// Merge every course's `calendar.ics` into `<download_root>/calendar.ics`, so
+/// courses skipped by this run keep their events in it.
+async fn write_combined_calendar(root: &Path) -> std::io::Result<()> {
+    let calendars = calendar::course_calendars(root)?;
+    let ics = calendar::merge_calendars("All courses", &calendars);
+    let dest = root.join(CALENDAR_FILE);
+    if tokio::fs::read_to_string(&dest).await.ok().as_deref() == Some(ics.as_str()) {
+        return Ok(());
     }
+    atomic_write(&dest, ics.as_bytes()).await?;
+    info!(path = %dest.display(), courses = calendars.len(), "wrote combined calendar");
     Ok(())
 }
 
+/// Course-wide listings loaded once per course instead of per module item.
+#[derive(Default)]
+struct CourseListings {
+    assignments: std::collections::HashMap<u64, Assignment>,
+    quizzes: std::collections::HashMap<u64, Quiz>,
+}
+
 #[allow(clippy::too_many_arguments)]
-async fn sync_module(
-    _cfg: &Config,
-    canvas: &CanvasClient,
+async fn sync_module<P: CourseProvider>(
+    cfg: &Config,
+    provider: &P,
     httpctx: &HttpCtx,
     course_dir: &Path,
     course_id: u64,
-    assignments: &std::collections::HashMap<u64, Assignment>,
+    course_name: &str,
+    listings: &CourseListings,
     state: &mut State,
+    run: &mut RunRecord,
     m: &Module,
+    snapshots: Option<&PageSnapshotter>,
     dry_run: bool,
     verbose: bool,
-) -> Result<(usize, usize), Box<dyn std::error::Error>> {
-    let module_dir =
-        course_dir
-            .join("Modules")
-            .join(format!("{}_{}", m.id, sanitize_component(&m.name)));
+    fail_fast: bool,
+    content: &ContentFilter,
+    window: Option<&Window>,
+) -> Result<(usize, usize), Error> {
+    let module_dir = module_dir_for(course_dir, m, &cfg.week_pattern);
     if !dry_run {
+        relocate_module_dir(course_dir, &module_dir).await?;
         ensure_dir(&module_dir).await?;
     }
     info!(course_id, module_id = m.id, "sync module");
 
     let mut pages_planned = 0usize;
     let mut files_planned = 0usize;
+    let mut unchanged = 0usize;
+    // Already synced without error and, per the window, not updated since
+    let skip = |state: &State, key: &str, unchanged: bool| {
+        unchanged
+            && state
+                .get(key)
+                .is_some_and(|s| s.last_error.is_none() && s.locked.is_none())
+    };
     let mut processed_ids: HashSet<u64> = HashSet::new();
+    let fctx = FileCtx {
+        cfg,
+        provider,
+        httpctx,
+        module_dir: &module_dir,
+        course_id,
+        course_name,
+        module_id: m.id,
+        module_name: &m.name,
+        folder: ATTACHMENTS_DIR,
+        dry_run,
+        verbose,
+        fail_fast,
+        content,
+    };
+    let mut media = MediaCtx::default();
+    let mut index: Vec<IndexEntry> = Vec::with_capacity(m.items.len());
+    let state_path = course_dir.join("state.json");
+    release_stale_claims(state, m);
     for (idx, item) in m.items.iter().enumerate() {
+        shutdown::check()?;
+        // Long modules are checkpointed too, so a crash keeps the finished items
+        if !dry_run
+            && state
+                .flush_every(&state_path, cfg.storage.state_flush_items)
+                .await?
+        {
+            debug!(course_id, module_id = m.id, "saved state");
+        }
+        let mut local: Option<String> = None;
         match item.kind.as_deref() {
-            Some("Page") => {
+            Some("Page") if fctx.reads_pages() => {
                 if let Some(page_url) = &item.page_url {
                     let key = format!("page:{}", page_url);
-                    let page = canvas.get_page(course_id, page_url).await?;
+                    if skip(
+                        state,
+                        &key,
+                        window.is_some_and(|w| w.page_unchanged(page_url)),
+                    ) {
+                        unchanged += 1;
+                        continue;
+                    }
+                    let Some(page) = fetch_page(&fctx, &key, page_url, state, run).await? else {
+                        index.push(IndexEntry::for_item(item, None));
+                        continue;
+                    };
                     let title = page.title.clone().unwrap_or_else(|| {
                         item.title
                             .clone()
                             .unwrap_or_else(|| format!("item_{}", idx))
                     });
                     let html = page.body.unwrap_or_default();
-                    let md = parse_html(&html);
-                    let hash = sha1_hex(md.as_bytes());
-                    let fname = format!("{:02}-{}.md", idx + 1, sanitize_component(&title));
-                    let dest = module_dir.join(&fname);
-                    if state.get(&key).and_then(|s| s.content_hash.as_deref())
-                        == Some(hash.as_str())
-                    {
-                        debug!(course_id, module_id = m.id, page_url, "page unchanged");
-                        if !dry_run && verbose {
+                    if content.includes(ContentKind::Pages) {
+                        let meta = fctx.front_matter(
+                            "page",
+                            item.content_id,
+                            page.updated_at.as_deref(),
+                            item.html_url.as_deref(),
+                        );
+                        let (md, hash) = with_front_matter(&meta, &parse_html(&html));
+                        let fname = format!("{:02}-{}.md", idx + 1, sanitize_component(&title));
+                        let dest = module_dir.join(&fname);
+                        local = Some(fname);
+                        if state.get(&key).and_then(|s| s.content_hash.as_deref())
+                            == Some(hash.as_str())
+                        {
+                            debug!(course_id, module_id = m.id, page_url, "page unchanged");
+                            if !dry_run && verbose {
+                                info!(
+                                    course_id,
+                                    module_id = m.id,
+                                    path = %dest.display(),
+                                    "page unchanged; skipping"
+                                );
+                            }
+                        } else if dry_run {
+                            pages_planned += 1;
+                            run.plan(course_id, &key, Some(&dest), content_reason(state, &key));
                             info!(
                                 course_id,
                                 module_id = m.id,
                                 path = %dest.display(),
-                                "page unchanged; skipping"
+                                bytes = md.len(),
+                                "dry-run page planned"
                             );
-                        }
-                    } else if dry_run {
-                        pages_planned += 1;
-                        info!(
-                            course_id,
-                            module_id = m.id,
-                            path = %dest.display(),
-                            bytes = md.len(),
-                            "dry-run page planned"
-                        );
-                    } else {
-                        atomic_write(&dest, md.as_bytes()).await?;
-                        state.set(
-                            key,
-                            ItemState {
-                                etag: None,
-                                updated_at: page.updated_at,
-                                size: Some(md.len() as u64),
-                                content_hash: Some(hash),
-                                last_error: None,
-                                error_count: None,
-                            },
-                        );
-                        info!(
-                            course_id,
-                            module_id = m.id,
-                            path = %dest.display(),
-                            "wrote page markdown"
-                        );
-                    }
-
-                    // Discover file links inside the page HTML and download
-                    let file_ids = discover_file_ids(&html);
-                    for fid in file_ids {
-                        if !processed_ids.insert(fid) {
-                            continue;
-                        }
-                        match canvas.get_file(fid).await {
-                            Ok(f) => {
-                                let fname = f
-                                    .display_name
-                                    .clone()
-                                    .or(f.filename.clone())
-                                    .unwrap_or_else(|| format!("file_{}", fid));
-                                let dest = module_dir
-                                    .join("Attachments")
-                                    .join(sanitize_filename_preserve_ext(&fname));
-                                let f_ext = dest
-                                    .extension()
-                                    .and_then(|s| s.to_str())
-                                    .unwrap_or_default();
-                                let keyf = format!("file:{}", f.id);
-                                if dry_run {
-                                    if state.get(&keyf).is_some() {
-                                        info!(
-                                            course_id,
-                                            module_id = m.id,
-                                            file_id = fid,
-                                            path = %dest.display(),
-                                            "dry-run skip file; already synced"
-                                        );
-                                    } else {
-                                        files_planned += 1;
-                                        info!(
-                                            course_id,
-                                            module_id = m.id,
-                                            file_id = fid,
-                                            path = %dest.display(),
-                                            file_ext = f_ext,
-                                            "dry-run file planned"
-                                        );
-                                    }
-                                } else {
-                                    ensure_dir(dest.parent().unwrap()).await?;
-                                    match download_if_needed(httpctx, &f, &dest, state, verbose)
-                                        .await
-                                    {
-                                        Ok(()) => {
-                                            info!(course_id, module_id = m.id, file_id = fid, path = %dest.display(), "downloaded file [{}]", f_ext);
-                                        }
-                                        Err(e) => {
-                                            warn!(course_id, module_id = m.id, file_id = fid, error = %e, "download failed");
-                                            let keyf = format!("file:{}", fid);
-                                            let current_state = state.get(&keyf);
-                                            let error_count = current_state
-                                                .and_then(|s| s.error_count)
-                                                .unwrap_or(0)
-                                                + 1;
-                                            state.set(
-                                                keyf,
-                                                ItemState {
-                                                    etag: current_state
-                                                        .and_then(|s| s.etag.clone()),
-                                                    updated_at: current_state
-                                                        .and_then(|s| s.updated_at.clone()),
-                                                    size: current_state.and_then(|s| s.size),
-                                                    content_hash: current_state
-                                                        .and_then(|s| s.content_hash.clone()),
-                                                    last_error: Some(e.to_string()),
-                                                    error_count: Some(error_count),
-                                                },
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                warn!(course_id, module_id = m.id, file_id = fid, error = %e, "unable to fetch file metadata (discovered)");
-                                // Record error in state
-                                let keyf = format!("file:{}", fid);
-                                let current_state = state.get(&keyf);
-                                let error_count =
-                                    current_state.and_then(|s| s.error_count).unwrap_or(0) + 1;
-                                state.set(
-                                    keyf,
-                                    ItemState {
-                                        etag: current_state.and_then(|s| s.etag.clone()),
-                                        updated_at: current_state
-                                            .and_then(|s| s.updated_at.clone()),
-                                        size: current_state.and_then(|s| s.size),
-                                        content_hash: current_state
-                                            .and_then(|s| s.content_hash.clone()),
-                                        last_error: Some(e.to_string()),
-                                        error_count: Some(error_count),
-                                    },
-                                );
-                            }
+                        } else {
+                            atomic_write(&dest, md.as_bytes()).await?;
+                            apply_remote_mtime(&dest, page.updated_at.as_deref());
+                            state.set(
+                                key,
+                                ItemState {
+                                    etag: None,
+                                    updated_at: page.updated_at.clone(),
+                                    size: Some(md.len() as u64),
+                                    content_hash: Some(hash),
+                                    last_error: None,
+                                    error_count: None,
+                                    last_modified: None,
+                                    uuid: None,
+                                    locked: None,
+                                },
+                            );
+                            info!(
+                                course_id,
+                                module_id = m.id,
+                                path = %dest.display(),
+                                "wrote page markdown"
+                            );
+                        }
+                        if cfg.sync.page_revisions && !dry_run {
+                            sync_page_revisions(
+                                &fctx,
+                                course_dir,
+                                page_url,
+                                item,
+                                page.updated_at.as_deref(),
+                                state,
+                            )
+                            .await?;
                         }
                     }
+
+                    // Discover file links inside the page HTML and download
+                    files_planned += sync_linked(
+                        &fctx,
+                        &html,
+                        &mut media,
+                        &mut processed_ids,
+                        "discovered",
+                        state,
+                        run,
+                    )
+                    .await?;
                 }
             }
             // Some modules link to pages via html_url even if kind isn't Page (e.g., ExternalUrl)
-            _ if item
-                .html_url
-                .as_deref()
-                .is_some_and(|u| is_course_page_url(u, course_id)) =>
+            _ if fctx.reads_pages()
+                && item
+                    .html_url
+                    .as_deref()
+                    .is_some_and(|u| is_course_page_url(u, course_id)) =>
             {
                 // Extract slug from html_url
                 if let Some(slug) = extract_page_slug(item.html_url.as_ref().unwrap()) {
                     let key = format!("page:{}", slug);
-                    let page = canvas.get_page(course_id, &slug).await?;
+                    if skip(state, &key, window.is_some_and(|w| w.page_unchanged(&slug))) {
+                        unchanged += 1;
+                        continue;
+                    }
+                    let Some(page) = fetch_page(&fctx, &key, &slug, state, run).await? else {
+                        index.push(IndexEntry::for_item(item, None));
+                        continue;
+                    };
                     let title = page
                         .title
                         .clone()
                         .unwrap_or_else(|| item.title.clone().unwrap_or_else(|| slug.clone()));
                     let html = page.body.unwrap_or_default();
-                    let md = parse_html(&html);
-                    let hash = sha1_hex(md.as_bytes());
-                    let fname = format!("{:02}-{}.md", idx + 1, sanitize_component(&title));
-                    let dest = module_dir.join(&fname);
-                    if state.get(&key).and_then(|s| s.content_hash.as_deref())
-                        == Some(hash.as_str())
-                    {
-                        if !dry_run && verbose {
-                            info!(
-                                course_id,
-                                module_id = m.id,
-                                path = %dest.display(),
-                                "page unchanged; skipping"
-                            );
-                        }
-                    } else if dry_run {
-                        pages_planned += 1;
-                        info!(
-                            course_id,
-                            module_id = m.id,
-                            path = %dest.display(),
-                            bytes = md.len(),
-                            "dry-run page planned"
-                        );
-                    } else {
-                        atomic_write(&dest, md.as_bytes()).await?;
-                        state.set(
-                            key,
-                            ItemState {
-                                etag: None,
-                                updated_at: page.updated_at,
-                                size: Some(md.len() as u64),
-                                content_hash: Some(hash),
-                                last_error: None,
-                                error_count: None,
-                            },
+                    if content.includes(ContentKind::Pages) {
+                        let meta = fctx.front_matter(
+                            "page",
+                            item.content_id,
+                            page.updated_at.as_deref(),
+                            item.html_url.as_deref(),
                         );
-                        info!(course_id, module_id = m.id, path = %dest.display(), "wrote page markdown");
-                    }
-                    let file_ids = discover_file_ids(&html);
-                    for fid in file_ids {
-                        if !processed_ids.insert(fid) {
-                            continue;
-                        }
-                        match canvas.get_file(fid).await {
-                            Ok(f) => {
-                                let fname = f
-                                    .display_name
-                                    .clone()
-                                    .or(f.filename.clone())
-                                    .unwrap_or_else(|| format!("file_{}", fid));
-                                let dest = module_dir
-                                    .join("Attachments")
-                                    .join(sanitize_filename_preserve_ext(&fname));
-                                let f_ext = dest
-                                    .extension()
-                                    .and_then(|s| s.to_str())
-                                    .unwrap_or_default();
-                                let keyf = format!("file:{}", f.id);
-                                if dry_run {
-                                    if state.get(&keyf).is_some() {
-                                        info!(
-                                            course_id,
-                                            module_id = m.id,
-                                            file_id = fid,
-                                            path = %dest.display(),
-                                            "dry-run skip file; already synced"
-                                        );
-                                    } else {
-                                        files_planned += 1;
-                                        info!(
-                                            course_id,
-                                            module_id = m.id,
-                                            file_id = fid,
-                                            path = %dest.display(),
-                                            file_ext = f_ext,
-                                            "dry-run file planned"
-                                        );
-                                    }
-                                } else {
-                                    ensure_dir(dest.parent().unwrap()).await?;
-                                    match download_if_needed(httpctx, &f, &dest, state, verbose)
-                                        .await
-                                    {
-                                        Ok(()) => {
-                                            info!(course_id, module_id = m.id, file_id = fid, path = %dest.display(), "downloaded file [{}]", f_ext);
-                                        }
-                                        Err(e) => {
-                                            warn!(course_id, module_id = m.id, file_id = fid, error = %e, "download failed");
-                                            let keyf = format!("file:{}", fid);
-                                            let current_state = state.get(&keyf);
-                                            let error_count = current_state
-                                                .and_then(|s| s.error_count)
-                                                .unwrap_or(0)
-                                                + 1;
-                                            state.set(
-                                                keyf,
-                                                ItemState {
-                                                    etag: current_state
-                                                        .and_then(|s| s.etag.clone()),
-                                                    updated_at: current_state
-                                                        .and_then(|s| s.updated_at.clone()),
-                                                    size: current_state.and_then(|s| s.size),
-                                                    content_hash: current_state
-                                                        .and_then(|s| s.content_hash.clone()),
-                                                    last_error: Some(e.to_string()),
-                                                    error_count: Some(error_count),
-                                                },
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                warn!(course_id, module_id = m.id, file_id = fid, error = %e, "unable to fetch file (page link)");
-                                // Record error in state
-                                let keyf = format!("file:{}", fid);
-                                let current_state = state.get(&keyf);
-                                let error_count =
-                                    current_state.and_then(|s| s.error_count).unwrap_or(0) + 1;
-                                state.set(
-                                    keyf,
-                                    ItemState {
-                                        etag: current_state.and_then(|s| s.etag.clone()),
-                                        updated_at: current_state
-                                            .and_then(|s| s.updated_at.clone()),
-                                        size: current_state.and_then(|s| s.size),
-                                        content_hash: current_state
-                                            .and_then(|s| s.content_hash.clone()),
-                                        last_error: Some(e.to_string()),
-                                        error_count: Some(error_count),
-                                    },
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            Some("File") => {
-                if let Some(fid) = item.content_id {
-                    if !processed_ids.insert(fid) {
-                        continue;
-                    }
-                    match canvas.get_file(fid).await {
-                        Ok(f) => {
-                            let fname = f
-                                .display_name
-                                .clone()
-                                .or(f.filename.clone())
-                                .unwrap_or_else(|| format!("file_{}", fid));
-                            let dest = module_dir
-                                .join("Attachments")
-                                .join(sanitize_filename_preserve_ext(&fname));
-                            let f_ext = dest
-                                .extension()
-                                .and_then(|s| s.to_str())
-                                .unwrap_or_default();
-                            let keyf = format!("file:{}", f.id);
-                            if dry_run {
-                                if state.get(&keyf).is_some() {
-                                    info!(
-                                        course_id,
-                                        module_id = m.id,
-                                        file_id = fid,
-                                        path = %dest.display(),
-                                        "dry-run skip file; already synced"
-                                    );
-                                } else {
-                                    files_planned += 1;
-                                    info!(
-                                        course_id,
-                                        module_id = m.id,
-                                        file_id = fid,
-                                        path = %dest.display(),
-                                        file_ext = f_ext,
-                                        "dry-run file planned"
-                                    );
-                                }
-                            } else {
-                                ensure_dir(dest.parent().unwrap()).await?;
-                                match download_if_needed(httpctx, &f, &dest, state, verbose).await {
-                                    Ok(()) => {
-                                        info!(course_id, module_id = m.id, file_id = fid, path = %dest.display(), "downloaded file [{}]", f_ext);
-                                    }
-                                    Err(e) => {
-                                        warn!(course_id, module_id = m.id, file_id = fid, error = %e, "download failed");
-                                        let keyf = format!("file:{}", fid);
-                                        let current_state = state.get(&keyf);
-                                        let error_count =
-                                            current_state.and_then(|s| s.error_count).unwrap_or(0)
-                                                + 1;
-                                        state.set(
-                                            keyf,
-                                            ItemState {
-                                                etag: current_state.and_then(|s| s.etag.clone()),
-                                                updated_at: current_state
-                                                    .and_then(|s| s.updated_at.clone()),
-                                                size: current_state.and_then(|s| s.size),
-                                                content_hash: current_state
-                                                    .and_then(|s| s.content_hash.clone()),
-                                                last_error: Some(e.to_string()),
-                                                error_count: Some(error_count),
-                                            },
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            warn!(course_id, module_id = m.id, file_id = fid, error = %e, "unable to fetch file metadata");
-                            // Record error in state
-                            let keyf = format!("file:{}", fid);
-                            let current_state = state.get(&keyf);
-                            let error_count =
-                                current_state.and_then(|s| s.error_count).unwrap_or(0) + 1;
-                            state.set(
-                                keyf,
-                                ItemState {
-                                    etag: current_state.and_then(|s| s.etag.clone()),
-                                    updated_at: current_state.and_then(|s| s.updated_at.clone()),
-                                    size: current_state.and_then(|s| s.size),
-                                    content_hash: current_state
-                                        .and_then(|s| s.content_hash.clone()),
-                                    last_error: Some(e.to_string()),
-                                    error_count: Some(error_count),
-                                },
-                            );
-                        }
-                    }
-                }
-            }
-            Some("Assignment") => {
-                if let Some(aid) = item.content_id {
-                    if let Some(assign) = assignments.get(&aid) {
-                        let atitle = assign.name.clone().unwrap_or_else(|| {
-                            item.title
-                                .clone()
-                                .unwrap_or_else(|| format!("assignment_{}", aid))
-                        });
-                        let html = assign.description.clone().unwrap_or_default();
-                        let md = parse_html(&html);
-                        let key = format!("assignment:{}", aid);
-                        let hash = sha1_hex(md.as_bytes());
-                        let fname =
-                            format!("{:02}-ASSIGN-{}.md", idx + 1, sanitize_component(&atitle));
-                        let dest = module_dir.join(fname);
+                        let (md, hash) = with_front_matter(&meta, &parse_html(&html));
+                        let fname = format!("{:02}-{}.md", idx + 1, sanitize_component(&title));
+                        let dest = module_dir.join(&fname);
+                        local = Some(fname);
                         if state.get(&key).and_then(|s| s.content_hash.as_deref())
                             == Some(hash.as_str())
                         {
@@ -596,217 +1195,1608 @@ async fn sync_module(
                                     course_id,
                                     module_id = m.id,
                                     path = %dest.display(),
-                                    "assignment unchanged; skipping"
+                                    "page unchanged; skipping"
                                 );
                             }
                         } else if dry_run {
                             pages_planned += 1;
+                            run.plan(course_id, &key, Some(&dest), content_reason(state, &key));
                             info!(
                                 course_id,
                                 module_id = m.id,
                                 path = %dest.display(),
                                 bytes = md.len(),
-                                "dry-run assignment planned"
+                                "dry-run page planned"
                             );
                         } else {
                             atomic_write(&dest, md.as_bytes()).await?;
+                            apply_remote_mtime(&dest, page.updated_at.as_deref());
                             state.set(
                                 key,
                                 ItemState {
                                     etag: None,
-                                    updated_at: assign.updated_at.clone(),
+                                    updated_at: page.updated_at.clone(),
                                     size: Some(md.len() as u64),
                                     content_hash: Some(hash),
                                     last_error: None,
                                     error_count: None,
+                                    last_modified: None,
+                                    uuid: None,
+                                    locked: None,
                                 },
                             );
-                            info!(course_id, module_id = m.id, path = %dest.display(), "wrote assignment markdown");
+                            info!(course_id, module_id = m.id, path = %dest.display(), "wrote page markdown");
+                        }
+                        if cfg.sync.page_revisions && !dry_run {
+                            sync_page_revisions(
+                                &fctx,
+                                course_dir,
+                                &slug,
+                                item,
+                                page.updated_at.as_deref(),
+                                state,
+                            )
+                            .await?;
+                        }
+                    }
+                    files_planned += sync_linked(
+                        &fctx,
+                        &html,
+                        &mut media,
+                        &mut processed_ids,
+                        "page link",
+                        state,
+                        run,
+                    )
+                    .await?;
+                }
+            }
+            Some("File") if content.includes(ContentKind::Files) => {
+                if let Some(fid) = item.content_id {
+                    if processed_ids.insert(fid) {
+                        let key = format!("file:{}", fid);
+                        if skip(state, &key, window.is_some_and(|w| w.file_unchanged(fid))) {
+                            unchanged += 1;
+                            continue;
+                        }
+                        let outcome = sync_file(&fctx, fid, "module item", state, run).await?;
+                        if outcome.planned {
+                            files_planned += 1;
+                        }
+                        local = outcome.dest.and_then(|d| relative_to(&d, &module_dir));
+                    }
+                }
+            }
+            Some("Assignment") => {
+                if let Some(aid) = item.content_id {
+                    if let Some(assign) = listings.assignments.get(&aid) {
+                        let atitle = assign.name.clone().unwrap_or_else(|| {
+                            item.title
+                                .clone()
+                                .unwrap_or_else(|| format!("assignment_{}", aid))
+                        });
+                        let html = assign.description.clone().unwrap_or_default();
+                        if content.includes(ContentKind::Assignments) {
+                            let mut md = parse_html(&html);
+                            if let Some(rubric) = assign.rubric.as_deref() {
+                                md.push_str(&render_rubric(rubric));
+                            }
+                            let key = format!("assignment:{}", aid);
+                            let meta = fctx.front_matter(
+                                "assignment",
+                                Some(aid),
+                                assign.updated_at.as_deref(),
+                                assign.html_url.as_deref().or(item.html_url.as_deref()),
+                            );
+                            let (md, hash) = with_front_matter(&meta, &md);
+                            let fname =
+                                format!("{:02}-ASSIGN-{}.md", idx + 1, sanitize_component(&atitle));
+                            let dest = module_dir.join(&fname);
+                            local = Some(fname);
+                            if state.get(&key).and_then(|s| s.content_hash.as_deref())
+                                == Some(hash.as_str())
+                            {
+                                if !dry_run && verbose {
+                                    info!(
+                                        course_id,
+                                        module_id = m.id,
+                                        path = %dest.display(),
+                                        "assignment unchanged; skipping"
+                                    );
+                                }
+                            } else if dry_run {
+                                pages_planned += 1;
+                                run.plan(course_id, &key, Some(&dest), content_reason(state, &key));
+                                info!(
+                                    course_id,
+                                    module_id = m.id,
+                                    path = %dest.display(),
+                                    bytes = md.len(),
+                                    "dry-run assignment planned"
+                                );
+                            } else {
+                                atomic_write(&dest, md.as_bytes()).await?;
+                                apply_remote_mtime(&dest, assign.updated_at.as_deref());
+                                state.set(
+                                    key,
+                                    ItemState {
+                                        etag: None,
+                                        updated_at: assign.updated_at.clone(),
+                                        size: Some(md.len() as u64),
+                                        content_hash: Some(hash),
+                                        last_error: None,
+                                        error_count: None,
+                                        last_modified: None,
+                                        uuid: None,
+                                        locked: None,
+                                    },
+                                );
+                                info!(course_id, module_id = m.id, path = %dest.display(), "wrote assignment markdown");
+                            }
                         }
 
-                        let file_ids = discover_file_ids(&html);
-                        for fid in file_ids {
-                            if !processed_ids.insert(fid) {
-                                continue;
-                            }
-                            match canvas.get_file(fid).await {
-                                Ok(f) => {
-                                    let fname = f
-                                        .display_name
-                                        .clone()
-                                        .or(f.filename.clone())
-                                        .unwrap_or_else(|| format!("file_{}", fid));
-                                    let dest = module_dir
-                                        .join("Attachments")
-                                        .join(sanitize_filename_preserve_ext(&fname));
-                                    let f_ext = dest
-                                        .extension()
-                                        .and_then(|s| s.to_str())
-                                        .unwrap_or_default();
-                                    let keyf = format!("file:{}", f.id);
-                                    if dry_run {
-                                        if state.get(&keyf).is_some() {
-                                            info!(
-                                                course_id,
-                                                module_id = m.id,
-                                                file_id = fid,
-                                                path = %dest.display(),
-                                                "dry-run skip file; already synced"
-                                            );
-                                        } else {
-                                            files_planned += 1;
-                                            info!(
-                                                course_id,
-                                                module_id = m.id,
-                                                file_id = fid,
-                                                path = %dest.display(),
-                                                file_ext = f_ext,
-                                                "dry-run file planned"
-                                            );
-                                        }
-                                    } else {
-                                        ensure_dir(dest.parent().unwrap()).await?;
-                                        match download_if_needed(httpctx, &f, &dest, state, verbose)
-                                            .await
-                                        {
-                                            Ok(()) => {
-                                                info!(course_id, module_id = m.id, file_id = fid, path = %dest.display(), "downloaded file [{}]", f_ext);
-                                            }
-                                            Err(e) => {
-                                                warn!(course_id, module_id = m.id, file_id = fid, error = %e, "download failed");
-                                                let keyf = format!("file:{}", fid);
-                                                let current_state = state.get(&keyf);
-                                                let error_count = current_state
-                                                    .and_then(|s| s.error_count)
-                                                    .unwrap_or(0)
-                                                    + 1;
-                                                state.set(
-                                                    keyf,
-                                                    ItemState {
-                                                        etag: current_state
-                                                            .and_then(|s| s.etag.clone()),
-                                                        updated_at: current_state
-                                                            .and_then(|s| s.updated_at.clone()),
-                                                        size: current_state.and_then(|s| s.size),
-                                                        content_hash: current_state
-                                                            .and_then(|s| s.content_hash.clone()),
-                                                        last_error: Some(e.to_string()),
-                                                        error_count: Some(error_count),
-                                                    },
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!(course_id, module_id = m.id, file_id = fid, error = %e, "unable to fetch file (assignment)");
-                                    // Record error in state
-                                    let keyf = format!("file:{}", fid);
-                                    let current_state = state.get(&keyf);
-                                    let error_count =
-                                        current_state.and_then(|s| s.error_count).unwrap_or(0) + 1;
-                                    state.set(
-                                        keyf,
-                                        ItemState {
-                                            etag: current_state.and_then(|s| s.etag.clone()),
-                                            updated_at: current_state
-                                                .and_then(|s| s.updated_at.clone()),
-                                            size: current_state.and_then(|s| s.size),
-                                            content_hash: current_state
-                                                .and_then(|s| s.content_hash.clone()),
-                                            last_error: Some(e.to_string()),
-                                            error_count: Some(error_count),
-                                        },
-                                    );
-                                }
-                            }
-                        }
+                        files_planned += sync_linked(
+                            &fctx,
+                            &html,
+                            &mut media,
+                            &mut processed_ids,
+                            "assignment",
+                            state,
+                            run,
+                        )
+                        .await?;
+                    }
+                }
+            }
+            Some("Quiz") if fctx.reads_quizzes() => {
+                if let Some(qid) = item.content_id {
+                    let outcome = sync_quiz(
+                        &fctx,
+                        listings,
+                        idx,
+                        qid,
+                        &mut media,
+                        &mut processed_ids,
+                        state,
+                        run,
+                    )
+                    .await?;
+                    pages_planned += outcome.pages;
+                    files_planned += outcome.files;
+                    local = outcome.local;
+                }
+            }
+            Some("ExternalUrl") if content.includes(ContentKind::External) => {
+                if let Some(url) = item.external_url.as_deref() {
+                    let title = item
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| format!("item_{}", item.id));
+                    if snapshot_external_url(&fctx, snapshots, idx, &title, url, state, run).await?
+                    {
+                        files_planned += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+        index.push(IndexEntry::for_item(item, local));
+    }
+
+    if unchanged > 0 {
+        debug!(
+            course_id,
+            module_id = m.id,
+            unchanged,
+            "skipped items unchanged since the window start"
+        );
+    }
+    // A partial sync would leave skipped items pointing at their remote URLs
+    if !dry_run && content.is_all() && unchanged == 0 {
+        let index_md = render_module_index(&m.name, &index);
+        let index_path = module_dir.join(INDEX_FILE);
+        let unchanged = tokio::fs::read(&index_path)
+            .await
+            .is_ok_and(|existing| existing == index_md.as_bytes());
+        if !unchanged {
+            atomic_write(&index_path, index_md.as_bytes()).await?;
+            debug!(course_id, module_id = m.id, path = %index_path.display(), "wrote module index");
+        }
+    }
+    Ok((pages_planned, files_planned))
+}
+
+/// One module item as listed in the module index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexEntry {
+    title: String,
+    kind: String,
+    indent: u32,
+    /// Local artifact path relative to the module dir, or the remote URL.
+    target: Option<String>,
+}
+
+impl IndexEntry {
+    /// Entry for `item`, linking `local` when it was synced and the remote URL otherwise.
+    fn for_item(item: &crate::canvas::ModuleItem, local: Option<String>) -> Self {
+        IndexEntry {
+            title: item
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("item_{}", item.id)),
+            kind: item.kind.clone().unwrap_or_else(|| "Unknown".to_string()),
+            indent: item.indent.unwrap_or(0),
+            target: local.or_else(|| item.external_url.clone().or(item.html_url.clone())),
+        }
+    }
+}
+
+/// Render the module's items in Canvas order, nesting by their indent level so
+/// SubHeader groupings survive the flattening into numbered files.
+fn render_module_index(module_name: &str, entries: &[IndexEntry]) -> String {
+    let mut out = format!("# {}\n\n", module_name);
+    for e in entries {
+        let pad = "  ".repeat(e.indent as usize);
+        if e.kind == "SubHeader" {
+            out.push_str(&format!("{}- **{}**\n", pad, e.title));
+            continue;
+        }
+        match &e.target {
+            Some(target) => out.push_str(&format!(
+                "{}- [{}] [{}](<{}>)\n",
+                pad, e.kind, e.title, target
+            )),
+            None => out.push_str(&format!("{}- [{}] {}\n", pad, e.kind, e.title)),
+        }
+    }
+    out
+}
+
+/// Total size of module files that are new or previously failed, used to
+/// check free space before a course is synced.
+async fn estimate_new_bytes<P: CourseProvider>(
+    provider: &P,
+    modules: &[Module],
+    state: &State,
+    filter: &SyncSettings,
+) -> u64 {
+    let mut seen = HashSet::new();
+    let mut total = 0u64;
+    for item in modules.iter().flat_map(|m| &m.items) {
+        let (Some("File"), Some(fid)) = (item.kind.as_deref(), item.content_id) else {
+            continue;
+        };
+        let synced = state
+            .get(&format!("file:{}", fid))
+            .is_some_and(|s| s.last_error.is_none());
+        if synced || !seen.insert(fid) {
+            continue;
+        }
+        if let Ok(f) = provider.get_file(fid).await {
+            if filter
+                .skip_reason(&file_path(Path::new(""), "", &f), f.size)
+                .is_none()
+            {
+                total += f.size.unwrap_or(0);
+            }
+        }
+    }
+    total
+}
+
+/// Local folder for a course: `<download_root>[/<first tag>]/<name>_<code>`.
+pub(crate) fn course_dir_for(cfg: &Config, c: &Course) -> PathBuf {
+    let code = c.course_code.clone().unwrap_or_default();
+    let mut course_dir = cfg.canvas_root();
+    if cfg.naming.group_by_tag {
+        if let Some(tag) = cfg.tags_for(c.id).first() {
+            course_dir.push(sanitize_component(tag));
+        }
+    }
+    course_dir.push(if code.is_empty() {
+        sanitize_component(&c.name)
+    } else {
+        format!(
+            "{}_{}",
+            sanitize_component(&c.name),
+            sanitize_component(code)
+        )
+    });
+    course_dir
+}
+
+/// Folder to sync course `c` into. The folder recorded in `courses.json` wins over
+/// the one named by [`course_dir_for`]; when they differ (the course was renamed or
+/// retagged) the old folder is moved to the new name, or with
+/// `naming.course_renames = "keep"` kept and aliased by a symlink under the new name.
+/// Folders synced before `courses.json` existed are found by their state's course id.
+pub(crate) async fn resolve_course_dir(
+    cfg: &Config,
+    c: &Course,
+    dry_run: bool,
+) -> Result<PathBuf, Error> {
+    let root = cfg.canvas_root();
+    let wanted = course_dir_for(cfg, c);
+    let mut dirs = CourseDirs::load(&root).await;
+    let previous = match dirs.get(&root, c.id) {
+        Some(dir) => Some(dir),
+        None => find_course_dir(&root, &wanted, c.id).await,
+    }
+    .filter(|dir| *dir != wanted && dir.is_dir() && !dir.is_symlink());
+    let dir = match previous {
+        None => wanted,
+        Some(previous) if dry_run => {
+            report!(
+                "{}",
+                tr!(
+                    "DRY-RUN course {} was renamed: {} -> {}",
+                    c.id,
+                    previous.display(),
+                    wanted.display()
+                )
+            );
+            previous
+        }
+        Some(previous) if wanted.is_symlink() && cfg.naming.course_renames == "keep" => previous,
+        Some(previous) if wanted.exists() && !wanted.is_symlink() => {
+            warn!(course_id = c.id, previous = %previous.display(), path = %wanted.display(), "renamed course folder already exists");
+            report_warning!(
+                "{}",
+                tr!(
+                    "Course {} was renamed but {} already exists; still syncing into {}",
+                    c.id,
+                    wanted.display(),
+                    previous.display()
+                )
+            );
+            previous
+        }
+        Some(previous) if cfg.naming.course_renames == "keep" => {
+            if let Some(parent) = wanted.parent() {
+                ensure_dir(parent).await?;
+            }
+            match link_dir(&previous, &wanted) {
+                Ok(()) => report!(
+                    "{}",
+                    tr!(
+                        "Course {} was renamed: {} now links to {}",
+                        c.id,
+                        wanted.display(),
+                        previous.display()
+                    )
+                ),
+                Err(e) => {
+                    warn!(course_id = c.id, path = %wanted.display(), error = %e, "unable to link renamed course folder")
+                }
+            }
+            previous
+        }
+        Some(previous) => {
+            if wanted.is_symlink() {
+                tokio::fs::remove_file(&wanted).await?;
+            }
+            if let Some(parent) = wanted.parent() {
+                ensure_dir(parent).await?;
+            }
+            tokio::fs::rename(&previous, &wanted).await?;
+            info!(course_id = c.id, from = %previous.display(), to = %wanted.display(), "moved renamed course folder");
+            report!(
+                "{}",
+                tr!(
+                    "Course {} was renamed: moved {} to {}",
+                    c.id,
+                    previous.display(),
+                    wanted.display()
+                )
+            );
+            wanted
+        }
+    };
+    if !dry_run && dirs.set(&root, c.id, &dir) {
+        ensure_dir(&root).await?;
+        dirs.save(&root).await?;
+    }
+    Ok(dir)
+}
+
+/// Folder under `root` whose state belongs to `course_id`, for archives synced
+/// before `courses.json` was kept. `wanted` is checked first.
+async fn find_course_dir(root: &Path, wanted: &Path, course_id: u64) -> Option<PathBuf> {
+    if State::load(&wanted.join("state.json")).await.course_id == Some(course_id) {
+        return None;
+    }
+    for dir in crate::fsutil::course_dirs(root, &[]).ok()? {
+        if State::load(&dir.join("state.json")).await.course_id == Some(course_id) {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+/// Symlink `alias` to the folder `target`.
+#[cfg(unix)]
+fn link_dir(target: &Path, alias: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, alias)
+}
+
+#[cfg(windows)]
+fn link_dir(target: &Path, alias: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, alias)
+}
+
+/// Folder of a module: `Modules/<id>_<name>`, or inside its week's folder when
+/// the name matches `week_pattern`.
+pub(crate) fn module_dir_for(course_dir: &Path, m: &Module, week_pattern: &str) -> PathBuf {
+    let name = format!("{}_{}", m.id, sanitize_component(&m.name));
+    match week_number(week_pattern, &m.name) {
+        Some(week) => week_dir(course_dir, week).join(name),
+        None => course_dir.join("Modules").join(name),
+    }
+}
+
+/// Move a module's folder to `module_dir` when it was synced elsewhere, i.e.
+/// before `week_pattern` was set or changed, so its files are not fetched again.
+async fn relocate_module_dir(course_dir: &Path, module_dir: &Path) -> Result<(), Error> {
+    let Some(name) = module_dir.file_name() else {
+        return Ok(());
+    };
+    if module_dir.exists() {
+        return Ok(());
+    }
+    let mut candidates = vec![course_dir.join("Modules").join(name)];
+    if let Ok(weeks) = std::fs::read_dir(course_dir.join(WEEKS_DIR)) {
+        candidates.extend(weeks.filter_map(|w| Some(w.ok()?.path().join(name))));
+    }
+    if let Some(previous) = candidates.into_iter().find(|c| c.is_dir()) {
+        if let Some(parent) = module_dir.parent() {
+            ensure_dir(parent).await?;
+        }
+        tokio::fs::rename(&previous, module_dir).await?;
+        info!(from = %previous.display(), to = %module_dir.display(), "moved module folder");
+        report!(
+            "{}",
+            tr!(
+                "Moved module folder {} to {}",
+                previous.display(),
+                module_dir.display()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Module subfolder for linked files.
+const ATTACHMENTS_DIR: &str = "Attachments";
+/// Module subfolder for videos embedded in pages and assignments.
+const MEDIA_DIR: &str = "Media";
+/// Module subfolder for snapshots of web pages linked as External URL items.
+const SNAPSHOT_DIR: &str = "External";
+/// `Attachments` subfolder for files shared through Google Drive, OneDrive or SharePoint.
+const EXTERNAL_DIR: &str = "External";
+
+/// Destination of a Canvas file inside a module's `Attachments` folder.
+pub(crate) fn attachment_path(module_dir: &Path, f: &FileObj) -> PathBuf {
+    file_path(module_dir, ATTACHMENTS_DIR, f)
+}
+
+fn file_path(module_dir: &Path, folder: &str, f: &FileObj) -> PathBuf {
+    let fname = f
+        .display_name
+        .clone()
+        .or(f.filename.clone())
+        .unwrap_or_else(|| format!("file_{}", f.id));
+    module_dir
+        .join(folder)
+        .join(sanitize_filename_preserve_ext(&fname))
+}
+
+/// `dest` unless another item of the course already writes to it, in which case
+/// `tag` is added to the file name so neither overwrites the other. The item that
+/// claimed a name first keeps it on later runs, until [`release_stale_claims`]
+/// frees it.
+pub(crate) fn claim_dest(
+    state: &mut State,
+    module_id: u64,
+    module_dir: &Path,
+    key: &str,
+    dest: PathBuf,
+    tag: &str,
+) -> PathBuf {
+    let claim = |path: &Path| {
+        let rel =
+            relative_to(path, module_dir).unwrap_or_else(|| path.to_string_lossy().into_owned());
+        format!("{}/{}", module_id, rel)
+    };
+    if state.claim_path(claim(&dest), key) {
+        return dest;
+    }
+    let tagged = tagged_path(&dest, tag);
+    state.claim_path(claim(&tagged), key);
+    debug!(module_id, key, path = %tagged.display(), "name taken by another item; renamed");
+    tagged
+}
+
+/// `path` with `_<tag>` added before the extension.
+fn tagged_path(path: &Path, tag: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, tag, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, tag),
+    };
+    path.with_file_name(name)
+}
+
+/// Free the names claimed in module `m` by items that left the module listing or
+/// whose file was renamed, so they do not push unrelated files onto a tagged name.
+/// Items found in page content are not listed; they claim their names again when
+/// their page is synced.
+fn release_stale_claims(state: &mut State, m: &Module) {
+    // Current names of the module's files, as file_path() would build them
+    let listed: std::collections::HashMap<String, PathBuf> = m
+        .items
+        .iter()
+        .filter(|item| item.kind.as_deref() == Some("File"))
+        .filter_map(|item| {
+            let name = sanitize_filename_preserve_ext(item.title.as_deref()?);
+            let id = item.content_id?;
+            Some((
+                format!("file:{}", id),
+                Path::new(ATTACHMENTS_DIR).join(name),
+            ))
+        })
+        .collect();
+    let released = state.release_paths(m.id, |rel, key| {
+        let Some(name) = listed.get(key) else {
+            return false;
+        };
+        let tag = key.trim_start_matches("file:");
+        [name.clone(), tagged_path(name, tag)]
+            .iter()
+            .any(|p| p.to_string_lossy().replace('\\', "/") == rel)
+    });
+    if released > 0 {
+        debug!(module_id = m.id, released, "released stale file names");
+    }
+}
+
+fn relative_to(path: &Path, base: &Path) -> Option<String> {
+    path.strip_prefix(base)
+        .ok()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+}
+
+/// Shared inputs for syncing a single course file into a module folder.
+struct FileCtx<'a, P> {
+    cfg: &'a Config,
+    provider: &'a P,
+    httpctx: &'a HttpCtx,
+    module_dir: &'a Path,
+    course_id: u64,
+    course_name: &'a str,
+    module_id: u64,
+    module_name: &'a str,
+    /// Module subfolder the file goes in (`Attachments` or `Media`).
+    folder: &'a str,
+    dry_run: bool,
+    verbose: bool,
+    fail_fast: bool,
+    content: &'a ContentFilter,
+}
+
+impl<P> Clone for FileCtx<'_, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for FileCtx<'_, P> {}
+
+impl<'a, P> FileCtx<'a, P> {
+    /// Front matter of a page, assignment or quiz of this module.
+    fn front_matter(
+        &self,
+        kind: &'a str,
+        canvas_id: Option<u64>,
+        updated_at: Option<&'a str>,
+        url: Option<&'a str>,
+    ) -> FrontMatter<'a> {
+        FrontMatter {
+            canvas_id,
+            kind,
+            course_id: self.course_id,
+            course: self.course_name,
+            module_id: self.module_id,
+            module: self.module_name,
+            updated_at,
+            url,
+        }
+    }
+
+    /// Whether pages are fetched, for their Markdown or the links in them.
+    fn reads_pages(&self) -> bool {
+        self.content.includes(ContentKind::Pages) || self.content.follows_links()
+    }
+
+    /// Whether quizzes are read, for their Markdown or the links in them.
+    fn reads_quizzes(&self) -> bool {
+        self.content.includes(ContentKind::Quizzes) || self.content.follows_links()
+    }
+}
+
+/// Result of syncing a single file.
+#[derive(Debug, Default)]
+struct FileOutcome {
+    /// Local destination, when the file metadata could be resolved.
+    dest: Option<PathBuf>,
+    /// Whether the file would be downloaded (dry-run only).
+    planned: bool,
+}
+
+/// Sync what `html` (a page or assignment body) links to and the content filter
+/// includes: course files, embedded media and shared documents. Returns how many
+/// would be downloaded in a dry run.
+async fn sync_linked<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    html: &str,
+    media: &mut MediaCtx,
+    processed_ids: &mut HashSet<u64>,
+    source: &str,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<usize, Error> {
+    let mut planned = 0usize;
+    if ctx.content.includes(ContentKind::Files) {
+        for fid in discover_file_ids(html) {
+            if processed_ids.insert(fid) && sync_file(ctx, fid, source, state, run).await?.planned {
+                planned += 1;
+            }
+        }
+    }
+    if ctx.content.includes(ContentKind::Media) {
+        planned += sync_embedded_media(ctx, html, media, processed_ids, state, run).await?;
+    }
+    if ctx.content.includes(ContentKind::External) {
+        planned += sync_shared_files(ctx, html, media, state, run).await?;
+    }
+    Ok(planned)
+}
+
+/// What syncing one quiz module item produced.
+#[derive(Debug, Default)]
+struct QuizOutcome {
+    /// Markdown files that would be written (dry-run only).
+    pages: usize,
+    /// Linked files that would be downloaded (dry-run only).
+    files: usize,
+    /// Markdown file name relative to the module dir.
+    local: Option<String>,
+}
+
+/// Write the quiz `quiz_id` as `NN-QUIZ-<title>.md`, with its questions when the
+/// account may see them, and sync what its description and questions link to.
+#[allow(clippy::too_many_arguments)]
+async fn sync_quiz<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    listings: &CourseListings,
+    idx: usize,
+    quiz_id: u64,
+    media: &mut MediaCtx,
+    processed_ids: &mut HashSet<u64>,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<QuizOutcome, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    let key = format!("quiz:{}", quiz_id);
+    let quiz = match listings.quizzes.get(&quiz_id) {
+        Some(quiz) => Some(quiz.clone()),
+        None => match ctx.provider.get_quiz(course_id, quiz_id).await {
+            Ok(quiz) => quiz,
+            Err(e) => {
+                warn!(course_id, module_id, quiz_id, error = %e, "unable to fetch quiz");
+                record_failure(state, run, key, e.into(), ctx.fail_fast)?;
+                return Ok(QuizOutcome::default());
+            }
+        },
+    };
+    let Some(quiz) = quiz else {
+        return Ok(QuizOutcome::default());
+    };
+    let questions = match ctx.provider.list_quiz_questions(course_id, quiz_id).await {
+        Ok(questions) => questions,
+        Err(e) => {
+            debug!(course_id, quiz_id, error = %e, "quiz questions not visible");
+            Vec::new()
+        }
+    };
+
+    let mut outcome = QuizOutcome::default();
+    if ctx.content.includes(ContentKind::Quizzes) {
+        let title = quiz
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("quiz_{}", quiz_id));
+        let fname = format!("{:02}-QUIZ-{}.md", idx + 1, sanitize_component(&title));
+        let dest = ctx.module_dir.join(&fname);
+        let meta = ctx.front_matter(
+            "quiz",
+            Some(quiz_id),
+            quiz.updated_at.as_deref(),
+            quiz.html_url.as_deref(),
+        );
+        let (md, hash) = with_front_matter(&meta, &render_quiz(&quiz, &questions));
+        if state.get(&key).and_then(|s| s.content_hash.as_deref()) == Some(hash.as_str()) {
+            debug!(course_id, module_id, quiz_id, "quiz unchanged");
+        } else if ctx.dry_run {
+            outcome.pages += 1;
+            run.plan(course_id, &key, Some(&dest), content_reason(state, &key));
+            info!(course_id, module_id, path = %dest.display(), bytes = md.len(), "dry-run quiz planned");
+        } else {
+            atomic_write(&dest, md.as_bytes()).await?;
+            apply_remote_mtime(&dest, quiz.updated_at.as_deref());
+            state.set(
+                key,
+                ItemState {
+                    etag: None,
+                    updated_at: quiz.updated_at.clone(),
+                    size: Some(md.len() as u64),
+                    content_hash: Some(hash),
+                    last_error: None,
+                    error_count: None,
+                    last_modified: None,
+                    uuid: None,
+                    locked: None,
+                },
+            );
+            info!(course_id, module_id, path = %dest.display(), questions = questions.len(), "wrote quiz markdown");
+        }
+        outcome.local = Some(fname);
+    }
+    let html = quizzes::linked_html(&quiz, &questions);
+    outcome.files = sync_linked(ctx, &html, media, processed_ids, "quiz", state, run).await?;
+    Ok(outcome)
+}
+
+/// Why a dry run would rewrite the Markdown stored under `key`.
+fn content_reason(state: &State, key: &str) -> PlanReason {
+    match state.get(key).and_then(|s| s.content_hash.as_ref()) {
+        Some(_) => PlanReason::ChangedHash,
+        None => PlanReason::New,
+    }
+}
+
+/// `Retry` for an item whose last attempt failed, `New` otherwise.
+fn retry_reason(state: &State, key: &str) -> PlanReason {
+    match state.get(key) {
+        Some(s) if s.last_error.is_some() => PlanReason::Retry,
+        _ => PlanReason::New,
+    }
+}
+
+/// Why a real run would download `f` to `dest`, or `None` if it would find the
+/// local copy current. Without the file's ETag at hand, a changed size or update
+/// time stands in for a changed ETag.
+fn file_plan_reason(state: &State, key: &str, f: &FileObj, dest: &Path) -> Option<PlanReason> {
+    if dest.with_extension("part").exists() {
+        return Some(PlanReason::Resume);
+    }
+    let Some(prev) = state.get(key) else {
+        return Some(PlanReason::New);
+    };
+    if prev.last_error.is_some() {
+        return Some(PlanReason::Retry);
+    }
+    if !dest.exists() {
+        return Some(PlanReason::New);
+    }
+    let changed = |before: Option<&str>, now: Option<&str>| {
+        before.is_some() && now.is_some() && before != now
+    };
+    if changed(prev.updated_at.as_deref(), f.updated_at.as_deref())
+        || (prev.size.is_some() && f.size.is_some() && prev.size != f.size)
+    {
+        return Some(PlanReason::ChangedEtag);
+    }
+    None
+}
+
+/// Course folder keeping the saved versions of each page, with `sync.page_revisions`.
+const PAGES_DIR: &str = "Pages";
+
+/// Save the versions of a page newer than those already in `Pages/<slug>/` as
+/// `rev-<n>.md`. The history is only looked up again once the page changes.
+async fn sync_page_revisions<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    course_dir: &Path,
+    page_url: &str,
+    item: &crate::canvas::ModuleItem,
+    updated_at: Option<&str>,
+    state: &mut State,
+) -> Result<(), Error> {
+    let key = format!("page-history:{}", page_url);
+    if updated_at.is_some() && state.get(&key).and_then(|s| s.updated_at.as_deref()) == updated_at {
+        return Ok(());
+    }
+    let course_id = ctx.course_id;
+    let dir = course_dir
+        .join(PAGES_DIR)
+        .join(sanitize_component(page_url));
+    let saved = latest_saved_revision(&dir);
+    let revisions = match ctx.provider.list_page_revisions(course_id, page_url).await {
+        Ok(revisions) => revisions,
+        Err(e) => {
+            debug!(course_id, page_url, error = %e, "page history not visible");
+            Vec::new()
+        }
+    };
+    let mut new: Vec<u64> = revisions
+        .iter()
+        .map(|r| r.revision_id)
+        .filter(|&id| id > saved)
+        .collect();
+    new.sort_unstable();
+    for revision_id in new {
+        let revision = match ctx
+            .provider
+            .get_page_revision(course_id, page_url, revision_id)
+            .await
+        {
+            Ok(Some(revision)) => revision,
+            Ok(None) => break,
+            Err(e) => {
+                // Later revisions would leave a gap; try again on the next sync
+                warn!(course_id, page_url, revision_id, error = %e, "unable to fetch page revision");
+                return Ok(());
+            }
+        };
+        let meta = ctx.front_matter(
+            "page_revision",
+            item.content_id,
+            revision.updated_at.as_deref(),
+            item.html_url.as_deref(),
+        );
+        let mut md = meta.render(None);
+        if let Some(title) = &revision.title {
+            md.push_str(&format!("# {}\n\n", title));
+        }
+        md.push_str(&parse_html(revision.body.as_deref().unwrap_or_default()));
+        let dest = dir.join(format!("rev-{}.md", revision_id));
+        atomic_write(&dest, md.as_bytes()).await?;
+        apply_remote_mtime(&dest, revision.updated_at.as_deref());
+        info!(course_id, page_url, revision_id, path = %dest.display(), "wrote page revision");
+    }
+    state.set(
+        key,
+        ItemState {
+            etag: None,
+            updated_at: updated_at.map(String::from),
+            size: None,
+            content_hash: None,
+            last_error: None,
+            error_count: None,
+            last_modified: None,
+            uuid: None,
+            locked: None,
+        },
+    );
+    Ok(())
+}
+
+/// Highest `n` of the `rev-<n>.md` files in `dir`, or 0.
+fn latest_saved_revision(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.to_str()?
+                .strip_prefix("rev-")?
+                .strip_suffix(".md")?
+                .parse()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Fetch a page, or record the failure under `key` and return `None` so the
+/// module carries on with its other items.
+async fn fetch_page<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    key: &str,
+    page_url: &str,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<Option<crate::canvas::PageObj>, Error> {
+    match ctx.provider.get_page(ctx.course_id, page_url).await {
+        Ok(page) => Ok(Some(page)),
+        Err(e) => {
+            warn!(course_id = ctx.course_id, module_id = ctx.module_id, page_url, error = %e, "unable to fetch page");
+            record_failure(state, run, key.to_string(), e.into(), ctx.fail_fast)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Fetch metadata for `fid` and download it if needed, recording failures in `state`.
+async fn sync_file<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    fid: u64,
+    source: &str,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<FileOutcome, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    let f = match ctx.provider.get_file(fid).await {
+        Ok(f) => f,
+        Err(e) => {
+            warn!(course_id, module_id, file_id = fid, source, error = %e, "unable to fetch file metadata");
+            record_failure(state, run, format!("file:{}", fid), e.into(), ctx.fail_fast)?;
+            return Ok(FileOutcome::default());
+        }
+    };
+    let keyf = format!("file:{}", f.id);
+    let dest = claim_dest(
+        state,
+        ctx.module_id,
+        ctx.module_dir,
+        &keyf,
+        file_path(ctx.module_dir, ctx.folder, &f),
+        &f.id.to_string(),
+    );
+    if let Some(reason) = f.lock_reason(chrono::Utc::now()) {
+        // Asking for it would only get a 403; try again on the next sync
+        info!(course_id, module_id, file_id = fid, path = %dest.display(), reason, "file locked on Canvas; deferred");
+        report_verbose!(
+            "{}",
+            tr!(
+                "🔒 {} is not available yet ({}); will retry on the next sync",
+                dest.display(),
+                reason
+            )
+        );
+        if !ctx.dry_run {
+            defer_locked(state, keyf, reason);
+        }
+        return Ok(FileOutcome {
+            dest: dest.exists().then_some(dest),
+            planned: false,
+        });
+    }
+    let f_ext = dest
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if ctx.dry_run {
+        if let Some(reason) = ctx.cfg.sync.skip_reason(&dest, f.size) {
+            info!(course_id, module_id, file_id = fid, path = %dest.display(), reason, "dry-run skip file; filtered out");
+            return Ok(FileOutcome {
+                dest: Some(dest),
+                planned: false,
+            });
+        }
+        let Some(reason) = file_plan_reason(state, &keyf, &f, &dest) else {
+            info!(
+                course_id,
+                module_id,
+                file_id = fid,
+                path = %dest.display(),
+                "dry-run skip file; already synced"
+            );
+            return Ok(FileOutcome {
+                dest: Some(dest),
+                planned: false,
+            });
+        };
+        run.plan(course_id, &keyf, Some(&dest), reason);
+        info!(
+            course_id,
+            module_id,
+            file_id = fid,
+            path = %dest.display(),
+            file_ext = f_ext,
+            %reason,
+            "dry-run file planned"
+        );
+        return Ok(FileOutcome {
+            dest: Some(dest),
+            planned: true,
+        });
+    }
+
+    ensure_dir(dest.parent().unwrap()).await?;
+    match download_if_needed(
+        ctx.httpctx,
+        &ctx.cfg.sync,
+        keyf.clone(),
+        &f,
+        &dest,
+        state,
+        ctx.verbose,
+    )
+    .await
+    {
+        Ok(Some(bytes)) => {
+            run.files_downloaded += 1;
+            run.bytes_downloaded += bytes;
+            info!(course_id, module_id, file_id = fid, path = %dest.display(), "downloaded file [{}]", f_ext);
+            if ctx.cfg.storage.dedup {
+                match store::dedup(&ctx.cfg.canvas_root(), &dest).await {
+                    Ok(saved) => run.bytes_deduplicated += saved,
+                    Err(e) => {
+                        warn!(course_id, module_id, file_id = fid, path = %dest.display(), error = %e, "unable to deduplicate file")
                     }
                 }
             }
-            _ => {}
+        }
+        Ok(None) => {
+            // Unlocked since the last sync, and the local copy is current
+            if let Some(prev) = state.items.get_mut(&keyf) {
+                prev.locked = None;
+            }
+        }
+        Err(e) => {
+            warn!(course_id, module_id, file_id = fid, error = %e, "download failed");
+            record_failure(state, run, keyf, e, ctx.fail_fast)?;
+        }
+    }
+    Ok(FileOutcome {
+        dest: Some(dest),
+        planned: false,
+    })
+}
+
+/// Media embeds and shared files handled so far in a module, and the course's
+/// Canvas media objects once they have been listed.
+#[derive(Default)]
+struct MediaCtx {
+    seen: HashSet<MediaEmbed>,
+    shared: HashSet<SharedFile>,
+    objects: Option<Vec<MediaObject>>,
+}
+
+/// Download videos embedded in `html` that `discover_file_ids` cannot see (Canvas
+/// media attachments and objects, Kaltura, Canvas Studio) into the module's `Media`
+/// folder. Returns how many would be downloaded in a dry run.
+async fn sync_embedded_media<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    html: &str,
+    media: &mut MediaCtx,
+    processed_ids: &mut HashSet<u64>,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<usize, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    let media_ctx = FileCtx {
+        folder: MEDIA_DIR,
+        ..*ctx
+    };
+    let mut planned = 0usize;
+    for embed in discover_media(html) {
+        if let MediaEmbed::Attachment(fid) = embed {
+            if processed_ids.insert(fid)
+                && sync_file(&media_ctx, fid, "media embed", state, run)
+                    .await?
+                    .planned
+            {
+                planned += 1;
+            }
+            continue;
+        }
+        if !media.seen.insert(embed.clone()) {
+            continue;
+        }
+        if let Some(url) = embed.external_url() {
+            if sync_external_video(ctx, &embed, &url, state, run).await? {
+                planned += 1;
+            }
+            continue;
+        }
+        let key = embed.state_key();
+        let resolved = match resolve_media(ctx, media, &embed).await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                warn!(course_id, module_id, media = ?embed, error = %e, "unable to resolve embedded media");
+                record_failure(state, run, key, e, ctx.fail_fast)?;
+                continue;
+            }
+        };
+        let dest = claim_dest(
+            state,
+            ctx.module_id,
+            ctx.module_dir,
+            &key,
+            ctx.module_dir
+                .join(MEDIA_DIR)
+                .join(sanitize_filename_preserve_ext(format!(
+                    "{}.{}",
+                    resolved.name, resolved.ext
+                ))),
+            &sha1_hex(key.as_bytes())[..8],
+        );
+        if dest.exists() && state.get(&key).is_some_and(|s| s.last_error.is_none()) {
+            debug!(course_id, module_id, path = %dest.display(), "media already downloaded");
+            continue;
+        }
+        if ctx.dry_run {
+            run.plan(course_id, &key, Some(&dest), retry_reason(state, &key));
+            info!(course_id, module_id, media = ?embed, path = %dest.display(), "dry-run media planned");
+            planned += 1;
+            continue;
+        }
+        ensure_dir(dest.parent().unwrap()).await?;
+        let f = FileObj {
+            id: 0,
+            display_name: Some(resolved.name),
+            filename: None,
+            size: resolved.size,
+            updated_at: None,
+            url: Some(resolved.url),
+            download_url: None,
+            uuid: None,
+            lock: FileLock::default(),
+        };
+        match download_if_needed(
+            ctx.httpctx,
+            &ctx.cfg.sync,
+            key.clone(),
+            &f,
+            &dest,
+            state,
+            ctx.verbose,
+        )
+        .await
+        {
+            Ok(Some(bytes)) => {
+                run.files_downloaded += 1;
+                run.bytes_downloaded += bytes;
+                info!(course_id, module_id, media = ?embed, path = %dest.display(), "downloaded media");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(course_id, module_id, media = ?embed, error = %e, "media download failed");
+                record_failure(state, run, key, e, ctx.fail_fast)?;
+            }
+        }
+    }
+    Ok(planned)
+}
+
+/// Archive a YouTube/Vimeo video with yt-dlp into the module's `Media` folder when
+/// an external video downloader is configured. Returns whether it would be
+/// downloaded in a dry run.
+async fn sync_external_video<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    embed: &MediaEmbed,
+    url: &str,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<bool, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    let Some(settings) = ctx.cfg.external_video_downloader.as_ref() else {
+        debug!(
+            course_id,
+            module_id, url, "no external video downloader configured"
+        );
+        return Ok(false);
+    };
+    let key = embed.state_key();
+    if ctx.dry_run {
+        let done = state.get(&key).is_some_and(|s| s.last_error.is_none());
+        if !done {
+            run.plan(course_id, &key, None, retry_reason(state, &key));
+            info!(course_id, module_id, url, "dry-run external video planned");
+        }
+        return Ok(!done);
+    }
+    let dir = ctx.module_dir.join(MEDIA_DIR);
+    match external_video::archive(settings, key.clone(), url, &dir, state).await {
+        Ok(Some(path)) => {
+            run.files_downloaded += 1;
+            run.bytes_downloaded += state.get(&key).and_then(|s| s.size).unwrap_or(0);
+            info!(course_id, module_id, url, path = %path.display(), "downloaded external video");
+        }
+        Ok(None) => debug!(course_id, module_id, url, "external video already archived"),
+        Err(e) => {
+            warn!(course_id, module_id, url, error = %e, "external video download failed");
+            record_failure(state, run, key, e.into(), ctx.fail_fast)?;
+        }
+    }
+    Ok(false)
+}
+
+/// Download Google Drive, OneDrive and SharePoint files linked from `html` into the
+/// module's `Attachments/External` folder. Only publicly shared links can be fetched;
+/// the rest are recorded as failures. Returns how many would be downloaded in a dry run.
+async fn sync_shared_files<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    html: &str,
+    media: &mut MediaCtx,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<usize, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    let mut planned = 0usize;
+    for file in discover_shared_files(html) {
+        if !media.shared.insert(file.clone()) {
+            continue;
+        }
+        let key = file.state_key();
+        if ctx.dry_run {
+            if state.get(&key).is_none_or(|s| s.last_error.is_some()) {
+                run.plan(course_id, &key, None, retry_reason(state, &key));
+                info!(course_id, module_id, file = ?file, "dry-run shared file planned");
+                planned += 1;
+            }
+            continue;
+        }
+        let url = file.download_url();
+        let name = match shared_files::probe(&ctx.httpctx.client, &url).await {
+            Ok(name) => name.unwrap_or_else(|| file.fallback_name()),
+            Err(e) => {
+                warn!(course_id, module_id, file = ?file, error = %e, "shared file unavailable");
+                record_failure(state, run, key, e.into(), ctx.fail_fast)?;
+                continue;
+            }
+        };
+        let dest = claim_dest(
+            state,
+            ctx.module_id,
+            ctx.module_dir,
+            &key,
+            ctx.module_dir
+                .join(ATTACHMENTS_DIR)
+                .join(EXTERNAL_DIR)
+                .join(sanitize_filename_preserve_ext(&name)),
+            &sha1_hex(key.as_bytes())[..8],
+        );
+        ensure_dir(dest.parent().unwrap()).await?;
+        let f = FileObj {
+            id: 0,
+            display_name: Some(name),
+            filename: None,
+            size: None,
+            updated_at: None,
+            url: Some(url),
+            download_url: None,
+            uuid: None,
+            lock: FileLock::default(),
+        };
+        // Stored validators turn unchanged files into a 304 on later runs
+        match download_if_needed(
+            ctx.httpctx,
+            &ctx.cfg.sync,
+            key.clone(),
+            &f,
+            &dest,
+            state,
+            ctx.verbose,
+        )
+        .await
+        {
+            Ok(Some(bytes)) => {
+                run.files_downloaded += 1;
+                run.bytes_downloaded += bytes;
+                info!(course_id, module_id, file = ?file, path = %dest.display(), "downloaded shared file");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(course_id, module_id, file = ?file, error = %e, "shared file download failed");
+                record_failure(state, run, key, e, ctx.fail_fast)?;
+            }
+        }
+    }
+    Ok(planned)
+}
+
+/// Save the web page behind an External URL item as `External/<NN>-<title>.mhtml`
+/// when snapshots are enabled. A page is captured once, so the copy outlives later
+/// edits to the site. Returns whether it would be captured in a dry run.
+async fn snapshot_external_url<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    snapshots: Option<&PageSnapshotter>,
+    idx: usize,
+    title: &str,
+    url: &str,
+    state: &mut State,
+    run: &mut RunRecord,
+) -> Result<bool, Error> {
+    let (course_id, module_id) = (ctx.course_id, ctx.module_id);
+    if !ctx.cfg.snapshot_external_urls || !is_snapshot_target(url) {
+        return Ok(false);
+    }
+    let key = format!("snapshot:{}", url);
+    let dest = ctx.module_dir.join(SNAPSHOT_DIR).join(format!(
+        "{:02}-{}.mhtml",
+        idx + 1,
+        sanitize_component(title)
+    ));
+    if dest.exists() && state.get(&key).is_some_and(|s| s.last_error.is_none()) {
+        debug!(
+            course_id,
+            module_id, url, "external URL already snapshotted"
+        );
+        return Ok(false);
+    }
+    if ctx.dry_run {
+        run.plan(course_id, &key, Some(&dest), retry_reason(state, &key));
+        info!(course_id, module_id, url, path = %dest.display(), "dry-run snapshot planned");
+        return Ok(true);
+    }
+    let Some(snapshots) = snapshots else {
+        return Ok(false);
+    };
+    match snapshots.snapshot(url).await {
+        Ok(mhtml) => {
+            ensure_dir(dest.parent().unwrap()).await?;
+            atomic_write(&dest, mhtml.as_bytes()).await?;
+            run.files_downloaded += 1;
+            run.bytes_downloaded += mhtml.len() as u64;
+            state.set(
+                key,
+                ItemState {
+                    etag: None,
+                    updated_at: None,
+                    size: Some(mhtml.len() as u64),
+                    content_hash: Some(sha1_hex(mhtml.as_bytes())),
+                    last_error: None,
+                    error_count: None,
+                    last_modified: None,
+                    uuid: None,
+                    locked: None,
+                },
+            );
+            info!(course_id, module_id, url, path = %dest.display(), "saved external URL snapshot");
+        }
+        Err(e) => {
+            warn!(course_id, module_id, url, error = %e, "external URL snapshot failed");
+            record_failure(state, run, key, e.into(), ctx.fail_fast)?;
+        }
+    }
+    Ok(false)
+}
+
+async fn resolve_media<P: CourseProvider>(
+    ctx: &FileCtx<'_, P>,
+    media: &mut MediaCtx,
+    embed: &MediaEmbed,
+) -> Result<ResolvedMedia, Error> {
+    match embed {
+        MediaEmbed::CanvasMedia(id) => {
+            if media.objects.is_none() {
+                let objects = ctx
+                    .provider
+                    .list_media_objects(ctx.course_id)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(course_id = ctx.course_id, error = %e, "unable to list media objects");
+                        Vec::new()
+                    });
+                media.objects = Some(objects);
+            }
+            canvas_media_source(media.objects.as_deref().unwrap_or_default(), id)
+                .ok_or_else(|| tr!("media object {} is not listed for the course", id).into())
+        }
+        MediaEmbed::Kaltura {
+            partner_id,
+            entry_id,
+        } => Ok(kaltura_source(*partner_id, entry_id)),
+        MediaEmbed::Studio { host, id } => Ok(studio_source(&ctx.httpctx.client, host, id).await?),
+        MediaEmbed::Attachment(id) => Err(tr!("attachment {} is a Canvas file", id).into()),
+        MediaEmbed::YouTube(_) | MediaEmbed::Vimeo { .. } => {
+            Err("externally hosted videos are downloaded with yt-dlp".into())
         }
     }
-    Ok((pages_planned, files_planned))
 }
 
-async fn download_if_needed(
+/// Keep the previous item metadata but bump its error counter, and count the
+/// failure in the run by category. With `fail_fast` the error is returned instead
+/// of being counted, so it ends the sync.
+/// Note under `key` that Canvas keeps the file locked for `reason`, keeping what
+/// was recorded about an earlier copy so it is not downloaded again needlessly.
+fn defer_locked(state: &mut State, key: String, reason: String) {
+    let mut item = state.get(&key).cloned().unwrap_or(ItemState {
+        etag: None,
+        updated_at: None,
+        size: None,
+        content_hash: None,
+        last_error: None,
+        error_count: None,
+        last_modified: None,
+        uuid: None,
+        locked: None,
+    });
+    item.locked = Some(reason);
+    state.set(key, item);
+}
+
+fn record_failure(
+    state: &mut State,
+    run: &mut RunRecord,
+    key: String,
+    error: Error,
+    fail_fast: bool,
+) -> Result<(), Error> {
+    // An interrupt is not the item's fault; stop the run instead
+    if error.kind() == ErrorKind::Cancelled {
+        return Err(error);
+    }
+    let current_state = state.get(&key);
+    let error_count = current_state.and_then(|s| s.error_count).unwrap_or(0) + 1;
+    state.set(
+        key.clone(),
+        ItemState {
+            etag: current_state.and_then(|s| s.etag.clone()),
+            updated_at: current_state.and_then(|s| s.updated_at.clone()),
+            size: current_state.and_then(|s| s.size),
+            content_hash: current_state.and_then(|s| s.content_hash.clone()),
+            last_error: Some(error.to_string()),
+            error_count: Some(error_count),
+            last_modified: current_state.and_then(|s| s.last_modified.clone()),
+            uuid: current_state.and_then(|s| s.uuid.clone()),
+            locked: current_state.and_then(|s| s.locked.clone()),
+        },
+    );
+    if fail_fast {
+        return Err(error);
+    }
+    run.fail(error.kind());
+    let course = state.course_id.map(|id| format!("course {} ", id));
+    run.failed_items
+        .push(format!("{}{}: {}", course.unwrap_or_default(), key, error));
+    Ok(())
+}
+
+/// Download `f` to `dest` with a single conditional GET.
+/// Returns the number of bytes written, or `None` when the file was unchanged or
+/// is left out by the `[sync]` file filters.
+#[allow(clippy::too_many_arguments)]
+#[instrument(name = "download", skip_all, fields(file_id = f.id, path = %dest.display()))]
+pub(crate) async fn download_if_needed(
     httpctx: &HttpCtx,
+    filter: &SyncSettings,
+    key: String,
     f: &FileObj,
     dest: &Path,
     state: &mut State,
     verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let key = format!("file:{}", f.id);
+) -> Result<Option<u64>, Error> {
+    if let Some(reason) = filter.skip_reason(dest, f.size) {
+        debug!(file_id = f.id, path = %dest.display(), reason, "skipped by file filter");
+        return Ok(None);
+    }
     let url = f
         .download_url
         .as_ref()
         .or(f.url.as_ref())
         .ok_or("missing file url")?;
 
-    // Probe HEAD for ETag/size
-    let head = httpctx.send(httpctx.client.head(url)).await?;
-    let status = head.status();
-    if !status.is_success() {
-        warn!(file_id = f.id, status = %status.as_u16(), "head non-success, will GET");
-    }
-    let etag = head
-        .headers()
-        .get(header::ETAG)
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.trim_matches('"').to_string());
-    let mut size = head
-        .headers()
-        .get(header::CONTENT_LENGTH)
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.parse::<u64>().ok());
-    if size.is_none() {
-        size = f.size;
-    }
-
-    let prev = state.get(&key);
-    if let (Some(prev), Some(et)) = (prev, etag.as_ref()) {
-        if prev.etag.as_deref() == Some(et) {
-            info!(file_id = f.id, path = %dest.display(), "unchanged (etag)");
-            if verbose {
-                info!(file_id = f.id, path = %dest.display(), "verbose skip (unchanged file)");
-            }
-            return Ok(());
-        }
-    }
-
     // Prepare dest and part
     let part = dest.with_extension("part");
     let mut start = 0u64;
     if let Ok(meta) = tokio::fs::metadata(&part).await {
         start = meta.len();
     }
+    let if_range = match start {
+        0 => None,
+        _ => load_resume_validator(&part).await,
+    };
+    if start > 0 && if_range.is_none() {
+        // Without a validator the partial bytes can't be tied to the current remote file
+        debug!(file_id = f.id, path = %part.display(), "partial download has no validator; restarting");
+        discard_partial(&part).await?;
+        start = 0;
+    }
+    let prev = state.get(&key).cloned();
+    let local_size = tokio::fs::metadata(dest).await.ok().map(|m| m.len());
+    let have_local = local_size.is_some();
 
-    // GET with Range if resuming
+    if let (0, Some(prev), Some(local_size)) = (start, prev.as_ref(), local_size) {
+        if is_metadata_unchanged(prev, f, local_size) {
+            debug!(file_id = f.id, path = %dest.display(), "unchanged (file metadata)");
+            return Ok(None);
+        }
+    }
+
+    // GET with Range/If-Range if resuming; otherwise validate the local copy with stored validators
     let mut req = httpctx.client.get(url);
-    if start > 0 {
-        req = req.header(header::RANGE, format!("bytes={}-", start));
+    if let (true, Some(validator)) = (start > 0, if_range.as_deref()) {
+        req = req
+            .header(header::RANGE, format!("bytes={}-", start))
+            .header(header::IF_RANGE, validator);
+    } else if let (true, Some(prev)) = (have_local, prev.as_ref()) {
+        if let Some(et) = prev.etag.as_deref() {
+            req = req.header(header::IF_NONE_MATCH, format!("\"{}\"", et));
+        }
+        if let Some(lm) = prev.last_modified.as_deref() {
+            req = req.header(header::IF_MODIFIED_SINCE, lm);
+        }
     }
     let resp = httpctx.send(req).await?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        info!(file_id = f.id, path = %dest.display(), "unchanged (304)");
+        if verbose {
+            info!(file_id = f.id, path = %dest.display(), "verbose skip (unchanged file)");
+        }
+        return Ok(None);
+    }
     if !(resp.status().is_success() || resp.status().as_u16() == 206) {
         return Err(format!("GET failed: {}", resp.status()).into());
     }
+    let plan = match resume_plan(&resp, start) {
+        Ok(plan) => plan,
+        Err(e) => {
+            discard_partial(&part).await?;
+            return Err(e.into());
+        }
+    };
+    if start > 0 && plan.offset == 0 {
+        info!(file_id = f.id, path = %dest.display(), "remote changed since partial download; restarting");
+        discard_partial(&part).await?;
+    }
+    start = plan.offset;
 
-    // Stream to part
-    let mut file = tokio::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&part)
-        .await?;
-    let mut stream = resp.bytes_stream();
-    use futures_util::StreamExt;
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk?;
-        file.write_all(&bytes).await?;
+    let (etag, last_modified) = response_validators(&resp);
+    let size = resp.content_length().or(f.size);
+
+    // Servers that ignore conditional headers still tell us whether the content changed
+    if start == 0 && have_local {
+        if let Some(prev) = prev.as_ref() {
+            if is_unchanged(prev, etag.as_deref(), last_modified.as_deref()) {
+                info!(file_id = f.id, path = %dest.display(), "unchanged (validators match)");
+                return Ok(None);
+            }
+        }
     }
-    file.flush().await?;
-    atomic_rename(&part, dest).await?;
+
+    // Stop before writing a body that cannot fit on disk
+    if let Some(total) = plan.total {
+        check_free_space(dest, total.saturating_sub(start), 0)?;
+    }
+
+    // Large files on range-capable servers are fetched as concurrent byte ranges
+    let (written, etag, last_modified) = match (
+        start,
+        httpctx.range_chunks_for(&resp),
+        resume_validator(&resp),
+    ) {
+        (0, Some(chunks), Some(validator)) => {
+            let total = resp.content_length().unwrap_or_default();
+            drop(resp);
+            info!(file_id = f.id, path = %dest.display(), total, chunks, "downloading in parallel ranges");
+            let ranged = httpctx
+                .download_ranges(
+                    httpctx.client.get(url),
+                    &part,
+                    dest,
+                    &validator,
+                    total,
+                    chunks,
+                )
+                .await
+                .map_err(Error::from)?;
+            match ranged {
+                RangedDownload::Complete(written) => (written, etag, last_modified),
+                RangedDownload::Changed(full) => {
+                    info!(file_id = f.id, path = %dest.display(), "remote changed during ranged download; restarting as one stream");
+                    let (etag, last_modified) = response_validators(&full);
+                    save_resume_validator(&part, &full).await?;
+                    let total = full.content_length();
+                    let written = stream_to_part(httpctx, full, &part, dest, 0, total).await?;
+                    (written, etag, last_modified)
+                }
+            }
+        }
+        _ => {
+            if start == 0 {
+                save_resume_validator(&part, &resp).await?;
+            }
+            let written = stream_to_part(httpctx, resp, &part, dest, start, plan.total).await?;
+            (written, etag, last_modified)
+        }
+    };
     info!(file_id = f.id, path = %dest.display(), "downloaded");
+    apply_remote_mtime(dest, f.updated_at.as_deref());
 
     // Update state
     let final_size = match tokio::fs::metadata(dest).await {
@@ -822,9 +2812,133 @@ async fn download_if_needed(
             content_hash: None,
             last_error: None,
             error_count: None,
+            last_modified,
+            uuid: f.uuid.clone(),
+            locked: None,
         },
     );
-    Ok(())
+    Ok(Some(written))
+}
+
+/// Stream `resp` into `part`, which already holds `start` bytes, then move it to
+/// `dest`. Returns the number of bytes written.
+async fn stream_to_part(
+    httpctx: &HttpCtx,
+    resp: reqwest::Response,
+    part: &Path,
+    dest: &Path,
+    start: u64,
+    total: Option<u64>,
+) -> Result<u64, Error> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(part)
+        .await?;
+    // Per-file bars on the dashboard or above the overall byte bar
+    let bar = file_bars().then(|| {
+        let bar = attach(download_bar(total, &display_name(dest)));
+        bar.set_position(start);
+        bar
+    });
+    if let Some(total) = total {
+        transfer_expect(total.saturating_sub(start));
+    }
+    let mut stream = resp.bytes_stream();
+    let mut written = 0u64;
+    use futures_util::StreamExt;
+    while let Some(chunk) = stream.next().await {
+        if shutdown::requested() {
+            // Keep the partial file; the next sync resumes it
+            file.flush().await?;
+            if let Some(bar) = &bar {
+                bar.finish_and_clear();
+            }
+            shutdown::check()?;
+        }
+        let bytes = chunk?;
+        if let Some(limiter) = &httpctx.bandwidth {
+            limiter.consume(bytes.len()).await;
+        }
+        file.write_all(&bytes).await?;
+        written += bytes.len() as u64;
+        if let Some(bar) = &bar {
+            bar.inc(bytes.len() as u64);
+        }
+        if total.is_none() {
+            transfer_expect(bytes.len() as u64);
+        }
+        transfer_inc(bytes.len() as u64);
+    }
+    file.flush().await?;
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    if let Some(total) = total {
+        if start + written != total {
+            discard_partial(part).await?;
+            return Err(tr!("size mismatch: got {} of {} bytes", start + written, total).into());
+        }
+    }
+    atomic_rename(part, dest).await?;
+    clear_resume_validator(part).await?;
+    Ok(written)
+}
+
+/// ETag (normalized) and Last-Modified of a response, as stored in state.
+fn response_validators(resp: &reqwest::Response) -> (Option<String>, Option<String>) {
+    let etag = resp
+        .headers()
+        .get(header::ETAG)
+        .and_then(|h| h.to_str().ok())
+        .map(normalize_etag);
+    let last_modified = resp
+        .headers()
+        .get(header::LAST_MODIFIED)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string());
+    (etag, last_modified)
+}
+
+/// Strip weak-validator prefix and quotes so stored ETags compare consistently.
+fn normalize_etag(raw: &str) -> String {
+    raw.trim()
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .to_string()
+}
+
+/// Compare response validators against stored state, preferring ETag and
+/// falling back to `Last-Modified` when the server sends no ETag.
+fn is_unchanged(prev: &ItemState, etag: Option<&str>, last_modified: Option<&str>) -> bool {
+    match (prev.etag.as_deref(), etag) {
+        (Some(old), Some(new)) => old == new,
+        _ => matches!(
+            (prev.last_modified.as_deref(), last_modified),
+            (Some(old), Some(new)) if old == new
+        ),
+    }
+}
+
+/// Files without HTTP validators, seeded by `adopt` or served by a CDN that sends
+/// neither ETag nor `Last-Modified`, can't be checked with a conditional GET; trust
+/// them while the Canvas `updated_at` and `uuid` and the local size still match
+/// what was recorded.
+fn is_metadata_unchanged(prev: &ItemState, f: &FileObj, local_size: u64) -> bool {
+    prev.etag.is_none()
+        && prev.last_modified.is_none()
+        && prev.updated_at.is_some()
+        && prev.updated_at == f.updated_at
+        && (prev.uuid.is_none() || prev.uuid == f.uuid)
+        && prev.size == Some(local_size)
+}
+
+/// `md` behind its front matter, and the hash that decides whether it is rewritten.
+/// The hash leaves out the sync time, so unchanged items keep their file.
+fn with_front_matter(meta: &FrontMatter, md: &str) -> (String, String) {
+    let hash = sha1_hex(format!("{}{}", meta.render(None), md).as_bytes());
+    let synced_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    (format!("{}{}", meta.render(Some(&synced_at)), md), hash)
 }
 
 fn sha1_hex(data: &[u8]) -> String {
@@ -863,3 +2977,547 @@ fn extract_page_slug(url: &str) -> Option<String> {
         .and_then(|c| c.get(2))
         .map(|m| m.as_str().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(etag: Option<&str>, last_modified: Option<&str>) -> ItemState {
+        ItemState {
+            etag: etag.map(String::from),
+            updated_at: None,
+            size: None,
+            content_hash: None,
+            last_error: None,
+            error_count: None,
+            last_modified: last_modified.map(String::from),
+            uuid: None,
+            locked: None,
+        }
+    }
+
+    #[test]
+    fn unchanged_prefers_etag_then_last_modified() {
+        let lm = "Wed, 21 Oct 2015 07:28:00 GMT";
+        assert!(is_unchanged(&item(Some("abc"), None), Some("abc"), None));
+        assert!(!is_unchanged(
+            &item(Some("abc"), Some(lm)),
+            Some("def"),
+            Some(lm)
+        ));
+        assert!(is_unchanged(&item(None, Some(lm)), None, Some(lm)));
+        assert!(is_unchanged(&item(Some("abc"), Some(lm)), None, Some(lm)));
+        assert!(!is_unchanged(&item(None, None), None, None));
+        assert_eq!(normalize_etag("W/\"abc\""), "abc");
+    }
+
+    #[test]
+    fn files_without_validators_skip_until_canvas_metadata_changes() {
+        let mut prev = item(None, None);
+        prev.updated_at = Some("2024-03-01T10:00:00Z".into());
+        prev.size = Some(5);
+        prev.uuid = Some("u1".into());
+        let mut f = FileObj {
+            id: 1,
+            display_name: None,
+            filename: None,
+            size: Some(5),
+            updated_at: Some("2024-03-01T10:00:00Z".into()),
+            url: None,
+            download_url: None,
+            uuid: Some("u1".into()),
+            lock: FileLock::default(),
+        };
+        assert!(is_metadata_unchanged(&prev, &f, 5));
+        assert!(!is_metadata_unchanged(&prev, &f, 6));
+        // A replaced file gets a new uuid even when Canvas keeps the update time
+        f.uuid = Some("u2".into());
+        assert!(!is_metadata_unchanged(&prev, &f, 5));
+        // Adopted copies have no uuid recorded
+        prev.uuid = None;
+        assert!(is_metadata_unchanged(&prev, &f, 5));
+        f.updated_at = Some("2024-04-01T10:00:00Z".into());
+        assert!(!is_metadata_unchanged(&prev, &f, 5));
+        // Files served with validators go through the conditional GET
+        let downloaded = ItemState {
+            etag: Some("e".into()),
+            updated_at: f.updated_at.clone(),
+            ..prev.clone()
+        };
+        assert!(!is_metadata_unchanged(&downloaded, &f, 5));
+    }
+
+    #[test]
+    fn colliding_names_get_the_file_id() {
+        let module_dir = Path::new("/archive/C/Modules/7_Unidad_1");
+        let dest = module_dir.join("Attachments").join("Practica_1.pdf");
+        let mut state = State::default();
+        let claim = |state: &mut State, key: &str, tag: &str| {
+            claim_dest(state, 7, module_dir, key, dest.clone(), tag)
+        };
+        assert_eq!(claim(&mut state, "file:1", "1"), dest);
+        let second = module_dir.join("Attachments").join("Practica_1_2.pdf");
+        assert_eq!(claim(&mut state, "file:2", "2"), second);
+        // Later runs keep the same names, whichever file comes first
+        assert_eq!(claim(&mut state, "file:2", "2"), second);
+        assert_eq!(claim(&mut state, "file:1", "1"), dest);
+        assert_eq!(state.paths["7/Attachments/Practica_1.pdf"], "file:1");
+    }
+
+    #[test]
+    fn renamed_files_release_their_old_name() {
+        let module_dir = Path::new("/archive/C/Modules/7_Unidad_1");
+        let attachment = |name: &str| module_dir.join("Attachments").join(name);
+        let mut state = State::default();
+        let claim = |state: &mut State, module_id: u64, key: &str, name: &str| {
+            let tag = key.trim_start_matches("file:");
+            claim_dest(state, module_id, module_dir, key, attachment(name), tag)
+        };
+        claim(&mut state, 7, "file:1", "Practica_1.pdf");
+        claim(&mut state, 7, "file:3", "Guia.pdf");
+        claim(&mut state, 7, "file:4", "Guia.pdf");
+        claim(&mut state, 8, "file:9", "Otro.pdf");
+
+        // File 1 was renamed and a new file 2 took its old name; nothing lists file 3 any more
+        let file = |id: u64, title: &str| crate::canvas::ModuleItem {
+            title: Some(title.into()),
+            ..module_item(id, "File", None, Some(id))
+        };
+        let module = Module {
+            id: 7,
+            name: "Unidad 1".into(),
+            items: vec![
+                file(1, "Practica 1 v2.pdf"),
+                file(2, "Practica_1.pdf"),
+                file(4, "Guia.pdf"),
+            ],
+        };
+        release_stale_claims(&mut state, &module);
+        assert_eq!(
+            state.paths.keys().collect::<Vec<_>>(),
+            ["7/Attachments/Guia_4.pdf", "8/Attachments/Otro.pdf"]
+        );
+        assert_eq!(
+            claim(&mut state, 7, "file:2", "Practica_1.pdf"),
+            attachment("Practica_1.pdf")
+        );
+    }
+
+    #[test]
+    fn module_index_nests_items_under_subheaders() {
+        let entries = vec![
+            IndexEntry {
+                title: "Semana 1".into(),
+                kind: "SubHeader".into(),
+                indent: 0,
+                target: None,
+            },
+            IndexEntry {
+                title: "Intro".into(),
+                kind: "Page".into(),
+                indent: 1,
+                target: Some("01-Intro.md".into()),
+            },
+            IndexEntry {
+                title: "Slides".into(),
+                kind: "File".into(),
+                indent: 1,
+                target: Some("Attachments/slides.pdf".into()),
+            },
+        ];
+        let md = render_module_index("Unidad 1", &entries);
+        assert_eq!(
+            md,
+            "# Unidad 1\n\n- **Semana 1**\n  - [Page] [Intro](<01-Intro.md>)\n  - [File] [Slides](<Attachments/slides.pdf>)\n"
+        );
+    }
+
+    /// In-memory course with one page that links a file also listed as a module item.
+    /// The page `missing` answers 404. Every page has two saved revisions.
+    struct MockProvider;
+
+    impl CourseProvider for MockProvider {
+        type Error = crate::canvas::CanvasError;
+
+        async fn list_courses(&self) -> Result<Vec<Course>, Self::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn list_modules_with_items(
+            &self,
+            _course_id: u64,
+        ) -> Result<Vec<Module>, Self::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn get_page(
+            &self,
+            _course_id: u64,
+            page_url: &str,
+        ) -> Result<crate::canvas::PageObj, Self::Error> {
+            if page_url == "missing" {
+                return Err(crate::canvas::CanvasError::Status(404, "Not Found".into()));
+            }
+            Ok(crate::canvas::PageObj {
+                title: Some(format!("Page {}", page_url)),
+                body: Some(
+                    r#"<p>See <a href="/courses/1/files/7/download">the guide</a></p>"#.into(),
+                ),
+                updated_at: None,
+            })
+        }
+
+        async fn get_file(&self, file_id: u64) -> Result<FileObj, Self::Error> {
+            Ok(FileObj {
+                id: file_id,
+                display_name: Some("guide.pdf".into()),
+                filename: None,
+                size: Some(1024),
+                updated_at: None,
+                url: Some(format!("https://lms.example/files/{}", file_id)),
+                download_url: None,
+                uuid: None,
+                lock: FileLock::default(),
+            })
+        }
+
+        async fn list_assignments(&self, _course_id: u64) -> Result<Vec<Assignment>, Self::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn list_page_revisions(
+            &self,
+            _course_id: u64,
+            _page_url: &str,
+        ) -> Result<Vec<crate::canvas::PageRevision>, Self::Error> {
+            Ok([2, 1]
+                .into_iter()
+                .map(|revision_id| crate::canvas::PageRevision {
+                    revision_id,
+                    updated_at: None,
+                    title: None,
+                    body: None,
+                })
+                .collect())
+        }
+
+        async fn get_page_revision(
+            &self,
+            _course_id: u64,
+            page_url: &str,
+            revision_id: u64,
+        ) -> Result<Option<crate::canvas::PageRevision>, Self::Error> {
+            Ok(Some(crate::canvas::PageRevision {
+                revision_id,
+                updated_at: Some(format!("2024-03-0{}T10:00:00Z", revision_id)),
+                title: Some(format!("Page {}", page_url)),
+                body: Some(format!("<p>Draft {}</p>", revision_id)),
+            }))
+        }
+    }
+
+    fn module_item(
+        id: u64,
+        kind: &str,
+        page_url: Option<&str>,
+        content_id: Option<u64>,
+    ) -> crate::canvas::ModuleItem {
+        crate::canvas::ModuleItem {
+            id,
+            title: Some(format!("item {}", id)),
+            kind: Some(kind.into()),
+            html_url: None,
+            page_url: page_url.map(String::from),
+            external_url: None,
+            content_id,
+            indent: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_plans_module_items_from_any_provider() {
+        let cfg = Config::default();
+        let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+        let module = Module {
+            id: 3,
+            name: "Semana 1".into(),
+            items: vec![
+                module_item(1, "Page", Some("intro"), None),
+                module_item(2, "File", None, Some(7)),
+            ],
+        };
+        let mut state = State::default();
+        let mut run = RunRecord::start();
+        let course_dir = std::env::temp_dir().join("u_crawler_mock_provider");
+        let planned = sync_module(
+            &cfg,
+            &MockProvider,
+            &httpctx,
+            &course_dir,
+            1,
+            "Course",
+            &CourseListings::default(),
+            &mut state,
+            &mut run,
+            &module,
+            None,
+            true,
+            false,
+            false,
+            &ContentFilter::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        // The file linked from the page is planned once, not again for its module item
+        assert_eq!(planned, (1, 1));
+        assert!(!course_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn page_revisions_are_saved_once() {
+        let mut cfg = Config::default();
+        cfg.sync.page_revisions = true;
+        let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+        let module = Module {
+            id: 3,
+            name: "Semana 1".into(),
+            items: vec![module_item(1, "Page", Some("intro"), None)],
+        };
+        let course_dir = tempfile::tempdir().unwrap();
+        let pages = ContentFilter {
+            only: vec![ContentKind::Pages],
+            skip: vec![],
+        };
+        let mut state = State::default();
+        sync_module(
+            &cfg,
+            &MockProvider,
+            &httpctx,
+            course_dir.path(),
+            1,
+            "Course",
+            &CourseListings::default(),
+            &mut state,
+            &mut RunRecord::start(),
+            &module,
+            None,
+            false,
+            false,
+            false,
+            &pages,
+            None,
+        )
+        .await
+        .unwrap();
+        let history = course_dir.path().join(PAGES_DIR).join("intro");
+        let first = std::fs::read_to_string(history.join("rev-1.md")).unwrap();
+        assert!(first.contains("type: page_revision"));
+        assert!(first.contains("# Page intro\n\nDraft 1"));
+        assert!(history.join("rev-2.md").exists());
+        assert_eq!(latest_saved_revision(&history), 2);
+        assert!(state.get("page-history:intro").is_some());
+    }
+
+    #[tokio::test]
+    async fn content_filter_limits_what_a_module_plans() {
+        let cfg = Config::default();
+        let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+        let module = Module {
+            id: 3,
+            name: "Semana 1".into(),
+            items: vec![
+                module_item(1, "Page", Some("intro"), None),
+                module_item(2, "File", None, Some(7)),
+            ],
+        };
+        let course_dir = std::env::temp_dir().join("u_crawler_mock_provider");
+        let mut planned = Vec::new();
+        for (only, skip) in [
+            (vec![ContentKind::Pages], vec![]),
+            (vec![], vec![ContentKind::Pages]),
+            (vec![ContentKind::Assignments], vec![]),
+        ] {
+            let content = ContentFilter { only, skip };
+            let counts = sync_module(
+                &cfg,
+                &MockProvider,
+                &httpctx,
+                &course_dir,
+                1,
+                "Course",
+                &CourseListings::default(),
+                &mut State::default(),
+                &mut RunRecord::start(),
+                &module,
+                None,
+                true,
+                false,
+                false,
+                &content,
+                None,
+            )
+            .await
+            .unwrap();
+            planned.push(counts);
+        }
+        // Skipping pages still follows their links to files
+        assert_eq!(planned, [(1, 0), (0, 1), (0, 0)]);
+    }
+
+    #[tokio::test]
+    async fn since_window_skips_synced_items_not_updated_since() {
+        let cfg = Config::default();
+        let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+        let module = Module {
+            id: 3,
+            name: "Semana 1".into(),
+            items: vec![module_item(1, "Page", Some("intro"), None)],
+        };
+        let window = |day: &str| {
+            let mut times = UpdateTimes::default();
+            times
+                .pages
+                .insert("intro".into(), "2024-02-20T10:00:00Z".into());
+            Window {
+                start: Since::from_str(day)
+                    .unwrap()
+                    .start(&State::default())
+                    .unwrap(),
+                times,
+            }
+        };
+        let mut planned = Vec::new();
+        for window in [window("2024-01-01"), window("2024-03-01")] {
+            // Synced before, with content that no longer matches the page
+            let mut state = State::default();
+            state.set("page:intro".into(), item(None, None));
+            let counts = sync_module(
+                &cfg,
+                &MockProvider,
+                &httpctx,
+                &std::env::temp_dir().join("u_crawler_mock_provider"),
+                1,
+                "Course",
+                &CourseListings::default(),
+                &mut state,
+                &mut RunRecord::start(),
+                &module,
+                None,
+                true,
+                false,
+                false,
+                &ContentFilter::default(),
+                Some(&window),
+            )
+            .await
+            .unwrap();
+            planned.push(counts.0);
+        }
+        // Updated inside the first window, so only that run reads the page again
+        assert_eq!(planned, [1, 0]);
+        assert_eq!(Since::from_str("last"), Ok(Since::LastRun));
+        assert!(Since::from_str("01/03/2024").is_err());
+    }
+
+    /// Dry-run a module with a missing page followed by a file.
+    async fn sync_with_missing_page(
+        state: &mut State,
+        run: &mut RunRecord,
+        fail_fast: bool,
+    ) -> Result<(usize, usize), Error> {
+        let cfg = Config::default();
+        let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+        let module = Module {
+            id: 3,
+            name: "Semana 1".into(),
+            items: vec![
+                module_item(1, "Page", Some("missing"), None),
+                module_item(2, "File", None, Some(7)),
+            ],
+        };
+        sync_module(
+            &cfg,
+            &MockProvider,
+            &httpctx,
+            &std::env::temp_dir().join("u_crawler_mock_provider"),
+            1,
+            "Course",
+            &CourseListings::default(),
+            state,
+            run,
+            &module,
+            None,
+            true,
+            false,
+            fail_fast,
+            &ContentFilter::default(),
+            None,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn failed_items_are_recorded_unless_failing_fast() {
+        let mut state = State::default();
+        let mut run = RunRecord::start();
+        let err = sync_with_missing_page(&mut state, &mut run, true)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Provider);
+
+        // The missing page is recorded and the file after it still planned
+        let planned = sync_with_missing_page(&mut state, &mut run, false).await;
+        assert_eq!(planned.unwrap(), (0, 1));
+        assert_eq!(state.get("page:missing").unwrap().error_count, Some(2));
+        assert_eq!(run.failed, 1);
+        assert_eq!(run.failures.get("provider"), Some(&1));
+        assert_eq!(
+            run.failed_items,
+            ["page:missing: http status 404: Not Found"]
+        );
+    }
+
+    #[tokio::test]
+    async fn renamed_course_folder_is_found_by_course_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = Config {
+            download_root: dir.path().to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let course = |name: &str| Course {
+            id: 42,
+            name: name.into(),
+            course_code: Some("FIS101".into()),
+            ..Default::default()
+        };
+        // A folder synced before courses.json existed
+        let old = dir.path().join("Fisica_FIS101");
+        let state = State {
+            course_id: Some(42),
+            ..Default::default()
+        };
+        state.save(&old.join("state.json")).await.unwrap();
+
+        let renamed = course("Fisica I");
+        assert_eq!(resolve_course_dir(&cfg, &renamed, true).await.unwrap(), old);
+        let moved = resolve_course_dir(&cfg, &renamed, false).await.unwrap();
+        assert_eq!(moved, dir.path().join("Fisica_I_FIS101"));
+        assert!(moved.join("state.json").exists() && !old.exists());
+
+        // With `keep`, the folder stays and the new name links to it
+        #[cfg(unix)]
+        {
+            let mut keep = cfg.clone();
+            keep.naming.course_renames = "keep".into();
+            let kept = resolve_course_dir(&keep, &course("Fisica General"), false)
+                .await
+                .unwrap();
+            assert_eq!(kept, moved);
+            let alias = dir.path().join("Fisica_General_FIS101");
+            assert!(alias.is_symlink() && alias.join("state.json").exists());
+            let again = resolve_course_dir(&keep, &course("Fisica General"), false).await;
+            assert_eq!(again.unwrap(), moved);
+        }
+    }
+}
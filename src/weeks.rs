@@ -0,0 +1,78 @@
+use chrono::{Datelike, NaiveDate};
+use regex::RegexBuilder;
+use std::path::{Path, PathBuf};
+
+/// Course subfolder grouping the modules whose names match `week_pattern`.
+pub const WEEKS_DIR: &str = "Weeks";
+
+/// Week number in a module name such as "Semana 3: Derivadas" per `pattern`
+/// (case-insensitive): its first capture group, or else the first number in the
+/// match. `None` when the pattern is empty or does not match.
+pub fn week_number(pattern: &str, name: &str) -> Option<u32> {
+    if pattern.trim().is_empty() {
+        return None;
+    }
+    let re = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()?;
+    let caps = re.captures(name)?;
+    let text = caps.get(1).or_else(|| caps.get(0))?.as_str();
+    let digits: String = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Folder name of week `week`, e.g. `Week_03`.
+pub fn week_name(week: u32) -> String {
+    format!("Week_{:02}", week)
+}
+
+/// Folder of week `week` of the course at `course_dir`, e.g. `Weeks/Week_03`.
+pub fn week_dir(course_dir: &Path, week: u32) -> PathBuf {
+    course_dir.join(WEEKS_DIR).join(week_name(week))
+}
+
+/// Week number of a folder named by [`week_name`].
+pub fn parse_week_dir(name: &str) -> Option<u32> {
+    name.strip_prefix("Week_")?.parse().ok()
+}
+
+/// Week of `date` in a course starting on `start`. Weeks run Monday to Sunday
+/// and week 1 is the one holding the start date; `None` before that.
+pub fn week_of_date(start: NaiveDate, date: NaiveDate) -> Option<u32> {
+    let monday = start - chrono::Duration::days(start.weekday().num_days_from_monday() as i64);
+    let days = (date - monday).num_days();
+    (days >= 0).then(|| (days / 7) as u32 + 1)
+}
+
+/// Date at the start of an RFC 3339 timestamp or a `YYYY-MM-DD - …` folder name.
+pub fn leading_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn week_numbers_from_names_and_dates() {
+        let pattern = r"Semana (\d+)";
+        assert_eq!(week_number(pattern, "SEMANA 3: Derivadas"), Some(3));
+        assert_eq!(week_number(r"week \d+", "Week 12 - Review"), Some(12));
+        assert_eq!(week_number(pattern, "Unidad 1"), None);
+        assert_eq!(week_number("", "Semana 3"), None);
+        assert_eq!(parse_week_dir("Week_03"), Some(3));
+
+        // A course starting on Wednesday 2024-02-07: week 1 began on Monday the 5th
+        let start = leading_date("2024-02-07T05:00:00Z").unwrap();
+        let date = |s: &str| leading_date(s).unwrap();
+        assert_eq!(week_of_date(start, date("2024-02-05 - Clase 1")), Some(1));
+        assert_eq!(week_of_date(start, date("2024-02-11")), Some(1));
+        assert_eq!(week_of_date(start, date("2024-02-19")), Some(3));
+        assert_eq!(week_of_date(start, date("2024-02-04")), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::Config;
+use crate::tr;
 use crate::zoom::db::ZoomDb;
 use crate::zoom::models::{
     RecordingFileResponse, RecordingListResponse, RecordingSummary, RecordingsResult,
@@ -26,7 +27,7 @@ pub enum ZoomApiError {
     #[error(transparent)]
     Json(#[from] serde_json::Error),
     #[error(transparent)]
-    Db(#[from] Box<dyn std::error::Error>),
+    Db(#[from] crate::Error),
     #[error(transparent)]
     Cookie(#[from] cookie::ParseError),
     #[error(transparent)]
@@ -53,7 +54,7 @@ impl ZoomClient {
         }
         let scid = scid.unwrap();
 
-        let cookies = db.load_cookies().map_err(ZoomApiError::Db)?;
+        let cookies = db.load_cookies(course_id).map_err(ZoomApiError::Db)?;
         info!("Loaded {} cookies from DB", cookies.len());
         if cookies.is_empty() {
             warn!("No cookies found in DB");
@@ -120,7 +121,7 @@ impl ZoomClient {
             }
         }
 
-        let client = Client::builder()
+        let client = crate::http::with_proxy(Client::builder(), cfg)
             .cookie_provider(cookie_store)
             .default_headers(headers)
             .build()?;
@@ -220,9 +221,10 @@ impl ZoomClient {
                 if status == reqwest::StatusCode::UNAUTHORIZED
                     || status == reqwest::StatusCode::FORBIDDEN
                 {
-                    return Err(ZoomApiError::Message(format!(
+                    return Err(ZoomApiError::Message(tr!(
                         "Zoom returned {} (likely cookies/headers invalid): {}",
-                        status, text
+                        status,
+                        text
                     )));
                 }
                 return Err(ZoomApiError::Message(format!("HTTP {} - {}", status, text)));
@@ -304,10 +306,20 @@ impl ZoomClient {
         let mut out = Vec::new();
         if let Some(result) = payload.result {
             if let Some(entries) = result.recording_files {
-                for entry in entries.into_iter().filter(|e| e.play_url.is_some()) {
+                for entry in entries {
+                    // Transcripts and chat logs have no player page; their download URL is the key
+                    let Some(play_url) = entry.play_url.clone().or_else(|| {
+                        entry
+                            .download_url
+                            .clone()
+                            .filter(|_| entry.file_type.as_deref() != Some("MP4"))
+                    }) else {
+                        continue;
+                    };
                     out.push(ZoomRecordingFile {
                         meeting_id: meeting.meeting_id.clone(),
-                        play_url: entry.play_url.unwrap(),
+                        file_id: entry.id.clone(),
+                        play_url,
                         download_url: entry.download_url.clone(),
                         file_type: entry.file_type.clone(),
                         recording_start: entry.recording_start.clone(),
@@ -315,6 +327,10 @@ impl ZoomClient {
                         start_time: meeting.start_time.clone(),
                         timezone: meeting.timezone.clone(),
                         meeting_number: meeting.meeting_number.clone(),
+                        file_size: entry.file_size,
+                        duration: meeting.duration,
+                        recording_type: entry.recording_type.clone(),
+                        passcode: None,
                     });
                 }
             }
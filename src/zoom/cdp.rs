@@ -0,0 +1,45 @@
+use crate::config::{Config, ConfigPaths};
+use crate::error::Error;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::headless::ZoomHeadless;
+use crate::{report, tr};
+use tracing::info;
+
+/// Capture Zoom credentials for a course from a Chrome the user already runs
+/// with remote debugging, for when the headless SSO flow cannot sign in.
+pub async fn sniff_cdp(course_id: u64, debug_port: u16, keep_tab: bool) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+
+    report!(
+        "{}",
+        tr!(
+            "Connecting to Chrome on port {} for course {}...",
+            debug_port,
+            course_id
+        )
+    );
+    crate::zoom::discover_tool_id(&cfg, &db, course_id).await?;
+    ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_from_running_browser(debug_port, keep_tab)
+        .await?;
+
+    let headers = db.get_all_request_headers(course_id)?;
+    info!(
+        course_id,
+        scid = ?db.get_scid(course_id)?,
+        cookies = db.load_cookies(course_id)?.len(),
+        headers = headers.len(),
+        "captured Zoom session over CDP"
+    );
+    report!(
+        "{}",
+        tr!(
+            "Captured Zoom session for course {}. Run 'u_crawler zoom flow --course-id {}' to download recordings.",
+            course_id,
+            course_id,
+        )
+    );
+    Ok(())
+}
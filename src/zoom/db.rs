@@ -1,26 +1,60 @@
-use crate::zoom::models::{RecordingListResponse, ZoomCookie, ZoomRecordingFile};
+use crate::error::Error;
+use crate::report_verbose;
+use crate::zoom::models::{
+    DiscoveredLink, DownloadStatus, RecordingListResponse, RecordingSummary, SessionExpiry,
+    ZoomCookie, ZoomDownload, ZoomRecordingFile,
+};
 use chrono::Utc;
 use rusqlite::{params, Connection};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
 
+/// SQLite-backed Zoom state. A single connection is opened up front and reused,
+/// so long-running processes don't churn file handles on every query.
 pub struct ZoomDb {
-    path: PathBuf,
+    conn: Mutex<Connection>,
 }
 
 impl ZoomDb {
-    pub fn new(config_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config_dir: &Path) -> Result<Self, Error> {
         let path = config_dir.join("zoom_state.sqlite");
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
-        let db = Self { path };
+        let db = Self {
+            conn: Mutex::new(Connection::open(&path)?),
+        };
         db.init()?;
         Ok(db)
     }
 
-    fn init(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.connection()?;
+    /// Problems reported by SQLite's `PRAGMA integrity_check`; empty when the database is sound.
+    pub fn integrity_check(&self) -> Result<Vec<String>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let problems = rows
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|r| r != "ok")
+            .collect();
+        Ok(problems)
+    }
+
+    fn init(&self) -> Result<(), Error> {
+        let conn = self.connection();
+        // Cookies used to live in one global table; keep those rows as a fallback
+        // shared by every course (course_id '') until a course captures its own
+        let global_cookies: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 AND SUM(name = 'course_id') = 0
+             FROM pragma_table_info('zoom_cookie')",
+            [],
+            |row| row.get(0),
+        )?;
+        if global_cookies {
+            conn.execute_batch("ALTER TABLE zoom_cookie RENAME TO zoom_cookie_global")?;
+        }
         conn.execute_batch(
             r#"
             PRAGMA journal_mode = WAL;
@@ -30,6 +64,7 @@ impl ZoomDb {
                 updated_at INTEGER NOT NULL
             );
             CREATE TABLE IF NOT EXISTS zoom_cookie (
+                course_id TEXT NOT NULL,
                 host TEXT NOT NULL,
                 name TEXT NOT NULL,
                 value TEXT NOT NULL,
@@ -38,7 +73,7 @@ impl ZoomDb {
                 secure INTEGER NOT NULL,
                 http_only INTEGER NOT NULL,
                 updated_at INTEGER NOT NULL,
-                PRIMARY KEY(host, name, path)
+                PRIMARY KEY(course_id, host, name, path)
             );
             CREATE TABLE IF NOT EXISTS zoom_request_headers (
                 course_id TEXT NOT NULL,
@@ -56,6 +91,16 @@ impl ZoomDb {
                 updated_at INTEGER NOT NULL,
                 PRIMARY KEY(course_id, referer)
             );
+            CREATE TABLE IF NOT EXISTS zoom_course_tool (
+                course_id TEXT PRIMARY KEY,
+                tool_id INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS zoom_session (
+                course_id TEXT PRIMARY KEY,
+                expires_at INTEGER,
+                captured_at INTEGER NOT NULL
+            );
             CREATE TABLE IF NOT EXISTS zoom_meetings (
                 meeting_id TEXT PRIMARY KEY,
                 course_id TEXT NOT NULL,
@@ -69,27 +114,55 @@ impl ZoomDb {
                 fetched_at INTEGER NOT NULL,
                 PRIMARY KEY(meeting_id, play_url)
             );
+            CREATE TABLE IF NOT EXISTS zoom_links (
+                course_id TEXT NOT NULL,
+                url TEXT NOT NULL,
+                label TEXT,
+                play_url TEXT,
+                meeting_id TEXT,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY(course_id, url)
+            );
+            CREATE TABLE IF NOT EXISTS zoom_downloads (
+                meeting_id TEXT NOT NULL,
+                file_id TEXT NOT NULL,
+                status TEXT NOT NULL,
+                path TEXT NOT NULL,
+                size INTEGER,
+                sha1 TEXT,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY(meeting_id, file_id)
+            );
             "#,
         )?;
+        if global_cookies {
+            conn.execute_batch(
+                "INSERT INTO zoom_cookie
+                 SELECT '', host, name, value, path, expires, secure, http_only, updated_at
+                 FROM zoom_cookie_global;
+                 DROP TABLE zoom_cookie_global;",
+            )?;
+        }
         Ok(())
     }
 
-    fn connection(&self) -> Result<Connection, rusqlite::Error> {
-        Connection::open(&self.path)
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // A panic mid-query cannot leave the connection itself inconsistent
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
     }
 
-    pub fn save_scid(&self, course_id: u64, scid: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.connection()?;
+    pub fn save_scid(&self, course_id: u64, scid: &str) -> Result<(), Error> {
+        let conn = self.connection();
         conn.execute(
             "REPLACE INTO zoom_course_scid(course_id, scid, updated_at) VALUES (?1, ?2, ?3)",
             params![course_id.to_string(), scid, Utc::now().timestamp()],
         )?;
-        println!("DB: Saved scid for course {}", course_id);
+        report_verbose!("DB: Saved scid for course {}", course_id);
         Ok(())
     }
 
-    pub fn get_scid(&self, course_id: u64) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        let conn = self.connection()?;
+    pub fn get_scid(&self, course_id: u64) -> Result<Option<String>, Error> {
+        let conn = self.connection();
         let mut stmt = conn.prepare("SELECT scid FROM zoom_course_scid WHERE course_id = ?1")?;
         let mut rows = stmt.query(params![course_id.to_string()])?;
         if let Some(row) = rows.next()? {
@@ -97,23 +170,106 @@ impl ZoomDb {
 
             Ok(Some(scid))
         } else {
-            println!("DB: No scid found for course {}", course_id);
+            report_verbose!("DB: No scid found for course {}", course_id);
             Ok(None)
         }
     }
 
-    pub fn replace_cookies(
+    /// Zoom LTI tool id discovered for a course.
+    pub fn get_tool_id(&self, course_id: u64) -> Result<Option<u64>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare("SELECT tool_id FROM zoom_course_tool WHERE course_id = ?1")?;
+        let mut rows = stmt.query(params![course_id.to_string()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get::<_, i64>(0)? as u64)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_tool_id(&self, course_id: u64, tool_id: u64) -> Result<(), Error> {
+        let conn = self.connection();
+        conn.execute(
+            "REPLACE INTO zoom_course_tool(course_id, tool_id, updated_at) VALUES (?1, ?2, ?3)",
+            params![
+                course_id.to_string(),
+                tool_id as i64,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record when a course's Zoom session was captured and when its cookies run out.
+    pub fn save_session_expiry(
         &self,
-        cookies: &[ZoomCookie],
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut conn = self.connection()?;
+        course_id: u64,
+        expires_at: Option<i64>,
+    ) -> Result<(), Error> {
+        let conn = self.connection();
+        conn.execute(
+            "REPLACE INTO zoom_session(course_id, expires_at, captured_at) VALUES (?1, ?2, ?3)",
+            params![course_id.to_string(), expires_at, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_session_expiry(&self, course_id: u64) -> Result<Option<SessionExpiry>, Error> {
+        let conn = self.connection();
+        let mut stmt =
+            conn.prepare("SELECT captured_at, expires_at FROM zoom_session WHERE course_id = ?1")?;
+        let mut rows = stmt.query(params![course_id.to_string()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(SessionExpiry {
+                captured_at: row.get(0)?,
+                expires_at: row.get(1)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Stored session expiries of every course, by course id.
+    pub fn list_session_expiries(&self) -> Result<Vec<(u64, SessionExpiry)>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare(
+            "SELECT course_id, captured_at, expires_at FROM zoom_session ORDER BY course_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                SessionExpiry {
+                    captured_at: row.get(1)?,
+                    expires_at: row.get(2)?,
+                },
+            ))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (course_id, expiry) = row?;
+            if let Ok(course_id) = course_id.parse() {
+                out.push((course_id, expiry));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Merge cookies captured for a course into its stored ones, replacing each
+    /// (host, name, path) with the fresher value and leaving other courses untouched.
+    pub fn save_cookies(&self, course_id: u64, cookies: &[ZoomCookie]) -> Result<(), Error> {
+        let mut conn = self.connection();
         let tx = conn.transaction()?;
-        tx.execute("DELETE FROM zoom_cookie", [])?;
         for cookie in cookies {
             tx.execute(
-                "INSERT INTO zoom_cookie(host, name, value, path, expires, secure, http_only, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                "INSERT INTO zoom_cookie(course_id, host, name, value, path, expires, secure, http_only, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(course_id, host, name, path) DO UPDATE SET
+                     value = excluded.value,
+                     expires = excluded.expires,
+                     secure = excluded.secure,
+                     http_only = excluded.http_only,
+                     updated_at = excluded.updated_at
+                 WHERE excluded.updated_at >= zoom_cookie.updated_at",
                 params![
+                    course_id.to_string(),
                     cookie.domain,
                     cookie.name,
                     cookie.value,
@@ -129,40 +285,54 @@ impl ZoomDb {
         Ok(())
     }
 
-    pub fn load_cookies(&self) -> Result<Vec<ZoomCookie>, Box<dyn std::error::Error>> {
-        let mut conn = self.connection()?;
+    /// Unexpired cookies for a course, falling back to cookies stored before they
+    /// were kept per course. Expired cookies are deleted along the way.
+    pub fn load_cookies(&self, course_id: u64) -> Result<Vec<ZoomCookie>, Error> {
+        let mut conn = self.connection();
         let mut stmt = conn.prepare(
-            "SELECT host, name, value, path, expires, secure, http_only FROM zoom_cookie",
+            "SELECT course_id, host, name, value, path, expires, secure, http_only FROM zoom_cookie
+             WHERE course_id IN (?1, '')
+             ORDER BY course_id = ?1 DESC, updated_at DESC",
+        )?;
+        let rows = stmt.query_map(
+            params![course_id.to_string()],
+            |row| -> Result<(String, ZoomCookie), rusqlite::Error> {
+                Ok((
+                    row.get(0)?,
+                    ZoomCookie {
+                        domain: row.get(1)?,
+                        name: row.get(2)?,
+                        value: row.get(3)?,
+                        path: row.get(4)?,
+                        expires: row.get::<_, Option<i64>>(5)?,
+                        secure: row.get::<_, i64>(6)? != 0,
+                        http_only: row.get::<_, i64>(7)? != 0,
+                    },
+                ))
+            },
         )?;
-        let rows = stmt.query_map([], |row| -> Result<ZoomCookie, rusqlite::Error> {
-            Ok(ZoomCookie {
-                domain: row.get(0)?,
-                name: row.get(1)?,
-                value: row.get(2)?,
-                path: row.get(3)?,
-                expires: row.get::<_, Option<i64>>(4)?,
-                secure: row.get::<_, i64>(5)? != 0,
-                http_only: row.get::<_, i64>(6)? != 0,
-            })
-        })?;
 
         let now = Utc::now().timestamp();
-        let mut valid = Vec::new();
+        let mut valid: Vec<ZoomCookie> = Vec::new();
         let mut expired = Vec::new();
 
-        for cookie in rows {
-            let cookie = cookie?;
+        for row in rows {
+            let (scope, cookie) = row?;
             let is_expired = match cookie.expires {
                 Some(ts) if ts > 0 => ts <= now,
                 Some(_) | None => false,
             };
             if is_expired {
                 expired.push((
+                    scope,
                     cookie.domain.clone(),
                     cookie.name.clone(),
                     cookie.path.clone(),
                 ));
-            } else {
+            } else if !valid.iter().any(|v| {
+                // The course's own cookies come first and shadow shared ones
+                v.domain == cookie.domain && v.name == cookie.name && v.path == cookie.path
+            }) {
                 valid.push(cookie);
             }
         }
@@ -170,10 +340,11 @@ impl ZoomDb {
 
         if !expired.is_empty() {
             let tx = conn.transaction()?;
-            for (domain, name, path) in expired {
+            for (scope, domain, name, path) in expired {
                 tx.execute(
-                    "DELETE FROM zoom_cookie WHERE host = ?1 AND name = ?2 AND path = ?3",
-                    params![domain, name, path],
+                    "DELETE FROM zoom_cookie
+                     WHERE course_id = ?1 AND host = ?2 AND name = ?3 AND path = ?4",
+                    params![scope, domain, name, path],
                 )?;
             }
             tx.commit()?;
@@ -182,11 +353,8 @@ impl ZoomDb {
         Ok(valid)
     }
 
-    pub fn delete_all_request_headers(
-        &self,
-        course_id: u64,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.connection()?;
+    pub fn delete_all_request_headers(&self, course_id: u64) -> Result<(), Error> {
+        let conn = self.connection();
         conn.execute(
             "DELETE FROM zoom_request_headers WHERE course_id = ?1",
             params![course_id.to_string()],
@@ -199,8 +367,8 @@ impl ZoomDb {
         course_id: u64,
         request_path: &str,
         headers: &[(String, String)],
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut conn = self.connection()?;
+    ) -> Result<(), Error> {
+        let mut conn = self.connection();
         let tx = conn.transaction()?;
         tx.execute(
             "DELETE FROM zoom_request_headers WHERE course_id = ?1 AND request_path = ?2",
@@ -223,11 +391,8 @@ impl ZoomDb {
         Ok(())
     }
 
-    pub fn get_all_request_headers(
-        &self,
-        course_id: u64,
-    ) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
-        let conn = self.connection()?;
+    pub fn get_all_request_headers(&self, course_id: u64) -> Result<Vec<(String, String)>, Error> {
+        let conn = self.connection();
         let mut stmt = conn.prepare(
             "SELECT header_name, header_value FROM zoom_request_headers WHERE course_id = ?1",
         )?;
@@ -246,8 +411,8 @@ impl ZoomDb {
         &self,
         course_id: u64,
         response: &RecordingListResponse,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut conn = self.connection()?;
+    ) -> Result<(), Error> {
+        let mut conn = self.connection();
         let tx = conn.transaction()?;
         if let Some(result) = &response.result {
             if let Some(list) = &result.list {
@@ -279,8 +444,8 @@ impl ZoomDb {
         _course_id: u64,
         meeting_id: &str,
         files: &[ZoomRecordingFile],
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut conn = self.connection()?;
+    ) -> Result<(), Error> {
+        let mut conn = self.connection();
         let tx = conn.transaction()?;
         tx.execute(
             "DELETE FROM zoom_files WHERE meeting_id = ?1",
@@ -305,4 +470,142 @@ impl ZoomDb {
         tx.commit()?;
         Ok(())
     }
+
+    /// Meetings cached by the last listing for a course, oldest first.
+    pub fn load_meetings(&self, course_id: u64) -> Result<Vec<RecordingSummary>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare("SELECT payload FROM zoom_meetings WHERE course_id = ?1")?;
+        let rows = stmt.query_map(params![course_id.to_string()], |row| {
+            row.get::<_, String>(0)
+        })?;
+        let mut meetings = Vec::new();
+        for payload in rows {
+            meetings.push(serde_json::from_str::<RecordingSummary>(&payload?)?);
+        }
+        meetings.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+        Ok(meetings)
+    }
+
+    /// Recording files cached for a meeting.
+    pub fn load_files(&self, meeting_id: &str) -> Result<Vec<ZoomRecordingFile>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare("SELECT payload FROM zoom_files WHERE meeting_id = ?1")?;
+        let rows = stmt.query_map(params![meeting_id], |row| row.get::<_, String>(0))?;
+        let mut files = Vec::new();
+        for payload in rows {
+            files.push(serde_json::from_str::<ZoomRecordingFile>(&payload?)?);
+        }
+        Ok(files)
+    }
+
+    /// Remember a recording link found in Canvas, keeping any earlier resolution.
+    pub fn save_link(&self, course_id: u64, link: &DiscoveredLink) -> Result<(), Error> {
+        let conn = self.connection();
+        conn.execute(
+            "INSERT INTO zoom_links(course_id, url, label, play_url, meeting_id, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(course_id, url) DO UPDATE SET
+                 label = COALESCE(excluded.label, label),
+                 play_url = COALESCE(excluded.play_url, play_url),
+                 meeting_id = COALESCE(excluded.meeting_id, meeting_id),
+                 updated_at = excluded.updated_at",
+            params![
+                course_id.to_string(),
+                link.url,
+                link.label,
+                link.play_url,
+                link.meeting_id,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Recording links discovered in a course, in discovery order.
+    pub fn load_links(&self, course_id: u64) -> Result<Vec<DiscoveredLink>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare(
+            "SELECT url, label, play_url, meeting_id FROM zoom_links
+             WHERE course_id = ?1 ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map(params![course_id.to_string()], |row| {
+            Ok(DiscoveredLink {
+                url: row.get(0)?,
+                label: row.get(1)?,
+                play_url: row.get(2)?,
+                meeting_id: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Meeting from a Zoom listing whose recording plays at `play_url`.
+    pub fn meeting_for_play_url(&self, play_url: &str) -> Result<Option<String>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare("SELECT meeting_id FROM zoom_files WHERE play_url = ?1")?;
+        let mut rows = stmt.query(params![play_url])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Download record for one recording file, if it was ever attempted.
+    pub fn get_download(
+        &self,
+        meeting_id: &str,
+        file_id: &str,
+    ) -> Result<Option<ZoomDownload>, Error> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare(
+            "SELECT status, path, size, sha1 FROM zoom_downloads
+             WHERE meeting_id = ?1 AND file_id = ?2",
+        )?;
+        let mut rows = stmt.query(params![meeting_id, file_id])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let status: String = row.get(0)?;
+        let path: String = row.get(1)?;
+        let size: Option<i64> = row.get(2)?;
+        Ok(Some(ZoomDownload {
+            meeting_id: meeting_id.to_string(),
+            file_id: file_id.to_string(),
+            status: DownloadStatus::parse(&status),
+            path: path.into(),
+            size: size.map(|s| s as u64),
+            sha1: row.get(3)?,
+        }))
+    }
+
+    /// Point the downloads saved under the folder `from` at the same files under
+    /// `to`, after the folder was moved. Returns how many were updated.
+    pub fn relocate_downloads(&self, from: &Path, to: &Path) -> Result<usize, Error> {
+        let conn = self.connection();
+        let prefix = format!("{}{}", from.to_string_lossy(), std::path::MAIN_SEPARATOR);
+        let moved = conn.execute(
+            "UPDATE zoom_downloads SET path = ?2 || substr(path, length(?1))
+             WHERE substr(path, 1, length(?1)) = ?1",
+            params![prefix, to.to_string_lossy()],
+        )?;
+        Ok(moved)
+    }
+
+    pub fn save_download(&self, download: &ZoomDownload) -> Result<(), Error> {
+        let conn = self.connection();
+        conn.execute(
+            "REPLACE INTO zoom_downloads(meeting_id, file_id, status, path, size, sha1, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                download.meeting_id,
+                download.file_id,
+                download.status.as_str(),
+                download.path.to_string_lossy(),
+                download.size.map(|s| s as i64),
+                download.sha1,
+                Utc::now().timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
 }
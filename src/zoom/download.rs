@@ -1,17 +1,28 @@
 use crate::config::Config;
+use crate::error::Error;
+use crate::{report, report_verbose, report_warning, tr};
 
+use crate::http::{
+    clear_resume_validator, discard_partial, load_resume_validator, resume_plan, resume_validator,
+    save_resume_validator, RangedDownload,
+};
 use crate::zoom::models::ReplayHeader;
 use futures_util::StreamExt;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue, RANGE};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, IF_RANGE, RANGE};
+use reqwest::StatusCode;
 use std::path::{Path, PathBuf};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
+/// Download `url` to `dest` with the captured `headers`, resuming a partial
+/// `.part` file when the server still serves the same content. A refused signed
+/// URL or cookies (HTTP 401/403) is reported as [`Error::Auth`].
 pub async fn http_download(
+    cfg: &Config,
     headers: &[(String, String)],
     url: &str,
     dest: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let client = reqwest::Client::builder()
+) -> Result<(), Error> {
+    let client = crate::http::with_proxy(reqwest::Client::builder(), cfg)
         .redirect(reqwest::redirect::Policy::limited(5))
         .build()?;
 
@@ -24,10 +35,18 @@ pub async fn http_download(
     if let Ok(meta) = tokio::fs::metadata(&tmp).await {
         resume_from = meta.len();
     }
+    let if_range = match resume_from {
+        0 => None,
+        _ => load_resume_validator(&tmp).await,
+    };
+    if resume_from > 0 && if_range.is_none() {
+        discard_partial(&tmp).await?;
+        resume_from = 0;
+    }
 
     let mut header_map = HeaderMap::new();
     for (name, value) in headers {
-        if name.eq_ignore_ascii_case("range") {
+        if name.eq_ignore_ascii_case("range") || name.eq_ignore_ascii_case("if-range") {
             continue;
         }
         if let Ok(header_name) = HeaderName::from_bytes(name.as_bytes()) {
@@ -38,15 +57,16 @@ pub async fn http_download(
             }
         }
     }
-    if resume_from > 0 {
+    if let (true, Some(validator)) = (resume_from > 0, if_range.as_deref()) {
         header_map.insert(
             RANGE,
             HeaderValue::from_str(&format!("bytes={}-", resume_from))?,
         );
+        header_map.insert(IF_RANGE, HeaderValue::from_str(validator)?);
     }
 
-    // DEBUG: Log all headers being sent
-    println!("HTTP download {} with {} headers:", url, header_map.len());
+    // Shown with -v
+    report_verbose!("HTTP download {} with {} headers:", url, header_map.len());
     for (k, v) in header_map.iter() {
         let val_str = v.to_str().unwrap_or("<binary>");
         let display_val = if val_str.len() > 100 {
@@ -54,20 +74,83 @@ pub async fn http_download(
         } else {
             val_str.to_string()
         };
-        println!("  {}: {}", k, display_val);
+        report_verbose!("  {}: {}", k, display_val);
     }
 
-    let mut request = client.get(url);
-    request = request.headers(header_map);
+    let request = client.get(url).headers(header_map);
+    let ranged = request.try_clone();
 
     let response = request.send().await?;
+    if matches!(
+        response.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        // The signed URL or its cookies expired; callers capture a fresh one
+        return Err(Error::Auth(tr!(
+            "HTTP {} while downloading {}",
+            response.status(),
+            url
+        )));
+    }
     if !(response.status().is_success() || response.status().as_u16() == 206) {
-        return Err(format!("HTTP {} while downloading {}", response.status(), url).into());
+        return Err(tr!("HTTP {} while downloading {}", response.status(), url).into());
+    }
+    // A sign-in page served with 200 must not be saved as the recording
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/html"));
+    if is_html {
+        return Err(tr!("{} returned a web page instead of the file", url).into());
+    }
+    let plan = match resume_plan(&response, resume_from) {
+        Ok(plan) => plan,
+        Err(e) => {
+            discard_partial(&tmp).await?;
+            return Err(e.into());
+        }
+    };
+    if resume_from > 0 && plan.offset == 0 {
+        report!(
+            "{}",
+            tr!("Remote recording changed since the partial download; restarting")
+        );
     }
+    resume_from = plan.offset;
+
+    // Multi-GB recordings download much faster as concurrent byte ranges
+    let ctx = crate::http::HttpCtx::new(cfg, client);
+    let (response, total) = match (
+        resume_from,
+        ctx.range_chunks_for(&response),
+        resume_validator(&response),
+        ranged,
+    ) {
+        (0, Some(chunks), Some(validator), Some(base)) => {
+            let total = response.content_length().unwrap_or_default();
+            drop(response);
+            match ctx
+                .download_ranges(base, &tmp, dest, &validator, total, chunks)
+                .await?
+            {
+                RangedDownload::Complete(_) => return Ok(()),
+                RangedDownload::Changed(full) => {
+                    report!(
+                        "{}",
+                        tr!("Remote recording changed during the download; restarting")
+                    );
+                    let total = full.content_length();
+                    (full, total)
+                }
+            }
+        }
+        _ => (response, plan.total),
+    };
 
     let mut file = tokio::fs::OpenOptions::new()
         .create(true)
-        .truncate(true)
+        .truncate(false)
         .write(true)
         .open(&tmp)
         .await?;
@@ -75,23 +158,44 @@ pub async fn http_download(
         file.seek(std::io::SeekFrom::Start(resume_from)).await?;
     } else {
         file.set_len(0).await?;
+        save_resume_validator(&tmp, &response).await?;
     }
 
+    let limiter = crate::http::BandwidthLimiter::from_config(cfg);
+    let mut written = 0u64;
     let mut stream = response.bytes_stream();
     while let Some(chunk) = stream.next().await {
         let data = chunk?;
+        if let Some(limiter) = &limiter {
+            limiter.consume(data.len()).await;
+        }
         file.write_all(&data).await?;
+        written += data.len() as u64;
     }
     file.flush().await?;
     file.sync_data().await?;
     drop(file);
 
+    if let Some(total) = total {
+        if resume_from + written != total {
+            discard_partial(&tmp).await?;
+            return Err(tr!(
+                "size mismatch for {}: got {} of {} bytes",
+                url,
+                resume_from + written,
+                total
+            )
+            .into());
+        }
+    }
     tokio::fs::rename(&tmp, dest).await?;
+    clear_resume_validator(&tmp).await?;
     Ok(())
 }
 
 fn temp_path(dest: &Path) -> PathBuf {
-    dest.with_extension("mp4.part")
+    let ext = dest.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    dest.with_extension(format!("{}.part", ext))
 }
 
 pub fn build_ffmpeg_headers(
@@ -130,7 +234,10 @@ pub fn build_ffmpeg_headers(
 
         headers.push(("Cookie".to_string(), cookie_header));
     } else {
-        println!("⚠ Warning: No cookies found for domain {}", domain);
+        report_warning!(
+            "{}",
+            tr!("⚠ Warning: No cookies found for domain {}", domain)
+        );
     }
 
     headers
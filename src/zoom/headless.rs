@@ -1,18 +1,81 @@
 use crate::config::Config;
+use crate::error::Error;
+use crate::fsutil::{atomic_write, hash_file, sanitize_filename_preserve_ext};
+use crate::progress::{download_bar, multi_progress};
+use crate::state::{CourseDirs, State};
+use crate::weeks::{leading_date, week_name, week_of_date};
 use crate::zoom::db::ZoomDb;
-use crate::zoom::models::{ZoomCookie, ZoomRecordingFile};
+use crate::zoom::models::{
+    select_views, session_expiry, DownloadStatus, MeetingSidecar, ZoomCookie, ZoomDownload,
+    ZoomRecordingFile,
+};
+use crate::{report, report_verbose, report_warning, tr};
 use base64::prelude::*;
 use chromiumoxide::browser::{Browser, BrowserConfig};
 use chromiumoxide::cdp::browser_protocol::network::EventRequestWillBeSent;
+use chromiumoxide::element::Element;
 use chromiumoxide::Page;
+use chrono::NaiveDate;
 use futures::StreamExt;
+use indicatif::ProgressBar;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time::sleep;
 use url::Url;
 
+/// Background tasks tied to one browser session. Dropping the guard aborts
+/// them, so early returns don't leave CDP loops and event listeners running.
+struct BrowserTasks {
+    handler: Option<JoinHandle<()>>,
+    listeners: Vec<JoinHandle<()>>,
+}
+
+impl BrowserTasks {
+    fn new(handler: JoinHandle<()>) -> Self {
+        Self {
+            handler: Some(handler),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Stop the event listeners and wait for the CDP handler to exit; call
+    /// after `Browser::close`.
+    async fn shutdown(mut self) -> Result<(), tokio::task::JoinError> {
+        for listener in self.listeners.drain(..) {
+            listener.abort();
+        }
+        match self.handler.take() {
+            Some(handler) => handler.await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for BrowserTasks {
+    fn drop(&mut self) {
+        for listener in &self.listeners {
+            listener.abort();
+        }
+        if let Some(handler) = &self.handler {
+            handler.abort();
+        }
+    }
+}
+
+/// What the SharePoint player revealed about one video link.
+#[derive(Debug, Clone, Default)]
+pub struct SharePointCapture {
+    /// Page title, usually the video's file name.
+    pub title: Option<String>,
+    /// DASH manifest requested by the player, readable by ffmpeg.
+    pub manifest: Option<String>,
+}
+
 pub struct ZoomHeadless<'a> {
     config: &'a Config,
     db: &'a ZoomDb,
@@ -28,40 +91,256 @@ impl<'a> ZoomHeadless<'a> {
         }
     }
 
-    pub async fn authenticate_and_capture(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let (mut browser, mut handler) = Browser::launch(
-            BrowserConfig::builder()
-                // .with_head()
-                // Running in full headless mode (no GUI)
-                // Let's try headless first, but maybe provide an option?
-                // The user said "headless browser", so let's stick to headless unless debugging.
-                // Actually, for SSO, sometimes headful is required if there are captchas or complex interactions,
-                // but standard Azure AD usually works in headless if user agent is set correctly.
-                // Let's use the config user agent.
-                .arg("--no-sandbox")
-                .arg("--disable-gpu")
-                .arg("--disable-dev-shm-usage")
-                .build()?,
-        )
-        .await?;
+    pub async fn authenticate_and_capture(&self) -> Result<(), Error> {
+        let (mut browser, mut tasks, page) = self.launch_browser().await?;
+        self.capture_session(&page, &mut tasks, &self.tool_url()?, true)
+            .await?;
+
+        browser.close().await?;
+        tasks.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Capture the session by opening a Canvas sessionless launch URL, which
+    /// reaches the Zoom LTI without any Canvas or Microsoft sign-in.
+    pub async fn capture_from_launch(&self, launch_url: &str) -> Result<(), Error> {
+        let (mut browser, mut tasks, page) = self.launch_browser().await?;
+        let result = self
+            .capture_session(&page, &mut tasks, launch_url, false)
+            .await;
+        browser.close().await?;
+        tasks.shutdown().await?;
+        result
+    }
+
+    /// Open a Canvas sessionless launch URL for another LTI tool, signing in through
+    /// SSO if the tool asks, and return the tool's final URL (with its fragment)
+    /// and the cookies it set on hosts containing `domain`.
+    pub async fn capture_tool_launch(
+        &self,
+        launch_url: &str,
+        domain: &str,
+    ) -> Result<(String, Vec<ZoomCookie>), Error> {
+        let (mut browser, tasks, page) = self.launch_browser().await?;
+        let result = self.open_tool(&page, launch_url, domain).await;
+        browser.close().await?;
+        tasks.shutdown().await?;
+        result
+    }
+
+    async fn open_tool(
+        &self,
+        page: &Page,
+        launch_url: &str,
+        domain: &str,
+    ) -> Result<(String, Vec<ZoomCookie>), Error> {
+        let sso = &self.config.zoom.sso;
+        page.goto(launch_url).await?;
+        self.handle_sso(page).await?;
+        let timeout = Duration::from_secs(sso.redirect_timeout_secs);
+        if wait_for_url(page, timeout, |u| u.contains(domain))
+            .await
+            .is_none()
+        {
+            save_diagnostics(page, "tool-launch").await;
+            return Err(tr!("LTI launch never reached {}", domain).into());
+        }
+        wait_for_load(page, Duration::from_secs(sso.redirect_wait_secs)).await;
+        // page.url() drops the fragment, where tools like Panopto keep the folder
+        let url = page
+            .evaluate("location.href")
+            .await?
+            .into_value::<String>()?;
+        let cookies = page
+            .get_cookies()
+            .await?
+            .into_iter()
+            .filter(|c| c.domain.contains(domain))
+            .map(|c| ZoomCookie {
+                domain: c.domain,
+                name: c.name,
+                value: c.value,
+                path: c.path,
+                expires: Some(c.expires as i64),
+                secure: c.secure,
+                http_only: c.http_only,
+            })
+            .collect();
+        Ok((url, cookies))
+    }
+
+    /// Open SharePoint / Stream video links in one browser session, signing in
+    /// through Microsoft SSO when asked. Returns the SharePoint cookies and, per
+    /// link, the player's page title and the video manifest it requested.
+    pub async fn capture_sharepoint_videos(
+        &self,
+        links: &[String],
+    ) -> Result<(Vec<ZoomCookie>, Vec<SharePointCapture>), Error> {
+        let (mut browser, tasks, page) = self.launch_browser().await?;
+        let result = self.open_sharepoint_links(&page, links).await;
+        browser.close().await?;
+        tasks.shutdown().await?;
+        result
+    }
+
+    async fn open_sharepoint_links(
+        &self,
+        page: &Page,
+        links: &[String],
+    ) -> Result<(Vec<ZoomCookie>, Vec<SharePointCapture>), Error> {
+        let timeout = Duration::from_secs(self.config.zoom.sso.scid_timeout_secs);
+        let mut cookies: HashMap<(String, String, String), ZoomCookie> = HashMap::new();
+        let mut captures = Vec::with_capacity(links.len());
+        for link in links {
+            let mut events = page.event_listener::<EventRequestWillBeSent>().await?;
+            page.goto(link.as_str()).await?;
+            self.handle_sso(page).await?;
+            let manifest = tokio::time::timeout(timeout, async {
+                while let Some(event) = events.next().await {
+                    if event.request.url.contains("videomanifest") {
+                        return Some(event.request.url.clone());
+                    }
+                }
+                None
+            })
+            .await
+            .ok()
+            .flatten();
+            if manifest.is_none() {
+                report!("{}", tr!("⚠ No video player found at {}", link));
+                save_diagnostics(page, "sharepoint-video").await;
+            }
+            let title = page
+                .evaluate("document.title")
+                .await
+                .ok()
+                .and_then(|r| r.into_value::<String>().ok())
+                .filter(|t| !t.trim().is_empty());
+            // FedAuth cookies are per host (tenant and tenant-my), so collect after every link
+            for c in page.get_cookies().await? {
+                if !c.domain.contains("sharepoint.com") {
+                    continue;
+                }
+                cookies.insert(
+                    (c.domain.clone(), c.name.clone(), c.path.clone()),
+                    ZoomCookie {
+                        domain: c.domain,
+                        name: c.name,
+                        value: c.value,
+                        path: c.path,
+                        expires: Some(c.expires as i64),
+                        secure: c.secure,
+                        http_only: c.http_only,
+                    },
+                );
+            }
+            captures.push(SharePointCapture { title, manifest });
+        }
+        Ok((cookies.into_values().collect(), captures))
+    }
+
+    /// Start a headless Chromium with the configured proxy and user agent.
+    async fn launch_browser(&self) -> Result<(Browser, BrowserTasks, Page), Error> {
+        let mut browser_config = BrowserConfig::builder()
+            // .with_head()
+            // Running in full headless mode (no GUI)
+            // Let's try headless first, but maybe provide an option?
+            // The user said "headless browser", so let's stick to headless unless debugging.
+            // Actually, for SSO, sometimes headful is required if there are captchas or complex interactions,
+            // but standard Azure AD usually works in headless if user agent is set correctly.
+            // Let's use the config user agent.
+            .arg("--no-sandbox")
+            .arg("--disable-gpu")
+            .arg("--disable-dev-shm-usage");
+        if let Some(proxy) = self.config.browser_proxy() {
+            browser_config = browser_config.arg(format!("--proxy-server={}", proxy));
+        }
+        let (browser, mut handler) = Browser::launch(browser_config.build()?).await?;
 
         let handle = tokio::spawn(async move {
             while let Some(h) = handler.next().await {
                 if let Err(e) = h {
-                    eprintln!("Browser handler error: {:?}", e);
+                    report_warning!("{}", tr!("Browser handler error: {}", format!("{:?}", e)));
                     break;
                 }
             }
-            println!("Browser handler loop exited.");
+            report!("{}", tr!("Browser handler loop exited."));
         });
+        let tasks = BrowserTasks::new(handle);
 
         let page = browser.new_page("about:blank").await?;
         page.set_user_agent(&self.config.zoom.user_agent).await?;
+        Ok((browser, tasks, page))
+    }
+
+    /// Canvas page embedding the course's Zoom LTI.
+    fn tool_url(&self) -> Result<String, Error> {
+        let tool_id = self
+            .db
+            .get_tool_id(self.course_id)?
+            .unwrap_or(self.config.zoom.external_tool_id);
+        Ok(format!(
+            "{}/courses/{}/external_tools/{}",
+            self.config.canvas.base_url, self.course_id, tool_id
+        ))
+    }
+
+    /// Capture `lti_scid`, Zoom API headers and cookies for the course in a
+    /// Chrome that is already running with `--remote-debugging-port`, reusing
+    /// the user's existing Canvas/Microsoft session. The browser is left open;
+    /// the capture tab is closed unless `keep_tab` is set.
+    pub async fn capture_from_running_browser(
+        &self,
+        debug_port: u16,
+        keep_tab: bool,
+    ) -> Result<(), Error> {
+        let endpoint = format!("http://127.0.0.1:{}", debug_port);
+        let (browser, mut handler) = Browser::connect(endpoint.as_str()).await.map_err(|e| {
+            tr!(
+                "could not connect to Chrome at {} ({}); start it with --remote-debugging-port={}",
+                endpoint,
+                e,
+                debug_port
+            )
+        })?;
+        let handle = tokio::spawn(async move {
+            while let Some(h) = handler.next().await {
+                if h.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut tasks = BrowserTasks::new(handle);
+
+        let page = browser.new_page("about:blank").await?;
+        let result = match self.tool_url() {
+            Ok(url) => self.capture_session(&page, &mut tasks, &url, true).await,
+            Err(e) => Err(e),
+        };
+        if !keep_tab {
+            let _ = page.close().await;
+        }
+        // Dropping the tasks only detaches from the user's browser; it keeps running
+        drop(tasks);
+        result
+    }
+
+    /// Open the course's Zoom LTI at `target_url` in `page`, signing in first when
+    /// `sign_in` is set, and store the captured session in the DB.
+    async fn capture_session(
+        &self,
+        page: &Page,
+        tasks: &mut BrowserTasks,
+        target_url: &str,
+        sign_in: bool,
+    ) -> Result<(), Error> {
+        let sso = &self.config.zoom.sso;
 
         // Enable network events
         // Check if we already have scid in DB
         if let Ok(Some(stored_scid)) = self.db.get_scid(self.course_id) {
-            println!("Found existing lti_scid in DB: {}", stored_scid);
+            report!("{}", tr!("Found existing lti_scid in DB: {}", stored_scid));
             // We still proceed to refresh cookies and verify scid
         }
 
@@ -109,7 +388,7 @@ impl<'a> ZoomHeadless<'a> {
         let re_xsrf = Regex::new(r#"(?i)['"]?x-xsrf-token['"]?\s*:\s*['"]([^'"]+)['"]"#).unwrap();
 
         // Spawn Fetch interception task
-        tokio::spawn(async move {
+        tasks.listeners.push(tokio::spawn(async move {
             while let Some(event) = request_paused_events.next().await {
                 let req_id = event.request_id.clone();
                 // Always continue the request eventually
@@ -119,7 +398,7 @@ impl<'a> ZoomHeadless<'a> {
                 // We only care if we have a response status code (response stage)
                 if event.response_status_code.is_some() {
                     // let url = event.request.url.clone();
-                    // println!("Fetch Interception: Response Paused for {}", url);
+                    // report!("Fetch Interception: Response Paused for {}", url);
 
                     // Get body
                     match page_inner
@@ -160,7 +439,7 @@ impl<'a> ZoomHeadless<'a> {
 
                             // Extract window.appConf
                             if let Some(idx) = content.find("window.appConf") {
-                                // println!("Found window.appConf in intercepted body!");
+                                // report!("Found window.appConf in intercepted body!");
                                 // Extract a chunk to parse
                                 // Extract a chunk to parse
                                 let start = idx;
@@ -169,8 +448,8 @@ impl<'a> ZoomHeadless<'a> {
                                 let chunk = &content[start..end];
 
                                 // Debug log to verify we are seeing the right content
-                                println!(
-                                    "DEBUG appConf chunk (first 500 chars):\n{}",
+                                report_verbose!(
+                                    "appConf chunk (first 500 chars):\n{}",
                                     &chunk[..chunk.len().min(500)]
                                 );
 
@@ -180,7 +459,7 @@ impl<'a> ZoomHeadless<'a> {
                                 if let Some(caps) = re_scid.captures(chunk) {
                                     if let Some(val) = caps.get(1) {
                                         let s = val.as_str().to_string();
-                                        println!("Captured lti_scid from Fetch: {}", s);
+                                        report!("{}", tr!("Captured lti_scid from Fetch: {}", s));
                                         let mut data = captured_data_clone.lock().unwrap();
                                         data.0 = Some(s);
                                     }
@@ -217,9 +496,12 @@ impl<'a> ZoomHeadless<'a> {
                                         }
 
                                         if !headers.is_empty() {
-                                            println!(
-                                                "Captured {} ajaxHeaders from Fetch (array format)",
-                                                headers.len()
+                                            report!(
+                                                "{}",
+                                                tr!(
+                                                    "Captured {} ajaxHeaders from Fetch (array format)",
+                                                    headers.len(),
+                                                )
                                             );
                                             let mut data = captured_data_clone.lock().unwrap();
                                             if data.1.is_none() {
@@ -252,9 +534,9 @@ impl<'a> ZoomHeadless<'a> {
                                                 "x-xsrf-token".to_string(),
                                                 val.as_str().to_string(),
                                             );
-                                            println!(
-                                                "Explicitly captured x-xsrf-token: {}",
-                                                val.as_str()
+                                            report!(
+                                                "{}",
+                                                tr!("Captured x-xsrf-token from the page config")
                                             );
                                         }
                                     }
@@ -262,7 +544,13 @@ impl<'a> ZoomHeadless<'a> {
                             }
                         }
                         Err(e) => {
-                            println!("Failed to get body in Fetch interception: {:?}", e);
+                            report!(
+                                "{}",
+                                tr!(
+                                    "Failed to get body in Fetch interception: {}",
+                                    format!("{:?}", e),
+                                )
+                            );
                         }
                     }
                 }
@@ -276,9 +564,9 @@ impl<'a> ZoomHeadless<'a> {
                     )
                     .await;
             }
-        });
+        }));
 
-        let _capture_task = tokio::spawn(async move {
+        tasks.listeners.push(tokio::spawn(async move {
             while let Some(event) = request_events.next().await {
                 let url = event.request.url.clone();
                 let mut data = captured_data_clone_for_fetch.lock().unwrap();
@@ -287,7 +575,7 @@ impl<'a> ZoomHeadless<'a> {
                     if let Ok(parsed) = Url::parse(&url) {
                         for (k, v) in parsed.query_pairs() {
                             if k == "lti_scid" {
-                                println!("Captured lti_scid from URL: {}", v);
+                                report!("{}", tr!("Captured lti_scid from URL: {}", v));
                                 data.0 = Some(v.to_string());
                             }
                         }
@@ -306,32 +594,32 @@ impl<'a> ZoomHeadless<'a> {
                             }
                         }
                     }
-                    println!("Captured Zoom API headers");
+                    report!("{}", tr!("Captured Zoom API headers"));
                     data.1 = Some(headers);
                 }
             }
-        });
+        }));
 
-        let target_url = format!(
-            "{}/courses/{}/external_tools/{}",
-            self.config.canvas.base_url, self.course_id, self.config.zoom.external_tool_id
-        );
-
-        println!("Navigating to: {}", target_url);
-        page.goto(&target_url).await?;
+        report!("{}", tr!("Navigating to: {}", target_url));
+        page.goto(target_url).await?;
 
         // Handle SSO
-        self.handle_sso(&page).await?;
+        if sign_in {
+            if let Err(e) = self.handle_sso(page).await {
+                save_diagnostics(page, "canvas-sso").await;
+                return Err(e);
+            }
+        }
 
         // Wait for Zoom LTI to load and capture data
-        println!("Waiting for Zoom LTI to load...");
+        report!("{}", tr!("Waiting for Zoom LTI to load..."));
 
         let mut scid = None;
         let mut captured_headers: HashMap<String, String> = HashMap::new();
 
         // Wait up to 60 seconds for the LTI load
         let start = std::time::Instant::now();
-        while start.elapsed() < Duration::from_secs(60) {
+        while start.elapsed() < Duration::from_secs(sso.scid_timeout_secs) {
             // Check shared state
             {
                 let data = captured_data.lock().unwrap();
@@ -381,8 +669,9 @@ impl<'a> ZoomHeadless<'a> {
 
         if let Some(s) = scid {
             self.db.save_scid(self.course_id, &s)?;
-            println!("Saved lti_scid to DB: {}", s);
+            report!("{}", tr!("Saved lti_scid to DB: {}", s));
         } else {
+            save_diagnostics(page, "lti-scid").await;
             return Err("Failed to capture lti_scid".into());
         }
 
@@ -397,84 +686,114 @@ impl<'a> ZoomHeadless<'a> {
 
             // Log keys to verify we have x-xsrf-token
             let keys: Vec<String> = header_list.iter().map(|(k, _)| k.clone()).collect();
-            println!("Saving headers: {:?}", keys);
+            report!("{}", tr!("Saving headers: {}", format!("{:?}", keys)));
 
             self.db.save_request_headers(
                 self.course_id,
                 "/api/v1/lti/rich/recording",
                 &header_list,
             )?;
-            println!("Saved {} request headers to DB", captured_headers.len());
+            report!(
+                "{}",
+                tr!("Saved {} request headers to DB", captured_headers.len())
+            );
         } else {
-            println!("Warning: No request headers captured");
+            report!("{}", tr!("Warning: No request headers captured"));
         }
 
         if !cookies.is_empty() {
-            self.db.replace_cookies(&cookies)?;
+            self.db.save_cookies(self.course_id, &cookies)?;
+            self.db
+                .save_session_expiry(self.course_id, session_expiry(&cookies))?;
         } else {
+            save_diagnostics(page, "zoom-cookies").await;
             return Err("Failed to capture Zoom cookies".into());
         }
 
         // Verification log
         let scid_after = self.db.get_scid(self.course_id)?;
-        let cookies_after = self.db.load_cookies()?;
+        let cookies_after = self.db.load_cookies(self.course_id)?;
         let headers_after = self.db.get_all_request_headers(self.course_id)?;
 
-        println!(
-            "AFTER HEADLESS SAVE -> scid={:?}, cookies={}, headers={}",
-            scid_after,
-            cookies_after.len(),
-            headers_after.len()
+        report!(
+            "{}",
+            tr!(
+                "AFTER HEADLESS SAVE -> scid={}, cookies={}, headers={}",
+                format!("{:?}", scid_after),
+                cookies_after.len(),
+                headers_after.len()
+            )
         );
 
-        browser.close().await?;
-        handle.await?;
-
         Ok(())
     }
 
-    async fn handle_sso(&self, page: &Page) -> Result<(), Box<dyn std::error::Error>> {
+    async fn handle_sso(&self, page: &Page) -> Result<(), Error> {
+        let sso = &self.config.zoom.sso;
         // Simple heuristic for Microsoft SSO
         // 1. Check for email input
         // 2. Check for password input
         // 3. Check for "Stay signed in"
 
-        println!("Checking for SSO login...");
+        report!("{}", tr!("Checking for SSO login..."));
 
-        // Wait a bit for redirects
-        sleep(Duration::from_secs(5)).await;
+        // Let the Canvas redirects finish loading
+        wait_for_load(page, Duration::from_secs(sso.redirect_wait_secs)).await;
 
         let mut url = page.url().await?.unwrap_or_default();
 
         // Handle Canvas Login Page (Pre-SSO)
         if url.contains("/login/canvas") {
-            println!("Detected Canvas login page. Attempting to initiate SSO...");
-            // Find the "ESTUDIANTES Y DOCENTES" button
-            let buttons = page.find_elements(".ic-Login__body button").await?;
+            report!(
+                "{}",
+                tr!("Detected Canvas login page. Attempting to initiate SSO...")
+            );
+            // Find the institution's SSO button among the configured candidates
+            let deadline = Instant::now() + Duration::from_secs(sso.button_timeout_secs);
             let mut clicked = false;
-            for button in buttons {
-                if let Ok(Some(text)) = button.inner_text().await {
-                    if text.to_uppercase().contains("ESTUDIANTES Y DOCENTES") {
-                        println!("Found SSO initiation button. Clicking...");
-                        button.click().await?;
-                        clicked = true;
-                        sleep(Duration::from_secs(5)).await; // Wait for redirect
-                        url = page.url().await?.unwrap_or_default(); // Update URL
-                        break;
+            'poll: while Instant::now() < deadline {
+                for selector in &sso.login_button_selectors {
+                    let Ok(buttons) = page.find_elements(selector.as_str()).await else {
+                        continue;
+                    };
+                    for button in buttons {
+                        let text = button.inner_text().await.ok().flatten().unwrap_or_default();
+                        if login_button_matches(&text, &sso.login_button_texts) {
+                            report!("{}", tr!("Found SSO initiation button. Clicking..."));
+                            button.click().await?;
+                            clicked = true;
+                            break 'poll;
+                        }
                     }
                 }
+                sleep(POLL_INTERVAL).await;
             }
-            if !clicked {
-                println!(
-                    "Warning: Could not find 'ESTUDIANTES Y DOCENTES' button on Canvas login page."
+            if clicked {
+                let timeout = Duration::from_secs(sso.redirect_timeout_secs);
+                if let Some(current) =
+                    wait_for_url(page, timeout, |u| !u.contains("/login/canvas")).await
+                {
+                    url = current;
+                    wait_for_load(page, Duration::from_secs(sso.redirect_wait_secs)).await;
+                }
+            } else {
+                report!(
+                    "{}",
+                    tr!(
+                        "Warning: Could not find an SSO button matching {} on the Canvas login page (see zoom.sso.login_button_texts).",
+                        format!("{:?}", sso.login_button_texts),
+                    )
                 );
             }
         }
 
         if !url.contains("login.microsoftonline.com") {
-            println!(
-                "Not on Microsoft SSO page (URL: {}), assuming already logged in or not required.",
-                url
+            report!(
+                "{}",
+                tr!(
+                    "Not on Microsoft SSO page (URL: {}), assuming already logged in or not required.",
+                    url,
+                )
             );
             return Ok(());
         }
@@ -483,14 +802,21 @@ impl<'a> ZoomHeadless<'a> {
         Ok(())
     }
 
-    async fn handle_microsoft_sso(&self, page: &Page) -> Result<(), Box<dyn std::error::Error>> {
-        println!("Handling Microsoft SSO...");
+    async fn handle_microsoft_sso(&self, page: &Page) -> Result<(), Error> {
+        report!("{}", tr!("Handling Microsoft SSO..."));
         self.handle_ms_account(page).await
     }
 
-    async fn handle_ms_account(&self, page: &Page) -> Result<(), Box<dyn std::error::Error>> {
+    async fn handle_ms_account(&self, page: &Page) -> Result<(), Error> {
+        let sso = &self.config.zoom.sso;
+        let step_timeout = Duration::from_secs(sso.button_timeout_secs);
         // First, check for remembered account tiles (account picker)
-        sleep(Duration::from_secs(2)).await;
+        wait_for_selector(
+            page,
+            &[EMAIL_INPUTS, PASSWORD_INPUTS, ".table[role='button']"],
+            step_timeout,
+        )
+        .await;
 
         let email_input_present = page.find_element("input[type='email']").await.is_ok()
             || page.find_element("input[name='loginfmt']").await.is_ok();
@@ -553,11 +879,19 @@ impl<'a> ZoomHeadless<'a> {
                     };
 
                     if let Some(idx) = selected_idx {
-                        println!("Found remembered account tile, clicking...");
+                        report!("{}", tr!("Found remembered account tile, clicking..."));
                         if let Err(e) = tiles[idx].click().await {
-                            println!("Warning: Failed to click account tile: {:?}", e);
+                            report!(
+                                "{}",
+                                tr!(
+                                    "Warning: Failed to click account tile: {}",
+                                    format!("{:?}", e)
+                                )
+                            );
                         } else {
-                            sleep(Duration::from_secs(3)).await;
+                            // The picker leads to either the password or the email form
+                            wait_for_selector(page, &[EMAIL_INPUTS, PASSWORD_INPUTS], step_timeout)
+                                .await;
                         }
                     }
                 }
@@ -566,75 +900,63 @@ impl<'a> ZoomHeadless<'a> {
 
         // Fallback: manual credential entry
         if let Some(email) = &self.config.canvas.sso_email {
-            println!("Attempting to enter email...");
-            // Selector for email input. Usually 'input[type="email"]' or 'input[name="loginfmt"]'
-            if let Ok(input) = page.find_element("input[type='email']").await {
-                input.click().await?.type_str(email).await?;
-                if let Ok(button) = page.find_element("input[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("button[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("#idSIButton9").await {
-                    button.click().await?;
-                }
-                sleep(Duration::from_secs(2)).await;
-            } else if let Ok(input) = page.find_element("input[name='loginfmt']").await {
-                input.click().await?.type_str(email).await?;
-                if let Ok(button) = page.find_element("input[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("button[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("#idSIButton9").await {
-                    button.click().await?;
-                }
-                sleep(Duration::from_secs(2)).await;
+            report!("{}", tr!("Attempting to enter email..."));
+            if submit_field(page, EMAIL_INPUTS, email).await? {
+                // Microsoft swaps the form in place, so wait for the password field
+                wait_for_selector(page, &[PASSWORD_INPUTS], step_timeout).await;
             }
         } else {
-            println!("Warning: sso_email not set; skipping email entry.");
+            report!(
+                "{}",
+                tr!("Warning: sso_email not set; skipping email entry.")
+            );
         }
 
         if let Some(password) = &self.config.canvas.sso_password {
-            println!("Attempting to enter password...");
-            // Selector for password input. 'input[type="password"]' or 'input[name="passwd"]'
-            if let Ok(input) = page.find_element("input[type='password']").await {
-                input.click().await?.type_str(password).await?;
-                if let Ok(button) = page.find_element("input[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("button[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("#idSIButton9").await {
-                    button.click().await?;
-                }
-                sleep(Duration::from_secs(2)).await;
-            } else if let Ok(input) = page.find_element("input[name='passwd']").await {
-                input.click().await?.type_str(password).await?;
-                if let Ok(button) = page.find_element("input[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("button[type='submit']").await {
-                    button.click().await?;
-                } else if let Ok(button) = page.find_element("#idSIButton9").await {
-                    button.click().await?;
+            report!("{}", tr!("Attempting to enter password..."));
+            if submit_field(page, PASSWORD_INPUTS, password).await? {
+                // Either the "Stay signed in?" prompt or a redirect away from Microsoft
+                let deadline = Instant::now() + Duration::from_secs(sso.redirect_timeout_secs);
+                while Instant::now() < deadline {
+                    let url = page.url().await?.unwrap_or_default();
+                    if !url.contains("login.microsoftonline.com")
+                        || page.content().await?.contains("Stay signed in?")
+                    {
+                        break;
+                    }
+                    sleep(POLL_INTERVAL).await;
                 }
-                sleep(Duration::from_secs(2)).await;
             }
         } else {
-            println!("Warning: sso_password not set; skipping password entry.");
+            report!(
+                "{}",
+                tr!("Warning: sso_password not set; skipping password entry.")
+            );
         }
 
         // "Stay signed in?" - usually has a "Yes" button (input[type="submit"] or button)
         if page.content().await?.contains("Stay signed in?") {
-            println!("Handling 'Stay signed in' prompt...");
+            report!("{}", tr!("Handling 'Stay signed in' prompt..."));
             // The "Yes" button often has id "idSIButton9"
             if page.find_element("#idSIButton9").await.is_ok() {
                 page.find_element("#idSIButton9").await?.click().await?;
             }
         }
 
-        sleep(Duration::from_secs(5)).await;
+        // Let the identity provider redirect back before the caller inspects the page
+        let post_login = Duration::from_secs(sso.post_login_wait_secs);
+        if wait_for_url(page, post_login, |u| {
+            !u.contains("login.microsoftonline.com")
+        })
+        .await
+        .is_some()
+        {
+            wait_for_load(page, post_login).await;
+        }
         Ok(())
     }
 
-    async fn is_zoom_login_page(&self, page: &Page) -> Result<bool, Box<dyn std::error::Error>> {
+    async fn is_zoom_login_page(&self, page: &Page) -> Result<bool, Error> {
         let url = page.url().await?.unwrap_or_default();
         let html = page.content().await?;
 
@@ -643,9 +965,49 @@ impl<'a> ZoomHeadless<'a> {
             || html.contains("Sign in with Microsoft"))
     }
 
-    async fn handle_zoom_play_sso(&self, page: &Page) -> Result<(), Box<dyn std::error::Error>> {
-        // Step 1: Wait for page to settle after navigation
-        sleep(Duration::from_secs(3)).await;
+    /// Fill in the passcode form of a protected share link, when the page shows one.
+    async fn enter_passcode(&self, page: &Page, passcode: Option<&str>) -> Result<(), Error> {
+        let settle = Duration::from_secs(self.config.zoom.sso.player_settle_secs);
+        wait_for_load(page, settle).await;
+        wait_for_selector(
+            page,
+            &[PASSCODE_INPUT, ZOOM_LOGIN_METHODS, ZOOM_PLAYER],
+            settle,
+        )
+        .await;
+        let Ok(input) = page.find_element(PASSCODE_INPUT).await else {
+            return Ok(());
+        };
+        let Some(passcode) = passcode else {
+            return Err("recording is protected by a passcode; pass it with --passcode".into());
+        };
+        report!("{}", tr!("Entering recording passcode..."));
+        input.click().await?.type_str(passcode).await?;
+        match page.find_element(PASSCODE_BUTTONS).await {
+            Ok(button) => {
+                button.click().await?;
+            }
+            Err(_) => {
+                input.press_key("Enter").await?;
+            }
+        }
+        // A wrong passcode leaves the form in place instead of loading the player
+        if wait_for_selector(page, &[ZOOM_PLAYER, ZOOM_LOGIN_METHODS], settle)
+            .await
+            .is_none()
+            && page.find_element(PASSCODE_INPUT).await.is_ok()
+        {
+            return Err("Zoom rejected the recording passcode".into());
+        }
+        Ok(())
+    }
+
+    async fn handle_zoom_play_sso(&self, page: &Page) -> Result<(), Error> {
+        let sso = &self.config.zoom.sso;
+        // Step 1: Wait for either the player or the login screen to appear
+        let settle = Duration::from_secs(sso.player_settle_secs);
+        wait_for_load(page, settle).await;
+        wait_for_selector(page, &[ZOOM_LOGIN_METHODS, ZOOM_PLAYER], settle).await;
 
         let url = page.url().await?.unwrap_or_default();
 
@@ -656,7 +1018,10 @@ impl<'a> ZoomHeadless<'a> {
                 if !html.contains("zm-login-methods__item")
                     && !html.contains("Sign in with Microsoft")
                 {
-                    println!("Zoom player already loaded, no authentication needed");
+                    report!(
+                        "{}",
+                        tr!("Zoom player already loaded, no authentication needed")
+                    );
                     return Ok(());
                 }
             }
@@ -664,30 +1029,42 @@ impl<'a> ZoomHeadless<'a> {
 
         // Step 3: Detect Zoom login screen
         if !self.is_zoom_login_page(page).await.unwrap_or(false) {
-            println!("No Zoom login detected, assuming already authenticated");
+            report!(
+                "{}",
+                tr!("No Zoom login detected, assuming already authenticated")
+            );
             return Ok(());
         }
 
-        println!("Zoom play_url: detected login screen, initiating Microsoft SSO...");
+        report!(
+            "{}",
+            tr!("Zoom play_url: detected login screen, initiating Microsoft SSO...")
+        );
 
         // Step 4: Click "Sign in with Microsoft" on Zoom
         let start = Instant::now();
         let mut clicked = false;
 
-        while start.elapsed() < Duration::from_secs(10) {
+        while start.elapsed() < Duration::from_secs(sso.button_timeout_secs) {
             // Try multiple selectors
             if let Ok(el) = page
                 .find_element("a[aria-label='Sign in with Microsoft']")
                 .await
             {
-                println!("Clicked 'Sign in with Microsoft' button (aria-label match)");
+                report!(
+                    "{}",
+                    tr!("Clicked 'Sign in with Microsoft' button (aria-label match)")
+                );
                 el.click().await?;
                 clicked = true;
                 break;
             }
 
             if let Ok(el) = page.find_element("a[aria-label*='Microsoft']").await {
-                println!("Clicked 'Sign in with Microsoft' button (aria-label partial match)");
+                report!(
+                    "{}",
+                    tr!("Clicked 'Sign in with Microsoft' button (aria-label partial match)")
+                );
                 el.click().await?;
                 clicked = true;
                 break;
@@ -698,7 +1075,7 @@ impl<'a> ZoomHeadless<'a> {
                 for method in methods {
                     if let Ok(Some(text)) = method.inner_text().await {
                         if text.to_lowercase().contains("microsoft") {
-                            println!("Clicked 'Microsoft' login method (text match)");
+                            report!("{}", tr!("Clicked 'Microsoft' login method (text match)"));
                             method.click().await?;
                             clicked = true;
                             break;
@@ -711,7 +1088,7 @@ impl<'a> ZoomHeadless<'a> {
                 break;
             }
 
-            sleep(Duration::from_millis(500)).await;
+            sleep(POLL_INTERVAL).await;
         }
 
         if !clicked {
@@ -719,50 +1096,53 @@ impl<'a> ZoomHeadless<'a> {
         }
 
         // Step 5: Wait for redirect to Microsoft
-        println!("Clicked Microsoft sign-in button, waiting for redirect...");
-        sleep(Duration::from_secs(3)).await;
-
-        let start = Instant::now();
-        let mut on_microsoft = false;
-        while start.elapsed() < Duration::from_secs(30) {
-            let current_url = page.url().await?.unwrap_or_default();
-            if current_url.contains("login.microsoftonline.com") {
-                println!("Redirected to Microsoft login: {}", current_url);
-                on_microsoft = true;
-                break;
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        report!(
+            "{}",
+            tr!("Clicked Microsoft sign-in button, waiting for redirect...")
+        );
 
-        if !on_microsoft {
+        let redirect_timeout = Duration::from_secs(sso.redirect_timeout_secs);
+        let Some(current_url) = wait_for_url(page, redirect_timeout, |u| {
+            u.contains("login.microsoftonline.com")
+        })
+        .await
+        else {
             return Err("Timeout waiting for redirect to Microsoft login".into());
-        }
+        };
+        report!("{}", tr!("Redirected to Microsoft login: {}", current_url));
 
         // Step 6: Handle Microsoft authentication (account picker or credentials)
         self.handle_ms_account(page).await?;
-        println!("Microsoft authentication complete, waiting for Zoom player...");
+        report!(
+            "{}",
+            tr!("Microsoft authentication complete, waiting for Zoom player...")
+        );
 
         // Step 7: Wait for return to Zoom
-        let start = Instant::now();
-        let mut back_on_zoom = false;
-        while start.elapsed() < Duration::from_secs(30) {
-            let current_url = page.url().await?.unwrap_or_default();
-            if current_url.contains("zoom.us") && !current_url.contains("signin") {
-                println!("Back on Zoom page: {}", current_url);
-                back_on_zoom = true;
-                break;
-            }
-            sleep(Duration::from_millis(1000)).await;
-        }
-
-        if !back_on_zoom {
+        let Some(current_url) = wait_for_url(page, redirect_timeout, |u| {
+            u.contains("zoom.us") && !u.contains("signin")
+        })
+        .await
+        else {
             return Err("Timeout waiting to return to Zoom after Microsoft authentication".into());
+        };
+        report!("{}", tr!("Back on Zoom page: {}", current_url));
+
+        // Wait for the player to initialize
+        wait_for_load(page, settle).await;
+        if wait_for_selector(
+            page,
+            &[ZOOM_PLAYER],
+            Duration::from_secs(sso.step_wait_secs),
+        )
+        .await
+        .is_some()
+        {
+            report!("{}", tr!("Zoom player loaded"));
+        } else {
+            report!("{}", tr!("Zoom player should now be loaded"));
         }
 
-        // Give the player time to initialize
-        sleep(Duration::from_secs(2)).await;
-        println!("Zoom player should now be loaded");
-
         Ok(())
     }
 
@@ -771,85 +1151,176 @@ impl<'a> ZoomHeadless<'a> {
         cfg: &crate::config::Config,
         _db: &ZoomDb,
         course_id: u64,
+        course_name: Option<&str>,
         files: Vec<ZoomRecordingFile>,
-        _concurrency: usize, // Not used since we process one-by-one
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        use crate::ffmpeg::{download_via_ffmpeg, ensure_ffmpeg_available, FfmpegError};
-        use crate::fsutil::sanitize_filename_preserve_ext;
+        concurrency: usize,
+    ) -> Result<(), Error> {
+        use crate::ffmpeg::ensure_ffmpeg_available;
         use crate::zoom::models::ReplayHeader;
-        use std::collections::HashMap;
-        use std::path::PathBuf;
 
         ensure_ffmpeg_available(&cfg.zoom.ffmpeg_path).await?;
 
-        let base = PathBuf::from(&cfg.download_root)
-            .join("Zoom")
-            .join(course_id.to_string());
-
-        tokio::fs::create_dir_all(&base).await?;
-
-        // Scan for existing recordings to avoid redownloading
-        let existing_files = scan_existing_recordings(&base)?;
-        let files_to_download: Vec<_> = files
-            .into_iter()
-            .filter(|file| {
-                let filename = sanitize_filename_preserve_ext(&(file.filename_hint() + ".mp4"));
-                if existing_files.contains(&filename) {
-                    println!("⏩ Skipping (already exists): {}", filename);
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let recordings = RecordingsDir::resolve(cfg, self.db, course_id).await?;
+        let base = &recordings.base;
+        let (all_files, files_to_download) =
+            plan_downloads(self.db, &recordings, files, &cfg.zoom.preferred_views).await?;
 
         if files_to_download.is_empty() {
-            println!("All recordings already downloaded!");
+            report!("{}", tr!("All recordings already downloaded!"));
             return Ok(());
         }
 
-        println!(
-            "Found {} recordings, {} new to download",
-            files_to_download.len() + existing_files.len(),
-            files_to_download.len()
+        report!(
+            "{}",
+            tr!(
+                "Found {} recordings, {} new to download",
+                all_files.len(),
+                files_to_download.len()
+            )
         );
 
-        let (mut browser, mut handler) = Browser::launch(
-            BrowserConfig::builder()
-                // Running in full headless mode (no GUI)
-                .arg("--no-sandbox")
-                .arg("--disable-gpu")
-                .build()?,
-        )
-        .await?;
+        // Fail before launching the browser rather than mid-download with ENOSPC
+        let needed: u64 = files_to_download
+            .iter()
+            .filter_map(|(f, _)| f.file_size)
+            .sum();
+        crate::fsutil::check_free_space(base, needed, cfg.free_space_reserve())?;
+
+        let mut browser_config = BrowserConfig::builder()
+            // Running in full headless mode (no GUI)
+            .arg("--no-sandbox")
+            .arg("--disable-gpu");
+        if let Some(proxy) = self.config.browser_proxy() {
+            browser_config = browser_config.arg(format!("--proxy-server={}", proxy));
+        }
+        let (mut browser, mut handler) = Browser::launch(browser_config.build()?).await?;
 
-        let handle = tokio::spawn(async move {
+        let tasks = BrowserTasks::new(tokio::spawn(async move {
             while let Some(h) = handler.next().await {
                 if h.is_err() {
                     break;
                 }
             }
-        });
+        }));
 
         let page = browser.new_page("about:blank").await?;
         page.set_user_agent(&self.config.zoom.user_agent).await?;
 
-        let mut name_counts: HashMap<String, usize> = HashMap::new();
-        println!("Starting capture and download (tokens expire quickly, processing one by one)...");
-        println!(
-            "Processing {} recordings (capture → download → next)...\n",
-            files_to_download.len()
+        // The browser captures one play URL at a time while up to `concurrency` downloads run
+        let workers = concurrency.max(1);
+        let slots = Arc::new(Semaphore::new(workers));
+        let mut downloads: JoinSet<(usize, u32, FetchOutcome)> = JoinSet::new();
+        let shared_cfg = Arc::new(cfg.clone());
+        let bars = multi_progress();
+        report!(
+            "{}",
+            tr!(
+                "Processing {} recordings with {} download worker(s) (capture → queue download → next)...\n",
+                files_to_download.len(),
+                workers,
+            )
         );
 
         let mut cookies_captured = false;
-
-        for (idx, file) in files_to_download.iter().enumerate() {
-            println!(
-                "\n[{}/{}] Processing: {}",
-                idx + 1,
-                files_to_download.len(),
-                file.play_url
+        // (index into files_to_download, capture attempt); expired tokens come back here
+        let mut queue: VecDeque<(usize, u32)> =
+            (0..files_to_download.len()).map(|i| (i, 0)).collect();
+
+        loop {
+            if crate::shutdown::requested() && !queue.is_empty() {
+                // Let running downloads finish, but start no more
+                tracing::info!(
+                    course_id,
+                    skipped = queue.len(),
+                    "interrupted; not starting the remaining recordings"
+                );
+                queue.clear();
+            }
+            let Some((idx, attempt)) = queue.pop_front() else {
+                // Nothing left to capture; running downloads may still hand items back
+                match downloads.join_next().await {
+                    Some(done) => {
+                        if settle_download(self.db, cfg, &files_to_download, &mut queue, done?)? {
+                            cookies_captured = false;
+                        }
+                        continue;
+                    }
+                    None => break,
+                }
+            };
+            let (file, dest) = &files_to_download[idx];
+            if attempt > 0 {
+                let wait = Duration::from_secs(2u64.pow(attempt.min(5)));
+                report!(
+                    "{}",
+                    tr!(
+                        "\n↻ Re-capturing {} after its token expired (attempt {}, waiting {}s)",
+                        file.play_url,
+                        attempt + 1,
+                        wait.as_secs()
+                    )
+                );
+                sleep(wait).await;
+            }
+            // Capture only once a worker is free so the signed URL is still fresh when its download starts
+            let permit = slots.clone().acquire_owned().await?;
+            while let Some(done) = downloads.try_join_next() {
+                if settle_download(self.db, cfg, &files_to_download, &mut queue, done?)? {
+                    cookies_captured = false;
+                }
+            }
+            report!(
+                "{}",
+                tr!(
+                    "\n[{}/{}] Processing: {}",
+                    idx + 1,
+                    files_to_download.len(),
+                    file.play_url
+                )
             );
+            let recorded_at = file
+                .recording_start
+                .as_deref()
+                .or(file.start_time.as_deref());
+
+            // Transcripts and chat are plain downloads with the session cookies
+            if !file.is_video() {
+                let url = file.download_url.as_deref().unwrap_or(&file.play_url);
+                let asset = ReplayHeader {
+                    download_url: url.to_string(),
+                    headers: HashMap::from([(
+                        "User-Agent".to_string(),
+                        self.config.zoom.user_agent.clone(),
+                    )]),
+                };
+                let cookies = self.db.load_cookies(self.course_id)?;
+                let headers = crate::zoom::download::build_ffmpeg_headers(
+                    cfg,
+                    &asset,
+                    &file.play_url,
+                    &cookies,
+                    url,
+                );
+                record_download(self.db, file, dest, DownloadStatus::InProgress)?;
+                let (cfg, url, dest) = (shared_cfg.clone(), url.to_string(), dest.clone());
+                let recorded_at = recorded_at.map(str::to_string);
+                let bar = bars.add(download_bar(file.file_size, &display_name(&dest)));
+                downloads.spawn(async move {
+                    let _permit = permit;
+                    let outcome = fetch_recording(
+                        &cfg,
+                        false,
+                        &headers,
+                        &url,
+                        &dest,
+                        recorded_at.as_deref(),
+                        bar,
+                    )
+                    .await;
+                    (idx, attempt, outcome)
+                });
+                continue;
+            }
 
             // STEP 1: Navigate to play URL
             let mut events = page
@@ -858,16 +1329,30 @@ impl<'a> ZoomHeadless<'a> {
                 .unwrap();
             page.goto(&file.play_url).await?;
 
-            // STEP 2: Authenticate if needed
+            // STEP 2: Unlock protected share links and authenticate if needed
+            if let Err(e) = self.enter_passcode(&page, file.passcode.as_deref()).await {
+                report!("✗ {}: {}", file.play_url, e);
+                save_diagnostics(&page, "zoom-passcode").await;
+                report!("{}", tr!("Skipping this file..."));
+                continue;
+            }
             if let Err(e) = self.handle_zoom_play_sso(&page).await {
-                println!("Warning: SSO failed for {}: {:?}", file.play_url, e);
-                println!("Skipping this file...");
+                report!(
+                    "{}",
+                    tr!(
+                        "Warning: SSO failed for {}: {}",
+                        file.play_url,
+                        format!("{:?}", e)
+                    )
+                );
+                save_diagnostics(&page, "zoom-play-sso").await;
+                report!("{}", tr!("Skipping this file..."));
                 continue;
             }
 
             // STEP 3: Capture fresh cookies (first file only) and load for downloads
             let zoom_cookies = if !cookies_captured {
-                println!("Capturing fresh cookies after SSO...");
+                report!("{}", tr!("Capturing fresh cookies after SSO..."));
                 let current_cookies = page.get_cookies().await?;
                 let mut fresh_cookies = Vec::new();
                 for c in current_cookies {
@@ -884,14 +1369,17 @@ impl<'a> ZoomHeadless<'a> {
                     }
                 }
                 if !fresh_cookies.is_empty() {
-                    self.db.replace_cookies(&fresh_cookies)?;
-                    println!("Saved {} fresh cookies for downloads", fresh_cookies.len());
+                    self.db.save_cookies(self.course_id, &fresh_cookies)?;
+                    report!(
+                        "{}",
+                        tr!("Saved {} fresh cookies for downloads", fresh_cookies.len())
+                    );
                 }
                 cookies_captured = true;
                 fresh_cookies
             } else {
                 // Load cookies from DB for subsequent files
-                self.db.load_cookies()?
+                self.db.load_cookies(self.course_id)?
             };
 
             // STEP 4: Wait for media request (capture EXACT headers from .mp4 request)
@@ -916,16 +1404,22 @@ impl<'a> ZoomHeadless<'a> {
                                     }
                                 }
 
-                                println!("✓ Captured download URL: {}", url);
-                                println!("  Captured {} headers from MP4 request:", headers.len());
+                                report!("{}", tr!("✓ Captured download URL: {}", url));
+                                report!(
+                                    "{}",
+                                    tr!(
+                                        "  Captured {} headers from MP4 request",
+                                        headers.len(),
+                                    )
+                                );
                                 for (k, v) in &headers {
-                                    // Log all headers (truncate long values like cookies)
+                                    // Shown with -v; cookie and token values are masked
                                     let display_val = if v.len() > 100 {
                                         format!("{}...", &v[..100])
                                     } else {
                                         v.clone()
                                     };
-                                    println!("    {}: {}", k, display_val);
+                                    report_verbose!("    {}: {}", k, display_val);
                                 }
 
                                 asset = Some(ReplayHeader {
@@ -943,25 +1437,13 @@ impl<'a> ZoomHeadless<'a> {
             let asset = match asset {
                 Some(a) => a,
                 None => {
-                    println!("✗ Could not capture download URL, skipping...");
+                    report!("{}", tr!("✗ Could not capture download URL, skipping..."));
+                    save_diagnostics(&page, "replay-capture").await;
                     continue;
                 }
             };
 
             // STEP 5: Download immediately (while token is fresh!)
-            let mut filename = sanitize_filename_preserve_ext(file.filename_hint() + ".mp4");
-            let count = name_counts.entry(filename.clone()).or_insert(0);
-            if *count > 0 {
-                let stem = filename.trim_end_matches(".mp4");
-                filename = format!("{}_{}.mp4", stem, count);
-            }
-            *count += 1;
-
-            let dest = base.join(&filename);
-            if let Some(parent) = dest.parent() {
-                tokio::fs::create_dir_all(parent).await?;
-            }
-
             let headers = crate::zoom::download::build_ffmpeg_headers(
                 cfg,
                 &asset,
@@ -970,34 +1452,47 @@ impl<'a> ZoomHeadless<'a> {
                 &asset.download_url,
             );
 
-            println!("⬇ Downloading to: {}", dest.display());
-            match download_via_ffmpeg(&cfg.zoom.ffmpeg_path, &headers, &asset.download_url, &dest)
-                .await
-            {
-                Ok(()) => println!("✓ Downloaded successfully!"),
-                Err(FfmpegError::Process { .. }) => {
-                    println!("✗ ffmpeg failed, trying HTTP fallback...");
-                    if let Err(e) =
-                        crate::zoom::download::http_download(&headers, &asset.download_url, &dest)
-                            .await
-                    {
-                        println!("✗ HTTP download also failed: {:?}", e);
-                    } else {
-                        println!("✓ Downloaded via HTTP!");
-                    }
-                }
-                Err(e) => {
-                    println!("✗ Download error: {:?}", e);
-                }
-            }
+            report!("{}", tr!("⬇ Queued download to: {}", dest.display()));
+            record_download(self.db, file, dest, DownloadStatus::InProgress)?;
+            let (cfg, url, dest) = (shared_cfg.clone(), asset.download_url, dest.clone());
+            let recorded_at = recorded_at.map(str::to_string);
+            let bar = bars.add(download_bar(file.file_size, &display_name(&dest)));
+            downloads.spawn(async move {
+                let _permit = permit;
+                let outcome = fetch_recording(
+                    &cfg,
+                    true,
+                    &headers,
+                    &url,
+                    &dest,
+                    recorded_at.as_deref(),
+                    bar,
+                )
+                .await;
+                (idx, attempt, outcome)
+            });
         }
 
         browser.close().await?;
-        handle.await?;
-
-        println!(
-            "\nAll files processed! Downloads saved to: {}",
-            base.display()
+        tasks.shutdown().await?;
+
+        finish_downloads(
+            cfg,
+            self.db,
+            &recordings,
+            &all_files,
+            &files_to_download,
+            course_name,
+        )
+        .await?;
+        crate::shutdown::check()?;
+
+        report!(
+            "{}",
+            tr!(
+                "\nAll files processed! Downloads saved to: {}",
+                base.display()
+            )
         );
         Ok(())
     }
@@ -1020,20 +1515,746 @@ impl<'a> ZoomHeadless<'a> {
     }
 }
 
-/// Helper function to scan existing .mp4 files in the recordings directory
-fn scan_existing_recordings(
-    dir: &std::path::Path,
-) -> Result<std::collections::HashSet<String>, Box<dyn std::error::Error>> {
-    let mut existing = std::collections::HashSet::new();
-    if dir.exists() {
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            if let Some(name) = entry.file_name().to_str() {
-                if name.ends_with(".mp4") {
-                    existing.insert(name.to_string());
+/// How often event-driven waits re-check the page.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const EMAIL_INPUTS: &str = "input[type='email'], input[name='loginfmt']";
+const PASSWORD_INPUTS: &str = "input[type='password'], input[name='passwd']";
+const SUBMIT_BUTTONS: &str = "input[type='submit'], button[type='submit'], #idSIButton9";
+const ZOOM_LOGIN_METHODS: &str = ".zm-login-methods__item, a[aria-label*='Microsoft']";
+const ZOOM_PLAYER: &str = "video";
+const PASSCODE_INPUT: &str = "#passcode, input[name='passcode']";
+const PASSCODE_BUTTONS: &str = "#passcode_btn, button[type='submit']";
+
+/// Wait for the page's pending navigation to finish and its document to load,
+/// giving up after `timeout`. Returns whether the page finished loading.
+async fn wait_for_load(page: &Page, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let _ = tokio::time::timeout(timeout, page.wait_for_navigation()).await;
+    while Instant::now() < deadline {
+        let state = page
+            .evaluate("document.readyState")
+            .await
+            .ok()
+            .and_then(|r| r.into_value::<String>().ok());
+        if state.as_deref() == Some("complete") {
+            return true;
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+    false
+}
+
+/// Poll until the page URL satisfies `done`, returning it, or `None` on timeout.
+async fn wait_for_url(
+    page: &Page,
+    timeout: Duration,
+    done: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let url = page.url().await.ok().flatten().unwrap_or_default();
+        if done(&url) {
+            return Some(url);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Poll until an element matching one of `selectors` appears, or `None` on timeout.
+async fn wait_for_selector(page: &Page, selectors: &[&str], timeout: Duration) -> Option<Element> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        for selector in selectors {
+            if let Ok(el) = page.find_element(*selector).await {
+                return Some(el);
+            }
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Type `value` into the first input matching `input` and press the form's
+/// submit button. Returns whether the input was present.
+async fn submit_field(page: &Page, input: &str, value: &str) -> Result<bool, Error> {
+    let Ok(field) = page.find_element(input).await else {
+        return Ok(false);
+    };
+    field.click().await?.type_str(value).await?;
+    if let Ok(button) = page.find_element(SUBMIT_BUTTONS).await {
+        button.click().await?;
+    }
+    Ok(true)
+}
+
+/// Whether a Canvas login button's text matches one of the configured labels;
+/// any button matches when no labels are configured.
+fn login_button_matches(text: &str, labels: &[String]) -> bool {
+    let text = text.to_uppercase();
+    labels.iter().all(|l| l.trim().is_empty())
+        || labels
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .any(|l| text.contains(&l.trim().to_uppercase()))
+}
+
+/// Save a full-page screenshot and the page HTML into `<config_dir>/diagnostics/`
+/// so headless failures can be inspected without re-running with a visible browser.
+/// Best effort: problems are logged and never mask the original error.
+async fn save_diagnostics(page: &Page, label: &str) {
+    let url = page.url().await.ok().flatten().unwrap_or_default();
+    let dir = match crate::config::ConfigPaths::new() {
+        Ok(paths) => paths.config_dir.join("diagnostics"),
+        Err(e) => {
+            tracing::warn!(error = %e, url = %url, label, "headless step failed; no diagnostics directory");
+            return;
+        }
+    };
+    if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+        tracing::warn!(error = %e, url = %url, label, "headless step failed; unable to create diagnostics directory");
+        return;
+    }
+    let stem = format!("{}-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), label);
+
+    let params = chromiumoxide::page::ScreenshotParams::builder()
+        .full_page(true)
+        .build();
+    match page.screenshot(params).await {
+        Ok(png) => {
+            if let Err(e) = tokio::fs::write(dir.join(format!("{}.png", stem)), png).await {
+                tracing::warn!(error = %e, "unable to write diagnostics screenshot");
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "unable to take diagnostics screenshot"),
+    }
+    match page.content().await {
+        Ok(html) => {
+            if let Err(e) = tokio::fs::write(dir.join(format!("{}.html", stem)), html).await {
+                tracing::warn!(error = %e, "unable to write diagnostics HTML");
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "unable to read page HTML for diagnostics"),
+    }
+
+    tracing::warn!(url = %url, label, dir = %dir.display(), "headless step failed; saved diagnostics");
+    report!(
+        "{}",
+        tr!(
+            "Saved diagnostics for '{}' (final URL: {}) to {}",
+            label,
+            url,
+            dir.join(&stem).display()
+        )
+    );
+}
+
+/// Destination inside the meeting folder. Videos of a meeting recorded in several
+/// layouts are named after their view; other repeated names get a numeric suffix.
+fn recording_dest(
+    recordings: &RecordingsDir,
+    file: &ZoomRecordingFile,
+    name_counts: &mut HashMap<PathBuf, usize>,
+    name_view: bool,
+) -> PathBuf {
+    let dir = recordings.meeting_dir(file);
+    let hint = match (file.file_type.as_deref(), file.view()) {
+        (Some(kind), _) if !file.is_video() => {
+            format!("{} {}", file.filename_hint(), kind.to_ascii_lowercase())
+        }
+        (_, Some(view)) if name_view => {
+            format!("{} {}", file.filename_hint(), view.replace('_', " "))
+        }
+        _ => file.filename_hint(),
+    };
+    let ext = file.extension();
+    let mut filename = sanitize_filename_preserve_ext(format!("{}.{}", hint, ext));
+    let count = name_counts.entry(dir.join(&filename)).or_insert(0);
+    if *count > 0 {
+        let stem = filename.trim_end_matches(&format!(".{}", ext));
+        filename = format!("{}_{}.{}", stem, count, ext);
+    }
+    *count += 1;
+    dir.join(filename)
+}
+
+/// Every recording file (videos first) and the `(file, destination)` pairs still to fetch.
+pub(crate) type DownloadPlan = (Vec<ZoomRecordingFile>, Vec<(ZoomRecordingFile, PathBuf)>);
+
+/// Course subfolder holding the course's Zoom recordings.
+pub const RECORDINGS_DIR: &str = "Recordings";
+
+/// Where a course's recordings go: `<course folder>/Recordings` once the course
+/// has been synced, else `Zoom/<course id>` under the download root. With
+/// `zoom.download_root`, both live under it, named after the course folder.
+pub(crate) struct RecordingsDir {
+    pub base: PathBuf,
+    /// Course start, when meetings are filed into week folders.
+    week_start: Option<NaiveDate>,
+}
+
+impl RecordingsDir {
+    /// Folder for `course_id`'s recordings. Recordings downloaded into
+    /// `Zoom/<course id>` before the course folder existed are moved into it.
+    pub(crate) async fn resolve(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<Self, Error> {
+        let root = cfg.canvas_root();
+        let zoom_root = cfg.zoom_root();
+        let legacy = zoom_root
+            .as_deref()
+            .unwrap_or(&root)
+            .join("Zoom")
+            .join(course_id.to_string());
+        let course_dir = CourseDirs::load(&root)
+            .await
+            .get(&root, course_id)
+            .filter(|dir| dir.is_dir());
+        let Some(course_dir) = course_dir else {
+            return Ok(Self {
+                base: legacy,
+                week_start: None,
+            });
+        };
+        let base = cfg
+            .zoom_course_dir(&course_dir)
+            .unwrap_or_else(|| course_dir.clone())
+            .join(RECORDINGS_DIR);
+        if legacy.is_dir() && !base.exists() {
+            if let Some(parent) = base.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::rename(&legacy, &base).await?;
+            let moved = db.relocate_downloads(&legacy, &base)?;
+            tracing::info!(course_id, from = %legacy.display(), to = %base.display(), moved, "moved recordings into the course folder");
+            report!(
+                "{}",
+                tr!(
+                    "Moved recordings of course {} from {} to {}",
+                    course_id,
+                    legacy.display(),
+                    base.display()
+                )
+            );
+        }
+        let week_start = if cfg.zoom.recordings_by_week {
+            State::load(&course_dir.join("state.json"))
+                .await
+                .start_at
+                .as_deref()
+                .and_then(leading_date)
+        } else {
+            None
+        };
+        Ok(Self { base, week_start })
+    }
+
+    /// Folder of the meeting `file` belongs to, inside its week's folder when
+    /// recordings are filed by week and the meeting date is known.
+    fn meeting_dir(&self, file: &ZoomRecordingFile) -> PathBuf {
+        let week = self.week_start.and_then(|start| {
+            let date = file
+                .recording_start
+                .as_deref()
+                .or(file.start_time.as_deref())?;
+            week_of_date(start, leading_date(date)?)
+        });
+        match week {
+            Some(week) => self.base.join(week_name(week)).join(file.meeting_folder()),
+            None => self.base.join(file.meeting_folder()),
+        }
+    }
+}
+
+/// Decide where each recording file goes and which still need downloading,
+/// skipping intact downloads and adopting files saved before tracking existed.
+pub(crate) async fn plan_downloads(
+    db: &ZoomDb,
+    recordings: &RecordingsDir,
+    files: Vec<ZoomRecordingFile>,
+    preferred_views: &[String],
+) -> Result<DownloadPlan, Error> {
+    let base = &recordings.base;
+    tokio::fs::create_dir_all(base).await?;
+
+    let mut files = select_views(files, preferred_views);
+    // Videos first: transcripts and chat reuse the cookies captured on a player page
+    files.sort_by_key(|f| !f.is_video());
+
+    let mut videos_per_meeting: HashMap<&str, usize> = HashMap::new();
+    for file in files.iter().filter(|f| f.view().is_some()) {
+        *videos_per_meeting.entry(&file.meeting_id).or_insert(0) += 1;
+    }
+    let multi_view: HashSet<String> = videos_per_meeting
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(meeting, _)| meeting.to_string())
+        .collect();
+
+    let all_files = files.clone();
+    let mut name_counts: HashMap<PathBuf, usize> = HashMap::new();
+    let mut files_to_download = Vec::new();
+    for file in files {
+        let name_view = multi_view.contains(&file.meeting_id);
+        let planned = recording_dest(recordings, &file, &mut name_counts, name_view);
+        let record = db.get_download(&file.meeting_id, file.file_key())?;
+        if let Some(done) = record.as_ref().filter(|r| r.is_intact()) {
+            report!(
+                "{}",
+                tr!("⏩ Skipping (already downloaded): {}", done.path.display())
+            );
+            continue;
+        }
+        // Unfinished downloads resume where they started, next to their .part file
+        let dest = match record.as_ref() {
+            Some(r) if r.status != DownloadStatus::Complete => r.path.clone(),
+            _ => planned,
+        };
+        if record.is_none() {
+            // Earlier versions saved every MP4 directly under the course folder
+            let legacy = dest.file_name().map(|name| base.join(name));
+            if let Some(legacy) = legacy.filter(|l| file.is_video() && l.exists()) {
+                if !dest.exists() {
+                    tokio::fs::create_dir_all(dest.parent().unwrap_or(base)).await?;
+                    tokio::fs::rename(&legacy, &dest).await?;
+                    report!("{}", tr!("↪ Moved into meeting folder: {}", dest.display()));
+                }
+            }
+            // Files saved before downloads were tracked are recorded once and then trusted
+            if dest.exists() {
+                record_download(db, &file, &dest, DownloadStatus::Complete)?;
+                report!(
+                    "{}",
+                    tr!("⏩ Skipping (already exists): {}", dest.display())
+                );
+                continue;
+            }
+        }
+        files_to_download.push((file, dest));
+    }
+    write_meeting_sidecars(recordings, &all_files).await?;
+    Ok((all_files, files_to_download))
+}
+
+/// Embed metadata into completed videos and refresh the meeting sidecars.
+pub(crate) async fn finish_downloads(
+    cfg: &Config,
+    db: &ZoomDb,
+    recordings: &RecordingsDir,
+    all_files: &[ZoomRecordingFile],
+    files_to_download: &[(ZoomRecordingFile, PathBuf)],
+    course_name: Option<&str>,
+) -> Result<(), Error> {
+    // Metadata goes in last: chapters come from transcripts fetched after the videos
+    if cfg.zoom.embed_metadata {
+        for (file, dest) in files_to_download.iter().filter(|(f, _)| f.is_video()) {
+            let done = db.get_download(&file.meeting_id, file.file_key())?;
+            if !done.is_some_and(|d| d.is_intact()) {
+                continue;
+            }
+            match embed_recording_metadata(cfg, file, dest, course_name).await {
+                Ok(()) => {
+                    crate::fsutil::apply_remote_mtime(
+                        dest,
+                        file.recording_start
+                            .as_deref()
+                            .or(file.start_time.as_deref()),
+                    );
+                    record_download(db, file, dest, DownloadStatus::Complete)?;
                 }
+                Err(e) => report!(
+                    "{}",
+                    tr!("⚠ {}: metadata not embedded: {}", display_name(dest), e)
+                ),
             }
         }
     }
-    Ok(existing)
+    if cfg.zoom.transcript_notes {
+        write_transcript_notes(db, all_files).await?;
+    }
+    write_meeting_sidecars(recordings, all_files).await?;
+    Ok(())
+}
+
+/// Write `<transcript>.md` next to each downloaded transcript whose notes are
+/// missing or out of date.
+async fn write_transcript_notes(db: &ZoomDb, files: &[ZoomRecordingFile]) -> Result<(), Error> {
+    use crate::zoom::transcript::{notes_markdown, parse_vtt};
+
+    for file in files.iter().filter(|f| f.extension() == "vtt") {
+        let Some(done) = db
+            .get_download(&file.meeting_id, file.file_key())?
+            .filter(|d| d.is_intact())
+        else {
+            continue;
+        };
+        let cues = parse_vtt(&tokio::fs::read_to_string(&done.path).await?);
+        let title = file.topic.as_deref().unwrap_or(&file.meeting_id);
+        let date = file
+            .recording_start
+            .as_deref()
+            .or(file.start_time.as_deref());
+        let md = notes_markdown(title, date, &cues);
+        let notes = done.path.with_extension("md");
+        if tokio::fs::read(&notes)
+            .await
+            .is_ok_and(|old| old == md.as_bytes())
+        {
+            continue;
+        }
+        atomic_write(&notes, md.as_bytes()).await?;
+        report_verbose!("{}", tr!("✓ Transcript notes: {}", notes.display()));
+    }
+    Ok(())
+}
+
+/// Result of one download attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FetchOutcome {
+    Complete,
+    /// The signed URL or cookies were rejected (401/403); capture again and retry.
+    Expired,
+    Failed,
+}
+
+/// Download one recording file, through ffmpeg for videos with a plain HTTP fallback
+/// (except for HLS playlists, which only ffmpeg can assemble).
+pub(crate) async fn fetch_recording(
+    cfg: &Config,
+    via_ffmpeg: bool,
+    headers: &[(String, String)],
+    url: &str,
+    dest: &Path,
+    recorded_at: Option<&str>,
+    bar: ProgressBar,
+) -> FetchOutcome {
+    use crate::ffmpeg::{download_via_ffmpeg, is_hls, transcode_in_place, FfmpegError};
+    use crate::fsutil::apply_remote_mtime;
+    use crate::zoom::download::http_download;
+
+    let name = display_name(dest);
+    let result = if via_ffmpeg {
+        match download_via_ffmpeg(
+            &cfg.zoom.ffmpeg_path,
+            cfg.zoom.ffmpeg_readrate,
+            headers,
+            url,
+            dest,
+            Some(&bar),
+        )
+        .await
+        {
+            Err(e) if e.is_auth_rejected() => {
+                report!("{}", tr!("✗ {}: token rejected by Zoom", name));
+                return FetchOutcome::Expired;
+            }
+            // A plain GET of a playlist would only save the playlist itself
+            Err(FfmpegError::Process { .. }) if !is_hls(url) => {
+                report!(
+                    "{}",
+                    tr!("✗ {}: ffmpeg failed, trying HTTP fallback...", name)
+                );
+                http_download(cfg, headers, url, dest).await
+            }
+            other => other.map_err(Into::into),
+        }
+    } else {
+        http_download(cfg, headers, url, dest).await
+    };
+    let is_mp4 = dest
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mp4"));
+    if let (Ok(()), true, Some(settings)) = (&result, is_mp4, &cfg.zoom.transcode) {
+        if let Err(e) = transcode_in_place(&cfg.zoom.ffmpeg_path, settings, dest, Some(&bar)).await
+        {
+            report!("{}", tr!("⚠ {}: keeping original recording: {}", name, e));
+        }
+    }
+    bar.finish_and_clear();
+    match result {
+        Ok(()) => {
+            apply_remote_mtime(dest, recorded_at);
+            report!("{}", tr!("✓ Downloaded {}", name));
+            FetchOutcome::Complete
+        }
+        Err(e @ Error::Auth(_)) => {
+            report!("✗ {}: {}", name, e);
+            FetchOutcome::Expired
+        }
+        Err(e) => {
+            report!("{}", tr!("✗ {}: download error: {}", name, e));
+            FetchOutcome::Failed
+        }
+    }
+}
+
+/// Interval between chapter markers generated from a transcript.
+const CHAPTER_INTERVAL_MS: u64 = 10 * 60 * 1000;
+
+/// Write title, date, course and meeting reference into the MP4, plus chapters
+/// from a transcript saved in the same meeting folder.
+async fn embed_recording_metadata(
+    cfg: &Config,
+    file: &ZoomRecordingFile,
+    dest: &Path,
+    course_name: Option<&str>,
+) -> Result<(), crate::ffmpeg::FfmpegError> {
+    use crate::ffmpeg::{embed_metadata, ffmetadata};
+    use crate::zoom::transcript::{chapters_from_cues, parse_vtt};
+
+    let comment = format!("Zoom meeting {} - {}", file.meeting_id, file.play_url);
+    let mut tags: Vec<(&str, &str)> = Vec::new();
+    if let Some(topic) = file.topic.as_deref() {
+        tags.push(("title", topic));
+    }
+    if let Some(date) = file
+        .recording_start
+        .as_deref()
+        .or(file.start_time.as_deref())
+    {
+        tags.push(("date", date));
+    }
+    if let Some(course) = course_name {
+        tags.push(("album", course));
+    }
+    tags.push(("comment", &comment));
+
+    let transcript = dest.parent().and_then(|dir| {
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .find(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("vtt")))
+    });
+    let chapters = match transcript {
+        Some(path) => chapters_from_cues(
+            &parse_vtt(&tokio::fs::read_to_string(&path).await?),
+            CHAPTER_INTERVAL_MS,
+        ),
+        None => Vec::new(),
+    };
+    embed_metadata(&cfg.zoom.ffmpeg_path, dest, &ffmetadata(&tags, &chapters)).await
+}
+
+pub(crate) fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Record a finished download, re-queueing items whose token expired while retries remain.
+/// Returns `true` when an item was re-queued, so fresh cookies should be captured.
+pub(crate) fn settle_download(
+    db: &ZoomDb,
+    cfg: &Config,
+    files: &[(ZoomRecordingFile, PathBuf)],
+    queue: &mut VecDeque<(usize, u32)>,
+    (idx, attempt, outcome): (usize, u32, FetchOutcome),
+) -> Result<bool, Error> {
+    let (file, dest) = &files[idx];
+    let status = match outcome {
+        FetchOutcome::Complete => DownloadStatus::Complete,
+        FetchOutcome::Expired if attempt < cfg.zoom.token_retries => {
+            queue.push_back((idx, attempt + 1));
+            return Ok(true);
+        }
+        FetchOutcome::Expired => {
+            report!(
+                "{}",
+                tr!(
+                    "✗ Giving up on {} after {} re-capture(s)",
+                    dest.display(),
+                    attempt
+                )
+            );
+            DownloadStatus::Failed
+        }
+        FetchOutcome::Failed => DownloadStatus::Failed,
+    };
+    record_download(db, file, dest, status)?;
+    Ok(false)
+}
+
+/// Record a download attempt; completed files are stored with their size and SHA-1.
+fn record_download(
+    db: &ZoomDb,
+    file: &ZoomRecordingFile,
+    dest: &Path,
+    status: DownloadStatus,
+) -> Result<(), Error> {
+    let (size, sha1) = match status {
+        DownloadStatus::Complete => {
+            let (size, sha1) = hash_file(dest)?;
+            (Some(size), Some(sha1))
+        }
+        _ => (None, None),
+    };
+    db.save_download(&ZoomDownload {
+        meeting_id: file.meeting_id.clone(),
+        file_id: file.file_key().to_string(),
+        status,
+        path: dest.to_path_buf(),
+        size,
+        sha1,
+    })
+}
+
+/// Write `meeting.json` into every meeting folder that exists on disk.
+async fn write_meeting_sidecars(
+    recordings: &RecordingsDir,
+    files: &[ZoomRecordingFile],
+) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for file in files {
+        let dir = recordings.meeting_dir(file);
+        if !dir.is_dir() || !seen.insert(dir.clone()) {
+            continue;
+        }
+        let sidecar = serde_json::to_vec_pretty(&MeetingSidecar::from(file))?;
+        atomic_write(&dir.join("meeting.json"), &sidecar).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dropping_browser_tasks_aborts_them() {
+        let handler = tokio::spawn(std::future::pending::<()>());
+        let listener = tokio::spawn(std::future::pending::<()>());
+        let (handler_abort, listener_abort) = (handler.abort_handle(), listener.abort_handle());
+
+        let mut tasks = BrowserTasks::new(handler);
+        tasks.listeners.push(listener);
+        drop(tasks);
+        tokio::task::yield_now().await;
+
+        assert!(handler_abort.is_finished());
+        assert!(listener_abort.is_finished());
+    }
+
+    #[test]
+    fn login_button_matching_uses_configured_labels() {
+        let unab = vec!["Estudiantes y Docentes".to_string()];
+        assert!(login_button_matches("ESTUDIANTES Y DOCENTES", &unab));
+        assert!(!login_button_matches("Administrativos", &unab));
+        assert!(login_button_matches("Anything", &[]));
+        assert!(login_button_matches("Anything", &[" ".to_string()]));
+    }
+
+    #[test]
+    fn expired_tokens_are_requeued_until_retries_run_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = ZoomDb::new(dir.path()).unwrap();
+        let mut cfg = Config::default();
+        cfg.zoom.token_retries = 1;
+        let file = ZoomRecordingFile {
+            meeting_id: "m1".into(),
+            file_id: Some("f1".into()),
+            play_url: "https://zoom.us/rec/play/1".into(),
+            download_url: None,
+            file_type: Some("MP4".into()),
+            recording_start: None,
+            topic: None,
+            start_time: None,
+            timezone: None,
+            meeting_number: None,
+            file_size: None,
+            duration: None,
+            recording_type: None,
+            passcode: None,
+        };
+        let files = vec![(file, dir.path().join("intro.mp4"))];
+        let mut queue = VecDeque::new();
+
+        let requeued =
+            settle_download(&db, &cfg, &files, &mut queue, (0, 0, FetchOutcome::Expired)).unwrap();
+        assert!(requeued);
+        assert_eq!(queue.pop_front(), Some((0, 1)));
+        assert!(db.get_download("m1", "f1").unwrap().is_none());
+
+        let requeued =
+            settle_download(&db, &cfg, &files, &mut queue, (0, 1, FetchOutcome::Expired)).unwrap();
+        assert!(!requeued);
+        assert!(queue.is_empty());
+        let record = db.get_download("m1", "f1").unwrap().unwrap();
+        assert_eq!(record.status, DownloadStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn recordings_move_into_the_course_folder_and_file_by_week() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("archive");
+        let db = ZoomDb::new(dir.path()).unwrap();
+        let mut cfg = Config {
+            download_root: root.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        cfg.zoom.recordings_by_week = true;
+        let course = root.join("Calculo_I");
+        std::fs::create_dir_all(&course).unwrap();
+        std::fs::write(
+            course.join("state.json"),
+            r#"{"course_id":101,"start_at":"2024-02-05T05:00:00Z","items":{}}"#,
+        )
+        .unwrap();
+        let mut dirs = CourseDirs::default();
+        dirs.set(&root, 101, &course);
+        dirs.save(&root).await.unwrap();
+
+        let file = ZoomRecordingFile {
+            meeting_id: "m1".into(),
+            file_id: Some("f1".into()),
+            play_url: "https://zoom.us/rec/play/1".into(),
+            download_url: None,
+            file_type: Some("MP4".into()),
+            recording_start: None,
+            topic: Some("Clase 3".into()),
+            start_time: Some("2024-02-14 10:00:00".into()),
+            timezone: None,
+            meeting_number: None,
+            file_size: None,
+            duration: None,
+            recording_type: None,
+            passcode: None,
+        };
+        // Downloaded before the course folder existed
+        let legacy = root.join("Zoom").join("101").join(file.meeting_folder());
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join("clase.mp4"), "mp4").unwrap();
+        record_download(
+            &db,
+            &file,
+            &legacy.join("clase.mp4"),
+            DownloadStatus::Complete,
+        )
+        .unwrap();
+
+        let recordings = RecordingsDir::resolve(&cfg, &db, 101).await.unwrap();
+        assert_eq!(recordings.base, course.join(RECORDINGS_DIR));
+        let moved = recordings
+            .base
+            .join(file.meeting_folder())
+            .join("clase.mp4");
+        let record = db.get_download("m1", "f1").unwrap().unwrap();
+        assert_eq!(record.path, moved);
+        assert!(record.is_intact());
+        // New downloads of that meeting go into its week's folder
+        assert_eq!(
+            recordings.meeting_dir(&file),
+            recordings.base.join("Week_02").join(file.meeting_folder())
+        );
+
+        // Recordings kept on another drive mirror the course folder names
+        let videos = dir.path().join("videos");
+        cfg.zoom.download_root = Some(videos.to_string_lossy().into_owned());
+        let recordings = RecordingsDir::resolve(&cfg, &db, 101).await.unwrap();
+        assert_eq!(
+            recordings.base,
+            videos.join("Calculo_I").join(RECORDINGS_DIR)
+        );
+    }
 }
@@ -0,0 +1,157 @@
+use crate::config::{Config, ConfigPaths};
+use crate::error::Error;
+use crate::http::build_http_client;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::headless::ZoomHeadless;
+use crate::zoom::models::{is_recording_link, DiscoveredLink};
+use crate::zoom::{course_name, ensure_session};
+use crate::{report, tr};
+use std::collections::HashSet;
+use tracing::info;
+
+/// Download the recordings behind share links that `recordings` found in a
+/// course's Canvas pages, skipping those already in the Zoom listing.
+pub async fn zoom_from_links(course_id: u64, concurrency: usize) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+
+    let links = db.load_links(course_id)?;
+    if links.is_empty() {
+        return Err(tr!("no Zoom recording links saved for course {}; run 'u_crawler recordings --course-id {}' first", course_id, course_id)
+        .into());
+    }
+
+    let http = build_http_client(&cfg);
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    let mut known = 0usize;
+    for mut link in links {
+        resolve(&db, &http, &mut link).await?;
+        db.save_link(course_id, &link)?;
+        if let Some(meeting_id) = &link.meeting_id {
+            report!(
+                "{}",
+                tr!("- {}: already listed as meeting {}", link.url, meeting_id)
+            );
+            known += 1;
+            continue;
+        }
+        let file = link.recording_file();
+        if seen.insert(file.play_url.clone()) {
+            files.push(file);
+        }
+    }
+
+    if files.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "All {} saved link(s) belong to meetings in the Zoom listing; 'zoom flow' downloads them.",
+                known,
+            )
+        );
+        return Ok(());
+    }
+    report!(
+        "{}",
+        tr!(
+            "Downloading {} linked recording(s); {} already covered by the Zoom listing.",
+            files.len(),
+            known
+        )
+    );
+
+    ensure_session(&cfg, &db, course_id).await?;
+    let course_name = course_name(course_id).await;
+    ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_and_download_immediately(
+            &cfg,
+            &db,
+            course_id,
+            course_name.as_deref(),
+            files,
+            concurrency,
+        )
+        .await?;
+    report!("{}", tr!("Linked recordings processed!"));
+    Ok(())
+}
+
+/// Download recordings from Zoom share or play URLs given directly, e.g. links
+/// sent by email that never appear in the course's Zoom listing. Files go into
+/// the course's recordings folder.
+pub async fn zoom_share(
+    course_id: u64,
+    urls: &[String],
+    passcode: Option<&str>,
+    concurrency: usize,
+) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+
+    let mut files = Vec::new();
+    for url in urls {
+        if !is_recording_link(url) {
+            return Err(tr!(
+                "{} is not a Zoom recording link (/rec/share/ or /rec/play/)",
+                url
+            )
+            .into());
+        }
+        let mut file = DiscoveredLink {
+            url: url.clone(),
+            label: None,
+            play_url: None,
+            meeting_id: None,
+        }
+        .recording_file();
+        file.passcode = passcode.map(str::to_string);
+        files.push(file);
+    }
+
+    ensure_session(&cfg, &db, course_id).await?;
+    let course_name = course_name(course_id).await;
+    ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_and_download_immediately(
+            &cfg,
+            &db,
+            course_id,
+            course_name.as_deref(),
+            files,
+            concurrency,
+        )
+        .await?;
+    report!("{}", tr!("Shared recordings processed!"));
+    Ok(())
+}
+
+/// Follow a share link to its player URL and match it against the Zoom listing.
+/// Links that cannot be resolved (e.g. passcode pages) keep their original URL.
+async fn resolve(
+    db: &ZoomDb,
+    http: &reqwest::Client,
+    link: &mut DiscoveredLink,
+) -> Result<(), Error> {
+    if link.play_url.is_none() {
+        match http.get(&link.url).send().await {
+            Ok(resp) if resp.url().path().contains("/rec/play/") => {
+                link.play_url = Some(resp.url().to_string());
+            }
+            Ok(resp) => {
+                info!(url = %link.url, landed = %resp.url(), "share link did not reach a player")
+            }
+            Err(e) => info!(url = %link.url, error = %e, "unable to resolve share link"),
+        }
+    }
+    if link.meeting_id.is_none() {
+        for url in link.play_url.iter().chain([&link.url]) {
+            if let Some(meeting_id) = db.meeting_for_play_url(url)? {
+                link.meeting_id = Some(meeting_id);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
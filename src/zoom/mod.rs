@@ -1,35 +1,311 @@
 pub mod api;
+pub mod cdp;
 pub mod db;
 pub mod download;
 pub mod headless;
+pub mod links;
 pub mod models;
+pub mod rest;
+pub mod session;
+pub mod transcript;
 
-use crate::config::ConfigPaths;
-use crate::progress::progress_bar;
-use api::{ZoomApiError, ZoomClient};
+use crate::canvas::{ExternalTool, Tab};
+use crate::config::{Config, ConfigPaths};
+use crate::error::Error;
+use crate::progress::{bar_println, format_bytes, progress_bar};
+use crate::{report, tr};
+use api::ZoomClient;
 use db::ZoomDb;
 use headless::ZoomHeadless;
-use models::{RecordingSummary, ZoomRecordingFile};
-use std::error::Error;
-use tracing::info;
+use models::{DownloadStatus, MeetingFilter, RecordingSummary, ZoomDownload, ZoomRecordingFile};
+use serde::Serialize;
+use tracing::{info, warn};
 
 pub async fn zoom_flow(
     course_id: u64,
     concurrency: usize,
     since: Option<String>,
-) -> Result<(), Box<dyn Error>> {
-    let cfg = crate::config::Config::load_or_init()?;
+) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
     let paths = ConfigPaths::new()?;
     let db = ZoomDb::new(&paths.config_dir)?;
+    crate::shutdown::listen();
 
-    println!("Starting Zoom flow for course {}", course_id);
+    report!("{}", tr!("Starting Zoom flow for course {}", course_id));
 
-    // 1. Check if we have valid credentials (scid + cookies + headers)
+    if let Some(api) = &cfg.zoom.api {
+        let course_name = course_name(course_id).await;
+        return rest::rest_flow(
+            &cfg,
+            &db,
+            api,
+            course_id,
+            concurrency,
+            since.as_deref(),
+            course_name.as_deref(),
+        )
+        .await;
+    }
+
+    ensure_session(&cfg, &db, course_id).await?;
+
+    report!(
+        "{}",
+        tr!("Starting listing and download for course {}", course_id)
+    );
+
+    // 2. List recordings using captured credentials
+    let client = ZoomClient::new(&cfg, &db, course_id).await?;
+
+    let listing = client.list_recordings(since.as_deref()).await?;
+    db.save_meetings(course_id, &listing)?;
+
+    let meetings: Vec<RecordingSummary> = listing
+        .result
+        .as_ref()
+        .and_then(|r| r.list.as_ref())
+        .cloned()
+        .unwrap_or_default();
+
+    if meetings.is_empty() {
+        report!(
+            "{}",
+            tr!("No Zoom meetings were found for course {}.", course_id)
+        );
+    } else {
+        report!(
+            "{}",
+            tr!(
+                "Captured {} Zoom meetings; fetching individual recording files...",
+                meetings.len()
+            )
+        );
+        for meeting in &meetings {
+            report!(
+                "{}",
+                tr!(
+                    "Found Meeting: ID={}, Topic='{}', Start={}",
+                    meeting.meeting_id,
+                    meeting.topic.as_deref().unwrap_or("N/A"),
+                    meeting.start_time.as_deref().unwrap_or("N/A")
+                )
+            );
+        }
+    }
+
+    // 3. Fetch recording files (API)
+    let mut all_files: Vec<ZoomRecordingFile> = Vec::new();
+    let meeting_progress = progress_bar(
+        meetings.len() as u64,
+        &tr!("Gathering recording files for course {}", course_id),
+    );
+    for summary in meetings {
+        meeting_progress.inc(1);
+        meeting_progress.set_message(tr!("Meeting {}", summary.meeting_id));
+        let files = client.fetch_recording_files(&summary).await?;
+        if files.is_empty() {
+            bar_println(
+                &meeting_progress,
+                tr!(
+                    "- {}: Zoom did not report downloadable files",
+                    summary.meeting_id
+                ),
+            );
+            continue;
+        }
+        db.save_files(course_id, &summary.meeting_id, &files)?;
+        bar_println(
+            &meeting_progress,
+            tr!(
+                "- {}: captured {} playUrl entries",
+                summary.meeting_id,
+                files.len()
+            ),
+        );
+        all_files.extend(files);
+    }
+    meeting_progress.finish_and_clear();
+
+    if all_files.is_empty() {
+        report!(
+            "{}",
+            tr!("No recordings with playUrl entries were available after the full flow; try again or verify permissions.")
+        );
+        return Ok(());
+    }
+
+    let course_name = course_name(course_id).await;
+
+    // 4. Capture play URLs and download immediately (one by one to avoid token expiration)
+    report!(
+        "{}",
+        tr!("Starting capture and download (tokens expire quickly, processing one by one)...")
+    );
+    ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_and_download_immediately(
+            &cfg,
+            &db,
+            course_id,
+            course_name.as_deref(),
+            all_files,
+            concurrency,
+        )
+        .await?;
+
+    report!("{}", tr!("All recordings processed!"));
+    Ok(())
+}
+
+/// Download selected meetings from the listing cached by `zoom flow`, reusing
+/// the stored session and the capture-and-download pipeline.
+pub async fn zoom_download(
+    course_id: u64,
+    filter: MeetingFilter,
+    concurrency: usize,
+) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+    crate::shutdown::listen();
+
+    let cached = db.load_meetings(course_id)?;
+    if cached.is_empty() {
+        return Err(tr!(
+            "no cached Zoom meetings for course {}; run 'u_crawler zoom flow --course-id {}' first",
+            course_id,
+            course_id
+        )
+        .into());
+    }
+    let meetings: Vec<RecordingSummary> =
+        cached.into_iter().filter(|m| filter.matches(m)).collect();
+    let mut files: Vec<ZoomRecordingFile> = Vec::new();
+    for meeting in &meetings {
+        let cached_files = db.load_files(&meeting.meeting_id)?;
+        if cached_files.is_empty() {
+            report!(
+                "{}",
+                tr!(
+                    "- {}: no recording files cached; run 'zoom flow' to refresh the listing",
+                    meeting.meeting_id
+                )
+            );
+        }
+        files.extend(cached_files);
+    }
+    if files.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "No cached recordings match the selection for course {}.",
+                course_id
+            )
+        );
+        return Ok(());
+    }
+    report!(
+        "{}",
+        tr!(
+            "Selected {} meeting(s) with {} recording file(s) for course {}",
+            meetings.len(),
+            files.len(),
+            course_id
+        )
+    );
+
+    let course_name = course_name(course_id).await;
+    if let Some(api) = &cfg.zoom.api {
+        let mut client = rest::ZoomRestClient::connect(&cfg, api).await?;
+        return rest::download_files(
+            &cfg,
+            &db,
+            &mut client,
+            course_id,
+            course_name.as_deref(),
+            files,
+            concurrency,
+        )
+        .await;
+    }
+    ensure_session(&cfg, &db, course_id).await?;
+    ZoomHeadless::new(&cfg, &db, course_id)
+        .capture_and_download_immediately(
+            &cfg,
+            &db,
+            course_id,
+            course_name.as_deref(),
+            files,
+            concurrency,
+        )
+        .await?;
+
+    report!("{}", tr!("Selected recordings processed!"));
+    Ok(())
+}
+
+/// Reuse the stored Zoom session (scid, cookies, API headers) when it still
+/// validates, renewing it ahead of expiry; otherwise relaunch the LTI without
+/// signing in, and only as a last resort sign in headlessly.
+async fn ensure_session(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<(), Error> {
+    if session_is_valid(cfg, db, course_id).await? {
+        if let Some(expires_at) = expiring_soon(cfg, db, course_id)? {
+            report!(
+                "{}",
+                tr!(
+                    "Zoom session expires {}; refreshing it ahead of time...",
+                    local_time(expires_at)
+                )
+            );
+            if let Err(e) = relaunch_lti(cfg, db, course_id).await {
+                warn!(course_id, error = %e, "proactive Zoom session refresh failed; keeping the current session");
+            }
+        }
+        return Ok(());
+    }
+
+    match relaunch_lti(cfg, db, course_id).await {
+        Ok(()) if session_is_valid(cfg, db, course_id).await? => return Ok(()),
+        Ok(()) => info!(
+            course_id,
+            "relaunched Zoom session did not validate; signing in"
+        ),
+        Err(e) => info!(course_id, error = %e, "sessionless LTI launch unavailable; signing in"),
+    }
+
+    discover_tool_id(cfg, db, course_id).await?;
+    report!(
+        "{}",
+        tr!("Starting headless capture (SSO + LTI scid + cookies)...")
+    );
+    ZoomHeadless::new(cfg, db, course_id)
+        .authenticate_and_capture()
+        .await?;
+    report!("{}", tr!("Headless capture finished."));
+
+    // Log what we captured
     let scid = db.get_scid(course_id)?;
-    let cookies = db.load_cookies()?;
+    let cookies = db.load_cookies(course_id)?;
     let headers = db.get_all_request_headers(course_id)?;
+    let xsrf_token = headers
+        .iter()
+        .find(|(k, _)| k.to_lowercase() == "x-xsrf-token")
+        .map(|(_, v)| v);
+
+    info!(
+        "HEADLESS RESULT -> course_id={}: lti_scid={:?}, xsrf_token={:?}, cookies_count={}",
+        course_id,
+        scid,
+        xsrf_token,
+        cookies.len(),
+    );
+    Ok(())
+}
 
-    let headless = ZoomHeadless::new(&cfg, &db, course_id);
+/// Whether the stored scid, cookies and API headers are complete and accepted by Zoom.
+async fn session_is_valid(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<bool, Error> {
+    let scid = db.get_scid(course_id)?;
+    let cookies = db.load_cookies(course_id)?;
+    let headers = db.get_all_request_headers(course_id)?;
 
     let xsrf_token = headers
         .iter()
@@ -69,134 +345,349 @@ pub async fn zoom_flow(
     let mut valid_session = false;
 
     if has_min_creds {
-        println!("Found existing credentials in DB. Validating...");
-        match ZoomClient::new(&cfg, &db, course_id).await {
+        report!("{}", tr!("Found existing credentials in DB. Validating..."));
+        match ZoomClient::new(cfg, db, course_id).await {
             Ok(client) => {
                 if client.validate_cookies().await {
-                    println!("Cookies are valid. Skipping headless capture.");
+                    report!("{}", tr!("Cookies are valid. Skipping headless capture."));
                     valid_session = true;
                 } else {
-                    println!("Cookies are invalid or expired.");
+                    report!("{}", tr!("Cookies are invalid or expired."));
                 }
             }
             Err(e) => {
-                println!("Failed to initialize Zoom client for validation: {}", e);
+                report!(
+                    "{}",
+                    tr!("Failed to initialize Zoom client for validation: {}", e)
+                );
             }
         }
     } else {
-        println!("Missing some credentials in DB.");
+        report!("{}", tr!("Missing some credentials in DB."));
     }
 
-    if !valid_session {
-        println!("Starting headless capture (SSO + LTI scid + cookies)...");
-        headless.authenticate_and_capture().await?;
-        println!("Headless capture finished.");
-
-        // Log what we captured
-        let scid = db.get_scid(course_id)?;
-        let cookies = db.load_cookies()?;
-        let headers = db.get_all_request_headers(course_id)?;
-        let xsrf_token = headers
-            .iter()
-            .find(|(k, _)| k.to_lowercase() == "x-xsrf-token")
-            .map(|(_, v)| v);
+    Ok(valid_session)
+}
 
-        info!(
-            "HEADLESS RESULT -> course_id={}: lti_scid={:?}, xsrf_token={:?}, cookies_count={}",
-            course_id,
-            scid,
-            xsrf_token,
-            cookies.len(),
-        );
+/// Expiry of the stored session when it falls within `zoom.refresh_within_hours`.
+fn expiring_soon(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<Option<i64>, Error> {
+    if cfg.zoom.refresh_within_hours == 0 {
+        return Ok(None);
     }
+    let window = cfg.zoom.refresh_within_hours as i64 * 3600;
+    let now = chrono::Utc::now().timestamp();
+    Ok(db
+        .get_session_expiry(course_id)?
+        .and_then(|s| s.expires_at)
+        .filter(|at| at - now <= window))
+}
 
-    println!("Starting listing and download for course {}", course_id);
-
-    // 2. List recordings using captured credentials
-    let client = ZoomClient::new(&cfg, &db, course_id)
-        .await
-        .map_err(map_api_err)?;
-
-    let listing = client
-        .list_recordings(since.as_deref())
+/// Re-run only the LTI launch: a Canvas sessionless launch URL opened headlessly
+/// yields a fresh scid, API headers and cookies without any sign-in.
+async fn relaunch_lti(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<(), Error> {
+    let tool_id = discover_tool_id(cfg, db, course_id).await?;
+    let canvas = crate::canvas::CanvasClient::from_config().await?;
+    let launch = canvas.sessionless_launch(course_id, tool_id).await?;
+    report!(
+        "{}",
+        tr!(
+            "Relaunching the Zoom LTI for course {} (no sign-in)...",
+            course_id
+        )
+    );
+    ZoomHeadless::new(cfg, db, course_id)
+        .capture_from_launch(&launch.url)
         .await
-        .map_err(map_api_err)?;
-    db.save_meetings(course_id, &listing)?;
+}
 
-    let meetings: Vec<RecordingSummary> = listing
-        .result
-        .as_ref()
-        .and_then(|r| r.list.as_ref())
-        .cloned()
-        .unwrap_or_default();
+/// Re-validate a course's stored Zoom session and renew it when it is invalid or
+/// close to expiring; `force` relaunches the LTI regardless.
+pub async fn zoom_refresh(course_id: u64, force: bool) -> Result<(), Error> {
+    let cfg = Config::load_or_init()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
 
-    if meetings.is_empty() {
-        println!("No Zoom meetings were found for course {course_id}.");
-    } else {
-        println!(
-            "Captured {} Zoom meetings; fetching individual recording files...",
-            meetings.len()
+    if cfg.zoom.api.is_some() {
+        report!(
+            "{}",
+            tr!("zoom.api is configured; the REST backend needs no browser session.")
         );
-        for meeting in &meetings {
-            println!(
-                "Found Meeting: ID={}, Topic='{}', Start={}",
-                meeting.meeting_id,
-                meeting.topic.as_deref().unwrap_or("N/A"),
-                meeting.start_time.as_deref().unwrap_or("N/A")
+        return Ok(());
+    }
+    if force {
+        relaunch_lti(&cfg, &db, course_id).await?;
+    } else {
+        ensure_session(&cfg, &db, course_id).await?;
+    }
+    match db.get_session_expiry(course_id)?.and_then(|s| s.expires_at) {
+        Some(at) => report!(
+            "{}",
+            tr!(
+                "Zoom session for course {} is valid until {}",
+                course_id,
+                local_time(at)
+            )
+        ),
+        None => report!(
+            "{}",
+            tr!(
+                "Zoom session for course {} is valid (no cookie expiry recorded)",
+                course_id
+            )
+        ),
+    }
+    Ok(())
+}
+
+fn local_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Find the course's Zoom LTI tool in Canvas and cache its id, so the headless
+/// capture opens the right tool without a hand-edited `zoom.external_tool_id`.
+/// Keeps the configured id when Canvas shows no Zoom tool or more than one.
+pub async fn discover_tool_id(cfg: &Config, db: &ZoomDb, course_id: u64) -> Result<u64, Error> {
+    if let Some(id) = db.get_tool_id(course_id)? {
+        return Ok(id);
+    }
+    let canvas = match crate::canvas::CanvasClient::from_config().await {
+        Ok(canvas) => canvas,
+        Err(e) => {
+            warn!(course_id, error = %e, "cannot query Canvas for the Zoom tool; using zoom.external_tool_id");
+            return Ok(cfg.zoom.external_tool_id);
+        }
+    };
+    // Listing tools needs teacher permissions; students only see the course tabs
+    let mut ids = match canvas.list_external_tools(course_id).await {
+        Ok(tools) => zoom_tool_ids(&tools),
+        Err(e) => {
+            info!(course_id, error = %e, "external tools not listable, checking course tabs");
+            Vec::new()
+        }
+    };
+    if ids.is_empty() {
+        ids = match canvas.list_tabs(course_id).await {
+            Ok(tabs) => zoom_tab_tool_ids(&tabs),
+            Err(e) => {
+                warn!(course_id, error = %e, "unable to list course tabs");
+                Vec::new()
+            }
+        };
+    }
+    match ids.as_slice() {
+        [id] => {
+            db.save_tool_id(course_id, *id)?;
+            info!(course_id, tool_id = id, "discovered Zoom LTI tool");
+            Ok(*id)
+        }
+        [] => {
+            warn!(
+                course_id,
+                fallback = cfg.zoom.external_tool_id,
+                "no Zoom LTI tool found in course; using zoom.external_tool_id"
             );
+            Ok(cfg.zoom.external_tool_id)
+        }
+        _ => {
+            warn!(course_id, candidates = ?ids, fallback = cfg.zoom.external_tool_id, "several Zoom LTI tools in course; using zoom.external_tool_id");
+            Ok(cfg.zoom.external_tool_id)
         }
     }
+}
 
-    // 3. Fetch recording files (API)
-    let mut all_files: Vec<ZoomRecordingFile> = Vec::new();
-    let meeting_progress = progress_bar(
-        meetings.len() as u64,
-        &format!("Gathering recording files for course {}", course_id),
-    );
-    for summary in meetings {
-        meeting_progress.inc(1);
-        meeting_progress.set_message(format!("Meeting {}", summary.meeting_id));
-        let files = client
-            .fetch_recording_files(&summary)
-            .await
-            .map_err(map_api_err)?;
-        if files.is_empty() {
-            meeting_progress.println(format!(
-                "- {}: Zoom did not report downloadable files",
-                summary.meeting_id
-            ));
-            continue;
+fn is_zoom(text: Option<&str>) -> bool {
+    text.is_some_and(|t| t.to_ascii_lowercase().contains("zoom"))
+}
+
+/// Ids of installed tools that look like the Zoom LTI.
+fn zoom_tool_ids(tools: &[ExternalTool]) -> Vec<u64> {
+    let mut ids: Vec<u64> = tools
+        .iter()
+        .filter(|t| {
+            is_zoom(t.name.as_deref()) || is_zoom(t.domain.as_deref()) || is_zoom(t.url.as_deref())
+        })
+        .map(|t| t.id)
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Ids of LTI tabs (`context_external_tool_<id>`) labelled Zoom.
+fn zoom_tab_tool_ids(tabs: &[Tab]) -> Vec<u64> {
+    let mut ids: Vec<u64> = tabs
+        .iter()
+        .filter(|t| is_zoom(t.label.as_deref()))
+        .filter_map(|t| t.id.strip_prefix("context_external_tool_")?.parse().ok())
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Canvas name of the course, for the MP4 metadata; Zoom only knows the course id.
+async fn course_name(course_id: u64) -> Option<String> {
+    let canvas = crate::canvas::CanvasClient::from_config().await.ok()?;
+    canvas
+        .list_courses()
+        .await
+        .ok()?
+        .into_iter()
+        .find(|c| c.id == course_id)
+        .map(|c| c.name)
+}
+
+/// A cached recording file as printed by `zoom list`.
+#[derive(Debug, Serialize)]
+struct ListedFile {
+    file_id: String,
+    file_type: Option<String>,
+    file_size: Option<u64>,
+    view: Option<String>,
+    status: &'static str,
+    path: Option<String>,
+}
+
+/// A cached meeting as printed by `zoom list`.
+#[derive(Debug, Serialize)]
+struct ListedMeeting {
+    meeting_id: String,
+    topic: Option<String>,
+    start_time: Option<String>,
+    duration: Option<i64>,
+    files: Vec<ListedFile>,
+}
+
+/// Print the meetings and recording files cached by the last `zoom flow` for a
+/// course, with their download state, without contacting Zoom.
+pub fn zoom_list(course_id: u64, json: bool) -> Result<(), Error> {
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+
+    let mut listed = Vec::new();
+    for meeting in db.load_meetings(course_id)? {
+        let mut files = Vec::new();
+        for file in db.load_files(&meeting.meeting_id)? {
+            let download = db.get_download(&file.meeting_id, file.file_key())?;
+            files.push(ListedFile {
+                file_id: file.file_key().to_string(),
+                file_type: file.file_type.clone(),
+                file_size: file.file_size,
+                view: file.view().map(str::to_string),
+                status: download_label(download.as_ref()),
+                path: download.map(|d| d.path.display().to_string()),
+            });
         }
-        db.save_files(course_id, &summary.meeting_id, &files)?;
-        meeting_progress.println(format!(
-            "- {}: captured {} playUrl entries",
-            summary.meeting_id,
-            files.len()
-        ));
-        all_files.extend(files.into_iter());
+        listed.push(ListedMeeting {
+            meeting_id: meeting.meeting_id,
+            topic: meeting.topic,
+            start_time: meeting.start_time,
+            duration: meeting.duration,
+            files,
+        });
     }
-    meeting_progress.finish_and_clear();
 
-    if all_files.is_empty() {
-        println!(
-            "No recordings with playUrl entries were available after the full flow; try again or verify permissions."
+    if json {
+        report!("{}", serde_json::to_string_pretty(&listed)?);
+        return Ok(());
+    }
+    if listed.is_empty() {
+        report!(
+            "{}",
+            tr!("No cached Zoom meetings for course {}.", course_id)
+        );
+        report!(
+            "{}",
+            tr!(
+                "Run 'u_crawler zoom flow --course-id {}' to list them from Zoom.",
+                course_id
+            )
         );
         return Ok(());
     }
-
-    // 4. Capture play URLs and download immediately (one by one to avoid token expiration)
-    println!("Starting capture and download (tokens expire quickly, processing one by one)...");
-    headless
-        .capture_and_download_immediately(&cfg, &db, course_id, all_files, concurrency)
-        .await?;
-
-    println!("All recordings processed!");
+    for meeting in &listed {
+        report!(
+            "{}  {}  {}{}",
+            meeting
+                .start_time
+                .clone()
+                .unwrap_or_else(|| tr!("unknown date")),
+            meeting.meeting_id,
+            meeting.topic.clone().unwrap_or_else(|| tr!("(no topic)")),
+            meeting
+                .duration
+                .map(|d| format!(" ({d} min)"))
+                .unwrap_or_default()
+        );
+        if meeting.files.is_empty() {
+            report!("{}", tr!("    no recording files cached"));
+        }
+        for file in &meeting.files {
+            report!(
+                "    {:<12} {:>10}  {}{}",
+                file.file_type.as_deref().unwrap_or("MP4"),
+                file.file_size
+                    .map(format_bytes)
+                    .unwrap_or_else(|| "-".into()),
+                file.status,
+                file.view
+                    .as_deref()
+                    .map(|v| format!("  [{v}]"))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    let files: Vec<&ListedFile> = listed.iter().flat_map(|m| &m.files).collect();
+    report!(
+        "{}",
+        tr!(
+            "{} meeting(s), {} file(s), {} downloaded",
+            listed.len(),
+            files.len(),
+            files.iter().filter(|f| f.status == "downloaded").count()
+        )
+    );
     Ok(())
 }
 
-fn map_api_err(err: ZoomApiError) -> Box<dyn Error> {
-    match err {
-        ZoomApiError::Db(e) => e,
-        other => Box::new(other),
+/// Download state of a recording file as shown by `zoom list`.
+fn download_label(download: Option<&ZoomDownload>) -> &'static str {
+    match download {
+        None => "not downloaded",
+        Some(d) if d.is_intact() => "downloaded",
+        Some(d) => match d.status {
+            DownloadStatus::Complete => "missing",
+            DownloadStatus::InProgress => "partial",
+            DownloadStatus::Failed => "failed",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_zoom_tools_and_tabs() {
+        let tools: Vec<ExternalTool> = serde_json::from_str(
+            r#"[{"id": 12, "name": "Turnitin", "url": "https://api.turnitin.com/lti", "domain": null},
+                {"id": 187, "name": "Zoom", "url": "https://applications.zoom.us/lti/advantage", "domain": "applications.zoom.us"}]"#,
+        )
+        .unwrap();
+        assert_eq!(zoom_tool_ids(&tools), vec![187]);
+
+        let tabs: Vec<Tab> = serde_json::from_str(
+            r#"[{"id": "home", "label": "Inicio", "type": "internal"},
+                {"id": "context_external_tool_187", "label": "Zoom", "type": "external"},
+                {"id": "context_external_tool_190", "label": "Zoom (anterior)", "type": "external"}]"#,
+        )
+        .unwrap();
+        assert_eq!(zoom_tab_tool_ids(&tabs), vec![187, 190]);
     }
 }
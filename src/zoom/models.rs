@@ -1,5 +1,9 @@
+use crate::fsutil::parse_timestamp;
+use chrono::{DateTime, Local, NaiveDate};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoomCookie {
@@ -15,6 +19,9 @@ pub struct ZoomCookie {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ZoomRecordingFile {
     pub meeting_id: String,
+    /// Zoom's id for this recording file, when the listing provides one.
+    #[serde(default)]
+    pub file_id: Option<String>,
     pub play_url: String,
     pub download_url: Option<String>,
     pub file_type: Option<String>,
@@ -23,6 +30,18 @@ pub struct ZoomRecordingFile {
     pub start_time: Option<String>,
     pub timezone: Option<String>,
     pub meeting_number: Option<String>,
+    /// Size in bytes reported by the recording list, when available.
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    /// Meeting duration in minutes, copied from the meeting summary.
+    #[serde(default)]
+    pub duration: Option<i64>,
+    /// Video layout, e.g. `speaker_view` or `shared_screen_with_speaker_view`.
+    #[serde(default)]
+    pub recording_type: Option<String>,
+    /// Passcode protecting a shared recording's player page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passcode: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +65,252 @@ impl ZoomRecordingFile {
             parts.join(" - ")
         }
     }
+
+    /// Stable key for this file within its meeting: Zoom's file id, else the play URL.
+    pub fn file_key(&self) -> &str {
+        self.file_id.as_deref().unwrap_or(&self.play_url)
+    }
+
+    /// Whether this entry is the meeting video (the only kind that needs the player to capture a URL).
+    pub fn is_video(&self) -> bool {
+        self.file_type
+            .as_deref()
+            .is_none_or(|t| t.eq_ignore_ascii_case("MP4"))
+    }
+
+    /// Layout of a video file, when Zoom reports one.
+    pub fn view(&self) -> Option<&str> {
+        self.recording_type
+            .as_deref()
+            .filter(|v| self.is_video() && !v.is_empty())
+    }
+
+    /// File extension used when saving this entry.
+    pub fn extension(&self) -> &'static str {
+        match self
+            .file_type
+            .as_deref()
+            .map(|t| t.to_ascii_uppercase())
+            .as_deref()
+        {
+            Some("TRANSCRIPT" | "CC") => "vtt",
+            Some("CHAT") => "txt",
+            Some("TIMELINE") => "json",
+            Some("M4A") => "m4a",
+            _ => "mp4",
+        }
+    }
+
+    /// Folder holding everything recorded for this meeting: `YYYY-MM-DD - Topic`.
+    pub fn meeting_folder(&self) -> String {
+        let name = self.filename_hint();
+        let cleaned = sanitize_filename::sanitize(name.trim());
+        let cleaned = cleaned.trim_end_matches(['.', ' ']);
+        if cleaned.is_empty() {
+            format!("zoom-{}", sanitize_filename::sanitize(&self.meeting_id))
+        } else {
+            cleaned.chars().take(120).collect()
+        }
+    }
+}
+
+/// Keep only the preferred video layouts of each meeting, plus all non-video files.
+/// A meeting without any preferred layout keeps all its videos rather than none.
+pub fn select_views(files: Vec<ZoomRecordingFile>, preferred: &[String]) -> Vec<ZoomRecordingFile> {
+    let is_preferred = |f: &ZoomRecordingFile| {
+        f.view()
+            .is_some_and(|v| preferred.iter().any(|p| p.eq_ignore_ascii_case(v)))
+    };
+    let meetings_with_preferred: HashSet<String> = files
+        .iter()
+        .filter(|f| is_preferred(f))
+        .map(|f| f.meeting_id.clone())
+        .collect();
+    files
+        .into_iter()
+        .filter(|f| {
+            preferred.is_empty()
+                || !f.is_video()
+                || is_preferred(f)
+                || !meetings_with_preferred.contains(&f.meeting_id)
+        })
+        .collect()
+}
+
+/// Which cached meetings `zoom download` should fetch. Empty criteria match everything.
+#[derive(Debug, Clone, Default)]
+pub struct MeetingFilter {
+    pub meeting_ids: Vec<String>,
+    /// Earliest meeting date, inclusive.
+    pub since: Option<NaiveDate>,
+    /// Latest meeting date, inclusive.
+    pub until: Option<NaiveDate>,
+    pub topic: Option<Regex>,
+}
+
+impl MeetingFilter {
+    pub fn matches(&self, meeting: &RecordingSummary) -> bool {
+        if !self.meeting_ids.is_empty() && !self.meeting_ids.contains(&meeting.meeting_id) {
+            return false;
+        }
+        if let Some(re) = &self.topic {
+            if !re.is_match(meeting.topic.as_deref().unwrap_or("")) {
+                return false;
+            }
+        }
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        // A meeting without a readable date cannot satisfy a date range
+        let Some(date) = meeting
+            .start_time
+            .as_deref()
+            .and_then(parse_timestamp)
+            .map(|t| DateTime::<Local>::from(t).date_naive())
+        else {
+            return false;
+        };
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+}
+
+/// Progress of a recording file download, as tracked in `zoom_downloads`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadStatus {
+    InProgress,
+    Complete,
+    Failed,
+}
+
+impl DownloadStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InProgress => "in_progress",
+            Self::Complete => "complete",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Unknown values are treated as unfinished so the file is fetched again.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "complete" => Self::Complete,
+            "failed" => Self::Failed,
+            _ => Self::InProgress,
+        }
+    }
+}
+
+/// A Zoom recording link found in a Canvas page, module item or assignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredLink {
+    pub url: String,
+    /// Title of the Canvas page, item or assignment the link was posted in.
+    pub label: Option<String>,
+    /// Player URL the link redirects to, once resolved.
+    pub play_url: Option<String>,
+    /// Meeting from the Zoom listing with the same player URL, if any.
+    pub meeting_id: Option<String>,
+}
+
+/// Whether a Zoom URL points at a cloud recording rather than a meeting to join.
+pub fn is_recording_link(url: &str) -> bool {
+    url.contains("/rec/share/") || url.contains("/rec/play/")
+}
+
+impl DiscoveredLink {
+    /// The link as a recording file for the download pipeline. Without a meeting
+    /// from the listing, the recording token stands in for the meeting id.
+    pub fn recording_file(&self) -> ZoomRecordingFile {
+        let play_url = self.play_url.clone().unwrap_or_else(|| self.url.clone());
+        let token = play_url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or_default();
+        ZoomRecordingFile {
+            meeting_id: format!("link-{}", token.chars().take(24).collect::<String>()),
+            file_id: None,
+            play_url,
+            download_url: None,
+            file_type: Some("MP4".into()),
+            recording_start: None,
+            topic: self.label.clone(),
+            start_time: None,
+            timezone: None,
+            meeting_number: None,
+            file_size: None,
+            duration: None,
+            recording_type: None,
+            passcode: None,
+        }
+    }
+}
+
+/// Cookies re-issued on every response, whose short lifetime says nothing about the session.
+const VOLATILE_COOKIES: &[&str] = &["__cf_bm", "_cfuvid", "cf_clearance"];
+
+/// When a captured Zoom session stops working: the earliest expiry among its
+/// persistent cookies. Session-only cookies carry no expiry and are ignored.
+pub fn session_expiry(cookies: &[ZoomCookie]) -> Option<i64> {
+    cookies
+        .iter()
+        .filter(|c| !VOLATILE_COOKIES.contains(&c.name.as_str()))
+        .filter_map(|c| c.expires.filter(|ts| *ts > 0))
+        .min()
+}
+
+/// Capture time and expected expiry of a course's stored Zoom session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionExpiry {
+    pub captured_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// Where a recording file was saved and what it looked like when it finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoomDownload {
+    pub meeting_id: String,
+    pub file_id: String,
+    pub status: DownloadStatus,
+    pub path: PathBuf,
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+}
+
+impl ZoomDownload {
+    /// Completed and still present on disk with the recorded size.
+    pub fn is_intact(&self) -> bool {
+        self.status == DownloadStatus::Complete
+            && std::fs::metadata(&self.path)
+                .map(|m| self.size.is_none_or(|s| s == m.len()))
+                .unwrap_or(false)
+    }
+}
+
+/// Contents of the `meeting.json` sidecar written next to each meeting's recordings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MeetingSidecar {
+    pub meeting_id: String,
+    pub topic: Option<String>,
+    pub meeting_number: Option<String>,
+    pub start_time: Option<String>,
+    pub timezone: Option<String>,
+    /// Duration in minutes.
+    pub duration: Option<i64>,
+}
+
+impl From<&ZoomRecordingFile> for MeetingSidecar {
+    fn from(file: &ZoomRecordingFile) -> Self {
+        Self {
+            meeting_id: file.meeting_id.clone(),
+            topic: file.topic.clone(),
+            meeting_number: file.meeting_number.clone(),
+            start_time: file.start_time.clone(),
+            timezone: file.timezone.clone(),
+            duration: file.duration,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -74,6 +339,17 @@ pub struct RecordingSummary {
     pub topic: Option<String>,
     pub start_time: Option<String>,
     pub timezone: Option<String>,
+    /// Duration in minutes; Zoom sends it as a number or a numeric string.
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub duration: Option<i64>,
+}
+
+fn lenient_i64<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(d)? {
+        Some(serde_json::Value::Number(n)) => n.as_i64(),
+        Some(serde_json::Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    })
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -93,6 +369,8 @@ pub struct RecordingFileResult {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RecordingFileEntry {
+    #[serde(default)]
+    pub id: Option<String>,
     #[serde(rename = "playUrl")]
     pub play_url: Option<String>,
     #[serde(rename = "downloadUrl")]
@@ -101,4 +379,169 @@ pub struct RecordingFileEntry {
     pub file_type: Option<String>,
     #[serde(rename = "recordingStart")]
     pub recording_start: Option<String>,
+    #[serde(rename = "fileSize", default)]
+    pub file_size: Option<u64>,
+    #[serde(rename = "recordingType", default)]
+    pub recording_type: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovered_links_become_recording_files() {
+        assert!(is_recording_link(
+            "https://unab.zoom.us/rec/share/AbC-12?startTime=1"
+        ));
+        assert!(!is_recording_link(
+            "https://unab.zoom.us/j/81234567890?pwd=x"
+        ));
+
+        let mut link = DiscoveredLink {
+            url: "https://unab.zoom.us/rec/share/AbC-12xyz?startTime=1".into(),
+            label: Some("Clase 4: grabación".into()),
+            play_url: None,
+            meeting_id: None,
+        };
+        let file = link.recording_file();
+        assert_eq!(file.meeting_id, "link-AbC-12xyz");
+        assert_eq!(file.play_url, link.url);
+        assert_eq!(file.meeting_folder(), "Clase 4 grabación");
+
+        link.play_url = Some("https://unab.zoom.us/rec/play/Zz9".into());
+        assert_eq!(link.recording_file().meeting_id, "link-Zz9");
+    }
+
+    #[test]
+    fn session_expiry_ignores_volatile_cookies() {
+        let cookie = |name: &str, expires: Option<i64>| ZoomCookie {
+            domain: "zoom.us".into(),
+            name: name.into(),
+            value: String::new(),
+            path: "/".into(),
+            expires,
+            secure: true,
+            http_only: true,
+        };
+        let cookies = [
+            cookie("__cf_bm", Some(1_000)),
+            cookie("_zm_ssid", Some(-1)),
+            cookie("_zm_lang", Some(90_000)),
+            cookie("cred", Some(50_000)),
+        ];
+        assert_eq!(session_expiry(&cookies), Some(50_000));
+        assert_eq!(session_expiry(&cookies[..2]), None);
+    }
+
+    #[test]
+    fn keeps_preferred_views_per_meeting() {
+        let file = |meeting: &str, kind: &str, view: Option<&str>| ZoomRecordingFile {
+            meeting_id: meeting.into(),
+            file_id: Some(format!("{meeting}-{kind}-{view:?}")),
+            play_url: String::new(),
+            download_url: None,
+            file_type: Some(kind.into()),
+            recording_start: None,
+            topic: None,
+            start_time: None,
+            timezone: None,
+            meeting_number: None,
+            file_size: None,
+            duration: None,
+            recording_type: view.map(str::to_string),
+            passcode: None,
+        };
+        let files = vec![
+            file("a", "MP4", Some("speaker_view")),
+            file("a", "MP4", Some("shared_screen_with_speaker_view")),
+            file("a", "TRANSCRIPT", None),
+            file("b", "MP4", Some("gallery_view")),
+        ];
+        let preferred = vec!["Shared_Screen_With_Speaker_View".to_string()];
+
+        let kept = select_views(files.clone(), &preferred);
+        let views: Vec<_> = kept
+            .iter()
+            .map(|f| (f.meeting_id.as_str(), f.view()))
+            .collect();
+        assert_eq!(
+            views,
+            [
+                ("a", Some("shared_screen_with_speaker_view")),
+                ("a", None),
+                ("b", Some("gallery_view")),
+            ]
+        );
+        assert_eq!(select_views(files, &[]).len(), 4);
+    }
+
+    #[test]
+    fn meeting_filter_combines_criteria() {
+        let meeting = |id: &str, topic: &str, start: &str| RecordingSummary {
+            meeting_id: id.into(),
+            meeting_number: None,
+            topic: Some(topic.into()),
+            start_time: Some(start.into()),
+            timezone: None,
+            duration: None,
+        };
+        let march = meeting("a", "Cálculo: clase 3", "2024-03-01 10:00:00");
+        let april = meeting("b", "Álgebra: repaso", "Apr 02, 2024 09:30 AM");
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+
+        assert!(MeetingFilter::default().matches(&march));
+        let by_id = MeetingFilter {
+            meeting_ids: vec!["b".into()],
+            ..Default::default()
+        };
+        assert!(!by_id.matches(&march) && by_id.matches(&april));
+        let range = MeetingFilter {
+            since: date("2024-03-01"),
+            until: date("2024-03-31"),
+            ..Default::default()
+        };
+        assert!(range.matches(&march) && !range.matches(&april));
+        let topic = MeetingFilter {
+            topic: Regex::new("(?i)repaso").ok(),
+            ..Default::default()
+        };
+        assert!(!topic.matches(&march) && topic.matches(&april));
+    }
+
+    #[test]
+    fn meeting_folder_and_companion_files() {
+        let summary: RecordingSummary = serde_json::from_str(
+            r#"{"meetingId":"abc/1=","topic":"Cálculo: clase 3","startTime":"2024-03-01 10:00:00","duration":"52"}"#,
+        )
+        .unwrap();
+        assert_eq!(summary.duration, Some(52));
+
+        let mut file = ZoomRecordingFile {
+            meeting_id: summary.meeting_id.clone(),
+            file_id: None,
+            play_url: "https://zoom.us/rec/play/1".into(),
+            download_url: None,
+            file_type: Some("MP4".into()),
+            recording_start: None,
+            topic: summary.topic.clone(),
+            start_time: summary.start_time.clone(),
+            timezone: None,
+            meeting_number: None,
+            file_size: None,
+            duration: summary.duration,
+            recording_type: Some("speaker_view".into()),
+            passcode: None,
+        };
+        assert_eq!(file.meeting_folder(), "2024-03-01 - Cálculo clase 3");
+        assert!(file.is_video());
+        assert_eq!(file.extension(), "mp4");
+        assert_eq!(file.view(), Some("speaker_view"));
+
+        file.file_type = Some("TRANSCRIPT".into());
+        assert!(!file.is_video());
+        assert_eq!(file.view(), None);
+        assert_eq!(file.extension(), "vtt");
+        assert_eq!(MeetingSidecar::from(&file).duration, Some(52));
+    }
 }
@@ -0,0 +1,505 @@
+use crate::config::{Config, ZoomApi};
+use crate::error::Error;
+use crate::progress::{download_bar, multi_progress};
+use crate::zoom::db::ZoomDb;
+use crate::zoom::headless::{
+    display_name, fetch_recording, finish_downloads, plan_downloads, settle_download, FetchOutcome,
+    RecordingsDir,
+};
+use crate::zoom::models::{
+    DownloadStatus, RecordingListResponse, RecordingSummary, RecordingsResult, ZoomDownload,
+    ZoomRecordingFile,
+};
+use crate::{report, tr};
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate};
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{debug, info};
+
+const OAUTH_URL: &str = "https://zoom.us/oauth/token";
+const API_BASE: &str = "https://api.zoom.us/v2";
+/// The recordings endpoint accepts at most one month per request.
+const WINDOW_DAYS: i64 = 30;
+/// How far back listings go without `--since`.
+const DEFAULT_LOOKBACK_DAYS: i64 = 365;
+
+#[derive(Debug, Error)]
+pub enum ZoomRestError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("Zoom API returned {0}: {1}")]
+    Status(u16, String),
+    #[error("--since must be a date like 2024-03-01, got '{0}'")]
+    BadSince(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordingsPage {
+    #[serde(default)]
+    meetings: Vec<ApiMeeting>,
+    next_page_token: Option<String>,
+}
+
+/// A meeting with cloud recordings, as returned by `GET /users/{id}/recordings`.
+#[derive(Debug, Deserialize)]
+struct ApiMeeting {
+    uuid: String,
+    id: Option<u64>,
+    topic: Option<String>,
+    start_time: Option<String>,
+    timezone: Option<String>,
+    duration: Option<i64>,
+    #[serde(default)]
+    recording_files: Vec<ApiRecordingFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRecordingFile {
+    id: Option<String>,
+    file_type: Option<String>,
+    file_size: Option<u64>,
+    play_url: Option<String>,
+    download_url: Option<String>,
+    recording_start: Option<String>,
+    recording_type: Option<String>,
+    status: Option<String>,
+}
+
+/// Zoom REST API client authenticated as a Server-to-Server OAuth app.
+pub struct ZoomRestClient {
+    http: Client,
+    api: ZoomApi,
+    token: String,
+}
+
+impl ZoomRestClient {
+    pub async fn connect(cfg: &Config, api: &ZoomApi) -> Result<Self, ZoomRestError> {
+        let http = crate::http::with_proxy(Client::builder(), cfg).build()?;
+        let mut client = Self {
+            http,
+            api: api.clone(),
+            token: String::new(),
+        };
+        client.refresh_token().await?;
+        Ok(client)
+    }
+
+    /// Fetch a new access token; they expire after an hour.
+    pub async fn refresh_token(&mut self) -> Result<(), ZoomRestError> {
+        let resp = self
+            .http
+            .post(OAUTH_URL)
+            .basic_auth(&self.api.client_id, Some(&self.api.client_secret))
+            .form(&[
+                ("grant_type", "account_credentials"),
+                ("account_id", self.api.account_id.as_str()),
+            ])
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ZoomRestError::Status(status.as_u16(), body));
+        }
+        self.token = resp.json::<TokenResponse>().await?.access_token;
+        debug!("refreshed Zoom API access token");
+        Ok(())
+    }
+
+    fn auth_header(&self) -> (String, String) {
+        (
+            "Authorization".to_string(),
+            format!("Bearer {}", self.token),
+        )
+    }
+
+    /// Meetings with cloud recordings between `from` and `to`, one month at a time.
+    async fn list_meetings(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ApiMeeting>, ZoomRestError> {
+        let url = format!(
+            "{}/users/{}/recordings",
+            API_BASE,
+            urlencoding::encode(&self.api.user_id)
+        );
+        let mut meetings = Vec::new();
+        for (start, end) in month_windows(from, to) {
+            let mut next_page: Option<String> = None;
+            loop {
+                let (start, end) = (start.to_string(), end.to_string());
+                let mut query = vec![
+                    ("from", start.as_str()),
+                    ("to", end.as_str()),
+                    ("page_size", "300"),
+                ];
+                if let Some(token) = next_page.as_deref() {
+                    query.push(("next_page_token", token));
+                }
+                let resp = self
+                    .http
+                    .get(&url)
+                    .bearer_auth(&self.token)
+                    .query(&query)
+                    .send()
+                    .await?;
+                let status = resp.status();
+                if !status.is_success() {
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(ZoomRestError::Status(status.as_u16(), body));
+                }
+                let page: RecordingsPage = resp.json().await?;
+                meetings.extend(page.meetings);
+                next_page = page.next_page_token.filter(|t| !t.is_empty());
+                if next_page.is_none() {
+                    break;
+                }
+            }
+        }
+        Ok(meetings)
+    }
+}
+
+/// Consecutive date ranges of at most `WINDOW_DAYS` covering `from..=to`.
+fn month_windows(from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut windows = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = (start + ChronoDuration::days(WINDOW_DAYS - 1)).min(to);
+        windows.push((start, end));
+        start = end + ChronoDuration::days(1);
+    }
+    windows
+}
+
+/// API timestamps are UTC RFC 3339; the LTI listing (and folder names) use local time.
+fn local_time(s: &str) -> String {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| s.to_string())
+}
+
+impl ApiMeeting {
+    fn summary(&self) -> RecordingSummary {
+        RecordingSummary {
+            meeting_id: self.uuid.clone(),
+            meeting_number: self.id.map(|id| id.to_string()),
+            topic: self.topic.clone(),
+            start_time: self.start_time.as_deref().map(local_time),
+            timezone: self.timezone.clone(),
+            duration: self.duration,
+        }
+    }
+
+    /// Finished recording files with a download URL, in the shared file model.
+    fn files(&self) -> Vec<ZoomRecordingFile> {
+        self.recording_files
+            .iter()
+            .filter(|f| f.status.as_deref().is_none_or(|s| s == "completed"))
+            .filter_map(|f| {
+                let download_url = f.download_url.clone()?;
+                Some(ZoomRecordingFile {
+                    meeting_id: self.uuid.clone(),
+                    file_id: f.id.clone(),
+                    play_url: f.play_url.clone().unwrap_or_else(|| download_url.clone()),
+                    download_url: Some(download_url),
+                    file_type: f.file_type.clone(),
+                    recording_start: f.recording_start.as_deref().map(local_time),
+                    topic: self.topic.clone(),
+                    start_time: self.start_time.as_deref().map(local_time),
+                    timezone: self.timezone.clone(),
+                    meeting_number: self.id.map(|id| id.to_string()),
+                    file_size: f.file_size,
+                    duration: self.duration,
+                    recording_type: f.recording_type.clone(),
+                    passcode: None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// List a course's recordings through the REST API, cache them like the LTI
+/// listing, and download them.
+pub async fn rest_flow(
+    cfg: &Config,
+    db: &ZoomDb,
+    api: &ZoomApi,
+    course_id: u64,
+    concurrency: usize,
+    since: Option<&str>,
+    course_name: Option<&str>,
+) -> Result<(), Error> {
+    let today = Local::now().date_naive();
+    let from = match since {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map_err(|_| ZoomRestError::BadSince(s.to_string()))?,
+        None => today - ChronoDuration::days(DEFAULT_LOOKBACK_DAYS),
+    };
+    let topic = api
+        .course_topics
+        .get(&course_id.to_string())
+        .map(|p| Regex::new(p))
+        .transpose()?;
+
+    report!(
+        "{}",
+        tr!(
+            "Listing Zoom cloud recordings for {} via the REST API...",
+            api.user_id
+        )
+    );
+    let mut client = ZoomRestClient::connect(cfg, api).await?;
+    let meetings: Vec<ApiMeeting> = client
+        .list_meetings(from, today)
+        .await?
+        .into_iter()
+        .filter(|m| {
+            topic
+                .as_ref()
+                .is_none_or(|re| re.is_match(m.topic.as_deref().unwrap_or("")))
+        })
+        .collect();
+    info!(
+        course_id,
+        meetings = meetings.len(),
+        "listed Zoom recordings via REST API"
+    );
+
+    let summaries: Vec<RecordingSummary> = meetings.iter().map(ApiMeeting::summary).collect();
+    db.save_meetings(
+        course_id,
+        &RecordingListResponse {
+            status: Some(true),
+            code: None,
+            result: Some(RecordingsResult {
+                page_num: None,
+                page_size: None,
+                total: Some(summaries.len() as i64),
+                list: Some(summaries),
+            }),
+        },
+    )?;
+    let mut files = Vec::new();
+    for meeting in &meetings {
+        let meeting_files = meeting.files();
+        report!(
+            "{}",
+            tr!(
+                "- {} ({}): {} file(s)",
+                meeting.topic.as_deref().unwrap_or("N/A"),
+                meeting.start_time.as_deref().unwrap_or("N/A"),
+                meeting_files.len()
+            )
+        );
+        db.save_files(course_id, &meeting.uuid, &meeting_files)?;
+        files.extend(meeting_files);
+    }
+    if files.is_empty() {
+        report!(
+            "{}",
+            tr!("No Zoom cloud recordings matched course {}.", course_id)
+        );
+        return Ok(());
+    }
+    download_files(
+        cfg,
+        db,
+        &mut client,
+        course_id,
+        course_name,
+        files,
+        concurrency,
+    )
+    .await
+}
+
+/// Download recording files straight from their API URLs with the shared
+/// planning, retry and metadata steps of the headless pipeline.
+pub async fn download_files(
+    cfg: &Config,
+    db: &ZoomDb,
+    client: &mut ZoomRestClient,
+    course_id: u64,
+    course_name: Option<&str>,
+    files: Vec<ZoomRecordingFile>,
+    concurrency: usize,
+) -> Result<(), Error> {
+    let recordings = RecordingsDir::resolve(cfg, db, course_id).await?;
+    let base = &recordings.base;
+    let (all_files, files_to_download) =
+        plan_downloads(db, &recordings, files, &cfg.zoom.preferred_views).await?;
+    if files_to_download.is_empty() {
+        report!("{}", tr!("All recordings already downloaded!"));
+        return Ok(());
+    }
+    if cfg.zoom.embed_metadata || cfg.zoom.transcode.is_some() {
+        crate::ffmpeg::ensure_ffmpeg_available(&cfg.zoom.ffmpeg_path).await?;
+    }
+    let needed: u64 = files_to_download
+        .iter()
+        .filter_map(|(f, _)| f.file_size)
+        .sum();
+    crate::fsutil::check_free_space(base, needed, cfg.free_space_reserve())?;
+    report!(
+        "{}",
+        tr!(
+            "Found {} recordings, {} new to download",
+            all_files.len(),
+            files_to_download.len()
+        )
+    );
+
+    let slots = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut downloads: JoinSet<(usize, u32, FetchOutcome)> = JoinSet::new();
+    let shared_cfg = Arc::new(cfg.clone());
+    let bars = multi_progress();
+    let mut queue: VecDeque<(usize, u32)> = (0..files_to_download.len()).map(|i| (i, 0)).collect();
+    let mut token_stale = false;
+    loop {
+        if crate::shutdown::requested() && !queue.is_empty() {
+            // Let running downloads finish, but start no more
+            info!(
+                course_id,
+                skipped = queue.len(),
+                "interrupted; not starting the remaining recordings"
+            );
+            queue.clear();
+        }
+        let Some((idx, attempt)) = queue.pop_front() else {
+            match downloads.join_next().await {
+                Some(done) => {
+                    token_stale |= settle_download(db, cfg, &files_to_download, &mut queue, done?)?;
+                    continue;
+                }
+                None => break,
+            }
+        };
+        let permit = slots.clone().acquire_owned().await?;
+        while let Some(done) = downloads.try_join_next() {
+            token_stale |= settle_download(db, cfg, &files_to_download, &mut queue, done?)?;
+        }
+        if token_stale {
+            client.refresh_token().await?;
+            token_stale = false;
+        }
+        let (file, dest) = &files_to_download[idx];
+        let Some(url) = file.download_url.clone() else {
+            continue;
+        };
+        db.save_download(&ZoomDownload {
+            meeting_id: file.meeting_id.clone(),
+            file_id: file.file_key().to_string(),
+            status: DownloadStatus::InProgress,
+            path: dest.clone(),
+            size: None,
+            sha1: None,
+        })?;
+        let headers = vec![client.auth_header()];
+        let (cfg, dest) = (shared_cfg.clone(), dest.clone());
+        let recorded_at = file
+            .recording_start
+            .clone()
+            .or_else(|| file.start_time.clone());
+        let bar = bars.add(download_bar(file.file_size, &display_name(&dest)));
+        downloads.spawn(async move {
+            let _permit = permit;
+            let outcome = fetch_recording(
+                &cfg,
+                false,
+                &headers,
+                &url,
+                &dest,
+                recorded_at.as_deref(),
+                bar,
+            )
+            .await;
+            (idx, attempt, outcome)
+        });
+    }
+
+    finish_downloads(
+        cfg,
+        db,
+        &recordings,
+        &all_files,
+        &files_to_download,
+        course_name,
+    )
+    .await?;
+    crate::shutdown::check()?;
+    report!(
+        "{}",
+        tr!(
+            "\nAll files processed! Downloads saved to: {}",
+            base.display()
+        )
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_cover_the_range_in_month_steps() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let windows = month_windows(date("2024-01-01"), date("2024-03-15"));
+        assert_eq!(windows.first().unwrap().0, date("2024-01-01"));
+        assert_eq!(windows.last().unwrap().1, date("2024-03-15"));
+        assert!(windows
+            .iter()
+            .all(|(s, e)| (*e - *s).num_days() < WINDOW_DAYS));
+        assert!(windows
+            .windows(2)
+            .all(|w| w[1].0 == w[0].1 + ChronoDuration::days(1)));
+        assert!(month_windows(date("2024-02-01"), date("2024-01-01")).is_empty());
+    }
+
+    #[test]
+    fn maps_api_meetings_to_shared_models() {
+        let meeting: ApiMeeting = serde_json::from_str(
+            r#"{"uuid": "abc/1==", "id": 81234567890, "topic": "Cálculo: clase 3",
+                "start_time": "2024-03-01T15:00:00Z", "duration": 52, "recording_files": [
+                {"id": "f1", "file_type": "MP4", "file_size": 1024, "status": "completed",
+                 "play_url": "https://zoom.us/rec/play/1", "download_url": "https://zoom.us/rec/download/1"},
+                {"id": "f2", "file_type": "TRANSCRIPT", "status": "completed",
+                 "download_url": "https://zoom.us/rec/download/2"},
+                {"id": "f3", "file_type": "MP4", "status": "processing",
+                 "download_url": "https://zoom.us/rec/download/3"}]}"#,
+        )
+        .unwrap();
+        let files = meeting.files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_key(), "f1");
+        assert!(files[0].is_video() && !files[1].is_video());
+        assert_eq!(files[1].play_url, "https://zoom.us/rec/download/2");
+        assert_eq!(files[0].meeting_number.as_deref(), Some("81234567890"));
+        let summary = meeting.summary();
+        assert_eq!(summary.meeting_id, "abc/1==");
+        assert_eq!(summary.duration, Some(52));
+        // Local-time format matches the LTI listing, so folder names agree
+        assert!(NaiveDate::parse_from_str(
+            summary.start_time.unwrap().split(' ').next().unwrap(),
+            "%Y-%m-%d"
+        )
+        .is_ok());
+    }
+}
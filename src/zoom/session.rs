@@ -0,0 +1,181 @@
+use crate::config::ConfigPaths;
+use crate::error::Error;
+use crate::fsutil::atomic_write;
+use crate::zoom::db::ZoomDb;
+use crate::zoom::models::{session_expiry, ZoomCookie};
+use crate::{report, tr};
+use chrono::Utc;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::Path;
+use thiserror::Error;
+
+/// Environment variable holding the passphrase for session files.
+pub const PASSPHRASE_ENV: &str = "U_CRAWLER_SESSION_PASSPHRASE";
+
+/// Request path the captured Zoom API headers are stored under.
+const HEADERS_PATH: &str = "/api/v1/lti/rich/recording";
+const MAGIC: &[u8; 5] = b"UCZS1";
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("set {PASSPHRASE_ENV} to the passphrase protecting the session file")]
+    MissingPassphrase,
+    #[error("not a u_crawler session file")]
+    BadFormat,
+    #[error("wrong passphrase or corrupted session file")]
+    Decrypt,
+    #[error("no Zoom session stored for course {0}; run 'u_crawler zoom flow' first")]
+    Empty(u64),
+    #[error("system random number generator unavailable")]
+    Random,
+}
+
+/// Everything `zoom flow` needs to skip headless SSO for a course.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub course_id: u64,
+    pub scid: String,
+    pub cookies: Vec<ZoomCookie>,
+    pub headers: Vec<(String, String)>,
+    pub exported_at: i64,
+}
+
+/// Write the stored Zoom session for a course to an encrypted file.
+pub async fn export_session(course_id: u64, out: &Path) -> Result<(), Error> {
+    let passphrase = passphrase()?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+    let bundle = SessionBundle {
+        course_id,
+        scid: db
+            .get_scid(course_id)?
+            .ok_or(SessionError::Empty(course_id))?,
+        cookies: db.load_cookies(course_id)?,
+        headers: db.get_all_request_headers(course_id)?,
+        exported_at: Utc::now().timestamp(),
+    };
+    let sealed = seal(&serde_json::to_vec(&bundle)?, &passphrase)?;
+    atomic_write(out, &sealed).await?;
+    report!(
+        "{}",
+        tr!(
+            "Exported Zoom session for course {} ({} cookies, {} headers) to {}",
+            course_id,
+            bundle.cookies.len(),
+            bundle.headers.len(),
+            out.display()
+        )
+    );
+    Ok(())
+}
+
+/// Replace the stored Zoom session with the one in an encrypted session file.
+pub async fn import_session(input: &Path) -> Result<(), Error> {
+    let passphrase = passphrase()?;
+    let sealed = tokio::fs::read(input).await?;
+    let bundle: SessionBundle = serde_json::from_slice(&open(&sealed, &passphrase)?)?;
+    let paths = ConfigPaths::new()?;
+    let db = ZoomDb::new(&paths.config_dir)?;
+    db.save_scid(bundle.course_id, &bundle.scid)?;
+    db.save_cookies(bundle.course_id, &bundle.cookies)?;
+    db.save_session_expiry(bundle.course_id, session_expiry(&bundle.cookies))?;
+    db.delete_all_request_headers(bundle.course_id)?;
+    db.save_request_headers(bundle.course_id, HEADERS_PATH, &bundle.headers)?;
+    report!(
+        "{}",
+        tr!(
+            "Imported Zoom session for course {} exported {}",
+            bundle.course_id,
+            chrono::DateTime::from_timestamp(bundle.exported_at, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| tr!("at an unknown time"))
+        )
+    );
+    Ok(())
+}
+
+fn passphrase() -> Result<String, SessionError> {
+    std::env::var(PASSPHRASE_ENV)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or(SessionError::MissingPassphrase)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).expect("32-byte key"))
+}
+
+/// Encrypt `plaintext` as `MAGIC | salt | nonce | ciphertext+tag`.
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, SessionError> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt).map_err(|_| SessionError::Random)?;
+    rng.fill(&mut nonce).map_err(|_| SessionError::Random)?;
+
+    let mut body = plaintext.to_vec();
+    derive_key(passphrase, &salt)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut body,
+        )
+        .map_err(|_| SessionError::Decrypt)?;
+    let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + body.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// Decrypt a buffer produced by [`seal`].
+pub fn open(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>, SessionError> {
+    let rest = sealed.strip_prefix(MAGIC).ok_or(SessionError::BadFormat)?;
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        return Err(SessionError::BadFormat);
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, body) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| SessionError::BadFormat)?;
+    let mut body = body.to_vec();
+    let plain = derive_key(passphrase, salt)
+        .open_in_place(nonce, Aad::from(MAGIC), &mut body)
+        .map_err(|_| SessionError::Decrypt)?;
+    Ok(plain.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_sessions_need_the_passphrase() {
+        let sealed = seal(b"{\"scid\":\"abc\"}", "correct horse").unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!sealed.windows(3).any(|w| w == b"abc"));
+        assert_eq!(
+            open(&sealed, "correct horse").unwrap(),
+            b"{\"scid\":\"abc\"}"
+        );
+        assert!(matches!(open(&sealed, "wrong"), Err(SessionError::Decrypt)));
+        assert!(matches!(
+            open(b"not a session", "correct horse"),
+            Err(SessionError::BadFormat)
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::ffmpeg::Chapter;
+use crate::tr;
+
+/// Longest chapter title taken from a transcript cue.
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Longest run of one speaker kept in a single paragraph of the notes.
+const PARAGRAPH_MS: u64 = 2 * 60 * 1000;
+
+/// Longest text before a colon that is taken for a speaker's name.
+const MAX_SPEAKER_CHARS: usize = 40;
+
+/// One timed line of a WebVTT transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Parse the cues of a WebVTT file, ignoring headers, cue ids and notes.
+pub fn parse_vtt(text: &str) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = line.split_once("-->") else {
+            continue;
+        };
+        // Cue settings may follow the end timestamp
+        let end = end.split_whitespace().next().unwrap_or("");
+        let (Some(start_ms), Some(end_ms)) = (parse_timestamp(start), parse_timestamp(end)) else {
+            continue;
+        };
+        let mut body = Vec::new();
+        while let Some(next) = lines.peek() {
+            if next.trim().is_empty() {
+                break;
+            }
+            body.push(next.trim());
+            lines.next();
+        }
+        cues.push(Cue {
+            start_ms,
+            end_ms,
+            text: body.join(" "),
+        });
+    }
+    cues
+}
+
+/// `HH:MM:SS.mmm` or `MM:SS.mmm` in milliseconds.
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (clock, millis) = s.split_once('.').unwrap_or((s, "0"));
+    let mut secs = 0u64;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(secs * 1000 + millis.parse::<u64>().ok()?)
+}
+
+/// Group transcript cues into chapters of roughly `interval_ms`, each titled with
+/// the first line spoken in it. The first chapter always starts at zero.
+pub fn chapters_from_cues(cues: &[Cue], interval_ms: u64) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for cue in cues.iter().filter(|c| !c.text.is_empty()) {
+        let starts_new = chapters
+            .last()
+            .is_none_or(|c| cue.start_ms >= c.start_ms + interval_ms);
+        if starts_new {
+            if let Some(prev) = chapters.last_mut() {
+                prev.end_ms = cue.start_ms;
+            }
+            chapters.push(Chapter {
+                start_ms: if chapters.is_empty() { 0 } else { cue.start_ms },
+                end_ms: cue.end_ms,
+                title: chapter_title(&cue.text),
+            });
+        } else if let Some(current) = chapters.last_mut() {
+            current.end_ms = current.end_ms.max(cue.end_ms);
+        }
+    }
+    chapters
+}
+
+/// Readable Markdown of a transcript: consecutive cues of the same speaker are
+/// merged into paragraphs, each starting with its timestamp and speaker.
+pub fn notes_markdown(title: &str, date: Option<&str>, cues: &[Cue]) -> String {
+    let mut out = format!("# {} — {}\n", title, tr!("Transcript"));
+    if let Some(date) = date {
+        out.push_str(&format!("\n_{}_\n", date));
+    }
+    // Speaker, start and text of the paragraph being built
+    let mut current: Option<(Option<&str>, u64, String)> = None;
+    for cue in cues.iter().filter(|c| !c.text.is_empty()) {
+        let (speaker, text) = split_speaker(&cue.text);
+        match current.as_mut() {
+            Some((s, start, body)) if *s == speaker && cue.start_ms < *start + PARAGRAPH_MS => {
+                body.push(' ');
+                body.push_str(text);
+            }
+            _ => {
+                if let Some(done) = current.take() {
+                    push_paragraph(&mut out, done);
+                }
+                current = Some((speaker, cue.start_ms, text.to_string()));
+            }
+        }
+    }
+    if let Some(done) = current {
+        push_paragraph(&mut out, done);
+    }
+    out
+}
+
+fn push_paragraph(out: &mut String, (speaker, start_ms, text): (Option<&str>, u64, String)) {
+    let secs = start_ms / 1000;
+    let stamp = format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    match speaker {
+        Some(speaker) => out.push_str(&format!("\n**[{}] {}:** {}\n", stamp, speaker, text)),
+        None => out.push_str(&format!("\n**[{}]** {}\n", stamp, text)),
+    }
+}
+
+/// Zoom prefixes each cue with `Name: `; text without such a prefix has no speaker.
+fn split_speaker(text: &str) -> (Option<&str>, &str) {
+    match text.split_once(": ") {
+        Some((name, rest))
+            if !name.is_empty()
+                && name.chars().count() <= MAX_SPEAKER_CHARS
+                && !name.contains(['.', '?', '!', ',']) =>
+        {
+            (Some(name.trim()), rest.trim())
+        }
+        _ => (None, text),
+    }
+}
+
+fn chapter_title(text: &str) -> String {
+    if text.chars().count() <= MAX_TITLE_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_TITLE_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VTT: &str = "WEBVTT\n\n1\n00:00:05.000 --> 00:00:09.500\nDocente: Buenos días a todos\n\n\
+                       2\n00:04:00.000 --> 00:04:03.000 align:start\nRepasemos la tarea\n\n\
+                       3\n00:10:30.250 --> 00:10:35.000\nAhora veremos integrales por partes\ncon ejemplos\n";
+
+    #[test]
+    fn parses_cues() {
+        let cues = parse_vtt(VTT);
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[0].start_ms, 5_000);
+        assert_eq!(cues[1].end_ms, 243_000);
+        assert_eq!(cues[2].start_ms, 630_250);
+        assert_eq!(
+            cues[2].text,
+            "Ahora veremos integrales por partes con ejemplos"
+        );
+    }
+
+    #[test]
+    fn merges_cues_into_speaker_paragraphs() {
+        let vtt = "WEBVTT\n\n1\n00:00:05.000 --> 00:00:07.000\nDocente: Buenos días\n\n\
+                   2\n00:00:07.500 --> 00:00:09.000\nDocente: a todos\n\n\
+                   3\n00:00:10.000 --> 00:00:12.000\nAna Pérez: ¿Hay tarea?\n\n\
+                   4\n01:02:03.000 --> 01:02:05.000\nSí, para el lunes\n";
+        assert_eq!(
+            notes_markdown("Clase 3", Some("2024-03-01"), &parse_vtt(vtt)),
+            "# Clase 3 — Transcript\n\n_2024-03-01_\n\
+             \n**[00:00:05] Docente:** Buenos días a todos\n\
+             \n**[00:00:10] Ana Pérez:** ¿Hay tarea?\n\
+             \n**[01:02:03]** Sí, para el lunes\n"
+        );
+    }
+
+    #[test]
+    fn groups_cues_into_chapters() {
+        let chapters = chapters_from_cues(&parse_vtt(VTT), 10 * 60 * 1000);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!((chapters[0].start_ms, chapters[0].end_ms), (0, 630_250));
+        assert_eq!(chapters[0].title, "Docente: Buenos días a todos");
+        assert_eq!(
+            (chapters[1].start_ms, chapters[1].end_ms),
+            (630_250, 635_000)
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use tempfile::tempdir;
+use u_crawler::auth::run_auth_canvas;
+use u_crawler::config::{load_config_from_path, save_config_to_path, set_overrides, Overrides};
+use u_crawler::Config;
+
+#[tokio::test]
+async fn auth_does_not_save_download_root_override() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let config_file = dir.path().join("config.toml");
+    let root = dir.path().join("archive");
+    let canvas_root = dir.path().join("canvas");
+    let zoom_root = dir.path().join("zoom");
+
+    let mut cfg = Config::default();
+    cfg.download_root = root.to_string_lossy().into_owned();
+    cfg.canvas.download_root = Some(canvas_root.to_string_lossy().into_owned());
+    cfg.zoom.download_root = Some(zoom_root.to_string_lossy().into_owned());
+    cfg.canvas.base_url = "https://canvas.example.edu".into();
+    cfg.canvas.token = Some("old".into());
+    cfg.expand_paths();
+    save_config_to_path(&cfg, &config_file).await?;
+
+    set_overrides(Overrides {
+        config_file: Some(config_file.clone()),
+        download_root: Some(PathBuf::from("/tmp/override")),
+    });
+    run_auth_canvas(None, Some("new".into()), None).await?;
+
+    let saved = load_config_from_path(&config_file).await?;
+    assert_eq!(saved.canvas.token.as_deref(), Some("new"));
+    assert_eq!(saved.download_root, cfg.download_root);
+    assert_eq!(saved.canvas.download_root, cfg.canvas.download_root);
+    assert_eq!(saved.zoom.download_root, cfg.zoom.download_root);
+
+    // Only the token changed
+    let mut expected = cfg.clone();
+    expected.canvas.token = Some("new".into());
+    assert_eq!(
+        toml::to_string_pretty(&saved)?,
+        toml::to_string_pretty(&expected)?
+    );
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Test-only HTTP server standing in for Canvas and file hosts.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request as received by the mock server.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path and query, e.g. `/api/v1/courses?per_page=100`.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A canned response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new(200)
+            .header("Content-Type", "application/json")
+            .body(body.into().into_bytes())
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// HTTP/1.1 server on a random local port. Routes match the method and the full
+/// path with query; anything else answers 404. Every request is recorded.
+pub struct MockServer {
+    url: String,
+    routes: Arc<Mutex<Vec<(String, String, Handler)>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes: Arc<Mutex<Vec<(String, String, Handler)>>> = Arc::default();
+        let requests: Arc<Mutex<Vec<Request>>> = Arc::default();
+        let (r, q) = (routes.clone(), requests.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (r, q) = (r.clone(), q.clone());
+                thread::spawn(move || serve(stream, &r, &q));
+            }
+        });
+        Self {
+            url,
+            routes,
+            requests,
+        }
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn route(
+        &self,
+        method: &str,
+        path: &str,
+        handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+    ) {
+        self.routes
+            .lock()
+            .unwrap()
+            .push((method.to_string(), path.to_string(), Arc::new(handler)));
+    }
+
+    /// Serve a fixed JSON body on `GET path`.
+    pub fn json(&self, path: &str, body: impl Into<String>) {
+        let body = body.into();
+        self.route("GET", path, move |_| Response::json(body.clone()));
+    }
+
+    /// Requests received for `path`, in order.
+    pub fn requests_to(&self, path: &str) -> Vec<Request> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    routes: &Mutex<Vec<(String, String, Handler)>>,
+    requests: &Mutex<Vec<Request>>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+    let mut line = String::new();
+    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((k, v)) = header.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    let request = Request {
+        method,
+        path,
+        headers,
+    };
+    if let Some(len) = request
+        .header("Content-Length")
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        let mut body = vec![0; len];
+        let _ = reader.read_exact(&mut body);
+    }
+    requests.lock().unwrap().push(request.clone());
+
+    let handler = routes
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(m, p, _)| *m == request.method && *p == request.path)
+        .map(|(_, _, h)| h.clone());
+    let response = match handler {
+        Some(h) => h(&request),
+        None => Response::new(404).body("not found"),
+    };
+
+    let mut out = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (k, v) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    out.push_str("\r\n");
+    let mut stream = stream;
+    let _ = stream.write_all(out.as_bytes());
+    if response.status != 304 {
+        let _ = stream.write_all(&response.body);
+    }
+    let _ = stream.flush();
+}
@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use chrono::Utc;
+use tempfile::tempdir;
+use u_crawler::history::{RunHistory, RunRecord};
+use u_crawler::zoom::db::ZoomDb;
+use u_crawler::zoom::models::ZoomCookie;
+
+/// Open file descriptors of this process (Linux only).
+#[cfg(target_os = "linux")]
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd")
+        .map(|d| d.count())
+        .unwrap_or(0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_fds() -> usize {
+    0
+}
+
+/// Simulates many daemon cycles against the same state and checks that file
+/// handles and in-memory history stay bounded.
+#[test]
+fn repeated_cycles_do_not_grow_handles_or_history() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let db = ZoomDb::new(dir.path())?;
+    let history_path = dir.path().join("history.json");
+    let rt = tokio::runtime::Builder::new_current_thread().build()?;
+    let now = Utc::now().timestamp();
+
+    let mut baseline = None;
+    for cycle in 0..500u64 {
+        let cookies = vec![ZoomCookie {
+            domain: "applications.zoom.us".into(),
+            name: "session".into(),
+            value: cycle.to_string(),
+            path: "/".into(),
+            expires: Some(now + 3600),
+            secure: true,
+            http_only: true,
+        }];
+        db.save_cookies(cycle % 7, &cookies)?;
+        db.save_scid(cycle % 7, "scid")?;
+        assert_eq!(db.load_cookies(cycle % 7)?.len(), 1);
+        assert!(db.get_scid(cycle % 7)?.is_some());
+
+        rt.block_on(async {
+            let mut history = RunHistory::load(&history_path).await;
+            history.push(RunRecord::start());
+            history.save(&history_path).await
+        })?;
+
+        // Measure after warm-up so lazily opened handles (WAL, runtime) are counted once
+        if cycle == 10 {
+            baseline = Some(open_fds());
+        }
+    }
+
+    assert!(open_fds() <= baseline.unwrap_or(0) + 2);
+    let history = rt.block_on(RunHistory::load(&history_path));
+    assert!(history.runs.len() <= 200);
+    Ok(())
+}
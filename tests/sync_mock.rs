@@ -0,0 +1,296 @@
+mod common;
+
+use common::{MockServer, Response};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+use u_crawler::history::RunHistory;
+use u_crawler::http::{build_http_client, resume_validator_path, HttpCtx};
+use u_crawler::plan::{Plan, PlanReason};
+use u_crawler::syncer::{summary_line, sync_with, SyncOptions, PLAN_FILE};
+use u_crawler::{Config, ErrorKind};
+
+const FILE_BODY: &[u8] = b"hello guide";
+const FILE_ETAG: &str = "\"v1\"";
+const FILE_PATH: &str = "/files/7/download";
+
+fn fixture(name: &str, base: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/canvas")
+        .join(name);
+    std::fs::read_to_string(path)
+        .expect("fixture")
+        .replace("{{base}}", base)
+}
+
+/// A Canvas with two pages of courses and one module holding a page and a file.
+/// The file honours `If-None-Match` and `Range`/`If-Range` like Canvas' file store.
+fn mock_canvas() -> MockServer {
+    let server = MockServer::start();
+    let base = server.url().to_string();
+    server.route(
+        "GET",
+        "/api/v1/courses?enrollment_state=active&include[]=term&per_page=100",
+        {
+            let base = base.clone();
+            move |_| {
+                Response::json(fixture("courses_page1.json", &base)).header(
+                    "Link",
+                    format!(
+                        "<{}/api/v1/courses?page=2&per_page=100>; rel=\"next\"",
+                        base
+                    ),
+                )
+            }
+        },
+    );
+    server.json(
+        "/api/v1/courses?page=2&per_page=100",
+        fixture("courses_page2.json", &base),
+    );
+    server.json(
+        "/api/v1/courses/101/modules?include=items&per_page=100",
+        fixture("modules.json", &base),
+    );
+    server.json("/api/v1/courses/101/assignments?per_page=100", "[]");
+    server.json(
+        "/api/v1/users/self/groups?per_page=100",
+        r#"[{"id": 55, "name": "Lab 2", "course_id": 101}, {"id": 56, "name": "Otro", "course_id": 999}]"#,
+    );
+    server.json(
+        "/api/v1/groups/55/pages?per_page=100",
+        r#"[{"url": "acta", "title": "Acta"}]"#,
+    );
+    server.json(
+        "/api/v1/groups/55/pages/acta",
+        r#"{"title": "Acta", "body": "<p>Reunión del grupo</p>"}"#,
+    );
+    server.json("/api/v1/groups/55/files?per_page=100", "[]");
+    for announcements in [false, true] {
+        server.json(
+            &format!(
+                "/api/v1/groups/55/discussion_topics?only_announcements={}&per_page=100",
+                announcements
+            ),
+            "[]",
+        );
+    }
+    server.json(
+        "/api/v1/calendar_events?context_codes[]=course_101&all_events=true&per_page=100",
+        r#"[{"id": 1, "title": "Certamen 1", "start_at": "2024-04-10T13:00:00Z", "end_at": "2024-04-10T15:00:00Z"}]"#,
+    );
+    server.json(
+        "/api/v1/courses/101/pages/intro",
+        fixture("page_intro.json", &base),
+    );
+    server.json("/api/v1/files/7", fixture("file_7.json", &base));
+    server.route("GET", FILE_PATH, |req| {
+        if req.header("If-None-Match") == Some(FILE_ETAG) {
+            return Response::new(304);
+        }
+        let range_start = req
+            .header("Range")
+            .and_then(|r| r.strip_prefix("bytes="))
+            .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+        match (range_start, req.header("If-Range")) {
+            (Some(start), Some(FILE_ETAG)) => Response::new(206)
+                .header("ETag", FILE_ETAG)
+                .header(
+                    "Content-Range",
+                    format!(
+                        "bytes {}-{}/{}",
+                        start,
+                        FILE_BODY.len() - 1,
+                        FILE_BODY.len()
+                    ),
+                )
+                .body(&FILE_BODY[start..]),
+            _ => Response::new(200).header("ETag", FILE_ETAG).body(FILE_BODY),
+        }
+    });
+    server
+}
+
+fn test_config(server: &MockServer, root: &Path) -> Config {
+    let mut cfg = Config {
+        download_root: root.to_string_lossy().into_owned(),
+        max_rps: 1000,
+        min_free_space: "0B".into(),
+        ..Default::default()
+    };
+    cfg.canvas.base_url = server.url().to_string();
+    cfg.canvas.token = Some("test-token".into());
+    cfg.zoom.enabled = false;
+    cfg
+}
+
+async fn sync(cfg: &Config, history: &Path, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let httpctx = HttpCtx::new(cfg, build_http_client(cfg));
+    let opts = SyncOptions {
+        course_id: Some(101),
+        dry_run,
+        ..Default::default()
+    };
+    let run = sync_with(cfg, httpctx, history, opts).await?;
+    assert_eq!(run.failed, 0, "{}", summary_line(&run));
+    Ok(())
+}
+
+fn attachments_dir(root: &Path) -> PathBuf {
+    root.join("Fisica_FIS101/Modules/5_Semana_1/Attachments")
+}
+
+#[tokio::test]
+async fn sync_pages_through_courses_and_revalidates_files() -> Result<(), Box<dyn Error>> {
+    let server = mock_canvas();
+    let dir = tempdir()?;
+    let root = dir.path().join("archive");
+    let history = dir.path().join("history.json");
+    let cfg = test_config(&server, &root);
+
+    sync(&cfg, &history, false).await?;
+    let file = attachments_dir(&root).join("guia.pdf");
+    assert_eq!(std::fs::read(&file)?, FILE_BODY);
+    let page = std::fs::read_to_string(root.join("Fisica_FIS101/Modules/5_Semana_1/01-Intro.md"))?;
+    assert!(page.contains("Bienvenidos"));
+    let acta = root.join("Fisica_FIS101/Groups/Lab_2/Modules/1_Pages/01-Acta.md");
+    assert!(std::fs::read_to_string(acta)?.contains("Reunión del grupo"));
+    assert!(!root.join("Fisica_FIS101/Groups/Otro").exists());
+    for calendar in [
+        root.join("Fisica_FIS101/calendar.ics"),
+        root.join("calendar.ics"),
+    ] {
+        assert!(std::fs::read_to_string(calendar)?.contains("SUMMARY:[FIS101] Certamen 1"));
+    }
+    assert_eq!(
+        server
+            .requests_to("/api/v1/courses/101/pages/intro")
+            .first()
+            .and_then(|r| r.header("Authorization").map(str::to_string))
+            .as_deref(),
+        Some("Bearer test-token")
+    );
+
+    // The second run revalidates the file with its ETag instead of downloading it again
+    sync(&cfg, &history, false).await?;
+    let downloads = server.requests_to(FILE_PATH);
+    assert_eq!(downloads.len(), 2);
+    assert_eq!(downloads[0].header("If-None-Match"), None);
+    assert_eq!(downloads[1].header("If-None-Match"), Some(FILE_ETAG));
+    assert_eq!(std::fs::read(&file)?, FILE_BODY);
+
+    let runs = RunHistory::load(&history).await.runs;
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0].files_downloaded, 1);
+    assert_eq!(runs[1].files_downloaded, 0);
+    assert_eq!(runs[1].failed, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn dry_run_lists_without_writing() -> Result<(), Box<dyn Error>> {
+    let server = mock_canvas();
+    let dir = tempdir()?;
+    let root = dir.path().join("archive");
+    let history = dir.path().join("history.json");
+
+    sync(&test_config(&server, &root), &history, true).await?;
+    assert!(!root.exists());
+    assert!(!history.exists());
+    assert!(server.requests_to(FILE_PATH).is_empty());
+    assert_eq!(server.requests_to("/api/v1/files/7").len(), 2);
+
+    // The plan lists each item once, sorted, with paths under the download root
+    let plan = Plan::load(&dir.path().join(PLAN_FILE)).await?;
+    let file = plan.actions.iter().find(|a| a.key == "file:7").unwrap();
+    assert_eq!(file.reason, PlanReason::New);
+    assert_eq!(
+        file.path.as_deref(),
+        Some("Fisica_FIS101/Modules/5_Semana_1/Attachments/guia.pdf")
+    );
+    assert!(plan.actions.iter().any(|a| a.key == "page:intro"));
+    assert!(plan.actions.windows(2).all(|w| w[0] < w[1]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_resumes_a_partial_download() -> Result<(), Box<dyn Error>> {
+    let server = mock_canvas();
+    let dir = tempdir()?;
+    let root = dir.path().join("archive");
+    let history = dir.path().join("history.json");
+
+    // A previous run stopped after five bytes
+    let attachments = attachments_dir(&root);
+    std::fs::create_dir_all(&attachments)?;
+    let part = attachments.join("guia.part");
+    std::fs::write(&part, &FILE_BODY[..5])?;
+    std::fs::write(resume_validator_path(&part), FILE_ETAG)?;
+
+    sync(&test_config(&server, &root), &history, false).await?;
+    let downloads = server.requests_to(FILE_PATH);
+    assert_eq!(downloads.len(), 1);
+    assert_eq!(downloads[0].header("Range"), Some("bytes=5-"));
+    assert_eq!(downloads[0].header("If-Range"), Some(FILE_ETAG));
+    assert_eq!(std::fs::read(attachments.join("guia.pdf"))?, FILE_BODY);
+    assert!(!part.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_token_stops_sync_before_listing_courses() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start();
+    server.route("GET", "/api/v1/users/self", |_| Response {
+        status: 401,
+        ..Response::json(r#"{"errors":[{"message":"Invalid access token."}]}"#)
+    });
+    let dir = tempdir()?;
+    let cfg = test_config(&server, &dir.path().join("archive"));
+    let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+
+    let err = sync_with(
+        &cfg,
+        httpctx,
+        &dir.path().join("history.json"),
+        SyncOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Auth);
+    assert!(err.to_string().contains("Invalid access token"));
+    assert!(err.to_string().contains("u_crawler auth canvas --token"));
+    assert!(server
+        .requests_to("/api/v1/courses?enrollment_state=active&include[]=term&per_page=100")
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn masquerading_adds_as_user_id_to_every_request() -> Result<(), Box<dyn Error>> {
+    let server = MockServer::start();
+    server.json(
+        "/api/v1/users/self?as_user_id=42",
+        r#"{"id": 42, "name": "Ana"}"#,
+    );
+    let courses =
+        "/api/v1/courses?enrollment_state=active&include[]=term&per_page=100&as_user_id=42";
+    server.json(courses, "[]");
+    let dir = tempdir()?;
+    let mut cfg = test_config(&server, &dir.path().join("archive"));
+    cfg.canvas.as_user_id = Some("42".into());
+    let httpctx = HttpCtx::new(&cfg, build_http_client(&cfg));
+
+    sync_with(
+        &cfg,
+        httpctx,
+        &dir.path().join("history.json"),
+        SyncOptions::default(),
+    )
+    .await?;
+    assert_eq!(server.requests_to(courses).len(), 1);
+    assert_eq!(
+        server.requests_to(courses)[0].header("Authorization"),
+        Some("Bearer test-token")
+    );
+    Ok(())
+}
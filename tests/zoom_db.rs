@@ -4,7 +4,10 @@ use chrono::Utc;
 use rusqlite::Connection;
 use tempfile::tempdir;
 use u_crawler::zoom::db::ZoomDb;
-use u_crawler::zoom::models::ZoomCookie;
+use u_crawler::zoom::models::{
+    DiscoveredLink, DownloadStatus, RecordingListResponse, ZoomCookie, ZoomDownload,
+    ZoomRecordingFile,
+};
 
 #[test]
 fn load_cookies_filters_expired_entries() -> Result<(), Box<dyn Error>> {
@@ -42,8 +45,8 @@ fn load_cookies_filters_expired_entries() -> Result<(), Box<dyn Error>> {
         },
     ];
 
-    db.replace_cookies(&cookies)?;
-    let loaded = db.load_cookies()?;
+    db.save_cookies(1, &cookies)?;
+    let loaded = db.load_cookies(1)?;
 
     let names: Vec<&str> = loaded.iter().map(|c| c.name.as_str()).collect();
     assert!(names.contains(&"valid"));
@@ -60,3 +63,154 @@ fn load_cookies_filters_expired_entries() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn cookies_are_kept_per_course() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    // A database from before cookies were scoped, with one shared session cookie
+    let conn = Connection::open(dir.path().join("zoom_state.sqlite"))?;
+    conn.execute_batch(
+        "CREATE TABLE zoom_cookie (
+             host TEXT NOT NULL, name TEXT NOT NULL, value TEXT NOT NULL, path TEXT NOT NULL,
+             expires INTEGER, secure INTEGER NOT NULL, http_only INTEGER NOT NULL,
+             updated_at INTEGER NOT NULL, PRIMARY KEY(host, name, path));
+         INSERT INTO zoom_cookie VALUES ('zoom.us', '_zm_ssid', 'legacy', '/', NULL, 1, 1, 0);",
+    )?;
+    drop(conn);
+
+    let db = ZoomDb::new(dir.path())?;
+    let cookie = |name: &str, value: &str| ZoomCookie {
+        domain: "zoom.us".into(),
+        name: name.into(),
+        value: value.into(),
+        path: "/".into(),
+        expires: None,
+        secure: true,
+        http_only: true,
+    };
+    let values = |course_id| -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut v: Vec<_> = db
+            .load_cookies(course_id)?
+            .into_iter()
+            .map(|c| (c.name, c.value))
+            .collect();
+        v.sort();
+        Ok(v)
+    };
+    assert_eq!(values(1)?, [("_zm_ssid".into(), "legacy".into())]);
+
+    db.save_cookies(1, &[cookie("_zm_ssid", "one"), cookie("cred", "a")])?;
+    db.save_cookies(2, &[cookie("_zm_ssid", "two")])?;
+    db.save_cookies(1, &[cookie("cred", "b")])?;
+    assert_eq!(
+        values(1)?,
+        [
+            ("_zm_ssid".into(), "one".into()),
+            ("cred".into(), "b".into())
+        ]
+    );
+    assert_eq!(values(2)?, [("_zm_ssid".into(), "two".into())]);
+    assert_eq!(values(3)?, [("_zm_ssid".into(), "legacy".into())]);
+
+    Ok(())
+}
+
+#[test]
+fn download_records_round_trip() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let db = ZoomDb::new(dir.path())?;
+    let video = dir.path().join("2024-03-01 - Intro").join("intro.mp4");
+    assert!(db.get_download("m1", "f1")?.is_none());
+
+    let mut record = ZoomDownload {
+        meeting_id: "m1".into(),
+        file_id: "f1".into(),
+        status: DownloadStatus::InProgress,
+        path: video.clone(),
+        size: None,
+        sha1: None,
+    };
+    db.save_download(&record)?;
+    let loaded = db.get_download("m1", "f1")?.unwrap();
+    assert_eq!(loaded, record);
+    assert!(!loaded.is_intact());
+
+    std::fs::create_dir_all(video.parent().unwrap())?;
+    std::fs::write(&video, b"mp4")?;
+    record.status = DownloadStatus::Complete;
+    record.size = Some(3);
+    record.sha1 = Some("abc".into());
+    db.save_download(&record)?;
+    assert!(db.get_download("m1", "f1")?.unwrap().is_intact());
+
+    std::fs::write(&video, b"truncated mp4")?;
+    assert!(!db.get_download("m1", "f1")?.unwrap().is_intact());
+    Ok(())
+}
+
+#[test]
+fn cached_meetings_and_files_round_trip() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let db = ZoomDb::new(dir.path())?;
+    let listing: RecordingListResponse = serde_json::from_str(
+        r#"{"status": true, "result": {"list": [
+            {"meetingId": "b", "topic": "Clase 2", "startTime": "2024-03-08 10:00", "duration": "55"},
+            {"meetingId": "a", "topic": "Clase 1", "startTime": "2024-03-01 10:00", "duration": 60}
+        ]}}"#,
+    )?;
+    db.save_meetings(42, &listing)?;
+    let file: ZoomRecordingFile = serde_json::from_str(
+        r#"{"meeting_id": "x", "file_id": "f1", "play_url": "https://zoom.us/rec/play/1",
+            "download_url": null, "file_type": "MP4", "recording_start": null, "topic": "Clase 1",
+            "start_time": null, "timezone": null, "meeting_number": null}"#,
+    )?;
+    db.save_files(42, "a", &[file])?;
+
+    let meetings = db.load_meetings(42)?;
+    let ids: Vec<&str> = meetings.iter().map(|m| m.meeting_id.as_str()).collect();
+    assert_eq!(ids, ["a", "b"]);
+    assert_eq!(meetings[1].duration, Some(55));
+    assert!(db.load_meetings(7)?.is_empty());
+
+    let files = db.load_files("a")?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].meeting_id, "a");
+    assert_eq!(files[0].file_key(), "f1");
+    assert!(db.load_files("b")?.is_empty());
+    assert_eq!(
+        db.meeting_for_play_url("https://zoom.us/rec/play/1")?,
+        Some("a".to_string())
+    );
+    Ok(())
+}
+
+#[test]
+fn discovered_links_keep_their_resolution() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let db = ZoomDb::new(dir.path())?;
+    let link = DiscoveredLink {
+        url: "https://unab.zoom.us/rec/share/abc".into(),
+        label: Some("Semana 3".into()),
+        play_url: None,
+        meeting_id: None,
+    };
+    db.save_link(42, &link)?;
+    db.save_link(
+        42,
+        &DiscoveredLink {
+            play_url: Some("https://unab.zoom.us/rec/play/xyz".into()),
+            ..link.clone()
+        },
+    )?;
+    // Rediscovering the link must not forget where it resolved to
+    db.save_link(42, &link)?;
+
+    let links = db.load_links(42)?;
+    assert_eq!(links.len(), 1);
+    assert_eq!(
+        links[0].play_url.as_deref(),
+        Some("https://unab.zoom.us/rec/play/xyz")
+    );
+    assert!(db.load_links(7)?.is_empty());
+    Ok(())
+}